@@ -1,20 +1,49 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use ferrite_config::FerriteConfig;
-use ferrite_logging::LogLevel;
-use std::{env, path::PathBuf, str::FromStr};
+use ferrite_logging::{LogFormat, LogLevel};
+use std::{env, path::PathBuf, time::Duration};
 
 #[derive(Parser, Debug)]
 #[command(
     author,
     version,
-    about = "Ferrite - A fast and efficient image viewer"
+    about = "Ferrite - A fast and efficient image viewer",
+    args_conflicts_with_subcommands = true
 )]
 pub struct Args {
-    /// Initial image file to open
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Image to open, an http(s) URL to download it from, or a folder whose
+    /// images, including those in its subfolders, are opened as a playlist
     #[arg(value_name = "IMAGE")]
     pub image_path: Option<PathBuf>,
 
+    /// Open the image or folder opened last again
+    #[arg(long, conflicts_with = "image_path")]
+    pub recent: bool,
+
+    /// Show the image piped to standard input, as in
+    /// `curl -s https://example.com/a.png | ferrite --stdin`
+    #[arg(long, conflicts_with_all = ["image_path", "recent"])]
+    pub stdin: bool,
+
+    /// Open a window of its own instead of showing the image in the window
+    /// already running
+    #[arg(long)]
+    pub new_window: bool,
+
+    /// Watch the folder of the image and show each image added to it, as
+    /// when following the output of a renderer
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Start without the image, zoom, panels and window placement of the
+    /// last session
+    #[arg(long)]
+    pub no_restore: bool,
+
     /// Set the logging level
     #[arg(long, value_name = "LEVEL", default_value = "info")]
     pub log_level: Option<String>,
@@ -22,6 +51,93 @@ pub struct Args {
     /// Generate a default configuration file
     #[arg(long)]
     pub generate_config: bool,
+
+    /// Log output format: text or json
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub log_format: Option<String>,
+
+    /// Write log output to this file instead of stdout and the default log
+    /// in the state directory
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Stream profiling spans to a Tracy profiler
+    #[arg(long)]
+    pub profile_tracy: bool,
+
+    /// Run without a window and rotate the desktop wallpaper through the
+    /// images in this folder
+    #[arg(long, value_name = "DIR")]
+    pub wallpaper_daemon: Option<PathBuf>,
+
+    /// How long each wallpaper stays, e.g. 90s, 30m or 2h
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        requires = "wallpaper_daemon"
+    )]
+    pub interval: Option<Duration>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Register Ferrite as a viewer for the supported image types
+    InstallAssociations,
+    /// Remove the registrations made by install-associations
+    UninstallAssociations,
+    /// Write a copy of an image shrunk to fit a square, without opening a
+    /// window
+    Thumbnail {
+        /// Image to read
+        input:  PathBuf,
+        /// File to write; its extension picks the format
+        output: PathBuf,
+        /// Longest side of the thumbnail in pixels
+        #[arg(long, default_value_t = 256)]
+        size:   u32,
+    },
+    /// Write an image in the format the extension of the output names,
+    /// without opening a window
+    Convert {
+        /// Image to read
+        input:  PathBuf,
+        /// File to write; its extension picks the format
+        output: PathBuf,
+    },
+    /// Decode every image in a folder and its subfolders, reporting the
+    /// decoding times per format, peak memory and how the cache fared
+    Bench {
+        /// Folder of images to decode
+        dir:  PathBuf,
+        /// Print the report as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Parses a duration with an optional `s`, `m` or `h` unit; plain numbers are
+/// seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", value))?;
+    let seconds = match unit {
+        "s" => Some(number),
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(60 * 60),
+        _ => return Err(format!("unknown duration unit: {}", unit)),
+    }
+    .ok_or_else(|| format!("duration too long: {}", value))?;
+    if seconds == 0 {
+        return Err("duration must be positive".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
 }
 
 impl Args {
@@ -88,4 +204,11 @@ impl Args {
             .and_then(|s| s.parse().ok())
             .unwrap_or(LogLevel::Info)
     }
+
+    pub fn get_log_format(&self) -> LogFormat {
+        self.log_format
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default()
+    }
 }
@@ -0,0 +1,57 @@
+use crate::{
+    defaults::batch::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for batch operations on several images
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchConfig {
+    /// Where results are written; defaults to a `converted` folder next to
+    /// the sources. Originals are never overwritten.
+    pub output_dir:        Option<PathBuf>,
+    pub jpeg_quality:      u8,
+    /// Image stamped into the bottom right corner by the watermark step
+    pub watermark:         Option<PathBuf>,
+    pub watermark_opacity: f32,
+    /// Width of the watermark as a fraction of the image width
+    pub watermark_scale:   f32,
+    /// Template the batch rename tool starts with
+    pub rename_template:   String,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            output_dir:        None,
+            jpeg_quality:      JPEG_QUALITY,
+            watermark:         None,
+            watermark_opacity: WATERMARK_OPACITY,
+            watermark_scale:   WATERMARK_SCALE,
+            rename_template:   RENAME_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl BatchConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=100).contains(&self.jpeg_quality) {
+            return Err(ConfigError::ValidationError(
+                "JPEG quality must be between 1 and 100".into(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.watermark_opacity) {
+            return Err(ConfigError::ValidationError(
+                "Watermark opacity must be between 0 and 1".into(),
+            ));
+        }
+        if self.watermark_scale <= 0.0 || self.watermark_scale > 1.0 {
+            return Err(ConfigError::ValidationError(
+                "Watermark scale must be above 0 and at most 1".into(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    defaults::cache::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// What is kept in memory and on disk so images and folders open quickly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Keep gallery thumbnails and an index of recently viewed files in
+    /// the cache and state directories, and load them again on startup
+    pub warm_start:     bool,
+    /// Number of recently viewed files remembered
+    pub recent_files:   usize,
+    /// Number of opened images and folders listed under File > Recent; 0
+    /// keeps no list
+    pub recent_menu:    usize,
+    /// Thumbnails kept on disk; the least recently used are removed
+    pub max_thumbnails: usize,
+    /// Memory for decoded images kept for quick navigation, in megabytes.
+    /// 0 turns the cache and prefetching off.
+    pub memory_mb:      u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            warm_start:     WARM_START,
+            recent_files:   RECENT_FILES,
+            recent_menu:    RECENT_MENU,
+            max_thumbnails: MAX_THUMBNAILS,
+            memory_mb:      MEMORY_MB,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.recent_files == 0 {
+            return Err(ConfigError::ValidationError(
+                "At least one recent file must be remembered".into(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{defaults::color::*, error::Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Noise added when images with more than 8 bits per channel are shown, so
+/// smooth gradients do not band
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum DitherMode {
+    #[serde(rename = "off")]
+    Off,
+    /// 8×8 Bayer matrix
+    #[serde(rename = "ordered")]
+    Ordered,
+    /// Blue-noise threshold texture, which leaves no visible pattern
+    #[serde(rename = "blue-noise")]
+    #[default]
+    BlueNoise,
+}
+
+/// How pixel values are treated when images are processed and displayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorConfig {
+    /// Resample and blend in linear light instead of on sRGB-encoded
+    /// values, which darkens fine detail when downscaling
+    pub linear_light:    bool,
+    /// Dithering of high bit depth images for the 8-bit display
+    pub dither:          DitherMode,
+    /// ICC profile of the monitor. Unset for an sRGB monitor.
+    pub display_profile: Option<PathBuf>,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            linear_light:    LINEAR_LIGHT,
+            dither:          DitherMode::default(),
+            display_profile: None,
+        }
+    }
+}
+
+impl ColorConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,37 +1,123 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::{fs, path::PathBuf};
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 use crate::{
+    batch::BatchConfig,
+    cache::CacheConfig,
+    color::ColorConfig,
+    decoding::DecodingConfig,
+    editors::EditorsConfig,
     error::{ConfigError, Result},
     input::ControlsConfig,
-    ui::{IndicatorConfig, SelectionConfig},
+    instance::InstanceConfig,
+    io::IoConfig,
+    lens::LensConfig,
+    logging::LoggingConfig,
+    loupe::LoupeConfig,
+    lut::LutConfig,
+    map::MapConfig,
+    navigation::NavigationConfig,
+    paths::PathsConfig,
+    remote::RemoteConfig,
+    slideshow::SlideshowConfig,
+    sorting::SortingConfig,
+    ui::{BackgroundConfig, IndicatorConfig, SelectionConfig, StatusBarConfig},
+    upscale::UpscaleConfig,
+    wallpaper::WallpaperConfig,
     window::WindowConfig,
+    xmp::XmpConfig,
     zoom::ZoomConfig,
     CONFIG_VERSION,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FerriteConfig {
-    version:       String,
-    pub window:    WindowConfig,
-    pub zoom:      ZoomConfig,
-    pub controls:  ControlsConfig,
-    pub indicator: IndicatorConfig,
-    pub selection: SelectionConfig,
+    version:        String,
+    pub window:     WindowConfig,
+    pub zoom:       ZoomConfig,
+    pub controls:   ControlsConfig,
+    pub indicator:  IndicatorConfig,
+    pub selection:  SelectionConfig,
+    #[serde(default)]
+    pub upscale:    UpscaleConfig,
+    #[serde(default)]
+    pub map:        MapConfig,
+    #[serde(default)]
+    pub xmp:        XmpConfig,
+    #[serde(default)]
+    pub logging:    LoggingConfig,
+    #[serde(default)]
+    pub decoding:   DecodingConfig,
+    #[serde(default)]
+    pub lens:       LensConfig,
+    #[serde(default)]
+    pub loupe:      LoupeConfig,
+    #[serde(default)]
+    pub slideshow:  SlideshowConfig,
+    #[serde(default)]
+    pub wallpaper:  WallpaperConfig,
+    #[serde(default)]
+    pub paths:      PathsConfig,
+    #[serde(default)]
+    pub navigation: NavigationConfig,
+    #[serde(default)]
+    pub io:         IoConfig,
+    #[serde(default)]
+    pub remote:     RemoteConfig,
+    #[serde(default)]
+    pub instance:   InstanceConfig,
+    #[serde(default)]
+    pub batch:      BatchConfig,
+    #[serde(default)]
+    pub lut:        LutConfig,
+    #[serde(default)]
+    pub color:      ColorConfig,
+    #[serde(default)]
+    pub cache:      CacheConfig,
+    #[serde(default)]
+    pub sorting:    SortingConfig,
+    #[serde(default)]
+    pub status_bar: StatusBarConfig,
+    #[serde(default)]
+    pub background: BackgroundConfig,
+    #[serde(default)]
+    pub editors:    EditorsConfig,
 }
 
 impl Default for FerriteConfig {
     fn default() -> Self {
         info!("Creating default configuration");
         Self {
-            version:   CONFIG_VERSION.to_string(),
-            window:    WindowConfig::default(),
-            zoom:      ZoomConfig::default(),
-            controls:  ControlsConfig::default(),
-            indicator: IndicatorConfig::default(),
-            selection: SelectionConfig::default(),
+            version:    CONFIG_VERSION.to_string(),
+            window:     WindowConfig::default(),
+            zoom:       ZoomConfig::default(),
+            controls:   ControlsConfig::default(),
+            indicator:  IndicatorConfig::default(),
+            selection:  SelectionConfig::default(),
+            upscale:    UpscaleConfig::default(),
+            map:        MapConfig::default(),
+            xmp:        XmpConfig::default(),
+            logging:    LoggingConfig::default(),
+            decoding:   DecodingConfig::default(),
+            lens:       LensConfig::default(),
+            loupe:      LoupeConfig::default(),
+            slideshow:  SlideshowConfig::default(),
+            wallpaper:  WallpaperConfig::default(),
+            paths:      PathsConfig::default(),
+            navigation: NavigationConfig::default(),
+            io:         IoConfig::default(),
+            remote:     RemoteConfig::default(),
+            instance:   InstanceConfig::default(),
+            batch:      BatchConfig::default(),
+            lut:        LutConfig::default(),
+            color:      ColorConfig::default(),
+            cache:      CacheConfig::default(),
+            sorting:    SortingConfig::default(),
+            status_bar: StatusBarConfig::default(),
+            background: BackgroundConfig::default(),
+            editors:    EditorsConfig::default(),
         }
     }
 }
@@ -128,6 +214,27 @@ impl FerriteConfig {
         self.controls.validate()?;
         self.indicator.validate()?;
         self.selection.validate()?;
+        self.upscale.validate()?;
+        self.map.validate()?;
+        self.xmp.validate()?;
+        self.logging.validate()?;
+        self.decoding.validate()?;
+        self.lens.validate()?;
+        self.loupe.validate()?;
+        self.slideshow.validate()?;
+        self.wallpaper.validate()?;
+        self.paths.validate()?;
+        self.navigation.validate()?;
+        self.io.validate()?;
+        self.remote.validate()?;
+        self.batch.validate()?;
+        self.lut.validate()?;
+        self.color.validate()?;
+        self.cache.validate()?;
+        self.sorting.validate()?;
+        self.editors.validate()?;
+        self.status_bar.validate()?;
+        self.background.validate()?;
         Ok(())
     }
 
@@ -142,3 +249,39 @@ impl FerriteConfig {
         Ok(new_config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_written_before_new_options_loads() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/generated-0.1.toml");
+        let config = FerriteConfig::load_from_path(&path).unwrap();
+        assert_eq!(config.zoom.zoom_step, 0.1);
+        let defaults = FerriteConfig::default();
+        assert_eq!(config.status_bar.show_star, defaults.status_bar.show_star);
+        assert_eq!(
+            config.navigation.max_images,
+            defaults.navigation.max_images
+        );
+    }
+
+    #[test]
+    fn test_partial_tables_keep_other_defaults() {
+        let defaults = FerriteConfig::default();
+        let status_bar: StatusBarConfig =
+            toml::from_str("show_star = false").unwrap();
+        assert!(!status_bar.show_star);
+        assert_eq!(status_bar.show_rating, defaults.status_bar.show_rating);
+
+        let decoding: DecodingConfig =
+            toml::from_str("sandboxed = false").unwrap();
+        assert!(!decoding.sandboxed);
+        assert_eq!(
+            decoding.worker_timeout_secs,
+            defaults.decoding.worker_timeout_secs
+        );
+    }
+}
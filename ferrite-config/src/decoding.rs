@@ -0,0 +1,67 @@
+use crate::{
+    defaults::decoding::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// How image files are decoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingConfig {
+    /// Decode in a separate, resource-limited process so a malformed file
+    /// cannot crash or compromise the viewer
+    pub sandboxed:           bool,
+    /// Address space limit of the decoder process
+    pub worker_memory_mb:    u64,
+    /// The decoder process is killed after this long
+    pub worker_timeout_secs: u64,
+    /// Images wider or taller than this are only opened after confirmation
+    pub max_dimension:       u32,
+    /// Same for images whose decoded pixels would take more memory
+    pub max_memory_mb:       u64,
+    /// Rotate and mirror photos as their EXIF orientation says
+    pub auto_orient:         bool,
+    /// Convert images with an embedded ICC profile to sRGB, and sRGB to
+    /// `color.display_profile` for display
+    pub color_management:    bool,
+    /// Demosaic the sensor data of camera RAW files instead of showing the
+    /// JPEG preview embedded in them. Slower, and only possible for
+    /// uncompressed data.
+    pub develop_raw:         bool,
+}
+
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        Self {
+            sandboxed:           SANDBOXED,
+            worker_memory_mb:    WORKER_MEMORY_MB,
+            worker_timeout_secs: WORKER_TIMEOUT_SECS,
+            max_dimension:       MAX_DIMENSION,
+            max_memory_mb:       MAX_MEMORY_MB,
+            auto_orient:         AUTO_ORIENT,
+            color_management:    COLOR_MANAGEMENT,
+            develop_raw:         DEVELOP_RAW,
+        }
+    }
+}
+
+impl DecodingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.worker_memory_mb < 64 {
+            return Err(ConfigError::ValidationError(
+                "Decoder memory limit must be at least 64 MB".into(),
+            ));
+        }
+        if self.worker_timeout_secs == 0 {
+            return Err(ConfigError::ValidationError(
+                "Decoder timeout must be at least one second".into(),
+            ));
+        }
+        if self.max_dimension == 0 || self.max_memory_mb == 0 {
+            return Err(ConfigError::ValidationError(
+                "Decoding limits must be greater than zero".into(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::types::{ColorRGBA, Corner, Key, MouseButton, Vector2D};
+use crate::types::{Corner, MouseButton};
 
 // Window defaults
 pub mod window {
@@ -17,14 +17,22 @@ pub mod zoom {
         &[0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
     pub const USE_PREDEFINED_STEPS: bool = false;
     pub const FOCAL_POINT_ENABLED: bool = true;
-    pub const TRANSITION_ENABLED: bool = false;
-    pub const TRANSITION_DURATION: f64 = 0.2;
+    pub const TRANSITION_ENABLED: bool = true;
+    pub const TRANSITION_DURATION: f64 = 0.1;
     pub const FIT_TO_WINDOW: bool = true;
     pub const MAINTAIN_ASPECT_RATIO: bool = true;
+    pub const PIXEL_PERFECT: bool = false;
+    pub const MIPMAPS: bool = true;
     // Add default fit mode - we'll use FitLonger as it's most commonly expected
+    #[allow(dead_code)]
     pub const DEFAULT_FIT_MODE: &str = "FitLonger";
 }
 
+pub mod touchpad {
+    pub const GESTURES: bool = true;
+    pub const COASTING: bool = true;
+}
+
 // UI defaults
 // defaults.rs
 pub mod indicator {
@@ -52,17 +60,127 @@ pub mod selection {
     pub const BOX_THICKNESS: f64 = 1.0;
 }
 
-pub mod controls {
-
-    pub const ZOOM_IN_KEYS: &[&str] = &["Equal", "Plus", "W"];
-    pub const ZOOM_OUT_KEYS: &[&str] = &["Minus", "S"];
-    pub const RESET_ZOOM_KEY: &str = "Num0";
-    pub const TOGGLE_FIT_KEY: &str = "F";
-    pub const QUIT_KEY: &str = "Q";
-}
-
 pub mod navigation {
 
+    #[allow(dead_code)]
     pub const DEFAULT_SORT_ORDER: &str = "name";
     pub const DEFAULT_REVERSE_ORDER: bool = false;
+    pub const FOLLOW_SYMLINKS: bool = true;
+    pub const DEDUPE_SYMLINKS: bool = true;
+    pub const SHOW_HIDDEN: bool = false;
+    pub const MAX_DEPTH: usize = 16;
+    pub const MAX_IMAGES: usize = 10_000;
+    pub const CONFIRM_DELETE: bool = true;
+}
+
+pub mod upscale {
+    pub const BINARY: &str = "realesrgan-ncnn-vulkan";
+    pub const MODEL: &str = "realesrgan-x4plus";
+    pub const DEFAULT_SCALE: u32 = 4;
+    pub const SUPPORTED_SCALES: &[u32] = &[2, 3, 4];
+}
+
+pub mod map {
+    pub const TILE_URL: &str = "https://tile.openstreetmap.org/{z}/{x}/{y}.png";
+    pub const DEFAULT_ZOOM: u8 = 13;
+    pub const MAX_ZOOM: u8 = 19;
+}
+
+pub mod logging {
+    pub const ENABLE_TRACY: bool = false;
+    pub const LOG_TO_FILE: bool = true;
+    pub const MAX_FILE_SIZE_MB: u64 = 10;
+    pub const MAX_FILES: u32 = 5;
+}
+
+pub mod decoding {
+    pub const SANDBOXED: bool = false;
+    pub const WORKER_MEMORY_MB: u64 = 4096;
+    pub const WORKER_TIMEOUT_SECS: u64 = 30;
+    pub const MAX_DIMENSION: u32 = 65535;
+    pub const MAX_MEMORY_MB: u64 = 1024;
+    pub const AUTO_ORIENT: bool = true;
+    pub const COLOR_MANAGEMENT: bool = true;
+    pub const DEVELOP_RAW: bool = false;
+}
+
+pub mod loupe {
+    pub const MAGNIFICATION: f32 = 4.0;
+    pub const MIN_MAGNIFICATION: f32 = 1.5;
+    pub const MAX_MAGNIFICATION: f32 = 32.0;
+    pub const SIZE: f32 = 240.0;
+}
+
+pub mod slideshow {
+    pub const INTERVAL_SECS: f64 = 5.0;
+    pub const KEN_BURNS: bool = false;
+    pub const TRANSITION_SECS: f64 = 0.6;
+    pub const SHUFFLE: bool = false;
+    pub const REPEAT: bool = true;
+}
+
+pub mod wallpaper {
+    /// Half an hour per wallpaper
+    pub const INTERVAL_SECS: u64 = 30 * 60;
+    pub const SHUFFLE: bool = false;
+}
+
+pub mod remote {
+    pub const MAX_DOWNLOAD_MB: u64 = 100;
+    pub const MAX_CACHE_MB: u64 = 500;
+    pub const TIMEOUT_SECS: f64 = 30.0;
+    pub const MAX_TIMEOUT_SECS: f64 = 3600.0;
+}
+
+pub mod instance {
+    pub const SINGLE_INSTANCE: bool = true;
+}
+
+pub mod io {
+    pub const TIMEOUT_SECS: f64 = 15.0;
+    pub const MAX_TIMEOUT_SECS: f64 = 3600.0;
+    pub const RETRIES: u32 = 2;
+    pub const READ_AHEAD_KB: usize = 1024;
+    pub const RELOAD_ON_CHANGE: bool = true;
+    pub const WATCH_INTERVAL_MS: u64 = 500;
+    pub const MIN_WATCH_INTERVAL_MS: u64 = 50;
+}
+
+pub mod batch {
+    pub const JPEG_QUALITY: u8 = 90;
+    pub const WATERMARK_OPACITY: f32 = 0.5;
+    /// Watermark width relative to the image width
+    pub const WATERMARK_SCALE: f32 = 0.2;
+    pub const RENAME_TEMPLATE: &str = "{date}_{camera}_{seq:04}";
+}
+
+pub mod color {
+    pub const LINEAR_LIGHT: bool = true;
+}
+
+pub mod cache {
+    pub const WARM_START: bool = true;
+    pub const RECENT_FILES: usize = 500;
+    pub const RECENT_MENU: usize = 10;
+    pub const MAX_THUMBNAILS: usize = 20_000;
+    pub const MEMORY_MB: u64 = 1024;
+}
+
+pub mod status_bar {
+    pub const VISIBLE: bool = true;
+    pub const SHOW_NAME: bool = true;
+    pub const SHOW_STAR: bool = true;
+    pub const SHOW_RATING: bool = true;
+    pub const SHOW_RESOLUTION: bool = true;
+    pub const SHOW_FILE_SIZE: bool = true;
+    pub const SHOW_FORMAT: bool = true;
+    pub const SHOW_ZOOM: bool = true;
+    pub const SHOW_CURSOR: bool = true;
+}
+
+pub mod background {
+    pub const COLOR: (u8, u8, u8, u8) = (128, 128, 128, 255);
+    pub const CHECKER_SIZE: f32 = 8.0;
+    pub const MIN_CHECKER_SIZE: f32 = 2.0;
+    pub const MAX_CHECKER_SIZE: f32 = 64.0;
 }
@@ -0,0 +1,39 @@
+use crate::error::{ConfigError, Result};
+use serde::{Deserialize, Serialize};
+
+/// A program the current image can be opened in, such as GIMP or Krita
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalEditor {
+    /// Shown in the File > Open With menu
+    pub name:    String,
+    /// Command line run through the shell, with `{path}` replaced by the
+    /// image, e.g. `gimp {path}`
+    pub command: String,
+}
+
+/// Programs for editing the current image. The first one is used by the
+/// Open in Editor shortcut.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorsConfig {
+    pub programs: Vec<ExternalEditor>,
+}
+
+impl EditorsConfig {
+    pub fn validate(&self) -> Result<()> {
+        for editor in &self.programs {
+            if editor.name.trim().is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "External editors must have a name".into(),
+                ));
+            }
+            if !editor.command.contains("{path}") {
+                return Err(ConfigError::ValidationError(format!(
+                    "Command of editor {} must contain a {{path}} placeholder",
+                    editor.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,62 +1,255 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::{debug, warn};
 
 use crate::{
-    defaults::controls::*,
-    error::{ConfigError, Result},
-    types::SerializableKey,
+    defaults::touchpad::*,
+    error::Result,
+    keymap::{Action, KeyChord, Keymap},
+    types::Key,
 };
 
+/// What turning the mouse wheel does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WheelAction {
+    /// Zoom in and out around the pointer
+    #[serde(rename = "zoom")]
+    Zoom,
+    /// Previous image when turned up, next image when turned down
+    #[serde(rename = "navigate")]
+    Navigate,
+    /// Move the image up and down
+    #[serde(rename = "pan")]
+    Pan,
+    #[serde(rename = "none")]
+    None,
+}
+
+/// Wheel action for each modifier held while turning. Ctrl takes
+/// precedence over Shift, and Shift over Alt.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ControlsConfig {
-    pub zoom_in_keys:   Vec<SerializableKey>,
-    pub zoom_out_keys:  Vec<SerializableKey>,
-    pub reset_zoom_key: SerializableKey,
-    pub toggle_fit_key: SerializableKey,
-    pub quit_key:       SerializableKey,
+#[serde(default)]
+pub struct WheelBindings {
+    pub plain: WheelAction,
+    pub ctrl:  WheelAction,
+    pub shift: WheelAction,
+    pub alt:   WheelAction,
 }
-impl Default for ControlsConfig {
+
+impl Default for WheelBindings {
     fn default() -> Self {
         Self {
-            zoom_in_keys:   ZOOM_IN_KEYS.iter().map(|&s| s.into()).collect(),
-            zoom_out_keys:  ZOOM_OUT_KEYS.iter().map(|&s| s.into()).collect(),
-            reset_zoom_key: RESET_ZOOM_KEY.into(),
-            toggle_fit_key: TOGGLE_FIT_KEY.into(),
-            quit_key:       QUIT_KEY.into(),
+            plain: WheelAction::Zoom,
+            ctrl:  WheelAction::Zoom,
+            shift: WheelAction::Pan,
+            alt:   WheelAction::Navigate,
         }
     }
 }
 
-impl ControlsConfig {
-    pub fn validate(&self) -> Result<()> {
-        if self.zoom_in_keys.as_slice().is_empty() {
-            return Err(ConfigError::ValidationError(
-                "No zoom in keys defined".into(),
-            ));
+impl WheelBindings {
+    pub fn action(&self, ctrl: bool, shift: bool, alt: bool) -> WheelAction {
+        if ctrl {
+            self.ctrl
+        } else if shift {
+            self.shift
+        } else if alt {
+            self.alt
+        } else {
+            self.plain
         }
-        if self.zoom_out_keys.as_slice().is_empty() {
-            return Err(ConfigError::ValidationError(
-                "No zoom out keys defined".into(),
-            ));
+    }
+}
+
+/// Action for each mouse button beyond the left and right ones, if any.
+/// They trigger the same actions as the keyboard shortcuts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ButtonBindings {
+    pub middle:  Option<Action>,
+    /// The side button usually labeled back
+    pub back:    Option<Action>,
+    pub forward: Option<Action>,
+}
+
+impl Default for ButtonBindings {
+    fn default() -> Self {
+        Self {
+            middle:  None,
+            back:    Some(Action::PreviousImage),
+            forward: Some(Action::NextImage),
+        }
+    }
+}
+
+/// Touchpad gestures. Two-finger scrolling is told apart from a mouse wheel
+/// by arriving in points rather than in notches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchpadConfig {
+    /// Pinching zooms and two-finger scrolling pans, instead of scrolling
+    /// doing what the wheel does
+    pub gestures: bool,
+    /// A two-finger pan keeps going for a moment after the fingers lift
+    pub coasting: bool,
+}
+
+impl Default for TouchpadConfig {
+    fn default() -> Self {
+        Self {
+            gestures: GESTURES, coasting: COASTING
         }
+    }
+}
 
-        // Check for key conflicts
-        let mut all_keys = Vec::new();
-        all_keys.extend(self.zoom_in_keys.as_slice());
-        all_keys.extend(self.zoom_out_keys.as_slice());
-        all_keys.push(&self.reset_zoom_key);
-        all_keys.push(&self.toggle_fit_key);
-
-        let mut seen = Vec::new();
-        for key in all_keys {
-            if seen.contains(&key) {
-                return Err(ConfigError::ValidationError(format!(
-                    "Duplicate key binding found: {:?}",
-                    key
-                )));
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "ControlsFile")]
+pub struct ControlsConfig {
+    pub wheel:    WheelBindings,
+    pub buttons:  ButtonBindings,
+    pub keymap:   Keymap,
+    pub touchpad: TouchpadConfig,
+}
+
+/// The `[controls]` table as written, with the key settings it had before
+/// the keymap
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ControlsFile {
+    wheel:          WheelBindings,
+    buttons:        ButtonBindings,
+    keymap:         BTreeMap<Action, Vec<KeyChord>>,
+    touchpad:       TouchpadConfig,
+    zoom_in_keys:   Option<Vec<String>>,
+    zoom_out_keys:  Option<Vec<String>>,
+    reset_zoom_key: Option<String>,
+    toggle_fit_key: Option<String>,
+    quit_key:       Option<String>,
+}
+
+impl From<ControlsFile> for ControlsConfig {
+    fn from(file: ControlsFile) -> Self {
+        let one = |key: Option<String>| key.map(|key| vec![key]);
+        let legacy = [
+            (
+                "zoom_in_keys",
+                Action::ZoomIn,
+                file.zoom_in_keys,
+                &["Equal", "Plus", "W"][..],
+            ),
+            ("zoom_out_keys", Action::ZoomOut, file.zoom_out_keys, &[
+                "Minus", "S",
+            ]),
+            ("reset_zoom_key", Action::ResetZoom, one(file.reset_zoom_key), &[
+                "Num0",
+            ]),
+            (
+                "toggle_fit_key",
+                Action::FitToWindow,
+                one(file.toggle_fit_key),
+                &["F"],
+            ),
+            // Every key but the others was written as Equal
+            ("quit_key", Action::Quit, one(file.quit_key), &["Equal"]),
+        ];
+
+        let mut keymap = Keymap::from(file.keymap.clone());
+        for (name, action, keys, generated) in legacy {
+            let Some(keys) = keys else {
+                continue;
+            };
+            if file.keymap.contains_key(&action) || keys == generated {
+                debug!("Ignoring controls.{}, kept by the keymap", name);
+                continue;
             }
-            seen.push(key);
+            let chords: Vec<KeyChord> = keys
+                .iter()
+                .filter_map(|key| legacy_key(key).map(KeyChord::new))
+                .collect();
+            warn!(
+                "controls.{} is deprecated; binding {:?} to {} in its place. \
+                 Set it under [controls.keymap] instead.",
+                name,
+                keys,
+                action.label()
+            );
+            keymap.rebind(action, chords);
         }
 
-        Ok(())
+        Self {
+            wheel: file.wheel,
+            buttons: file.buttons,
+            keymap,
+            touchpad: file.touchpad,
+        }
+    }
+}
+
+/// Keys as the settings before the keymap named them, which wrote digits
+/// as `Num0`
+fn legacy_key(name: &str) -> Option<Key> {
+    match name {
+        "Equal" => Some(Key::Equals),
+        name => Key::from_name(name.strip_prefix("Num").unwrap_or(name)),
+    }
+}
+
+impl ControlsConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.keymap.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_config_without_new_options_loads() {
+        let generated: toml::Table =
+            toml::from_str(include_str!("../tests/data/generated-0.1.toml"))
+                .unwrap();
+        let controls: ControlsConfig =
+            generated["controls"].clone().try_into().unwrap();
+        assert_eq!(controls.wheel.shift, WheelAction::Pan);
+
+        let wheel: WheelBindings = toml::from_str("ctrl = 'none'").unwrap();
+        assert_eq!(wheel.ctrl, WheelAction::None);
+        assert_eq!(wheel.plain, WheelAction::Zoom);
+    }
+
+    #[test]
+    fn test_old_key_settings_move_into_the_keymap() {
+        // As generated, they are left to the keymap's defaults
+        let generated: toml::Table =
+            toml::from_str(include_str!("../tests/data/generated-0.1.toml"))
+                .unwrap();
+        let controls: ControlsConfig =
+            generated["controls"].clone().try_into().unwrap();
+        assert_eq!(controls.keymap, Keymap::default());
+
+        let controls: ControlsConfig = toml::from_str(
+            r#"
+            zoom_in_keys = ["Q"]
+            toggle_fit_key = "F"
+            reset_zoom_key = "Num9"
+            keymap = { reset_zoom = ["8"] }
+            "#,
+        )
+        .unwrap();
+        let q = KeyChord::new(Key::Q);
+        assert_eq!(legacy_key("Num9"), Some(Key::Num9));
+        assert_eq!(controls.keymap.chords(Action::ZoomIn), &[q]);
+        // Taken from the action it was bound to before
+        assert!(!controls.keymap.chords(Action::Quit).contains(&q));
+        assert_eq!(
+            controls.keymap.chords(Action::FitToWindow),
+            Keymap::default().chords(Action::FitToWindow)
+        );
+        // The keymap wins over the old setting
+        assert_eq!(controls.keymap.chords(Action::ResetZoom), &[
+            KeyChord::new(Key::Num8)
+        ]);
+        assert!(controls.validate().is_ok());
     }
 }
@@ -0,0 +1,24 @@
+use crate::defaults::instance::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for forwarding images to a running viewer instead of opening
+/// another window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceConfig {
+    /// Send images opened while a window is running to that window
+    pub single_instance: bool,
+    /// Socket the running window listens on; defaults to one in the runtime
+    /// directory of the user. Its folder must be the user's and closed to
+    /// others, and is created that way when missing
+    pub socket_path:     Option<PathBuf>,
+}
+
+impl Default for InstanceConfig {
+    fn default() -> Self {
+        Self {
+            single_instance: SINGLE_INSTANCE, socket_path: None
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    defaults::io::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How image files are read from disk. The defaults are meant for network
+/// mounts (NFS, SMB) that may stall, and cost nothing on local disks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IoConfig {
+    /// Give up on a read that has not finished after this long
+    pub timeout_secs:      f64,
+    /// Extra attempts after a transient error; timeouts are not retried
+    pub retries:           u32,
+    /// Size of the reads issued to the file system
+    pub read_ahead_kb:     usize,
+    /// Show the image again when its file is rewritten, keeping the view
+    pub reload_on_change:  bool,
+    /// How often the file on screen is checked for changes
+    pub watch_interval_ms: u64,
+}
+
+impl Default for IoConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs:      TIMEOUT_SECS,
+            retries:           RETRIES,
+            read_ahead_kb:     READ_AHEAD_KB,
+            reload_on_change:  RELOAD_ON_CHANGE,
+            watch_interval_ms: WATCH_INTERVAL_MS,
+        }
+    }
+}
+
+impl IoConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !valid_timeout(self.timeout_secs) {
+            return Err(ConfigError::ValidationError(format!(
+                "Read timeout must be between 0 and {} seconds",
+                MAX_TIMEOUT_SECS
+            )));
+        }
+        if self.read_ahead_kb < 4 {
+            return Err(ConfigError::ValidationError(
+                "Read-ahead must be at least 4 KB".into(),
+            ));
+        }
+        if self.watch_interval_ms < MIN_WATCH_INTERVAL_MS {
+            return Err(ConfigError::ValidationError(format!(
+                "File watch interval must be at least {} ms",
+                MIN_WATCH_INTERVAL_MS
+            )));
+        }
+        Ok(())
+    }
+
+    /// The read timeout, or the default one if the configured value was
+    /// never validated and is out of range
+    pub fn timeout(&self) -> Duration {
+        if valid_timeout(self.timeout_secs) {
+            Duration::from_secs_f64(self.timeout_secs)
+        } else {
+            Duration::from_secs_f64(TIMEOUT_SECS)
+        }
+    }
+}
+
+fn valid_timeout(secs: f64) -> bool {
+    secs > 0.0 && secs <= MAX_TIMEOUT_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_out_of_range_are_rejected() {
+        for timeout_secs in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e20] {
+            let config = IoConfig {
+                timeout_secs,
+                ..IoConfig::default()
+            };
+            assert!(config.validate().is_err(), "{}", timeout_secs);
+            assert_eq!(config.timeout(), Duration::from_secs_f64(TIMEOUT_SECS));
+        }
+        assert!(IoConfig::default().validate().is_ok());
+    }
+}
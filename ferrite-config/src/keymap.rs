@@ -0,0 +1,639 @@
+//! Keyboard shortcuts: named actions and the key chords that trigger them.
+//!
+//! In the config file the keymap is a table from action name to a list of
+//! chords such as `"Ctrl+Shift+V"`. Actions left out keep their default
+//! chords and an empty list unbinds one.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    error::{ConfigError, Result},
+    types::Key,
+};
+
+/// Something a key chord can do
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextImage,
+    PreviousImage,
+    /// Move the view over an image larger than the window. Their chords may
+    /// also belong to another action, which they do while the image fits.
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    FitToWindow,
+    FillWindow,
+    ActualSize,
+    RotateClockwise,
+    RotateCounterClockwise,
+    FlipHorizontal,
+    FlipVertical,
+    ToggleMenu,
+    ToggleFullscreen,
+    ToggleSlideshow,
+    PauseSlideshow,
+    CloseView,
+    OpenFile,
+    MoveToTrash,
+    Rename,
+    MoveToFolder,
+    ToggleStar,
+    /// Gives the image that many stars, or takes them away when it has
+    /// them already
+    RateOne,
+    RateTwo,
+    RateThree,
+    RateFour,
+    RateFive,
+    Tags,
+    FilterBar,
+    CopyImage,
+    CopyPath,
+    PasteImage,
+    SaveAs,
+    OpenInEditor,
+    CompareClipboard,
+    SplitView,
+    Gallery,
+    Filmstrip,
+    ExifInfo,
+    EditMetadata,
+    Map,
+    ScanCodes,
+    Upscale,
+    Checksums,
+    LensCorrection,
+    Palette,
+    Histogram,
+    Measure,
+    CycleGrid,
+    Guides,
+    PixelPerfect,
+    Loupe,
+    LoupeLarger,
+    LoupeSmaller,
+    PixelInspector,
+    Crop,
+    Adjust,
+    Shortcuts,
+}
+
+impl Action {
+    /// Every action, in the order the shortcuts page lists them
+    pub const ALL: &'static [Action] = &[
+        Action::NextImage,
+        Action::PreviousImage,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ResetZoom,
+        Action::FitToWindow,
+        Action::FillWindow,
+        Action::ActualSize,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::FlipHorizontal,
+        Action::FlipVertical,
+        Action::ToggleMenu,
+        Action::ToggleFullscreen,
+        Action::ToggleSlideshow,
+        Action::PauseSlideshow,
+        Action::CloseView,
+        Action::OpenFile,
+        Action::MoveToTrash,
+        Action::Rename,
+        Action::MoveToFolder,
+        Action::ToggleStar,
+        Action::RateOne,
+        Action::RateTwo,
+        Action::RateThree,
+        Action::RateFour,
+        Action::RateFive,
+        Action::Tags,
+        Action::FilterBar,
+        Action::CopyImage,
+        Action::CopyPath,
+        Action::PasteImage,
+        Action::SaveAs,
+        Action::OpenInEditor,
+        Action::CompareClipboard,
+        Action::SplitView,
+        Action::Gallery,
+        Action::Filmstrip,
+        Action::ExifInfo,
+        Action::EditMetadata,
+        Action::Map,
+        Action::ScanCodes,
+        Action::Upscale,
+        Action::Checksums,
+        Action::LensCorrection,
+        Action::Palette,
+        Action::Histogram,
+        Action::Measure,
+        Action::CycleGrid,
+        Action::Guides,
+        Action::PixelPerfect,
+        Action::Loupe,
+        Action::LoupeLarger,
+        Action::LoupeSmaller,
+        Action::PixelInspector,
+        Action::Crop,
+        Action::Adjust,
+        Action::Shortcuts,
+        Action::Quit,
+    ];
+
+    /// The stars a rating action gives
+    pub fn rating(self) -> Option<u8> {
+        match self {
+            Action::RateOne => Some(1),
+            Action::RateTwo => Some(2),
+            Action::RateThree => Some(3),
+            Action::RateFour => Some(4),
+            Action::RateFive => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Whether the action pans, which only applies while the image is
+    /// larger than the view
+    pub fn is_pan(self) -> bool {
+        matches!(
+            self,
+            Action::PanLeft
+                | Action::PanRight
+                | Action::PanUp
+                | Action::PanDown
+        )
+    }
+
+    /// What the action does, for listing it to the user
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::NextImage => "Next image",
+            Action::PreviousImage => "Previous image",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ResetZoom => "Reset zoom",
+            Action::FitToWindow => "Fit to window",
+            Action::FillWindow => "Fill window",
+            Action::ActualSize => "Actual size",
+            Action::RotateClockwise => "Rotate clockwise",
+            Action::RotateCounterClockwise => "Rotate counter-clockwise",
+            Action::FlipHorizontal => "Flip horizontally",
+            Action::FlipVertical => "Flip vertically",
+            Action::ToggleMenu => "Show or hide the menu",
+            Action::ToggleFullscreen => "Fullscreen",
+            Action::ToggleSlideshow => "Start or stop the slideshow",
+            Action::PauseSlideshow => "Pause the slideshow",
+            Action::CloseView => "Leave gallery, slideshow or comparison",
+            Action::OpenFile => "Open a file",
+            Action::MoveToTrash => "Move the image to the trash",
+            Action::Rename => "Rename the image",
+            Action::MoveToFolder => "Move the image to a folder",
+            Action::ToggleStar => "Star or unstar the image",
+            Action::RateOne => "Rate one star",
+            Action::RateTwo => "Rate two stars",
+            Action::RateThree => "Rate three stars",
+            Action::RateFour => "Rate four stars",
+            Action::RateFive => "Rate five stars",
+            Action::Tags => "Rating and tags",
+            Action::FilterBar => "Filter by rating and tags",
+            Action::CopyImage => "Copy the image",
+            Action::CopyPath => "Copy the file path",
+            Action::PasteImage => "Show the image on the clipboard",
+            Action::SaveAs => "Save the image as",
+            Action::OpenInEditor => "Open the image in the first editor",
+            Action::CompareClipboard => "Compare with the clipboard",
+            Action::SplitView => "Split the window in two",
+            Action::Gallery => "Gallery",
+            Action::Filmstrip => "Filmstrip",
+            Action::ExifInfo => "EXIF info",
+            Action::EditMetadata => "Edit metadata",
+            Action::Map => "Location map",
+            Action::ScanCodes => "Scan for codes",
+            Action::Upscale => "Upscale",
+            Action::Checksums => "Checksums",
+            Action::LensCorrection => "Lens correction",
+            Action::Palette => "Color palette",
+            Action::Histogram => "Histogram",
+            Action::Measure => "Measure",
+            Action::CycleGrid => "Cycle composition grids",
+            Action::Guides => "Guides",
+            Action::PixelPerfect => "Snap zoom to whole pixels",
+            Action::Loupe => "Loupe",
+            Action::LoupeLarger => "Larger loupe",
+            Action::LoupeSmaller => "Smaller loupe",
+            Action::PixelInspector => "Pixel inspector",
+            Action::Crop => "Crop",
+            Action::Adjust => "Adjust brightness and colors",
+            Action::Shortcuts => "Keyboard shortcuts",
+        }
+    }
+}
+
+/// A key together with the modifiers held for it. Ctrl stands for Command
+/// on macOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    pub key:   Key,
+    pub ctrl:  bool,
+    pub shift: bool,
+    pub alt:   bool,
+}
+
+impl KeyChord {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub const fn ctrl(self) -> Self {
+        Self {
+            ctrl: true,
+            ..self
+        }
+    }
+
+    pub const fn shift(self) -> Self {
+        Self {
+            shift: true,
+            ..self
+        }
+    }
+
+    /// Parses chords written like `Ctrl+Shift+V`, `F11` or `Space`, using
+    /// egui's key names.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid =
+            || ConfigError::ValidationError(format!("Invalid key: {}", text));
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts
+            .pop()
+            .and_then(Key::from_name)
+            .ok_or_else(invalid)?;
+        let mut chord = Self::new(key);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "cmd" | "command" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" | "option" => chord.alt = true,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(chord)
+    }
+
+    fn format(&self, key: &str) -> String {
+        let mut text = String::new();
+        for (held, name) in
+            [(self.ctrl, "Ctrl+"), (self.shift, "Shift+"), (self.alt, "Alt+")]
+        {
+            if held {
+                text.push_str(name);
+            }
+        }
+        text + key
+    }
+}
+
+/// Shows keys by their symbol where they have one, such as `+` or `⏵`
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(self.key.symbol_or_name()))
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = ConfigError;
+
+    fn try_from(text: String) -> Result<Self> {
+        Self::parse(&text)
+    }
+}
+
+/// Written with key names, which are easier to type in the config file
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.format(chord.key.name())
+    }
+}
+
+/// The chords bound to each action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<Action, Vec<KeyChord>>",
+    into = "BTreeMap<Action, Vec<KeyChord>>"
+)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let key = KeyChord::new;
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| {
+                let chords = match action {
+                    Action::Quit => vec![key(Key::Q)],
+                    Action::NextImage => {
+                        vec![
+                            key(Key::ArrowRight),
+                            key(Key::D),
+                            key(Key::PageDown),
+                        ]
+                    },
+                    Action::PreviousImage => {
+                        vec![key(Key::ArrowLeft), key(Key::A), key(Key::PageUp)]
+                    },
+                    Action::PanLeft => vec![key(Key::ArrowLeft)],
+                    Action::PanRight => vec![key(Key::ArrowRight)],
+                    Action::PanUp => vec![key(Key::ArrowUp)],
+                    Action::PanDown => vec![key(Key::ArrowDown)],
+                    Action::ZoomIn => {
+                        vec![key(Key::Plus), key(Key::Equals), key(Key::W)]
+                    },
+                    Action::ZoomOut => vec![key(Key::Minus), key(Key::S)],
+                    Action::ResetZoom => vec![key(Key::Num0)],
+                    Action::FitToWindow => vec![key(Key::F)],
+                    Action::FillWindow => vec![key(Key::F).shift()],
+                    Action::ActualSize => vec![key(Key::Num1)],
+                    Action::RotateClockwise => vec![key(Key::C)],
+                    Action::RotateCounterClockwise => vec![key(Key::C).shift()],
+                    Action::FlipHorizontal => vec![key(Key::X)],
+                    Action::FlipVertical => vec![key(Key::Y)],
+                    Action::ToggleMenu => vec![key(Key::M)],
+                    Action::ToggleFullscreen => vec![key(Key::F11)],
+                    Action::ToggleSlideshow => vec![key(Key::F5)],
+                    Action::PauseSlideshow => vec![key(Key::Space)],
+                    Action::CloseView => vec![key(Key::Escape)],
+                    Action::OpenFile => vec![key(Key::O).ctrl()],
+                    Action::MoveToTrash => vec![key(Key::Delete)],
+                    Action::Rename => vec![key(Key::F2)],
+                    Action::MoveToFolder => vec![key(Key::M).shift()],
+                    Action::ToggleStar => vec![key(Key::S).shift()],
+                    Action::RateOne => vec![key(Key::Num1).ctrl()],
+                    Action::RateTwo => vec![key(Key::Num2).ctrl()],
+                    Action::RateThree => vec![key(Key::Num3).ctrl()],
+                    Action::RateFour => vec![key(Key::Num4).ctrl()],
+                    Action::RateFive => vec![key(Key::Num5).ctrl()],
+                    Action::Tags => vec![key(Key::T).shift()],
+                    Action::FilterBar => vec![key(Key::F).ctrl()],
+                    Action::CopyImage => vec![key(Key::C).ctrl()],
+                    Action::CopyPath => vec![key(Key::C).ctrl().shift()],
+                    Action::PasteImage => vec![key(Key::V).ctrl()],
+                    Action::SaveAs => vec![key(Key::S).ctrl()],
+                    Action::OpenInEditor => vec![key(Key::E).shift()],
+                    Action::CompareClipboard => {
+                        vec![key(Key::V).ctrl().shift()]
+                    },
+                    Action::SplitView => vec![key(Key::Backslash)],
+                    Action::Gallery => vec![key(Key::T)],
+                    Action::Filmstrip => vec![key(Key::N)],
+                    Action::ExifInfo => vec![key(Key::E)],
+                    Action::EditMetadata => vec![key(Key::K)],
+                    Action::Map => vec![key(Key::L)],
+                    Action::ScanCodes => vec![key(Key::B)],
+                    Action::Upscale => vec![key(Key::U)],
+                    Action::Checksums => vec![key(Key::H)],
+                    Action::LensCorrection => vec![key(Key::J)],
+                    Action::Palette => vec![key(Key::P)],
+                    Action::Histogram => vec![key(Key::H).shift()],
+                    Action::Measure => vec![key(Key::R)],
+                    Action::CycleGrid => vec![key(Key::G)],
+                    Action::Guides => vec![key(Key::Semicolon)],
+                    Action::PixelPerfect => vec![key(Key::I)],
+                    Action::Loupe => vec![key(Key::Z)],
+                    Action::LoupeLarger => vec![key(Key::CloseBracket)],
+                    Action::LoupeSmaller => vec![key(Key::OpenBracket)],
+                    Action::PixelInspector => vec![key(Key::I).shift()],
+                    Action::Crop => vec![key(Key::X).shift()],
+                    Action::Adjust => vec![key(Key::A).shift()],
+                    Action::Shortcuts => vec![key(Key::F1)],
+                };
+                (action, chords)
+            })
+            .collect();
+        Self {
+            bindings,
+        }
+    }
+}
+
+impl From<BTreeMap<Action, Vec<KeyChord>>> for Keymap {
+    fn from(bindings: BTreeMap<Action, Vec<KeyChord>>) -> Self {
+        let mut keymap = Self::default();
+        keymap.bindings.extend(bindings);
+        keymap
+    }
+}
+
+impl From<Keymap> for BTreeMap<Action, Vec<KeyChord>> {
+    fn from(keymap: Keymap) -> Self {
+        keymap.bindings
+    }
+}
+
+impl Keymap {
+    pub fn chords(&self, action: Action) -> &[KeyChord] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Adds `chord` to the chords of `action`, if it is not there yet.
+    pub fn bind(&mut self, action: Action, chord: KeyChord) {
+        let chords = self.bindings.entry(action).or_default();
+        if !chords.contains(&chord) {
+            chords.push(chord);
+        }
+    }
+
+    /// Binds `action` to exactly `chords`, taking them from any other
+    /// action they were bound to.
+    pub fn rebind(&mut self, action: Action, chords: Vec<KeyChord>) {
+        for (other, bound) in self.bindings.iter_mut() {
+            if *other != action {
+                bound.retain(|chord| !chords.contains(chord));
+            }
+        }
+        self.bindings.insert(action, chords);
+    }
+
+    pub fn unbind(&mut self, action: Action, chord: KeyChord) {
+        if let Some(chords) = self.bindings.get_mut(&action) {
+            chords.retain(|c| *c != chord);
+        }
+    }
+
+    /// The action bound to a key pressed with the given modifiers, other
+    /// than panning. A chord naming Shift wins; otherwise Shift is ignored,
+    /// since keys such as `+` need it on many layouts.
+    pub fn action(
+        &self,
+        key: Key,
+        ctrl: bool,
+        shift: bool,
+        alt: bool,
+    ) -> Option<Action> {
+        let find = |shift| {
+            let pressed = KeyChord {
+                key,
+                ctrl,
+                shift,
+                alt,
+            };
+            self.bindings
+                .iter()
+                .find(|(action, chords)| {
+                    !action.is_pan() && chords.contains(&pressed)
+                })
+                .map(|(action, _)| *action)
+        };
+        find(shift).or_else(|| if shift { find(false) } else { None })
+    }
+
+    /// The actions other than `action` that `chord` is also bound to. A
+    /// panning action shares its chords with the others.
+    pub fn conflicts(&self, action: Action, chord: KeyChord) -> Vec<Action> {
+        self.bindings
+            .iter()
+            .filter(|(other, chords)| {
+                **other != action
+                    && other.is_pan() == action.is_pan()
+                    && chords.contains(&chord)
+            })
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (&action, chords) in &self.bindings {
+            for &chord in chords {
+                if let Some(other) = self.conflicts(action, chord).first() {
+                    return Err(ConfigError::ValidationError(format!(
+                        "{} is bound to both {:?} and {:?}",
+                        chord, action, other
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chords_round_trip_through_text() {
+        for text in ["Ctrl+Shift+V", "F11", "Space", "Shift+C", "Plus"] {
+            assert_eq!(String::from(KeyChord::parse(text).unwrap()), text);
+        }
+        assert_eq!(
+            KeyChord::parse("Shift+Plus").unwrap().to_string(),
+            "Shift++"
+        );
+        assert_eq!(
+            KeyChord::parse("cmd+o").unwrap(),
+            KeyChord::new(Key::O).ctrl()
+        );
+        assert!(KeyChord::parse("Hyper+O").is_err());
+        assert!(KeyChord::parse("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn test_partial_keymap_keeps_other_defaults() {
+        let keymap: Keymap = toml::from_str(
+            r#"
+            next_image = ["N", "Space"]
+            quit = []
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            keymap.action(Key::Space, false, false, false),
+            Some(Action::NextImage)
+        );
+        assert_eq!(keymap.action(Key::Q, false, false, false), None);
+        assert_eq!(keymap.chords(Action::ZoomOut).len(), 2);
+
+        // Space and N are also taken by the slideshow and filmstrip
+        assert_eq!(
+            keymap.conflicts(Action::NextImage, KeyChord::new(Key::N)),
+            [Action::Filmstrip]
+        );
+        assert!(keymap.validate().is_err());
+        assert!(Keymap::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_shift_chords_win_over_plain_ones() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(Key::F, false, true, false),
+            Some(Action::FillWindow)
+        );
+        assert_eq!(
+            keymap.action(Key::F, false, false, false),
+            Some(Action::FitToWindow)
+        );
+        // Typed with Shift on most layouts
+        assert_eq!(
+            keymap.action(Key::Plus, false, true, false),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(keymap.action(Key::O, false, false, false), None);
+    }
+
+    #[test]
+    fn test_pan_chords_are_shared_with_other_actions() {
+        let mut keymap = Keymap::default();
+        let left = KeyChord::new(Key::ArrowLeft);
+        assert_eq!(keymap.chords(Action::PanLeft), &[left]);
+        assert_eq!(
+            keymap.action(Key::ArrowLeft, false, false, false),
+            Some(Action::PreviousImage)
+        );
+        assert!(keymap.conflicts(Action::PanLeft, left).is_empty());
+
+        // Only with each other do panning actions conflict
+        keymap.bind(Action::PanRight, left);
+        assert_eq!(keymap.conflicts(Action::PanLeft, left), [Action::PanRight]);
+        assert!(keymap.validate().is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for lensfun-based lens correction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LensConfig {
+    /// Extra lensfun database directory, searched before the system ones
+    pub database_dir: Option<PathBuf>,
+    /// Correct every photo with a known lens as soon as it is opened
+    pub auto_apply:   bool,
+}
+
+impl LensConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -28,10 +28,37 @@ pub use config::FerriteConfig;
 pub use error::{ConfigError, Result};
 
 // Re-export configuration component types
-pub use input::ControlsConfig;
-pub use ui::{IndicatorConfig, SelectionConfig};
+pub use batch::BatchConfig;
+pub use cache::CacheConfig;
+pub use color::{ColorConfig, DitherMode};
+pub use decoding::DecodingConfig;
+pub use editors::{EditorsConfig, ExternalEditor};
+pub use input::{ControlsConfig, TouchpadConfig, WheelAction, WheelBindings};
+pub use instance::InstanceConfig;
+pub use io::IoConfig;
+pub use keymap::{Action, KeyChord, Keymap};
+pub use lens::LensConfig;
+pub use logging::LoggingConfig;
+pub use loupe::{LoupeConfig, LoupeShape};
+pub use lut::LutConfig;
+pub use map::MapConfig;
+pub use navigation::{NavigationConfig, SortOrder};
+pub use paths::PathsConfig;
+pub use remote::RemoteConfig;
+pub use slideshow::{SlideshowConfig, Transition};
+pub use sorting::{MoveTarget, SortingConfig};
+pub use ui::{
+    BackgroundConfig,
+    BackgroundMode,
+    IndicatorConfig,
+    SelectionConfig,
+    StatusBarConfig,
+};
+pub use upscale::UpscaleConfig;
+pub use wallpaper::WallpaperConfig;
 pub use window::WindowConfig;
-pub use zoom::ZoomConfig;
+pub use xmp::{XmpConfig, XmpTarget};
+pub use zoom::{ZoomConfig, ZoomSteps};
 
 // Re-export common types used in configuration
 pub use types::{Color32, ColorRGBA, Corner, Key, MouseButton, Vector2D};
@@ -41,12 +68,32 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const CONFIG_VERSION: &str = "0.1";
 
 // Internal modules
+mod batch;
+mod cache;
+mod color;
 mod config;
+mod decoding;
 mod defaults;
+mod editors;
 mod error;
 mod input;
+mod instance;
+mod io;
+mod keymap;
+mod lens;
+mod logging;
+mod loupe;
+mod lut;
+mod map;
 mod navigation;
+mod paths;
+mod remote;
+mod slideshow;
+mod sorting;
 mod types;
 mod ui;
+mod upscale;
+mod wallpaper;
 mod window;
+mod xmp;
 mod zoom;
@@ -0,0 +1,42 @@
+use crate::{
+    defaults::logging::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// Logging and profiling settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Stream spans to a Tracy profiler from startup. Can also be enabled
+    /// with `--profile-tracy` or toggled at runtime from the Tools menu.
+    pub enable_tracy:     bool,
+    /// Keep a rotating log in the platform state directory
+    pub log_to_file:      bool,
+    /// Size at which the log file is rotated
+    pub max_file_size_mb: u64,
+    /// Number of rotated log files to keep
+    pub max_files:        u32,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            enable_tracy:     ENABLE_TRACY,
+            log_to_file:      LOG_TO_FILE,
+            max_file_size_mb: MAX_FILE_SIZE_MB,
+            max_files:        MAX_FILES,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_file_size_mb == 0 {
+            return Err(ConfigError::ValidationError(
+                "Log file size limit must be at least 1 MB".into(),
+            ));
+        }
+        Ok(())
+    }
+}
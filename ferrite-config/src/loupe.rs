@@ -0,0 +1,60 @@
+use crate::{
+    defaults::loupe::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum LoupeShape {
+    #[serde(rename = "circle")]
+    #[default]
+    Circle,
+    #[serde(rename = "square")]
+    Square,
+}
+
+/// Settings for the magnifier that follows the cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoupeConfig {
+    /// Magnification relative to the image as currently displayed
+    pub magnification: f32,
+    /// Diameter or side length in screen points
+    pub size:          f32,
+    pub shape:         LoupeShape,
+}
+
+impl Default for LoupeConfig {
+    fn default() -> Self {
+        Self {
+            magnification: MAGNIFICATION,
+            size:          SIZE,
+            shape:         LoupeShape::default(),
+        }
+    }
+}
+
+impl LoupeConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_MAGNIFICATION..=MAX_MAGNIFICATION)
+            .contains(&self.magnification)
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Loupe magnification must be between {} and {}",
+                MIN_MAGNIFICATION, MAX_MAGNIFICATION
+            )));
+        }
+        if self.size < 32.0 {
+            return Err(ConfigError::ValidationError(
+                "Loupe size must be at least 32".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn magnification_range() -> std::ops::RangeInclusive<f32> {
+        MIN_MAGNIFICATION..=MAX_MAGNIFICATION
+    }
+}
@@ -0,0 +1,26 @@
+use crate::error::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for creative `.cube` LUTs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LutConfig {
+    /// Folder the LUT browser lists; unset uses `luts` in the platform
+    /// config directory
+    pub folder: Option<PathBuf>,
+}
+
+impl LutConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn resolve_folder(&self) -> Option<PathBuf> {
+        self.folder.clone().or_else(|| {
+            ProjectDirs::from("com", "ferrite", "ferrite")
+                .map(|dirs| dirs.config_dir().join("luts"))
+        })
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    defaults::map::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for the GPS map panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapConfig {
+    /// Tile server template with `{z}`, `{x}` and `{y}` placeholders
+    pub tile_url:     String,
+    pub default_zoom: u8,
+    /// Where downloaded tiles are kept; defaults to `tiles` in the cache
+    /// directory
+    pub cache_dir:    Option<PathBuf>,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            tile_url:     TILE_URL.to_string(),
+            default_zoom: DEFAULT_ZOOM,
+            cache_dir:    None,
+        }
+    }
+}
+
+impl MapConfig {
+    pub fn validate(&self) -> Result<()> {
+        for placeholder in ["{z}", "{x}", "{y}"] {
+            if !self.tile_url.contains(placeholder) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Map tile URL must contain {}",
+                    placeholder
+                )));
+            }
+        }
+        if self.default_zoom > MAX_ZOOM {
+            return Err(ConfigError::ValidationError(format!(
+                "Map zoom must be at most {}",
+                MAX_ZOOM
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn max_zoom() -> u8 {
+        MAX_ZOOM
+    }
+}
@@ -4,38 +4,84 @@ use crate::{
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
 pub enum SortOrder {
+    /// File name, with runs of digits compared by value
     #[serde(rename = "name")]
+    #[default]
     ByName,
     #[serde(rename = "modified")]
     ByModified,
+    #[serde(rename = "size")]
+    BySize,
+    /// EXIF capture date; images without one come after the dated ones
+    /// when ascending
+    #[serde(rename = "captured")]
+    ByCaptureDate,
 }
 
-impl Default for SortOrder {
-    fn default() -> Self {
-        Self::ByName
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [
+        SortOrder::ByName,
+        SortOrder::ByModified,
+        SortOrder::BySize,
+        SortOrder::ByCaptureDate,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::ByName => "Name",
+            SortOrder::ByModified => "Date Modified",
+            SortOrder::BySize => "File Size",
+            SortOrder::ByCaptureDate => "Date Taken",
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NavigationConfig {
-    pub sort_order:    SortOrder,
-    pub reverse_order: bool,
+    pub sort_order:      SortOrder,
+    /// Sort descending instead of ascending
+    pub reverse_order:   bool,
+    /// List symlinked images alongside regular files
+    pub follow_symlinks: bool,
+    /// Show an image reachable through several links only once
+    pub dedupe_symlinks: bool,
+    /// Include dot files
+    pub show_hidden:     bool,
+    /// Levels of subfolders searched when a folder is opened
+    pub max_depth:       usize,
+    /// Images listed at most from an opened folder
+    pub max_images:      usize,
+    /// Ask before moving the current image to the trash
+    pub confirm_delete:  bool,
 }
 
 impl Default for NavigationConfig {
     fn default() -> Self {
         Self {
-            sort_order: SortOrder::default(), reverse_order: false
+            sort_order:      SortOrder::default(),
+            reverse_order:   DEFAULT_REVERSE_ORDER,
+            follow_symlinks: FOLLOW_SYMLINKS,
+            dedupe_symlinks: DEDUPE_SYMLINKS,
+            show_hidden:     SHOW_HIDDEN,
+            max_depth:       MAX_DEPTH,
+            max_images:      MAX_IMAGES,
+            confirm_delete:  CONFIRM_DELETE,
         }
     }
 }
 
 impl NavigationConfig {
     pub fn validate(&self) -> Result<()> {
-        // Currently no validation needed, but keeping the method for
-        // consistency and future extensions
+        if self.max_images == 0 {
+            return Err(ConfigError::ValidationError(
+                "Opening a folder must list at least one image".into(),
+            ));
+        }
         Ok(())
     }
 }
@@ -0,0 +1,61 @@
+use crate::error::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Where Ferrite keeps files other than this configuration, which is found
+/// through `FERRITE_CONF` or the platform config directory. Unset entries
+/// use the platform directories: `$XDG_CACHE_HOME/ferrite` and
+/// `$XDG_STATE_HOME/ferrite` on Linux.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// Disposable data such as map tiles and rendered wallpapers
+    pub cache_dir: Option<PathBuf>,
+    /// Data worth keeping between runs: logs, crash reports, sessions and
+    /// recent files
+    pub state_dir: Option<PathBuf>,
+}
+
+impl PathsConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn resolve_cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir.clone().or_else(|| {
+            ProjectDirs::from("com", "ferrite", "ferrite")
+                .map(|dirs| dirs.cache_dir().to_path_buf())
+        })
+    }
+
+    /// The configured state directory, else the platform one, else the
+    /// local data directory on platforms without a state directory.
+    pub fn resolve_state_dir(&self) -> Option<PathBuf> {
+        self.state_dir.clone().or_else(|| {
+            ProjectDirs::from("com", "ferrite", "ferrite").map(|dirs| {
+                dirs.state_dir()
+                    .unwrap_or(dirs.data_local_dir())
+                    .to_path_buf()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_directories_override_platform_ones() {
+        let paths = PathsConfig {
+            cache_dir: Some(PathBuf::from("/srv/ferrite/cache")),
+            state_dir: None,
+        };
+        assert_eq!(
+            paths.resolve_cache_dir(),
+            Some(PathBuf::from("/srv/ferrite/cache"))
+        );
+        assert_ne!(paths.resolve_state_dir(), paths.resolve_cache_dir());
+    }
+}
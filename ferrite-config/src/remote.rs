@@ -0,0 +1,69 @@
+use crate::{
+    defaults::remote::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+/// Settings for images opened from HTTP(S) URLs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Downloads larger than this are refused
+    pub max_download_mb: u64,
+    /// Give up on a download that has not finished after this long
+    pub timeout_secs:    f64,
+    /// Where downloaded images are kept; defaults to `remote` in the cache
+    /// directory
+    pub cache_dir:       Option<PathBuf>,
+    /// The least recently opened downloads are removed once the cache grows
+    /// past this
+    pub max_cache_mb:    u64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            max_download_mb: MAX_DOWNLOAD_MB,
+            timeout_secs:    TIMEOUT_SECS,
+            cache_dir:       None,
+            max_cache_mb:    MAX_CACHE_MB,
+        }
+    }
+}
+
+impl RemoteConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_download_mb == 0 {
+            return Err(ConfigError::ValidationError(
+                "Download size limit must be at least 1 MB".into(),
+            ));
+        }
+        if self.max_cache_mb == 0 {
+            return Err(ConfigError::ValidationError(
+                "Download cache limit must be at least 1 MB".into(),
+            ));
+        }
+        if !valid_timeout(self.timeout_secs) {
+            return Err(ConfigError::ValidationError(format!(
+                "Download timeout must be between 0 and {} seconds",
+                MAX_TIMEOUT_SECS
+            )));
+        }
+        Ok(())
+    }
+
+    /// The download timeout, or the default one if the configured value was
+    /// never validated and is out of range
+    pub fn timeout(&self) -> Duration {
+        if valid_timeout(self.timeout_secs) {
+            Duration::from_secs_f64(self.timeout_secs)
+        } else {
+            Duration::from_secs_f64(TIMEOUT_SECS)
+        }
+    }
+}
+
+fn valid_timeout(secs: f64) -> bool {
+    secs > 0.0 && secs <= MAX_TIMEOUT_SECS
+}
@@ -0,0 +1,64 @@
+use crate::{
+    defaults::slideshow::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// How one slideshow image is replaced by the next
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum Transition {
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "crossfade")]
+    #[default]
+    Crossfade,
+    /// The next image pushes the current one out to the left
+    #[serde(rename = "slide")]
+    Slide,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlideshowConfig {
+    /// How long each image is shown
+    pub interval_secs:   f64,
+    /// Slowly pan and zoom across each image while it is shown
+    pub ken_burns:       bool,
+    pub transition:      Transition,
+    pub transition_secs: f64,
+    /// Show the folder in random order, each image once per round
+    pub shuffle:         bool,
+    /// Start over after the last image instead of stopping
+    pub repeat:          bool,
+}
+
+impl Default for SlideshowConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs:   INTERVAL_SECS,
+            ken_burns:       KEN_BURNS,
+            transition:      Transition::default(),
+            transition_secs: TRANSITION_SECS,
+            shuffle:         SHUFFLE,
+            repeat:          REPEAT,
+        }
+    }
+}
+
+impl SlideshowConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.interval_secs.is_finite() && self.interval_secs >= 0.5) {
+            return Err(ConfigError::ValidationError(
+                "Slideshow interval must be at least 0.5 seconds".into(),
+            ));
+        }
+        if !(0.0..self.interval_secs).contains(&self.transition_secs) {
+            return Err(ConfigError::ValidationError(
+                "Slideshow transition must be shorter than the interval".into(),
+            ));
+        }
+        Ok(())
+    }
+}
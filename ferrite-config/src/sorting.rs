@@ -0,0 +1,54 @@
+use crate::{
+    error::{ConfigError, Result},
+    keymap::KeyChord,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A folder the current image is moved into when `key` is pressed. A
+/// relative folder is taken from the image's own folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveTarget {
+    pub key:    KeyChord,
+    pub folder: PathBuf,
+}
+
+/// Moving images into folders with single keys, for sorting through a
+/// folder of photos
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortingConfig {
+    /// Keys that move the current image into a folder, such as `1` to `9`.
+    /// They win over the keymap, so they can take keys bound there.
+    pub targets: Vec<MoveTarget>,
+}
+
+impl SortingConfig {
+    /// The folder bound to `key`, if any
+    pub fn target(&self, key: KeyChord) -> Option<&MoveTarget> {
+        self.targets
+            .iter()
+            .find(|target| target.key == key)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (index, target) in self.targets.iter().enumerate() {
+            if target.folder.as_os_str().is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Sorting target {} has no folder",
+                    target.key
+                )));
+            }
+            if self.targets[..index]
+                .iter()
+                .any(|other| other.key == target.key)
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "Key {} is bound to several sorting targets",
+                    target.key
+                )));
+            }
+        }
+        Ok(())
+    }
+}
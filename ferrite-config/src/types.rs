@@ -50,6 +50,11 @@ impl ColorRGBA {
         Ok(Self::new(r, g, b, a))
     }
 
+    /// Converts the color for drawing with egui
+    pub fn to_color32(&self) -> Color32 {
+        Color32::from_rgba_unmultiplied(self.r, self.g, self.b, self.a)
+    }
+
     /// Converts the color to a hexadecimal string
     pub fn to_hex(&self) -> String {
         format!("#{:02X}{:02X}{:02X}{:02X}", self.r, self.g, self.b, self.a)
@@ -90,34 +95,28 @@ impl Vector2D {
 }
 
 /// Represents the corner of a window or display area
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
 pub enum Corner {
     TopLeft,
+    #[default]
     TopRight,
     BottomLeft,
     BottomRight,
 }
 
-impl Default for Corner {
-    fn default() -> Self {
-        Corner::TopRight
-    }
-}
-
 /// Represents a mouse button
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
 pub enum MouseButton {
+    #[default]
     Left,
     Right,
     Middle,
 }
 
-impl Default for MouseButton {
-    fn default() -> Self {
-        MouseButton::Left
-    }
-}
-
 /// Re-export eframe types for consistency
 pub use eframe::egui::{Color32, Key};
 
@@ -140,40 +139,3 @@ mod tests {
         assert!(Vector2D::new(f64::INFINITY, 2.0).is_err());
     }
 }
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(from = "&str", into = "String")]
-pub struct SerializableKey(Key);
-
-impl From<&str> for SerializableKey {
-    fn from(s: &str) -> Self {
-        // Implement key string parsing
-        Self(match s {
-            "Equal" => Key::Equals,
-            "Plus" => Key::Plus,
-            "Minus" => Key::Minus,
-            "W" => Key::W,
-            "S" => Key::S,
-            "F" => Key::F,
-            "Q" => Key::Q,
-            "Num0" => Key::Num0,
-            _ => Key::Equals, // Default
-        })
-    }
-}
-
-impl From<SerializableKey> for String {
-    fn from(key: SerializableKey) -> Self {
-        match key.0 {
-            Key::Equals => "Equal",
-            Key::Plus => "Plus",
-            Key::Minus => "Minus",
-            Key::W => "W",
-            Key::S => "S",
-            Key::F => "F",
-            Key::Num0 => "Num0",
-            _ => "Equal",
-        }
-        .to_string()
-    }
-}
@@ -0,0 +1,78 @@
+use crate::{
+    defaults::background::*,
+    error::{ConfigError, Result},
+    types::ColorRGBA,
+};
+use serde::{Deserialize, Serialize};
+
+/// What shows through the transparent parts of an image
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum BackgroundMode {
+    #[serde(rename = "checkerboard")]
+    #[default]
+    Checkerboard,
+    /// The color set in `BackgroundConfig::color`
+    #[serde(rename = "solid")]
+    Solid,
+    #[serde(rename = "black")]
+    Black,
+    #[serde(rename = "white")]
+    White,
+    /// The window's own panel color
+    #[serde(rename = "none")]
+    None,
+}
+
+impl BackgroundMode {
+    pub const ALL: [BackgroundMode; 5] = [
+        BackgroundMode::Checkerboard,
+        BackgroundMode::Solid,
+        BackgroundMode::Black,
+        BackgroundMode::White,
+        BackgroundMode::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BackgroundMode::Checkerboard => "Checkerboard",
+            BackgroundMode::Solid => "Solid Color",
+            BackgroundMode::Black => "Black",
+            BackgroundMode::White => "White",
+            BackgroundMode::None => "Window Color",
+        }
+    }
+}
+
+/// Background drawn behind the image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundConfig {
+    pub mode:         BackgroundMode,
+    pub color:        ColorRGBA,
+    /// Side of one checkerboard square in screen points
+    pub checker_size: f32,
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            mode:         BackgroundMode::default(),
+            color:        ColorRGBA::new(COLOR.0, COLOR.1, COLOR.2, COLOR.3),
+            checker_size: CHECKER_SIZE,
+        }
+    }
+}
+
+impl BackgroundConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_CHECKER_SIZE..=MAX_CHECKER_SIZE).contains(&self.checker_size) {
+            return Err(ConfigError::ValidationError(format!(
+                "Checkerboard size must be between {} and {}",
+                MIN_CHECKER_SIZE, MAX_CHECKER_SIZE
+            )));
+        }
+        Ok(())
+    }
+}
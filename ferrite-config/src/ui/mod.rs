@@ -1,5 +1,9 @@
+mod background;
 mod indicator;
 mod selection;
+mod status_bar;
 
+pub use background::{BackgroundConfig, BackgroundMode};
 pub use indicator::IndicatorConfig;
 pub use selection::SelectionConfig;
+pub use status_bar::StatusBarConfig;
@@ -0,0 +1,44 @@
+use crate::{defaults::status_bar::*, error::Result};
+use serde::{Deserialize, Serialize};
+
+/// The bar along the bottom of the window describing the image on screen.
+/// Each field can be hidden on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusBarConfig {
+    pub visible:         bool,
+    pub show_name:       bool,
+    /// A star on images among the favorites
+    pub show_star:       bool,
+    /// The XMP star rating of rated images
+    pub show_rating:     bool,
+    /// Width and height in pixels
+    pub show_resolution: bool,
+    pub show_file_size:  bool,
+    pub show_format:     bool,
+    pub show_zoom:       bool,
+    /// Image coordinates and color of the pixel under the cursor
+    pub show_cursor:     bool,
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            visible:         VISIBLE,
+            show_name:       SHOW_NAME,
+            show_star:       SHOW_STAR,
+            show_rating:     SHOW_RATING,
+            show_resolution: SHOW_RESOLUTION,
+            show_file_size:  SHOW_FILE_SIZE,
+            show_format:     SHOW_FORMAT,
+            show_zoom:       SHOW_ZOOM,
+            show_cursor:     SHOW_CURSOR,
+        }
+    }
+}
+
+impl StatusBarConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    defaults::upscale::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Settings for the external super-resolution tool used by the upscale
+/// action. Any binary with the `realesrgan-ncnn-vulkan` command line
+/// (`-i`, `-o`, `-n`, `-s`) works.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpscaleConfig {
+    pub binary:        PathBuf,
+    pub model:         String,
+    pub default_scale: u32,
+    /// Directory for exported results; defaults to the source image folder
+    pub output_dir:    Option<PathBuf>,
+}
+
+impl Default for UpscaleConfig {
+    fn default() -> Self {
+        Self {
+            binary:        PathBuf::from(BINARY),
+            model:         MODEL.to_string(),
+            default_scale: DEFAULT_SCALE,
+            output_dir:    None,
+        }
+    }
+}
+
+impl UpscaleConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !SUPPORTED_SCALES.contains(&self.default_scale) {
+            return Err(ConfigError::ValidationError(format!(
+                "Upscale factor must be one of {:?}",
+                SUPPORTED_SCALES
+            )));
+        }
+        if self.model.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Upscale model name cannot be empty".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn supported_scales() -> &'static [u32] {
+        SUPPORTED_SCALES
+    }
+}
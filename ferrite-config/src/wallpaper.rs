@@ -0,0 +1,47 @@
+use crate::{
+    defaults::wallpaper::*,
+    error::{ConfigError, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// Settings for `--wallpaper-daemon`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WallpaperConfig {
+    /// How long each wallpaper stays, unless `--interval` is given
+    pub interval_secs: u64,
+    /// Pick the next wallpaper at random instead of in folder order
+    pub shuffle:       bool,
+    /// Command that sets the wallpaper, with `{path}` replaced by the
+    /// rendered PNG. Detected from the desktop environment when unset.
+    pub command:       Option<String>,
+}
+
+impl Default for WallpaperConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: INTERVAL_SECS,
+            shuffle:       SHUFFLE,
+            command:       None,
+        }
+    }
+}
+
+impl WallpaperConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.interval_secs == 0 {
+            return Err(ConfigError::ValidationError(
+                "Wallpaper interval must be at least one second".into(),
+            ));
+        }
+        if let Some(command) = &self.command {
+            if !command.contains("{path}") {
+                return Err(ConfigError::ValidationError(
+                    "Wallpaper command must contain a {path} placeholder"
+                        .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
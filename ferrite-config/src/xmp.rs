@@ -0,0 +1,28 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+
+/// Where edited descriptive metadata is written
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum XmpTarget {
+    /// An `.xmp` file next to the image
+    #[serde(rename = "sidecar")]
+    #[default]
+    Sidecar,
+    /// Inside the image file (JPEG only; other formats use a sidecar)
+    #[serde(rename = "embedded")]
+    Embedded,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct XmpConfig {
+    pub target: XmpTarget,
+}
+
+impl XmpConfig {
+    pub fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
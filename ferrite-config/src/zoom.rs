@@ -36,11 +36,12 @@ impl Default for ZoomSteps {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum FitMode {
     /// Display image at actual size (100% zoom)
     OneToOne,
     /// Fit image to window, scaling by the longer dimension
+    #[default]
     FitLonger,
     /// Fit image to window, scaling by the shorter dimension
     FitShorter,
@@ -48,13 +49,8 @@ pub enum FitMode {
     Custom,
 }
 
-impl Default for FitMode {
-    fn default() -> Self {
-        FitMode::FitLonger
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ZoomConfig {
     pub min_zoom:              f64,
     pub max_zoom:              f64,
@@ -63,11 +59,20 @@ pub struct ZoomConfig {
     pub use_predefined_steps:  bool,
     pub zoom_steps:            ZoomSteps,
     pub focal_point_enabled:   bool,
+    /// Ease zooming in and out instead of jumping; turn off to reduce
+    /// motion
     pub transition_enabled:    bool,
+    /// Seconds a zoom transition takes
     pub transition_duration:   f64,
     pub fit_to_window:         bool,
     pub maintain_aspect_ratio: bool,
     pub default_fit_mode:      FitMode,
+    /// Snap zoom to whole multiples of the physical pixel grid and sample
+    /// without filtering, so pixel art and screenshots stay sharp
+    pub pixel_perfect:         bool,
+    /// Draw zoomed-out images from pre-halved copies instead of sampling
+    /// the full resolution texture, which aliases
+    pub mipmaps:               bool,
 }
 
 impl Default for ZoomConfig {
@@ -85,6 +90,8 @@ impl Default for ZoomConfig {
             fit_to_window:         FIT_TO_WINDOW,
             maintain_aspect_ratio: MAINTAIN_ASPECT_RATIO,
             default_fit_mode:      FitMode::default(),
+            pixel_perfect:         PIXEL_PERFECT,
+            mipmaps:               MIPMAPS,
         }
     }
 }
@@ -159,4 +166,15 @@ mod tests {
         invalid.min_zoom = -1.0;
         assert!(invalid.validate().is_err());
     }
+
+    #[test]
+    fn test_generated_config_without_new_options_loads() {
+        let generated: toml::Table =
+            toml::from_str(include_str!("../tests/data/generated-0.1.toml"))
+                .unwrap();
+        let zoom: ZoomConfig = generated["zoom"].clone().try_into().unwrap();
+        assert_eq!(zoom.zoom_step, 0.1);
+        assert_eq!(zoom.pixel_perfect, PIXEL_PERFECT);
+        assert_eq!(zoom.mipmaps, MIPMAPS);
+    }
 }
@@ -0,0 +1,112 @@
+//! Brightness, contrast, gamma and saturation applied to the displayed
+//! pixels, leaving the decoded image and the file as they are.
+
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+
+/// Tone and color changes, all neutral by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustments {
+    /// Added to every channel, from -1 to 1
+    pub brightness: f32,
+    /// From -1 to 1; the spread around middle gray is scaled by 4 to the
+    /// power of this
+    pub contrast:   f32,
+    /// Above 1 brightens the mid tones, below 1 darkens them
+    pub gamma:      f32,
+    /// 0 is grayscale, 1 unchanged, 2 twice as colorful
+    pub saturation: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast:   0.0,
+            gamma:      1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The new value of each 8-bit value for brightness, contrast and gamma
+    fn table(&self) -> [u8; 256] {
+        let factor = 4f32.powf(self.contrast);
+        let mut table = [0; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            let v = value as f32 / 255.0 + self.brightness;
+            let v = ((v - 0.5) * factor + 0.5).clamp(0.0, 1.0);
+            let v = v.powf(1.0 / self.gamma);
+            *entry = (v * 255.0).round() as u8;
+        }
+        table
+    }
+
+    /// Adjusts display pixels in place. Alpha is kept.
+    pub fn apply(&self, pixels: &mut RgbaImage) {
+        if self.is_neutral() {
+            return;
+        }
+        let table = self.table();
+        let saturation = self.saturation;
+        pixels.par_chunks_mut(4).for_each(|pixel| {
+            let [r, g, b] = [0, 1, 2].map(|i| table[pixel[i] as usize] as f32);
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            for (channel, value) in pixel.iter_mut().zip([r, g, b]) {
+                let saturated = luma + (value - luma) * saturation;
+                *channel = saturated.round().clamp(0.0, 255.0) as u8;
+            }
+        });
+    }
+
+    /// An 8-bit copy of `image` with the adjustments applied, for saving
+    pub fn applied_to(&self, image: &DynamicImage) -> DynamicImage {
+        let mut pixels = image.to_rgba8();
+        self.apply(&mut pixels);
+        DynamicImage::ImageRgba8(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_neutral_adjustments_keep_pixels() {
+        let mut pixels = RgbaImage::from_pixel(3, 2, Rgba([12, 130, 250, 40]));
+        let before = pixels.clone();
+        Adjustments::default().apply(&mut pixels);
+        assert_eq!(pixels, before);
+        assert_eq!(Adjustments::default().table()[77], 77);
+    }
+
+    #[test]
+    fn test_brightness_and_saturation() {
+        let mut pixels = RgbaImage::from_pixel(1, 1, Rgba([200, 100, 0, 128]));
+        Adjustments {
+            brightness: 0.2,
+            saturation: 0.0,
+            ..Adjustments::default()
+        }
+        .apply(&mut pixels);
+        // 251, 151 and 51, mixed to their luminance
+        let [r, g, b, a] = pixels.get_pixel(0, 0).0;
+        assert_eq!((r, g, b, a), (165, 165, 165, 128));
+
+        // Halves the distance to middle gray
+        let mut pixels =
+            RgbaImage::from_pixel(1, 1, Rgba([100, 100, 100, 255]));
+        Adjustments {
+            contrast: -0.5,
+            ..Adjustments::default()
+        }
+        .apply(&mut pixels);
+        assert_eq!(pixels.get_pixel(0, 0).0, [114, 114, 114, 255]);
+    }
+}
@@ -0,0 +1,325 @@
+//! Animated GIF, WebP and PNG files: decoding all frames and timing their
+//! playback.
+//!
+//! Frames are decoded in the viewer process, so animations are not played
+//! when decoding is sandboxed; the first frame is shown as a still image.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::{AnimationDecoder, Frame, ImageResult, RgbaImage};
+use std::{io::Cursor, path::Path, time::Duration};
+use thiserror::Error;
+
+use crate::image as images;
+
+/// Frames shown for less than this are shown for [`DEFAULT_DELAY`], as in
+/// browsers; many GIFs rely on it
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Playback speed multipliers to step through
+pub const SPEEDS: [f32; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+
+#[derive(Error, Debug)]
+pub enum AnimationError {
+    #[error("Failed to read animation: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to decode animation: {0}")]
+    Decode(#[from] image::ImageError),
+
+    #[error("Animation frames need more than {0} MB")]
+    TooLarge(u64),
+}
+
+/// How often an animation plays in total
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopCount {
+    Infinite,
+    Plays(u32),
+}
+
+pub struct Animation {
+    pub frames: Vec<RgbaImage>,
+    pub delays: Vec<Duration>,
+    pub loops:  LoopCount,
+}
+
+/// Decodes every frame of an animated file. Returns `None` for files with
+/// a single frame and formats that cannot be animated.
+pub fn decode_file(
+    path: &Path,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<Option<Animation>, AnimationError> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !matches!(extension.as_str(), "gif" | "webp" | "png" | "apng") {
+        return Ok(None);
+    }
+    let bytes = images::read_bytes(path, decoding, io)?;
+    decode(&bytes, decoding.max_memory_mb)
+}
+
+fn decode(
+    bytes: &[u8],
+    max_memory_mb: u64,
+) -> Result<Option<Animation>, AnimationError> {
+    let frames: Box<dyn Iterator<Item = ImageResult<Frame>>> =
+        match image::guess_format(bytes) {
+            #[cfg(feature = "gif")]
+            Ok(image::ImageFormat::Gif) => Box::new(
+                image::codecs::gif::GifDecoder::new(Cursor::new(bytes))?
+                    .into_frames(),
+            ),
+            #[cfg(feature = "webp")]
+            Ok(image::ImageFormat::WebP) => {
+                let decoder =
+                    image::codecs::webp::WebPDecoder::new(Cursor::new(bytes))?;
+                if !decoder.has_animation() {
+                    return Ok(None);
+                }
+                Box::new(decoder.into_frames())
+            },
+            Ok(image::ImageFormat::Png) => {
+                let decoder =
+                    image::codecs::png::PngDecoder::new(Cursor::new(bytes))?;
+                if !decoder.is_apng() {
+                    return Ok(None);
+                }
+                Box::new(decoder.apng().into_frames())
+            },
+            _ => return Ok(None),
+        };
+
+    let budget = max_memory_mb * 1024 * 1024;
+    let mut used = 0;
+    let (mut images, mut delays) = (Vec::new(), Vec::new());
+    for frame in frames {
+        let frame = frame?;
+        let delay = Duration::from(frame.delay());
+        let image = frame.into_buffer();
+        used += image.as_raw().len() as u64;
+        if used > budget {
+            return Err(AnimationError::TooLarge(max_memory_mb));
+        }
+        images.push(image);
+        delays.push(if delay < MIN_DELAY { DEFAULT_DELAY } else { delay });
+    }
+    if images.len() < 2 {
+        return Ok(None);
+    }
+    Ok(Some(Animation {
+        frames: images,
+        delays,
+        loops: loop_count(bytes),
+    }))
+}
+
+/// Reads the loop count from the NETSCAPE2.0 extension of a GIF, the ANIM
+/// chunk of a WebP or the acTL chunk of an APNG. In all three 0 means
+/// forever. GIFs count repetitions after the first play.
+pub fn loop_count(bytes: &[u8]) -> LoopCount {
+    let after = |marker: &[u8]| {
+        bytes
+            .windows(marker.len())
+            .position(|w| w == marker)
+            .map(|i| &bytes[i + marker.len()..])
+    };
+    let plays = |count: u32| match count {
+        0 => LoopCount::Infinite,
+        n => LoopCount::Plays(n),
+    };
+
+    if bytes.starts_with(b"GIF") {
+        return match after(b"NETSCAPE2.0") {
+            Some([3, 1, lo, hi, ..]) => match u16::from_le_bytes([*lo, *hi]) {
+                0 => LoopCount::Infinite,
+                n => LoopCount::Plays(n as u32 + 1),
+            },
+            _ => LoopCount::Plays(1),
+        };
+    }
+    if bytes.starts_with(b"RIFF") {
+        // Chunk size, then the background color before the count
+        if let Some([_, _, _, _, _, _, _, _, lo, hi, ..]) = after(b"ANIM") {
+            return plays(u16::from_le_bytes([*lo, *hi]) as u32);
+        }
+    } else if let Some([_, _, _, _, a, b, c, d, ..]) = after(b"acTL") {
+        return plays(u32::from_be_bytes([*a, *b, *c, *d]));
+    }
+    LoopCount::Infinite
+}
+
+/// Which frame of an animation is due, advanced by the time between UI
+/// frames
+#[derive(Debug, Clone)]
+pub struct Playback {
+    delays:  Vec<Duration>,
+    loops:   LoopCount,
+    frame:   usize,
+    /// Time spent on the current frame, in animation time
+    elapsed: Duration,
+    plays:   u32,
+    paused:  bool,
+    speed:   f32,
+}
+
+impl Playback {
+    pub fn new(delays: Vec<Duration>, loops: LoopCount) -> Self {
+        Self {
+            delays,
+            loops,
+            frame: 0,
+            elapsed: Duration::ZERO,
+            plays: 0,
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.delays.len()
+    }
+
+    pub fn loops(&self) -> LoopCount {
+        self.loops
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether the animation stopped on its last frame after playing as
+    /// often as it asks to
+    pub fn is_finished(&self) -> bool {
+        matches!(self.loops, LoopCount::Plays(n) if self.plays >= n)
+    }
+
+    /// Pauses or resumes; a finished animation starts over.
+    pub fn toggle_pause(&mut self) {
+        if self.is_finished() {
+            self.plays = 0;
+            self.frame = 0;
+            self.elapsed = Duration::ZERO;
+            self.paused = false;
+        } else {
+            self.paused = !self.paused;
+        }
+    }
+
+    /// Pauses and moves one frame forward or back, wrapping around.
+    pub fn step(&mut self, forward: bool) {
+        let count = self.frame_count();
+        self.paused = true;
+        self.elapsed = Duration::ZERO;
+        self.frame = if forward {
+            (self.frame + 1) % count
+        } else {
+            (self.frame + count - 1) % count
+        };
+    }
+
+    /// Moves to the next faster or slower entry of [`SPEEDS`].
+    pub fn change_speed(&mut self, faster: bool) {
+        let index = SPEEDS
+            .iter()
+            .position(|&s| s >= self.speed)
+            .unwrap_or(SPEEDS.len() - 1);
+        let index = if faster {
+            (index + 1).min(SPEEDS.len() - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        self.speed = SPEEDS[index];
+    }
+
+    /// Advances by `dt` of wall time. Returns whether the frame changed.
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        if self.paused || self.is_finished() {
+            return false;
+        }
+        let start = self.frame;
+        self.elapsed += dt.mul_f32(self.speed);
+        while self.elapsed >= self.delays[self.frame] {
+            self.elapsed -= self.delays[self.frame];
+            if self.frame + 1 < self.frame_count() {
+                self.frame += 1;
+                continue;
+            }
+            self.plays += 1;
+            if self.is_finished() {
+                self.elapsed = Duration::ZERO;
+                break;
+            }
+            self.frame = 0;
+        }
+        self.frame != start
+    }
+
+    /// Wall time until the next frame is due, if playing
+    pub fn until_next(&self) -> Option<Duration> {
+        if self.paused || self.is_finished() {
+            return None;
+        }
+        let remaining = self.delays[self.frame].saturating_sub(self.elapsed);
+        Some(remaining.div_f32(self.speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_respects_loop_count() {
+        let delays = vec![Duration::from_millis(100); 3];
+        let mut playback = Playback::new(delays, LoopCount::Plays(2));
+        assert!(!playback.advance(Duration::from_millis(50)));
+        assert!(playback.advance(Duration::from_millis(60)));
+        assert_eq!(playback.frame(), 1);
+
+        // Second play ends on the last frame
+        playback.advance(Duration::from_millis(440));
+        assert_eq!(playback.frame(), 2);
+        assert!(!playback.is_finished());
+        playback.advance(Duration::from_millis(100));
+        assert!(playback.is_finished());
+        assert_eq!(playback.frame(), 2);
+        assert_eq!(playback.until_next(), None);
+
+        playback.toggle_pause();
+        assert_eq!(playback.frame(), 0);
+        playback.change_speed(true);
+        assert_eq!(playback.speed(), 1.5);
+        playback.step(false);
+        assert!(playback.is_paused());
+        assert_eq!(playback.frame(), 2);
+        assert!(!playback.advance(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_loop_count_markers() {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(b"!\xffNETSCAPE2.0\x03\x01\x02\x00\x00");
+        assert_eq!(loop_count(&gif), LoopCount::Plays(3));
+        assert_eq!(loop_count(b"GIF89a"), LoopCount::Plays(1));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.extend_from_slice(b"ANIM\x06\0\0\0\xff\xff\xff\xff\x00\x00");
+        assert_eq!(loop_count(&webp), LoopCount::Infinite);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend_from_slice(b"\0\0\0\x08acTL\0\0\0\x04\0\0\0\x05");
+        assert_eq!(loop_count(&png), LoopCount::Plays(5));
+    }
+}
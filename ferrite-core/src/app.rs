@@ -1,12 +1,85 @@
-use eframe::egui::{self, Context, Key};
-use std::path::PathBuf;
+use eframe::egui::{self, Context};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::{
-    image::{ImageLoadError, ImageManager},
-    navigation::NavigationManager,
-    ui::{menu::MenuBar, render::ImageRenderer, zoom::ZoomHandler},
+    archive,
+    clipboard,
+    crash,
+    desktop::{self, Desktop, DesktopEvent},
+    editor,
+    favorites::Favorites,
+    gallery,
+    hdr,
+    image::{self, derived_path, display::Appearance, ImageManager},
+    instance::Inbox,
+    navigation::{self, NavigationManager},
+    remote,
+    selection::Region,
+    session::{PanelState, SessionState, WindowGeometry, ZoomState},
+    trash,
+    ui::{
+        adjust::AdjustPanel,
+        animation::AnimationPlayer,
+        batch::BatchPanel,
+        checksum::ChecksumPanel,
+        codes::CodeScanner,
+        compare::CompareView,
+        crash_notice::CrashNotice,
+        crop::CropTool,
+        download::DownloadIndicator,
+        exif::ExifPanel,
+        export_view::ViewExporter,
+        filmstrip::Filmstrip,
+        filter_bar::FilterBar,
+        folder_picker::FolderPicker,
+        gallery::{GalleryAction, GalleryView},
+        grid::GridOverlay,
+        guides::GuideOverlay,
+        hdr::HdrPanel,
+        histogram::HistogramPanel,
+        input,
+        inspector::PixelInspector,
+        large_image::LargeImagePrompt,
+        lens::LensPanel,
+        loupe::Loupe,
+        lut::LutPanel,
+        map::MapPanel,
+        measure::MeasureTool,
+        menu::{MenuAction, MenuBar},
+        open_dialog::OpenDialog,
+        overlay::ImageGeometry,
+        palette::PalettePanel,
+        panorama::PanoramaPanel,
+        playlist::{PlaylistAction, PlaylistPanel},
+        recent::RecentMenu,
+        rename_file::RenameDialog,
+        render::{ImageRenderer, ViewInput},
+        save_as::SaveAsDialog,
+        selection::{SelectionAction, SelectionTool},
+        shortcuts::ShortcutsPanel,
+        slideshow::Slideshow,
+        split::SplitPane,
+        stack::StackPanel,
+        status_bar::{Marks, StatusBar},
+        straighten::StraightenPanel,
+        tags::TagPanel,
+        tone_map::ToneMapPanel,
+        trash::TrashPrompt,
+        upscale::UpscalePanel,
+        xmp_editor::MetadataEditor,
+        zoom::{FitMode, ZoomHandler, ZOOM_PRESETS},
+    },
+    view_filter::ViewFilter,
+    warm_start::RecentIndex,
+    watch::FileWatcher,
 };
-use ferrite_config::FerriteConfig;
+use ferrite_config::{Action, FerriteConfig, WheelAction};
+
+/// Side of the spinner shown while an image decodes
+const LOADING_SPINNER_SIZE: f32 = 32.0;
 
 pub struct FeriteApp {
     config:        FerriteConfig,
@@ -14,6 +87,82 @@ pub struct FeriteApp {
     navigation:    NavigationManager,
     zoom_handler:  ZoomHandler,
     menu_bar:      MenuBar,
+    code_scanner:  CodeScanner,
+    upscale_panel: UpscalePanel,
+    map_panel:     MapPanel,
+    xmp_editor:    MetadataEditor,
+    checksums:     ChecksumPanel,
+    lens_panel:    LensPanel,
+    palette:       PalettePanel,
+    measure:       MeasureTool,
+    grid:          GridOverlay,
+    guides:        GuideOverlay,
+    selection:     SelectionTool,
+    loupe:         Loupe,
+    view_exporter: ViewExporter,
+    compare:       CompareView,
+    /// The open dialog picks the image to compare with
+    compare_pick:  bool,
+    slideshow:     Slideshow,
+    /// Area the image was drawn in during the last frame
+    viewport:      egui::Rect,
+    /// Where the image itself was drawn in the last frame
+    image_rect:    Option<egui::Rect>,
+    batch:         BatchPanel,
+    gallery:       GalleryView,
+    stack:         StackPanel,
+    hdr:           HdrPanel,
+    panorama:      PanoramaPanel,
+    lut:           LutPanel,
+    animation:     AnimationPlayer,
+    straighten:    StraightenPanel,
+    open_dialog:   OpenDialog,
+    exif_panel:    ExifPanel,
+    filmstrip:     Filmstrip,
+    playlist:      PlaylistPanel,
+    shortcuts:     ShortcutsPanel,
+    tone_map:      ToneMapPanel,
+    trash_prompt:  TrashPrompt,
+    folder_picker: FolderPicker,
+    rename_dialog: RenameDialog,
+    save_dialog:   SaveAsDialog,
+    status_bar:    StatusBar,
+    inspector:     PixelInspector,
+    histogram:     HistogramPanel,
+    crop:          CropTool,
+    adjust:        AdjustPanel,
+    recent_menu:   RecentMenu,
+    favorites:     Favorites,
+    /// Only starred images are listed from the folder
+    starred_only:  bool,
+    tags:          TagPanel,
+    filter_bar:    FilterBar,
+    /// Second image beside the main one, with its own folder
+    split:         SplitPane,
+    download:      DownloadIndicator,
+    crash_notice:  CrashNotice,
+    /// Recently viewed files, when kept between runs
+    recent:        Option<RecentIndex>,
+    /// Wheel travel bound to navigation not yet turned into image changes
+    wheel_travel:  f32,
+    /// Borderless fullscreen, kept until toggled off for the session
+    fullscreen:    bool,
+    /// Last reported position and size of the window outside fullscreen
+    window:        Option<WindowGeometry>,
+    /// Started with the first frame, which brings the context to wake
+    watcher:       Option<FileWatcher>,
+    /// File decoded again after it changed on disk, shown in the same view
+    reloading:     Option<PathBuf>,
+    /// Jump to the newest image whenever one is added to the folder
+    monitor:       bool,
+    /// Images sent by later starts of the viewer, when this is the window
+    /// they go to
+    inbox:         Option<Inbox>,
+    /// The desktop outside the window, when there is a window
+    desktop:       Option<Desktop>,
+    /// Opened in an external editor, so reloaded on save even when reloading
+    /// is turned off
+    editing:       Option<PathBuf>,
 }
 
 impl FeriteApp {
@@ -21,14 +170,88 @@ impl FeriteApp {
         cc: &eframe::CreationContext<'_>,
         initial_image: Option<PathBuf>,
         config: FerriteConfig,
+    ) -> Self {
+        if let Some(gl) = cc.gl.as_deref() {
+            crash::set_gpu_info(gpu_description(gl));
+        }
+
+        let mut app = Self::from_config(initial_image, config);
+        app.crash_notice = CrashNotice::new(crash::take_pending_report());
+        app.desktop = Some(Desktop::attach(cc));
+        app
+    }
+
+    /// Builds the application without a window or graphics context. `new`
+    /// wraps this for eframe; headless tests drive it through `ui`.
+    pub fn from_config(
+        initial_image: Option<PathBuf>,
+        config: FerriteConfig,
     ) -> Self {
         // Initialize our core components with their default states
-        let image_manager = ImageManager::new();
-        let navigation = NavigationManager::new();
-        let zoom_handler = ZoomHandler::new(
+        let image_manager = ImageManager::new(
+            config.decoding.clone(),
+            config.io.clone(),
+            config.cache.memory_mb,
+        );
+        let navigation = NavigationManager::new(config.navigation.clone());
+        let mut zoom_handler = ZoomHandler::new(
             config.zoom.default_zoom, // Initial zoom level from config
         );
+        if config.zoom.transition_enabled {
+            zoom_handler.set_transition(config.zoom.transition_duration);
+        }
         let menu_bar = MenuBar::new(config.window.hide_menu);
+        let code_scanner = CodeScanner::new();
+        let upscale_panel = UpscalePanel::new(&config.upscale);
+        let map_panel = MapPanel::new(&config.map);
+        let xmp_editor = MetadataEditor::new();
+        let checksums = ChecksumPanel::new();
+        let lens_panel = LensPanel::new();
+        let palette = PalettePanel::new();
+        let measure = MeasureTool::new();
+        let grid = GridOverlay::new();
+        let guides = GuideOverlay::new();
+        let selection = SelectionTool::new();
+        let loupe = Loupe::new(&config.loupe);
+        let view_exporter = ViewExporter::new();
+        let compare = CompareView::new();
+        let slideshow = Slideshow::new();
+        let batch = BatchPanel::new(&config);
+        let gallery = GalleryView::new(&config);
+        let stack = StackPanel::new();
+        let hdr = HdrPanel::new();
+        let panorama = PanoramaPanel::new();
+        let lut = LutPanel::new();
+        let animation = AnimationPlayer::new();
+        let straighten = StraightenPanel::new();
+        let open_dialog = OpenDialog::new();
+        let exif_panel = ExifPanel::new();
+        let filmstrip = Filmstrip::new(&config);
+        let playlist = PlaylistPanel::new();
+        let shortcuts = ShortcutsPanel::new();
+        let tone_map = ToneMapPanel::new();
+        let trash_prompt = TrashPrompt::new();
+        let folder_picker = FolderPicker::new();
+        let rename_dialog = RenameDialog::new();
+        let save_dialog = SaveAsDialog::new(&config);
+        let status_bar = StatusBar::new();
+        let inspector = PixelInspector::new();
+        let histogram = HistogramPanel::new();
+        let crop = CropTool::new(config.batch.jpeg_quality);
+        let adjust = AdjustPanel::new(config.batch.jpeg_quality);
+        let recent_menu = RecentMenu::new(&config);
+        let favorites = Favorites::load(config.paths.resolve_state_dir());
+        let tags = TagPanel::new();
+        let filter_bar = FilterBar::new();
+        let split = SplitPane::new(&config);
+        let download = DownloadIndicator::new();
+        let crash_notice = CrashNotice::new(None);
+        let recent = config.cache.warm_start.then(|| {
+            RecentIndex::load(
+                config.paths.resolve_state_dir(),
+                config.cache.recent_files,
+            )
+        });
 
         let mut app = Self {
             config,
@@ -36,9 +259,79 @@ impl FeriteApp {
             navigation,
             zoom_handler,
             menu_bar,
+            code_scanner,
+            upscale_panel,
+            map_panel,
+            xmp_editor,
+            checksums,
+            lens_panel,
+            palette,
+            measure,
+            grid,
+            guides,
+            selection,
+            loupe,
+            view_exporter,
+            compare,
+            compare_pick: false,
+            slideshow,
+            viewport: egui::Rect::NOTHING,
+            image_rect: None,
+            batch,
+            gallery,
+            stack,
+            hdr,
+            panorama,
+            lut,
+            animation,
+            straighten,
+            open_dialog,
+            exif_panel,
+            filmstrip,
+            playlist,
+            shortcuts,
+            tone_map,
+            trash_prompt,
+            folder_picker,
+            rename_dialog,
+            save_dialog,
+            status_bar,
+            inspector,
+            histogram,
+            crop,
+            adjust,
+            recent_menu,
+            favorites,
+            starred_only: false,
+            tags,
+            filter_bar,
+            split,
+            download,
+            crash_notice,
+            recent,
+            wheel_travel: 0.0,
+            fullscreen: false,
+            window: None,
+            watcher: None,
+            reloading: None,
+            monitor: false,
+            inbox: None,
+            desktop: None,
+            editing: None,
         };
 
-        if let Some(path) = initial_image {
+        if let Some(url) = initial_image
+            .as_ref()
+            .and_then(|path| path.to_str())
+            .filter(|path| remote::is_url(path))
+        {
+            app.download.start(url, &app.config);
+        } else if let Some(path) = initial_image
+            .as_ref()
+            .filter(|path| path.is_dir() || archive::is_archive(path))
+        {
+            app.open_paths(vec![path.clone()]);
+        } else if let Some(path) = initial_image {
             // First try to load the directory containing the image
             if let Some(()) = app.navigation.load_current_directory(&path) {
                 tracing::info!("Successfully loaded directory for navigation");
@@ -50,72 +343,1619 @@ impl FeriteApp {
             }
 
             // Then attempt to load the initial image
-            if !app.image_manager.load_image(path).is_ok() {
-                tracing::warn!("Failed to load initial image");
-            }
+            app.recent_menu.record(&path);
+            app.image_manager.request_image(path);
+            let recent = app
+                .recent
+                .as_ref()
+                .map(RecentIndex::entries)
+                .unwrap_or_default();
+            app.gallery
+                .warm(&app.config, app.navigation.images(), recent);
         }
 
         app
     }
 
-    fn handle_files_dropped(&mut self, ctx: &Context, files: Vec<PathBuf>) {
-        if let Some(path) = files.first() {
-            if let Some(extension) = path.extension() {
-                if matches!(
-                    extension.to_str().map(|s| s.to_lowercase()),
-                    Some(ext) if ["jpg", "jpeg", "png", "gif", "bmp"].contains(&ext.as_str())
-                ) {
-                    let _ = self.image_manager.load_image(path.clone());
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Sorting keys come first, so they can take keys of the keymap
+        let targets: Vec<_> = self
+            .config
+            .sorting
+            .targets
+            .iter()
+            .map(|target| target.key)
+            .collect();
+        for index in input::take_chords(ctx, &targets) {
+            let folder = self.config.sorting.targets[index].folder.clone();
+            self.move_current_to(&folder);
+        }
+
+        let actions = input::pressed_actions(ctx, &self.config.controls);
+
+        // Handle navigation keyboard events
+        if self.split.is_focused() {
+            self.split.navigate(&actions);
+        } else {
+            self.navigation
+                .handle_keyboard_input(&actions, &mut self.image_manager);
+        }
+
+        for action in actions {
+            match action {
+                // Close the window through the application context
+                Action::Quit => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close)
+                },
+                Action::ToggleMenu => self.menu_bar.toggle(),
+                // Scan the current image for QR codes and barcodes
+                Action::ScanCodes => self.scan_codes(),
+                Action::Upscale => self.upscale_panel.toggle(),
+                // Show the capture location map
+                Action::Map => self.map_panel.toggle(),
+                Action::ExifInfo => self.exif_panel.toggle(),
+                // Edit XMP metadata of the current image
+                Action::EditMetadata => self.xmp_editor.toggle(),
+                Action::Checksums => self.checksums.toggle(),
+                // Correct distortion, chromatic aberration and vignetting
+                Action::LensCorrection => self.lens_panel.toggle(),
+                // Extract the dominant colors of the current image
+                Action::Palette => self.palette.toggle(),
+                Action::Histogram => self.histogram.toggle(),
+                // Measure distances and angles on the image
+                Action::Measure => self.measure.toggle(),
+                Action::CycleGrid => self.grid.cycle(),
+                Action::PixelPerfect => {
+                    self.config.zoom.pixel_perfect =
+                        !self.config.zoom.pixel_perfect
+                },
+                // Show the rulers for dragging out guides
+                Action::Guides => self.guides.toggle(),
+                // Magnify the area under the cursor
+                Action::Loupe => self.loupe.toggle(),
+                Action::PixelInspector => self.inspector.toggle(),
+                Action::Crop => self.crop.toggle(),
+                Action::Adjust => self.adjust.toggle(),
+                Action::LoupeLarger if self.loupe.is_active() => {
+                    self.loupe.adjust(1.0)
+                },
+                Action::LoupeSmaller if self.loupe.is_active() => {
+                    self.loupe.adjust(-1.0)
+                },
+                // Show the folder as a strip along the bottom
+                Action::Filmstrip => self.filmstrip.toggle(),
+                // Browse the folder as thumbnails
+                Action::Gallery => self.gallery.toggle(),
+                // Use the whole monitor for the image
+                Action::ToggleFullscreen => self.toggle_fullscreen(ctx),
+                Action::ToggleSlideshow => {
+                    self.slideshow.toggle(ctx, &self.config.slideshow)
+                },
+                // The animation player takes its own keys while one is shown
+                Action::PauseSlideshow
+                    if self.slideshow.is_running()
+                        && !self.animation.is_playing() =>
+                {
+                    self.slideshow.toggle_pause(ctx)
+                },
+                Action::CloseView => {
+                    self.gallery.close();
+                    self.slideshow.stop();
+                    self.compare.close();
+                },
+                Action::OpenFile => self.show_open_dialog(),
+                Action::MoveToTrash => self.move_current_to_trash(),
+                Action::Rename => self.rename_current(),
+                Action::MoveToFolder => self.pick_folder(),
+                Action::ToggleStar => self.toggle_star(),
+                Action::Tags => self.tags.toggle(),
+                Action::FilterBar => {
+                    self.filter_bar.toggle();
+                    self.apply_filter();
+                },
+                Action::RateOne
+                | Action::RateTwo
+                | Action::RateThree
+                | Action::RateFour
+                | Action::RateFive => {
+                    let stars = action.rating().unwrap_or_default();
+                    if self.tags.rate(stars, &self.config.xmp) {
+                        self.refilter();
+                    }
+                },
+                Action::CopyImage => self.copy_image(),
+                Action::CopyPath => self.copy_path(ctx),
+                Action::PasteImage => self.paste_image(),
+                Action::SaveAs => self.show_save_dialog(),
+                Action::OpenInEditor => self.open_in_editor(0),
+                // Compare the current image with the one on the clipboard
+                Action::CompareClipboard => self.compare.open_clipboard(),
+                Action::SplitView => self.toggle_split(),
+                Action::Shortcuts => self.shortcuts.toggle(),
+                _ => {},
+            }
+        }
+    }
+
+    /// Writes the settings changed in the app, such as the keymap, to the
+    /// config file.
+    fn save_config(&mut self) {
+        match self.write_config() {
+            Ok(()) => self
+                .shortcuts
+                .set_status("Saved to the config file"),
+            Err(e) => {
+                tracing::warn!("Failed to save the configuration: {}", e);
+                self.shortcuts
+                    .set_status(format!("Failed to save: {}", e));
+            },
+        }
+    }
+
+    fn write_config(&self) -> ferrite_config::Result<()> {
+        FerriteConfig::resolve_config_path()
+            .and_then(|path| self.config.save_to_path(&path))
+    }
+
+    /// Opens the config file in a text editor, writing it out first when
+    /// there is none yet. Changes apply on the next start.
+    fn open_preferences(&mut self) {
+        let result = FerriteConfig::resolve_config_path()
+            .and_then(|path| {
+                if !path.exists() {
+                    self.config.save_to_path(&path)?;
                 }
+                Ok(path)
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|path| {
+                desktop::edit_text(&path).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to open the preferences: {}", e);
+        }
+    }
+
+    /// Moves the image on screen to the front of the recent files index.
+    fn remember_current(&mut self) {
+        let (Some(recent), Some(path)) =
+            (&mut self.recent, self.image_manager.current_path())
+        else {
+            return;
+        };
+        if let Err(e) = recent.record(path) {
+            tracing::warn!("Failed to save recent files: {}", e);
+            // Keep running without rewriting the index every frame
+            self.recent = None;
+        }
+    }
+
+    /// Decodes the file on screen again once it was rewritten on disk, and
+    /// lists its folder again once files were added to it or removed.
+    fn reload_changed(&mut self, ctx: &Context) {
+        let enabled = self.config.io.reload_on_change;
+        let path = self
+            .image_manager
+            .current_path()
+            .filter(|&path| enabled || self.editing.as_ref() == Some(path));
+        let directory = self
+            .navigation
+            .directory()
+            .filter(|_| enabled || self.monitor);
+        let interval = Duration::from_millis(self.config.io.watch_interval_ms);
+        let watcher = self.watcher.get_or_insert_with(|| {
+            let ctx = ctx.clone();
+            FileWatcher::new(interval, move || ctx.request_repaint())
+        });
+        watcher.watch(path.map(PathBuf::as_path));
+        watcher.watch_directory(directory);
+        let changes = watcher.changed();
+
+        if changes.directory.is_some() {
+            self.navigation.refresh();
+            let newest = self
+                .navigation
+                .newest()
+                .map(Path::to_path_buf)
+                .filter(|_| self.monitor);
+            if let Some(newest) = newest.filter(|newest| {
+                Some(newest) != self.image_manager.current_path()
+            }) {
+                tracing::info!(
+                    "Showing {}, new in the folder",
+                    newest.display()
+                );
+                self.navigation.select(&newest);
+                self.image_manager.request_image(newest);
+                return;
             }
         }
+        let Some(path) = changes.file else {
+            return;
+        };
+        tracing::info!("{} changed on disk, reloading", path.display());
+        self.image_manager.forget(&path);
+        self.image_manager.request_image(path.clone());
+        self.reloading = Some(path);
+    }
+
+    /// Takes up the folder list sorted and filtered in the background, if
+    /// any, moving on like [`Self::apply_filter`] if the image on screen
+    /// did not pass.
+    fn poll_navigation(&mut self, ctx: &Context) {
+        if self.navigation.poll() {
+            if !self.navigation.images().is_empty() {
+                self.show_listed();
+            }
+            self.image_manager
+                .prefetch(self.navigation.neighbours());
+        }
+        if self.navigation.is_arranging() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    /// Shows the image the loader thread finished, if any.
+    fn poll_loading(&mut self, ctx: &Context) {
+        let result = self.image_manager.poll();
+        // A reloaded file keeps the view, unless another file superseded it
+        let reloaded = result.is_some()
+            && self.reloading.take().as_ref()
+                == self.image_manager.current_path();
+        match result {
+            Some(Ok(())) if reloaded => {},
+            Some(Ok(())) => {
+                self.zoom_handler.reset_view_position();
+                self.zoom_handler.reset_transform();
+                self.image_manager
+                    .prefetch(self.navigation.neighbours());
+            },
+            Some(Err(e)) => tracing::warn!("Failed to open image: {}", e),
+            None => {},
+        }
+        if self.image_manager.loading().is_some() {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Opens a single image with its folder, or an archive at its first
+    /// image; several files, or a folder, become a playlist.
+    fn open_paths(&mut self, files: Vec<PathBuf>) {
+        let images =
+            navigation::collect_images(&files, &self.config.navigation);
+        if !images.is_empty() {
+            // The first ends up on top
+            for path in files.iter().rev() {
+                self.recent_menu.record(path);
+            }
+        }
+        match images.first() {
+            None => {},
+            Some(image) if files.len() == 1 && !files[0].is_dir() => {
+                self.open_file(image.clone());
+            },
+            Some(first) => {
+                let first = first.clone();
+                self.navigation.load_playlist(images);
+                self.playlist.show();
+                self.gallery.close();
+                self.image_manager.request_image(first);
+            },
+        }
+    }
+
+    /// Opens `files` in the pane that has the keys.
+    fn open_in_focused(&mut self, files: Vec<PathBuf>) {
+        if self.split.is_focused() {
+            self.split
+                .open_paths(files, &self.config.navigation);
+        } else {
+            self.open_paths(files);
+        }
+    }
+
+    fn handle_playlist_action(&mut self, action: PlaylistAction) {
+        match action {
+            PlaylistAction::Open(path) => self.open_from_folder(path),
+            PlaylistAction::Move {
+                from,
+                to,
+            } => self.navigation.move_image(from, to),
+            PlaylistAction::Remove(index) => {
+                if let Some(next) = self.navigation.remove_image(index) {
+                    self.image_manager.request_image(next);
+                }
+            },
+        }
+    }
+
+    /// Moves the image on screen to the trash, asking first unless turned
+    /// off.
+    fn move_current_to_trash(&mut self) {
+        let Some(path) = self.image_manager.current_path().cloned() else {
+            return;
+        };
+        if self.config.navigation.confirm_delete {
+            self.trash_prompt.ask(path);
+        } else {
+            self.move_to_trash(path);
+        }
+    }
+
+    /// Moves `path` to the trash and shows the image that takes its place
+    /// in the folder.
+    fn move_to_trash(&mut self, path: PathBuf) {
+        if let Err(e) = trash::move_to_trash(&path) {
+            tracing::warn!("Failed to trash {}: {}", path.display(), e);
+            self.trash_prompt.fail(e.to_string());
+            return;
+        }
+        crash::record_action(format!("Trashed {}", path.display()));
+        self.image_manager.forget(&path);
+        let index = self
+            .navigation
+            .images()
+            .iter()
+            .position(|image| *image == path);
+        let next = index.and_then(|index| self.navigation.remove_image(index));
+        match next {
+            Some(next) => self.image_manager.request_image(next),
+            None if self.navigation.images().is_empty() => {
+                self.image_manager.close()
+            },
+            None => {},
+        }
+    }
+
+    fn rename_current(&mut self) {
+        if let Some(path) = self.image_manager.current_path() {
+            self.rename_dialog.open(path);
+        }
+    }
+
+    fn pick_folder(&mut self) {
+        if let Some(path) = self.image_manager.current_path() {
+            self.folder_picker.open(path, &self.config);
+        }
+    }
+
+    /// Moves the image on screen into `folder`, relative to its own, and
+    /// shows the image that takes its place.
+    fn move_current_to(&mut self, folder: &Path) {
+        let Some(path) = self.image_manager.current_path().cloned() else {
+            return;
+        };
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        let folder = parent.join(folder);
+        let result = gallery::move_to_folder(&[path], &folder);
+        for (path, e) in &result.failed {
+            tracing::warn!(
+                "Failed to move {} to {}: {}",
+                path.display(),
+                folder.display(),
+                e
+            );
+        }
+        if !result.moved.is_empty() {
+            crash::record_action(format!("Moved to {}", folder.display()));
+            self.folder_picker.remember(&folder);
+            self.apply_renames(&result.moved);
+        }
+    }
+
+    /// Puts the pixels of the image on screen on the system clipboard.
+    fn copy_image(&mut self) {
+        let Some(data) = self.image_manager.current_image() else {
+            return;
+        };
+        match clipboard::copy_image(&data.original) {
+            Ok(()) => crash::record_action("Copied the image"),
+            Err(e) => tracing::warn!("Failed to copy the image: {}", e),
+        }
+    }
+
+    fn copy_path(&self, ctx: &Context) {
+        if let Some(path) = self.image_manager.current_path() {
+            let text = path.display().to_string();
+            ctx.output_mut(|o| o.copied_text = text);
+        }
+    }
+
+    /// Shows an image given as its encoded bytes, such as one piped to
+    /// standard input. Like a pasted image, it has no file.
+    pub fn show_bytes(&mut self, bytes: &[u8]) {
+        match image::decode_memory(bytes, &self.config.decoding) {
+            Ok(image) => {
+                self.gallery.close();
+                self.image_manager.show_unsaved(image);
+                self.zoom_handler.reset_view_position();
+                self.zoom_handler.reset_transform();
+            },
+            Err(e) => tracing::warn!("Failed to show the image: {}", e),
+        }
+    }
+
+    /// Shows the image on the clipboard in place of the file on screen,
+    /// without saving it.
+    fn paste_image(&mut self) {
+        match clipboard::paste_image() {
+            Ok(image) => {
+                self.gallery.close();
+                self.image_manager.show_unsaved(image);
+                self.zoom_handler.reset_view_position();
+                self.zoom_handler.reset_transform();
+            },
+            Err(e) => tracing::warn!("No image on the clipboard: {}", e),
+        }
+    }
+
+    /// Opens the file on screen in the configured editor at `index`. The
+    /// image is reloaded whenever the editor saves it.
+    fn open_in_editor(&mut self, index: usize) {
+        let Some(editor) = self.config.editors.programs.get(index) else {
+            tracing::warn!("No external editor is configured");
+            return;
+        };
+        let Some(path) = self.image_manager.current_path().cloned() else {
+            return;
+        };
+        match editor::launch(editor, &path) {
+            Ok(()) => self.editing = Some(path),
+            Err(e) => {
+                tracing::warn!("Failed to start {}: {}", editor.name, e)
+            },
+        }
+    }
+
+    fn show_save_dialog(&mut self) {
+        if self.image_manager.current_image().is_none() {
+            return;
+        }
+        let dir = self
+            .navigation
+            .current()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        self.save_dialog.show(
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
+            &dir,
+        );
+    }
+
+    fn show_open_dialog(&mut self) {
+        self.compare_pick = false;
+        self.open_dialog.show(
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
+        );
+    }
+
+    /// Opens an image from any folder, which becomes the one navigated.
+    fn open_file(&mut self, path: PathBuf) {
+        if self
+            .navigation
+            .load_current_directory(&path)
+            .is_none()
+        {
+            tracing::warn!("Failed to list the folder of {}", path.display());
+        }
+        self.gallery.close();
+        self.image_manager.request_image(path);
+    }
+
+    /// Shows an image downloaded from a URL on its own; the other files of
+    /// the download cache are not its folder.
+    fn open_downloaded(&mut self, path: PathBuf) {
+        self.navigation.load_playlist(vec![path.clone()]);
+        self.gallery.close();
+        self.image_manager.request_image(path);
+    }
+
+    /// Opens an image from the current directory list, keeping navigation
+    /// in sync.
+    fn open_from_folder(&mut self, path: PathBuf) {
+        self.navigation.select(&path);
+        self.image_manager.request_image(path);
+    }
+
+    /// Follows files renamed or moved in the app, keeping the cache, the
+    /// folder and the current image in step. The current image stays on
+    /// screen under its new name; if it left the folder, the image that
+    /// took its place is shown instead.
+    fn apply_renames(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        self.gallery.rename(renamed);
+        self.image_manager.rename(renamed);
+        if let Err(e) = self.favorites.rename(renamed) {
+            tracing::warn!("Failed to save the starred images: {}", e);
+        }
+        if self.starred_only {
+            self.navigation.set_filter(self.view_filter());
+        }
+        self.navigation.follow_renames(renamed);
+        self.show_listed();
+    }
+
+    /// Stars the image on screen or takes its star away.
+    fn toggle_star(&mut self) {
+        let Some(path) = self.image_manager.current_path().cloned() else {
+            return;
+        };
+        match self.favorites.toggle(&path) {
+            Ok(true) => crash::record_action("Starred the image"),
+            Ok(false) => crash::record_action("Unstarred the image"),
+            Err(e) => {
+                tracing::warn!("Failed to save the starred images: {}", e)
+            },
+        }
+        if self.starred_only {
+            self.apply_filter();
+        }
+    }
+
+    /// What the folder is narrowed down to by the stars and the filter bar
+    fn view_filter(&self) -> ViewFilter {
+        ViewFilter {
+            starred: self
+                .starred_only
+                .then(|| self.favorites.paths().clone()),
+            ..self.filter_bar.filter()
+        }
+    }
+
+    /// Lists the folder again through the filter, showing the first image
+    /// passing if the one on screen does not. Browsing the starred ones
+    /// ends once the last star is taken away; while no image passes, the
+    /// one on screen stays.
+    fn apply_filter(&mut self) {
+        if self.favorites.is_empty() {
+            self.starred_only = false;
+        }
+        self.navigation.set_filter(self.view_filter());
+        if !self.navigation.images().is_empty() {
+            self.show_listed();
+        }
+    }
+
+    /// Applies the filter again after the rating or tags of the image on
+    /// screen changed, in case it no longer passes.
+    fn refilter(&mut self) {
+        if !self.view_filter().is_empty() {
+            self.apply_filter();
+        }
+    }
+
+    /// Shows the image that became current once the image on screen left
+    /// the list.
+    fn show_listed(&mut self) {
+        let Some(current) = self.image_manager.current_path().cloned() else {
+            return;
+        };
+        if self.navigation.select(&current) {
+            return;
+        }
+        match self.navigation.current() {
+            Some(next) => self
+                .image_manager
+                .request_image(next.to_path_buf()),
+            None if self.navigation.images().is_empty() => {
+                self.image_manager.close()
+            },
+            None => {},
+        }
+    }
+
+    fn handle_gallery_action(&mut self, action: GalleryAction) {
+        match action {
+            GalleryAction::Open(path) => {
+                self.gallery.close();
+                self.open_from_folder(path);
+            },
+            GalleryAction::Compare(first, second) => {
+                self.gallery.close();
+                self.open_from_folder(first);
+                self.compare.open_file(
+                    &second,
+                    &self.config.decoding,
+                    &self.config.io,
+                );
+            },
+            GalleryAction::Batch(paths) => self.batch.open_with(paths),
+            GalleryAction::Stack(frames) => {
+                self.gallery.close();
+                self.open_from_folder(frames[0].clone());
+                self.stack.open_with(frames);
+            },
+            GalleryAction::Hdr(frames) => self.hdr.open_with(frames),
+            GalleryAction::Panorama(photos) => {
+                self.gallery.close();
+                self.open_from_folder(photos[0].clone());
+                self.panorama.open_with(photos);
+            },
+            GalleryAction::Moved(moved) => self.apply_renames(&moved),
+        }
+    }
+
+    pub fn current_path(&self) -> Option<&Path> {
+        self.image_manager
+            .current_path()
+            .map(PathBuf::as_path)
+    }
+
+    /// Image of the second pane, while the window is split
+    pub fn pane_path(&self) -> Option<&Path> {
+        self.split
+            .is_open()
+            .then(|| self.split.current_path())
+            .flatten()
+    }
+
+    /// Whether an image is being decoded in the background
+    pub fn is_loading(&self) -> bool {
+        self.image_manager.loading().is_some()
+            || self.navigation.is_arranging()
+            || self.split.is_loading()
+            || self.download.is_busy()
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Switches borderless fullscreen, which also hides the menu bar.
+    fn toggle_fullscreen(&mut self, ctx: &Context) {
+        self.fullscreen = !self.fullscreen;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(
+            self.fullscreen,
+        ));
+    }
+
+    pub fn zoom_level(&self) -> f64 {
+        self.zoom_handler.zoom_level()
+    }
+
+    /// Where the image was drawn in the last frame
+    pub fn image_rect(&self) -> Option<egui::Rect> {
+        self.image_rect
+    }
+
+    fn advance_slideshow(&mut self, ctx: &Context) {
+        if !self.slideshow.update(ctx, &self.config.slideshow) {
+            return;
+        }
+        if let (Some(texture), Some(rect)) = (
+            self.image_manager
+                .current_image()
+                .and_then(|data| data.texture.clone()),
+            self.image_rect,
+        ) {
+            self.slideshow.set_outgoing(texture, rect);
+        }
+        let next = self.slideshow.next(
+            self.navigation.images(),
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
+            &self.config.slideshow,
+        );
+        match next {
+            Some(next) => {
+                self.open_from_folder(next);
+                self.slideshow
+                    .start_slide(ctx, &self.config.slideshow);
+            },
+            // Past the last image without looping
+            None => self.slideshow.stop(),
+        }
+    }
+
+    fn zoom_to_region(&mut self, region: Region) {
+        let Some((width, height)) = self.image_manager.get_current_dimensions()
+        else {
+            return;
+        };
+        self.zoom_handler.zoom_to_region(
+            egui::vec2(width as f32, height as f32),
+            egui::vec2(region.x as f32, region.y as f32),
+            egui::vec2(region.width as f32, region.height as f32),
+            self.viewport.size(),
+            self.config.selection.zoom_to_longer_side,
+        );
+    }
+
+    /// Changes image once per line of wheel travel bound to navigation;
+    /// turning up goes back.
+    fn handle_wheel_navigation(&mut self, ctx: &Context) {
+        self.wheel_travel += input::wheel_travel(
+            ctx,
+            &self.config.controls,
+            WheelAction::Navigate,
+        );
+        while self.wheel_travel.abs() >= input::POINTS_PER_LINE {
+            let back = self.wheel_travel > 0.0;
+            self.wheel_travel -=
+                input::POINTS_PER_LINE.copysign(self.wheel_travel);
+            self.step(back);
+        }
+    }
+
+    /// Shows the previous or the next image, in the focused side of a split
+    /// view.
+    fn step(&mut self, back: bool) {
+        if self.split.is_focused() {
+            self.split.step(back);
+            return;
+        }
+        let path = if back {
+            self.navigation.previous_image()
+        } else {
+            self.navigation.next_image()
+        };
+        if let Some(path) = path {
+            self.image_manager.request_image(path);
+        }
+    }
+
+    fn toggle_split(&mut self) {
+        self.split.toggle(
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
+            &self.config.navigation,
+        );
+    }
+
+    /// Saves the displayed image as rotated and flipped in the view, as a
+    /// PNG next to the source.
+    fn save_transformed_copy(&mut self) {
+        let Some(path) = self
+            .image_manager
+            .current_path()
+            .map(|source| derived_path(source, "rotated"))
+        else {
+            return;
+        };
+        let Some(image) = self
+            .image_manager
+            .current_image()
+            .map(|data| &data.original)
+        else {
+            return;
+        };
+        match self
+            .zoom_handler
+            .transform()
+            .apply(image)
+            .save(&path)
+        {
+            Ok(()) => {
+                tracing::info!("Saved rotated copy to {}", path.display())
+            },
+            Err(e) => tracing::warn!("Failed to save rotated copy: {}", e),
+        }
+    }
+
+    fn scan_codes(&mut self) {
+        let path = self.image_manager.current_path().cloned();
+        if let Some(image_data) = self.image_manager.current_image() {
+            self.code_scanner
+                .start_scan(&image_data.original, path);
+        }
     }
 }
 
 impl eframe::App for FeriteApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Handle quit action by sending a close event to the application
-        // context
-        if ctx.input(|i| i.key_pressed(Key::Q)) {
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-        }
-        // Handle file drops
-        if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
-            let files: Vec<_> = ctx
-                .input(|i| i.raw.dropped_files.clone())
+        self.ui(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        let state_dir = self.config.paths.resolve_state_dir();
+        if let Err(e) = self.session().save(state_dir.as_deref()) {
+            tracing::warn!("Failed to save the session: {}", e);
+        }
+    }
+}
+
+impl FeriteApp {
+    /// What to bring back on the next start
+    pub fn session(&self) -> SessionState {
+        let zoom = match self.zoom_handler.get_fit_mode() {
+            FitMode::OneToOne => ZoomState::OneToOne,
+            FitMode::FitLonger => ZoomState::FitLonger,
+            FitMode::FitShorter => ZoomState::FitShorter,
+            FitMode::Custom => {
+                ZoomState::Custom(self.zoom_handler.zoom_level())
+            },
+        };
+        SessionState {
+            image: self.image_manager.current_path().cloned(),
+            zoom,
+            panels: PanelState {
+                exif:       self.exif_panel.is_open(),
+                filmstrip:  self.filmstrip.is_open(),
+                histogram:  self.histogram.is_open(),
+                map:        self.map_panel.is_open(),
+                palette:    self.palette.is_open(),
+                playlist:   self.playlist.is_open(),
+                tags:       self.tags.is_open(),
+                filter_bar: self.filter_bar.is_open(),
+            },
+            fullscreen: self.fullscreen,
+            window: self.window,
+        }
+    }
+
+    /// Follows the folder of the image like a render output: whenever an
+    /// image is added, the newest one is shown.
+    pub fn set_monitor(&mut self, monitor: bool) {
+        self.monitor = monitor;
+    }
+
+    /// Opens the images other starts of the viewer send to `inbox` in this
+    /// window.
+    pub fn set_inbox(&mut self, inbox: Inbox) {
+        self.inbox = Some(inbox);
+    }
+
+    /// Browses only the starred images of the folder, or all of them again.
+    /// Stays off while nothing is starred.
+    pub fn set_starred_only(&mut self, starred_only: bool) {
+        self.starred_only = starred_only;
+        self.apply_filter();
+    }
+
+    /// Browses only the images rated at least `min_rating` with all of
+    /// `tags`, showing them in the filter bar.
+    pub fn filter_by(&mut self, min_rating: u8, tags: &[String]) {
+        self.filter_bar.show(min_rating, tags);
+        self.apply_filter();
+    }
+
+    /// Applies the zoom, panels and fullscreen of a saved session. The image
+    /// and the window are restored by whoever opens them.
+    pub fn restore_session(&mut self, session: &SessionState) {
+        match session.zoom {
+            ZoomState::OneToOne => {
+                self.zoom_handler.set_fit_mode(FitMode::OneToOne)
+            },
+            ZoomState::FitLonger => {
+                self.zoom_handler.set_fit_mode(FitMode::FitLonger)
+            },
+            ZoomState::FitShorter => self
+                .zoom_handler
+                .set_fit_mode(FitMode::FitShorter),
+            ZoomState::Custom(zoom) => self.zoom_handler.set_zoom(zoom),
+        }
+        let panels = &session.panels;
+        if panels.exif != self.exif_panel.is_open() {
+            self.exif_panel.toggle();
+        }
+        if panels.filmstrip != self.filmstrip.is_open() {
+            self.filmstrip.toggle();
+        }
+        if panels.histogram != self.histogram.is_open() {
+            self.histogram.toggle();
+        }
+        if panels.map != self.map_panel.is_open() {
+            self.map_panel.toggle();
+        }
+        if panels.palette != self.palette.is_open() {
+            self.palette.toggle();
+        }
+        if panels.playlist != self.playlist.is_open() {
+            self.playlist.toggle();
+        }
+        if panels.tags != self.tags.is_open() {
+            self.tags.toggle();
+        }
+        if panels.filter_bar != self.filter_bar.is_open() {
+            self.filter_bar.toggle();
+        }
+        self.fullscreen = session.fullscreen;
+    }
+
+    /// Runs one frame of the application against `ctx`.
+    pub fn ui(&mut self, ctx: &Context) {
+        self.reload_changed(ctx);
+        self.poll_loading(ctx);
+        self.poll_navigation(ctx);
+        self.split.poll(ctx);
+        if !self.fullscreen {
+            self.window = ctx
+                .input(|i| {
+                    let viewport = i.viewport();
+                    let outer = viewport.outer_rect?;
+                    let inner = viewport.inner_rect?;
+                    Some(WindowGeometry {
+                        x:      outer.min.x,
+                        y:      outer.min.y,
+                        width:  inner.width(),
+                        height: inner.height(),
+                    })
+                })
+                .or(self.window);
+        }
+
+        if let Some(path) = self.download.poll(ctx) {
+            self.open_downloaded(path);
+        }
+
+        // Handle file drops; dropped links, and links pasted outside text
+        // fields, are downloaded
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        let pasted = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Paste(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        });
+        let links = dropped
+            .iter()
+            .filter(|f| f.path.is_none())
+            .map(|f| f.name.clone())
+            .chain(
+                pasted
+                    .into_iter()
+                    .filter(|_| !ctx.wants_keyboard_input()),
+            );
+        for link in links.filter(|link| remote::is_url(link)) {
+            self.download.start(&link, &self.config);
+        }
+        if dropped.iter().any(|f| f.path.is_some()) {
+            let files: Vec<_> = dropped
                 .into_iter()
                 .filter_map(|f| f.path)
                 .collect();
-            self.handle_files_dropped(ctx, files);
+            self.open_in_focused(files);
         }
 
-        // Handle navigation keyboard events
-        self.navigation.handle_keyboard_input(
-            ctx,
-            &mut self.image_manager,
-            &mut self.zoom_handler,
+        let forwarded = self.inbox.as_ref().and_then(Inbox::take);
+        if let Some(paths) = forwarded {
+            let (links, files): (Vec<_>, Vec<_>) = paths
+                .into_iter()
+                .partition(|path| path.to_str().is_some_and(remote::is_url));
+            for link in links.iter().filter_map(|link| link.to_str()) {
+                self.download.start(link, &self.config);
+            }
+            if !files.is_empty() {
+                self.open_in_focused(files);
+            }
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+        let events = self
+            .desktop
+            .as_ref()
+            .map(Desktop::take)
+            .unwrap_or_default();
+        for event in events {
+            match event {
+                DesktopEvent::Open(files) => {
+                    self.open_in_focused(files);
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                },
+                DesktopEvent::Preferences => self.open_preferences(),
+                DesktopEvent::Quit => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close)
+                },
+                DesktopEvent::Previous => self.step(true),
+                DesktopEvent::Next => self.step(false),
+            }
+        }
+
+        self.tags.sync(
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
         );
 
-        // Toggle menu visibility
-        if ctx.input(|i| i.key_pressed(Key::M)) {
-            self.menu_bar.toggle();
+        // Single-key shortcuts are ignored while a text field has focus
+        if !ctx.wants_keyboard_input() {
+            self.crop.nudge(ctx);
+            if self.gallery.is_active() {
+                let action = self.gallery.handle_keys(
+                    ctx,
+                    self.navigation.images(),
+                    self.image_manager
+                        .current_path()
+                        .map(PathBuf::as_path),
+                );
+                if let Some(action) = action {
+                    self.handle_gallery_action(action);
+                }
+            } else if let Some(view) = self.split.focused_view() {
+                input::pan_with_keys(ctx, &self.config.controls.keymap, view);
+            } else {
+                input::pan_with_keys(
+                    ctx,
+                    &self.config.controls.keymap,
+                    &mut self.zoom_handler,
+                );
+            }
+            self.handle_shortcuts(ctx);
+        }
+        if !self.gallery.is_active() {
+            self.handle_wheel_navigation(ctx);
+        }
+
+        self.code_scanner
+            .update(ctx, self.image_manager.current_path());
+        self.upscale_panel
+            .update(ctx, &mut self.image_manager);
+        self.view_exporter.update(ctx);
+        self.batch.update(ctx);
+        if let Some(desktop) = &mut self.desktop {
+            desktop.set_progress(self.batch.progress());
+        }
+        self.stack.update(ctx, &mut self.image_manager);
+        self.panorama.update(ctx, &mut self.image_manager);
+        self.straighten
+            .update(ctx, &mut self.image_manager);
+        self.hdr
+            .update(ctx, &mut self.image_manager, self.navigation.images());
+        self.lens_panel
+            .update(ctx, &mut self.image_manager, &self.config.lens);
+        self.lut.update(ctx, &mut self.image_manager);
+        self.animation
+            .update(ctx, &mut self.image_manager, &self.config);
+
+        self.advance_slideshow(ctx);
+
+        let mut menu_action = None;
+        let mut gallery_action = None;
+
+        self.exif_panel.render(
+            ctx,
+            self.image_manager
+                .current_path()
+                .map(PathBuf::as_path),
+        );
+        if self.navigation.is_playlist() {
+            if let Some(action) = self.playlist.render(
+                ctx,
+                self.navigation.images(),
+                self.image_manager
+                    .current_path()
+                    .map(PathBuf::as_path),
+            ) {
+                self.handle_playlist_action(action);
+            }
+        }
+        if !self.gallery.is_active() && !self.fullscreen {
+            let marks = Marks {
+                starred: self
+                    .image_manager
+                    .current_path()
+                    .is_some_and(|path| self.favorites.contains(path)),
+                rating:  self.tags.rating(),
+            };
+            if let Some(zoom) = self.status_bar.render(
+                ctx,
+                &self.config.status_bar,
+                &mut self.image_manager,
+                self.image_rect,
+                self.zoom_handler.zoom_percentage(),
+                marks,
+            ) {
+                self.zoom_handler.set_zoom(zoom);
+            }
+        }
+        if !self.gallery.is_active() {
+            if let Some(path) = self.filmstrip.render(
+                ctx,
+                &self.config,
+                self.navigation.images(),
+                self.image_manager
+                    .current_path()
+                    .map(PathBuf::as_path),
+                &self.favorites,
+            ) {
+                self.open_from_folder(path);
+            }
+            if self
+                .filter_bar
+                .render(ctx, self.navigation.images().len())
+            {
+                self.apply_filter();
+            }
         }
 
         // Set up the main UI panel
         egui::CentralPanel::default().show(ctx, |ui| {
             // Render menu bar if not hidden
-            if !self.menu_bar.is_hidden() {
-                self.menu_bar.render(ui, ctx, &mut self.config);
+            if !self.menu_bar.is_hidden() && !self.fullscreen {
+                menu_action = self.menu_bar.render(
+                    ui,
+                    ctx,
+                    &mut self.config,
+                    &mut self.recent_menu,
+                    &self.favorites,
+                    self.starred_only,
+                );
+            }
+
+            if self.gallery.is_active() {
+                gallery_action = self.gallery.render(
+                    ui,
+                    &self.config,
+                    self.navigation.images(),
+                    self.image_manager
+                        .current_path()
+                        .map(PathBuf::as_path),
+                );
+                return;
+            }
+
+            let pan_enabled = !self.measure.is_active()
+                && !self.inspector.is_active()
+                && !self.crop.is_active()
+                && !self.guides.wants_pointer(ctx)
+                && !self.compare.wants_pointer(ctx)
+                && !self
+                    .selection
+                    .wants_pointer(ctx, &self.config.selection);
+            // In compare mode, or with the window split, the image and its
+            // tools get the left half
+            let full = ui.available_rect_before_wrap();
+            let (main_rect, compare_rect) = if self.compare.is_active() {
+                self.compare.panes(full)
+            } else {
+                (full, egui::Rect::NOTHING)
+            };
+            let main_rect = if self.split.is_open() && !self.compare.is_active()
+            {
+                let (main_rect, pane_rect) = self.split.panes(full);
+                self.split
+                    .follow_pointer(ctx, main_rect, pane_rect);
+                self.split.render(
+                    ui,
+                    ctx,
+                    pane_rect,
+                    &self.config,
+                    Appearance {
+                        tone:   self.tone_map.tone(),
+                        adjust: self.adjust.adjustments(),
+                    },
+                );
+                main_rect
+            } else {
+                main_rect
+            };
+            self.viewport = main_rect;
+            let mut main_ui = ui.child_ui(main_rect, *ui.layout());
+            main_ui.set_clip_rect(main_rect.intersect(ui.clip_rect()));
+            let ui = &mut main_ui;
+
+            if let Some(size) = self.image_manager.get_current_dimensions() {
+                self.slideshow.apply_motion(
+                    ctx,
+                    &self.config.slideshow,
+                    &mut self.zoom_handler,
+                    size,
+                    main_rect.size(),
+                );
             }
 
             // Render the image and handle all interactions
-            ImageRenderer::render(
+            let image_rect = ImageRenderer::render(
                 ui,
                 ctx,
                 &mut self.image_manager,
                 &mut self.zoom_handler,
                 &self.config,
+                Appearance {
+                    tone:   self.tone_map.tone(),
+                    adjust: self.adjust.adjustments(),
+                },
+                ViewInput {
+                    keys:  !self.split.is_focused(),
+                    wheel: !self.split.is_open()
+                        || ui.rect_contains_pointer(main_rect),
+                    drag:  pan_enabled,
+                },
+            );
+
+            self.image_rect = image_rect;
+            // Double-clicking the image toggles fullscreen, unless a tool
+            // takes the clicks
+            if pan_enabled
+                && ui.rect_contains_pointer(main_rect)
+                && ctx.input(|i| {
+                    i.pointer
+                        .button_double_clicked(egui::PointerButton::Primary)
+                })
+            {
+                self.toggle_fullscreen(ctx);
+            }
+            let incoming = self
+                .image_manager
+                .current_image()
+                .and_then(|data| data.texture.as_ref())
+                .zip(image_rect);
+            self.slideshow.paint_transition(
+                ui,
+                ctx,
+                &self.config.slideshow,
+                main_rect,
+                incoming,
             );
+            if self.image_manager.loading().is_some() {
+                egui::Spinner::new()
+                    .size(LOADING_SPINNER_SIZE)
+                    .paint_at(
+                        ui,
+                        egui::Rect::from_center_size(
+                            main_rect.center(),
+                            egui::Vec2::splat(LOADING_SPINNER_SIZE),
+                        ),
+                    );
+            }
+
+            // Draw overlays on top of the image
+            if let (Some(rect), Some(size)) =
+                (image_rect, self.image_manager.get_current_dimensions())
+            {
+                self.grid.paint(ui.painter(), rect);
+                self.code_scanner
+                    .paint_outlines(ui.painter(), rect, size);
+                if let Some(geometry) = ImageGeometry::new(rect, size) {
+                    self.guides.interact(ui, geometry);
+                    self.measure.interact(ui, geometry);
+                    if !self.crop.is_active() {
+                        self.selection.interact(
+                            ui,
+                            geometry,
+                            &self.config.selection,
+                            self.image_manager.current_path(),
+                        );
+                    }
+                    self.crop.interact(
+                        ui,
+                        geometry,
+                        self.image_manager.current_path(),
+                    );
+                    if let Some(texture) = self
+                        .image_manager
+                        .current_image()
+                        .and_then(|data| data.texture.as_ref())
+                    {
+                        self.loupe.paint(
+                            ui,
+                            geometry,
+                            texture.id(),
+                            &self.config.loupe,
+                        );
+                    }
+                    if let Some(data) = self.image_manager.current_image() {
+                        self.inspector
+                            .interact(ui, geometry, &data.original);
+                    }
+                }
+            }
+
+            if let Some((width, _)) =
+                self.image_manager.get_current_dimensions()
+            {
+                self.compare.render_pane(
+                    ui,
+                    compare_rect,
+                    image_rect,
+                    &self.zoom_handler,
+                    width,
+                    self.config.color.dither,
+                );
+            }
         });
+
+        if let Some(action) = gallery_action {
+            self.handle_gallery_action(action);
+        }
+
+        self.code_scanner.render_window(ctx);
+        self.upscale_panel.render_window(
+            ctx,
+            &mut self.image_manager,
+            &self.config.upscale,
+        );
+        if let Some(path) = self.map_panel.render_window(
+            ctx,
+            &self.config.map,
+            &self.config.paths,
+            self.image_manager.current_path(),
+            self.navigation.images(),
+        ) {
+            self.open_from_folder(path);
+        }
+        self.xmp_editor.render_window(
+            ctx,
+            &self.config.xmp,
+            self.image_manager.current_path(),
+        );
+        if self.tags.render_window(ctx, &self.config.xmp) {
+            self.refilter();
+        }
+        if let Some(path) = self
+            .checksums
+            .render_window(ctx, self.image_manager.current_path())
+        {
+            self.open_from_folder(path);
+        }
+
+        self.lens_panel
+            .render_window(ctx, &mut self.image_manager);
+        self.lut
+            .render_window(ctx, &mut self.image_manager, &self.config);
+        if !self.gallery.is_active() {
+            self.animation.render_controls(ctx, self.viewport);
+            self.slideshow.render_controls(
+                ctx,
+                &mut self.config.slideshow,
+                self.viewport,
+            );
+        }
+        if let Some(renamed) = self.batch.render_window(
+            ctx,
+            &self.config,
+            self.navigation.images(),
+        ) {
+            self.apply_renames(&renamed);
+        }
+        self.stack
+            .render_window(ctx, &mut self.image_manager, &self.config);
+        if let Some(path) =
+            self.hdr
+                .render_window(ctx, &mut self.image_manager, &self.config)
+        {
+            self.gallery.close();
+            self.open_from_folder(path);
+        }
+        self.panorama
+            .render_window(ctx, &mut self.image_manager, &self.config);
+        self.straighten.render_window(
+            ctx,
+            &mut self.image_manager,
+            &self.config,
+        );
+        self.measure.render_window(ctx);
+        if self
+            .shortcuts
+            .render_window(ctx, &mut self.config.controls.keymap)
+        {
+            self.save_config();
+        }
+        if let Some(SelectionAction::ZoomTo(region)) = self
+            .selection
+            .render_window(ctx, &mut self.image_manager)
+        {
+            self.zoom_to_region(region);
+        }
+        self.palette.render_window(
+            ctx,
+            self.image_manager.current_path(),
+            self.image_manager.original_image(),
+        );
+        self.compare.update(
+            ctx,
+            self.image_manager.current_path(),
+            self.image_manager.is_showing_derived(),
+            self.image_manager.displayed_image(),
+        );
+        self.histogram.render_window(
+            ctx,
+            self.image_manager.current_path(),
+            self.image_manager.is_showing_derived(),
+            self.image_manager.displayed_image(),
+        );
+        // Only images with values past white need tone mapping
+        let showing_hdr = !self.image_manager.is_showing_derived()
+            && self
+                .image_manager
+                .original_image()
+                .is_some_and(hdr::is_hdr);
+        if showing_hdr && self.tone_map.render(ctx) {
+            if let Some(image) = self.image_manager.current_image() {
+                image.texture = None;
+            }
+        }
+
+        if let Some(path) = self.open_dialog.render_window(ctx, &self.config) {
+            if std::mem::take(&mut self.compare_pick) {
+                self.compare.open_file(
+                    &path,
+                    &self.config.decoding,
+                    &self.config.io,
+                );
+            } else {
+                self.open_in_focused(vec![path]);
+            }
+        }
+        self.view_exporter.render_window(ctx);
+        self.compare.render_window(ctx);
+        self.crash_notice.render_window(ctx);
+        self.download.render(ctx);
+        self.remember_current();
+        LargeImagePrompt::render(ctx, &mut self.image_manager);
+        if let Some(path) = self
+            .trash_prompt
+            .render_window(ctx, &mut self.config.navigation.confirm_delete)
+        {
+            self.move_to_trash(path);
+        }
+        if let Some(path) = self
+            .save_dialog
+            .render_window(ctx, &mut self.image_manager)
+        {
+            tracing::info!("Saved the image to {}", path.display());
+            // An unsaved image becomes an ordinary file of its folder
+            if self.image_manager.is_unsaved() {
+                self.open_file(path);
+            }
+        }
+        if let Some(path) = self
+            .crop
+            .render_window(ctx, &mut self.image_manager)
+        {
+            tracing::info!("Saved the crop to {}", path.display());
+            if self.image_manager.current_path() == Some(&path) {
+                self.image_manager.forget(&path);
+                self.image_manager.request_image(path);
+            } else {
+                self.navigation.refresh();
+            }
+        }
+        if let Some(path) = self
+            .adjust
+            .render_window(ctx, &mut self.image_manager)
+        {
+            tracing::info!("Exported the adjusted image to {}", path.display());
+            self.navigation.refresh();
+        }
+        if let Some(renamed) = self.rename_dialog.render_window(ctx) {
+            crash::record_action(format!("Renamed {}", renamed.0.display()));
+            self.apply_renames(&[renamed]);
+        }
+        if let Some(folder) = self.folder_picker.render_window(ctx) {
+            self.move_current_to(&folder);
+        }
+
+        if let Some(action) = menu_action {
+            crash::record_action(format!("Menu: {:?}", action));
+        }
+        match menu_action {
+            Some(MenuAction::Open) => self.show_open_dialog(),
+            Some(MenuAction::MoveToTrash) => self.move_current_to_trash(),
+            Some(MenuAction::Rename) => self.rename_current(),
+            Some(MenuAction::MoveToFolder) => self.pick_folder(),
+            Some(MenuAction::ToggleStar) => self.toggle_star(),
+            Some(MenuAction::Tags) => self.tags.toggle(),
+            Some(MenuAction::FilterBar) => {
+                self.filter_bar.toggle();
+                self.apply_filter();
+            },
+            Some(MenuAction::StarredOnly) => {
+                self.set_starred_only(!self.starred_only)
+            },
+            Some(MenuAction::CopyImage) => self.copy_image(),
+            Some(MenuAction::CopyPath) => self.copy_path(ctx),
+            Some(MenuAction::PasteImage) => self.paste_image(),
+            Some(MenuAction::SaveAs) => self.show_save_dialog(),
+            Some(MenuAction::OpenInEditor(index)) => self.open_in_editor(index),
+            Some(MenuAction::OpenRecent(index)) => {
+                if let Some(path) = self.recent_menu.entries().get(index) {
+                    self.open_paths(vec![path.clone()]);
+                }
+            },
+            Some(MenuAction::ClearRecent) => self.recent_menu.clear(),
+            Some(MenuAction::Rotate {
+                clockwise,
+            }) => self
+                .zoom_handler
+                .change_transform(|t| t.rotate(clockwise)),
+            Some(MenuAction::FlipHorizontal) => self
+                .zoom_handler
+                .change_transform(|t| t.flip_horizontal()),
+            Some(MenuAction::FlipVertical) => self
+                .zoom_handler
+                .change_transform(|t| t.flip_vertical()),
+            Some(MenuAction::SaveTransformed) => self.save_transformed_copy(),
+            Some(MenuAction::ViewMode(mode)) => {
+                self.zoom_handler.set_fit_mode(mode);
+            },
+            Some(MenuAction::ZoomPreset(index)) => {
+                self.zoom_handler.set_zoom(ZOOM_PRESETS[index]);
+            },
+            Some(MenuAction::ScanCodes) => self.scan_codes(),
+            Some(MenuAction::Upscale) => self.upscale_panel.toggle(),
+            Some(MenuAction::ToggleMap) => self.map_panel.toggle(),
+            Some(MenuAction::ExifInfo) => self.exif_panel.toggle(),
+            Some(MenuAction::EditMetadata) => self.xmp_editor.toggle(),
+            Some(MenuAction::Checksums) => self.checksums.toggle(),
+            Some(MenuAction::Batch) => self.batch.toggle(),
+            Some(MenuAction::Gallery) => self.gallery.toggle(),
+            Some(MenuAction::Playlist) => self.playlist.toggle(),
+            Some(MenuAction::Shortcuts) => self.shortcuts.toggle(),
+            Some(MenuAction::ColorManagement) => self
+                .image_manager
+                .set_decoding(self.config.decoding.clone()),
+            #[cfg(feature = "raw")]
+            Some(MenuAction::DevelopRaw) => self
+                .image_manager
+                .set_decoding(self.config.decoding.clone()),
+            Some(MenuAction::Background) => {
+                if let Err(e) = self.write_config() {
+                    tracing::warn!("Failed to save the background: {}", e);
+                }
+            },
+            Some(MenuAction::Sort) => {
+                let navigation = &self.config.navigation;
+                self.navigation
+                    .set_order(navigation.sort_order, navigation.reverse_order);
+                if let Err(e) = self.write_config() {
+                    tracing::warn!("Failed to save the sort order: {}", e);
+                }
+            },
+            Some(MenuAction::Fullscreen) => self.toggle_fullscreen(ctx),
+            Some(MenuAction::Filmstrip) => self.filmstrip.toggle(),
+            Some(MenuAction::Stack) => self.stack.toggle(),
+            Some(MenuAction::Hdr) => self.hdr.toggle(),
+            Some(MenuAction::Panorama) => self.panorama.toggle(),
+            Some(MenuAction::Straighten) => self.straighten.toggle(),
+            Some(MenuAction::AutoStraighten) => self
+                .straighten
+                .auto_straighten(&self.image_manager),
+            Some(MenuAction::CreativeLut) => self.lut.toggle(),
+            Some(MenuAction::LensCorrection) => self.lens_panel.toggle(),
+            Some(MenuAction::Palette) => self.palette.toggle(),
+            Some(MenuAction::Histogram) => self.histogram.toggle(),
+            Some(MenuAction::Measure) => self.measure.toggle(),
+            Some(MenuAction::CycleGrid) => self.grid.cycle(),
+            Some(MenuAction::ToggleGuides) => self.guides.toggle(),
+            Some(MenuAction::ToggleLoupe) => self.loupe.toggle(),
+            Some(MenuAction::PixelInspector) => self.inspector.toggle(),
+            Some(MenuAction::Crop) => self.crop.toggle(),
+            Some(MenuAction::Adjust) => self.adjust.toggle(),
+            Some(MenuAction::Slideshow) => {
+                self.slideshow.toggle(ctx, &self.config.slideshow)
+            },
+            Some(MenuAction::CompareClipboard) => self.compare.open_clipboard(),
+            Some(MenuAction::CompareFile) => {
+                self.show_open_dialog();
+                self.compare_pick = true;
+            },
+            Some(MenuAction::CloseCompare) => self.compare.close(),
+            Some(MenuAction::SplitView) => self.toggle_split(),
+            Some(MenuAction::ExportView) => {
+                if let Some(source) = self.image_manager.current_path() {
+                    self.view_exporter.request(source, self.viewport);
+                }
+            },
+            None => {},
+        }
+
+        ferrite_logging::frame_mark();
     }
 }
+
+fn gpu_description(gl: &eframe::glow::Context) -> String {
+    use eframe::glow::{self, HasContext};
+
+    // SAFETY: plain string queries on the context eframe created for us,
+    // made on the thread that owns it
+    let (vendor, renderer, version) = unsafe {
+        (
+            gl.get_parameter_string(glow::VENDOR),
+            gl.get_parameter_string(glow::RENDERER),
+            gl.get_parameter_string(glow::VERSION),
+        )
+    };
+    format!("{} {} (OpenGL {})", vendor, renderer, version)
+}
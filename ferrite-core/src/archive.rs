@@ -0,0 +1,421 @@
+//! Browsing the images inside ZIP archives, such as CBZ comic books,
+//! without extracting them.
+//!
+//! An image in an archive goes by a virtual path: the path of the archive
+//! followed by the name of the entry, as in `comics/issue1.cbz/p01.jpg`.
+//! Navigation lists an archive like a folder and the reader decodes an
+//! entry when it is opened, so the rest of the viewer never needs to know
+//! whether an image is a file of its own. Only the central directory and
+//! the requested entry are read.
+//!
+//! Stored and deflated entries are supported. RAR archives, which CBR files
+//! usually are, need a decoder Ferrite does not have; CBR files that are
+//! ZIP archives under another name open like CBZ ones.
+
+use flate2::read::DeflateDecoder;
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::image::SupportedFormats;
+
+/// Extensions of the archives that are browsed like folders
+pub const EXTENSIONS: &[&str] = &["zip", "cbz", "cbr"];
+
+const END_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const RAR_SIGNATURE: &[u8] = b"Rar!\x1a\x07";
+const END_LEN: usize = 22;
+const CENTRAL_LEN: usize = 46;
+const LOCAL_LEN: usize = 30;
+/// The end record is followed by a comment of at most this many bytes
+const MAX_COMMENT: usize = u16::MAX as usize;
+/// Most bytes reserved ahead of inflating an entry, whatever its header
+/// claims
+const MAX_PREALLOC: usize = 64 << 20;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_UTF8: u16 = 1 << 11;
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("Failed to read the archive: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Not a ZIP archive")]
+    NotZip,
+
+    #[error("RAR archives are not supported")]
+    Rar,
+
+    #[error("ZIP64 archives are not supported")]
+    Zip64,
+
+    #[error("{0} is encrypted")]
+    Encrypted(String),
+
+    #[error("{0} uses unsupported compression method {1}")]
+    Method(String, u16),
+
+    #[error("No entry {0} in the archive")]
+    Missing(String),
+
+    #[error("{0} is larger than its header claims or the memory limit allows")]
+    TooLarge(String),
+}
+
+impl From<ArchiveError> for io::Error {
+    fn from(error: ArchiveError) -> Self {
+        match error {
+            ArchiveError::Io(e) => e,
+            ArchiveError::Missing(_) => {
+                io::Error::new(ErrorKind::NotFound, error)
+            },
+            ArchiveError::TooLarge(_) => {
+                io::Error::new(ErrorKind::OutOfMemory, error)
+            },
+            _ => io::Error::new(ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+/// A file of the archive, as listed in its central directory
+struct Entry {
+    name:            String,
+    method:          u16,
+    flags:           u16,
+    compressed_size: u64,
+    size:            u64,
+    /// Where its local header starts
+    offset:          u64,
+}
+
+pub struct Archive {
+    path:    PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Archive {
+    /// Reads the list of entries of the archive at `path`.
+    pub fn open(path: &Path) -> Result<Self, ArchiveError> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let tail_len = len.min((END_LEN + MAX_COMMENT) as u64);
+        file.seek(SeekFrom::Start(len - tail_len))?;
+        let mut tail = vec![0; tail_len as usize];
+        file.read_exact(&mut tail)?;
+
+        let Some(end) = (0..(tail.len() + 1).saturating_sub(END_LEN))
+            .rev()
+            .find(|&at| u32_at(&tail, at) == END_SIGNATURE)
+        else {
+            file.seek(SeekFrom::Start(0))?;
+            let mut magic = [0; RAR_SIGNATURE.len()];
+            return Err(match file.read_exact(&mut magic) {
+                Ok(()) if magic == RAR_SIGNATURE => ArchiveError::Rar,
+                _ => ArchiveError::NotZip,
+            });
+        };
+        let count = u16_at(&tail, end + 10);
+        let directory_len = u32_at(&tail, end + 12);
+        let directory_offset = u32_at(&tail, end + 16);
+        if count == u16::MAX || directory_offset == u32::MAX {
+            return Err(ArchiveError::Zip64);
+        }
+        // Both come from the file, so they are checked before allocating
+        if directory_offset as u64 + directory_len as u64 > len {
+            return Err(ArchiveError::NotZip);
+        }
+
+        file.seek(SeekFrom::Start(directory_offset as u64))?;
+        let mut directory = vec![0; directory_len as usize];
+        file.read_exact(&mut directory)?;
+        let mut entries = Vec::with_capacity(count as usize);
+        let mut at = 0;
+        while at + CENTRAL_LEN <= directory.len()
+            && u32_at(&directory, at) == CENTRAL_SIGNATURE
+        {
+            let flags = u16_at(&directory, at + 8);
+            let name_len = u16_at(&directory, at + 28) as usize;
+            let extra_len = u16_at(&directory, at + 30) as usize;
+            let comment_len = u16_at(&directory, at + 32) as usize;
+            let name = directory
+                .get(at + CENTRAL_LEN..at + CENTRAL_LEN + name_len)
+                .ok_or(ArchiveError::NotZip)?;
+            // Names that are not UTF-8 are in code page 437, which agrees
+            // with ASCII
+            let name = if flags & FLAG_UTF8 != 0 {
+                String::from_utf8_lossy(name).into_owned()
+            } else {
+                name.iter().map(|&b| b as char).collect()
+            };
+            let sizes = [
+                u32_at(&directory, at + 20),
+                u32_at(&directory, at + 24),
+                u32_at(&directory, at + 42),
+            ];
+            if sizes.contains(&u32::MAX) {
+                return Err(ArchiveError::Zip64);
+            }
+            entries.push(Entry {
+                name,
+                method: u16_at(&directory, at + 10),
+                flags,
+                compressed_size: sizes[0] as u64,
+                size: sizes[1] as u64,
+                offset: sizes[2] as u64,
+            });
+            at += CENTRAL_LEN + name_len + extra_len + comment_len;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// The virtual paths of the supported images in the archive, in the
+    /// order they were stored. Folders, and names that would point outside
+    /// the archive, are left out.
+    pub fn images(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.name.ends_with('/'))
+            .filter(|entry| {
+                Path::new(&entry.name)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+            })
+            .map(|entry| self.path.join(&entry.name))
+            .filter(|path| SupportedFormats::is_supported(path.extension()))
+            .collect()
+    }
+
+    /// Reads and inflates the entry called `name`, which may be at most
+    /// `max_size` bytes once inflated.
+    pub fn read(
+        &self,
+        name: &str,
+        max_size: u64,
+    ) -> Result<Vec<u8>, ArchiveError> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| ArchiveError::Missing(name.to_string()))?;
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(ArchiveError::Encrypted(name.to_string()));
+        }
+        if entry.size > max_size {
+            return Err(ArchiveError::TooLarge(name.to_string()));
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut header = [0; LOCAL_LEN];
+        file.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_SIGNATURE {
+            return Err(ArchiveError::NotZip);
+        }
+        let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+        file.seek(SeekFrom::Current(skip))?;
+
+        let data = file.take(entry.compressed_size);
+        let mut bytes =
+            Vec::with_capacity((entry.size as usize).min(MAX_PREALLOC));
+        match entry.method {
+            STORED => data.take(entry.size).read_to_end(&mut bytes)?,
+            // One byte more than claimed tells a lying header, before a
+            // crafted entry can inflate without end
+            DEFLATED => DeflateDecoder::new(data)
+                .take(entry.size + 1)
+                .read_to_end(&mut bytes)?,
+            method => {
+                return Err(ArchiveError::Method(name.to_string(), method))
+            },
+        };
+        if bytes.len() as u64 > entry.size {
+            return Err(ArchiveError::TooLarge(name.to_string()));
+        }
+        Ok(bytes)
+    }
+}
+
+/// Whether `path` is an archive to browse like a folder
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        && path.is_file()
+}
+
+/// Splits a virtual path into the archive holding it and the name of the
+/// entry, which is empty for the archive itself. `None` for a path outside
+/// any archive.
+pub fn locate(path: &Path) -> Option<(&Path, String)> {
+    let archive = path.ancestors().find(|dir| is_archive(dir))?;
+    let name = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive, name))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::DeflateEncoder, Compression};
+    use std::{fs, io::Write};
+
+    /// A ZIP archive of `files`, deflating those marked so
+    fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let (mut bytes, mut directory) = (Vec::new(), Vec::new());
+        for &(name, data, deflate) in files {
+            let stored = if deflate {
+                let mut encoder =
+                    DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                data.to_vec()
+            };
+            let method: u16 = if deflate { DEFLATED } else { STORED };
+            let offset = bytes.len() as u32;
+            let sizes = [stored.len() as u32, data.len() as u32];
+
+            bytes.extend(LOCAL_SIGNATURE.to_le_bytes());
+            bytes.extend([0; 4]);
+            bytes.extend(method.to_le_bytes());
+            bytes.extend([0; 8]);
+            sizes
+                .iter()
+                .for_each(|s| bytes.extend(s.to_le_bytes()));
+            bytes.extend((name.len() as u16).to_le_bytes());
+            bytes.extend([0; 2]);
+            bytes.extend(name.as_bytes());
+            bytes.extend(&stored);
+
+            directory.extend(CENTRAL_SIGNATURE.to_le_bytes());
+            directory.extend([0; 4]);
+            directory.extend(FLAG_UTF8.to_le_bytes());
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 8]);
+            sizes
+                .iter()
+                .for_each(|s| directory.extend(s.to_le_bytes()));
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend([0; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let offset = bytes.len() as u32;
+        bytes.extend(&directory);
+        bytes.extend(END_SIGNATURE.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((directory.len() as u32).to_le_bytes());
+        bytes.extend(offset.to_le_bytes());
+        bytes.extend([0; 2]);
+        bytes
+    }
+
+    #[test]
+    fn test_entries_are_listed_and_inflated() {
+        let dir = std::env::temp_dir().join("ferrite-archive");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.cbz");
+        let page = vec![7; 5000];
+        fs::write(
+            &path,
+            zip(&[
+                ("pages/", b"", false),
+                ("pages/01.png", &page, true),
+                ("notes.txt", b"text", false),
+                ("../escape.png", b"", false),
+                ("02.jpg", b"stored", false),
+            ]),
+        )
+        .unwrap();
+
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.images(), [
+            path.join("pages/01.png"),
+            path.join("02.jpg")
+        ]);
+        assert_eq!(archive.read("pages/01.png", u64::MAX).unwrap(), page);
+        assert_eq!(archive.read("02.jpg", u64::MAX).unwrap(), b"stored");
+        assert!(matches!(
+            archive.read("03.jpg", u64::MAX),
+            Err(ArchiveError::Missing(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_entries_cannot_inflate_past_their_size() {
+        let dir = std::env::temp_dir().join("ferrite-archive-bomb");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bomb.zip");
+        let mut bytes = zip(&[("01.png", &[0; 100_000], true)]);
+        fs::write(&path, &bytes).unwrap();
+        let archive = Archive::open(&path).unwrap();
+        assert!(matches!(
+            archive.read("01.png", 1000),
+            Err(ArchiveError::TooLarge(_))
+        ));
+
+        // The central directory claims far less than the entry inflates to
+        let directory = u32_at(&bytes, bytes.len() - 6) as usize;
+        bytes[directory + 24..directory + 28]
+            .copy_from_slice(&10u32.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let archive = Archive::open(&path).unwrap();
+        assert!(matches!(
+            archive.read("01.png", u64::MAX),
+            Err(ArchiveError::TooLarge(_))
+        ));
+
+        // Nor can the directory be longer than the file
+        let end = bytes.len() - END_LEN;
+        bytes[end + 12..end + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(Archive::open(&path), Err(ArchiveError::NotZip)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_virtual_paths_are_split_at_the_archive() {
+        let dir = std::env::temp_dir().join("ferrite-archive-locate");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.cbr");
+        fs::write(&path, b"Rar!\x1a\x07\x00 not really").unwrap();
+
+        let page = path.join("pages/01.png");
+        let (archive, name) = locate(&page).unwrap();
+        assert_eq!((archive, name.as_str()), (path.as_path(), "pages/01.png"));
+        assert_eq!(locate(&path).unwrap().1, "");
+        assert!(locate(&dir.join("book.png")).is_none());
+        assert!(matches!(Archive::open(&path), Err(ArchiveError::Rar)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,259 @@
+//! Registers Ferrite as a handler for the image types it was built with.
+//!
+//! On Linux a desktop entry with a `MimeType` list is installed into the
+//! user's applications directory and made the default through `xdg-mime`.
+//! On Windows the per-user registry gets a `Ferrite.Image` ProgID linked
+//! from every extension's `OpenWithProgids`. macOS takes file types from the
+//! app bundle's `Info.plist`, so only guidance is printed there.
+
+use directories::BaseDirs;
+use std::{
+    env,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::image::SupportedFormats;
+
+const DESKTOP_FILE: &str = "ferrite.desktop";
+const PROG_ID: &str = "Ferrite.Image";
+
+#[derive(Error, Debug)]
+pub enum AssociationError {
+    #[error("Failed to locate the Ferrite executable: {0}")]
+    Executable(io::Error),
+
+    #[error("No home directory to install associations into")]
+    NoHome,
+
+    #[error("Failed to write association files: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("{command} failed: {stderr}")]
+    Command { command: String, stderr: String },
+}
+
+/// Registers the associations and returns a summary for the user.
+pub fn install() -> Result<String, AssociationError> {
+    let exe = env::current_exe().map_err(AssociationError::Executable)?;
+    if cfg!(windows) {
+        for args in registry_commands(&exe, true) {
+            run("reg", &args)?;
+        }
+        Ok(format!(
+            "Registered {} for {} in the current user's registry. Pick it \
+             under \"Open with\" to make it the default.",
+            PROG_ID,
+            SupportedFormats::EXTENSIONS.join(", ")
+        ))
+    } else if cfg!(target_os = "macos") {
+        Ok(info_plist_guidance())
+    } else {
+        let path = desktop_file_path()?;
+        fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))?;
+        fs::write(&path, desktop_entry(&exe))?;
+        info!("Wrote {}", path.display());
+
+        refresh_desktop_database(&path);
+        let mut args = vec!["default".to_string(), DESKTOP_FILE.to_string()];
+        args.extend(
+            SupportedFormats::mime_types()
+                .iter()
+                .map(|m| m.to_string()),
+        );
+        if let Err(e) = run("xdg-mime", &args) {
+            warn!("Could not set Ferrite as the default viewer: {}", e);
+        }
+        Ok(format!(
+            "Installed {} for {}",
+            path.display(),
+            SupportedFormats::mime_types().join(", ")
+        ))
+    }
+}
+
+/// Undoes [`install`].
+pub fn uninstall() -> Result<String, AssociationError> {
+    let exe = env::current_exe().map_err(AssociationError::Executable)?;
+    if cfg!(windows) {
+        for args in registry_commands(&exe, false) {
+            // Extensions that were never registered have nothing to delete
+            if let Err(e) = run("reg", &args) {
+                warn!("{}", e);
+            }
+        }
+        Ok(format!("Removed {} from the registry", PROG_ID))
+    } else if cfg!(target_os = "macos") {
+        Ok("File types come from the app bundle; remove Ferrite.app to \
+            unregister them."
+            .to_string())
+    } else {
+        let path = desktop_file_path()?;
+        match fs::remove_file(&path) {
+            Ok(()) => {},
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e.into()),
+        }
+        refresh_desktop_database(&path);
+        remove_mime_defaults()?;
+        Ok(format!("Removed {}", path.display()))
+    }
+}
+
+fn desktop_file_path() -> Result<PathBuf, AssociationError> {
+    let dirs = BaseDirs::new().ok_or(AssociationError::NoHome)?;
+    Ok(dirs
+        .data_dir()
+        .join("applications")
+        .join(DESKTOP_FILE))
+}
+
+fn desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\nType=Application\nName=Ferrite\nGenericName=Image \
+         Viewer\nComment=Fast and efficient image viewer\nExec=\"{}\" \
+         %f\nTerminal=false\nCategories=Graphics;Viewer;\nMimeType={};\n",
+        exe.display(),
+        SupportedFormats::mime_types().join(";")
+    )
+}
+
+fn refresh_desktop_database(desktop_file: &Path) {
+    if let Some(dir) = desktop_file.parent() {
+        let dir = dir.display().to_string();
+        if let Err(e) = run("update-desktop-database", &[dir]) {
+            warn!("Could not refresh the desktop database: {}", e);
+        }
+    }
+}
+
+/// Drops the defaults `xdg-mime` recorded for Ferrite from `mimeapps.list`.
+fn remove_mime_defaults() -> Result<(), AssociationError> {
+    let dirs = BaseDirs::new().ok_or(AssociationError::NoHome)?;
+    let path = dirs.config_dir().join("mimeapps.list");
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let kept: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.ends_with(&format!("={}", DESKTOP_FILE)))
+        .collect();
+    fs::write(&path, kept.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// `reg` argument lists that add or delete the ProgID and its extensions.
+fn registry_commands(exe: &Path, install: bool) -> Vec<Vec<String>> {
+    let classes = r"HKCU\Software\Classes";
+    let prog_id = format!(r"{}\{}", classes, PROG_ID);
+    let extension_key =
+        |ext: &str| format!(r"{}\.{}\OpenWithProgids", classes, ext);
+    let args = |parts: &[&str]| parts.iter().map(|p| p.to_string()).collect();
+
+    let mut commands = Vec::new();
+    if install {
+        let open = format!("\"{}\" \"%1\"", exe.display());
+        commands.push(args(&["add", &prog_id, "/ve", "/d", "Image", "/f"]));
+        commands.push(args(&[
+            "add",
+            &format!(r"{}\shell\open\command", prog_id),
+            "/ve",
+            "/d",
+            &open,
+            "/f",
+        ]));
+        for ext in SupportedFormats::EXTENSIONS {
+            commands.push(args(&[
+                "add",
+                &extension_key(ext),
+                "/v",
+                PROG_ID,
+                "/t",
+                "REG_NONE",
+                "/f",
+            ]));
+        }
+    } else {
+        commands.push(args(&["delete", &prog_id, "/f"]));
+        for ext in SupportedFormats::EXTENSIONS {
+            commands.push(args(&[
+                "delete",
+                &extension_key(ext),
+                "/v",
+                PROG_ID,
+                "/f",
+            ]));
+        }
+    }
+    commands
+}
+
+fn info_plist_guidance() -> String {
+    let extensions: String = SupportedFormats::EXTENSIONS
+        .iter()
+        .map(|ext| format!("                <string>{}</string>\n", ext))
+        .collect();
+    format!(
+        "macOS reads file associations from the app bundle. Add this to \
+         Ferrite.app/Contents/Info.plist, then register the bundle with\n  \
+         /System/Library/Frameworks/CoreServices.framework/Frameworks/\
+         LaunchServices.framework/Support/lsregister -f Ferrite.app\n\n\
+         <key>CFBundleDocumentTypes</key>\n<array>\n    <dict>\n        \
+         <key>CFBundleTypeName</key>\n        <string>Image</string>\n        \
+         <key>CFBundleTypeRole</key>\n        <string>Viewer</string>\n        \
+         <key>CFBundleTypeExtensions</key>\n        <array>\n{}        \
+         </array>\n    </dict>\n</array>\n",
+        extensions
+    )
+}
+
+fn run(program: &str, args: &[String]) -> Result<(), AssociationError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| AssociationError::Command {
+            command: program.to_string(),
+            stderr:  e.to_string(),
+        })?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AssociationError::Command {
+            command: format!("{} {}", program, args.join(" ")),
+            stderr:  String::from_utf8_lossy(&output.stderr)
+                .trim()
+                .to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_entry_lists_mime_types() {
+        let entry = desktop_entry(Path::new("/usr/bin/ferrite"));
+        assert!(entry.contains("Exec=\"/usr/bin/ferrite\" %f"));
+        let mimes = entry
+            .lines()
+            .find_map(|line| line.strip_prefix("MimeType="))
+            .unwrap();
+        assert!(mimes.split(';').any(|m| m == "image/png"));
+        assert!(mimes.ends_with(';'));
+    }
+
+    #[test]
+    fn test_registry_uninstall_mirrors_install() {
+        let exe = Path::new(r"C:\Ferrite\ferrite.exe");
+        let installed = registry_commands(exe, true);
+        let removed = registry_commands(exe, false);
+        // The ProgID and its open command are removed by one delete
+        assert_eq!(installed.len(), removed.len() + 1);
+        assert!(removed.iter().all(|args| args[0] == "delete"));
+    }
+}
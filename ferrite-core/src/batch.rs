@@ -0,0 +1,386 @@
+//! Batch processing of several images: resize, rotate, watermark and
+//! convert, in that order, writing the results as new files.
+//!
+//! Files are processed in parallel on the rayon pool. Every finished file
+//! is reported over a channel so the UI can show progress and collect the
+//! errors.
+
+use ferrite_config::{BatchConfig, DecodingConfig, IoConfig};
+use image::{
+    imageops::FilterType,
+    DynamicImage,
+    ImageError,
+    ImageFormat,
+    RgbaImage,
+};
+use rayon::prelude::*;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{
+    image::{self as images, ImageLoadError},
+    linear,
+};
+
+#[derive(Error, Debug)]
+pub enum BatchError {
+    #[error(transparent)]
+    Load(#[from] ImageLoadError),
+
+    #[error("Failed to write result: {0}")]
+    Save(#[from] ImageError),
+
+    #[error("Failed to create output folder: {0}")]
+    OutputDir(std::io::Error),
+}
+
+/// Clockwise rotation applied to every image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] =
+        [Rotation::None, Rotation::Cw90, Rotation::Cw180, Rotation::Cw270];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Rotation::None => "None",
+            Rotation::Cw90 => "90° clockwise",
+            Rotation::Cw180 => "180°",
+            Rotation::Cw270 => "90° counter-clockwise",
+        }
+    }
+
+    fn apply(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Rotation::None => image,
+            Rotation::Cw90 => image.rotate90(),
+            Rotation::Cw180 => image.rotate180(),
+            Rotation::Cw270 => image.rotate270(),
+        }
+    }
+}
+
+/// Formats results can be written in; like decoding, each one other than
+/// PNG depends on a cargo feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    #[cfg(feature = "jpeg")]
+    Jpeg,
+    #[cfg(feature = "bmp")]
+    Bmp,
+    #[cfg(feature = "tiff")]
+    Tiff,
+}
+
+impl OutputFormat {
+    pub const AVAILABLE: &'static [OutputFormat] = &[
+        OutputFormat::Png,
+        #[cfg(feature = "jpeg")]
+        OutputFormat::Jpeg,
+        #[cfg(feature = "bmp")]
+        OutputFormat::Bmp,
+        #[cfg(feature = "tiff")]
+        OutputFormat::Tiff,
+    ];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            #[cfg(feature = "jpeg")]
+            OutputFormat::Jpeg => "jpg",
+            #[cfg(feature = "bmp")]
+            OutputFormat::Bmp => "bmp",
+            #[cfg(feature = "tiff")]
+            OutputFormat::Tiff => "tiff",
+        }
+    }
+
+    /// The format to write `path` in, told by its extension.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        let ext = match ext.as_str() {
+            "jpeg" => "jpg",
+            "tif" => "tiff",
+            ext => ext,
+        };
+        Self::AVAILABLE
+            .iter()
+            .copied()
+            .find(|format| format.extension() == ext)
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            #[cfg(feature = "jpeg")]
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            #[cfg(feature = "bmp")]
+            OutputFormat::Bmp => ImageFormat::Bmp,
+            #[cfg(feature = "tiff")]
+            OutputFormat::Tiff => ImageFormat::Tiff,
+        }
+    }
+}
+
+/// What to do with every file of a batch
+#[derive(Debug, Clone, Default)]
+pub struct BatchJob {
+    /// Shrink so the longer side is at most this many pixels
+    pub resize:       Option<u32>,
+    pub rotation:     Rotation,
+    /// Stamp the configured watermark image
+    pub watermark:    bool,
+    pub format:       OutputFormat,
+    /// Resample and blend in linear light
+    pub linear_light: bool,
+}
+
+/// Progress reported for every file of a batch
+pub enum BatchEvent {
+    Done {
+        output: PathBuf,
+    },
+    Failed {
+        source: PathBuf,
+        error:  String,
+    },
+    /// All files have been handled
+    Finished,
+}
+
+/// Processes `paths` on the rayon pool, reporting each file on `events`.
+pub fn run(
+    paths: Vec<PathBuf>,
+    job: BatchJob,
+    config: BatchConfig,
+    decoding: DecodingConfig,
+    io: IoConfig,
+    events: Sender<BatchEvent>,
+) {
+    let watermark = match (job.watermark, &config.watermark) {
+        (true, Some(path)) => match images::decode_file(path, &decoding, &io) {
+            Ok(image) => Some(image.to_rgba8()),
+            Err(e) => {
+                let _ = events.send(BatchEvent::Failed {
+                    source: path.clone(),
+                    error:  format!("Watermark: {}", e),
+                });
+                let _ = events.send(BatchEvent::Finished);
+                return;
+            },
+        },
+        _ => None,
+    };
+
+    info!("Processing {} files", paths.len());
+    paths
+        .into_par_iter()
+        .for_each_with(events.clone(), |events, source| {
+            let result = process_file(
+                &source,
+                &job,
+                watermark.as_ref(),
+                &config,
+                &decoding,
+                &io,
+            );
+            let _ = events.send(match result {
+                Ok(output) => BatchEvent::Done {
+                    output,
+                },
+                Err(e) => {
+                    warn!("Batch: {} failed: {}", source.display(), e);
+                    BatchEvent::Failed {
+                        source,
+                        error: e.to_string(),
+                    }
+                },
+            });
+        });
+    let _ = events.send(BatchEvent::Finished);
+}
+
+fn process_file(
+    source: &Path,
+    job: &BatchJob,
+    watermark: Option<&RgbaImage>,
+    config: &BatchConfig,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<PathBuf, BatchError> {
+    let image = images::decode_file(source, decoding, io)?;
+    let result = process(image, job, watermark, config);
+
+    let output = output_path(source, config.output_dir.as_deref(), job.format);
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir).map_err(BatchError::OutputDir)?;
+    }
+    save(&result, &output, job.format, config.jpeg_quality)?;
+    Ok(output)
+}
+
+/// Applies the steps of `job` to one image.
+pub fn process(
+    image: DynamicImage,
+    job: &BatchJob,
+    watermark: Option<&RgbaImage>,
+    config: &BatchConfig,
+) -> DynamicImage {
+    let image = match job.resize {
+        Some(max) if image.width().max(image.height()) > max => linear::resize(
+            &image,
+            max,
+            max,
+            FilterType::Lanczos3,
+            job.linear_light,
+        ),
+        _ => image,
+    };
+    let image = job.rotation.apply(image);
+    match watermark {
+        Some(mark) => DynamicImage::ImageRgba8(stamp(
+            image.to_rgba8(),
+            mark,
+            config.watermark_scale,
+            config.watermark_opacity,
+            job.linear_light,
+        )),
+        None => image,
+    }
+}
+
+/// Blends `mark` into the bottom right corner, scaled to `scale` of the
+/// image width.
+fn stamp(
+    mut image: RgbaImage,
+    mark: &RgbaImage,
+    scale: f32,
+    opacity: f32,
+    linear: bool,
+) -> RgbaImage {
+    let width = ((image.width() as f32 * scale).round() as u32).max(1);
+    let height = ((mark.height() as f32 * width as f32 / mark.width() as f32)
+        .round() as u32)
+        .max(1);
+    let mut mark = linear::resize_exact(
+        &DynamicImage::ImageRgba8(mark.clone()),
+        width,
+        height,
+        FilterType::Triangle,
+        linear,
+    )
+    .into_rgba8();
+    for pixel in mark.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    let margin = (image.width() / 50) as i64;
+    let x = image.width() as i64 - width as i64 - margin;
+    let y = image.height() as i64 - height as i64 - margin;
+    linear::overlay(&mut image, &mark, x, y, linear);
+    image
+}
+
+/// Where the result for `source` is written: `output_dir`, or a
+/// `converted` folder next to the source.
+pub fn output_path(
+    source: &Path,
+    output_dir: Option<&Path>,
+    format: OutputFormat,
+) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    let dir = output_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| {
+            source
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+                .join("converted")
+        });
+    dir.join(format!("{}.{}", stem, format.extension()))
+}
+
+#[cfg_attr(not(feature = "jpeg"), allow(unused_variables))]
+pub(crate) fn save(
+    image: &DynamicImage,
+    path: &Path,
+    format: OutputFormat,
+    jpeg_quality: u8,
+) -> Result<(), ImageError> {
+    match format {
+        #[cfg(feature = "jpeg")]
+        OutputFormat::Jpeg => {
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            // JPEG has no alpha channel
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(
+                image::codecs::jpeg::JpegEncoder::new_with_quality(
+                    file,
+                    jpeg_quality,
+                ),
+            )
+        },
+        _ => image.save_with_format(path, format.image_format()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_resize_rotate_and_watermark() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            400,
+            200,
+            Rgba([0, 0, 0, 255]),
+        ));
+        let mark = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+        let job = BatchJob {
+            resize: Some(100),
+            rotation: Rotation::Cw90,
+            watermark: true,
+            ..BatchJob::default()
+        };
+        let config = BatchConfig {
+            watermark_opacity: 1.0,
+            ..BatchConfig::default()
+        };
+
+        let result = process(image, &job, Some(&mark), &config).to_rgba8();
+        assert_eq!(result.dimensions(), (50, 100));
+        // Watermark in the bottom right corner, untouched top left
+        assert_eq!(result.get_pixel(45, 95), &Rgba([255, 255, 255, 255]));
+        assert_eq!(result.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_output_path() {
+        let source = Path::new("/photos/cat.jpeg");
+        assert_eq!(
+            output_path(source, None, OutputFormat::Png),
+            PathBuf::from("/photos/converted/cat.png")
+        );
+        assert_eq!(
+            output_path(source, Some(Path::new("/out")), OutputFormat::Png),
+            PathBuf::from("/out/cat.png")
+        );
+    }
+}
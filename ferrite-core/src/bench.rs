@@ -0,0 +1,348 @@
+//! Timing the decoding of a folder of images, to catch loader regressions.
+//!
+//! Every image is read and decoded one after another, exactly as the viewer
+//! opens it, and the times are summed up per format. The decoded images are
+//! then replayed through a cache with the configured budget, newest first
+//! as when stepping back through the folder, to show how much of it the
+//! cache keeps. With sandboxed decoding the memory of the decoding worker
+//! is not in the peak reported.
+
+use ferrite_config::FerriteConfig;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use crate::{
+    image::{self as images, ImageCache},
+    navigation,
+};
+
+const MB: f64 = 1024.0 * 1024.0;
+
+#[derive(Error, Debug)]
+pub enum BenchError {
+    #[error("No supported images in {0}")]
+    NoImages(PathBuf),
+
+    #[error("Failed to write the report: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Decoding times of the files of one format
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FormatStats {
+    /// Extension, in lower case
+    pub format:         String,
+    pub files:          usize,
+    pub failed:         usize,
+    pub total_ms:       f64,
+    pub mean_ms:        f64,
+    pub min_ms:         f64,
+    pub max_ms:         f64,
+    /// Megapixels decoded per second
+    pub megapixels_sec: f64,
+    /// Largest decoded image, in MB of pixels
+    pub largest_mb:     f64,
+}
+
+/// How the decoded images fared in a cache of the configured size
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub budget_mb: u64,
+    /// Images still cached when stepping back through the folder
+    pub hits:      usize,
+    /// Images that would have been decoded again
+    pub misses:    usize,
+    /// Decoded size of the images the cache held after the first pass
+    pub held_mb:   f64,
+}
+
+/// A file that could not be decoded
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub path:  PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub folder:         PathBuf,
+    pub files:          usize,
+    pub total_ms:       f64,
+    pub formats:        Vec<FormatStats>,
+    pub cache:          CacheStats,
+    /// Largest resident size of the process, where the system tells it
+    pub peak_memory_mb: Option<f64>,
+    pub failures:       Vec<Failure>,
+}
+
+/// Decodes every image in `folder` and its subfolders, calling `progress`
+/// with the number done and the number found after each one.
+pub fn run(
+    folder: &Path,
+    config: &FerriteConfig,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<BenchReport, BenchError> {
+    let paths =
+        navigation::collect_images(&[folder.to_path_buf()], &config.navigation);
+    if paths.is_empty() {
+        return Err(BenchError::NoImages(folder.to_path_buf()));
+    }
+
+    let mut times: BTreeMap<String, (FormatStats, Vec<Duration>, u64)> =
+        BTreeMap::new();
+    let mut failures = Vec::new();
+    let mut cache = ImageCache::new(config.cache.memory_mb * 1024 * 1024);
+    let start = Instant::now();
+    for (done, path) in paths.iter().enumerate() {
+        let format = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let (stats, durations, pixels) =
+            times.entry(format.clone()).or_insert_with(|| {
+                (
+                    FormatStats {
+                        format,
+                        ..FormatStats::default()
+                    },
+                    Vec::new(),
+                    0,
+                )
+            });
+        stats.files += 1;
+
+        let decode = Instant::now();
+        match images::decode_file(path, &config.decoding, &config.io) {
+            Ok(image) => {
+                durations.push(decode.elapsed());
+                *pixels += image.width() as u64 * image.height() as u64;
+                stats.largest_mb = stats
+                    .largest_mb
+                    .max(image.as_bytes().len() as f64 / MB);
+                cache.insert(path.clone(), path.clone(), Arc::new(image));
+            },
+            Err(e) => {
+                stats.failed += 1;
+                failures.push(Failure {
+                    path:  path.clone(),
+                    error: e.to_string(),
+                });
+            },
+        }
+        progress(done + 1, paths.len());
+    }
+    let total = start.elapsed();
+
+    let mut cache_stats = CacheStats {
+        budget_mb: config.cache.memory_mb,
+        held_mb: cache.bytes() as f64 / MB,
+        ..CacheStats::default()
+    };
+    for path in paths.iter().rev() {
+        if cache.get(path).is_some() {
+            cache_stats.hits += 1;
+        } else {
+            cache_stats.misses += 1;
+        }
+    }
+
+    Ok(BenchReport {
+        folder: folder.to_path_buf(),
+        files: paths.len(),
+        total_ms: millis(total),
+        formats: times
+            .into_values()
+            .map(|(stats, durations, pixels)| {
+                summarize(stats, &durations, pixels)
+            })
+            .collect(),
+        cache: cache_stats,
+        peak_memory_mb: peak_memory().map(|bytes| bytes as f64 / MB),
+        failures,
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn summarize(
+    mut stats: FormatStats,
+    durations: &[Duration],
+    pixels: u64,
+) -> FormatStats {
+    let total: Duration = durations.iter().sum();
+    stats.total_ms = millis(total);
+    if let (Some(min), Some(max)) =
+        (durations.iter().min(), durations.iter().max())
+    {
+        stats.min_ms = millis(*min);
+        stats.max_ms = millis(*max);
+        stats.mean_ms = stats.total_ms / durations.len() as f64;
+    }
+    if total > Duration::ZERO {
+        stats.megapixels_sec = pixels as f64 / 1e6 / total.as_secs_f64();
+    }
+    stats
+}
+
+/// Peak resident size of the process in bytes
+#[cfg(target_os = "linux")]
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_memory() -> Option<u64> {
+    None
+}
+
+impl BenchReport {
+    pub fn to_json(&self) -> Result<String, BenchError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The report as a plain text table
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{} images in {} decoded in {:.0} ms\n\n{:<8} {:>6} {:>6} {:>10} \
+             {:>9} {:>9} {:>9} {:>8} {:>10}\n",
+            self.files,
+            self.folder.display(),
+            self.total_ms,
+            "format",
+            "files",
+            "failed",
+            "total ms",
+            "mean ms",
+            "min ms",
+            "max ms",
+            "MP/s",
+            "largest MB"
+        );
+        for stats in &self.formats {
+            table.push_str(&format!(
+                "{:<8} {:>6} {:>6} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>8.1} \
+                 {:>10.1}\n",
+                stats.format,
+                stats.files,
+                stats.failed,
+                stats.total_ms,
+                stats.mean_ms,
+                stats.min_ms,
+                stats.max_ms,
+                stats.megapixels_sec,
+                stats.largest_mb
+            ));
+        }
+        table.push_str(&format!(
+            "\nCache of {} MB held {:.1} MB; stepping back hit {} and missed \
+             {} images\n",
+            self.cache.budget_mb,
+            self.cache.held_mb,
+            self.cache.hits,
+            self.cache.misses
+        ));
+        match self.peak_memory_mb {
+            Some(peak) => {
+                table.push_str(&format!("Peak memory {:.1} MB\n", peak))
+            },
+            None => table.push_str("Peak memory not available here\n"),
+        }
+        for failure in &self.failures {
+            table.push_str(&format!(
+                "Failed {}: {}\n",
+                failure.path.display(),
+                failure.error
+            ));
+        }
+        table.truncate(table.trim_end().len());
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use std::fs;
+
+    /// A folder of three 100x100 PNG files and a broken one
+    fn folder(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            RgbaImage::from_pixel(100, 100, Rgba([1, 2, 3, 255]))
+                .save(dir.join(name))
+                .unwrap();
+        }
+        fs::write(dir.join("broken.png"), b"\x89PNG\r\n\x1a\nbroken").unwrap();
+        dir
+    }
+
+    fn config(cache_mb: u64) -> FerriteConfig {
+        let mut config = FerriteConfig::default();
+        // The test binary cannot act as the decoding worker
+        config.decoding.sandboxed = false;
+        config.cache.memory_mb = cache_mb;
+        config
+    }
+
+    #[test]
+    fn test_formats_are_timed_and_failures_listed() {
+        let dir = folder("ferrite-bench-formats");
+        let mut calls = Vec::new();
+        let report =
+            run(&dir, &config(64), |done, found| calls.push((done, found)))
+                .unwrap();
+
+        assert_eq!(report.files, 4);
+        assert_eq!(calls.last(), Some(&(4, 4)));
+        assert_eq!(report.formats.len(), 1);
+        let png = &report.formats[0];
+        assert_eq!((png.format.as_str(), png.files, png.failed), ("png", 4, 1));
+        assert!(png.min_ms <= png.mean_ms && png.mean_ms <= png.max_ms);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].path.ends_with("broken.png"));
+        // All three fit in the cache
+        assert_eq!((report.cache.hits, report.cache.misses), (3, 1));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["formats"][0]["files"], 4);
+        assert!(report.table().contains("png"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            run(&dir, &config(64), |_, _| {}),
+            Err(BenchError::NoImages(_))
+        ));
+    }
+
+    #[test]
+    fn test_small_cache_misses_when_stepping_back() {
+        let dir = folder("ferrite-bench-cache");
+        let report = run(&dir, &config(0), |_, _| {}).unwrap();
+        assert_eq!(report.cache.held_mb, 0.0);
+        assert_eq!((report.cache.hits, report.cache.misses), (0, 4));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
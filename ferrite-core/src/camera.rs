@@ -0,0 +1,136 @@
+//! The 2D camera of the image view: a uniform scale and a translation that
+//! take image points to the screen.
+//!
+//! Image points are displayed image pixels measured from the image center;
+//! screen points are measured from the center of the view. A camera without
+//! translation shows the image centered, so a new scale only needs a new
+//! translation to keep a chosen point in place.
+
+use eframe::egui::{Pos2, Rect, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Screen points per image pixel
+    pub scale:       f64,
+    /// Where the image center lands, from the center of the view
+    pub translation: Vec2,
+}
+
+impl Transform {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            translation: Vec2::ZERO,
+        }
+    }
+
+    pub fn to_screen(self, point: Vec2) -> Vec2 {
+        self.translation + point * self.scale as f32
+    }
+
+    pub fn to_image(self, screen: Vec2) -> Vec2 {
+        (screen - self.translation) / self.scale as f32
+    }
+
+    /// Changes the scale while the image point under `anchor` stays there.
+    pub fn zoom_about(&mut self, anchor: Vec2, scale: f64) {
+        let point = self.to_image(anchor);
+        self.scale = scale;
+        self.translation = anchor - point * scale as f32;
+    }
+
+    /// The view `t` of the way from this one to `to`. Both parts move
+    /// linearly, which keeps the anchor of a zoom between them fixed.
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        Self {
+            scale:       self.scale + (to.scale - self.scale) * t,
+            translation: self.translation
+                + (to.translation - self.translation) * t as f32,
+        }
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.translation += delta;
+    }
+
+    /// Where an image of `size` pixels is drawn in a view centered on
+    /// `center`.
+    pub fn image_rect(&self, center: Pos2, size: Vec2) -> Rect {
+        Rect::from_center_size(
+            center + self.translation,
+            size * self.scale as f32,
+        )
+    }
+
+    /// Pulls the image back until at least `margin` points of it overlap a
+    /// view of `view` size. Along a side where it fits in the view it is
+    /// centered.
+    pub fn keep_in_view(&mut self, image_size: Vec2, view: Vec2, margin: f32) {
+        let scaled = image_size * self.scale as f32;
+        let limit = (view + scaled) / 2.0 - Vec2::splat(margin);
+        let (x, y) = (scaled.x > view.x, scaled.y > view.y);
+        let limit = Vec2::new(
+            if x { limit.x } else { 0.0 },
+            if y { limit.y } else { 0.0 },
+        );
+        self.translation = self.translation.clamp(-limit, limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_zoom_keeps_the_anchor_fixed() {
+        let mut camera = Transform::new(0.5);
+        camera.pan(Vec2::new(30.0, -12.0));
+        // Near the edge of the view, where zooming used to drift
+        let anchor = Vec2::new(390.0, -290.0);
+        let under_pointer = camera.to_image(anchor);
+        for scale in [0.55, 0.605, 3.0, 0.1, 0.5] {
+            camera.zoom_about(anchor, scale);
+            assert_near(camera.to_image(anchor), under_pointer);
+            assert_near(camera.to_screen(under_pointer), anchor);
+        }
+        // Zooming back out ends where it started
+        assert_near(camera.translation, Vec2::new(30.0, -12.0));
+
+        // So do the views in between when animating a zoom
+        let mut zoomed = camera;
+        zoomed.zoom_about(anchor, 4.0);
+        for t in [0.0, 0.3, 0.8, 1.0] {
+            let between = camera.lerp(zoomed, t);
+            assert_near(between.to_image(anchor), under_pointer);
+        }
+
+        let rect =
+            camera.image_rect(Pos2::new(400.0, 300.0), Vec2::new(200.0, 100.0));
+        assert_near(rect.center().to_vec2(), Vec2::new(430.0, 288.0));
+        assert_near(rect.size(), Vec2::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn test_panning_stops_short_of_losing_the_image() {
+        let view = Vec2::new(800.0, 600.0);
+        let mut camera = Transform::new(2.0);
+        camera.pan(Vec2::new(5000.0, -5000.0));
+        camera.keep_in_view(Vec2::new(600.0, 400.0), view, 50.0);
+        // 50 points of the 1200x800 image still overlap the view
+        assert_near(camera.translation, Vec2::new(950.0, -650.0));
+
+        // Images smaller than the view stay centered along that side
+        let mut camera = Transform::new(1.0);
+        camera.pan(Vec2::new(1000.0, 40.0));
+        camera.keep_in_view(Vec2::new(20.0, 20.0), view, 50.0);
+        assert_near(camera.translation, Vec2::ZERO);
+        let mut camera = Transform::new(1.0);
+        camera.pan(Vec2::new(1000.0, 40.0));
+        camera.keep_in_view(Vec2::new(1000.0, 20.0), view, 50.0);
+        assert_near(camera.translation, Vec2::new(850.0, 0.0));
+    }
+}
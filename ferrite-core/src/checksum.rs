@@ -0,0 +1,194 @@
+//! File checksums and verification against `sha256sum`-style manifests.
+//!
+//! Hashing streams the file so large RAW files and videos do not have to be
+//! held in memory.
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// MD5 and SHA-256 digests of a file as lowercase hex strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChecksums {
+    pub md5:    String,
+    pub sha256: String,
+}
+
+/// Outcome of checking a single manifest entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Pass,
+    Fail,
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyEntry {
+    pub path:   PathBuf,
+    pub status: VerifyStatus,
+}
+
+/// Computes both digests in a single pass over the file.
+#[instrument(skip_all, fields(path = %path.display()))]
+pub fn hash_file(path: &Path) -> io::Result<FileChecksums> {
+    let mut file = File::open(path)?;
+    let mut md5 = Md5::new();
+    let mut sha256 = Sha256::new();
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        md5.update(&buffer[..read]);
+        sha256.update(&buffer[..read]);
+    }
+
+    Ok(FileChecksums {
+        md5:    to_hex(&md5.finalize()),
+        sha256: to_hex(&sha256.finalize()),
+    })
+}
+
+/// Parses `sha256sum` output: one `<hex>  <name>` entry per line, with an
+/// optional `*` marking binary mode before the name. Lines that do not look
+/// like an entry are skipped.
+pub fn parse_manifest(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let (digest, name) =
+                line.trim_end().split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            let valid = digest.len() == 64
+                && digest.chars().all(|c| c.is_ascii_hexdigit());
+            (valid && !name.is_empty())
+                .then(|| (digest.to_ascii_lowercase(), name.to_string()))
+        })
+        .collect()
+}
+
+/// Looks for a `.sha256` manifest in `folder`.
+pub fn find_manifest(folder: &Path) -> Option<PathBuf> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(folder)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("sha256"))
+        })
+        .collect();
+    manifests.sort();
+    manifests.into_iter().next()
+}
+
+/// Checks every file listed in the manifest. Names are resolved relative to
+/// the manifest's folder.
+#[instrument(skip_all, fields(manifest = %manifest.display()))]
+pub fn verify_manifest(manifest: &Path) -> io::Result<Vec<VerifyEntry>> {
+    let text = fs::read_to_string(manifest)?;
+    let folder = manifest.parent().unwrap_or(Path::new("."));
+
+    let entries: Vec<VerifyEntry> = parse_manifest(&text)
+        .into_iter()
+        .map(|(expected, name)| {
+            let path = folder.join(name);
+            let status = match hash_file(&path) {
+                Ok(sums) if sums.sha256 == expected => VerifyStatus::Pass,
+                Ok(_) => VerifyStatus::Fail,
+                Err(_) => VerifyStatus::Missing,
+            };
+            VerifyEntry {
+                path,
+                status,
+            }
+        })
+        .collect();
+
+    let passed = entries
+        .iter()
+        .filter(|e| e.status == VerifyStatus::Pass)
+        .count();
+    info!("Verified {}/{} files", passed, entries.len());
+    Ok(entries)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str =
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn test_hash_file() {
+        let dir = std::env::temp_dir().join("ferrite-checksum-hash");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hello.txt");
+        fs::write(&path, b"hello").unwrap();
+
+        let sums = hash_file(&path).unwrap();
+        assert_eq!(sums.md5, "5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(sums.sha256, HELLO_SHA256);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let text = format!(
+            "{HELLO_SHA256}  a.jpg\n{}  *b c.png\n# comment\nshort  x.jpg\n",
+            HELLO_SHA256.to_uppercase()
+        );
+        let entries = parse_manifest(&text);
+        assert_eq!(entries, vec![
+            (HELLO_SHA256.to_string(), "a.jpg".to_string()),
+            (HELLO_SHA256.to_string(), "b c.png".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_verify_manifest() {
+        let dir = std::env::temp_dir().join("ferrite-checksum-verify");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("good.jpg"), b"hello").unwrap();
+        fs::write(dir.join("bad.jpg"), b"tampered").unwrap();
+        let manifest = dir.join("photos.sha256");
+        fs::write(
+            &manifest,
+            format!(
+                "{HELLO_SHA256}  good.jpg\n{HELLO_SHA256}  \
+                 bad.jpg\n{HELLO_SHA256}  gone.jpg\n"
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(find_manifest(&dir), Some(manifest.clone()));
+        let statuses: Vec<VerifyStatus> = verify_manifest(&manifest)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.status)
+            .collect();
+        assert_eq!(statuses, vec![
+            VerifyStatus::Pass,
+            VerifyStatus::Fail,
+            VerifyStatus::Missing
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
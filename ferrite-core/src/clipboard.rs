@@ -0,0 +1,28 @@
+//! Image access to the system clipboard. Text goes through egui instead.
+
+use arboard::{Clipboard, ImageData};
+use image::{DynamicImage, RgbaImage};
+use std::borrow::Cow;
+
+pub use arboard::Error as ClipboardError;
+
+pub fn copy_image(image: &DynamicImage) -> Result<(), ClipboardError> {
+    let rgba = image.to_rgba8();
+    Clipboard::new()?.set_image(ImageData {
+        width:  rgba.width() as usize,
+        height: rgba.height() as usize,
+        bytes:  Cow::Owned(rgba.into_raw()),
+    })
+}
+
+/// Reads the image currently on the clipboard.
+pub fn paste_image() -> Result<DynamicImage, ClipboardError> {
+    let data = Clipboard::new()?.get_image()?;
+    RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+    .map(DynamicImage::ImageRgba8)
+    .ok_or(ClipboardError::ConversionFailure)
+}
@@ -0,0 +1,314 @@
+//! Scanline decoder for EAN-13 (and therefore UPC-A) barcodes.
+//!
+//! Rows of the image are binarized and turned into runs of dark and light
+//! pixels. A barcode is a sequence of 59 runs (guards plus twelve encoded
+//! digits) spanning 95 modules, so every candidate run sequence is
+//! normalized to modules and matched against the digit tables.
+
+use image::GrayImage;
+
+use super::{CodeKind, DetectedCode};
+
+/// Module widths of the L (odd parity) digit codes. The G codes are the same
+/// widths reversed and the R codes are the same widths with inverted color.
+const DIGIT_WIDTHS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+/// Parity of the left six digits (true = even/G code) for each leading digit
+const FIRST_DIGIT_PARITY: [[bool; 6]; 10] = [
+    [false, false, false, false, false, false],
+    [false, false, true, false, true, true],
+    [false, false, true, true, false, true],
+    [false, false, true, true, true, false],
+    [false, true, false, false, true, true],
+    [false, true, true, false, false, true],
+    [false, true, true, true, false, false],
+    [false, true, false, true, false, true],
+    [false, true, false, true, true, false],
+    [false, true, true, false, true, false],
+];
+
+/// Number of runs making up a complete EAN-13 symbol
+const SYMBOL_RUNS: usize = 59;
+/// Number of modules making up a complete EAN-13 symbol
+const SYMBOL_MODULES: f32 = 95.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    dark:   bool,
+    start:  u32,
+    length: u32,
+}
+
+/// Decodes all EAN-13 barcodes found on horizontal scanlines.
+pub fn scan(gray: &GrayImage) -> Vec<DetectedCode> {
+    let step = (gray.height() / 200).max(1);
+    let mut found: Vec<(String, f32, f32, f32, f32)> = Vec::new();
+
+    for y in (0..gray.height()).step_by(step as usize) {
+        let runs = row_runs(gray, y);
+        for (content, x0, x1) in decode_row(&runs) {
+            let yf = y as f32;
+            match found.iter_mut().find(|entry| entry.0 == content) {
+                Some(entry) => {
+                    entry.1 = entry.1.min(x0);
+                    entry.2 = entry.2.max(x1);
+                    entry.3 = entry.3.min(yf);
+                    entry.4 = entry.4.max(yf + step as f32);
+                },
+                None => found.push((content, x0, x1, yf, yf + step as f32)),
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(content, x0, x1, y0, y1)| DetectedCode {
+            kind: CodeKind::Ean13,
+            content,
+            outline: [(x0, y0), (x1, y0), (x1, y1), (x0, y1)],
+        })
+        .collect()
+}
+
+/// Binarizes one row around its midpoint luminance and collapses it to runs.
+fn row_runs(gray: &GrayImage, y: u32) -> Vec<Run> {
+    let row: Vec<u8> = (0..gray.width())
+        .map(|x| gray.get_pixel(x, y)[0])
+        .collect();
+    let (min, max) = row
+        .iter()
+        .fold((u8::MAX, u8::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+
+    // A row without contrast cannot contain a barcode
+    if max.saturating_sub(min) < 48 {
+        return Vec::new();
+    }
+    let threshold = ((min as u16 + max as u16) / 2) as u8;
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (x, &value) in row.iter().enumerate() {
+        let dark = value < threshold;
+        match runs.last_mut() {
+            Some(run) if run.dark == dark => run.length += 1,
+            _ => runs.push(Run {
+                dark,
+                start: x as u32,
+                length: 1,
+            }),
+        }
+    }
+    runs
+}
+
+/// Tries every dark run as the start of a symbol, in both reading
+/// directions. Returns the decoded digits and the horizontal extent.
+fn decode_row(runs: &[Run]) -> Vec<(String, f32, f32)> {
+    let mut results = Vec::new();
+    if runs.len() < SYMBOL_RUNS + 1 {
+        return results;
+    }
+
+    let reversed: Vec<Run> = runs.iter().rev().copied().collect();
+    for candidates in [runs, reversed.as_slice()] {
+        let mut i = 1;
+        while i + SYMBOL_RUNS <= candidates.len() {
+            let window = &candidates[i..i + SYMBOL_RUNS];
+            let quiet = candidates[i - 1];
+            if window[0].dark && !quiet.dark {
+                if let Some(digits) = decode_symbol(window, quiet) {
+                    let first = window[0];
+                    let last = window[SYMBOL_RUNS - 1];
+                    let x0 = first.start.min(last.start) as f32;
+                    let x1 = (first.start + first.length)
+                        .max(last.start + last.length)
+                        as f32;
+                    results.push((digits, x0, x1));
+                    i += SYMBOL_RUNS;
+                    continue;
+                }
+            }
+            i += 1;
+        }
+    }
+    results
+}
+
+fn decode_symbol(window: &[Run], quiet: Run) -> Option<String> {
+    let total: u32 = window.iter().map(|r| r.length).sum();
+    let module = total as f32 / SYMBOL_MODULES;
+    if module < 1.0 || (quiet.length as f32) < module * 3.0 {
+        return None;
+    }
+
+    // Start, middle and end guards are all single-module runs
+    let is_guard = |runs: &[Run]| {
+        runs.iter().all(|r| {
+            let width = r.length as f32 / module;
+            (0.4..=1.8).contains(&width)
+        })
+    };
+    if !is_guard(&window[0..3])
+        || !is_guard(&window[27..32])
+        || !is_guard(&window[56..59])
+    {
+        return None;
+    }
+
+    let mut digits = [0u8; 13];
+    let mut parity = [false; 6];
+    for n in 0..6 {
+        let runs = &window[3 + n * 4..7 + n * 4];
+        let (digit, even) = match_digit(runs, true)?;
+        digits[n + 1] = digit;
+        parity[n] = even;
+    }
+    for n in 0..6 {
+        let runs = &window[32 + n * 4..36 + n * 4];
+        let (digit, _) = match_digit(runs, false)?;
+        digits[n + 7] = digit;
+    }
+
+    digits[0] = FIRST_DIGIT_PARITY
+        .iter()
+        .position(|p| *p == parity)? as u8;
+
+    if !checksum_valid(&digits) {
+        return None;
+    }
+    Some(
+        digits
+            .iter()
+            .map(|d| char::from(b'0' + d))
+            .collect(),
+    )
+}
+
+/// Matches four runs against the digit tables. Left-half digits may use
+/// either parity; returns the digit and whether it was an even (G) code.
+fn match_digit(runs: &[Run], left_half: bool) -> Option<(u8, bool)> {
+    let sum: u32 = runs.iter().map(|r| r.length).sum();
+    let widths: Vec<f32> = runs
+        .iter()
+        .map(|r| r.length as f32 * 7.0 / sum as f32)
+        .collect();
+
+    let error = |pattern: &[u8; 4], reverse: bool| -> f32 {
+        (0..4)
+            .map(|k| {
+                let expected =
+                    if reverse { pattern[3 - k] } else { pattern[k] };
+                (widths[k] - expected as f32).abs()
+            })
+            .sum()
+    };
+
+    let mut best: Option<(u8, bool, f32)> = None;
+    for (digit, pattern) in DIGIT_WIDTHS.iter().enumerate() {
+        let parities: &[bool] =
+            if left_half { &[false, true] } else { &[false] };
+        for &even in parities {
+            let err = error(pattern, even);
+            if best.is_none_or(|(_, _, e)| err < e) {
+                best = Some((digit as u8, even, err));
+            }
+        }
+    }
+
+    best.filter(|(_, _, err)| *err < 1.5)
+        .map(|(digit, even, _)| (digit, even))
+}
+
+fn checksum_valid(digits: &[u8; 13]) -> bool {
+    let sum: u32 = digits[..12]
+        .iter()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 0 { d as u32 } else { d as u32 * 3 })
+        .sum();
+    (10 - sum % 10) % 10 == digits[12] as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// Renders an EAN-13 symbol as a list of modules (true = bar)
+    fn encode(code: &str) -> Vec<bool> {
+        let digits: Vec<u8> = code.bytes().map(|b| b - b'0').collect();
+        let mut modules = vec![true, false, true];
+
+        let push = |modules: &mut Vec<bool>, widths: [u8; 4], bar: bool| {
+            let mut color = bar;
+            for w in widths {
+                modules.extend(std::iter::repeat_n(color, w as usize));
+                color = !color;
+            }
+        };
+
+        let parity = FIRST_DIGIT_PARITY[digits[0] as usize];
+        for n in 0..6 {
+            let mut widths = DIGIT_WIDTHS[digits[n + 1] as usize];
+            if parity[n] {
+                widths.reverse();
+            }
+            push(&mut modules, widths, false);
+        }
+        modules.extend([false, true, false, true, false]);
+        for n in 0..6 {
+            push(&mut modules, DIGIT_WIDTHS[digits[n + 7] as usize], true);
+        }
+        modules.extend([true, false, true]);
+        modules
+    }
+
+    fn render(modules: &[bool], scale: u32) -> GrayImage {
+        let quiet = 10 * scale;
+        let width = modules.len() as u32 * scale + quiet * 2;
+        GrayImage::from_fn(width, 40, |x, _| {
+            let inside = x >= quiet && x < width - quiet;
+            if inside && modules[((x - quiet) / scale) as usize] {
+                Luma([20])
+            } else {
+                Luma([235])
+            }
+        })
+    }
+
+    #[test]
+    fn test_decodes_synthetic_barcode() {
+        let modules = encode("4006381333931");
+        assert_eq!(modules.len(), 95);
+
+        let codes = scan(&render(&modules, 3));
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].content, "4006381333931");
+        assert_eq!(codes[0].kind, CodeKind::Ean13);
+    }
+
+    #[test]
+    fn test_decodes_mirrored_barcode() {
+        let mut modules = encode("9780201379624");
+        modules.reverse();
+
+        let codes = scan(&render(&modules, 2));
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].content, "9780201379624");
+    }
+
+    #[test]
+    fn test_rejects_bad_checksum() {
+        let digits = [4, 0, 0, 6, 3, 8, 1, 3, 3, 3, 9, 3, 2];
+        assert!(!checksum_valid(&digits));
+    }
+}
@@ -0,0 +1,61 @@
+//! Detection of machine-readable codes (QR codes and linear barcodes) in
+//! decoded images.
+//!
+//! Detection works on a greyscale copy of the image and reports every code
+//! it finds together with its outline in image pixel coordinates, so the UI
+//! can draw it on top of the displayed image regardless of zoom and pan.
+
+use image::{DynamicImage, GrayImage};
+use tracing::{info, instrument};
+
+mod ean;
+mod qr;
+
+/// The symbology a detected code was decoded with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeKind {
+    Qr,
+    Ean13,
+}
+
+impl CodeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CodeKind::Qr => "QR",
+            CodeKind::Ean13 => "EAN-13",
+        }
+    }
+}
+
+/// A single decoded code and where it was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCode {
+    pub kind:    CodeKind,
+    pub content: String,
+    /// Corners of the code outline in image pixel coordinates, in drawing
+    /// order
+    pub outline: [(f32, f32); 4],
+}
+
+impl DetectedCode {
+    /// Returns true when the decoded content looks like something a browser
+    /// can open.
+    pub fn is_link(&self) -> bool {
+        let lower = self.content.to_lowercase();
+        lower.starts_with("http://")
+            || lower.starts_with("https://")
+            || lower.starts_with("mailto:")
+    }
+}
+
+/// Scans an image for all supported code types.
+#[instrument(skip_all, fields(width = image.width(), height = image.height()))]
+pub fn scan(image: &DynamicImage) -> Vec<DetectedCode> {
+    let gray: GrayImage = image.to_luma8();
+
+    let mut codes = qr::scan(&gray);
+    codes.extend(ean::scan(&gray));
+
+    info!("Code scan finished, found {} code(s)", codes.len());
+    codes
+}
@@ -0,0 +1,35 @@
+use image::GrayImage;
+use rqrr::PreparedImage;
+use tracing::debug;
+
+use super::{CodeKind, DetectedCode};
+
+/// Finds and decodes all QR codes in a greyscale image.
+pub fn scan(gray: &GrayImage) -> Vec<DetectedCode> {
+    let mut prepared = PreparedImage::prepare_from_greyscale(
+        gray.width() as usize,
+        gray.height() as usize,
+        |x, y| gray.get_pixel(x as u32, y as u32)[0],
+    );
+
+    prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| match grid.decode() {
+            Ok((_, content)) => {
+                let outline = grid
+                    .bounds
+                    .map(|point| (point.x as f32, point.y as f32));
+                Some(DetectedCode {
+                    kind: CodeKind::Qr,
+                    content,
+                    outline,
+                })
+            },
+            Err(e) => {
+                debug!("Found QR grid that failed to decode: {}", e);
+                None
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+//! Converting and thumbnailing files without a window, for scripts and for
+//! trying the decoding pipeline from the command line.
+//!
+//! Files are read and decoded exactly as the viewer opens them, with the
+//! same limits and sandbox, and written by the encoders of batch
+//! processing, so a file that fails here fails the same way in the window.
+
+use ferrite_config::FerriteConfig;
+use image::{DynamicImage, ImageError};
+use std::path::Path;
+use thiserror::Error;
+use tracing::info;
+
+use crate::{
+    batch::{self, OutputFormat},
+    image::{self as images, ImageLoadError},
+    linear,
+};
+
+#[derive(Error, Debug)]
+pub enum ConvertError {
+    #[error(transparent)]
+    Load(#[from] ImageLoadError),
+
+    #[error("Cannot write {0}: its extension names no format to write")]
+    Format(String),
+
+    #[error("Failed to write the result: {0}")]
+    Save(#[from] ImageError),
+
+    #[error("A thumbnail needs a size of at least 1 pixel")]
+    Size,
+}
+
+/// Writes `input` to `output` in the format its extension names, returning
+/// a summary for the user.
+pub fn convert(
+    input: &Path,
+    output: &Path,
+    config: &FerriteConfig,
+) -> Result<String, ConvertError> {
+    let format = output_format(output)?;
+    let image = images::decode_file(input, &config.decoding, &config.io)?;
+    write(&image, output, format, config)
+}
+
+/// Writes `input` to `output` shrunk to fit a square of `size` pixels, as
+/// the gallery makes its thumbnails. Smaller images are kept at their size.
+pub fn thumbnail(
+    input: &Path,
+    output: &Path,
+    size: u32,
+    config: &FerriteConfig,
+) -> Result<String, ConvertError> {
+    if size == 0 {
+        return Err(ConvertError::Size);
+    }
+    let format = output_format(output)?;
+    let image = images::decode_file(input, &config.decoding, &config.io)?;
+    let image = if image.width().max(image.height()) > size {
+        linear::thumbnail(&image, size, size, config.color.linear_light)
+    } else {
+        image
+    };
+    write(&image, output, format, config)
+}
+
+fn output_format(output: &Path) -> Result<OutputFormat, ConvertError> {
+    OutputFormat::for_path(output)
+        .ok_or_else(|| ConvertError::Format(output.display().to_string()))
+}
+
+fn write(
+    image: &DynamicImage,
+    output: &Path,
+    format: OutputFormat,
+    config: &FerriteConfig,
+) -> Result<String, ConvertError> {
+    batch::save(image, output, format, config.batch.jpeg_quality)?;
+    info!("Wrote {}", output.display());
+    Ok(format!(
+        "Wrote {} ({}x{})",
+        output.display(),
+        image.width(),
+        image.height()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use std::fs;
+
+    fn config() -> FerriteConfig {
+        let mut config = FerriteConfig::default();
+        // The test binary cannot act as the decoding worker
+        config.decoding.sandboxed = false;
+        config
+    }
+
+    #[test]
+    fn test_thumbnails_fit_the_size() {
+        let dir = std::env::temp_dir().join("ferrite-convert-thumbnail");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("wide.png");
+        RgbaImage::from_pixel(400, 100, Rgba([10, 20, 30, 255]))
+            .save(&input)
+            .unwrap();
+
+        let output = dir.join("thumb.png");
+        let summary = thumbnail(&input, &output, 64, &config()).unwrap();
+        assert!(summary.ends_with("(64x16)"));
+        assert_eq!(image::open(&output).unwrap().width(), 64);
+        // Never enlarged
+        thumbnail(&input, &output, 1000, &config()).unwrap();
+        assert_eq!(image::open(&output).unwrap().width(), 400);
+        assert!(matches!(
+            thumbnail(&input, &output, 0, &config()),
+            Err(ConvertError::Size)
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_format_comes_from_the_extension() {
+        let dir = std::env::temp_dir().join("ferrite-convert-format");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("source.png");
+        let original = RgbaImage::from_fn(8, 4, |x, y| {
+            Rgba([(x * 30) as u8, (y * 60) as u8, 128, 255])
+        });
+        original.save(&input).unwrap();
+
+        let output = dir.join("copy.PNG");
+        convert(&input, &output, &config()).unwrap();
+        assert_eq!(image::open(&output).unwrap().to_rgba8(), original);
+        assert!(matches!(
+            convert(&input, &dir.join("copy.txt"), &config()),
+            Err(ConvertError::Format(_))
+        ));
+        assert!(matches!(
+            convert(&dir.join("missing.png"), &output, &config()),
+            Err(ConvertError::Load(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
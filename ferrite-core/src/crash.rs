@@ -0,0 +1,229 @@
+//! Crash reports for panics.
+//!
+//! A panic hook writes a plain-text report with the panic message, a
+//! backtrace, system information and the last few user actions into the
+//! crash directory, and leaves a marker so the next start can point the
+//! user at the report.
+
+use directories::ProjectDirs;
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How many recent actions are kept for the report
+const MAX_ACTIONS: usize = 20;
+const PENDING_MARKER: &str = "last-crash";
+
+#[derive(Default)]
+struct CrashContext {
+    actions:      VecDeque<String>,
+    current_file: Option<PathBuf>,
+    gpu:          Option<String>,
+}
+
+static CONTEXT: Mutex<Option<CrashContext>> = Mutex::new(None);
+static CRASH_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn with_context(f: impl FnOnce(&mut CrashContext)) {
+    if let Ok(mut guard) = CONTEXT.lock() {
+        f(guard.get_or_insert_with(CrashContext::default));
+    }
+}
+
+/// Remembers a user action so it shows up in a crash report.
+pub fn record_action(action: impl Into<String>) {
+    let action = action.into();
+    with_context(|context| {
+        if context.actions.len() == MAX_ACTIONS {
+            context.actions.pop_front();
+        }
+        context.actions.push_back(action);
+    });
+}
+
+pub fn set_current_file(path: &Path) {
+    with_context(|context| context.current_file = Some(path.to_path_buf()));
+}
+
+pub fn set_gpu_info(description: String) {
+    with_context(|context| context.gpu = Some(description));
+}
+
+/// Where crash reports are written: `crashes` in the state directory given
+/// to [`install`], or in the platform one.
+fn crash_dir() -> Option<PathBuf> {
+    if let Some(dir) = CRASH_DIR.get() {
+        return Some(dir.clone());
+    }
+    let dirs = ProjectDirs::from("com", "ferrite", "ferrite")?;
+    let base = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+    Some(base.join("crashes"))
+}
+
+/// Installs the panic hook. The default hook still runs afterwards, so the
+/// panic is printed to stderr as usual.
+pub fn install(state_dir: Option<PathBuf>) {
+    if let Some(state_dir) = state_dir {
+        let _ = CRASH_DIR.set(state_dir.join("crashes"));
+    }
+    let Some(dir) = crash_dir() else {
+        return;
+    };
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        write_report(&dir, info);
+        previous(info);
+    }));
+}
+
+/// Returns the report left by a crash in the previous run, once.
+pub fn take_pending_report() -> Option<PathBuf> {
+    let marker = crash_dir()?.join(PENDING_MARKER);
+    let report = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+    Some(PathBuf::from(report.trim()))
+}
+
+fn write_report(dir: &Path, info: &PanicHookInfo) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+
+    // The panic may have happened while the context was locked; never block
+    let report = match CONTEXT.try_lock() {
+        Ok(guard) => format_report(info, guard.as_ref()),
+        Err(_) => format_report(info, None),
+    };
+
+    if fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&path, report))
+        .and_then(|_| {
+            fs::write(dir.join(PENDING_MARKER), path.display().to_string())
+        })
+        .is_ok()
+    {
+        eprintln!("Crash report written to {}", path.display());
+    }
+}
+
+fn format_report(
+    info: &PanicHookInfo,
+    context: Option<&CrashContext>,
+) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "<unknown>".to_string());
+
+    let mut report = String::new();
+    let _ =
+        writeln!(report, "Ferrite {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Panic: {}", message);
+    let _ = writeln!(report, "Location: {}", location);
+    let _ = writeln!(
+        report,
+        "Thread: {}",
+        std::thread::current()
+            .name()
+            .unwrap_or("<unnamed>")
+    );
+    let _ = writeln!(report);
+    let _ = writeln!(
+        report,
+        "OS: {} ({} {})",
+        os_description(),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        report,
+        "GPU: {}",
+        context
+            .and_then(|c| c.gpu.as_deref())
+            .unwrap_or("<unknown>")
+    );
+    let _ = writeln!(
+        report,
+        "Current file: {}",
+        context
+            .and_then(|c| c.current_file.as_ref())
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "<none>".to_string())
+    );
+
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Last actions:");
+    match context {
+        Some(context) if !context.actions.is_empty() => {
+            for action in &context.actions {
+                let _ = writeln!(report, "  {}", action);
+            }
+        },
+        _ => {
+            let _ = writeln!(report, "  <none>");
+        },
+    }
+
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Backtrace:");
+    let _ = writeln!(report, "{}", Backtrace::force_capture());
+    report
+}
+
+fn os_description() -> String {
+    fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|release| {
+            release.lines().find_map(|line| {
+                line.strip_prefix("PRETTY_NAME=")
+                    .map(|name| name.trim_matches('"').to_string())
+            })
+        })
+        .unwrap_or_else(|| std::env::consts::FAMILY.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_contains_context() {
+        let context = CrashContext {
+            actions:      VecDeque::from(vec!["Opened cat.jpg".to_string()]),
+            current_file: Some(PathBuf::from("/photos/cat.jpg")),
+            gpu:          Some("Test GPU".to_string()),
+        };
+
+        let report = std::sync::Arc::new(Mutex::new(String::new()));
+        let captured = report.clone();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            *captured.lock().unwrap() = format_report(info, Some(&context));
+        }));
+        let _ = panic::catch_unwind(|| panic!("boom"));
+        panic::set_hook(previous);
+
+        let report = report.lock().unwrap();
+        assert!(report.contains("Panic: boom"));
+        assert!(report.contains("GPU: Test GPU"));
+        assert!(report.contains("Current file: /photos/cat.jpg"));
+        assert!(report.contains("  Opened cat.jpg"));
+        assert!(report.contains("Backtrace:"));
+    }
+}
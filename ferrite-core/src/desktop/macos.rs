@@ -0,0 +1,241 @@
+//! AppKit, reached through the Objective-C runtime.
+//!
+//! One object of a class registered here receives the notification that
+//! the application is about to finish launching, the open-documents Apple
+//! event and the actions of the application menu, and turns them into
+//! [`DesktopEvent`]s.
+
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    runtime::{Object, Sel},
+    sel,
+    sel_impl,
+};
+use std::{
+    ffi::{c_char, CStr, CString},
+    path::PathBuf,
+    ptr,
+    sync::OnceLock,
+};
+
+use super::{push, DesktopEvent};
+
+type Id = *mut Object;
+
+/// `kCoreEventClass` and `kAEOpenDocuments`
+const CORE_EVENT_CLASS: u32 = u32::from_be_bytes(*b"aevt");
+const OPEN_DOCUMENTS: u32 = u32::from_be_bytes(*b"odoc");
+/// `keyDirectObject`, the files of an open-documents event
+const DIRECT_OBJECT: u32 = u32::from_be_bytes(*b"----");
+/// `NSEventModifierFlagOption | NSEventModifierFlagCommand`
+const OPTION_COMMAND: usize = (1 << 19) | (1 << 20);
+
+/// Registers for the end of launching, which is when AppKit expects Apple
+/// event handlers to be installed. NSApp itself is left alone: winit
+/// creates it as its own subclass.
+pub fn prepare() {
+    // SAFETY: the notification center and the handler live for the whole
+    // process
+    unsafe {
+        let center: Id = msg_send![class!(NSNotificationCenter), defaultCenter];
+        let name = ns_string("NSApplicationWillFinishLaunchingNotification");
+        let _: () = msg_send![center,
+            addObserver: handler()
+            selector: sel!(willFinishLaunching:)
+            name: name
+            object: ptr::null_mut::<Object>()];
+    }
+}
+
+/// Replaces winit's application menu with one that has Preferences and a
+/// Quit that closes the window like the viewer's own key does.
+pub fn install_menu() {
+    // SAFETY: called on the main thread once NSApp runs, with objects that
+    // are retained by the menus they are added to
+    unsafe {
+        let app: Id = msg_send![class!(NSApplication), sharedApplication];
+        let bar: Id = msg_send![class!(NSMenu), new];
+        let app_item: Id = msg_send![class!(NSMenuItem), new];
+        let _: () = msg_send![bar, addItem: app_item];
+
+        let menu: Id = msg_send![class!(NSMenu), new];
+        add_item(
+            menu,
+            "About Ferrite",
+            sel!(orderFrontStandardAboutPanel:),
+            "",
+            None,
+        );
+        add_separator(menu);
+        add_item(
+            menu,
+            "Preferences…",
+            sel!(showPreferences:),
+            ",",
+            Some(handler()),
+        );
+        add_separator(menu);
+        add_item(menu, "Hide Ferrite", sel!(hide:), "h", None);
+        let others = add_item(
+            menu,
+            "Hide Others",
+            sel!(hideOtherApplications:),
+            "h",
+            None,
+        );
+        let _: () =
+            msg_send![others, setKeyEquivalentModifierMask: OPTION_COMMAND];
+        add_item(menu, "Show All", sel!(unhideAllApplications:), "", None);
+        add_separator(menu);
+        add_item(menu, "Quit Ferrite", sel!(quit:), "q", Some(handler()));
+
+        let _: () = msg_send![app_item, setSubmenu: menu];
+        let _: () = msg_send![app, setMainMenu: bar];
+    }
+}
+
+/// Shows `label` on the dock icon, or takes the badge away.
+pub fn set_badge(label: Option<String>) {
+    // SAFETY: called on the main thread once NSApp runs
+    unsafe {
+        let app: Id = msg_send![class!(NSApplication), sharedApplication];
+        let tile: Id = msg_send![app, dockTile];
+        let label = label.map_or(ptr::null_mut(), |label| ns_string(&label));
+        let _: () = msg_send![tile, setBadgeLabel: label];
+    }
+}
+
+/// The one instance of the class receiving notifications, Apple events and
+/// menu actions
+fn handler() -> Id {
+    // Kept as an address, since pointers cannot be shared between threads
+    static HANDLER: OnceLock<usize> = OnceLock::new();
+    *HANDLER.get_or_init(|| {
+        let mut decl =
+            ClassDecl::new("FerriteDesktopHandler", class!(NSObject))
+                .expect("handler class is registered once");
+        // SAFETY: the signatures match the selectors they are added for
+        unsafe {
+            decl.add_method(
+                sel!(willFinishLaunching:),
+                will_finish_launching as extern "C" fn(&Object, Sel, Id),
+            );
+            decl.add_method(
+                sel!(openDocuments:withReplyEvent:),
+                open_documents as extern "C" fn(&Object, Sel, Id, Id),
+            );
+            decl.add_method(
+                sel!(showPreferences:),
+                show_preferences as extern "C" fn(&Object, Sel, Id),
+            );
+            decl.add_method(
+                sel!(quit:),
+                quit as extern "C" fn(&Object, Sel, Id),
+            );
+        }
+        let class = decl.register();
+        // SAFETY: a plain NSObject subclass, never released
+        let handler: Id = unsafe { msg_send![class, new] };
+        handler as usize
+    }) as Id
+}
+
+extern "C" fn will_finish_launching(this: &Object, _: Sel, _: Id) {
+    // SAFETY: the handler outlives the event manager's use of it
+    unsafe {
+        let manager: Id =
+            msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+        let _: () = msg_send![manager,
+            setEventHandler: this as *const Object as Id
+            andSelector: sel!(openDocuments:withReplyEvent:)
+            forEventClass: CORE_EVENT_CLASS
+            andEventID: OPEN_DOCUMENTS];
+    }
+}
+
+extern "C" fn open_documents(_: &Object, _: Sel, event: Id, _reply: Id) {
+    let mut paths = Vec::new();
+    // SAFETY: `event` is the NSAppleEventDescriptor of the event handled
+    unsafe {
+        let files: Id =
+            msg_send![event, paramDescriptorForKeyword: DIRECT_OBJECT];
+        if files.is_null() {
+            return;
+        }
+        // A single file may come on its own instead of in a list
+        let count: isize = msg_send![files, numberOfItems];
+        let items: Vec<Id> = if count == 0 {
+            vec![files]
+        } else {
+            (1..=count)
+                .map(|index| -> Id {
+                    msg_send![files, descriptorAtIndex: index]
+                })
+                .collect()
+        };
+        for item in items.into_iter().filter(|item| !item.is_null()) {
+            let url: Id = msg_send![item, fileURL];
+            if url.is_null() {
+                continue;
+            }
+            let path: Id = msg_send![url, path];
+            if let Some(path) = rust_string(path) {
+                paths.push(PathBuf::from(path));
+            }
+        }
+    }
+    if !paths.is_empty() {
+        push(DesktopEvent::Open(paths));
+    }
+}
+
+extern "C" fn show_preferences(_: &Object, _: Sel, _: Id) {
+    push(DesktopEvent::Preferences);
+}
+
+extern "C" fn quit(_: &Object, _: Sel, _: Id) {
+    push(DesktopEvent::Quit);
+}
+
+unsafe fn add_item(
+    menu: Id,
+    title: &str,
+    action: Sel,
+    key: &str,
+    target: Option<Id>,
+) -> Id {
+    let item: Id = msg_send![menu,
+        addItemWithTitle: ns_string(title)
+        action: action
+        keyEquivalent: ns_string(key)];
+    if let Some(target) = target {
+        let _: () = msg_send![item, setTarget: target];
+    }
+    item
+}
+
+unsafe fn add_separator(menu: Id) {
+    let separator: Id = msg_send![class!(NSMenuItem), separatorItem];
+    let _: () = msg_send![menu, addItem: separator];
+}
+
+unsafe fn ns_string(text: &str) -> Id {
+    let text = CString::new(text).unwrap_or_default();
+    msg_send![class!(NSString), stringWithUTF8String: text.as_ptr()]
+}
+
+unsafe fn rust_string(string: Id) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let utf8: *const c_char = msg_send![string, UTF8String];
+    if utf8.is_null() {
+        return None;
+    }
+    CStr::from_ptr(utf8)
+        .to_str()
+        .ok()
+        .map(String::from)
+}
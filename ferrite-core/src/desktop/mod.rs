@@ -0,0 +1,162 @@
+//! Integration with the desktop beyond the window itself.
+//!
+//! On macOS the application menu has About, Preferences and Quit, images
+//! opened from Finder arrive through the open-documents Apple event, and the
+//! dock icon is badged with the progress of a batch. On Windows the taskbar
+//! button shows that progress and has previous and next buttons in its
+//! thumbnail toolbar, and opened images are listed in the jump list.
+//! Elsewhere nothing is hooked up.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+use egui::Context;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(windows)]
+mod win32;
+
+/// Something asked of the viewer from outside its window
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesktopEvent {
+    /// Images opened from the file manager
+    Open(Vec<PathBuf>),
+    Preferences,
+    Quit,
+    /// From the thumbnail toolbar of the taskbar button
+    Previous,
+    Next,
+}
+
+struct Queue {
+    events: Vec<DesktopEvent>,
+    wake:   Option<Context>,
+}
+
+/// Filled from callbacks of the system, which carry no state to reach the
+/// app through. Events from before the window opened wait here for it.
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    events: Vec::new(),
+    wake:   None,
+});
+
+#[cfg_attr(not(any(target_os = "macos", windows)), allow(dead_code))]
+fn push(event: DesktopEvent) {
+    let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    queue.events.push(event);
+    if let Some(ctx) = &queue.wake {
+        ctx.request_repaint();
+    }
+}
+
+fn take_events() -> Vec<DesktopEvent> {
+    let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+    std::mem::take(&mut queue.events)
+}
+
+/// Hooks in before the window opens, so the images the viewer is started
+/// with from the file manager are not missed. Called before
+/// `eframe::run_native`.
+pub fn prepare() {
+    #[cfg(target_os = "macos")]
+    macos::prepare();
+}
+
+/// The desktop the window was opened on
+pub struct Desktop {
+    /// Percentage last shown, not repeated every frame
+    progress: Option<u32>,
+    #[cfg(windows)]
+    taskbar:  Option<win32::Taskbar>,
+}
+
+impl Desktop {
+    pub fn attach(cc: &eframe::CreationContext<'_>) -> Self {
+        QUEUE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .wake = Some(cc.egui_ctx.clone());
+        #[cfg(target_os = "macos")]
+        macos::install_menu();
+        Self {
+            progress:                None,
+            #[cfg(windows)]
+            taskbar:                 win32::Taskbar::attach(cc),
+        }
+    }
+
+    /// What the desktop asked for since the last call.
+    pub fn take(&self) -> Vec<DesktopEvent> {
+        take_events()
+    }
+
+    /// Shows the fraction of a batch done, or nothing once it finished.
+    pub fn set_progress(&mut self, fraction: Option<f32>) {
+        let progress = percent(fraction);
+        if progress == self.progress {
+            return;
+        }
+        self.progress = progress;
+        #[cfg(target_os = "macos")]
+        macos::set_badge(progress.map(|percent| format!("{}%", percent)));
+        #[cfg(windows)]
+        if let Some(taskbar) = &self.taskbar {
+            taskbar.set_progress(progress);
+        }
+    }
+}
+
+/// Lists `path` among the recent documents of the system, where it has
+/// such a list outside the viewer.
+pub(crate) fn note_recent(path: &Path) {
+    #[cfg(windows)]
+    win32::note_recent(path);
+    #[cfg(not(windows))]
+    let _ = path;
+}
+
+/// Opens `path` in the text editor of the system.
+pub(crate) fn edit_text(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut open = Command::new("open");
+        open.arg("-t");
+        open
+    } else if cfg!(windows) {
+        Command::new("notepad")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(drop)
+}
+
+fn percent(fraction: Option<f32>) -> Option<u32> {
+    fraction.map(|fraction| (fraction.clamp(0.0, 1.0) * 100.0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_wait_until_taken() {
+        push(DesktopEvent::Open(vec![PathBuf::from("/a.png")]));
+        push(DesktopEvent::Preferences);
+        assert_eq!(take_events(), vec![
+            DesktopEvent::Open(vec![PathBuf::from("/a.png")]),
+            DesktopEvent::Preferences
+        ]);
+        assert!(take_events().is_empty());
+    }
+
+    #[test]
+    fn test_progress_is_shown_in_whole_percent() {
+        assert_eq!(percent(Some(0.426)), Some(42));
+        assert_eq!(percent(Some(1.5)), Some(100));
+        assert_eq!(percent(None), None);
+    }
+}
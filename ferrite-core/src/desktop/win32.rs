@@ -0,0 +1,229 @@
+//! The taskbar button of the window and the shell's recent documents.
+//!
+//! Progress and the thumbnail toolbar go through `ITaskbarList3` on the
+//! window handle. Clicks on the toolbar arrive at the window as
+//! `WM_COMMAND`, caught by a subclass of winit's window procedure. Explorer
+//! drops the buttons when it restarts and announces the new taskbar button
+//! with `TaskbarButtonCreated`, on which they are added again.
+
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use std::{
+    ffi::c_void,
+    os::windows::ffi::OsStrExt,
+    path::Path,
+    sync::OnceLock,
+};
+use tracing::debug;
+use windows::{
+    core::{w, Result},
+    Win32::{
+        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
+        System::Com::{
+            CoCreateInstance,
+            CoInitializeEx,
+            CLSCTX_INPROC_SERVER,
+            COINIT_APARTMENTTHREADED,
+        },
+        UI::{
+            Shell::{
+                DefSubclassProc,
+                ITaskbarList3,
+                SHAddToRecentDocs,
+                SetWindowSubclass,
+                TaskbarList,
+                SHARD_PATHW,
+                TBPF_NOPROGRESS,
+                TBPF_NORMAL,
+                THBF_ENABLED,
+                THB_FLAGS,
+                THB_ICON,
+                THB_TOOLTIP,
+                THUMBBUTTON,
+            },
+            WindowsAndMessaging::{
+                CreateIcon,
+                RegisterWindowMessageW,
+                HICON,
+                WM_COMMAND,
+            },
+        },
+    },
+};
+
+use super::{push, DesktopEvent};
+
+/// Notification code of a thumbnail toolbar click
+const THBN_CLICKED: u32 = 0x1800;
+const PREVIOUS: u32 = 1;
+const NEXT: u32 = 2;
+const ICON_SIZE: usize = 16;
+
+/// The taskbar button of the window
+pub struct Taskbar {
+    hwnd: HWND,
+    list: ITaskbarList3,
+}
+
+impl Taskbar {
+    /// Adds the previous and next buttons and starts listening for them.
+    pub fn attach(cc: &eframe::CreationContext<'_>) -> Option<Self> {
+        let hwnd = match cc.window_handle().ok()?.as_raw() {
+            RawWindowHandle::Win32(handle) => HWND(handle.hwnd.get()),
+            _ => return None,
+        };
+        let list = match taskbar_list() {
+            Ok(list) => list,
+            Err(e) => {
+                debug!("No taskbar to integrate with: {}", e);
+                return None;
+            },
+        };
+        // SAFETY: the window outlives the subclass, which winit's window
+        // procedure chains to
+        unsafe {
+            SetWindowSubclass(hwnd, Some(subclass), 1, 0);
+        }
+        // Fails while the taskbar button is not there yet; then it is added
+        // on TaskbarButtonCreated
+        if let Err(e) = add_buttons(&list, hwnd) {
+            debug!("Thumbnail toolbar not added yet: {}", e);
+        }
+        Some(Self {
+            hwnd,
+            list,
+        })
+    }
+
+    /// Shows `percent` on the taskbar button, or takes the progress away.
+    pub fn set_progress(&self, percent: Option<u32>) {
+        // SAFETY: `hwnd` is the window of this taskbar button
+        let result = unsafe {
+            match percent {
+                Some(percent) => self
+                    .list
+                    .SetProgressState(self.hwnd, TBPF_NORMAL)
+                    .and_then(|()| {
+                        self.list.SetProgressValue(
+                            self.hwnd,
+                            percent as u64,
+                            100,
+                        )
+                    }),
+                None => self
+                    .list
+                    .SetProgressState(self.hwnd, TBPF_NOPROGRESS),
+            }
+        };
+        if let Err(e) = result {
+            debug!("Failed to show progress on the taskbar: {}", e);
+        }
+    }
+}
+
+/// Adds `path` to the recent documents of the shell, which Windows lists
+/// in the jump list of the program that opens the type.
+pub fn note_recent(path: &Path) {
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    // SAFETY: `wide` is a nul-terminated path that outlives the call
+    unsafe {
+        SHAddToRecentDocs(
+            SHARD_PATHW.0 as u32,
+            Some(wide.as_ptr() as *const c_void),
+        );
+    }
+}
+
+fn taskbar_list() -> Result<ITaskbarList3> {
+    // SAFETY: COM is set up for the thread of the window, which winit has
+    // usually done already; a second call only adds a reference
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let list: ITaskbarList3 =
+            CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+        list.HrInit()?;
+        Ok(list)
+    }
+}
+
+fn add_buttons(list: &ITaskbarList3, hwnd: HWND) -> Result<()> {
+    let buttons = [
+        button(PREVIOUS, "Previous image", true)?,
+        button(NEXT, "Next image", false)?,
+    ];
+    // SAFETY: `hwnd` is the window of the taskbar button
+    unsafe { list.ThumbBarAddButtons(hwnd, &buttons) }
+}
+
+fn button(id: u32, tip: &str, back: bool) -> Result<THUMBBUTTON> {
+    let mut button = THUMBBUTTON {
+        dwMask: THB_ICON | THB_TOOLTIP | THB_FLAGS,
+        iId: id,
+        hIcon: arrow(back)?,
+        dwFlags: THBF_ENABLED,
+        ..Default::default()
+    };
+    for (slot, unit) in button.szTip.iter_mut().zip(tip.encode_utf16()) {
+        *slot = unit;
+    }
+    Ok(button)
+}
+
+/// A white triangle pointing to the previous or the next image
+fn arrow(back: bool) -> Result<HICON> {
+    let mut bgra = vec![0u8; ICON_SIZE * ICON_SIZE * 4];
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let along = if back { ICON_SIZE - 1 - x } else { x } as f32;
+            let half = 11.0 - along;
+            if (5.0..=11.0).contains(&along) && (y as f32 - 7.5).abs() <= half {
+                bgra[(y * ICON_SIZE + x) * 4..][..4].fill(255);
+            }
+        }
+    }
+    // Opaque where the colour's alpha says so
+    let mask = [0u8; ICON_SIZE * ICON_SIZE / 8];
+    // SAFETY: both bitmaps are as large as a 16 by 16 icon needs
+    unsafe {
+        CreateIcon(
+            HINSTANCE::default(),
+            ICON_SIZE as i32,
+            ICON_SIZE as i32,
+            1,
+            32,
+            mask.as_ptr(),
+            bgra.as_ptr(),
+        )
+    }
+}
+
+unsafe extern "system" fn subclass(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    _data: usize,
+) -> LRESULT {
+    static BUTTON_CREATED: OnceLock<u32> = OnceLock::new();
+    let button_created = *BUTTON_CREATED
+        .get_or_init(|| RegisterWindowMessageW(w!("TaskbarButtonCreated")));
+
+    if message == WM_COMMAND && (wparam.0 >> 16) as u32 & 0xffff == THBN_CLICKED
+    {
+        match wparam.0 as u32 & 0xffff {
+            PREVIOUS => push(DesktopEvent::Previous),
+            NEXT => push(DesktopEvent::Next),
+            _ => {},
+        }
+    } else if message == button_created {
+        if let Err(e) = taskbar_list().and_then(|list| add_buttons(&list, hwnd))
+        {
+            debug!("Failed to add the thumbnail toolbar: {}", e);
+        }
+    }
+    DefSubclassProc(hwnd, message, wparam, lparam)
+}
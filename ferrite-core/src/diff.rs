@@ -0,0 +1,150 @@
+//! Pixel differences between two images, for comparing encoder outputs
+//! against their source.
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView, RgbaImage};
+use rayon::prelude::*;
+
+/// Color that marks differing pixels; stronger differences are more opaque
+const HIGHLIGHT: [u8; 3] = [255, 0, 255];
+
+/// Where and how much two images differ
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// Same size as the first image, with differing pixels highlighted and
+    /// the rest transparent
+    pub mask:      RgbaImage,
+    /// Pixels with a channel differing by more than the threshold
+    pub differing: u64,
+    pub total:     u64,
+    /// Largest difference of any channel
+    pub max:       u8,
+    /// Peak signal-to-noise ratio over RGB in dB, `None` when identical
+    pub psnr:      Option<f64>,
+    /// Whether the second image was scaled to the size of the first
+    pub resized:   bool,
+}
+
+impl Difference {
+    pub fn differing_share(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.differing as f64 / total as f64,
+        }
+    }
+}
+
+/// Compares `second` against `first` pixel by pixel. Pixels where any
+/// channel differs by more than `threshold` are marked in the mask. A
+/// second image of another size is scaled to the first one's first.
+pub fn compare(
+    first: &DynamicImage,
+    second: &DynamicImage,
+    threshold: u8,
+) -> Difference {
+    let (width, height) = first.dimensions();
+    let resized = second.dimensions() != (width, height);
+    let first = first.to_rgba8();
+    let second = match resized {
+        true => second
+            .resize_exact(width, height, FilterType::Triangle)
+            .to_rgba8(),
+        false => second.to_rgba8(),
+    };
+
+    let mut mask = RgbaImage::new(width, height);
+    let (differing, max, squared) = mask
+        .par_chunks_mut(4)
+        .zip(first.par_chunks(4))
+        .zip(second.par_chunks(4))
+        .map(|((marked, a), b)| {
+            let mut max = 0;
+            let mut squared = 0u64;
+            for channel in 0..4 {
+                let delta = a[channel].abs_diff(b[channel]);
+                max = max.max(delta);
+                if channel < 3 {
+                    squared += delta as u64 * delta as u64;
+                }
+            }
+            if max > threshold {
+                let [r, g, b] = HIGHLIGHT;
+                // Even the smallest marked difference stays visible
+                let alpha = 96 + (max as u16 * 159 / 255) as u8;
+                marked.copy_from_slice(&[r, g, b, alpha]);
+            }
+            ((max > threshold) as u64, max, squared)
+        })
+        .reduce(
+            || (0, 0, 0),
+            |(count, max, squared), (other_count, other_max, other_squared)| {
+                (
+                    count + other_count,
+                    max.max(other_max),
+                    squared + other_squared,
+                )
+            },
+        );
+
+    let total = width as u64 * height as u64;
+    let samples = total * 3;
+    let psnr = (squared > 0 && samples > 0).then(|| {
+        let mse = squared as f64 / samples as f64;
+        10.0 * (255.0 * 255.0 / mse).log10()
+    });
+    Difference {
+        mask,
+        differing,
+        total,
+        max,
+        psnr,
+        resized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_marks_pixels_past_the_threshold() {
+        let first = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut second = first.clone();
+        second.put_pixel(1, 2, Rgba([110, 100, 100, 255]));
+        second.put_pixel(3, 3, Rgba([102, 100, 100, 255]));
+        let first = DynamicImage::ImageRgba8(first);
+        let second = DynamicImage::ImageRgba8(second);
+
+        let difference = compare(&first, &second, 4);
+        assert_eq!(difference.differing, 1);
+        assert_eq!(difference.total, 16);
+        assert_eq!(difference.max, 10);
+        assert!(!difference.resized);
+        assert_eq!(difference.mask.get_pixel(1, 2).0[..3], HIGHLIGHT);
+        assert_eq!(difference.mask.get_pixel(3, 3).0[3], 0);
+        // 104 / 48 squared error per sample
+        let psnr = difference.psnr.unwrap();
+        assert!((psnr - 44.77).abs() < 0.01, "psnr {}", psnr);
+
+        let same = compare(&first, &first, 0);
+        assert_eq!((same.differing, same.max, same.psnr), (0, 0, None));
+    }
+
+    #[test]
+    fn test_scales_a_second_image_of_another_size() {
+        let first = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            8,
+            8,
+            Rgba([0, 0, 0, 255]),
+        ));
+        let second = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            4,
+            4,
+            Rgba([0, 0, 0, 255]),
+        ));
+        let difference = compare(&first, &second, 0);
+        assert!(difference.resized);
+        assert_eq!(difference.mask.dimensions(), (8, 8));
+        assert_eq!(difference.differing, 0);
+    }
+}
@@ -0,0 +1,229 @@
+//! Dithering of images with more than 8 bits per channel for the 8-bit
+//! textures the viewer draws with. Rounding a smooth 16-bit or float
+//! gradient straight to 8 bits leaves visible bands; adding a threshold
+//! pattern before truncating trades them for fine noise.
+
+use ferrite_config::DitherMode;
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+/// Side of the blue-noise tile
+const TILE: usize = 64;
+
+/// Converts `image` to 8-bit RGBA for display. 8-bit images are converted
+/// as they are; deeper ones are dithered with `mode`.
+pub fn to_display(image: &DynamicImage, mode: DitherMode) -> RgbaImage {
+    let thresholds = match mode {
+        DitherMode::Off => return image.to_rgba8(),
+        DitherMode::Ordered => Thresholds::Bayer,
+        DitherMode::BlueNoise => Thresholds::BlueNoise(blue_noise()),
+    };
+    match image {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => image.to_rgba8(),
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let rgba = image.to_rgba32f();
+            quantize(rgba.as_raw(), rgba.width(), &thresholds, |v| v * 255.0)
+        },
+        _ => {
+            let rgba = image.to_rgba16();
+            quantize(rgba.as_raw(), rgba.width(), &thresholds, |v| {
+                v as f32 * (255.0 / 65535.0)
+            })
+        },
+    }
+}
+
+enum Thresholds {
+    Bayer,
+    BlueNoise(&'static [f32]),
+}
+
+impl Thresholds {
+    /// Threshold in 0..1 for pixel `x`, `y`
+    fn at(&self, x: usize, y: usize) -> f32 {
+        match self {
+            Self::Bayer => (bayer(x % 8, y % 8) as f32 + 0.5) / 64.0,
+            Self::BlueNoise(tile) => tile[(y % TILE) * TILE + x % TILE],
+        }
+    }
+}
+
+/// Index of `x`, `y` in the 8×8 Bayer matrix, built by interleaving the
+/// bits of the coordinates
+fn bayer(x: usize, y: usize) -> usize {
+    let mut index = 0;
+    for bit in 0..3 {
+        let (bx, by) = ((x >> bit) & 1, (y >> bit) & 1);
+        index = (index << 2) | ((bx ^ by) << 1) | by;
+    }
+    index
+}
+
+/// Truncates RGBA `samples`, scaled to 0..=255 by `scale`, after adding the
+/// threshold of their pixel to the color channels.
+fn quantize<S: Copy + Sync>(
+    samples: &[S],
+    width: u32,
+    thresholds: &Thresholds,
+    scale: impl Fn(S) -> f32 + Sync,
+) -> RgbaImage {
+    let width = width as usize;
+    let height = samples.len() / 4 / width.max(1);
+    let mut output = vec![0; samples.len()];
+    output
+        .par_chunks_mut(width * 4)
+        .zip(samples.par_chunks(width * 4))
+        .enumerate()
+        .for_each(|(y, (row, source))| {
+            for (x, (out, pixel)) in row
+                .chunks_exact_mut(4)
+                .zip(source.chunks_exact(4))
+                .enumerate()
+            {
+                let threshold = thresholds.at(x, y);
+                for c in 0..3 {
+                    out[c] =
+                        (scale(pixel[c]) + threshold).clamp(0.0, 255.0) as u8;
+                }
+                out[3] = scale(pixel[3]).round().clamp(0.0, 255.0) as u8;
+            }
+        });
+    RgbaImage::from_raw(width as u32, height as u32, output)
+        .expect("buffer matches dimensions")
+}
+
+fn blue_noise() -> &'static [f32] {
+    static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+    NOISE.get_or_init(void_and_cluster)
+}
+
+/// Ranks the pixels of a tile with Ulichney's void-and-cluster method, so
+/// every threshold level is spread as evenly as possible.
+fn void_and_cluster() -> Vec<f32> {
+    const SIGMA: f32 = 1.5;
+    let n = TILE * TILE;
+    // Gaussian of the wrapped distance, as the tile repeats
+    let kernel: Vec<f32> = (0..n)
+        .map(|i| {
+            let (x, y) = (i % TILE, i / TILE);
+            let dx = x.min(TILE - x) as f32;
+            let dy = y.min(TILE - y) as f32;
+            (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp()
+        })
+        .collect();
+    let splat = |energy: &mut [f32], point: usize, sign: f32| {
+        let (px, py) = (point % TILE, point / TILE);
+        for (i, e) in energy.iter_mut().enumerate() {
+            let dx = (i % TILE + TILE - px) % TILE;
+            let dy = (i / TILE + TILE - py) % TILE;
+            *e += sign * kernel[dy * TILE + dx];
+        }
+    };
+    // Tightest cluster among the set pixels, or largest void among the
+    // unset ones
+    let extreme = |on: &[bool], energy: &[f32], set: bool| {
+        let candidates = (0..n).filter(|&i| on[i] == set);
+        if set {
+            candidates.max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        } else {
+            candidates.min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        }
+        .expect("tile has pixels of both kinds")
+    };
+
+    let mut on = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut rng = fastrand::Rng::with_seed(0x0b1e_5eed);
+    let initial = n / 10;
+    let mut placed = 0;
+    while placed < initial {
+        let point = rng.usize(..n);
+        if !on[point] {
+            on[point] = true;
+            splat(&mut energy, point, 1.0);
+            placed += 1;
+        }
+    }
+    // Move cluster pixels into voids until the pattern is even
+    for _ in 0..n {
+        let cluster = extreme(&on, &energy, true);
+        on[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = extreme(&on, &energy, false);
+        on[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    let (prototype, prototype_energy) = (on.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = extreme(&on, &energy, true);
+        rank[cluster] = r;
+        on[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+    }
+    let (mut on, mut energy) = (prototype, prototype_energy);
+    for r in initial..n {
+        let void = if r + 1 == n {
+            (0..n).find(|&i| !on[i]).expect("one pixel left")
+        } else {
+            extreme(&on, &energy, false)
+        };
+        rank[void] = r;
+        on[void] = true;
+        splat(&mut energy, void, 1.0);
+    }
+    rank.into_iter()
+        .map(|r| (r as f32 + 0.5) / n as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_thresholds_cover_every_level_once() {
+        let mut bayer: Vec<usize> =
+            (0..64).map(|i| bayer(i % 8, i / 8)).collect();
+        bayer.sort_unstable();
+        assert_eq!(bayer, (0..64).collect::<Vec<_>>());
+
+        let mut noise = blue_noise().to_vec();
+        noise.sort_by(f32::total_cmp);
+        noise.dedup();
+        assert_eq!(noise.len(), TILE * TILE);
+    }
+
+    #[test]
+    fn test_dithering_keeps_the_mean_between_levels() {
+        // A flat 16-bit value a quarter of the way from 100 to 101
+        let value = ((100.25 / 255.0) * 65535.0f32).round() as u16;
+        let image = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(
+            TILE as u32,
+            TILE as u32,
+            Rgb([value; 3]),
+        ));
+
+        let flat = to_display(&image, DitherMode::Off);
+        assert!(flat.pixels().all(|p| p[0] == 100));
+        for mode in [DitherMode::Ordered, DitherMode::BlueNoise] {
+            let dithered = to_display(&image, mode);
+            let mean = dithered
+                .pixels()
+                .map(|p| p[0] as f32)
+                .sum::<f32>()
+                / (TILE * TILE) as f32;
+            assert!((mean - 100.25).abs() < 0.02, "{:?}: {}", mode, mean);
+            assert!(dithered.pixels().all(|p| p[3] == 255));
+        }
+    }
+}
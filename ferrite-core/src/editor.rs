@@ -0,0 +1,47 @@
+//! Opening the image on screen in an external editor from the config.
+
+use ferrite_config::ExternalEditor;
+use std::{io, path::Path, thread};
+use tracing::info;
+
+use crate::process::shell_command;
+
+/// Starts `editor` on `image` without waiting for it to close.
+pub fn launch(editor: &ExternalEditor, image: &Path) -> io::Result<()> {
+    info!("Opening {} in {}", image.display(), editor.name);
+    let mut child = shell_command(&editor.command, image).spawn()?;
+    // Waited on in the background, so it does not linger once closed
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn test_runs_the_command_on_the_image() {
+        let dir = std::env::temp_dir()
+            .join(format!("ferrite-editor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("it's here.png");
+        fs::write(&image, b"").unwrap();
+
+        let editor = ExternalEditor {
+            name:    "Test".into(),
+            command: "printf saved > {path}".into(),
+        };
+        launch(&editor, &image).unwrap();
+        for _ in 0..100 {
+            if fs::read(&image).unwrap() == b"saved" {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(fs::read(&image).unwrap(), b"saved");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+//! Writing the image on screen to a file, converted to another format with
+//! that format's options.
+//!
+//! Encoding a large image takes a while, so the encoders work row by row
+//! and report how far they got through a [`Progress`], which can also stop
+//! them. [`run`] does this on the calling thread and sends the progress over
+//! a channel, like a batch does.
+
+use image::{DynamicImage, GenericImageView, ImageError};
+use std::{
+    cell::Cell,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+};
+use thiserror::Error;
+use tracing::{info, warn};
+
+mod webp;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Failed to write the file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to encode PNG: {0}")]
+    Png(#[from] png::EncodingError),
+
+    #[error("Failed to encode: {0}")]
+    Image(#[from] ImageError),
+
+    #[error("{format} images can be at most {max} pixels wide and high")]
+    TooLarge { format: &'static str, max: u32 },
+
+    #[error("Export cancelled")]
+    Cancelled,
+}
+
+/// Formats the image on screen can be saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    #[cfg(feature = "jpeg")]
+    Jpeg,
+    /// Lossless only
+    WebP,
+}
+
+impl ExportFormat {
+    pub const AVAILABLE: &'static [ExportFormat] = &[
+        ExportFormat::Png,
+        #[cfg(feature = "jpeg")]
+        ExportFormat::Jpeg,
+        ExportFormat::WebP,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG",
+            #[cfg(feature = "jpeg")]
+            ExportFormat::Jpeg => "JPEG",
+            ExportFormat::WebP => "WebP (lossless)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            #[cfg(feature = "jpeg")]
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::WebP => "webp",
+        }
+    }
+
+    /// The format of a file named like `path`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let extension = match extension.as_str() {
+            "jpeg" => "jpg",
+            extension => extension,
+        };
+        Self::AVAILABLE
+            .iter()
+            .copied()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// How hard PNG compression tries, trading time for size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl PngCompression {
+    pub const ALL: [PngCompression; 3] =
+        [PngCompression::Fast, PngCompression::Default, PngCompression::Best];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PngCompression::Fast => "Fast",
+            PngCompression::Default => "Default",
+            PngCompression::Best => "Smallest",
+        }
+    }
+}
+
+impl From<PngCompression> for png::Compression {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Default,
+            PngCompression::Best => png::Compression::Best,
+        }
+    }
+}
+
+/// The format to write and the options of each format; those of other
+/// formats are kept so switching back restores them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format:          ExportFormat,
+    /// 1 to 100
+    pub jpeg_quality:    u8,
+    pub png_compression: PngCompression,
+}
+
+/// Reports how far an encoder got, and tells it to stop once cancelled.
+pub struct Progress<'a> {
+    report: &'a dyn Fn(f32),
+    cancel: &'a AtomicBool,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(report: &'a dyn Fn(f32), cancel: &'a AtomicBool) -> Self {
+        Self {
+            report,
+            cancel,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Reports `done` of `total` steps, failing once cancelled.
+    fn step(&self, done: u32, total: u32) -> Result<(), ExportError> {
+        if self.is_cancelled() {
+            return Err(ExportError::Cancelled);
+        }
+        (self.report)(done as f32 / total.max(1) as f32);
+        Ok(())
+    }
+}
+
+/// Progress of an export on its thread
+pub enum ExportEvent {
+    /// Fraction done, from 0 to 1
+    Progress(f32),
+    Finished(Result<PathBuf, ExportError>),
+}
+
+/// Writes `image` to `path` with `options`, reporting on `events`. A file
+/// that could not be finished is removed.
+pub fn run(
+    image: DynamicImage,
+    path: PathBuf,
+    options: ExportOptions,
+    events: Sender<ExportEvent>,
+    cancel: &AtomicBool,
+) {
+    let last = Cell::new(0);
+    let report = |fraction: f32| {
+        // Whole percents are plenty for a progress bar
+        let percent = (fraction * 100.0) as u32;
+        if percent != last.replace(percent) {
+            let _ = events.send(ExportEvent::Progress(fraction));
+        }
+    };
+    let result =
+        export(&image, &path, options, &Progress::new(&report, cancel));
+    match &result {
+        Ok(()) => info!("Exported {}", path.display()),
+        Err(e) => warn!("Failed to export {}: {}", path.display(), e),
+    }
+    let _ = events.send(ExportEvent::Finished(result.map(|()| path)));
+}
+
+/// Writes `image` to the file at `path`, removing it again on failure.
+pub fn export(
+    image: &DynamicImage,
+    path: &Path,
+    options: ExportOptions,
+    progress: &Progress,
+) -> Result<(), ExportError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let result = encode(image, options, &mut file, progress)
+        .and_then(|()| file.flush().map_err(ExportError::from));
+    if result.is_err() {
+        drop(file);
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+pub fn encode<W: Write>(
+    image: &DynamicImage,
+    options: ExportOptions,
+    writer: W,
+    progress: &Progress,
+) -> Result<(), ExportError> {
+    match options.format {
+        ExportFormat::Png => {
+            encode_png(image, options.png_compression, writer, progress)
+        },
+        #[cfg(feature = "jpeg")]
+        ExportFormat::Jpeg => {
+            encode_jpeg(image, options.jpeg_quality, writer, progress)
+        },
+        ExportFormat::WebP => webp::encode(&image.to_rgba8(), writer, progress),
+    }
+}
+
+/// Writes 8 bits per channel, or 16 for images with more, and keeps alpha
+/// only if the image has it.
+fn encode_png<W: Write>(
+    image: &DynamicImage,
+    compression: PngCompression,
+    writer: W,
+    progress: &Progress,
+) -> Result<(), ExportError> {
+    let (width, height) = image.dimensions();
+    let color = image.color();
+    let deep = color.bytes_per_pixel() > color.channel_count();
+    let big_endian = |samples: Vec<u16>| {
+        samples
+            .into_iter()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<u8>>()
+    };
+    let (png_color, bytes) = match (color.has_alpha(), deep) {
+        (false, false) => (png::ColorType::Rgb, image.to_rgb8().into_raw()),
+        (true, false) => (png::ColorType::Rgba, image.to_rgba8().into_raw()),
+        (false, true) => {
+            (png::ColorType::Rgb, big_endian(image.to_rgb16().into_raw()))
+        },
+        (true, true) => {
+            (png::ColorType::Rgba, big_endian(image.to_rgba16().into_raw()))
+        },
+    };
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png_color);
+    encoder.set_depth(match deep {
+        true => png::BitDepth::Sixteen,
+        false => png::BitDepth::Eight,
+    });
+    encoder.set_compression(compression.into());
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+    let row = bytes.len() / height.max(1) as usize;
+    for (y, line) in bytes.chunks(row.max(1)).enumerate() {
+        stream.write_all(line)?;
+        progress.step(y as u32 + 1, height)?;
+    }
+    stream.finish()?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(feature = "jpeg")]
+fn encode_jpeg<W: Write>(
+    image: &DynamicImage,
+    quality: u8,
+    writer: W,
+    progress: &Progress,
+) -> Result<(), ExportError> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    // JPEG has no alpha channel
+    let view = Watched {
+        image: image.to_rgb8(),
+        progress,
+        row: Cell::new(0),
+    };
+    JpegEncoder::new_with_quality(writer, quality.clamp(1, 100))
+        .encode_image(&view)?;
+    match progress.is_cancelled() {
+        true => Err(ExportError::Cancelled),
+        false => Ok(()),
+    }
+}
+
+/// The pixels handed to an encoder that takes the whole image at once,
+/// reporting progress as it reads further down. Once cancelled, it reads
+/// black so the encoder finishes quickly.
+#[cfg(feature = "jpeg")]
+struct Watched<'a> {
+    image:    image::RgbImage,
+    progress: &'a Progress<'a>,
+    row:      Cell<u32>,
+}
+
+#[cfg(feature = "jpeg")]
+impl GenericImageView for Watched<'_> {
+    type Pixel = image::Rgb<u8>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = self.image.dimensions();
+        (0, 0, width, height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> image::Rgb<u8> {
+        if y > self.row.get() {
+            self.row.set(y);
+            // Cancelling shows up in the check below
+            let _ = self.progress.step(y, self.image.height());
+        }
+        if self.progress.is_cancelled() {
+            return image::Rgb([0, 0, 0]);
+        }
+        *self.image.get_pixel(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 30, |x, y| {
+            Rgba([(x * 6) as u8, (y * 8) as u8, 128, 255 - x as u8])
+        }))
+    }
+
+    fn options(format: ExportFormat) -> ExportOptions {
+        ExportOptions {
+            format,
+            jpeg_quality: 90,
+            png_compression: PngCompression::Best,
+        }
+    }
+
+    #[test]
+    fn test_formats_decode_back_with_progress() {
+        let image = gradient();
+        let cancel = AtomicBool::new(false);
+        for &format in ExportFormat::AVAILABLE {
+            let reported = Cell::new(0.0f32);
+            let report = |fraction: f32| {
+                assert!(fraction >= reported.get());
+                reported.set(fraction);
+            };
+            let mut bytes = Vec::new();
+            encode(
+                &image,
+                options(format),
+                &mut bytes,
+                &Progress::new(&report, &cancel),
+            )
+            .unwrap();
+            assert!(reported.get() > 0.9, "{:?} reported no progress", format);
+
+            let read = image::ImageFormat::from_extension(format.extension())
+                .filter(image::ImageFormat::reading_enabled);
+            let Some(read) = read else {
+                continue;
+            };
+            let decoded = image::load(Cursor::new(&bytes), read).unwrap();
+            assert_eq!(decoded.dimensions(), (40, 30));
+            if format != ExportFormat::Png && format != ExportFormat::WebP {
+                continue;
+            }
+            // The lossless formats keep every pixel including alpha
+            assert_eq!(decoded.to_rgba8(), image.to_rgba8(), "{:?}", format);
+        }
+        #[cfg(feature = "jpeg")]
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.JPEG")),
+            Some(ExportFormat::Jpeg)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("a.gif")), None);
+    }
+
+    #[test]
+    fn test_cancelled_export_leaves_no_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("ferrite-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.png");
+        let cancel = AtomicBool::new(true);
+
+        let result = export(
+            &gradient(),
+            &path,
+            options(ExportFormat::Png),
+            &Progress::new(&|_| {}, &cancel),
+        );
+        assert!(matches!(result, Err(ExportError::Cancelled)));
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,435 @@
+//! Lossless WebP (VP8L) encoding, which the image crate can only decode.
+//!
+//! Pixels go through the subtract-green transform and a predictor that
+//! averages the left and top neighbours, and the residuals are written as
+//! literals with one set of prefix codes. Without backward references or a
+//! colour cache the files are larger than those of libwebp, but they are
+//! exact and quick to write.
+
+use image::RgbaImage;
+use std::{cmp::Reverse, collections::BinaryHeap, io::Write};
+
+use super::{ExportError, Progress};
+
+/// Largest width and height a VP8L header can describe
+const MAX_SIZE: u32 = 16384;
+/// Predictor blocks are `1 << BLOCK_BITS` pixels wide. All use the same
+/// mode, so this only sets how many are listed.
+const BLOCK_BITS: u32 = 9;
+/// Predicts the average of the left and top pixels
+const PREDICTOR_MODE: usize = 7;
+/// Order in which the lengths of the code length code are written
+const CODE_LENGTH_ORDER: [usize; 19] =
+    [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+/// Green literals followed by the 24 prefixes of backward reference
+/// lengths
+const GREEN_SYMBOLS: usize = 256 + 24;
+const MAX_CODE_LENGTH: u32 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u32 = 7;
+
+pub fn encode<W: Write>(
+    image: &RgbaImage,
+    mut writer: W,
+    progress: &Progress,
+) -> Result<(), ExportError> {
+    let (width, height) = image.dimensions();
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(ExportError::TooLarge {
+            format: "WebP", max: MAX_SIZE
+        });
+    }
+    let stride = width as usize;
+    // Two passes over the rows, the first to count the symbols
+    let steps = height * 2;
+
+    let mut pixels: Vec<[u8; 4]> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            [r.wrapping_sub(g), g, b.wrapping_sub(g), a]
+        })
+        .collect();
+    // Backwards, so the neighbours are still unpredicted
+    let mut counts =
+        [vec![0; GREEN_SYMBOLS], vec![0; 256], vec![0; 256], vec![0; 256]];
+    for y in (0..height as usize).rev() {
+        for x in (0..stride).rev() {
+            let i = y * stride + x;
+            let prediction = match (x, y) {
+                (0, 0) => [0, 0, 0, 255],
+                (_, 0) => pixels[i - 1],
+                (0, _) => pixels[i - stride],
+                _ => average(pixels[i - 1], pixels[i - stride]),
+            };
+            let [r, g, b, a] = pixels[i];
+            let residual = [
+                r.wrapping_sub(prediction[0]),
+                g.wrapping_sub(prediction[1]),
+                b.wrapping_sub(prediction[2]),
+                a.wrapping_sub(prediction[3]),
+            ];
+            counts[0][residual[1] as usize] += 1;
+            counts[1][residual[0] as usize] += 1;
+            counts[2][residual[2] as usize] += 1;
+            counts[3][residual[3] as usize] += 1;
+            pixels[i] = residual;
+        }
+        progress.step(height - y as u32, steps)?;
+    }
+
+    let mut bits = BitWriter::default();
+    bits.put(0x2f, 8);
+    bits.put(width - 1, 14);
+    bits.put(height - 1, 14);
+    let alpha_used = image.pixels().any(|pixel| pixel[3] != 255);
+    bits.put(alpha_used as u32, 1);
+    // Version
+    bits.put(0, 3);
+
+    // Subtract green
+    bits.put(1, 1);
+    bits.put(2, 2);
+    // Predictor, with one mode for every block: the green of an image of
+    // the blocks, whose codes have a single symbol and take no bits
+    bits.put(1, 1);
+    bits.put(0, 2);
+    bits.put(BLOCK_BITS - 2, 3);
+    bits.put(0, 1);
+    for symbol in [PREDICTOR_MODE, 0, 0, 0, 0] {
+        write_single(&mut bits, symbol);
+    }
+    bits.put(0, 1);
+
+    // No colour cache and a single set of codes for the whole image
+    bits.put(0, 1);
+    bits.put(0, 1);
+    let codes = counts.map(|counts| write_code(&mut bits, &counts));
+    // Distances, never used
+    write_single(&mut bits, 0);
+
+    let [green, red, blue, alpha] = &codes;
+    for (y, row) in pixels.chunks(stride).enumerate() {
+        for &[r, g, b, a] in row {
+            green.write(&mut bits, g as usize);
+            red.write(&mut bits, r as usize);
+            blue.write(&mut bits, b as usize);
+            alpha.write(&mut bits, a as usize);
+        }
+        progress.step(height + y as u32 + 1, steps)?;
+    }
+
+    let data = bits.finish();
+    let size = data.len() as u32;
+    let padding = size & 1;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(4 + 8 + size + padding).to_le_bytes())?;
+    writer.write_all(b"WEBPVP8L")?;
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(&data)?;
+    if padding == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+/// Per channel average, rounded down
+fn average(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    std::array::from_fn(|i| ((a[i] as u16 + b[i] as u16) / 2) as u8)
+}
+
+/// Bits packed from the least significant end of each byte
+#[derive(Default)]
+struct BitWriter {
+    bytes:   Vec<u8>,
+    pending: u64,
+    count:   u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u32, count: u32) {
+        debug_assert!(count == 32 || value >> count == 0);
+        self.pending |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+        self.bytes
+    }
+}
+
+/// A canonical prefix code, as the decoder rebuilds it from the lengths
+struct PrefixCode {
+    lengths: Vec<u8>,
+    /// Bit-reversed, as the first bit of a code goes out first
+    codes:   Vec<u16>,
+}
+
+impl PrefixCode {
+    fn new(lengths: Vec<u8>) -> Self {
+        let mut per_length = [0u16; MAX_CODE_LENGTH as usize + 1];
+        for &length in &lengths {
+            per_length[length as usize] += 1;
+        }
+        per_length[0] = 0;
+        let mut next = [0u16; MAX_CODE_LENGTH as usize + 1];
+        let mut code = 0;
+        for length in 1..next.len() {
+            code = (code + per_length[length - 1]) << 1;
+            next[length] = code;
+        }
+
+        // A single symbol takes no bits at all
+        let single = lengths
+            .iter()
+            .filter(|&&length| length > 0)
+            .count()
+            <= 1;
+        let codes = lengths
+            .iter()
+            .map(|&length| {
+                if length == 0 || single {
+                    return 0;
+                }
+                let code = next[length as usize];
+                next[length as usize] += 1;
+                code.reverse_bits() >> (16 - length)
+            })
+            .collect();
+        let lengths = match single {
+            true => vec![0; lengths.len()],
+            false => lengths,
+        };
+        Self {
+            lengths,
+            codes,
+        }
+    }
+
+    fn write(&self, bits: &mut BitWriter, symbol: usize) {
+        bits.put(self.codes[symbol] as u32, self.lengths[symbol] as u32);
+    }
+}
+
+/// Writes a code of just `symbol`, which must be below 256.
+fn write_single(bits: &mut BitWriter, symbol: usize) {
+    debug_assert!(symbol < 256);
+    // Simple code of one symbol, stored in one or eight bits
+    bits.put(1, 1);
+    bits.put(0, 1);
+    if symbol < 2 {
+        bits.put(0, 1);
+        bits.put(symbol as u32, 1);
+    } else {
+        bits.put(1, 1);
+        bits.put(symbol as u32, 8);
+    }
+}
+
+/// Writes the code for symbols occurring `counts` times and returns it.
+fn write_code(bits: &mut BitWriter, counts: &[u32]) -> PrefixCode {
+    let used: Vec<usize> = (0..counts.len())
+        .filter(|&symbol| counts[symbol] > 0)
+        .collect();
+    if used.len() <= 1 {
+        let symbol = used.first().copied().unwrap_or_default();
+        write_single(bits, symbol);
+        let mut lengths = vec![0; counts.len()];
+        lengths[symbol] = 1;
+        return PrefixCode::new(lengths);
+    }
+
+    let lengths = code_lengths(counts, MAX_CODE_LENGTH);
+    let tokens = length_tokens(&lengths);
+    let mut token_counts = [0; 19];
+    for &(token, _) in &tokens {
+        token_counts[token as usize] += 1;
+    }
+    let token_lengths =
+        code_lengths(&token_counts, MAX_CODE_LENGTH_CODE_LENGTH);
+
+    bits.put(0, 1);
+    let written = CODE_LENGTH_ORDER
+        .iter()
+        .rposition(|&token| token_lengths[token] > 0)
+        .map_or(0, |last| last + 1)
+        .max(4);
+    bits.put(written as u32 - 4, 4);
+    for &token in &CODE_LENGTH_ORDER[..written] {
+        bits.put(token_lengths[token] as u32, 3);
+    }
+    // Lengths follow for the whole alphabet
+    bits.put(0, 1);
+    let token_code = PrefixCode::new(token_lengths);
+    for (token, extra) in tokens {
+        token_code.write(bits, token as usize);
+        match token {
+            17 => bits.put(extra, 3),
+            18 => bits.put(extra, 7),
+            _ => {},
+        }
+    }
+    PrefixCode::new(lengths)
+}
+
+/// The code lengths as written: lengths themselves, with runs of zeros
+/// as 17 (3 to 10) or 18 (11 to 138) and the run in the extra bits
+fn length_tokens(lengths: &[u8]) -> Vec<(u8, u32)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let zeros = lengths[i..]
+            .iter()
+            .take_while(|&&length| length == 0)
+            .count();
+        let taken = match zeros {
+            0 => {
+                tokens.push((lengths[i], 0));
+                1
+            },
+            1..=2 => {
+                tokens.extend(std::iter::repeat_n((0, 0), zeros));
+                zeros
+            },
+            3..=10 => {
+                tokens.push((17, zeros as u32 - 3));
+                zeros
+            },
+            _ => {
+                let run = zeros.min(138);
+                tokens.push((18, run as u32 - 11));
+                run
+            },
+        };
+        i += taken;
+    }
+    tokens
+}
+
+/// Huffman code lengths for symbols occurring `counts` times, flattening
+/// the counts until no code is longer than `limit`
+fn code_lengths(counts: &[u32], limit: u32) -> Vec<u8> {
+    let mut counts = counts.to_vec();
+    loop {
+        let lengths = huffman_lengths(&counts);
+        if lengths.iter().all(|&length| length <= limit) {
+            return lengths
+                .into_iter()
+                .map(|length| length as u8)
+                .collect();
+        }
+        for count in counts.iter_mut().filter(|count| **count > 0) {
+            *count = (*count >> 1) | 1;
+        }
+    }
+}
+
+fn huffman_lengths(counts: &[u32]) -> Vec<u32> {
+    let mut lengths = vec![0; counts.len()];
+    let used: Vec<usize> = (0..counts.len())
+        .filter(|&symbol| counts[symbol] > 0)
+        .collect();
+    if used.len() <= 1 {
+        for &symbol in &used {
+            lengths[symbol] = 1;
+        }
+        return lengths;
+    }
+
+    // Leaves first, then each merged node; parents always come later
+    let mut parents = vec![0; used.len() * 2 - 1];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((counts[symbol] as u64, node)))
+        .collect();
+    let mut next = used.len();
+    while let (Some(Reverse((a, x))), Some(Reverse((b, y)))) =
+        (heap.pop(), heap.pop())
+    {
+        parents[x] = next;
+        parents[y] = next;
+        heap.push(Reverse((a + b, next)));
+        next += 1;
+    }
+    let mut depths = vec![0; next];
+    for node in (0..next - 1).rev() {
+        depths[node] = depths[parents[node]] + 1;
+    }
+    for (node, &symbol) in used.iter().enumerate() {
+        lengths[symbol] = depths[node];
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba};
+    use std::{io::Cursor, sync::atomic::AtomicBool};
+
+    fn round_trip(image: &RgbaImage) -> RgbaImage {
+        let cancel = AtomicBool::new(false);
+        let mut bytes = Vec::new();
+        encode(image, &mut bytes, &Progress::new(&|_| {}, &cancel)).unwrap();
+        image::load(Cursor::new(&bytes), image::ImageFormat::WebP)
+            .map(DynamicImage::into_rgba8)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_images_survive_unchanged() {
+        // Noise gives long codes, flat areas single symbols
+        let noise = RgbaImage::from_fn(37, 23, |_, _| {
+            Rgba([fastrand::u8(..), fastrand::u8(..), fastrand::u8(..), 255])
+        });
+        let flat = RgbaImage::from_pixel(5, 9, Rgba([10, 200, 30, 255]));
+        let mut mixed = RgbaImage::from_fn(64, 16, |x, y| {
+            Rgba([x as u8 * 4, y as u8 * 16, 255 - x as u8, (x * y) as u8])
+        });
+        mixed.put_pixel(63, 15, Rgba([1, 2, 3, 4]));
+        for image in [noise, flat, mixed, RgbaImage::new(1, 1)] {
+            assert_eq!(round_trip(&image), image);
+        }
+    }
+
+    #[test]
+    fn test_code_lengths_stay_within_the_limit() {
+        // Fibonacci counts make the deepest possible Huffman tree
+        let mut counts = vec![1u32, 1];
+        while counts.len() < 30 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+        assert!(huffman_lengths(&counts)
+            .iter()
+            .any(|&length| length > 15));
+        for limit in [7, 15] {
+            let lengths = code_lengths(&counts, limit);
+            assert!(lengths
+                .iter()
+                .all(|&length| length as u32 <= limit));
+            // Still a complete code the decoder accepts
+            let kraft: f64 = lengths
+                .iter()
+                .map(|&length| 0.5f64.powi(length as i32))
+                .sum();
+            assert!((kraft - 1.0).abs() < 1e-9);
+        }
+
+        assert_eq!(length_tokens(&[0; 150]), [(18, 127), (18, 1)]);
+        assert_eq!(length_tokens(&[3, 0, 0, 5, 0, 0, 0]), [
+            (3, 0),
+            (0, 0),
+            (0, 0),
+            (5, 0),
+            (17, 0)
+        ]);
+    }
+}
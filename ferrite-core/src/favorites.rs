@@ -0,0 +1,141 @@
+//! Images starred by the user. They are kept as one absolute path per line
+//! in `favorites` in the state directory, so the stars stay with the files
+//! across runs and folders.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+const FILE_NAME: &str = "favorites";
+
+pub struct Favorites {
+    /// Where the stars are saved; `None` keeps them for the session only
+    file:    Option<PathBuf>,
+    starred: BTreeSet<PathBuf>,
+}
+
+impl Favorites {
+    /// Reads the stars kept in `state_dir`. A missing or unreadable file
+    /// starts out with none.
+    pub fn load(state_dir: Option<PathBuf>) -> Self {
+        let file = state_dir.map(|dir| dir.join(FILE_NAME));
+        let starred = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            file,
+            starred,
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        key(path).is_some_and(|key| self.starred.contains(&key))
+    }
+
+    pub fn paths(&self) -> &BTreeSet<PathBuf> {
+        &self.starred
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starred.is_empty()
+    }
+
+    /// Stars `path` or takes its star away, then saves. Returns whether it
+    /// is starred now.
+    pub fn toggle(&mut self, path: &Path) -> io::Result<bool> {
+        let Some(key) = key(path) else {
+            return Ok(false);
+        };
+        let starred = if self.starred.remove(&key) {
+            false
+        } else {
+            self.starred.insert(key);
+            true
+        };
+        self.save()?;
+        Ok(starred)
+    }
+
+    /// Moves the stars of files renamed or moved elsewhere in the app to
+    /// their new paths.
+    pub fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+        let mut changed = false;
+        for (old, new) in renamed {
+            if let (Some(old), Some(new)) = (key(old), key(new)) {
+                if self.starred.remove(&old) {
+                    self.starred.insert(new);
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for path in &self.starred {
+            text.push_str(&path.to_string_lossy());
+            text.push('\n');
+        }
+        fs::write(file, text)
+    }
+}
+
+/// The absolute form of `path`, so a star set from one working directory is
+/// found from another
+fn key(path: &Path) -> Option<PathBuf> {
+    std::path::absolute(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stars_are_saved_and_toggled_off() {
+        let dir = std::env::temp_dir().join("ferrite-favorites");
+        let _ = fs::remove_dir_all(&dir);
+        let image = dir.join("a.png");
+
+        let mut favorites = Favorites::load(Some(dir.clone()));
+        assert!(!favorites.contains(&image));
+        assert!(favorites.toggle(&image).unwrap());
+        assert!(Favorites::load(Some(dir.clone())).contains(&image));
+
+        assert!(!favorites.toggle(&image).unwrap());
+        assert!(Favorites::load(Some(dir.clone())).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stars_follow_renamed_files() {
+        let mut favorites = Favorites::load(None);
+        let (old, new) =
+            (PathBuf::from("/photos/a.png"), PathBuf::from("/b.png"));
+        favorites.toggle(&old).unwrap();
+        favorites
+            .rename(&[(old.clone(), new.clone())])
+            .unwrap();
+        assert!(!favorites.contains(&old));
+        assert!(favorites.contains(&new));
+    }
+}
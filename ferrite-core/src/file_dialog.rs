@@ -0,0 +1,233 @@
+//! The desktop's own open and save dialogs, run as a separate program:
+//! zenity or kdialog on free desktops, AppleScript on macOS and Windows
+//! Forms through PowerShell. The dialog blocks until the user is done, so
+//! call it off the UI thread. The suggested path and the file types reach
+//! the scripts through the environment, so they need no quoting.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FileDialogError {
+    #[error("No file dialog is available: {0}")]
+    Unavailable(io::Error),
+
+    #[error("The file dialog failed: {0}")]
+    Failed(String),
+}
+
+/// Environment variables the scripts read the suggestion from
+const DIR_VARIABLE: &str = "FERRITE_DIALOG_DIR";
+const NAME_VARIABLE: &str = "FERRITE_DIALOG_NAME";
+const FILTER_VARIABLE: &str = "FERRITE_DIALOG_FILTER";
+
+/// Asks where to save, starting at `suggested`. `None` if the user
+/// cancelled.
+pub fn save_file(suggested: &Path) -> Result<Option<PathBuf>, FileDialogError> {
+    let dir = suggested.parent().unwrap_or(Path::new("."));
+    let name = suggested.file_name().unwrap_or_default();
+    let mut commands = platform::save_commands(suggested);
+    for command in &mut commands {
+        command
+            .env(DIR_VARIABLE, dir)
+            .env(NAME_VARIABLE, name);
+    }
+    run(commands)
+}
+
+/// Asks for a file to open in `dir`, listing those with one of
+/// `extensions`. `None` if the user cancelled.
+pub fn open_file(
+    dir: &Path,
+    extensions: &[&str],
+) -> Result<Option<PathBuf>, FileDialogError> {
+    let mut commands = platform::open_commands(dir, extensions);
+    for command in &mut commands {
+        command
+            .env(DIR_VARIABLE, dir)
+            .env(FILTER_VARIABLE, platform::filter(extensions));
+    }
+    run(commands)
+}
+
+/// Runs the first of `commands` that can be started, and reads the path
+/// it prints.
+fn run(mut commands: Vec<Command>) -> Result<Option<PathBuf>, FileDialogError> {
+    let mut last = None;
+    for command in &mut commands {
+        match command.output() {
+            Ok(output) if output.status.success() => {
+                let chosen = String::from_utf8_lossy(&output.stdout);
+                let chosen = chosen.trim_end_matches(['\r', '\n']);
+                return Ok((!chosen.is_empty()).then(|| PathBuf::from(chosen)));
+            },
+            Ok(output) => {
+                let error = String::from_utf8_lossy(&output.stderr);
+                return match platform::is_cancel(output.status.code(), &error) {
+                    true => Ok(None),
+                    false => Err(FileDialogError::Failed(error.trim().into())),
+                };
+            },
+            // Try the next dialog program
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(FileDialogError::Unavailable(last.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "unsupported platform")
+    })))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::*;
+
+    pub fn save_commands(suggested: &Path) -> Vec<Command> {
+        let mut zenity = Command::new("zenity");
+        zenity
+            .args(["--file-selection", "--save", "--confirm-overwrite"])
+            .arg(format!("--filename={}", suggested.display()));
+        let mut kdialog = Command::new("kdialog");
+        kdialog.arg("--getsavefilename").arg(suggested);
+        vec![zenity, kdialog]
+    }
+
+    pub fn open_commands(dir: &Path, extensions: &[&str]) -> Vec<Command> {
+        let patterns = filter(extensions);
+        let mut zenity = Command::new("zenity");
+        zenity
+            .arg("--file-selection")
+            // A trailing slash starts inside the folder
+            .arg(format!("--filename={}/", dir.display()))
+            .arg(format!("--file-filter=Images | {}", patterns));
+        let mut kdialog = Command::new("kdialog");
+        kdialog
+            .arg("--getopenfilename")
+            .arg(dir)
+            .arg(format!("{}|Images", patterns));
+        vec![zenity, kdialog]
+    }
+
+    /// Glob patterns separated by spaces, as both take them
+    pub fn filter(extensions: &[&str]) -> String {
+        extensions
+            .iter()
+            .map(|ext| format!("*.{}", ext))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Both exit with 1 when closed without choosing
+    pub fn is_cancel(code: Option<i32>, _error: &str) -> bool {
+        code == Some(1)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    pub fn save_commands(_suggested: &Path) -> Vec<Command> {
+        let script = concat!(
+            "POSIX path of (choose file name ",
+            "default name (system attribute \"FERRITE_DIALOG_NAME\") ",
+            "default location (POSIX file ",
+            "(system attribute \"FERRITE_DIALOG_DIR\")))"
+        );
+        let mut osascript = Command::new("osascript");
+        osascript.args(["-e", script]);
+        vec![osascript]
+    }
+
+    pub fn open_commands(_dir: &Path, _extensions: &[&str]) -> Vec<Command> {
+        let script = concat!(
+            "set types to words of (system attribute \
+             \"FERRITE_DIALOG_FILTER\")\n",
+            "POSIX path of (choose file of type types ",
+            "default location (POSIX file ",
+            "(system attribute \"FERRITE_DIALOG_DIR\")))"
+        );
+        let mut osascript = Command::new("osascript");
+        osascript.args(["-e", script]);
+        vec![osascript]
+    }
+
+    /// Extensions separated by spaces, which `choose file` takes as types
+    pub fn filter(extensions: &[&str]) -> String {
+        extensions.join(" ")
+    }
+
+    /// AppleScript reports cancelling as error -128
+    pub fn is_cancel(_code: Option<i32>, error: &str) -> bool {
+        error.contains("-128")
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+
+    pub fn save_commands(_suggested: &Path) -> Vec<Command> {
+        let script = concat!(
+            "Add-Type -AssemblyName System.Windows.Forms; ",
+            "$d = New-Object System.Windows.Forms.SaveFileDialog; ",
+            "$d.InitialDirectory = $env:FERRITE_DIALOG_DIR; ",
+            "$d.FileName = $env:FERRITE_DIALOG_NAME; ",
+            "if ($d.ShowDialog() -eq 'OK') { $d.FileName }"
+        );
+        let mut powershell = Command::new("powershell");
+        powershell.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+        vec![powershell]
+    }
+
+    pub fn open_commands(_dir: &Path, _extensions: &[&str]) -> Vec<Command> {
+        let script = concat!(
+            "Add-Type -AssemblyName System.Windows.Forms; ",
+            "$d = New-Object System.Windows.Forms.OpenFileDialog; ",
+            "$d.InitialDirectory = $env:FERRITE_DIALOG_DIR; ",
+            "$d.Filter = $env:FERRITE_DIALOG_FILTER; ",
+            "if ($d.ShowDialog() -eq 'OK') { $d.FileName }"
+        );
+        let mut powershell = Command::new("powershell");
+        powershell.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+        vec![powershell]
+    }
+
+    /// A Windows Forms filter, e.g. `Images|*.png;*.jpg`
+    pub fn filter(extensions: &[&str]) -> String {
+        let patterns: Vec<String> = extensions
+            .iter()
+            .map(|ext| format!("*.{}", ext))
+            .collect();
+        format!("Images|{}", patterns.join(";"))
+    }
+
+    /// Cancelling prints nothing and succeeds
+    pub fn is_cancel(_code: Option<i32>, _error: &str) -> bool {
+        false
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use super::*;
+
+    pub fn save_commands(_suggested: &Path) -> Vec<Command> {
+        Vec::new()
+    }
+
+    pub fn open_commands(_dir: &Path, _extensions: &[&str]) -> Vec<Command> {
+        Vec::new()
+    }
+
+    pub fn filter(_extensions: &[&str]) -> String {
+        String::new()
+    }
+
+    pub fn is_cancel(_code: Option<i32>, _error: &str) -> bool {
+        false
+    }
+}
@@ -0,0 +1,10 @@
+//! View filters: pixel transforms applied to the displayed image without
+//! modifying the file. The same filters are used when exporting.
+
+use image::DynamicImage;
+
+pub trait ViewFilter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, image: &DynamicImage) -> DynamicImage;
+}
@@ -0,0 +1,360 @@
+//! Selection model of the gallery: which thumbnails are selected and how
+//! clicks and rubber bands change that.
+//!
+//! The selection holds paths rather than indices so it survives the folder
+//! being listed again, e.g. after files were renamed or moved.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Modifier keys held while clicking a thumbnail
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClickModifiers {
+    /// Ctrl (Cmd on macOS): toggle one item or add to the selection
+    pub toggle: bool,
+    /// Shift: select the range from the last clicked item
+    pub range:  bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Selection {
+    selected: BTreeSet<PathBuf>,
+    /// Item a Shift-click range starts from
+    anchor:   Option<PathBuf>,
+    /// Selection before the current rubber band started
+    band:     Option<BTreeSet<PathBuf>>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.selected.contains(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.selected.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    pub fn select_all(&mut self, items: &[PathBuf]) {
+        self.selected = items.iter().cloned().collect();
+    }
+
+    /// Selected paths in the order of `items`.
+    pub fn paths(&self, items: &[PathBuf]) -> Vec<PathBuf> {
+        items
+            .iter()
+            .filter(|path| self.selected.contains(*path))
+            .cloned()
+            .collect()
+    }
+
+    /// Position of the item last clicked or stepped to with the keyboard,
+    /// which Enter opens
+    pub fn focus(&self, items: &[PathBuf]) -> Option<usize> {
+        let anchor = self.anchor.as_ref()?;
+        items.iter().position(|path| path == anchor)
+    }
+
+    /// Updates the selection for a click on `items[index]`.
+    pub fn click(
+        &mut self,
+        items: &[PathBuf],
+        index: usize,
+        modifiers: ClickModifiers,
+    ) {
+        let Some(path) = items.get(index) else {
+            return;
+        };
+        let anchor = self
+            .anchor
+            .as_ref()
+            .and_then(|anchor| items.iter().position(|p| p == anchor));
+
+        match (modifiers.range, anchor) {
+            (true, Some(anchor)) => {
+                if !modifiers.toggle {
+                    self.selected.clear();
+                }
+                let (start, end) = (anchor.min(index), anchor.max(index));
+                self.selected
+                    .extend(items[start..=end].iter().cloned());
+                // The anchor stays so the range can be adjusted
+                return;
+            },
+            _ if modifiers.toggle => {
+                if !self.selected.remove(path) {
+                    self.selected.insert(path.clone());
+                }
+            },
+            _ => {
+                self.selected.clear();
+                self.selected.insert(path.clone());
+            },
+        }
+        self.anchor = Some(path.clone());
+    }
+
+    /// Starts a rubber band. With `additive` the band adds to the current
+    /// selection, otherwise it replaces it.
+    pub fn begin_band(&mut self, additive: bool) {
+        self.band = Some(if additive {
+            self.selected.clone()
+        } else {
+            BTreeSet::new()
+        });
+    }
+
+    /// Selects the items under the rubber band.
+    pub fn update_band(
+        &mut self,
+        items: &[PathBuf],
+        hits: impl IntoIterator<Item = usize>,
+    ) {
+        let Some(base) = &self.band else {
+            return;
+        };
+        self.selected = base.clone();
+        self.selected.extend(
+            hits.into_iter()
+                .filter_map(|index| items.get(index))
+                .cloned(),
+        );
+    }
+
+    pub fn end_band(&mut self) {
+        self.band = None;
+    }
+
+    pub fn is_banding(&self) -> bool {
+        self.band.is_some()
+    }
+
+    /// Follows files that were renamed or moved.
+    pub fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        for (old, new) in renamed {
+            if self.selected.remove(old) {
+                self.selected.insert(new.clone());
+            }
+            if self.anchor.as_ref() == Some(old) {
+                self.anchor = Some(new.clone());
+            }
+        }
+    }
+}
+
+/// Thumbnail cells laid out in rows of equal width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLayout {
+    pub columns: usize,
+    /// Side of a square cell, including spacing
+    pub cell:    f32,
+    pub count:   usize,
+}
+
+impl GridLayout {
+    pub fn new(width: f32, cell: f32, count: usize) -> Self {
+        Self {
+            columns: ((width / cell).floor() as usize).max(1),
+            cell,
+            count,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.count.div_ceil(self.columns)
+    }
+
+    /// Top left corner of cell `index`, relative to the grid.
+    pub fn origin(&self, index: usize) -> (f32, f32) {
+        (
+            (index % self.columns) as f32 * self.cell,
+            (index / self.columns) as f32 * self.cell,
+        )
+    }
+
+    /// Cells of the rows overlapping the band from `top` to `bottom`,
+    /// relative to the grid; only these need drawing.
+    pub fn rows_between(&self, top: f32, bottom: f32) -> Range<usize> {
+        let first = (top.max(0.0) / self.cell) as usize;
+        let last = (bottom.max(0.0) / self.cell).ceil() as usize;
+        (first * self.columns).min(self.count)
+            ..(last * self.columns).min(self.count)
+    }
+
+    /// The cell `columns` across and `rows` down from `index`, kept within
+    /// the grid.
+    pub fn step(&self, index: usize, columns: isize, rows: isize) -> usize {
+        let last = self.count.saturating_sub(1) as isize;
+        let target = index as isize + columns + rows * self.columns as isize;
+        target.clamp(0, last) as usize
+    }
+
+    /// Cell under a point relative to the grid.
+    pub fn index_at(&self, x: f32, y: f32) -> Option<usize> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let column = (x / self.cell) as usize;
+        let index = (y / self.cell) as usize * self.columns + column;
+        (column < self.columns && index < self.count).then_some(index)
+    }
+
+    /// Cells overlapping the rectangle spanned by two corners.
+    pub fn indices_in(&self, a: (f32, f32), b: (f32, f32)) -> Vec<usize> {
+        let span = |lo: f32, hi: f32, max: usize| {
+            let first = (lo.max(0.0) / self.cell) as usize;
+            let last = ((hi / self.cell).ceil() as usize).min(max);
+            first..last
+        };
+        if a.0.max(b.0) < 0.0 || a.1.max(b.1) < 0.0 {
+            return Vec::new();
+        }
+        let columns = span(a.0.min(b.0), a.0.max(b.0), self.columns);
+        let rows = span(a.1.min(b.1), a.1.max(b.1), self.rows());
+        rows.flat_map(|row| {
+            columns
+                .clone()
+                .map(move |column| row * self.columns + column)
+        })
+        .filter(|&index| index < self.count)
+        .collect()
+    }
+}
+
+/// Outcome of moving files
+#[derive(Debug, Default)]
+pub struct Moved {
+    /// Old and new paths of the files that were moved
+    pub moved:  Vec<(PathBuf, PathBuf)>,
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+/// Moves `paths` into `folder`, skipping files whose name is taken there.
+pub fn move_to_folder(paths: &[PathBuf], folder: &Path) -> Moved {
+    let mut result = Moved::default();
+    if let Err(e) = fs::create_dir_all(folder) {
+        result.failed = paths
+            .iter()
+            .map(|path| (path.clone(), io::Error::new(e.kind(), e.to_string())))
+            .collect();
+        return result;
+    }
+    for path in paths {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let target = folder.join(name);
+        let outcome = if target.exists() {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", target.display()),
+            ))
+        } else {
+            fs::rename(path, &target)
+        };
+        match outcome {
+            Ok(()) => result.moved.push((path.clone(), target)),
+            Err(e) => result.failed.push((path.clone(), e)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<PathBuf> {
+        (0..6)
+            .map(|i| PathBuf::from(format!("/photos/{}.jpg", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_click_modifiers() {
+        let items = items();
+        let mut selection = Selection::new();
+        let toggle = ClickModifiers {
+            toggle: true, range: false
+        };
+        let range = ClickModifiers {
+            toggle: false, range: true
+        };
+
+        selection.click(&items, 1, ClickModifiers::default());
+        selection.click(&items, 4, range);
+        assert_eq!(selection.paths(&items), items[1..=4].to_vec());
+
+        // Shift-click again moves the end of the range, not its start
+        selection.click(&items, 2, range);
+        assert_eq!(selection.paths(&items), items[1..=2].to_vec());
+
+        selection.click(&items, 5, toggle);
+        selection.click(&items, 1, toggle);
+        assert_eq!(selection.paths(&items), vec![
+            items[2].clone(),
+            items[5].clone()
+        ]);
+
+        selection.click(&items, 3, ClickModifiers::default());
+        assert_eq!(selection.paths(&items), vec![items[3].clone()]);
+    }
+
+    #[test]
+    fn test_rubber_band() {
+        let items = items();
+        let layout = GridLayout::new(300.0, 100.0, items.len());
+        assert_eq!(layout.columns, 3);
+        assert_eq!(layout.index_at(250.0, 150.0), Some(5));
+        assert_eq!(layout.index_at(350.0, 50.0), None);
+
+        let mut selection = Selection::new();
+        selection.click(&items, 0, ClickModifiers::default());
+        selection.begin_band(true);
+        // Dragged from the right of the second row up into the first
+        selection.update_band(
+            &items,
+            layout.indices_in((290.0, 120.0), (150.0, 80.0)),
+        );
+        selection.end_band();
+        assert_eq!(selection.paths(&items), vec![
+            items[0].clone(),
+            items[1].clone(),
+            items[2].clone(),
+            items[4].clone(),
+            items[5].clone(),
+        ]);
+    }
+
+    #[test]
+    fn test_visible_rows_and_keyboard_steps() {
+        let layout = GridLayout::new(300.0, 100.0, 7);
+        assert_eq!(layout.rows_between(150.0, 250.0), 3..7);
+        assert_eq!(layout.rows_between(-40.0, 90.0), 0..3);
+        assert_eq!(layout.rows_between(400.0, 500.0), 7..7);
+
+        assert_eq!(layout.step(1, 0, 1), 4);
+        assert_eq!(layout.step(5, 0, 1), 6);
+        assert_eq!(layout.step(1, -1, -1), 0);
+        assert_eq!(layout.step(2, 1, 0), 3);
+
+        let items = items();
+        let mut selection = Selection::new();
+        assert_eq!(selection.focus(&items), None);
+        selection.click(&items, 4, ClickModifiers::default());
+        assert_eq!(selection.focus(&items), Some(4));
+    }
+}
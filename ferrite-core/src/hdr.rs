@@ -0,0 +1,420 @@
+//! Merging of bracketed exposures into one high dynamic range image.
+//!
+//! Brackets are found by capture time and exposure settings from EXIF. The
+//! frames are converted to linear light, divided by their exposure and
+//! averaged with weights that fall off towards black and white, so every
+//! part of the scene comes from the frames that exposed it well. Frames are
+//! expected to line up, as when shot from a tripod.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::{DynamicImage, GenericImageView, Rgb32FImage, RgbImage, RgbaImage};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::{
+    image::{self as images, ImageLoadError},
+    metadata::{self, ExposureInfo},
+    rename::Timestamp,
+};
+
+/// Frames further apart than this are not part of the same bracket
+const BRACKET_GAP_SECS: i64 = 2;
+/// Smallest exposure difference between frames of a bracket, in EV
+const MIN_EV_STEP: f64 = 0.3;
+/// Below this total weight a pixel is clipped in every frame
+const MIN_WEIGHT: f32 = 1e-3;
+
+#[derive(Error, Debug)]
+pub enum HdrError {
+    #[error("Merging needs at least two frames")]
+    TooFewFrames,
+
+    #[error("{0} has no exposure time in its EXIF data")]
+    NoExposure(PathBuf),
+
+    #[error("{path} is {found:?}, expected {expected:?} like the first frame")]
+    SizeMismatch {
+        path:     PathBuf,
+        expected: (u32, u32),
+        found:    (u32, u32),
+    },
+
+    #[error(transparent)]
+    Load(#[from] ImageLoadError),
+
+    #[error("Failed to write result: {0}")]
+    Save(#[from] image::ImageError),
+}
+
+/// A photo that can be part of a bracket
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub path:     PathBuf,
+    /// Seconds since the epoch, from `DateTimeOriginal`
+    pub taken:    i64,
+    pub exposure: ExposureInfo,
+}
+
+impl Frame {
+    /// Returns `None` for files without capture time or shutter time.
+    pub fn read(path: &Path) -> Option<Self> {
+        let exif = metadata::read_exif(path)?;
+        let taken = metadata::ascii_field(&exif, exif::Tag::DateTimeOriginal)
+            .and_then(|text| Timestamp::from_exif(&text))?;
+        Some(Self {
+            path:     path.to_path_buf(),
+            taken:    taken.seconds(),
+            exposure: ExposureInfo::from_exif(&exif)?,
+        })
+    }
+
+    fn ev(&self) -> f64 {
+        self.exposure.relative_exposure().log2()
+    }
+}
+
+/// Groups frames taken in quick succession at different exposures. A frame
+/// at an exposure already in the group starts a new bracket, so plain
+/// bursts are not mistaken for brackets.
+pub fn find_brackets(mut frames: Vec<Frame>) -> Vec<Vec<Frame>> {
+    frames.sort_by(|a, b| (a.taken, &a.path).cmp(&(b.taken, &b.path)));
+
+    let mut brackets = Vec::new();
+    let mut current: Vec<Frame> = Vec::new();
+    for frame in frames {
+        let joins = current.last().is_some_and(|last| {
+            frame.taken - last.taken <= BRACKET_GAP_SECS
+                && current
+                    .iter()
+                    .all(|f| (f.ev() - frame.ev()).abs() >= MIN_EV_STEP)
+        });
+        if !joins {
+            brackets.push(std::mem::take(&mut current));
+        }
+        current.push(frame);
+    }
+    brackets.push(current);
+    brackets.retain(|bracket| bracket.len() >= 2);
+    brackets
+}
+
+/// How the merged radiance is mapped to displayable values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    /// Compresses highlights smoothly, keeping colors
+    #[default]
+    Reinhard,
+    /// Filmic curve with more contrast
+    Filmic,
+    /// Linear, clipping what does not fit
+    Clip,
+}
+
+impl ToneMap {
+    pub const ALL: [ToneMap; 3] =
+        [ToneMap::Reinhard, ToneMap::Filmic, ToneMap::Clip];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ToneMap::Reinhard => "Reinhard",
+            ToneMap::Filmic => "Filmic (ACES)",
+            ToneMap::Clip => "Clip",
+        }
+    }
+}
+
+/// Decodes and merges the frames at `paths`.
+pub fn merge_files(
+    paths: &[PathBuf],
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<Rgb32FImage, HdrError> {
+    let frames = paths
+        .par_iter()
+        .map(|path| {
+            let exposure = metadata::read_exposure(path)
+                .ok_or_else(|| HdrError::NoExposure(path.clone()))?;
+            let image = images::decode_file(path, decoding, io)?;
+            Ok((path.clone(), image, exposure.relative_exposure()))
+        })
+        .collect::<Result<Vec<_>, HdrError>>()?;
+
+    let size = frames
+        .first()
+        .map(|(_, image, _)| image.dimensions())
+        .unwrap_or_default();
+    if let Some((path, image, _)) = frames
+        .iter()
+        .find(|(_, image, _)| image.dimensions() != size)
+    {
+        return Err(HdrError::SizeMismatch {
+            path:     path.clone(),
+            expected: size,
+            found:    image.dimensions(),
+        });
+    }
+    let frames: Vec<(DynamicImage, f64)> = frames
+        .into_iter()
+        .map(|(_, image, exposure)| (image, exposure))
+        .collect();
+    merge(&frames)
+}
+
+/// Merges frames given with their relative exposures. The result is in
+/// linear light, scaled so the middle exposure keeps its brightness.
+pub fn merge(frames: &[(DynamicImage, f64)]) -> Result<Rgb32FImage, HdrError> {
+    if frames.len() < 2 {
+        return Err(HdrError::TooFewFrames);
+    }
+    let mut exposures: Vec<f64> = frames.iter().map(|(_, e)| *e).collect();
+    exposures.sort_by(f64::total_cmp);
+    let reference = exposures[exposures.len() / 2];
+
+    // Darkest first, for the fallback of clipped pixels
+    let mut frames: Vec<_> = frames
+        .iter()
+        .map(|(image, exposure)| {
+            (image.to_rgb16(), (exposure / reference) as f32)
+        })
+        .collect();
+    frames.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let linear: Vec<f32> = (0..=u16::MAX)
+        .map(|v| srgb_to_linear(v as f32 / u16::MAX as f32))
+        .collect();
+    let (width, height) = frames[0].0.dimensions();
+    let mut result = Rgb32FImage::new(width, height);
+    result
+        .par_chunks_mut(width as usize * 3)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(3).enumerate() {
+                for (c, out) in pixel.iter_mut().enumerate() {
+                    let mut sum = 0.0;
+                    let mut weights = 0.0;
+                    for (image, exposure) in &frames {
+                        let value = image.get_pixel(x as u32, y as u32)[c];
+                        let z = value as f32 / u16::MAX as f32;
+                        let weight = 1.0 - (2.0 * z - 1.0).abs();
+                        sum += weight * linear[value as usize] / exposure;
+                        weights += weight;
+                    }
+                    *out = if weights > MIN_WEIGHT {
+                        sum / weights
+                    } else {
+                        // Blown out everywhere: the darkest frame knows most.
+                        // Black everywhere: the brightest one does.
+                        let (darkest, exposure) = &frames[0];
+                        let value = darkest.get_pixel(x as u32, y as u32)[c];
+                        let (image, exposure) = if value > u16::MAX / 2 {
+                            (darkest, exposure)
+                        } else {
+                            let (brightest, exposure) =
+                                &frames[frames.len() - 1];
+                            (brightest, exposure)
+                        };
+                        let value = image.get_pixel(x as u32, y as u32)[c];
+                        linear[value as usize] / exposure
+                    };
+                }
+            }
+        });
+    Ok(result)
+}
+
+/// Tone mapping of an HDR image as it is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub method: ToneMap,
+    /// Exposure adjustment in stops, applied before mapping
+    pub ev:     f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            method: ToneMap::default(), ev: 0.0
+        }
+    }
+}
+
+/// Maps linear `rgb` to 8-bit sRGB after multiplying it by `scale`
+fn map_pixel(rgb: &[f32], method: ToneMap, scale: f32) -> [u8; 3] {
+    let rgb = [rgb[0] * scale, rgb[1] * scale, rgb[2] * scale];
+    let mapped = match method {
+        ToneMap::Reinhard => {
+            let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            let factor =
+                if luminance > 0.0 { 1.0 / (1.0 + luminance) } else { 1.0 };
+            rgb.map(|v| v * factor)
+        },
+        ToneMap::Filmic => rgb.map(|v| {
+            // Narkowicz's fit of the ACES curve
+            (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14)
+        }),
+        ToneMap::Clip => rgb,
+    };
+    mapped.map(|v| (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0).round() as u8)
+}
+
+/// Maps merged radiance to an 8-bit sRGB image, brightened or darkened by
+/// `ev` first.
+pub fn tone_map(hdr: &Rgb32FImage, method: ToneMap, ev: f32) -> RgbImage {
+    let scale = ev.exp2();
+    let mut out = RgbImage::new(hdr.width(), hdr.height());
+    out.par_chunks_mut(3)
+        .zip(hdr.par_chunks(3))
+        .for_each(|(out, pixel)| {
+            out.copy_from_slice(&map_pixel(pixel, method, scale))
+        });
+    out
+}
+
+/// Whether `image` holds linear values that may exceed 1, as decoded from
+/// OpenEXR and Radiance HDR files
+pub fn is_hdr(image: &DynamicImage) -> bool {
+    matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    )
+}
+
+/// Tone-maps an HDR image for display, keeping its alpha
+pub fn tone_map_rgba(image: &DynamicImage, tone: ToneMapping) -> RgbaImage {
+    let scale = tone.ev.exp2();
+    let converted;
+    let rgba = match image.as_rgba32f() {
+        Some(rgba) => rgba,
+        None => {
+            converted = image.to_rgba32f();
+            &converted
+        },
+    };
+    let mut out = RgbaImage::new(rgba.width(), rgba.height());
+    out.par_chunks_mut(4)
+        .zip(rgba.par_chunks(4))
+        .for_each(|(out, pixel)| {
+            out[..3].copy_from_slice(&map_pixel(pixel, tone.method, scale));
+            out[3] = (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+    out
+}
+
+/// Writes the merged radiance as OpenEXR.
+#[cfg(feature = "exr")]
+pub fn save_exr(hdr: &Rgb32FImage, path: &Path) -> Result<(), HdrError> {
+    hdr.save_with_format(path, image::ImageFormat::OpenExr)?;
+    Ok(())
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn frame(name: &str, taken: i64, exposure_time: f64) -> Frame {
+        Frame {
+            path: PathBuf::from(name),
+            taken,
+            exposure: ExposureInfo {
+                exposure_time,
+                f_number: Some(8.0),
+                iso: Some(100.0),
+                bias: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_find_brackets() {
+        let frames = vec![
+            frame("a", 100, 1.0 / 250.0),
+            frame("b", 100, 1.0 / 60.0),
+            frame("c", 101, 1.0 / 15.0),
+            // Same exposure as `a`: a new bracket starts
+            frame("d", 102, 1.0 / 250.0),
+            frame("e", 102, 1.0 / 60.0),
+            // Too late for it
+            frame("f", 200, 1.0 / 15.0),
+        ];
+        let names: Vec<Vec<String>> = find_brackets(frames)
+            .iter()
+            .map(|bracket| {
+                bracket
+                    .iter()
+                    .map(|f| f.path.display().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(names, vec![vec!["a", "b", "c"], vec!["d", "e"]]);
+    }
+
+    #[test]
+    fn test_merge_recovers_clipped_highlights() {
+        // Two patches, the right one four times brighter in the scene.
+        // The long exposure clips it, the short one keeps it.
+        let scene = |exposure: f32| {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(2, 1, |x, _| {
+                let radiance = if x == 0 { 0.1 } else { 0.4 };
+                let v = (linear_to_srgb((radiance * exposure).min(1.0)) * 255.0)
+                    .round() as u8;
+                Rgb([v, v, v])
+            }))
+        };
+        let frames = [(scene(1.0), 1.0), (scene(4.0), 4.0)];
+        let hdr = merge(&frames).unwrap();
+        // The reference is the longer exposure, at index len / 2
+        let ratio = hdr.get_pixel(1, 0)[0] / hdr.get_pixel(0, 0)[0];
+        assert!((ratio - 4.0).abs() < 0.2, "ratio {}", ratio);
+
+        let mapped = tone_map(&hdr, ToneMap::Reinhard, 0.0);
+        assert!(mapped.get_pixel(1, 0)[0] < 255);
+        assert!(mapped.get_pixel(0, 0)[0] < mapped.get_pixel(1, 0)[0]);
+    }
+
+    #[test]
+    fn test_tone_mapping_follows_exposure_and_keeps_alpha() {
+        let image = DynamicImage::ImageRgba32F(
+            image::Rgba32FImage::from_raw(2, 1, vec![
+                4.0, 4.0, 4.0, 0.5, 0.18, 0.18, 0.18, 1.0,
+            ])
+            .unwrap(),
+        );
+        let shown = |method, ev| {
+            let mapped = tone_map_rgba(&image, ToneMapping {
+                method,
+                ev,
+            });
+            [mapped.get_pixel(0, 0).0, mapped.get_pixel(1, 0).0]
+        };
+
+        // Four times white clips, unless mapped or darkened two stops
+        assert_eq!(shown(ToneMap::Clip, 0.0)[0], [255, 255, 255, 128]);
+        assert_eq!(shown(ToneMap::Clip, -2.0)[0], [255, 255, 255, 128]);
+        let [bright, mid] = shown(ToneMap::Reinhard, 0.0);
+        assert!(bright[0] < 255 && bright[0] > mid[0]);
+        assert_eq!(bright[3], 128);
+        // Exposure brightens what the operator leaves alone
+        assert!(
+            shown(ToneMap::Filmic, 1.0)[1][0]
+                > shown(ToneMap::Filmic, 0.0)[1][0]
+        );
+    }
+}
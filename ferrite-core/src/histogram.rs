@@ -0,0 +1,124 @@
+//! Per-channel histograms of 8-bit values.
+
+use image::DynamicImage;
+use rayon::prelude::*;
+
+/// Rows counted per parallel task
+const ROWS_PER_TASK: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Luminance,
+    Red,
+    Green,
+    Blue,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] =
+        [Channel::Luminance, Channel::Red, Channel::Green, Channel::Blue];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Channel::Luminance => "Luminance",
+            Channel::Red => "Red",
+            Channel::Green => "Green",
+            Channel::Blue => "Blue",
+        }
+    }
+}
+
+/// Number of pixels with each value, per channel. Alpha is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    bins: [[u32; 256]; 4],
+}
+
+impl Histogram {
+    fn empty() -> Self {
+        Self {
+            bins: [[0; 256]; 4]
+        }
+    }
+
+    pub fn bins(&self, channel: Channel) -> &[u32; 256] {
+        &self.bins[channel as usize]
+    }
+
+    /// Highest count of any value in `channels`, for scaling the plot
+    pub fn peak(&self, channels: impl IntoIterator<Item = Channel>) -> u32 {
+        channels
+            .into_iter()
+            .flat_map(|channel| self.bins(channel).iter().copied())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (bins, other) in self.bins.iter_mut().zip(&other.bins) {
+            for (count, other) in bins.iter_mut().zip(other) {
+                *count += other;
+            }
+        }
+    }
+}
+
+/// Counts every pixel of `image`, spread over all cores. Luminance uses
+/// the Rec. 709 weights on the stored values.
+pub fn compute(image: &DynamicImage) -> Histogram {
+    let rgb = image.to_rgb8();
+    let row = rgb.width() as usize * 3;
+    if row == 0 {
+        return Histogram::empty();
+    }
+    rgb.as_raw()
+        .par_chunks(row * ROWS_PER_TASK)
+        .map(|rows| {
+            let mut histogram = Histogram::empty();
+            for pixel in rows.chunks_exact(3) {
+                let [r, g, b] = [pixel[0], pixel[1], pixel[2]];
+                let luminance =
+                    (2126 * r as u32 + 7152 * g as u32 + 722 * b as u32 + 5000)
+                        / 10_000;
+                histogram.bins[Channel::Luminance as usize]
+                    [luminance as usize] += 1;
+                histogram.bins[Channel::Red as usize][r as usize] += 1;
+                histogram.bins[Channel::Green as usize][g as usize] += 1;
+                histogram.bins[Channel::Blue as usize][b as usize] += 1;
+            }
+            histogram
+        })
+        .reduce(Histogram::empty, |mut total, part| {
+            total.add(&part);
+            total
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_counts_every_pixel_per_channel() {
+        let mut image = RgbImage::from_pixel(10, 200, Rgb([255, 0, 10]));
+        image.put_pixel(3, 150, Rgb([255, 255, 255]));
+        let histogram = compute(&DynamicImage::ImageRgb8(image));
+
+        assert_eq!(histogram.bins(Channel::Red)[255], 2000);
+        assert_eq!(histogram.bins(Channel::Green)[0], 1999);
+        assert_eq!(histogram.bins(Channel::Green)[255], 1);
+        assert_eq!(histogram.bins(Channel::Blue)[10], 1999);
+        // 0.2126 * 255 + 0.0722 * 10
+        assert_eq!(histogram.bins(Channel::Luminance)[55], 1999);
+        assert_eq!(histogram.bins(Channel::Luminance)[255], 1);
+        assert_eq!(histogram.peak(Channel::ALL), 2000);
+        assert_eq!(histogram.peak([Channel::Green]), 1999);
+    }
+
+    #[test]
+    fn test_empty_image() {
+        let histogram = compute(&DynamicImage::new_rgb8(0, 0));
+        assert_eq!(histogram.peak(Channel::ALL), 0);
+    }
+}
@@ -0,0 +1,185 @@
+//! Decoded images kept in memory for stepping back and forth without
+//! decoding again.
+//!
+//! The cache is bounded by the decoded size of its images rather than their
+//! number, so a few very large photos cannot exhaust memory. The least
+//! recently used go first; an image larger than the whole budget is not
+//! kept at all. The images are shared with the one on screen rather than
+//! copied, so the current image is held once.
+
+use image::DynamicImage;
+use lru::LruCache;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+struct Cached {
+    absolute: PathBuf,
+    image:    Arc<DynamicImage>,
+    /// Modification time when decoded, to notice edits
+    modified: Option<SystemTime>,
+}
+
+/// Decoded files keyed by the path they were requested under
+pub struct ImageCache {
+    entries: LruCache<PathBuf, Cached>,
+    bytes:   u64,
+    budget:  u64,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn size(image: &DynamicImage) -> u64 {
+    image.as_bytes().len() as u64
+}
+
+impl ImageCache {
+    /// A cache holding at most `budget` bytes of decoded pixels
+    pub fn new(budget: u64) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            bytes: 0,
+            budget,
+        }
+    }
+
+    /// Whether anything can be kept at all
+    pub fn is_enabled(&self) -> bool {
+        self.budget > 0
+    }
+
+    /// Decoded size of the images held
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.contains(path)
+    }
+
+    /// The absolute path and image of `path`, unless the file changed since
+    /// it was decoded
+    pub fn get(&mut self, path: &Path) -> Option<(PathBuf, Arc<DynamicImage>)> {
+        let fresh = self
+            .entries
+            .get(path)?
+            .modified
+            .is_some_and(|time| modified(path) == Some(time));
+        if !fresh {
+            self.remove(path);
+            return None;
+        }
+        self.entries
+            .get(path)
+            .map(|cached| (cached.absolute.clone(), Arc::clone(&cached.image)))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    /// Keeps `image` as the most recently used, evicting others to stay
+    /// within the budget.
+    pub fn insert(
+        &mut self,
+        path: PathBuf,
+        absolute: PathBuf,
+        image: Arc<DynamicImage>,
+    ) {
+        self.remove(&path);
+        let bytes = size(&image);
+        if bytes > self.budget {
+            return;
+        }
+        let modified = modified(&path);
+        self.bytes += bytes;
+        self.entries.put(path, Cached {
+            absolute,
+            image,
+            modified,
+        });
+        while self.bytes > self.budget {
+            match self.entries.pop_lru() {
+                Some((_, evicted)) => self.bytes -= size(&evicted.image),
+                None => break,
+            }
+        }
+    }
+
+    /// Keeps the image of `old` under `new`, a file renamed or moved
+    /// elsewhere in the app, as the file itself is unchanged.
+    pub fn rename(&mut self, old: &Path, new: &Path, absolute: PathBuf) {
+        if let Some(mut cached) = self.entries.pop(old) {
+            cached.absolute = absolute;
+            self.entries.put(new.to_path_buf(), cached);
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(cached) = self.entries.pop(path) {
+            self.bytes -= size(&cached.image);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    /// An RGBA image of `side`² pixels, 4 bytes each
+    fn square(side: u32) -> Arc<DynamicImage> {
+        Arc::new(DynamicImage::ImageRgba8(RgbaImage::new(side, side)))
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_beyond_budget() {
+        let mut cache = ImageCache::new(3 * 400);
+        for name in ["a", "b", "c"] {
+            cache.insert(name.into(), name.into(), square(10));
+        }
+        assert_eq!(cache.bytes(), 1200);
+        // Missing files are never fresh, so mark `a` as used directly
+        cache.entries.get(Path::new("a"));
+        cache.insert("d".into(), "d".into(), square(10));
+        assert!(!cache.contains(Path::new("b")));
+        assert!(cache.contains(Path::new("a")));
+
+        // One large image displaces the rest, a larger one is not kept
+        cache.insert("e".into(), "e".into(), square(17));
+        assert_eq!(cache.bytes(), 17 * 17 * 4);
+        cache.insert("f".into(), "f".into(), square(18));
+        assert!(!cache.contains(Path::new("f")));
+        assert!(cache.contains(Path::new("e")));
+    }
+
+    #[test]
+    fn test_edited_file_is_decoded_again() {
+        let dir = std::env::temp_dir().join("ferrite-image-cache");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.png");
+        fs::write(&path, b"original").unwrap();
+
+        let mut cache = ImageCache::new(1 << 20);
+        cache.insert(path.clone(), path.clone(), square(4));
+        assert_eq!(cache.get(&path).unwrap().1.width(), 4);
+
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(cache.get(&path).is_none());
+        assert_eq!(cache.bytes(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,15 +1,26 @@
 use eframe::egui;
 use image::DynamicImage;
+use std::sync::Arc;
+
+use super::TiledTexture;
 
 pub struct ImageData {
     pub(crate) texture:  Option<egui::TextureHandle>,
-    pub(crate) original: DynamicImage,
+    /// Halved copies of `texture` for zooming out, smallest last
+    pub(crate) mipmaps:  Vec<egui::TextureHandle>,
+    /// Set when the image is too large for one texture
+    pub(crate) tiles:    Option<TiledTexture>,
+    /// Shared with the cache, which keeps the same pixels for reopening
+    pub(crate) original: Arc<DynamicImage>,
 }
 
 impl ImageData {
-    pub fn new(image: DynamicImage) -> Self {
+    pub fn new(image: impl Into<Arc<DynamicImage>>) -> Self {
         Self {
-            texture: None, original: image
+            texture:  None,
+            mipmaps:  Vec::new(),
+            tiles:    None,
+            original: image.into(),
         }
     }
 
@@ -0,0 +1,63 @@
+//! Conversion of decoded pixels to the 8-bit RGBA that textures hold.
+//!
+//! HDR images are tone-mapped, deeper images dithered, the view adjustments
+//! applied and the result converted for the monitor's color profile if one
+//! is configured. Tiles of
+//! large images are converted the same way as the image as a whole.
+
+use ferrite_config::{DitherMode, FerriteConfig};
+use image::{DynamicImage, RgbaImage};
+
+use super::icc::DisplayProfile;
+use crate::{
+    adjust::Adjustments,
+    dither,
+    hdr::{self, ToneMapping},
+};
+
+/// How the image is shown, as set in the panels rather than the config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Appearance {
+    /// For HDR images only
+    pub tone:   ToneMapping,
+    pub adjust: Adjustments,
+}
+
+#[derive(Clone)]
+pub struct DisplayConversion {
+    dither:  DitherMode,
+    tone:    ToneMapping,
+    adjust:  Adjustments,
+    profile: Option<DisplayProfile>,
+}
+
+impl DisplayConversion {
+    /// The conversion `config` asks for, with `appearance`
+    pub fn new(config: &FerriteConfig, appearance: Appearance) -> Self {
+        let profile = config
+            .color
+            .display_profile
+            .as_deref()
+            .filter(|_| config.decoding.color_management)
+            .and_then(DisplayProfile::load);
+        Self {
+            dither: config.color.dither,
+            tone: appearance.tone,
+            adjust: appearance.adjust,
+            profile,
+        }
+    }
+
+    pub fn apply(&self, image: &DynamicImage) -> RgbaImage {
+        let mut pixels = if hdr::is_hdr(image) {
+            hdr::tone_map_rgba(image, self.tone)
+        } else {
+            dither::to_display(image, self.dither)
+        };
+        self.adjust.apply(&mut pixels);
+        match &self.profile {
+            Some(profile) => profile.apply(pixels),
+            None => pixels,
+        }
+    }
+}
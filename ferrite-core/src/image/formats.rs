@@ -7,9 +7,48 @@ pub struct SupportedFormats;
 
 impl SupportedFormats {
     /// List of supported image extensions in lowercase.
-    /// These match the formats that the `image` crate can decode.
-    pub const EXTENSIONS: &'static [&'static str] =
-        &["jpg", "jpeg", "png", "gif", "bmp", "ico", "tiff", "tga", "webp"];
+    /// These match the formats the `image` crate was built with, plus HEIC
+    /// through libheif; each one other than PNG is behind a cargo feature
+    /// (`heic` for both HEIC names).
+    pub const EXTENSIONS: &'static [&'static str] = &[
+        #[cfg(feature = "jpeg")]
+        "jpg",
+        #[cfg(feature = "jpeg")]
+        "jpeg",
+        "png",
+        #[cfg(feature = "gif")]
+        "gif",
+        #[cfg(feature = "bmp")]
+        "bmp",
+        #[cfg(feature = "ico")]
+        "ico",
+        #[cfg(feature = "tiff")]
+        "tiff",
+        #[cfg(feature = "tiff")]
+        "tif",
+        #[cfg(feature = "tga")]
+        "tga",
+        #[cfg(feature = "webp")]
+        "webp",
+        #[cfg(feature = "exr")]
+        "exr",
+        #[cfg(feature = "hdr")]
+        "hdr",
+        #[cfg(feature = "raw")]
+        "cr2",
+        #[cfg(feature = "raw")]
+        "nef",
+        #[cfg(feature = "raw")]
+        "arw",
+        #[cfg(feature = "raw")]
+        "dng",
+        #[cfg(feature = "avif")]
+        "avif",
+        #[cfg(feature = "heic")]
+        "heic",
+        #[cfg(feature = "heic")]
+        "heif",
+    ];
 
     /// Checks if a given file extension is supported by the image viewer.
     /// The check is case-insensitive to handle files with uppercase extensions.
@@ -27,12 +66,51 @@ impl SupportedFormats {
             .unwrap_or(false)
     }
 
+    /// MIME type for a supported extension, used when registering file
+    /// associations.
+    pub fn mime_type(extension: &str) -> Option<&'static str> {
+        let mime = match extension {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "bmp" => "image/bmp",
+            "ico" => "image/vnd.microsoft.icon",
+            "tiff" | "tif" => "image/tiff",
+            "tga" => "image/x-tga",
+            "webp" => "image/webp",
+            "exr" => "image/x-exr",
+            "hdr" => "image/vnd.radiance",
+            "cr2" => "image/x-canon-cr2",
+            "nef" => "image/x-nikon-nef",
+            "arw" => "image/x-sony-arw",
+            "dng" => "image/x-adobe-dng",
+            "avif" => "image/avif",
+            "heic" => "image/heic",
+            "heif" => "image/heif",
+            _ => return None,
+        };
+        Self::EXTENSIONS
+            .contains(&extension)
+            .then_some(mime)
+    }
+
+    /// MIME types of all supported formats, without duplicates
+    pub fn mime_types() -> Vec<&'static str> {
+        let mut mimes: Vec<&str> = Self::EXTENSIONS
+            .iter()
+            .filter_map(|e| Self::mime_type(e))
+            .collect();
+        mimes.dedup();
+        mimes
+    }
+
     /// Gets a formatted string of all supported extensions for display
     /// purposes. Useful for error messages or UI elements that need to show
     /// supported formats.
     ///
     /// # Returns
     /// A string like "jpg, jpeg, png, gif, bmp, ico, tiff, tga, webp"
+    #[allow(dead_code)]
     pub fn supported_formats_string() -> String {
         Self::EXTENSIONS.join(", ")
     }
@@ -41,14 +119,17 @@ impl SupportedFormats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::ffi::OsString;
 
     #[test]
     fn test_supported_extensions() {
         // Test valid extensions with different cases
-        assert!(SupportedFormats::is_supported(Some(OsStr::new("jpg"))));
-        assert!(SupportedFormats::is_supported(Some(OsStr::new("JPG"))));
+        assert!(SupportedFormats::is_supported(Some(OsStr::new("png"))));
         assert!(SupportedFormats::is_supported(Some(OsStr::new("PNG"))));
+        #[cfg(feature = "jpeg")]
+        {
+            assert!(SupportedFormats::is_supported(Some(OsStr::new("jpg"))));
+            assert!(SupportedFormats::is_supported(Some(OsStr::new("JPG"))));
+        }
 
         // Test invalid extensions
         assert!(!SupportedFormats::is_supported(Some(OsStr::new("txt"))));
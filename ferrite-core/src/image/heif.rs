@@ -0,0 +1,94 @@
+//! HEIC/HEIF decoding through libheif, for the photos phones save by
+//! default. The `image` crate has no decoder for them.
+
+use image::{
+    error::{DecodingError, ImageFormatHint},
+    DynamicImage,
+    ImageError,
+    ImageResult,
+    RgbaImage,
+};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+/// Major brands of HEVC coded HEIF files. `mif1` is left out as AVIF files
+/// use it too.
+const BRANDS: &[&[u8; 4]] =
+    &[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx"];
+
+/// Whether `bytes` start like a HEIC file
+pub fn is_heif(bytes: &[u8]) -> bool {
+    bytes.len() >= 12
+        && &bytes[4..8] == b"ftyp"
+        && BRANDS.iter().any(|brand| &bytes[8..12] == *brand)
+}
+
+fn decoding_error(
+    e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("HEIF".to_string()),
+        e,
+    ))
+}
+
+/// Size of the primary image, read without decoding it
+pub fn dimensions(bytes: &[u8]) -> ImageResult<(u32, u32)> {
+    let context =
+        HeifContext::read_from_bytes(bytes).map_err(decoding_error)?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(decoding_error)?;
+    Ok((handle.width(), handle.height()))
+}
+
+/// Decodes the primary image to 8 bit RGB, or RGBA if it has alpha.
+pub fn decode(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let lib = LibHeif::new();
+    let context =
+        HeifContext::read_from_bytes(bytes).map_err(decoding_error)?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(decoding_error)?;
+    let image = lib
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+        .map_err(decoding_error)?;
+
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| decoding_error("libheif returned no RGBA plane"))?;
+    // Rows may be padded
+    let row = plane.width as usize * 4;
+    let mut data = Vec::with_capacity(row * plane.height as usize);
+    for y in 0..plane.height as usize {
+        data.extend_from_slice(&plane.data[y * plane.stride..][..row]);
+    }
+    let rgba = RgbaImage::from_raw(plane.width, plane.height, data)
+        .ok_or_else(|| decoding_error("RGBA plane is too short"))?;
+    Ok(if handle.has_alpha_channel() {
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recognizes_heic_but_not_avif() {
+        let header = |brand: &[u8; 4]| {
+            let mut bytes = vec![0, 0, 0, 24];
+            bytes.extend_from_slice(b"ftyp");
+            bytes.extend_from_slice(brand);
+            bytes.extend_from_slice(&[0; 12]);
+            bytes
+        };
+        assert!(is_heif(&header(b"heic")));
+        assert!(is_heif(&header(b"heix")));
+        assert!(!is_heif(&header(b"avif")));
+        assert!(!is_heif(&header(b"mif1")));
+        assert!(!is_heif(b"ftypheic"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Color management with ICC profiles.
+//!
+//! Everything past decoding assumes sRGB pixels, but photos from wide-gamut
+//! cameras and editors often come in Adobe RGB or Display P3 with the
+//! profile embedded. Drawn as sRGB they look dull or shifted. Such files are
+//! converted to sRGB once decoded, and sRGB is converted to the monitor's
+//! profile, if one is configured, just before the pixels are uploaded.
+
+use image::{
+    DynamicImage,
+    ImageBuffer,
+    ImageDecoder,
+    ImageFormat,
+    Pixel,
+    RgbaImage,
+};
+use moxcms::{
+    ColorProfile,
+    DataColorSpace,
+    Layout,
+    Transform8BitExecutor,
+    TransformOptions,
+};
+use std::{fs, io::Cursor, path::Path, sync::Arc};
+use tracing::{debug, warn};
+
+/// The ICC profile embedded in a file read into `bytes`, for the formats
+/// whose headers can be read without decoding the pixels
+pub fn read(bytes: &[u8]) -> Option<Vec<u8>> {
+    let cursor = Cursor::new(bytes);
+    match image::guess_format(bytes).ok()? {
+        ImageFormat::Png => image::codecs::png::PngDecoder::new(cursor)
+            .ok()?
+            .icc_profile(),
+        #[cfg(feature = "jpeg")]
+        ImageFormat::Jpeg => image::codecs::jpeg::JpegDecoder::new(cursor)
+            .ok()?
+            .icc_profile(),
+        #[cfg(feature = "tiff")]
+        ImageFormat::Tiff => image::codecs::tiff::TiffDecoder::new(cursor)
+            .ok()?
+            .icc_profile(),
+        _ => None,
+    }
+}
+
+/// Runs `pixels`, laid out as `layout`, through a transform from `source`
+/// to sRGB
+fn convert<P: Pixel<Subpixel = u8>>(
+    pixels: &ImageBuffer<P, Vec<u8>>,
+    layout: Layout,
+    source: &ColorProfile,
+) -> Option<ImageBuffer<P, Vec<u8>>> {
+    let transform = source
+        .create_transform_8bit(
+            layout,
+            &ColorProfile::new_srgb(),
+            layout,
+            TransformOptions::default(),
+        )
+        .map_err(|e| warn!("Unusable color profile: {}", e))
+        .ok()?;
+    let mut converted = ImageBuffer::new(pixels.width(), pixels.height());
+    transform.transform(pixels, &mut converted).ok()?;
+    Some(converted)
+}
+
+fn convert_16<P: Pixel<Subpixel = u16>>(
+    pixels: &ImageBuffer<P, Vec<u16>>,
+    layout: Layout,
+    source: &ColorProfile,
+) -> Option<ImageBuffer<P, Vec<u16>>> {
+    let transform = source
+        .create_transform_16bit(
+            layout,
+            &ColorProfile::new_srgb(),
+            layout,
+            TransformOptions::default(),
+        )
+        .map_err(|e| warn!("Unusable color profile: {}", e))
+        .ok()?;
+    let mut converted = ImageBuffer::new(pixels.width(), pixels.height());
+    transform.transform(pixels, &mut converted).ok()?;
+    Some(converted)
+}
+
+/// Converts `image` from the color space described by `profile` to sRGB.
+/// Images the profile does not fit, such as grayscale ones, and float
+/// images, which hold linear values already, are returned unchanged.
+pub fn to_srgb(image: DynamicImage, profile: &[u8]) -> DynamicImage {
+    let source = match ColorProfile::new_from_slice(profile) {
+        Ok(source) => source,
+        Err(e) => {
+            warn!("Ignoring unreadable color profile: {}", e);
+            return image;
+        },
+    };
+    if source.color_space != DataColorSpace::Rgb {
+        debug!("Not converting from {:?} profile", source.color_space);
+        return image;
+    }
+    let converted = match &image {
+        DynamicImage::ImageRgb8(rgb) => {
+            convert(rgb, Layout::Rgb, &source).map(DynamicImage::ImageRgb8)
+        },
+        DynamicImage::ImageRgba8(rgba) => {
+            convert(rgba, Layout::Rgba, &source).map(DynamicImage::ImageRgba8)
+        },
+        DynamicImage::ImageRgb16(rgb) => {
+            convert_16(rgb, Layout::Rgb, &source).map(DynamicImage::ImageRgb16)
+        },
+        DynamicImage::ImageRgba16(rgba) => {
+            convert_16(rgba, Layout::Rgba, &source)
+                .map(DynamicImage::ImageRgba16)
+        },
+        _ => None,
+    };
+    converted.unwrap_or(image)
+}
+
+/// Conversion from sRGB to the profile of the monitor
+#[derive(Clone)]
+pub struct DisplayProfile(Arc<Transform8BitExecutor>);
+
+impl DisplayProfile {
+    /// Reads the ICC profile at `path`, logging why it cannot be used
+    pub fn load(path: &Path) -> Option<Self> {
+        let bytes = fs::read(path)
+            .map_err(|e| {
+                warn!(
+                    "Failed to read display profile {}: {}",
+                    path.display(),
+                    e
+                )
+            })
+            .ok()?;
+        Self::from_icc(&bytes)
+            .map_err(|e| {
+                warn!("Unusable display profile {}: {}", path.display(), e)
+            })
+            .ok()
+    }
+
+    pub fn from_icc(bytes: &[u8]) -> Result<Self, moxcms::CmsError> {
+        let display = ColorProfile::new_from_slice(bytes)?;
+        let transform = ColorProfile::new_srgb().create_transform_8bit(
+            Layout::Rgba,
+            &display,
+            Layout::Rgba,
+            TransformOptions::default(),
+        )?;
+        Ok(Self(transform))
+    }
+
+    /// Converts sRGB `image` for the monitor
+    pub fn apply(&self, image: RgbaImage) -> RgbaImage {
+        let mut converted = RgbaImage::new(image.width(), image.height());
+        match self.0.transform(&image, &mut converted) {
+            Ok(()) => converted,
+            Err(e) => {
+                warn!("Failed to convert for the display profile: {}", e);
+                image
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn rgb(image: &DynamicImage) -> [u8; 3] {
+        image.to_rgb8().get_pixel(0, 0).0
+    }
+
+    fn assert_near(a: [u8; 3], b: [u8; 3]) {
+        assert!(
+            a.iter().zip(b).all(|(a, b)| a.abs_diff(b) <= 1),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn test_reads_profile_embedded_in_jpeg() {
+        let profile = ColorProfile::new_display_p3().encode().unwrap();
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(8, 8))
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        assert_eq!(read(&jpeg), None);
+
+        // APP2 segment right after the start of image marker
+        let mut segment = b"ICC_PROFILE\0\x01\x01".to_vec();
+        segment.extend_from_slice(&profile);
+        let length = (segment.len() + 2) as u16;
+        let mut tagged = jpeg[..2].to_vec();
+        tagged.extend_from_slice(&[0xFF, 0xE2]);
+        tagged.extend_from_slice(&length.to_be_bytes());
+        tagged.extend_from_slice(&segment);
+        tagged.extend_from_slice(&jpeg[2..]);
+        assert_eq!(read(&tagged), Some(profile));
+    }
+
+    #[test]
+    fn test_wide_gamut_colors_convert_to_srgb() {
+        let p3 = ColorProfile::new_display_p3().encode().unwrap();
+        let pixel = |color| {
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb(color)))
+        };
+
+        // Same white point and transfer curve, so grays stay put
+        assert_near(rgb(&to_srgb(pixel([128, 128, 128]), &p3)), [
+            128, 128, 128,
+        ]);
+        // P3 red is more saturated than sRGB can show at the same value
+        let [r, g, b] = rgb(&to_srgb(pixel([200, 100, 50]), &p3));
+        assert!(r > 210 && g < 100 && b < 50, "{:?}", [r, g, b]);
+
+        let srgb = ColorProfile::new_srgb().encode().unwrap();
+        assert_near(rgb(&to_srgb(pixel([200, 100, 50]), &srgb)), [
+            200, 100, 50,
+        ]);
+        // Broken profiles leave the pixels alone
+        assert_eq!(rgb(&to_srgb(pixel([200, 100, 50]), b"junk")), [
+            200, 100, 50
+        ]);
+
+        let display = DisplayProfile::from_icc(&p3).unwrap();
+        let shown = display.apply(pixel([255, 0, 0]).to_rgba8());
+        // sRGB red sits inside the P3 gamut
+        let [r, g, b, a] = shown.get_pixel(0, 0).0;
+        assert!(r < 255 && g > 0 && b > 0 && a == 255);
+    }
+}
@@ -0,0 +1,150 @@
+//! Protection against decompression bombs: files that are tiny on disk but
+//! claim enormous dimensions.
+//!
+//! The header is read before decoding and compared against the configured
+//! limits; the decoder itself is given the same limits as a backstop for
+//! formats whose header lies.
+
+use ferrite_config::DecodingConfig;
+use image::{
+    io::{Limits, Reader},
+    DynamicImage,
+    ImageResult,
+};
+use std::io::Cursor;
+
+/// Decoded size assumed per pixel; images are displayed as RGBA8
+const BYTES_PER_PIXEL: u64 = 4;
+
+/// An image whose header exceeds the configured limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Oversized {
+    pub width:  u32,
+    pub height: u32,
+    pub bytes:  u64,
+}
+
+impl Oversized {
+    pub fn megabytes(&self) -> u64 {
+        self.bytes / (1024 * 1024)
+    }
+}
+
+fn max_bytes(config: &DecodingConfig) -> u64 {
+    config.max_memory_mb * 1024 * 1024
+}
+
+/// Parses only the header of a file's contents and reports whether decoding
+/// it would exceed the limits.
+pub fn check(
+    bytes: &[u8],
+    config: &DecodingConfig,
+) -> ImageResult<Option<Oversized>> {
+    #[cfg(feature = "heic")]
+    if super::heif::is_heif(bytes) {
+        let (width, height) = super::heif::dimensions(bytes)?;
+        return Ok(exceeds(width, height, config));
+    }
+    #[cfg(feature = "raw")]
+    if super::raw::is_raw(bytes) {
+        let (width, height) =
+            super::raw::dimensions(bytes, config.develop_raw)?;
+        return Ok(exceeds(width, height, config));
+    }
+    let (width, height) = Reader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    Ok(exceeds(width, height, config))
+}
+
+fn exceeds(
+    width: u32,
+    height: u32,
+    config: &DecodingConfig,
+) -> Option<Oversized> {
+    let bytes = width as u64 * height as u64 * BYTES_PER_PIXEL;
+    let too_large = width > config.max_dimension
+        || height > config.max_dimension
+        || bytes > max_bytes(config);
+    too_large.then_some(Oversized {
+        width,
+        height,
+        bytes,
+    })
+}
+
+/// Decodes a file's contents, refusing allocations beyond the limits unless
+/// `unlimited` is set.
+pub fn decode(
+    bytes: &[u8],
+    config: &DecodingConfig,
+    unlimited: bool,
+) -> ImageResult<DynamicImage> {
+    // Checked against the limits beforehand, as libheif takes none
+    #[cfg(feature = "heic")]
+    if super::heif::is_heif(bytes) {
+        return super::heif::decode(bytes);
+    }
+    // Same here, the generic decoder would clamp to 8 bits
+    #[cfg(feature = "hdr")]
+    if super::radiance::is_radiance(bytes) {
+        return super::radiance::decode(bytes);
+    }
+    // The TIFF decoder would show a thumbnail at best
+    #[cfg(feature = "raw")]
+    if super::raw::is_raw(bytes) {
+        return super::raw::decode(bytes, config.develop_raw);
+    }
+    let mut reader = Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+    if unlimited {
+        reader.no_limits();
+    } else {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(config.max_dimension);
+        limits.max_image_height = Some(config.max_dimension);
+        limits.max_alloc = Some(max_bytes(config));
+        reader.limits(limits);
+    }
+    reader.decode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeds_dimension_and_memory_limits() {
+        let config = DecodingConfig {
+            max_dimension: 10_000,
+            max_memory_mb: 100,
+            ..DecodingConfig::default()
+        };
+
+        assert_eq!(exceeds(4000, 3000, &config), None);
+        assert!(exceeds(10_001, 10, &config).is_some());
+        // 6000x6000 RGBA is ~137 MB
+        let oversized = exceeds(6000, 6000, &config).unwrap();
+        assert_eq!(oversized.megabytes(), 137);
+        // The classic bomb: a tiny file claiming 100000x100000
+        assert!(exceeds(100_000, 100_000, &DecodingConfig::default()).is_some());
+    }
+
+    #[test]
+    fn test_check_reads_header_only() {
+        let dir = std::env::temp_dir().join("ferrite-limits-check");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wide.png");
+        image::RgbImage::new(300, 2).save(&path).unwrap();
+
+        let config = DecodingConfig {
+            max_dimension: 256,
+            ..DecodingConfig::default()
+        };
+        let bytes = std::fs::read(&path).unwrap();
+        let oversized = check(&bytes, &config).unwrap().unwrap();
+        assert_eq!((oversized.width, oversized.height), (300, 2));
+        assert!(decode(&bytes, &config, false).is_err());
+        assert!(decode(&bytes, &config, true).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! Decoding files on a dedicated thread so the window keeps drawing while a
+//! large image is read.
+//!
+//! Requests are numbered; only the result of the latest one is handed out.
+//! While the thread is busy, requests that pile up are skipped in favour of
+//! the newest, so holding down a navigation key does not queue a decode
+//! for every file passed over.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::DynamicImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use tracing::warn;
+
+use super::ImageLoadError;
+
+struct Request {
+    generation: u64,
+    path:       PathBuf,
+    unlimited:  bool,
+}
+
+/// A finished request
+pub struct Loaded {
+    generation: u64,
+    /// The path as requested
+    pub path:   PathBuf,
+    /// The absolute path and image, or why it could not be opened
+    pub result: Result<(PathBuf, Arc<DynamicImage>), ImageLoadError>,
+}
+
+pub struct Loader {
+    requests: Sender<Request>,
+    results:  Receiver<Loaded>,
+    latest:   u64,
+    decoding: DecodingConfig,
+    io:       IoConfig,
+    /// Finished without the thread: found in the cache, or loaded in
+    /// place because the thread is gone
+    inline:   Option<Loaded>,
+}
+
+/// Opens `path`, with the image ready to share with the cache
+fn open(
+    path: &Path,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+    unlimited: bool,
+) -> Result<(PathBuf, Arc<DynamicImage>), ImageLoadError> {
+    super::open(path, decoding, io, unlimited)
+        .map(|(absolute, image)| (absolute, Arc::new(image)))
+}
+
+impl Loader {
+    /// Starts the loader thread. It ends once the loader is dropped.
+    pub fn spawn(decoding: DecodingConfig, io: IoConfig) -> Self {
+        let (requests, incoming) = mpsc::channel::<Request>();
+        let (finished, results) = mpsc::channel();
+        let (thread_decoding, thread_io) = (decoding.clone(), io.clone());
+        let spawned = thread::Builder::new()
+            .name("image-loader".to_string())
+            .spawn(move || {
+                while let Ok(mut request) = incoming.recv() {
+                    // Only the newest of the waiting requests matters
+                    while let Ok(newer) = incoming.try_recv() {
+                        request = newer;
+                    }
+                    let result = open(
+                        &request.path,
+                        &thread_decoding,
+                        &thread_io,
+                        request.unlimited,
+                    );
+                    let loaded = Loaded {
+                        generation: request.generation,
+                        path: request.path,
+                        result,
+                    };
+                    if finished.send(loaded).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start the image loader thread: {}", e);
+        }
+        Self {
+            requests,
+            results,
+            latest: 0,
+            decoding,
+            io,
+            inline: None,
+        }
+    }
+
+    pub fn request(&mut self, path: PathBuf, unlimited: bool) {
+        self.latest += 1;
+        let request = Request {
+            generation: self.latest,
+            path,
+            unlimited,
+        };
+        if let Err(mpsc::SendError(request)) = self.requests.send(request) {
+            // Without the thread the request would never finish
+            let result = open(
+                &request.path,
+                &self.decoding,
+                &self.io,
+                request.unlimited,
+            );
+            self.finish(request.path, result);
+        }
+    }
+
+    /// Makes `result` the outcome of a new request, superseding any that
+    /// is still being decoded.
+    pub fn finish(
+        &mut self,
+        path: PathBuf,
+        result: Result<(PathBuf, Arc<DynamicImage>), ImageLoadError>,
+    ) {
+        self.latest += 1;
+        self.inline = Some(Loaded {
+            generation: self.latest,
+            path,
+            result,
+        });
+    }
+
+    /// Drops the result of the latest request, for when something else took
+    /// its place on screen.
+    pub fn cancel(&mut self) {
+        self.latest += 1;
+        self.inline = None;
+    }
+
+    /// The result of the latest request, once it is decoded. Results of
+    /// superseded requests are dropped.
+    pub fn poll(&mut self) -> Option<Loaded> {
+        if let Some(loaded) = self.inline.take() {
+            if loaded.generation == self.latest {
+                return Some(loaded);
+            }
+        }
+        self.results
+            .try_iter()
+            .filter(|loaded| loaded.generation == self.latest)
+            .last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use std::{
+        fs,
+        time::{Duration, Instant},
+    };
+
+    fn wait(loader: &mut Loader) -> Loaded {
+        let start = Instant::now();
+        loop {
+            if let Some(loaded) = loader.poll() {
+                return loaded;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_loads_off_the_calling_thread() {
+        let dir = fixture("ferrite-loader-basic");
+        let path = dir.join("a.png");
+        RgbImage::new(6, 4).save(&path).unwrap();
+
+        let mut loader =
+            Loader::spawn(DecodingConfig::default(), IoConfig::default());
+        assert!(loader.poll().is_none());
+        loader.request(path.clone(), false);
+        let loaded = wait(&mut loader);
+        assert_eq!(loaded.path, path);
+        let (absolute, image) = loaded.result.unwrap();
+        assert!(absolute.is_absolute());
+        assert_eq!((image.width(), image.height()), (6, 4));
+
+        loader.request(dir.join("missing.png"), false);
+        assert!(matches!(
+            wait(&mut loader).result,
+            Err(ImageLoadError::IoError(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_newer_request_supersedes_older() {
+        let dir = fixture("ferrite-loader-latest");
+        let paths: Vec<PathBuf> = (0..5)
+            .map(|i| {
+                let path = dir.join(format!("{}.png", i));
+                RgbImage::new(i + 1, 1).save(&path).unwrap();
+                path
+            })
+            .collect();
+
+        let mut loader =
+            Loader::spawn(DecodingConfig::default(), IoConfig::default());
+        for path in &paths {
+            loader.request(path.clone(), false);
+        }
+        let loaded = wait(&mut loader);
+        assert_eq!(&loaded.path, paths.last().unwrap());
+        assert_eq!(loaded.result.unwrap().1.width(), 5);
+        // Nothing older turns up afterwards
+        thread::sleep(Duration::from_millis(50));
+        assert!(loader.poll().is_none());
+
+        // Nor does a cache hit that was followed by a decode
+        loader.finish(
+            paths[0].clone(),
+            Err(ImageLoadError::InvalidPath("cached".into())),
+        );
+        loader.request(paths[1].clone(), false);
+        assert_eq!(wait(&mut loader).path, paths[1]);
+
+        // A cancelled request hands out nothing
+        loader.request(paths[2].clone(), false);
+        loader.cancel();
+        thread::sleep(Duration::from_millis(50));
+        assert!(loader.poll().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
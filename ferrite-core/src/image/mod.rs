@@ -1,24 +1,248 @@
-use eframe::egui::{self, Context};
+use eframe::egui::{self, Context, TextureHandle};
+use ferrite_config::{DecodingConfig, IoConfig};
 use ferrite_logging::metrics::PerformanceMetrics;
-use std::{fs, path::PathBuf, time::Instant};
-use tracing::{info, info_span, instrument, warn, Instrument};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{debug, info, info_span, instrument, warn};
 
+mod cache;
 mod data;
+pub(crate) mod display;
+#[cfg(feature = "heic")]
+mod heif;
+pub(crate) mod icc;
+pub mod limits;
+mod loader;
+mod orientation;
+mod prefetch;
+#[cfg(feature = "hdr")]
+mod radiance;
+#[cfg(feature = "raw")]
+mod raw;
+mod reader;
+mod tiles;
 
-use data::ImageData;
+pub(crate) use cache::ImageCache;
+pub(crate) use data::ImageData;
+use image::DynamicImage;
+use loader::Loader;
+use prefetch::Prefetcher;
+pub(crate) use tiles::TiledTexture;
 
 pub struct ImageManager {
     current_image: Option<ImageData>,
     current_path:  Option<PathBuf>,
+    /// The decoded file while a derived image (e.g. an upscaled copy) is
+    /// being displayed in its place
+    stashed_image: Option<ImageData>,
+    decoding:      DecodingConfig,
+    io:            IoConfig,
+    /// A file that was not opened because it exceeds the decoding limits,
+    /// waiting for the user to confirm
+    oversized:     Option<(PathBuf, Oversized)>,
+    /// Started on the first background request
+    loader:        Option<Loader>,
+    /// File requested from the loader and not yet shown
+    loading:       Option<PathBuf>,
+    /// Recently shown and prefetched files, keyed by the requested path
+    image_cache:   ImageCache,
+    /// Started on the first prefetch
+    prefetcher:    Option<Prefetcher>,
 }
 
 use image::ImageError;
 use std::io;
 use thiserror::Error;
 
+use crate::sandbox::{self, SandboxError};
+use limits::Oversized;
+
 mod formats;
 pub use formats::SupportedFormats;
 
+/// Path for a PNG derived from `source`, saved next to it, e.g.
+/// `photo.jpg` with suffix `crop` -> `photo_crop.png`.
+pub fn derived_path(source: &Path, suffix: &str) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    source
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+        .join(format!("{}_{}.png", stem, suffix))
+}
+
+/// Like `derived_path`, with `extension` and a number added to the suffix
+/// if the name is taken, e.g. `photo_crop_2.jpg`.
+pub fn free_derived_path(
+    source: &Path,
+    suffix: &str,
+    extension: &str,
+) -> PathBuf {
+    (1..)
+        .map(|n| match n {
+            1 => derived_path(source, suffix),
+            n => derived_path(source, &format!("{}_{}", suffix, n)),
+        })
+        .map(|path| path.with_extension(extension))
+        .find(|path| !path.exists())
+        .expect("some name is free")
+}
+
+/// Decodes a file that was read into `bytes`, in the sandboxed worker
+/// process if configured, then converts it to sRGB and turns it upright
+/// unless disabled.
+fn decode_bytes(
+    path: &Path,
+    bytes: &[u8],
+    decoding: &DecodingConfig,
+    unlimited: bool,
+) -> Result<DynamicImage, ImageLoadError> {
+    let image = if decoding.sandboxed {
+        sandbox::decode_with_current_exe(path, bytes, decoding)?
+    } else {
+        limits::decode(bytes, decoding, unlimited)?
+    };
+    let image = match icc::read(bytes).filter(|_| decoding.color_management) {
+        Some(profile) => {
+            debug!("Converting {} to sRGB", path.display());
+            icc::to_srgb(image, &profile)
+        },
+        None => image,
+    };
+    match orientation::read(bytes).filter(|_| decoding.auto_orient) {
+        Some(tag) => {
+            debug!("Applying EXIF orientation {} to {}", tag, path.display());
+            Ok(orientation::apply(image, tag))
+        },
+        None => Ok(image),
+    }
+}
+
+/// Reads and decodes a file for processing outside the viewer, with the
+/// same I/O handling, limits and sandboxing as opening it.
+pub fn decode_file(
+    path: &Path,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<DynamicImage, ImageLoadError> {
+    let (absolute_path, bytes) =
+        reader::read_file(path, io, max_bytes(decoding))?;
+    if let Ok(Some(size)) = limits::check(&bytes, decoding) {
+        return Err(ImageLoadError::TooLarge(size));
+    }
+    decode_bytes(&absolute_path, &bytes, decoding, false)
+}
+
+/// Decodes an image held in memory, such as one piped to standard input,
+/// with the same limits and sandboxing as a file. The format is told by the
+/// first bytes.
+pub fn decode_memory(
+    bytes: &[u8],
+    decoding: &DecodingConfig,
+) -> Result<DynamicImage, ImageLoadError> {
+    if let Ok(Some(size)) = limits::check(bytes, decoding) {
+        return Err(ImageLoadError::TooLarge(size));
+    }
+    // The sandboxed worker is handed the bytes themselves, so nothing is
+    // written to disk
+    decode_bytes(Path::new("standard input"), bytes, decoding, false)
+}
+
+/// Reads a file with the configured I/O handling, for decoders that need
+/// its bytes.
+pub(crate) fn read_bytes(
+    path: &Path,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> io::Result<Vec<u8>> {
+    reader::read_file(path, io, max_bytes(decoding)).map(|(_, bytes)| bytes)
+}
+
+/// Most bytes an archive entry may inflate to: the memory decoding may use
+fn max_bytes(decoding: &DecodingConfig) -> u64 {
+    decoding.max_memory_mb.saturating_mul(1024 * 1024)
+}
+
+/// Reads, checks and decodes `path`, returning the absolute path with the
+/// image. Runs on the loader thread for the viewer.
+fn open(
+    path: &Path,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+    unlimited: bool,
+) -> Result<(PathBuf, DynamicImage), ImageLoadError> {
+    let metrics = PerformanceMetrics::new("image_loading", true);
+
+    let result = info_span!("image_loading_process").in_scope(|| {
+        let (absolute_path, bytes) =
+            reader::read_file(path, io, max_bytes(decoding)).map_err(|e| {
+                warn!("Failed to read {}: {}", path.display(), e);
+                ImageLoadError::IoError(e)
+            })?;
+
+        if !unlimited {
+            // Unreadable headers are left for the decoder to report
+            if let Ok(Some(size)) = limits::check(&bytes, decoding) {
+                warn!(
+                    event = "decode_refused",
+                    path = %absolute_path.display(),
+                    width = size.width,
+                    height = size.height,
+                    "Image exceeds decoding limits"
+                );
+                return Err(ImageLoadError::TooLarge(size));
+            }
+        }
+
+        info!("Loading image from disk: {}", absolute_path.display());
+        let decoded = info_span!("decode", path = %absolute_path.display())
+            .in_scope(|| {
+                decode_bytes(&absolute_path, &bytes, decoding, unlimited)
+            });
+        match decoded {
+            Ok(img) => {
+                let dimensions = (img.width(), img.height());
+                info!(
+                    event = "file_opened",
+                    path = %absolute_path.display(),
+                    width = dimensions.0,
+                    height = dimensions.1,
+                    "Successfully loaded image: dimensions={}x{}",
+                    dimensions.0,
+                    dimensions.1
+                );
+                Ok((absolute_path, img))
+            },
+            Err(e) => {
+                warn!(
+                    event = "decode_failed",
+                    path = %absolute_path.display(),
+                    error = %e,
+                    "Failed to load image: {}",
+                    e
+                );
+                Err(e)
+            },
+        }
+    });
+
+    let duration = metrics.finish();
+    info!(
+        event = "image_loaded",
+        duration_ms = duration.as_millis() as u64,
+        "Image loading completed in {} ms",
+        duration.as_millis()
+    );
+
+    result
+}
+
 #[derive(Error, Debug)]
 pub enum ImageLoadError {
     #[error("Failed to access image file: {0}")]
@@ -29,57 +253,226 @@ pub enum ImageLoadError {
 
     #[error("Invalid image path: {0}")]
     InvalidPath(String),
+
+    #[error(transparent)]
+    Sandbox(#[from] SandboxError),
+
+    #[error(
+        "Image is {}x{} ({} MB decoded), above the configured limits",
+        .0.width,
+        .0.height,
+        .0.megabytes()
+    )]
+    TooLarge(Oversized),
 }
 
 impl ImageManager {
     #[instrument(skip_all)]
-    pub fn new() -> Self {
+    /// `cache_mb` bounds the decoded images kept for quick navigation.
+    pub fn new(decoding: DecodingConfig, io: IoConfig, cache_mb: u64) -> Self {
         info!("Initializing ImageManager");
         Self {
-            current_image: None, current_path: None
+            current_image: None,
+            current_path: None,
+            stashed_image: None,
+            decoding,
+            io,
+            oversized: None,
+            loader: None,
+            loading: None,
+            image_cache: ImageCache::new(cache_mb * 1024 * 1024),
+            prefetcher: None,
         }
     }
 
+    /// Loads `path` on the calling thread, for use without a window.
     pub fn load_image(&mut self, path: PathBuf) -> Result<(), ImageLoadError> {
-        let metrics = PerformanceMetrics::new("image_loading", true);
+        let result = open(&path, &self.decoding, &self.io, false)
+            .map(|(absolute, image)| (absolute, Arc::new(image)));
+        self.apply(path, result)
+    }
 
-        let result = info_span!("image_loading_process").in_scope(|| {
-            let absolute_path = fs::canonicalize(&path).map_err(|e| {
-                warn!("Failed to resolve path: {}", e);
-                ImageLoadError::IoError(e)
-            })?;
+    /// The file held back by the decoding limits, if any
+    pub fn oversized(&self) -> Option<(&Path, Oversized)> {
+        self.oversized
+            .as_ref()
+            .map(|(path, size)| (path.as_path(), *size))
+    }
 
-            if !absolute_path.exists() {
-                return Err(ImageLoadError::InvalidPath(format!(
-                    "Path does not exist: {}",
-                    absolute_path.display()
-                )));
+    pub fn take_oversized(&mut self) -> Option<PathBuf> {
+        self.oversized.take().map(|(path, _)| path)
+    }
+
+    /// Starts decoding `path` on the loader thread. The current image stays
+    /// on screen until [`Self::poll`] picks up the new one; a newer request
+    /// supersedes one still in flight.
+    pub fn request_image(&mut self, path: PathBuf) {
+        self.request(path, false);
+    }
+
+    /// Like [`Self::request_image`], for a file the user chose to open
+    /// despite exceeding the decoding limits.
+    pub fn request_image_unlimited(&mut self, path: PathBuf) {
+        self.request(path, true);
+    }
+
+    fn request(&mut self, path: PathBuf, unlimited: bool) {
+        let cached = self.image_cache.get(&path);
+        let loader = self.loader.get_or_insert_with(|| {
+            Loader::spawn(self.decoding.clone(), self.io.clone())
+        });
+        match cached {
+            Some(image) => loader.finish(path.clone(), Ok(image)),
+            None => loader.request(path.clone(), unlimited),
+        }
+        self.loading = Some(path);
+    }
+
+    /// Decodes `paths` in the background into the cache, skipping those
+    /// already in it. Replaces the files of the previous call.
+    pub fn prefetch(&mut self, paths: Vec<PathBuf>) {
+        if !self.image_cache.is_enabled() {
+            return;
+        }
+        let paths: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| !self.image_cache.contains(path))
+            .collect();
+        self.prefetcher
+            .get_or_insert_with(|| {
+                Prefetcher::spawn(self.decoding.clone(), self.io.clone())
+            })
+            .request(paths);
+    }
+
+    /// Decodes with `decoding` from now on, starting over with the current
+    /// file and forgetting those decoded with the old settings.
+    pub fn set_decoding(&mut self, decoding: DecodingConfig) {
+        self.decoding = decoding;
+        self.loader = None;
+        self.prefetcher = None;
+        self.image_cache.clear();
+        if let Some(path) = self.current_path.clone() {
+            self.request_image(path);
+        }
+    }
+
+    /// Follows files renamed or moved elsewhere in the app, so their decoded
+    /// images stay in use under the new paths.
+    pub fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        for (old, new) in renamed {
+            let absolute =
+                fs::canonicalize(new).unwrap_or_else(|_| new.clone());
+            self.image_cache
+                .rename(old, new, absolute.clone());
+            if self.current_path.as_ref() == Some(old) {
+                crate::crash::set_current_file(&absolute);
+                self.current_path = Some(absolute);
             }
+        }
+    }
 
-            info!("Loading image from disk: {}", absolute_path.display());
-            match image::open(&absolute_path) {
-                Ok(img) => {
-                    let dimensions = (img.width(), img.height());
-                    info!(
-                        "Successfully loaded image: dimensions={}x{}",
-                        dimensions.0, dimensions.1
-                    );
-
-                    self.current_image = Some(ImageData::new(img));
-                    self.current_path = Some(absolute_path);
-                    Ok(())
-                },
-                Err(e) => {
-                    warn!("Failed to load image: {}", e);
-                    Err(ImageLoadError::ImageError(e))
-                },
+    /// Forgets `path` after the file was deleted or moved away. The image
+    /// stays on screen if it is the current one, until another replaces it
+    /// or [`Self::close`] is called.
+    pub fn forget(&mut self, path: &Path) {
+        self.image_cache.remove(path);
+        if self.loading.as_deref() == Some(path) {
+            self.loading = None;
+        }
+    }
+
+    /// Stops showing any image.
+    pub fn close(&mut self) {
+        self.current_image = None;
+        self.current_path = None;
+        self.stashed_image = None;
+    }
+
+    /// Shows `image`, which has no file, such as one pasted from the
+    /// clipboard. It stays unsaved until a file is opened in its place.
+    pub fn show_unsaved(&mut self, image: DynamicImage) {
+        if let Some(loader) = &mut self.loader {
+            loader.cancel();
+        }
+        self.loading = None;
+        self.oversized = None;
+        crate::crash::record_action(format!(
+            "Pasted a {}x{} image",
+            image.width(),
+            image.height()
+        ));
+        self.current_image = Some(ImageData::new(image));
+        self.current_path = None;
+        self.stashed_image = None;
+    }
+
+    /// Whether the image on screen has no file, see [`Self::show_unsaved`]
+    pub fn is_unsaved(&self) -> bool {
+        self.current_image.is_some() && self.current_path.is_none()
+    }
+
+    /// The file being decoded in the background, if any
+    pub fn loading(&self) -> Option<&Path> {
+        self.loading.as_deref()
+    }
+
+    /// Takes the result of the latest request once it is decoded, making
+    /// the image current if it succeeded.
+    pub fn poll(&mut self) -> Option<Result<(), ImageLoadError>> {
+        let prefetched = self
+            .prefetcher
+            .as_ref()
+            .map(Prefetcher::poll)
+            .unwrap_or_default();
+        for done in prefetched {
+            // Keep what was shown ahead of what may never be
+            if !self.image_cache.contains(&done.path) {
+                self.image_cache
+                    .insert(done.path, done.absolute, done.image);
             }
-        });
+        }
 
-        let duration = metrics.finish();
-        info!("Image loading completed in {} ms", duration.as_millis());
+        let loaded = self.loader.as_mut()?.poll()?;
+        self.loading = None;
+        if let Ok((absolute, image)) = &loaded.result {
+            self.image_cache.insert(
+                loaded.path.clone(),
+                absolute.clone(),
+                Arc::clone(image),
+            );
+            debug!(
+                "Image cache holds {} MB",
+                self.image_cache.bytes() / (1024 * 1024)
+            );
+        }
+        Some(self.apply(loaded.path, loaded.result))
+    }
 
-        result
+    fn apply(
+        &mut self,
+        path: PathBuf,
+        result: Result<(PathBuf, Arc<DynamicImage>), ImageLoadError>,
+    ) -> Result<(), ImageLoadError> {
+        self.oversized = None;
+        match result {
+            Ok((absolute_path, img)) => {
+                crate::crash::set_current_file(&absolute_path);
+                crate::crash::record_action(format!(
+                    "Opened {}",
+                    absolute_path.display()
+                ));
+                self.current_image = Some(ImageData::new(img));
+                self.current_path = Some(absolute_path);
+                self.stashed_image = None;
+                Ok(())
+            },
+            Err(ImageLoadError::TooLarge(size)) => {
+                self.oversized = Some((path, size));
+                Err(ImageLoadError::TooLarge(size))
+            },
+            Err(e) => Err(e),
+        }
     }
 
     // Add method to get current image dimensions
@@ -89,6 +482,7 @@ impl ImageManager {
             .map(|img| img.dimensions())
     }
 
+    #[allow(dead_code)]
     #[instrument(skip(self, ctx))]
     pub fn show_performance_window(&self, ctx: &Context) {
         egui::Window::new("Performance Metrics").show(ctx, |ui| {
@@ -114,4 +508,64 @@ impl ImageManager {
     pub fn current_image(&mut self) -> Option<&mut ImageData> {
         self.current_image.as_mut()
     }
+
+    pub fn current_path(&self) -> Option<&PathBuf> {
+        self.current_path.as_ref()
+    }
+
+    /// The image on screen, which may be derived from the file.
+    pub fn displayed_image(&self) -> Option<&DynamicImage> {
+        self.current_image
+            .as_ref()
+            .map(|data| &*data.original)
+    }
+
+    /// The decoded file, even while a derived image is displayed.
+    pub fn original_image(&self) -> Option<&DynamicImage> {
+        self.stashed_image
+            .as_ref()
+            .or(self.current_image.as_ref())
+            .map(|data| &*data.original)
+    }
+
+    /// Displays an image derived from the current file in its place. The
+    /// decoded file is kept so it can be restored without reloading.
+    pub fn show_derived(&mut self, image: DynamicImage) {
+        let derived = ImageData::new(image);
+        match self.stashed_image {
+            Some(_) => self.current_image = Some(derived),
+            None => {
+                self.stashed_image = self.current_image.replace(derived);
+            },
+        }
+    }
+
+    /// Switches back to the decoded file after `show_derived`.
+    pub fn restore_original(&mut self) {
+        if let Some(mut original) = self.stashed_image.take() {
+            // Re-upload so the view is fitted to the original dimensions
+            original.texture = None;
+            self.current_image = Some(original);
+        }
+    }
+
+    pub fn is_showing_derived(&self) -> bool {
+        self.stashed_image.is_some()
+    }
+
+    /// Shows `texture`, a frame of the current animated file, without
+    /// refitting the view. Ignored until the file itself has been drawn
+    /// once and while a derived image is displayed.
+    pub fn show_frame(&mut self, texture: TextureHandle) {
+        if self.stashed_image.is_some() {
+            return;
+        }
+        if let Some(current) = &mut self.current_image {
+            if current.texture.is_some() {
+                current.texture = Some(texture);
+                current.mipmaps.clear();
+                current.tiles = None;
+            }
+        }
+    }
 }
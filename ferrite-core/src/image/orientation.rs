@@ -0,0 +1,87 @@
+//! Turning photos upright according to their EXIF orientation tag.
+//!
+//! Cameras and phones store pixels as the sensor saw them and record how
+//! the device was held; without applying that, portrait photos show up
+//! sideways.
+
+use exif::{In, Reader, Tag};
+use image::DynamicImage;
+use std::io::Cursor;
+
+/// The EXIF orientation of a file read into `bytes`, from 1 (upright) to 8
+pub fn read(bytes: &[u8]) -> Option<u32> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+        .filter(|o| (1..=8).contains(o))
+}
+
+/// Rotates and mirrors `image` so that orientation `orientation` shows
+/// upright.
+pub fn apply(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        // Mirrored along the diagonal
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, Value};
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    #[test]
+    fn test_reads_orientation_tag() {
+        let field = Field {
+            tag:     Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value:   Value::Short(vec![6]),
+        };
+        let mut writer = Writer::new();
+        writer.push_field(&field);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        assert_eq!(read(tiff.get_ref()), Some(6));
+        assert_eq!(read(b"no exif here"), None);
+    }
+
+    #[test]
+    fn test_marked_corner_ends_up_top_left() {
+        // Where the top left corner of the scene lands in the stored pixels
+        // of a 3x2 photo, for each orientation
+        let stored_corner = |orientation| match orientation {
+            1 => (0, 0, 3, 2),
+            2 => (2, 0, 3, 2),
+            3 => (2, 1, 3, 2),
+            4 => (0, 1, 3, 2),
+            5 => (0, 0, 2, 3),
+            6 => (0, 2, 2, 3),
+            7 => (1, 2, 2, 3),
+            _ => (1, 0, 2, 3),
+        };
+        for orientation in 1..=8 {
+            let (x, y, width, height) = stored_corner(orientation);
+            let mut stored = RgbaImage::new(width, height);
+            stored.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            let upright = apply(DynamicImage::ImageRgba8(stored), orientation);
+            assert_eq!(upright.dimensions(), (3, 2), "{}", orientation);
+            assert_eq!(
+                upright.get_pixel(0, 0),
+                Rgba([255, 0, 0, 255]),
+                "{}",
+                orientation
+            );
+        }
+    }
+}
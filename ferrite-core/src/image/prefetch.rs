@@ -0,0 +1,173 @@
+//! Decoding the neighbours of the current image ahead of time, so stepping
+//! through a folder shows them without waiting.
+//!
+//! Each request replaces the one before: files of an older request that
+//! were not decoded yet are skipped once the view has moved on.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use tracing::{debug, warn};
+
+/// A file decoded ahead of time
+pub struct Prefetched {
+    /// The path as requested
+    pub path:     PathBuf,
+    pub absolute: PathBuf,
+    pub image:    Arc<DynamicImage>,
+}
+
+pub struct Prefetcher {
+    requests: Sender<Vec<PathBuf>>,
+    results:  Receiver<Prefetched>,
+}
+
+impl Prefetcher {
+    /// Starts the prefetch thread. It ends once the prefetcher is dropped.
+    pub fn spawn(decoding: DecodingConfig, io: IoConfig) -> Self {
+        let (requests, incoming) = mpsc::channel::<Vec<PathBuf>>();
+        let (finished, results) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("image-prefetch".to_string())
+            .spawn(move || {
+                let mut pending = Vec::new();
+                loop {
+                    if pending.is_empty() {
+                        match incoming.recv() {
+                            Ok(paths) => pending = paths,
+                            Err(_) => break,
+                        }
+                    }
+                    while let Ok(newer) = incoming.try_recv() {
+                        pending = newer;
+                    }
+                    if pending.is_empty() {
+                        continue;
+                    }
+                    let path = pending.remove(0);
+                    match super::open(&path, &decoding, &io, false) {
+                        Ok((absolute, image)) => {
+                            let prefetched = Prefetched {
+                                path,
+                                absolute,
+                                image: Arc::new(image),
+                            };
+                            if finished.send(prefetched).is_err() {
+                                break;
+                            }
+                        },
+                        // Opening it for real reports the problem
+                        Err(e) => {
+                            debug!("Not prefetching {}: {}", path.display(), e)
+                        },
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start the image prefetch thread: {}", e);
+        }
+        Self {
+            requests,
+            results,
+        }
+    }
+
+    /// Decodes `paths` in order, dropping what is left of the last request.
+    pub fn request(&self, paths: Vec<PathBuf>) {
+        let _ = self.requests.send(paths);
+    }
+
+    /// Files decoded since the last call
+    pub fn poll(&self) -> Vec<Prefetched> {
+        self.results.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use std::{
+        fs,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    /// `count` images, each a pixel wider than the one before and
+    /// `height` high
+    fn fixture(name: &str, count: u32, height: u32) -> (PathBuf, Vec<PathBuf>) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let paths = (0..count)
+            .map(|i| {
+                let path = dir.join(format!("{}.png", i));
+                RgbImage::new(i + 1, height).save(&path).unwrap();
+                path
+            })
+            .collect();
+        (dir, paths)
+    }
+
+    fn collect(prefetcher: &Prefetcher, count: usize) -> Vec<Prefetched> {
+        let start = Instant::now();
+        let mut done = Vec::new();
+        while done.len() < count {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            done.extend(prefetcher.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        done
+    }
+
+    #[test]
+    fn test_decodes_requested_files_in_order() {
+        let (dir, paths) = fixture("ferrite-prefetch-order", 2, 1);
+        let prefetcher =
+            Prefetcher::spawn(DecodingConfig::default(), IoConfig::default());
+        let mut request = paths.clone();
+        request.insert(1, dir.join("missing.png"));
+        prefetcher.request(request);
+
+        let done = collect(&prefetcher, 2);
+        let decoded: Vec<&Path> =
+            done.iter().map(|p| p.path.as_path()).collect();
+        assert_eq!(decoded, [paths[0].as_path(), paths[1].as_path()]);
+        assert_eq!(done[1].image.width(), 2);
+        assert!(done[0].absolute.is_absolute());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_newer_request_replaces_what_is_left() {
+        // Tall enough that decoding takes longer than sending a request
+        let (dir, paths) = fixture("ferrite-prefetch-replace", 6, 4000);
+        let prefetcher =
+            Prefetcher::spawn(DecodingConfig::default(), IoConfig::default());
+        prefetcher.request(paths[..5].to_vec());
+        prefetcher.request(vec![paths[5].clone()]);
+
+        let start = Instant::now();
+        let mut done = Vec::new();
+        while !done
+            .iter()
+            .any(|p: &Prefetched| p.path == paths[5])
+        {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            done.extend(prefetcher.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        // At most the file in progress when the new request came in
+        assert!(done.len() <= 2, "{}", done.len());
+        thread::sleep(Duration::from_millis(50));
+        assert!(prefetcher.poll().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,64 @@
+//! Radiance HDR (`.hdr`) decoding to floating point pixels. The `image`
+//! crate's generic decoder clamps these files to 8 bits, losing everything
+//! brighter than white before it can be tone-mapped.
+
+use image::{
+    codecs::hdr::HdrDecoder,
+    error::{LimitError, LimitErrorKind},
+    DynamicImage,
+    ImageError,
+    ImageFormat,
+    ImageResult,
+    Rgb32FImage,
+};
+use std::io::Cursor;
+
+/// Whether `bytes` start like a Radiance HDR file
+pub fn is_radiance(bytes: &[u8]) -> bool {
+    image::guess_format(bytes).is_ok_and(|format| format == ImageFormat::Hdr)
+}
+
+pub fn decode(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let decoder = HdrDecoder::new(Cursor::new(bytes))?;
+    let metadata = decoder.metadata();
+    let data = decoder
+        .read_image_hdr()?
+        .into_iter()
+        .flat_map(|pixel| pixel.0)
+        .collect();
+    Rgb32FImage::from_raw(metadata.width, metadata.height, data)
+        .map(DynamicImage::ImageRgb32F)
+        .ok_or_else(|| {
+            ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::hdr::HdrEncoder, Rgb};
+
+    #[test]
+    fn test_keeps_values_brighter_than_white() {
+        let pixels = [Rgb([0.25, 1.0, 4.0]), Rgb([16.0, 0.0, 0.5])];
+        let mut bytes = Vec::new();
+        HdrEncoder::new(&mut bytes)
+            .encode(&pixels, 2, 1)
+            .unwrap();
+        assert!(is_radiance(&bytes));
+        assert!(!is_radiance(b"\x89PNG\r\n\x1a\n"));
+
+        let DynamicImage::ImageRgb32F(decoded) = decode(&bytes).unwrap() else {
+            panic!("expected float pixels");
+        };
+        assert_eq!(decoded.dimensions(), (2, 1));
+        // RGBE keeps 8 bits of mantissa per channel
+        for (decoded, expected) in decoded.pixels().zip(pixels) {
+            for (a, b) in decoded.0.iter().zip(expected.0) {
+                assert!((a - b).abs() <= b / 64.0 + 1e-3, "{} != {}", a, b);
+            }
+        }
+    }
+}
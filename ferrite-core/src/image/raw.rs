@@ -0,0 +1,695 @@
+//! Camera RAW files (CR2, NEF, ARW, DNG): TIFF containers holding the
+//! sensor data along with JPEG previews the camera rendered of it.
+//!
+//! The largest embedded preview is shown by default, as it decodes as fast
+//! as any JPEG and carries the camera's own processing. Developing the
+//! sensor data instead is opt-in through `decoding.develop_raw`. Only
+//! uncompressed Bayer data can be developed, with bilinear demosaicing and
+//! the white balance the camera recorded but without its color matrix;
+//! other files fall back to the preview.
+
+use image::{
+    error::{DecodingError, ImageFormatHint},
+    DynamicImage,
+    ImageBuffer,
+    ImageError,
+    ImageFormat,
+    ImageResult,
+    Rgb,
+};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use tracing::{debug, warn};
+
+use crate::linear;
+
+const COMPRESSION: u16 = 259;
+const PHOTOMETRIC: u16 = 262;
+const WIDTH: u16 = 256;
+const HEIGHT: u16 = 257;
+const BITS_PER_SAMPLE: u16 = 258;
+const SAMPLES_PER_PIXEL: u16 = 277;
+const STRIP_OFFSETS: u16 = 273;
+const STRIP_BYTE_COUNTS: u16 = 279;
+const TILE_OFFSETS: u16 = 324;
+const SUB_IFDS: u16 = 330;
+const JPEG_OFFSET: u16 = 513;
+const JPEG_LENGTH: u16 = 514;
+const CFA_REPEAT_PATTERN_DIM: u16 = 33421;
+const CFA_PATTERN: u16 = 33422;
+const DNG_VERSION: u16 = 50706;
+const BLACK_LEVEL: u16 = 50714;
+const WHITE_LEVEL: u16 = 50717;
+const AS_SHOT_NEUTRAL: u16 = 50728;
+
+/// Photometric interpretation of Bayer sensor data
+const CFA: u32 = 32803;
+/// Same for sensor data already demosaiced, which no preview is among
+const LINEAR_RAW: u32 = 34892;
+/// Bounds the IFDs read from a file, whose offsets may well loop
+const MAX_IFDS: usize = 64;
+
+fn decoding_error(
+    e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("RAW".to_string()),
+        e,
+    ))
+}
+
+#[derive(Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+/// One image file directory: its tags and their values, numbers of any
+/// type widened to `f64`
+struct Ifd {
+    entries: Vec<(u16, Vec<f64>)>,
+}
+
+impl Ifd {
+    fn values(&self, tag: u16) -> &[f64] {
+        self.entries
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn uint(&self, tag: u16) -> Option<u32> {
+        self.values(tag).first().map(|v| *v as u32)
+    }
+
+    fn uints(&self, tag: u16) -> Vec<u32> {
+        self.values(tag)
+            .iter()
+            .map(|v| *v as u32)
+            .collect()
+    }
+
+    fn has(&self, tag: u16) -> bool {
+        self.entries.iter().any(|(t, _)| *t == tag)
+    }
+}
+
+/// The IFDs of a TIFF based file: the main chain and the SubIFDs hanging
+/// off it, where cameras put the sensor data and larger previews
+struct Tiff<'a> {
+    bytes: &'a [u8],
+    order: ByteOrder,
+    ifds:  Vec<Ifd>,
+}
+
+impl<'a> Tiff<'a> {
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let order = match bytes.get(..4)? {
+            b"II*\0" => ByteOrder::Little,
+            b"MM\0*" => ByteOrder::Big,
+            _ => return None,
+        };
+        let mut tiff = Self {
+            bytes,
+            order,
+            ifds: Vec::new(),
+        };
+        let mut pending = vec![tiff.u32(4)?];
+        let mut seen = HashSet::new();
+        while let Some(offset) = pending.pop() {
+            if offset == 0 || !seen.insert(offset) || seen.len() > MAX_IFDS {
+                continue;
+            }
+            let Some((ifd, next)) = tiff.ifd(offset as usize) else {
+                continue;
+            };
+            pending.push(next);
+            pending.extend(ifd.uints(SUB_IFDS));
+            tiff.ifds.push(ifd);
+        }
+        Some(tiff)
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        self.bytes.get(offset..offset.checked_add(len)?)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.slice(offset, 2)?.try_into().ok()?;
+        Some(match self.order {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.slice(offset, 4)?.try_into().ok()?;
+        Some(match self.order {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    /// The IFD at `offset` and the offset of the next one
+    fn ifd(&self, offset: usize) -> Option<(Ifd, u32)> {
+        let count = self.u16(offset)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = offset + 2 + i * 12;
+            let tag = self.u16(entry)?;
+            if let Some(values) = self.entry_values(entry) {
+                entries.push((tag, values));
+            }
+        }
+        let next = self.u32(offset + 2 + count * 12)?;
+        Some((
+            Ifd {
+                entries,
+            },
+            next,
+        ))
+    }
+
+    /// Values of the 12 byte IFD entry at `entry`; `None` for types that
+    /// are not numbers and values past the end of the file
+    fn entry_values(&self, entry: usize) -> Option<Vec<f64>> {
+        let kind = self.u16(entry + 2)?;
+        let count = self.u32(entry + 4)? as usize;
+        let size = match kind {
+            // BYTE, UNDEFINED
+            1 | 7 => 1,
+            // SHORT
+            3 => 2,
+            // LONG, SLONG, IFD
+            4 | 9 | 13 => 4,
+            // RATIONAL, SRATIONAL
+            5 | 10 => 8,
+            _ => return None,
+        };
+        let len = count.checked_mul(size)?;
+        let start =
+            if len <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+        let data = self.slice(start, len)?;
+        let values = (0..count)
+            .map(|i| {
+                let at = start + i * size;
+                match kind {
+                    1 | 7 => data[i] as f64,
+                    3 => self.u16(at).unwrap_or(0) as f64,
+                    9 => self.u32(at).unwrap_or(0) as i32 as f64,
+                    5 | 10 => {
+                        let (n, d) = (
+                            self.u32(at).unwrap_or(0),
+                            self.u32(at + 4).unwrap_or(1),
+                        );
+                        if kind == 10 {
+                            n as i32 as f64 / (d as i32).max(1) as f64
+                        } else {
+                            n as f64 / d.max(1) as f64
+                        }
+                    },
+                    _ => self.u32(at).unwrap_or(0) as f64,
+                }
+            })
+            .collect();
+        Some(values)
+    }
+
+    /// Embedded JPEGs the `image` crate can decode, with their sizes
+    fn previews(&self) -> Vec<(&'a [u8], (u32, u32))> {
+        let mut previews = Vec::new();
+        for ifd in &self.ifds {
+            let photometric = ifd.uint(PHOTOMETRIC);
+            if matches!(photometric, Some(CFA | LINEAR_RAW)) {
+                continue;
+            }
+            let mut candidates = vec![(JPEG_OFFSET, JPEG_LENGTH)];
+            // Strips hold a JPEG when compressed as one, but the sensor data
+            // of CR2 files is lossless JPEG, which jpeg_size rules out
+            if matches!(ifd.uint(COMPRESSION), Some(6 | 7))
+                && ifd.values(STRIP_OFFSETS).len() == 1
+            {
+                candidates.push((STRIP_OFFSETS, STRIP_BYTE_COUNTS));
+            }
+            for (offset, length) in candidates {
+                let (Some(offset), Some(length)) =
+                    (ifd.uint(offset), ifd.uint(length))
+                else {
+                    continue;
+                };
+                let Some(jpeg) = self.slice(offset as usize, length as usize)
+                else {
+                    continue;
+                };
+                if let Some(size) = jpeg_size(jpeg) {
+                    previews.push((jpeg, size));
+                }
+            }
+        }
+        previews
+    }
+
+    fn largest_preview(&self) -> Option<(&'a [u8], (u32, u32))> {
+        self.previews()
+            .into_iter()
+            .max_by_key(|(_, (w, h))| *w as u64 * *h as u64)
+    }
+
+    fn sensor_ifd(&self) -> Option<&Ifd> {
+        self.ifds
+            .iter()
+            .find(|ifd| ifd.uint(PHOTOMETRIC) == Some(CFA))
+    }
+}
+
+/// Size of a baseline or progressive JPEG, read from its frame header.
+/// Other kinds, lossless JPEG among them, give `None`.
+fn jpeg_size(jpeg: &[u8]) -> Option<(u32, u32)> {
+    if jpeg.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut at = 2;
+    loop {
+        if *jpeg.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *jpeg.get(at + 1)?;
+        match marker {
+            // Fill bytes
+            0xFF => at += 1,
+            0x01 | 0xD0..=0xD7 => at += 2,
+            0xC0..=0xC2 => {
+                let frame = jpeg.get(at + 5..at + 9)?;
+                let height = u16::from_be_bytes([frame[0], frame[1]]);
+                let width = u16::from_be_bytes([frame[2], frame[3]]);
+                return Some((width as u32, height as u32));
+            },
+            // Other frame types or the scan, before any supported frame
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => {
+                return None
+            },
+            _ => {
+                let length = u16::from_be_bytes(
+                    jpeg.get(at + 2..at + 4)?.try_into().ok()?,
+                );
+                at += 2 + length as usize;
+            },
+        }
+    }
+}
+
+/// Whether `bytes` look like a camera RAW file rather than a plain TIFF
+pub fn is_raw(bytes: &[u8]) -> bool {
+    // Canon marks CR2 files right after the TIFF header
+    if bytes.get(8..10) == Some(b"CR") {
+        return true;
+    }
+    Tiff::parse(bytes).is_some_and(|tiff| {
+        tiff.ifds.iter().any(|ifd| {
+            ifd.has(DNG_VERSION) || ifd.uint(PHOTOMETRIC) == Some(CFA)
+        })
+    })
+}
+
+/// Size of the image [`decode`] returns, for checking against the limits
+pub fn dimensions(bytes: &[u8], develop: bool) -> ImageResult<(u32, u32)> {
+    let tiff = Tiff::parse(bytes)
+        .ok_or_else(|| decoding_error("not a TIFF based RAW file"))?;
+    if develop {
+        if let Ok(sensor) = Sensor::read(&tiff) {
+            return Ok((sensor.width, sensor.height));
+        }
+    }
+    tiff.largest_preview()
+        .map(|(_, size)| size)
+        .ok_or_else(|| decoding_error("no embedded preview"))
+}
+
+/// Decodes the largest preview embedded in the file, or develops the
+/// sensor data when `develop` is set and the data allows it.
+pub fn decode(bytes: &[u8], develop: bool) -> ImageResult<DynamicImage> {
+    let tiff = Tiff::parse(bytes)
+        .ok_or_else(|| decoding_error("not a TIFF based RAW file"))?;
+    if develop {
+        match Sensor::read(&tiff) {
+            Ok(sensor) => {
+                debug!(
+                    "Developing {}x{} sensor data",
+                    sensor.width, sensor.height
+                );
+                return Ok(DynamicImage::ImageRgb16(sensor.demosaic()));
+            },
+            Err(reason) => {
+                warn!("Showing the embedded preview instead: {}", reason)
+            },
+        }
+    }
+    let (jpeg, _) = tiff
+        .largest_preview()
+        .ok_or_else(|| decoding_error("no embedded preview"))?;
+    image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg)
+}
+
+/// Uncompressed Bayer sensor data with what is needed to develop it
+struct Sensor {
+    width:   u32,
+    height:  u32,
+    /// Sample values, row by row
+    samples: Vec<u16>,
+    /// Color of the photosites in each 2x2 block, 0 red, 1 green, 2 blue
+    pattern: [[usize; 2]; 2],
+    black:   f32,
+    white:   f32,
+    /// Multipliers that make a neutral gray come out gray
+    gains:   [f32; 3],
+}
+
+impl Sensor {
+    fn read(tiff: &Tiff) -> Result<Self, String> {
+        let ifd = tiff
+            .sensor_ifd()
+            .ok_or("the file holds no Bayer sensor data")?;
+        let compression = ifd.uint(COMPRESSION).unwrap_or(1);
+        if compression != 1 {
+            return Err(format!(
+                "sensor data compressed with scheme {} is not supported",
+                compression
+            ));
+        }
+        let bits = ifd.uint(BITS_PER_SAMPLE).unwrap_or(0);
+        if ifd.uint(SAMPLES_PER_PIXEL).unwrap_or(1) != 1
+            || !matches!(bits, 8 | 16)
+            || ifd.has(TILE_OFFSETS)
+        {
+            return Err(format!(
+                "sensor data with {} bit samples or in tiles is not supported",
+                bits
+            ));
+        }
+        let (width, height) = ifd
+            .uint(WIDTH)
+            .zip(ifd.uint(HEIGHT))
+            .filter(|(w, h)| *w > 0 && *h > 0)
+            .ok_or("sensor data has no size")?;
+
+        let mut data = Vec::new();
+        for (offset, count) in ifd
+            .uints(STRIP_OFFSETS)
+            .into_iter()
+            .zip(ifd.uints(STRIP_BYTE_COUNTS))
+        {
+            let strip = tiff
+                .slice(offset as usize, count as usize)
+                .ok_or("sensor data runs past the end of the file")?;
+            data.extend_from_slice(strip);
+        }
+        let count = width as usize * height as usize;
+        let samples: Vec<u16> = match bits {
+            8 => data.iter().map(|b| *b as u16).collect(),
+            _ => data
+                .chunks_exact(2)
+                .map(|pair| {
+                    let pair = [pair[0], pair[1]];
+                    match tiff.order {
+                        ByteOrder::Little => u16::from_le_bytes(pair),
+                        ByteOrder::Big => u16::from_be_bytes(pair),
+                    }
+                })
+                .collect(),
+        };
+        if samples.len() < count {
+            return Err("sensor data is shorter than its size".to_string());
+        }
+
+        let pattern = match ifd.uints(CFA_PATTERN).as_slice() {
+            [a, b, c, d]
+                if ifd
+                    .uints(CFA_REPEAT_PATTERN_DIM)
+                    .iter()
+                    .all(|dim| *dim == 2)
+                    && [a, b, c, d].iter().all(|color| **color <= 2) =>
+            {
+                [[*a as usize, *b as usize], [*c as usize, *d as usize]]
+            },
+            [] => [[0, 1], [1, 2]],
+            _ => return Err("unsupported color filter pattern".to_string()),
+        };
+        let black = ifd
+            .values(BLACK_LEVEL)
+            .first()
+            .copied()
+            .unwrap_or(0.0);
+        let white = ifd
+            .values(WHITE_LEVEL)
+            .first()
+            .copied()
+            .unwrap_or(((1u32 << bits) - 1) as f64);
+        let gains = match ifd.values(AS_SHOT_NEUTRAL) {
+            [r, g, b] if *r > 0.0 && *g > 0.0 && *b > 0.0 => {
+                [(g / r) as f32, 1.0, (g / b) as f32]
+            },
+            _ => [1.0; 3],
+        };
+        Ok(Self {
+            width,
+            height,
+            samples: samples[..count].to_vec(),
+            pattern,
+            black: black as f32,
+            white: (white as f32).max(black as f32 + 1.0),
+            gains,
+        })
+    }
+
+    fn color(&self, x: u32, y: u32) -> usize {
+        self.pattern[y as usize % 2][x as usize % 2]
+    }
+
+    /// Bilinear demosaicing: each color a photosite did not record is the
+    /// average of the neighbours that did
+    fn demosaic(&self) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+        let (width, height) = (self.width, self.height);
+        let mut rgb = ImageBuffer::new(width, height);
+        rgb.par_chunks_mut(width as usize * 3)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let y = y as u32;
+                for x in 0..width {
+                    let mut sums = [0.0f32; 3];
+                    let mut counts = [0u32; 3];
+                    for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                        for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                            let color = self.color(nx, ny);
+                            // A photosite's own color is taken as is
+                            if color == self.color(x, y) && (nx, ny) != (x, y) {
+                                continue;
+                            }
+                            let sample =
+                                self.samples[(ny * width + nx) as usize] as f32;
+                            sums[color] += sample;
+                            counts[color] += 1;
+                        }
+                    }
+                    let pixel = Rgb(std::array::from_fn(|c| {
+                        let value = sums[c] / counts[c].max(1) as f32;
+                        let value = (value - self.black)
+                            / (self.white - self.black)
+                            * self.gains[c];
+                        (linear::encode(value) * u16::MAX as f32).round() as u16
+                    }));
+                    row[x as usize * 3..][..3].copy_from_slice(&pixel.0);
+                }
+            });
+        rgb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use std::io::Cursor;
+
+    enum Value {
+        Byte(Vec<u8>),
+        Short(Vec<u16>),
+        Long(Vec<u32>),
+        Rational(Vec<(u32, u32)>),
+        /// Stored after the IFDs, the entry holding its offset
+        Data(Vec<u8>),
+        /// Offset of another of the IFDs
+        Ifd(usize),
+    }
+
+    /// Little endian TIFF of `ifds`, the first being IFD0 and the others
+    /// only reachable as SubIFDs
+    fn tiff(ifds: &[Vec<(u16, Value)>]) -> Vec<u8> {
+        let mut offsets = Vec::new();
+        let mut end = 8;
+        for ifd in ifds {
+            offsets.push(end as u32);
+            end += 2 + 12 * ifd.len() + 4;
+        }
+        let mut out = b"II*\0\x08\0\0\0".to_vec();
+        let mut extra = Vec::new();
+        for ifd in ifds {
+            out.extend((ifd.len() as u16).to_le_bytes());
+            for (tag, value) in ifd {
+                let (kind, count, mut payload): (u16, usize, Vec<u8>) =
+                    match value {
+                        Value::Byte(v) => (1, v.len(), v.clone()),
+                        Value::Short(v) => (
+                            3,
+                            v.len(),
+                            v.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                        ),
+                        Value::Long(v) => (
+                            4,
+                            v.len(),
+                            v.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                        ),
+                        Value::Rational(v) => (
+                            5,
+                            v.len(),
+                            v.iter()
+                                .flat_map(|(n, d)| {
+                                    [n.to_le_bytes(), d.to_le_bytes()]
+                                })
+                                .flatten()
+                                .collect(),
+                        ),
+                        Value::Data(data) => {
+                            let offset = (end + extra.len()) as u32;
+                            extra.extend_from_slice(data);
+                            (4, 1, offset.to_le_bytes().to_vec())
+                        },
+                        Value::Ifd(i) => {
+                            (4, 1, offsets[*i].to_le_bytes().to_vec())
+                        },
+                    };
+                out.extend(tag.to_le_bytes());
+                out.extend(kind.to_le_bytes());
+                out.extend((count as u32).to_le_bytes());
+                if payload.len() > 4 {
+                    let offset = (end + extra.len()) as u32;
+                    extra.append(&mut payload);
+                    payload = offset.to_le_bytes().to_vec();
+                }
+                payload.resize(4, 0);
+                out.extend(payload);
+            }
+            out.extend(0u32.to_le_bytes());
+        }
+        out.extend(extra);
+        out
+    }
+
+    fn jpeg(width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(
+            width,
+            height,
+            Rgb(color),
+        ))
+        .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+        .unwrap();
+        jpeg
+    }
+
+    /// DNG-like file with a thumbnail and a preview in IFD0 and 4x4 RGGB
+    /// sensor data of the given compression in a SubIFD
+    fn camera_file(compression: u16, sensor: Vec<(u16, Value)>) -> Vec<u8> {
+        let thumbnail = jpeg(8, 8, [0, 0, 255]);
+        let preview = jpeg(32, 16, [255, 0, 0]);
+        let mut raw = vec![
+            (WIDTH, Value::Long(vec![4])),
+            (HEIGHT, Value::Long(vec![4])),
+            (BITS_PER_SAMPLE, Value::Short(vec![16])),
+            (COMPRESSION, Value::Short(vec![compression])),
+            (PHOTOMETRIC, Value::Short(vec![CFA as u16])),
+            (CFA_REPEAT_PATTERN_DIM, Value::Short(vec![2, 2])),
+            (CFA_PATTERN, Value::Byte(vec![0, 1, 1, 2])),
+        ];
+        raw.extend(sensor);
+        tiff(&[
+            vec![
+                (COMPRESSION, Value::Short(vec![6])),
+                (STRIP_BYTE_COUNTS, Value::Long(vec![preview.len() as u32])),
+                (STRIP_OFFSETS, Value::Data(preview)),
+                (JPEG_LENGTH, Value::Long(vec![thumbnail.len() as u32])),
+                (JPEG_OFFSET, Value::Data(thumbnail)),
+                (SUB_IFDS, Value::Ifd(1)),
+                (DNG_VERSION, Value::Byte(vec![1, 4, 0, 0])),
+            ],
+            raw,
+        ])
+    }
+
+    fn bayer(red: u16, green: u16, blue: u16) -> Vec<(u16, Value)> {
+        let samples: Vec<u8> = (0..16u32)
+            .flat_map(|i| {
+                let (x, y) = (i % 4, i / 4);
+                let sample = match (x % 2, y % 2) {
+                    (0, 0) => red,
+                    (1, 1) => blue,
+                    _ => green,
+                };
+                sample.to_le_bytes()
+            })
+            .collect();
+        vec![
+            (STRIP_BYTE_COUNTS, Value::Long(vec![samples.len() as u32])),
+            (STRIP_OFFSETS, Value::Data(samples)),
+            (WHITE_LEVEL, Value::Long(vec![1000])),
+        ]
+    }
+
+    #[test]
+    fn test_shows_the_largest_embedded_preview() {
+        let file = camera_file(7, Vec::new());
+        assert!(is_raw(&file));
+        assert_eq!(dimensions(&file, false).unwrap(), (32, 16));
+
+        // Compressed sensor data cannot be developed, so both show the
+        // preview rather than the thumbnail
+        for develop in [false, true] {
+            let image = decode(&file, develop).unwrap();
+            assert_eq!((image.width(), image.height()), (32, 16));
+            let [r, g, b] = image.to_rgb8().get_pixel(16, 8).0;
+            assert!(r > 200 && g < 50 && b < 50);
+        }
+
+        let mut plain = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(4, 4))
+            .write_to(&mut Cursor::new(&mut plain), ImageFormat::Tiff)
+            .unwrap();
+        assert!(!is_raw(&plain));
+        assert!(!is_raw(&jpeg(4, 4, [0; 3])));
+    }
+
+    #[test]
+    fn test_develops_uncompressed_bayer_data() {
+        let file = camera_file(1, bayer(1000, 500, 250));
+        assert_eq!(dimensions(&file, true).unwrap(), (4, 4));
+        let developed = decode(&file, true).unwrap().to_rgb16();
+        // Every pixel gets all three colors from its neighbours
+        for pixel in developed.pixels() {
+            let [r, g, b] = pixel.0;
+            assert_eq!(r, u16::MAX);
+            assert!(g > b && b > 0, "{:?}", pixel);
+        }
+
+        // Dim sensor data of a gray card, balanced by the camera's neutral
+        let mut sensor = bayer(250, 500, 125);
+        sensor.push((
+            AS_SHOT_NEUTRAL,
+            Value::Rational(vec![(1, 2), (1, 1), (1, 4)]),
+        ));
+        let developed = decode(&camera_file(1, sensor), true)
+            .unwrap()
+            .to_rgb16();
+        let [r, g, b] = developed.get_pixel(1, 2).0;
+        assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{:?}", [r, g, b]);
+    }
+}
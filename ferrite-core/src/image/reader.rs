@@ -0,0 +1,154 @@
+//! Reading image files without trusting the file system to answer.
+//!
+//! A stalled network mount can block `open` or `read` for minutes. Files
+//! are therefore read on a separate thread that the caller waits on with a
+//! timeout; if the mount never answers, the thread is abandoned and the
+//! load fails instead of freezing the viewer. Transient errors are retried
+//! and reads are issued in large chunks to make fewer round trips.
+
+use ferrite_config::IoConfig;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, ErrorKind, Read},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+use tracing::warn;
+
+use crate::archive::{self, Archive};
+
+/// Pause before the first retry, doubled for every further one
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Resolves `path` and reads the whole file, or the entry of an archive it
+/// names, returning the absolute path and the contents. An archive entry is
+/// not inflated past `max_bytes`.
+pub fn read_file(
+    path: &Path,
+    config: &IoConfig,
+    max_bytes: u64,
+) -> io::Result<(PathBuf, Vec<u8>)> {
+    let timeout = config.timeout();
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match read_with_timeout(
+            path,
+            config.read_ahead_kb * 1024,
+            max_bytes,
+            timeout,
+        ) {
+            Err(e) if attempt < config.retries && is_transient(&e) => {
+                warn!(
+                    "Reading {} failed ({}), retrying in {:?}",
+                    path.display(),
+                    e,
+                    delay
+                );
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+fn read_with_timeout(
+    path: &Path,
+    chunk: usize,
+    max_bytes: u64,
+    timeout: Duration,
+) -> io::Result<(PathBuf, Vec<u8>)> {
+    let (sender, receiver) = mpsc::channel();
+    let owned = path.to_path_buf();
+    thread::Builder::new()
+        .name("ferrite-read".into())
+        .spawn(move || {
+            let _ = sender.send(read(&owned, chunk, max_bytes));
+        })?;
+
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("no answer from the file system after {:?}", timeout),
+            ))
+        })
+}
+
+fn read(
+    path: &Path,
+    chunk: usize,
+    max_bytes: u64,
+) -> io::Result<(PathBuf, Vec<u8>)> {
+    if let Some((archive, name)) = archive::locate(path) {
+        let archive = fs::canonicalize(archive)?;
+        let bytes = Archive::open(&archive)?.read(&name, max_bytes)?;
+        return Ok((archive.join(name), bytes));
+    }
+    let absolute = fs::canonicalize(path)?;
+    let file = File::open(&absolute)?;
+    advise_sequential(&file);
+    let size = file
+        .metadata()
+        .map(|m| m.len() as usize)
+        .unwrap_or(0);
+
+    let mut bytes = Vec::with_capacity(size);
+    BufReader::with_capacity(chunk, file).read_to_end(&mut bytes)?;
+    Ok((absolute, bytes))
+}
+
+/// Errors a network file system may recover from on its own.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::StaleNetworkFileHandle
+            | ErrorKind::ResourceBusy
+    )
+}
+
+/// Lets the kernel read ahead more aggressively for the whole file.
+#[cfg(target_os = "linux")]
+fn advise_sequential(file: &File) {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the descriptor is valid for the duration of the call
+    unsafe {
+        libc::posix_fadvise(
+            file.as_raw_fd(),
+            0,
+            0,
+            libc::POSIX_FADV_SEQUENTIAL,
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise_sequential(_file: &File) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_fails_without_retrying() {
+        let config = IoConfig {
+            retries: 5,
+            ..IoConfig::default()
+        };
+        let start = std::time::Instant::now();
+        let error =
+            read_file(Path::new("/nonexistent/ferrite.png"), &config, u64::MAX)
+                .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(start.elapsed() < RETRY_DELAY);
+    }
+}
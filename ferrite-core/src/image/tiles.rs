@@ -0,0 +1,279 @@
+//! Drawing images larger than the GPU's texture size limit in tiles.
+//!
+//! Such an image is uploaded whole only as a downscaled overview. Once the
+//! view zooms in past the detail the overview holds, the tiles in view are
+//! cut from the decoded image and uploaded a few per frame, over the
+//! overview, and dropped again once panned or zoomed out of view.
+
+use eframe::egui::{
+    ColorImage,
+    Context,
+    Pos2,
+    Rect,
+    TextureHandle,
+    TextureOptions,
+};
+use image::DynamicImage;
+use std::collections::HashMap;
+use tracing::info_span;
+
+use super::display::DisplayConversion;
+
+/// Side of a tile in image pixels, a multiple of the dither pattern so it
+/// lines up across tiles
+pub const TILE_SIDE: u32 = 1024;
+/// Pixels each tile repeats of its neighbours, so filtering at a seam
+/// blends across it instead of clamping to the tile edge
+const BORDER: u32 = 1;
+/// Keeps panning responsive while new tiles come into view
+const UPLOADS_PER_FRAME: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub column: u32,
+    pub row:    u32,
+}
+
+/// Rectangle of image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x:      u32,
+    pub y:      u32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+/// Division of an image into square tiles, the last row and column cut
+/// short by its edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    width:  u32,
+    height: u32,
+    side:   u32,
+}
+
+impl TileGrid {
+    pub fn new(width: u32, height: u32, side: u32) -> Self {
+        Self {
+            width,
+            height,
+            side,
+        }
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.width.div_ceil(self.side)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.height.div_ceil(self.side)
+    }
+
+    /// Pixels `tile` covers
+    pub fn bounds(&self, tile: Tile) -> PixelRect {
+        let x = tile.column * self.side;
+        let y = tile.row * self.side;
+        PixelRect {
+            x,
+            y,
+            width: self.side.min(self.width - x),
+            height: self.side.min(self.height - y),
+        }
+    }
+
+    /// Pixels uploaded for `tile`: its bounds and a border taken from its
+    /// neighbours
+    pub fn padded(&self, tile: Tile) -> PixelRect {
+        let bounds = self.bounds(tile);
+        let x = bounds.x.saturating_sub(BORDER);
+        let y = bounds.y.saturating_sub(BORDER);
+        PixelRect {
+            x,
+            y,
+            width: (bounds.x + bounds.width + BORDER).min(self.width) - x,
+            height: (bounds.y + bounds.height + BORDER).min(self.height) - y,
+        }
+    }
+
+    /// Where the bounds of `tile` lie in the texture of its padded pixels
+    pub fn inner_uv(&self, tile: Tile) -> Rect {
+        let bounds = self.bounds(tile);
+        let padded = self.padded(tile);
+        let uv = |x: u32, y: u32| {
+            Pos2::new(
+                (x - padded.x) as f32 / padded.width as f32,
+                (y - padded.y) as f32 / padded.height as f32,
+            )
+        };
+        Rect::from_min_max(
+            uv(bounds.x, bounds.y),
+            uv(bounds.x + bounds.width, bounds.y + bounds.height),
+        )
+    }
+
+    /// Where the bounds of `tile` lie in the whole image, from (0, 0) at
+    /// its top left to (1, 1) at its bottom right
+    pub fn image_uv(&self, tile: Tile) -> Rect {
+        let bounds = self.bounds(tile);
+        let uv = |x: u32, y: u32| {
+            Pos2::new(
+                x as f32 / self.width as f32,
+                y as f32 / self.height as f32,
+            )
+        };
+        Rect::from_min_max(
+            uv(bounds.x, bounds.y),
+            uv(bounds.x + bounds.width, bounds.y + bounds.height),
+        )
+    }
+
+    /// Tiles overlapping `area`, given in the coordinates of
+    /// [`Self::image_uv`]
+    pub fn covering(&self, area: Rect) -> Vec<Tile> {
+        let area =
+            area.intersect(Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)));
+        if !area.is_positive() {
+            return Vec::new();
+        }
+        let range = |min: f32, max: f32, size: u32, count: u32| {
+            let first = (min * size as f32 / self.side as f32).floor() as u32;
+            let end = (max * size as f32 / self.side as f32).ceil() as u32;
+            first.min(count)..end.min(count)
+        };
+        let columns = range(area.min.x, area.max.x, self.width, self.columns());
+        let rows = range(area.min.y, area.max.y, self.height, self.rows());
+        rows.flat_map(|row| {
+            columns.clone().map(move |column| Tile {
+                column,
+                row,
+            })
+        })
+        .collect()
+    }
+}
+
+/// Full resolution tiles of an image too large for one texture
+pub struct TiledTexture {
+    grid:       TileGrid,
+    textures:   HashMap<Tile, TextureHandle>,
+    /// Same as for the overview
+    conversion: DisplayConversion,
+}
+
+impl TiledTexture {
+    pub fn new(width: u32, height: u32, conversion: DisplayConversion) -> Self {
+        Self {
+            grid: TileGrid::new(width, height, TILE_SIDE),
+            textures: HashMap::new(),
+            conversion,
+        }
+    }
+
+    pub fn grid(&self) -> &TileGrid {
+        &self.grid
+    }
+
+    pub fn texture(&self, tile: Tile) -> Option<&TextureHandle> {
+        self.textures.get(&tile)
+    }
+
+    /// Uploads the tiles of `image` in `visible` that are missing, a few at
+    /// a time, and frees all others. Returns whether some are still missing.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        image: &DynamicImage,
+        visible: &[Tile],
+        options: TextureOptions,
+    ) -> bool {
+        self.textures
+            .retain(|tile, _| visible.contains(tile));
+
+        let mut missing = visible
+            .iter()
+            .filter(|tile| !self.textures.contains_key(tile))
+            .copied()
+            .collect::<Vec<_>>();
+        let pending = missing.len() > UPLOADS_PER_FRAME;
+        missing.truncate(UPLOADS_PER_FRAME);
+
+        for tile in missing {
+            let _span = info_span!("tile_upload").entered();
+            let rect = self.grid.padded(tile);
+            let pixels = self.conversion.apply(&image.crop_imm(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+            ));
+            let texture = ctx.load_texture(
+                format!("current-image-tile-{}-{}", tile.column, tile.row),
+                ColorImage::from_rgba_unmultiplied(
+                    [rect.width as usize, rect.height as usize],
+                    pixels.as_raw(),
+                ),
+                options,
+            );
+            self.textures.insert(tile, texture);
+        }
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(column: u32, row: u32) -> Tile {
+        Tile {
+            column,
+            row,
+        }
+    }
+
+    #[test]
+    fn test_grid_covers_the_image_with_overlapping_borders() {
+        let grid = TileGrid::new(2500, 1000, 1024);
+        assert_eq!((grid.columns(), grid.rows()), (3, 1));
+
+        // The last column is cut short by the image edge
+        assert_eq!(grid.bounds(tile(2, 0)), PixelRect {
+            x:      2048,
+            y:      0,
+            width:  452,
+            height: 1000,
+        });
+        // Inner tiles borrow a pixel on each side, edge tiles only inwards
+        assert_eq!(grid.padded(tile(1, 0)), PixelRect {
+            x:      1023,
+            y:      0,
+            width:  1026,
+            height: 1000,
+        });
+        assert_eq!(grid.padded(tile(2, 0)).width, 453);
+
+        let uv = grid.inner_uv(tile(1, 0));
+        assert!((uv.min.x - 1.0 / 1026.0).abs() < 1e-6);
+        assert!((uv.max.x - 1025.0 / 1026.0).abs() < 1e-6);
+        assert_eq!((uv.min.y, uv.max.y), (0.0, 1.0));
+        assert_eq!(grid.image_uv(tile(2, 0)).max, Pos2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn test_only_tiles_in_view_are_needed() {
+        let grid = TileGrid::new(5000, 3000, 1024);
+        let covering = |min: (f32, f32), max: (f32, f32)| {
+            grid.covering(Rect::from_min_max(min.into(), max.into()))
+        };
+
+        // Pixels 1500..2300 by 1400..2000
+        assert_eq!(covering((0.3, 0.4667), (0.46, 0.6667)), [
+            tile(1, 1),
+            tile(2, 1)
+        ]);
+        assert_eq!(covering((0.0, 0.0), (1.0, 1.0)).len(), 15);
+        // Parts outside the image need nothing
+        assert_eq!(covering((-0.5, 0.9), (-0.1, 1.2)), []);
+        assert_eq!(covering((0.99, 0.99), (1.5, 1.5)), [tile(4, 2)]);
+    }
+}
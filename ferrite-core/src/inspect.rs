@@ -0,0 +1,97 @@
+//! Reading exact pixel values from the decoded image, for the pixel
+//! inspector and the status bar. Values come from the image in memory, not
+//! from the texture, so they are the file's own colors before any display
+//! conversion.
+
+use image::{DynamicImage, GenericImageView};
+
+/// One pixel of the image, as 8-bit RGBA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelSample {
+    pub x:    u32,
+    pub y:    u32,
+    pub rgba: [u8; 4],
+}
+
+impl PixelSample {
+    /// `#RRGGBB`, with the alpha appended when the pixel is not opaque
+    pub fn hex(&self) -> String {
+        let [r, g, b, a] = self.rgba;
+        match a {
+            255 => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            a => format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a),
+        }
+    }
+}
+
+/// The pixel containing the image coordinates `(x, y)`, if inside the
+/// image.
+pub fn sample(image: &DynamicImage, (x, y): (f32, f32)) -> Option<PixelSample> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    let (x, y) = (x as u32, y as u32);
+    (x < image.width() && y < image.height()).then(|| PixelSample {
+        x,
+        y,
+        rgba: image.get_pixel(x, y).0,
+    })
+}
+
+/// The square of pixels `radius` around `center`, row by row. Pixels
+/// outside the image are `None`.
+pub fn neighbourhood(
+    image: &DynamicImage,
+    center: PixelSample,
+    radius: u32,
+) -> Vec<Option<[u8; 4]>> {
+    let radius = radius as i64;
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let mut pixels = Vec::with_capacity((2 * radius as usize + 1).pow(2));
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let (x, y) = (center.x as i64 + dx, center.y as i64 + dy);
+            let inside = (0..width).contains(&x) && (0..height).contains(&y);
+            pixels.push(inside.then(|| image.get_pixel(x as u32, y as u32).0));
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn test_image() -> DynamicImage {
+        let mut image = RgbaImage::new(4, 3);
+        image.put_pixel(2, 1, Rgba([255, 128, 0, 255]));
+        image.put_pixel(0, 0, Rgba([1, 2, 3, 4]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn test_sample_and_hex() {
+        let image = test_image();
+        let pixel = sample(&image, (2.7, 1.2)).unwrap();
+        assert_eq!((pixel.x, pixel.y), (2, 1));
+        assert_eq!(pixel.hex(), "#FF8000");
+        assert_eq!(sample(&image, (0.5, 0.5)).unwrap().hex(), "#01020304");
+
+        assert_eq!(sample(&image, (4.0, 0.0)), None);
+        assert_eq!(sample(&image, (-0.5, 0.0)), None);
+    }
+
+    #[test]
+    fn test_neighbourhood_at_the_edge() {
+        let image = test_image();
+        let corner = sample(&image, (0.0, 0.0)).unwrap();
+        let pixels = neighbourhood(&image, corner, 1);
+        assert_eq!(pixels.len(), 9);
+        // The row above and the column to the left are outside
+        assert!(pixels[..4].iter().all(Option::is_none));
+        assert_eq!(pixels[4], Some([1, 2, 3, 4]));
+        assert_eq!(pixels[6], None);
+        assert_eq!(pixels[8], Some([0, 0, 0, 0]));
+    }
+}
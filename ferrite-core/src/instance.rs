@@ -0,0 +1,408 @@
+//! Keeping to one window.
+//!
+//! The first window listens on a socket local to the user: a Unix domain
+//! socket, or elsewhere a loopback port written to a file at the same
+//! place. Starting the viewer again with an image connects to it, sends the
+//! absolute paths, one per line, and exits, so the running window opens the
+//! image instead of a second one appearing. A socket left behind by a window
+//! that crashed is noticed because nothing answers on it, and replaced.
+//!
+//! Only the user may reach the window. The Unix socket lives in a folder
+//! only they can enter, the runtime directory or one created for it, and a
+//! socket owned by anyone else is not connected to. A loopback port is open
+//! to every local user, so the file naming it also holds a random token
+//! that each connection has to send first.
+
+use ferrite_config::InstanceConfig;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+use crate::remote;
+
+/// First line of every message, so stray connections are ignored
+const HEADER: &str = "ferrite-open 1";
+/// How long a connection may take to send its paths
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where the running window listens, as configured or the default for the
+/// user.
+pub fn socket_path(config: &InstanceConfig) -> PathBuf {
+    config
+        .socket_path
+        .clone()
+        .unwrap_or_else(platform::default_path)
+}
+
+/// Hands `paths` to the window listening on `socket`. Returns whether one
+/// took them; when none runs, or a path cannot be sent, the caller opens a
+/// window of its own.
+pub fn forward(socket: &Path, paths: &[PathBuf]) -> bool {
+    let connection = match platform::connect(socket) {
+        Ok(connection) => connection,
+        Err(e) => {
+            debug!("No window is listening on {}: {}", socket.display(), e);
+            return false;
+        },
+    };
+    let (mut stream, token) = connection;
+    let mut message = format!("{}\n", HEADER);
+    if let Some(token) = token {
+        message.push_str(&token);
+        message.push('\n');
+    }
+    for path in paths {
+        let absolute = match path.to_str() {
+            Some(url) if remote::is_url(url) => path.clone(),
+            _ => fs::canonicalize(path).unwrap_or_else(|_| {
+                std::env::current_dir()
+                    .map(|dir| dir.join(path))
+                    .unwrap_or_else(|_| path.clone())
+            }),
+        };
+        match absolute.to_str() {
+            Some(line) if !line.contains('\n') => {
+                message.push_str(line);
+                message.push('\n');
+            },
+            _ => return false,
+        }
+    }
+
+    let sent = stream
+        .set_write_timeout(Some(TIMEOUT))
+        .and_then(|()| stream.write_all(message.as_bytes()));
+    match sent {
+        Ok(()) => {
+            info!("Opened in the running window through {}", socket.display());
+            true
+        },
+        Err(e) => {
+            debug!("Failed to send to {}: {}", socket.display(), e);
+            false
+        },
+    }
+}
+
+/// The socket of the running window, bound but not yet answering.
+pub struct Listener {
+    socket: platform::Socket,
+    path:   PathBuf,
+    /// Sent first by every connection, where others could connect too
+    token:  Option<String>,
+}
+
+impl Listener {
+    /// Takes `path` for this window. Fails while another window holds it,
+    /// or when its folder is open to other users.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            platform::private_dir(parent)?;
+        }
+        let (socket, token) = platform::bind(path)?;
+        Ok(Self {
+            socket,
+            path: path.to_path_buf(),
+            token,
+        })
+    }
+
+    /// Starts answering on a background thread, calling `wake` whenever
+    /// paths arrive so the window looks at its [`Inbox`].
+    pub fn serve(self, wake: impl Fn() + Send + 'static) -> Inbox {
+        let (sender, receiver) = mpsc::channel();
+        let socket = self.socket;
+        let token = self.token;
+        thread::spawn(move || {
+            for stream in socket.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept a connection: {}", e);
+                        continue;
+                    },
+                };
+                let _ = stream.set_read_timeout(Some(TIMEOUT));
+                let mut lines = BufReader::new(stream).lines();
+                if !matches!(lines.next(), Some(Ok(header)) if header == HEADER)
+                {
+                    continue;
+                }
+                if let Some(token) = &token {
+                    if !matches!(lines.next(), Some(Ok(sent)) if sent == *token)
+                    {
+                        warn!("Ignoring a connection without the token");
+                        continue;
+                    }
+                }
+                let paths: Vec<PathBuf> = lines
+                    .map_while(Result::ok)
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect();
+                if paths.is_empty() {
+                    continue;
+                }
+                if sender.send(paths).is_err() {
+                    break;
+                }
+                wake();
+            }
+        });
+        Inbox {
+            receiver,
+            path: self.path,
+        }
+    }
+}
+
+/// Paths sent by later starts of the viewer
+pub struct Inbox {
+    receiver: Receiver<Vec<PathBuf>>,
+    path:     PathBuf,
+}
+
+impl Inbox {
+    /// The paths of the next start that sent some, if any is waiting.
+    pub fn take(&self) -> Option<Vec<PathBuf>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for Inbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{
+        env,
+        fs::{self, DirBuilder},
+        io::{self, ErrorKind},
+        os::unix::{
+            fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+        path::{Path, PathBuf},
+    };
+
+    pub type Socket = UnixListener;
+
+    fn uid() -> u32 {
+        // SAFETY: getuid has no preconditions and cannot fail
+        unsafe { libc::getuid() }
+    }
+
+    pub fn default_path() -> PathBuf {
+        match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => PathBuf::from(dir).join("ferrite.sock"),
+            None => env::temp_dir()
+                .join(format!("ferrite-{}", uid()))
+                .join("instance.sock"),
+        }
+    }
+
+    /// Creates `dir` for the user alone, or checks that it is theirs and
+    /// that nobody else can add to it.
+    pub fn private_dir(dir: &Path) -> io::Result<()> {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        let metadata = fs::symlink_metadata(dir)?;
+        if !metadata.is_dir()
+            || metadata.uid() != uid()
+            || metadata.mode() & 0o022 != 0
+        {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} is open to other users", dir.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Connects to the socket at `path` if the user owns it. There is no
+    /// token: only the user can reach it.
+    pub fn connect(path: &Path) -> io::Result<(UnixStream, Option<String>)> {
+        if fs::symlink_metadata(path)?.uid() != uid() {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} belongs to another user", path.display()),
+            ));
+        }
+        Ok((UnixStream::connect(path)?, None))
+    }
+
+    pub fn bind(path: &Path) -> io::Result<(UnixListener, Option<String>)> {
+        let listener = match UnixListener::bind(path) {
+            // Left behind by a window that did not close cleanly
+            Err(e)
+                if e.kind() == ErrorKind::AddrInUse
+                    && UnixStream::connect(path).is_err() =>
+            {
+                fs::remove_file(path)?;
+                UnixListener::bind(path)
+            },
+            result => result,
+        }?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok((listener, None))
+    }
+}
+
+/// Without Unix domain sockets, a loopback port and the token to send are
+/// kept in the file
+#[cfg(not(unix))]
+mod platform {
+    use std::{
+        env,
+        fs,
+        io::{self, ErrorKind},
+        net::{Ipv4Addr, TcpListener, TcpStream},
+        path::{Path, PathBuf},
+    };
+
+    pub type Socket = TcpListener;
+
+    /// In the temporary folder of the user, which only they can read
+    pub fn default_path() -> PathBuf {
+        env::temp_dir().join("ferrite-instance.port")
+    }
+
+    pub fn private_dir(dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    pub fn connect(path: &Path) -> io::Result<(TcpStream, Option<String>)> {
+        let content = fs::read_to_string(path)?;
+        let (port, token) = content
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| io::Error::from(ErrorKind::InvalidData))?;
+        let port: u16 = port
+            .parse()
+            .map_err(|_| io::Error::from(ErrorKind::InvalidData))?;
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+        Ok((stream, Some(token.to_string())))
+    }
+
+    pub fn bind(path: &Path) -> io::Result<(TcpListener, Option<String>)> {
+        if connect(path).is_ok() {
+            return Err(ErrorKind::AddrInUse.into());
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        // From the system, so other users cannot guess it
+        let mut secret = [0; 16];
+        getrandom::getrandom(&mut secret).map_err(io::Error::other)?;
+        let token: String = secret
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        fs::write(
+            path,
+            format!("{} {}", listener.local_addr()?.port(), token),
+        )?;
+        Ok((listener, Some(token)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
+
+    fn socket(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ferrite-instance-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("socket")
+    }
+
+    #[test]
+    fn test_paths_reach_the_running_window() {
+        let path = socket("forward");
+        let woken = Arc::new(AtomicUsize::new(0));
+        let counter = woken.clone();
+        let inbox = Listener::bind(&path).unwrap().serve(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let url = PathBuf::from("https://example.com/a.png");
+        assert!(forward(&path, &[PathBuf::from("relative.png"), url.clone()]));
+        let start = Instant::now();
+        let paths = loop {
+            if let Some(paths) = inbox.take() {
+                break paths;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].is_absolute());
+        assert!(paths[0].ends_with("relative.png"));
+        assert_eq!(paths[1], url);
+        while woken.load(Ordering::SeqCst) == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        // A second window does not take the socket over
+        assert!(Listener::bind(&path).is_err());
+        drop(inbox);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_nobody_listening_is_noticed() {
+        let path = socket("stale");
+        assert!(!forward(&path, &[PathBuf::from("a.png")]));
+
+        // As left by a window that crashed
+        #[cfg(unix)]
+        {
+            platform::private_dir(path.parent().unwrap()).unwrap();
+            drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        }
+        assert!(!forward(&path, &[PathBuf::from("a.png")]));
+        let _inbox = Listener::bind(&path).unwrap().serve(|| {});
+        assert!(forward(&path, &[PathBuf::from("a.png")]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_folders_open_to_others_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = socket("shared");
+        let dir = path.parent().unwrap();
+        fs::create_dir_all(dir).unwrap();
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert_eq!(
+            Listener::bind(&path).err().map(|e| e.kind()),
+            Some(io::ErrorKind::PermissionDenied)
+        );
+
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).unwrap();
+        let _inbox = Listener::bind(&path).unwrap().serve(|| {});
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0);
+    }
+}
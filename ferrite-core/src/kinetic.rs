@@ -0,0 +1,75 @@
+//! Kinetic scrolling: a pan that keeps going after the fingers lift off
+//! the touchpad and slows to a stop.
+
+use eframe::egui::Vec2;
+
+/// Share of the speed lost per second, as the rate of an exponential decay
+const FRICTION: f32 = 5.0;
+
+/// Points per second below which coasting stops
+const STOP_SPEED: f32 = 20.0;
+
+/// Weight of the latest movement in the tracked speed, smoothing out
+/// uneven touchpad events
+const SMOOTHING: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Coasting {
+    /// Points per second
+    velocity: Vec2,
+}
+
+impl Coasting {
+    /// Follows fingers that moved by `delta` over the last `dt` seconds.
+    pub fn track(&mut self, delta: Vec2, dt: f32) {
+        let velocity = delta / dt.max(1e-3);
+        self.velocity =
+            self.velocity * (1.0 - SMOOTHING) + velocity * SMOOTHING;
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = Vec2::ZERO;
+    }
+
+    /// How far to move over the next `dt` seconds, or `None` once slowed to
+    /// a stop.
+    pub fn coast(&mut self, dt: f32) -> Option<Vec2> {
+        if self.velocity.length() < STOP_SPEED {
+            self.stop();
+            return None;
+        }
+        let step = self.velocity * dt;
+        self.velocity *= (-FRICTION * dt).exp();
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coasting_slows_to_a_stop() {
+        let mut coasting = Coasting::default();
+        for _ in 0..10 {
+            coasting.track(Vec2::new(10.0, 0.0), 0.01);
+        }
+        let mut steps = Vec::new();
+        while let Some(step) = coasting.coast(0.01) {
+            steps.push(step.x);
+            assert!(steps.len() < 1000, "never stopped");
+        }
+        // Starting near the speed of the fingers, ever slower
+        assert!((steps[0] - 10.0).abs() < 0.1);
+        assert!(steps.windows(2).all(|pair| pair[1] < pair[0]));
+        assert_eq!(coasting.coast(0.01), None);
+    }
+
+    #[test]
+    fn test_stopping_ends_coasting() {
+        let mut coasting = Coasting::default();
+        coasting.track(Vec2::new(0.0, -50.0), 0.02);
+        coasting.stop();
+        assert_eq!(coasting.coast(0.02), None);
+    }
+}
@@ -0,0 +1,180 @@
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+
+use super::{DistortionModel, TcaModel};
+use crate::filters::ViewFilter;
+
+/// Which lens corrections to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorrectionOptions {
+    pub distortion: bool,
+    pub tca:        bool,
+    pub vignetting: bool,
+}
+
+impl CorrectionOptions {
+    pub fn all() -> Self {
+        Self {
+            distortion: true, tca: true, vignetting: true
+        }
+    }
+}
+
+impl Default for CorrectionOptions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Lens correction for one focal length and aperture. Distortion and
+/// chromatic aberration use radii normalized to half the shorter image side,
+/// vignetting to half the diagonal, as in lensfun.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LensCorrection {
+    pub distortion: Option<DistortionModel>,
+    pub tca:        Option<TcaModel>,
+    pub vignetting: Option<[f32; 3]>,
+}
+
+impl LensCorrection {
+    pub fn is_identity(&self) -> bool {
+        self.distortion.is_none()
+            && self.tca.is_none()
+            && self.vignetting.is_none()
+    }
+
+    pub fn correct(&self, source: &RgbaImage) -> RgbaImage {
+        let (width, height) = source.dimensions();
+        let center = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+        let radius = (width.min(height) as f32 / 2.0).max(1.0);
+        let diagonal = ((width as f32).hypot(height as f32) / 2.0).max(1.0);
+
+        let mut output = RgbaImage::new(width, height);
+        output
+            .par_chunks_mut(width as usize * 4)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                    let nx = (x as f32 - center.0) / radius;
+                    let ny = (y as f32 - center.1) / radius;
+
+                    let scale = self
+                        .distortion
+                        .map_or(1.0, |d| d.scale(nx.hypot(ny)));
+                    let (dx, dy) = (nx * scale, ny * scale);
+                    let to_pixel = |factor: f32| {
+                        (
+                            center.0 + dx * factor * radius,
+                            center.1 + dy * factor * radius,
+                        )
+                    };
+
+                    let green = to_pixel(1.0);
+                    let mut value = sample(source, green);
+                    if let Some(tca) = &self.tca {
+                        let rd = dx.hypot(dy);
+                        let channel =
+                            |k: [f32; 3]| k[0] * rd * rd + k[1] * rd + k[2];
+                        value[0] =
+                            sample(source, to_pixel(channel(tca.red)))[0];
+                        value[2] =
+                            sample(source, to_pixel(channel(tca.blue)))[2];
+                    }
+
+                    if let Some([k1, k2, k3]) = self.vignetting {
+                        let r2 = ((green.0 - center.0).powi(2)
+                            + (green.1 - center.1).powi(2))
+                            / (diagonal * diagonal);
+                        let falloff =
+                            1.0 + k1 * r2 + k2 * r2 * r2 + k3 * r2.powi(3);
+                        if falloff > 0.0 {
+                            for channel in &mut value[..3] {
+                                *channel /= falloff;
+                            }
+                        }
+                    }
+
+                    for (out, v) in pixel.iter_mut().zip(value) {
+                        *out = v.round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            });
+        output
+    }
+}
+
+impl ViewFilter for LensCorrection {
+    fn name(&self) -> &'static str {
+        "Lens correction"
+    }
+
+    fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        DynamicImage::ImageRgba8(self.correct(&image.to_rgba8()))
+    }
+}
+
+/// Bilinear sample; positions outside the image are transparent black.
+fn sample(image: &RgbaImage, (x, y): (f32, f32)) -> [f32; 4] {
+    let (width, height) = image.dimensions();
+    if x < -0.5 || y < -0.5 || x > width as f32 - 0.5 || y > height as f32 - 0.5
+    {
+        return [0.0; 4];
+    }
+    let x = x.clamp(0.0, width as f32 - 1.0);
+    let y = y.clamp(0.0, height as f32 - 1.0);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let mut result = [0.0; 4];
+    for (channel, out) in result.iter_mut().enumerate() {
+        let at = |px: u32, py: u32| image.get_pixel(px, py)[channel] as f32;
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        *out = top * (1.0 - fy) + bottom * fy;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(40, 30, |x, y| {
+            Rgba([(x * 6) as u8, (y * 8) as u8, 100, 255])
+        })
+    }
+
+    #[test]
+    fn test_zero_coefficients_are_identity() {
+        let correction = LensCorrection {
+            distortion: Some(DistortionModel::PtLens {
+                a: 0.0,
+                b: 0.0,
+                c: 0.0,
+            }),
+            tca:        Some(TcaModel {
+                red:  [0.0, 0.0, 1.0],
+                blue: [0.0, 0.0, 1.0],
+            }),
+            vignetting: Some([0.0, 0.0, 0.0]),
+        };
+        let source = gradient();
+        assert_eq!(correction.correct(&source), source);
+    }
+
+    #[test]
+    fn test_vignetting_brightens_corners() {
+        let source = RgbaImage::from_pixel(41, 41, Rgba([100, 100, 100, 255]));
+        let correction = LensCorrection {
+            vignetting: Some([-0.5, 0.0, 0.0]),
+            ..LensCorrection::default()
+        };
+        let corrected = correction.correct(&source);
+        assert_eq!(corrected.get_pixel(20, 20)[0], 100);
+        assert!(corrected.get_pixel(0, 0)[0] > 180);
+        assert_eq!(corrected.get_pixel(0, 0)[3], 255);
+    }
+}
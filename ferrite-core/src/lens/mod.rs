@@ -0,0 +1,535 @@
+//! Lens correction profiles from the lensfun database.
+//!
+//! Only the parts of the lensfun XML format needed for correction are read:
+//! lens maker and model plus the distortion, transverse chromatic aberration
+//! and vignetting calibrations per focal length.
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+
+use crate::metadata::LensInfo;
+
+mod correct;
+
+pub use correct::{CorrectionOptions, LensCorrection};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DistortionModel {
+    /// `Rd = Ru * (a Ru³ + b Ru² + c Ru + 1 - a - b - c)`
+    PtLens { a: f32, b: f32, c: f32 },
+    /// `Rd = Ru * (1 - k1 + k1 Ru²)`
+    Poly3 { k1: f32 },
+    /// `Rd = Ru * (1 + k1 Ru² + k2 Ru⁴)`
+    Poly5 { k1: f32, k2: f32 },
+}
+
+impl DistortionModel {
+    /// Ratio between distorted and undistorted radius at radius `ru`
+    pub fn scale(&self, ru: f32) -> f32 {
+        match *self {
+            DistortionModel::PtLens {
+                a,
+                b,
+                c,
+            } => a * ru.powi(3) + b * ru * ru + c * ru + 1.0 - a - b - c,
+            DistortionModel::Poly3 {
+                k1,
+            } => 1.0 - k1 + k1 * ru * ru,
+            DistortionModel::Poly5 {
+                k1,
+                k2,
+            } => 1.0 + k1 * ru * ru + k2 * ru.powi(4),
+        }
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |x: f32, y: f32| x + (y - x) * t;
+        match (*self, *other) {
+            (
+                DistortionModel::PtLens {
+                    a,
+                    b,
+                    c,
+                },
+                DistortionModel::PtLens {
+                    a: a2,
+                    b: b2,
+                    c: c2,
+                },
+            ) => DistortionModel::PtLens {
+                a: mix(a, a2),
+                b: mix(b, b2),
+                c: mix(c, c2),
+            },
+            (
+                DistortionModel::Poly3 {
+                    k1,
+                },
+                DistortionModel::Poly3 {
+                    k1: k1b,
+                },
+            ) => DistortionModel::Poly3 {
+                k1: mix(k1, k1b)
+            },
+            (
+                DistortionModel::Poly5 {
+                    k1,
+                    k2,
+                },
+                DistortionModel::Poly5 {
+                    k1: k1b,
+                    k2: k2b,
+                },
+            ) => DistortionModel::Poly5 {
+                k1: mix(k1, k1b), k2: mix(k2, k2b)
+            },
+            // Different models cannot be blended; use the closer one
+            _ if t < 0.5 => *self,
+            _ => *other,
+        }
+    }
+}
+
+/// Per-channel `poly3` chromatic aberration coefficients `[b, c, v]` for
+/// `Rd = Ru * (b Ru² + c Ru + v)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TcaModel {
+    pub red:  [f32; 3],
+    pub blue: [f32; 3],
+}
+
+impl TcaModel {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |x: [f32; 3], y: [f32; 3]| {
+            [0, 1, 2].map(|i| x[i] + (y[i] - x[i]) * t)
+        };
+        Self {
+            red:  mix(self.red, other.red),
+            blue: mix(self.blue, other.blue),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Calibrated<T> {
+    focal: f32,
+    value: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vignetting {
+    focal:    f32,
+    aperture: f32,
+    distance: f32,
+    /// `pa` model: `Cd = Cs * (1 + k1 r² + k2 r⁴ + k3 r⁶)`
+    k:        [f32; 3],
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LensProfile {
+    pub maker:  String,
+    pub model:  String,
+    distortion: Vec<Calibrated<DistortionModel>>,
+    tca:        Vec<Calibrated<TcaModel>>,
+    vignetting: Vec<Vignetting>,
+}
+
+impl LensProfile {
+    pub fn has_distortion(&self) -> bool {
+        !self.distortion.is_empty()
+    }
+
+    pub fn has_tca(&self) -> bool {
+        !self.tca.is_empty()
+    }
+
+    pub fn has_vignetting(&self) -> bool {
+        !self.vignetting.is_empty()
+    }
+
+    /// Builds the correction for a shot at the given focal length and
+    /// aperture, interpolating between calibrated focal lengths.
+    pub fn correction(
+        &self,
+        focal: f32,
+        aperture: Option<f32>,
+        options: CorrectionOptions,
+    ) -> LensCorrection {
+        LensCorrection {
+            distortion: options
+                .distortion
+                .then(|| {
+                    interpolate(&self.distortion, focal, DistortionModel::lerp)
+                })
+                .flatten(),
+            tca:        options
+                .tca
+                .then(|| interpolate(&self.tca, focal, TcaModel::lerp))
+                .flatten(),
+            vignetting: options
+                .vignetting
+                .then(|| self.vignetting_at(focal, aperture))
+                .flatten(),
+        }
+    }
+
+    /// Picks the closest focal length, then the closest aperture (in stops),
+    /// preferring the farthest focus distance.
+    fn vignetting_at(
+        &self,
+        focal: f32,
+        aperture: Option<f32>,
+    ) -> Option<[f32; 3]> {
+        let nearest_focal = self
+            .vignetting
+            .iter()
+            .map(|v| v.focal)
+            .min_by(|a, b| (a - focal).abs().total_cmp(&(b - focal).abs()))?;
+        let stops = |f: f32| 2.0 * f.max(0.1).log2();
+
+        self.vignetting
+            .iter()
+            .filter(|v| v.focal == nearest_focal)
+            .min_by(|a, b| {
+                let distance_to = |v: &Vignetting| {
+                    aperture
+                        .map_or(0.0, |f| (stops(v.aperture) - stops(f)).abs())
+                };
+                distance_to(a)
+                    .total_cmp(&distance_to(b))
+                    .then(b.distance.total_cmp(&a.distance))
+            })
+            .map(|v| v.k)
+    }
+}
+
+fn interpolate<T: Copy>(
+    calibrations: &[Calibrated<T>],
+    focal: f32,
+    lerp: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let mut sorted: Vec<_> = calibrations.to_vec();
+    sorted.sort_by(|a, b| a.focal.total_cmp(&b.focal));
+    let first = sorted.first()?;
+    let last = sorted.last()?;
+    if focal <= first.focal {
+        return Some(first.value);
+    }
+    if focal >= last.focal {
+        return Some(last.value);
+    }
+    sorted.windows(2).find_map(|pair| {
+        let (low, high) = (&pair[0], &pair[1]);
+        (focal >= low.focal && focal <= high.focal).then(|| {
+            let span = high.focal - low.focal;
+            let t = if span > 0.0 { (focal - low.focal) / span } else { 0.0 };
+            lerp(&low.value, &high.value, t)
+        })
+    })
+}
+
+/// All lens profiles found in the lensfun database files
+#[derive(Debug, Default)]
+pub struct LensDatabase {
+    lenses: Vec<LensProfile>,
+}
+
+impl LensDatabase {
+    /// Standard install locations of the lensfun database
+    pub fn default_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let Some(data) = directories::BaseDirs::new() {
+            dirs.push(data.data_dir().join("lensfun"));
+        }
+        dirs.push(PathBuf::from("/usr/local/share/lensfun/version_1"));
+        dirs.push(PathBuf::from("/usr/share/lensfun/version_1"));
+        dirs
+    }
+
+    /// Reads every `.xml` file in the given directories that exist.
+    pub fn load(dirs: &[PathBuf]) -> Self {
+        let mut lenses = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+                if path.extension().is_some_and(|ext| ext == "xml") {
+                    lenses.extend(load_file(&path));
+                }
+            }
+        }
+        info!("Loaded {} lens profiles", lenses.len());
+        Self {
+            lenses,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn from_profiles(lenses: Vec<LensProfile>) -> Self {
+        Self {
+            lenses,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenses.is_empty()
+    }
+
+    /// Finds the profile for the lens a photo was taken with. An exact model
+    /// match wins; otherwise the longest database model whose words, apart
+    /// from the maker, all appear in the EXIF lens name is used.
+    pub fn find(&self, info: &LensInfo) -> Option<&LensProfile> {
+        let wanted = normalize(&info.lens_model);
+        if let Some(exact) = self
+            .lenses
+            .iter()
+            .find(|lens| normalize(&lens.model) == wanted)
+        {
+            return Some(exact);
+        }
+
+        let exif_tokens = tokens(&info.lens_model);
+        self.lenses
+            .iter()
+            .filter(|lens| {
+                let maker = normalize(&lens.maker);
+                let model: Vec<String> = tokens(&lens.model)
+                    .into_iter()
+                    .filter(|t| *t != maker)
+                    .collect();
+                !model.is_empty()
+                    && model.iter().all(|t| exif_tokens.contains(t))
+            })
+            .max_by_key(|lens| lens.model.len())
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '.')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn tokens(name: &str) -> Vec<String> {
+    name.split(|c: char| c.is_whitespace() || c == '/')
+        .map(normalize)
+        .filter(|t| !t.is_empty() && t != "f")
+        .collect()
+}
+
+fn load_file(path: &Path) -> Vec<LensProfile> {
+    match fs::read_to_string(path) {
+        Ok(xml) => parse(&xml).unwrap_or_else(|e| {
+            warn!("Invalid lensfun file {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(e) => {
+            debug!("Cannot read {}: {}", path.display(), e);
+            Vec::new()
+        },
+    }
+}
+
+/// Parses the `<lens>` entries of a lensfun XML document.
+pub fn parse(xml: &str) -> Result<Vec<LensProfile>, quick_xml::Error> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut lenses = Vec::new();
+    let mut current: Option<LensProfile> = None;
+    let mut text_target: Option<&'static str> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.name().as_ref() {
+                b"lens" => current = Some(LensProfile::default()),
+                b"maker" if current.is_some() => text_target = Some("maker"),
+                // Localized names carry a `lang` attribute; keep the default
+                b"model"
+                    if current.is_some() && attribute(&e, "lang").is_none() =>
+                {
+                    text_target = Some("model")
+                },
+                _ => {},
+            },
+            Event::Empty(e) => {
+                if let Some(lens) = current.as_mut() {
+                    read_calibration(&e, lens);
+                }
+            },
+            Event::Text(text) => {
+                if let (Some(target), Some(lens)) =
+                    (text_target, current.as_mut())
+                {
+                    let value = text.unescape()?.into_owned();
+                    match target {
+                        "maker" if lens.maker.is_empty() => lens.maker = value,
+                        "model" if lens.model.is_empty() => lens.model = value,
+                        _ => {},
+                    }
+                }
+            },
+            Event::End(e) => {
+                text_target = None;
+                if e.name().as_ref() == b"lens" {
+                    lenses
+                        .extend(current.take().filter(|l| !l.model.is_empty()));
+                }
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+    }
+    Ok(lenses)
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .filter_map(Result::ok)
+        .find(|a| a.key.as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+fn read_calibration(element: &BytesStart, lens: &mut LensProfile) {
+    let number = |name: &str| {
+        attribute(element, name).and_then(|v| v.parse::<f32>().ok())
+    };
+    let Some(focal) = number("focal") else {
+        return;
+    };
+    let model = attribute(element, "model").unwrap_or_default();
+    let zero = |name: &str| number(name).unwrap_or(0.0);
+
+    match (element.name().as_ref(), model.as_str()) {
+        (b"distortion", "ptlens") => lens.distortion.push(Calibrated {
+            focal,
+            value: DistortionModel::PtLens {
+                a: zero("a"),
+                b: zero("b"),
+                c: zero("c"),
+            },
+        }),
+        (b"distortion", "poly3") => lens.distortion.push(Calibrated {
+            focal,
+            value: DistortionModel::Poly3 {
+                k1: zero("k1")
+            },
+        }),
+        (b"distortion", "poly5") => lens.distortion.push(Calibrated {
+            focal,
+            value: DistortionModel::Poly5 {
+                k1: zero("k1"), k2: zero("k2")
+            },
+        }),
+        (b"tca", "poly3") => lens.tca.push(Calibrated {
+            focal,
+            value: TcaModel {
+                red:  [zero("br"), zero("cr"), number("vr").unwrap_or(1.0)],
+                blue: [zero("bb"), zero("cb"), number("vb").unwrap_or(1.0)],
+            },
+        }),
+        (b"tca", "linear") => lens.tca.push(Calibrated {
+            focal,
+            value: TcaModel {
+                red:  [0.0, 0.0, number("kr").unwrap_or(1.0)],
+                blue: [0.0, 0.0, number("kb").unwrap_or(1.0)],
+            },
+        }),
+        (b"vignetting", "pa") => lens.vignetting.push(Vignetting {
+            focal,
+            aperture: zero("aperture"),
+            distance: zero("distance"),
+            k: [zero("k1"), zero("k2"), zero("k3")],
+        }),
+        (name, model) => debug!(
+            "Skipping unsupported lensfun calibration {} / {}",
+            String::from_utf8_lossy(name),
+            model
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        <lensdatabase version="1">
+            <lens>
+                <maker>Canon</maker>
+                <model>Canon EF 24-105mm f/4L IS USM</model>
+                <model lang="de">Canon EF 24-105 mm</model>
+                <mount>Canon EF</mount>
+                <calibration>
+                    <distortion model="ptlens" focal="24" a="0.02" b="-0.06" c="0"/>
+                    <distortion model="ptlens" focal="105" a="0.0" b="0.01" c="0"/>
+                    <tca model="poly3" focal="24" vr="1.0004" vb="0.9998"/>
+                    <vignetting model="pa" focal="24" aperture="4" distance="10" k1="-0.6" k2="0.2" k3="-0.1"/>
+                    <vignetting model="pa" focal="24" aperture="8" distance="10" k1="-0.3" k2="0.1" k3="0"/>
+                </calibration>
+            </lens>
+        </lensdatabase>
+    "#;
+
+    fn info(model: &str, focal: f32) -> LensInfo {
+        LensInfo {
+            camera_make:  None,
+            camera_model: None,
+            lens_make:    None,
+            lens_model:   model.to_string(),
+            focal_length: focal,
+            aperture:     Some(5.6),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_match() {
+        let database = LensDatabase::from_profiles(parse(SAMPLE).unwrap());
+        let lens = database
+            .find(&info("EF 24-105mm f/4L IS USM", 50.0))
+            .unwrap();
+        assert_eq!(lens.maker, "Canon");
+        assert_eq!(lens.model, "Canon EF 24-105mm f/4L IS USM");
+        assert!(
+            lens.has_distortion() && lens.has_tca() && lens.has_vignetting()
+        );
+        assert!(database
+            .find(&info("Nikon AF-S 50mm f/1.8G", 50.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_focal_interpolation_and_vignetting_choice() {
+        let lens = &parse(SAMPLE).unwrap()[0];
+        let correction =
+            lens.correction(64.5, Some(5.6), CorrectionOptions::all());
+
+        match correction.distortion {
+            Some(DistortionModel::PtLens {
+                a,
+                b,
+                ..
+            }) => {
+                assert!((a - 0.01).abs() < 1e-6);
+                assert!((b + 0.025).abs() < 1e-6);
+            },
+            other => panic!("unexpected distortion {:?}", other),
+        }
+        // f/5.6 is one stop from both calibrations; the first wins the tie
+        assert_eq!(lens.vignetting_at(24.0, Some(8.0)), Some([-0.3, 0.1, 0.0]));
+        assert_eq!(
+            lens.vignetting_at(30.0, Some(4.0)),
+            Some([-0.6, 0.2, -0.1])
+        );
+    }
+}
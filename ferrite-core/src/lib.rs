@@ -1,6 +1,59 @@
+mod adjust;
+mod animation;
 mod app;
+mod archive;
+pub mod associations;
+mod batch;
+pub mod bench;
+mod camera;
+mod checksum;
+mod clipboard;
+mod codes;
+pub mod convert;
+pub mod crash;
+pub mod desktop;
+mod diff;
+mod dither;
+mod editor;
+mod export;
+mod favorites;
+mod file_dialog;
+mod filters;
+mod gallery;
+mod hdr;
+mod histogram;
 mod image;
+mod inspect;
+pub mod instance;
+mod kinetic;
+mod lens;
+mod linear;
+mod lut;
+mod map;
+mod measure;
+mod metadata;
+mod mipmap;
 mod navigation;
+mod palette;
+mod panorama;
+mod process;
+pub mod recent;
+mod remote;
+mod rename;
+pub mod sandbox;
+mod selection;
+pub mod session;
+mod slideshow;
+mod stack;
+mod straighten;
+mod trash;
 mod ui;
+mod upscale;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod uri;
+mod view_filter;
+pub mod wallpaper;
+mod warm_start;
+mod watch;
 
 pub use app::FeriteApp;
@@ -0,0 +1,361 @@
+//! Resampling and blending in linear light. Averaging sRGB-encoded values
+//! gives too dark a result wherever light and dark pixels meet, so fine
+//! detail dims when an image is scaled down.
+//!
+//! Pixels are decoded to linear values with premultiplied alpha, processed,
+//! and encoded back to the bit depth they came in. Float images are taken
+//! to hold linear values already, as HDR formats such as OpenEXR do.
+
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage,
+    Rgba32FImage,
+    RgbaImage,
+};
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+/// sRGB transfer function from encoded to linear values
+pub fn decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Inverse of [`decode`]; clamps to 0..=1
+pub fn encode(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn decode_u8(value: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| decode(i as f32 / 255.0)))
+        [value as usize]
+}
+
+fn encode_u8(value: f32) -> u8 {
+    (encode(value) * 255.0).round() as u8
+}
+
+enum Depth {
+    Eight,
+    Sixteen,
+    Float,
+}
+
+fn depth(image: &DynamicImage) -> Depth {
+    match image {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => Depth::Eight,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            Depth::Float
+        },
+        _ => Depth::Sixteen,
+    }
+}
+
+/// Averages `factor`×`factor` blocks of RGBA `samples` in linear light.
+/// Blocks at the right and bottom edges may be partial.
+fn box_linear<S: Copy + Sync>(
+    samples: &[S],
+    (width, height): (u32, u32),
+    factor: u32,
+    color: impl Fn(S) -> f32 + Sync,
+    alpha: impl Fn(S) -> f32 + Sync,
+) -> Rgba32FImage {
+    let out_width = width.div_ceil(factor);
+    let out_height = height.div_ceil(factor);
+    let mut data = vec![0.0; out_width as usize * out_height as usize * 4];
+    data.par_chunks_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(oy, row)| {
+            let y0 = oy as u32 * factor;
+            let y1 = (y0 + factor).min(height);
+            for (ox, out) in row.chunks_exact_mut(4).enumerate() {
+                let x0 = ox as u32 * factor;
+                let x1 = (x0 + factor).min(width);
+                let mut sum = [0.0; 4];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let i = (y as usize * width as usize + x as usize) * 4;
+                        let a = alpha(samples[i + 3]);
+                        for c in 0..3 {
+                            sum[c] += color(samples[i + c]) * a;
+                        }
+                        sum[3] += a;
+                    }
+                }
+                let count = ((x1 - x0) * (y1 - y0)) as f32;
+                for c in 0..4 {
+                    out[c] = sum[c] / count;
+                }
+            }
+        });
+    Rgba32FImage::from_raw(out_width, out_height, data)
+        .expect("buffer matches dimensions")
+}
+
+/// Decodes `image` to linear light with premultiplied alpha, shrunk by an
+/// integer `factor` on the way.
+fn shrink_to_linear(image: &DynamicImage, factor: u32) -> Rgba32FImage {
+    let size = (image.width(), image.height());
+    match depth(image) {
+        Depth::Eight => {
+            let converted;
+            let rgba = match image.as_rgba8() {
+                Some(rgba) => rgba,
+                None => {
+                    converted = image.to_rgba8();
+                    &converted
+                },
+            };
+            box_linear(rgba.as_raw(), size, factor, decode_u8, |a| {
+                a as f32 / 255.0
+            })
+        },
+        Depth::Sixteen => {
+            let rgba = image.to_rgba16();
+            box_linear(
+                rgba.as_raw(),
+                size,
+                factor,
+                |v| decode(v as f32 / 65535.0),
+                |a| a as f32 / 65535.0,
+            )
+        },
+        Depth::Float => {
+            let rgba = image.to_rgba32f();
+            box_linear(rgba.as_raw(), size, factor, |v| v, |a| a)
+        },
+    }
+}
+
+pub fn to_linear(image: &DynamicImage) -> Rgba32FImage {
+    shrink_to_linear(image, 1)
+}
+
+/// Encodes linear, premultiplied pixels back to the depth and channels of
+/// `like`.
+pub fn from_linear(linear: &Rgba32FImage, like: &DynamicImage) -> DynamicImage {
+    let straight = |pixel: &[f32]| {
+        let a = pixel[3];
+        let color = |c: usize| if a > 0.0 { pixel[c] / a } else { 0.0 };
+        [color(0), color(1), color(2), a.clamp(0.0, 1.0)]
+    };
+    let (width, height) = linear.dimensions();
+    let image = match depth(like) {
+        Depth::Eight => {
+            let data = linear
+                .as_raw()
+                .par_chunks_exact(4)
+                .flat_map_iter(|pixel| {
+                    let [r, g, b, a] = straight(pixel);
+                    [
+                        encode_u8(r),
+                        encode_u8(g),
+                        encode_u8(b),
+                        (a * 255.0).round() as u8,
+                    ]
+                })
+                .collect();
+            DynamicImage::ImageRgba8(
+                RgbaImage::from_raw(width, height, data)
+                    .expect("buffer matches dimensions"),
+            )
+        },
+        Depth::Sixteen => {
+            let data = linear
+                .as_raw()
+                .par_chunks_exact(4)
+                .flat_map_iter(|pixel| {
+                    let [r, g, b, a] = straight(pixel);
+                    let quantize = |v: f32| (v * 65535.0).round() as u16;
+                    [
+                        quantize(encode(r)),
+                        quantize(encode(g)),
+                        quantize(encode(b)),
+                        quantize(a),
+                    ]
+                })
+                .collect();
+            DynamicImage::ImageRgba16(
+                image::ImageBuffer::from_raw(width, height, data)
+                    .expect("buffer matches dimensions"),
+            )
+        },
+        Depth::Float => {
+            let data = linear
+                .as_raw()
+                .par_chunks_exact(4)
+                .flat_map_iter(straight)
+                .collect();
+            DynamicImage::ImageRgba32F(
+                Rgba32FImage::from_raw(width, height, data)
+                    .expect("buffer matches dimensions"),
+            )
+        },
+    };
+
+    if like.color().has_alpha() {
+        return image;
+    }
+    match image {
+        DynamicImage::ImageRgba8(_) => {
+            DynamicImage::ImageRgb8(image.into_rgb8())
+        },
+        DynamicImage::ImageRgba16(_) => {
+            DynamicImage::ImageRgb16(image.into_rgb16())
+        },
+        _ => DynamicImage::ImageRgb32F(image.into_rgb32f()),
+    }
+}
+
+/// Largest size within `max_width`×`max_height` with the aspect ratio of
+/// `width`×`height`
+fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let ratio = (max_width as f64 / width as f64)
+        .min(max_height as f64 / height as f64);
+    (
+        ((width as f64 * ratio).round() as u32).max(1),
+        ((height as f64 * ratio).round() as u32).max(1),
+    )
+}
+
+/// Like [`DynamicImage::resize_exact`], in linear light when `linear`
+pub fn resize_exact(
+    image: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+    linear: bool,
+) -> DynamicImage {
+    if !linear {
+        return image.resize_exact(width, height, filter);
+    }
+    let resized = imageops::resize(&to_linear(image), width, height, filter);
+    from_linear(&resized, image)
+}
+
+/// Like [`DynamicImage::resize`], in linear light when `linear`
+pub fn resize(
+    image: &DynamicImage,
+    max_width: u32,
+    max_height: u32,
+    filter: FilterType,
+    linear: bool,
+) -> DynamicImage {
+    let (width, height) =
+        fit(image.width(), image.height(), max_width, max_height);
+    resize_exact(image, width, height, filter, linear)
+}
+
+/// Like [`DynamicImage::thumbnail`], in linear light when `linear`. Large
+/// images are box-averaged first, which is fast and keeps memory use low.
+pub fn thumbnail(
+    image: &DynamicImage,
+    max_width: u32,
+    max_height: u32,
+    linear: bool,
+) -> DynamicImage {
+    if !linear {
+        return image.thumbnail(max_width, max_height);
+    }
+    let (width, height) =
+        fit(image.width(), image.height(), max_width, max_height);
+    // Leave the last factor of two or more to the triangle filter
+    let factor = (image.width() / width)
+        .min(image.height() / height)
+        .div_euclid(2)
+        .max(1);
+    let shrunk = shrink_to_linear(image, factor);
+    let resized =
+        imageops::resize(&shrunk, width, height, FilterType::Triangle);
+    from_linear(&resized, image)
+}
+
+/// Composites `top` over `bottom` with its top left corner at `x`, `y`,
+/// like [`imageops::overlay`], mixing colors in linear light when `linear`.
+pub fn overlay(
+    bottom: &mut RgbaImage,
+    top: &RgbaImage,
+    x: i64,
+    y: i64,
+    linear: bool,
+) {
+    if !linear {
+        imageops::overlay(bottom, top, x, y);
+        return;
+    }
+    for (tx, ty, source) in top.enumerate_pixels() {
+        let (bx, by) = (x + tx as i64, y + ty as i64);
+        if bx < 0
+            || by < 0
+            || bx >= bottom.width() as i64
+            || by >= bottom.height() as i64
+        {
+            continue;
+        }
+        let target = bottom.get_pixel_mut(bx as u32, by as u32);
+        let top_alpha = source[3] as f32 / 255.0;
+        let bottom_alpha = target[3] as f32 / 255.0 * (1.0 - top_alpha);
+        let alpha = top_alpha + bottom_alpha;
+        if alpha <= 0.0 {
+            continue;
+        }
+        for c in 0..3 {
+            let value = (decode_u8(source[c]) * top_alpha
+                + decode_u8(target[c]) * bottom_alpha)
+                / alpha;
+            target[c] = encode_u8(value);
+        }
+        target[3] = (alpha * 255.0).round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_transfer_round_trip() {
+        for value in 0..=255u8 {
+            assert_eq!(encode_u8(decode_u8(value)), value);
+        }
+        assert!((decode(0.5) - 0.214).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_downscaling_checkerboard_keeps_brightness() {
+        let checkerboard =
+            DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+                if (x + y) % 2 == 0 {
+                    Rgb([255, 255, 255])
+                } else {
+                    Rgb([0, 0, 0])
+                }
+            }));
+        // Half the light of white, which is 188 once encoded, where gamma
+        // space averaging gives 128
+        for image in [
+            thumbnail(&checkerboard, 8, 8, true),
+            resize(&checkerboard, 8, 8, FilterType::Triangle, true),
+        ] {
+            assert!(matches!(image, DynamicImage::ImageRgb8(_)));
+            assert_eq!(image.width(), 8);
+            let value = image.to_rgb8().get_pixel(4, 4)[0];
+            assert!((186..=190).contains(&value), "{}", value);
+        }
+        let gamma = thumbnail(&checkerboard, 8, 8, false);
+        assert!(gamma.to_rgb8().get_pixel(4, 4)[0] < 140);
+    }
+}
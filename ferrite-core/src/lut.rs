@@ -0,0 +1,327 @@
+//! Creative LUTs in the Adobe/Resolve `.cube` format, applied to the
+//! displayed image as a view filter.
+
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use rayon::prelude::*;
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
+
+use crate::filters::ViewFilter;
+
+#[derive(Error, Debug)]
+pub enum LutError {
+    #[error("Failed to read LUT: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("LUT does not declare LUT_1D_SIZE or LUT_3D_SIZE")]
+    MissingSize,
+
+    #[error("DOMAIN_MAX must exceed DOMAIN_MIN")]
+    InvalidDomain,
+
+    #[error("LUT has {found} entries, expected {expected}")]
+    EntryCount { found: usize, expected: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    /// One curve per channel
+    One,
+    /// A lattice indexed with red varying fastest
+    Three,
+}
+
+/// A parsed `.cube` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    pub title:  Option<String>,
+    dimension:  Dimension,
+    size:       usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    table:      Vec<[f32; 3]>,
+}
+
+impl CubeLut {
+    pub fn load(path: &Path) -> Result<Self, LutError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, LutError> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: &str| LutError::Parse {
+                line:    index + 1,
+                message: message.to_string(),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = line
+                .split_once(char::is_whitespace)
+                .unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    let value: usize =
+                        rest.parse().map_err(|_| error("invalid size"))?;
+                    if !(2..=256).contains(&value) {
+                        return Err(error("size must be between 2 and 256"));
+                    }
+                    let dimension = if keyword == "LUT_1D_SIZE" {
+                        Dimension::One
+                    } else {
+                        Dimension::Three
+                    };
+                    size = Some((dimension, value));
+                },
+                "DOMAIN_MIN" => {
+                    domain_min =
+                        triple(rest).ok_or_else(|| error("bad domain"))?
+                },
+                "DOMAIN_MAX" => {
+                    domain_max =
+                        triple(rest).ok_or_else(|| error("bad domain"))?
+                },
+                _ if keyword.starts_with(|c: char| {
+                    c.is_ascii_digit() || c == '-' || c == '.'
+                }) =>
+                {
+                    table.push(triple(line).ok_or_else(|| error("bad entry"))?);
+                },
+                // Vendor keywords such as LUT_IN_VIDEO_RANGE do not change
+                // the mapping of full-range images
+                _ => {},
+            }
+        }
+
+        let (dimension, size) = size.ok_or(LutError::MissingSize)?;
+        let expected = match dimension {
+            Dimension::One => size,
+            Dimension::Three => size * size * size,
+        };
+        if table.len() != expected {
+            return Err(LutError::EntryCount {
+                found: table.len(),
+                expected,
+            });
+        }
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            return Err(LutError::InvalidDomain);
+        }
+
+        Ok(Self {
+            title,
+            dimension,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// Maps one color with channels in 0..=1, interpolating between
+    /// lattice points.
+    pub fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let position: [f32; 3] = std::array::from_fn(|c| {
+            let t = (rgb[c] - self.domain_min[c])
+                / (self.domain_max[c] - self.domain_min[c]);
+            t.clamp(0.0, 1.0) * last
+        });
+
+        match self.dimension {
+            Dimension::One => std::array::from_fn(|c| {
+                let (i, f) = split(position[c], self.size);
+                lerp(self.table[i][c], self.table[i + 1][c], f)
+            }),
+            Dimension::Three => {
+                let (r, fr) = split(position[0], self.size);
+                let (g, fg) = split(position[1], self.size);
+                let (b, fb) = split(position[2], self.size);
+                let at = |r: usize, g: usize, b: usize| {
+                    self.table[(b * self.size + g) * self.size + r]
+                };
+                std::array::from_fn(|c| {
+                    let plane = |b: usize| {
+                        let low = lerp(at(r, g, b)[c], at(r + 1, g, b)[c], fr);
+                        let high = lerp(
+                            at(r, g + 1, b)[c],
+                            at(r + 1, g + 1, b)[c],
+                            fr,
+                        );
+                        lerp(low, high, fg)
+                    };
+                    lerp(plane(b), plane(b + 1), fb)
+                })
+            },
+        }
+    }
+}
+
+fn triple(text: &str) -> Option<[f32; 3]> {
+    let mut values = text
+        .split_whitespace()
+        .map(|v| v.parse::<f32>().ok());
+    let triple = [values.next()??, values.next()??, values.next()??];
+    values.next().is_none().then_some(triple)
+}
+
+/// Lower lattice index and the fraction towards the next one
+fn split(position: f32, size: usize) -> (usize, f32) {
+    let index = (position.floor() as usize).min(size - 2);
+    (index, position - index as f32)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Lists the `.cube` files in `folder` by name. A missing folder has none.
+pub fn list(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut luts: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("cube"))
+        })
+        .collect();
+    luts.sort_by_key(|path| path.file_name().map(|n| n.to_ascii_lowercase()));
+    luts
+}
+
+/// A LUT blended with the original colors by `strength`
+#[derive(Debug, Clone)]
+pub struct LutFilter {
+    pub lut:      Arc<CubeLut>,
+    pub strength: f32,
+}
+
+impl LutFilter {
+    fn grade(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let mapped = self.lut.map(rgb);
+        std::array::from_fn(|c| lerp(rgb[c], mapped[c], self.strength))
+    }
+}
+
+impl ViewFilter for LutFilter {
+    fn name(&self) -> &'static str {
+        "Creative LUT"
+    }
+
+    /// 8-bit images stay 8-bit; deeper ones are graded in floating point
+    /// so the LUT does not band them.
+    fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        match image {
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_) => {
+                let mut output: RgbaImage = image.to_rgba8();
+                output.par_chunks_mut(4).for_each(|pixel| {
+                    let rgb = std::array::from_fn(|c| pixel[c] as f32 / 255.0);
+                    let graded = self.grade(rgb);
+                    for c in 0..3 {
+                        pixel[c] =
+                            (graded[c].clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                });
+                DynamicImage::ImageRgba8(output)
+            },
+            _ => {
+                let mut output: Rgba32FImage = image.to_rgba32f();
+                output.par_chunks_mut(4).for_each(|pixel| {
+                    let graded = self.grade([pixel[0], pixel[1], pixel[2]]);
+                    pixel[..3].copy_from_slice(&graded);
+                });
+                DynamicImage::ImageRgba16(
+                    DynamicImage::ImageRgba32F(output).to_rgba16(),
+                )
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_identity_cube_keeps_colors() {
+        let mut text = String::from("TITLE \"Identity\"\nLUT_3D_SIZE 2\n");
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text.push_str(&format!("{} {} {}\n", r, g, b));
+                }
+            }
+        }
+        let lut = CubeLut::parse(&text).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Identity"));
+        let mapped = lut.map([0.25, 0.5, 0.75]);
+        for (a, b) in mapped.iter().zip([0.25, 0.5, 0.75]) {
+            assert!((a - b).abs() < 1e-6);
+        }
+
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            2,
+            2,
+            image::Rgb([10, 128, 250]),
+        ));
+        let filter = LutFilter {
+            lut: Arc::new(lut), strength: 1.0
+        };
+        assert_eq!(
+            filter.apply(&image).to_rgba8().get_pixel(1, 1),
+            &Rgba([10, 128, 250, 255])
+        );
+    }
+
+    #[test]
+    fn test_1d_curve_and_errors() {
+        let lut = CubeLut::parse("LUT_1D_SIZE 3\n1 1 1\n0.5 0.5 0.5\n0 0 0\n")
+            .unwrap();
+        let mapped = lut.map([0.0, 0.25, 1.0]);
+        assert_eq!(mapped[0], 1.0);
+        assert!((mapped[1] - 0.75).abs() < 1e-6);
+        assert_eq!(mapped[2], 0.0);
+
+        assert!(matches!(
+            CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n"),
+            Err(LutError::EntryCount {
+                found: 1, expected: 8
+            })
+        ));
+        assert!(matches!(
+            CubeLut::parse("LUT_1D_SIZE 2\n0 0\n1 1 1\n"),
+            Err(LutError::Parse {
+                line: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            CubeLut::parse("0 0 0\n"),
+            Err(LutError::MissingSize)
+        ));
+    }
+}
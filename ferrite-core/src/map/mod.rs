@@ -0,0 +1,105 @@
+//! Slippy-map support for showing where a photo was taken.
+//!
+//! Positions are projected with Web Mercator into "world pixels" at a given
+//! zoom level, which maps directly onto the 256px tile grid used by
+//! OpenStreetMap-compatible tile servers.
+
+use crate::metadata::GpsCoordinate;
+
+mod tiles;
+
+pub use tiles::{TileCache, TileId};
+
+/// Edge length of a map tile in pixels
+pub const TILE_SIZE: f64 = 256.0;
+
+/// Projects a coordinate to world pixel coordinates at `zoom`.
+pub fn project(coordinate: GpsCoordinate, zoom: u8) -> (f64, f64) {
+    let world = TILE_SIZE * f64::from(1u32 << zoom);
+    let lat = coordinate
+        .latitude
+        .clamp(-85.051_128, 85.051_128)
+        .to_radians();
+
+    let x = (coordinate.longitude + 180.0) / 360.0 * world;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI)
+        / 2.0
+        * world;
+    (x, y)
+}
+
+/// Picks the highest zoom level at which all coordinates fit into a view of
+/// `width` x `height` pixels.
+pub fn zoom_to_fit(
+    coordinates: &[GpsCoordinate],
+    width: f64,
+    height: f64,
+    max_zoom: u8,
+) -> u8 {
+    for zoom in (0..=max_zoom).rev() {
+        let projected: Vec<(f64, f64)> = coordinates
+            .iter()
+            .map(|&c| project(c, zoom))
+            .collect();
+        let (min_x, max_x, min_y, max_y) = bounds(&projected);
+        if max_x - min_x <= width && max_y - min_y <= height {
+            return zoom;
+        }
+    }
+    0
+}
+
+/// Returns the centre of the bounding box of a set of coordinates, in world
+/// pixels at `zoom`.
+pub fn center_of(coordinates: &[GpsCoordinate], zoom: u8) -> (f64, f64) {
+    let projected: Vec<(f64, f64)> = coordinates
+        .iter()
+        .map(|&c| project(c, zoom))
+        .collect();
+    let (min_x, max_x, min_y, max_y) = bounds(&projected);
+    ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0)
+}
+
+fn bounds(points: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    points.iter().fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(latitude: f64, longitude: f64) -> GpsCoordinate {
+        GpsCoordinate {
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn test_projection_of_origin_is_world_center() {
+        let (x, y) = project(coord(0.0, 0.0), 1);
+        assert!((x - 256.0).abs() < 1e-6);
+        assert!((y - 256.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_projection_matches_known_tile() {
+        // Berlin Brandenburg Gate lies in tile 8800/5373 at zoom 14
+        let (x, y) = project(coord(52.5163, 13.3777), 14);
+        assert_eq!((x / TILE_SIZE) as u32, 8800);
+        assert_eq!((y / TILE_SIZE) as u32, 5373);
+    }
+
+    #[test]
+    fn test_zoom_to_fit_shrinks_for_distant_points() {
+        let near = [coord(48.85, 2.35), coord(48.86, 2.36)];
+        let far = [coord(48.85, 2.35), coord(40.71, -74.0)];
+        assert!(zoom_to_fit(&near, 300.0, 300.0, 19) > 10);
+        assert!(zoom_to_fit(&far, 300.0, 300.0, 19) < 4);
+    }
+}
@@ -0,0 +1,173 @@
+use eframe::egui::{ColorImage, Context, TextureHandle, TextureOptions};
+use ferrite_config::MapConfig;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+use tracing::{debug, instrument, warn};
+
+/// Refuse tile responses larger than this; real tiles are a few dozen KB
+const MAX_TILE_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+enum TileState {
+    Loading,
+    Ready(TextureHandle),
+    Failed,
+}
+
+/// Downloads map tiles on a background thread, keeps them on disk between
+/// runs and holds the decoded ones as textures for drawing.
+pub struct TileCache {
+    tiles:    HashMap<TileId, TileState>,
+    requests: Sender<TileId>,
+    results:  Receiver<(TileId, Option<ColorImage>)>,
+}
+
+impl TileCache {
+    /// Tiles are kept in `map.cache_dir`, or `tiles` inside `cache_root`.
+    pub fn new(config: &MapConfig, cache_root: Option<PathBuf>) -> Self {
+        let (requests, request_rx) = mpsc::channel::<TileId>();
+        let (result_tx, results) = mpsc::channel();
+        let url = config.tile_url.clone();
+        let cache_dir = config
+            .cache_dir
+            .clone()
+            .or_else(|| cache_root.map(|root| root.join("tiles")));
+
+        thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new()
+                .user_agent(&format!("Ferrite/{}", env!("CARGO_PKG_VERSION")))
+                .timeout(Duration::from_secs(15))
+                .build();
+
+            for id in request_rx {
+                let image = load_tile(&agent, &url, cache_dir.as_deref(), id);
+                if result_tx.send((id, image)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            tiles: HashMap::new(),
+            requests,
+            results,
+        }
+    }
+
+    /// Turns finished downloads into textures.
+    #[instrument(skip_all)]
+    pub fn poll(&mut self, ctx: &Context) {
+        while let Ok((id, image)) = self.results.try_recv() {
+            let state = match image {
+                Some(image) => TileState::Ready(ctx.load_texture(
+                    format!("map-tile-{}-{}-{}", id.z, id.x, id.y),
+                    image,
+                    TextureOptions::LINEAR,
+                )),
+                None => TileState::Failed,
+            };
+            self.tiles.insert(id, state);
+            ctx.request_repaint();
+        }
+    }
+
+    /// Returns the texture for a tile, queueing a download if it has not
+    /// been requested yet.
+    pub fn tile(&mut self, id: TileId) -> Option<&TextureHandle> {
+        let state = self.tiles.entry(id).or_insert_with(|| {
+            let _ = self.requests.send(id);
+            TileState::Loading
+        });
+        match state {
+            TileState::Ready(texture) => Some(texture),
+            TileState::Loading | TileState::Failed => None,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.tiles
+            .values()
+            .any(|state| matches!(state, TileState::Loading))
+    }
+}
+
+#[instrument(skip(agent, url_template, cache_dir))]
+fn load_tile(
+    agent: &ureq::Agent,
+    url_template: &str,
+    cache_dir: Option<&Path>,
+    id: TileId,
+) -> Option<ColorImage> {
+    let cached = cache_dir.map(|dir| {
+        dir.join(id.z.to_string())
+            .join(id.x.to_string())
+            .join(format!("{}.png", id.y))
+    });
+
+    let bytes = match cached
+        .as_ref()
+        .and_then(|path| fs::read(path).ok())
+    {
+        Some(bytes) => bytes,
+        None => {
+            let bytes = download_tile(agent, url_template, id)?;
+            if let Some(path) = &cached {
+                let written = path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(path, &bytes));
+                if let Err(e) = written {
+                    warn!("Failed to cache map tile: {}", e);
+                }
+            }
+            bytes
+        },
+    };
+
+    let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+    Some(ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_flat_samples().as_slice(),
+    ))
+}
+
+fn download_tile(
+    agent: &ureq::Agent,
+    url_template: &str,
+    id: TileId,
+) -> Option<Vec<u8>> {
+    let url = url_template
+        .replace("{z}", &id.z.to_string())
+        .replace("{x}", &id.x.to_string())
+        .replace("{y}", &id.y.to_string());
+    debug!("Downloading map tile {}", url);
+
+    let response = match agent.get(&url).call() {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Failed to download map tile {}: {}", url, e);
+            return None;
+        },
+    };
+
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_TILE_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    Some(bytes)
+}
@@ -0,0 +1,52 @@
+//! Distance and angle measurements in image pixels.
+
+/// A line between two points in image pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub start: (f32, f32),
+    pub end:   (f32, f32),
+}
+
+impl Measurement {
+    pub fn length(&self) -> f32 {
+        (self.end.0 - self.start.0).hypot(self.end.1 - self.start.1)
+    }
+
+    /// Angle against the horizontal in degrees, counter-clockwise positive
+    /// as on paper (image y grows downwards).
+    pub fn angle(&self) -> f32 {
+        (self.start.1 - self.end.1)
+            .atan2(self.end.0 - self.start.0)
+            .to_degrees()
+    }
+
+    /// Length in millimeters for a calibration of `pixels_per_mm`.
+    pub fn millimeters(&self, pixels_per_mm: f32) -> Option<f32> {
+        (pixels_per_mm > 0.0).then(|| self.length() / pixels_per_mm)
+    }
+
+    /// Calibration that makes this line `millimeters` long.
+    pub fn calibrate(&self, millimeters: f32) -> Option<f32> {
+        (millimeters > 0.0 && self.length() > 0.0)
+            .then(|| self.length() / millimeters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_angle_and_calibration() {
+        let line = Measurement {
+            start: (10.0, 50.0), end: (40.0, 10.0)
+        };
+        assert_eq!(line.length(), 50.0);
+        assert!((line.angle() - 53.13).abs() < 0.01);
+
+        let pixels_per_mm = line.calibrate(25.0).unwrap();
+        assert_eq!(pixels_per_mm, 2.0);
+        assert_eq!(line.millimeters(pixels_per_mm), Some(25.0));
+        assert_eq!(line.calibrate(0.0), None);
+    }
+}
@@ -0,0 +1,174 @@
+use lru::LruCache;
+use std::{
+    fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use super::{
+    read_capture_date,
+    read_exif,
+    xmp::{self, XmpFields},
+    ExifSummary,
+};
+
+/// Files whose EXIF summary is remembered
+const CAPACITY: usize = 256;
+
+/// Files whose capture date or XMP fields are remembered, enough for a
+/// few large folders
+const SHARED_CAPACITY: usize = 65536;
+
+/// Values read from files along with the modification times they were read
+/// at, shared by every listing so a folder sorted or filtered again reads
+/// no file twice
+type Shared<S, T> = Mutex<Option<LruCache<PathBuf, (S, T)>>>;
+
+static CAPTURE_DATES: Shared<Option<SystemTime>, Option<String>> =
+    Mutex::new(None);
+/// Stamped with the times of the image and of its sidecar
+static XMP_FIELDS: Shared<(Option<SystemTime>, Option<SystemTime>), XmpFields> =
+    Mutex::new(None);
+
+struct Entry {
+    /// Modification time when parsed, to notice edits
+    modified: Option<SystemTime>,
+    summary:  Option<ExifSummary>,
+}
+
+/// EXIF summaries keyed by path, parsed the first time a file is asked for
+pub struct MetadataCache {
+    entries: LruCache<PathBuf, Entry>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The value of `path` in `cache` if it was read at `stamp`, or else what
+/// `read` returns, which is remembered. The lock is not held while reading,
+/// so other threads keep going.
+fn shared<S: PartialEq, T: Clone>(
+    cache: &Shared<S, T>,
+    path: &Path,
+    stamp: S,
+    read: impl FnOnce(&Path) -> T,
+) -> T {
+    let lock = || cache.lock().unwrap_or_else(|e| e.into_inner());
+    let cached = lock()
+        .as_mut()
+        .and_then(|entries| entries.get(path))
+        .filter(|(read_at, _)| *read_at == stamp)
+        .map(|(_, value)| value.clone());
+    if let Some(value) = cached {
+        return value;
+    }
+    let value = read(path);
+    lock()
+        .get_or_insert_with(|| {
+            LruCache::new(NonZeroUsize::new(SHARED_CAPACITY).unwrap())
+        })
+        .put(path.to_path_buf(), (stamp, value.clone()));
+    value
+}
+
+/// Like [`read_capture_date`], reading `path` only the first time and again
+/// once it changes on disk. Safe to call from several threads.
+pub fn capture_date(path: &Path) -> Option<String> {
+    shared(&CAPTURE_DATES, path, modified(path), read_capture_date)
+}
+
+/// Like [`xmp::read`], reading the image and its sidecar only the first
+/// time and again once either changes on disk. Safe to call from several
+/// threads.
+pub fn xmp_fields(path: &Path) -> XmpFields {
+    let sidecar = xmp::existing_sidecar(path).and_then(|path| modified(&path));
+    shared(&XMP_FIELDS, path, (modified(path), sidecar), xmp::read)
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(CAPACITY).unwrap())
+        }
+    }
+
+    /// The summary of `path`, or `None` if it has no EXIF data. The file is
+    /// read again once it changes on disk.
+    pub fn get(&mut self, path: &Path) -> Option<&ExifSummary> {
+        let modified = modified(path);
+        let fresh = self
+            .entries
+            .peek(path)
+            .is_some_and(|entry| entry.modified == modified);
+        if !fresh {
+            let summary =
+                read_exif(path).map(|exif| ExifSummary::from_exif(&exif));
+            self.entries.put(path.to_path_buf(), Entry {
+                modified,
+                summary,
+            });
+        }
+        self.entries.get(path)?.summary.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::summary::tests::sample_tiff;
+
+    #[test]
+    fn test_rereads_file_once_changed() {
+        let dir = std::env::temp_dir().join("ferrite-metadata-cache");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.tif");
+        fs::write(&path, b"not an image").unwrap();
+
+        let mut cache = MetadataCache::new();
+        assert!(cache.get(&path).is_none());
+        assert_eq!(cache.entries.len(), 1);
+
+        fs::write(&path, sample_tiff()).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let summary = cache.get(&path).unwrap();
+        assert_eq!(summary.camera.as_deref(), Some("FUJIFILM X-T4"));
+        assert_eq!(cache.entries.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_capture_dates_follow_edits() {
+        let dir = std::env::temp_dir().join("ferrite-capture-date-cache");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.tif");
+        fs::write(&path, sample_tiff()).unwrap();
+        let date = capture_date(&path);
+        assert!(date.is_some());
+
+        // Unchanged files are not read again
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, b"not an image").unwrap();
+        let file = fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap();
+        file.set_modified(modified).unwrap();
+        assert_eq!(capture_date(&path), date);
+
+        file.set_modified(modified + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(capture_date(&path), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,56 @@
+use exif::{Exif, In, Tag, Value};
+
+use super::rational_field;
+
+/// Exposure settings a photo was taken with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposureInfo {
+    /// Shutter time in seconds
+    pub exposure_time: f64,
+    pub f_number:      Option<f64>,
+    pub iso:           Option<f64>,
+    /// Exposure compensation in EV
+    pub bias:          Option<f64>,
+}
+
+impl ExposureInfo {
+    /// Returns `None` unless the shutter time is known.
+    pub fn from_exif(exif: &Exif) -> Option<Self> {
+        let exposure_time =
+            rational_field(exif, Tag::ExposureTime).filter(|t| *t > 0.0)?;
+        let iso = match &exif
+            .get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+            .map(|f| &f.value)
+        {
+            Some(Value::Short(values)) => values.first().map(|&v| v as f64),
+            Some(Value::Long(values)) => values.first().map(|&v| v as f64),
+            _ => None,
+        };
+        let bias = match &exif
+            .get_field(Tag::ExposureBiasValue, In::PRIMARY)
+            .map(|f| &f.value)
+        {
+            Some(Value::SRational(values)) => values
+                .first()
+                .filter(|r| r.denom != 0)
+                .map(|r| r.to_f64()),
+            _ => None,
+        };
+
+        Some(Self {
+            exposure_time,
+            f_number: rational_field(exif, Tag::FNumber).filter(|f| *f > 0.0),
+            iso: iso.filter(|iso| *iso > 0.0),
+            bias,
+        })
+    }
+
+    /// Light gathered relative to 1 s at f/1 and ISO 100. Twice the value
+    /// is one EV brighter.
+    pub fn relative_exposure(&self) -> f64 {
+        let aperture = self.f_number.unwrap_or(1.0);
+        self.exposure_time * self.iso.unwrap_or(100.0)
+            / 100.0
+            / (aperture * aperture)
+    }
+}
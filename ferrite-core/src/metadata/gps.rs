@@ -0,0 +1,51 @@
+use exif::{Exif, In, Tag, Value};
+
+use super::ascii_field;
+
+/// A WGS84 position in decimal degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsCoordinate {
+    pub latitude:  f64,
+    pub longitude: f64,
+}
+
+impl GpsCoordinate {
+    pub fn from_exif(exif: &Exif) -> Option<Self> {
+        let latitude = degrees(exif, Tag::GPSLatitude)?;
+        let longitude = degrees(exif, Tag::GPSLongitude)?;
+
+        let latitude = match ascii_field(exif, Tag::GPSLatitudeRef).as_deref() {
+            Some("S") => -latitude,
+            _ => latitude,
+        };
+        let longitude = match ascii_field(exif, Tag::GPSLongitudeRef).as_deref()
+        {
+            Some("W") => -longitude,
+            _ => longitude,
+        };
+
+        let coordinate = Self {
+            latitude,
+            longitude,
+        };
+        coordinate.is_valid().then_some(coordinate)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.latitude.is_finite()
+            && self.longitude.is_finite()
+            && (-90.0..=90.0).contains(&self.latitude)
+            && (-180.0..=180.0).contains(&self.longitude)
+    }
+}
+
+/// Converts a degrees/minutes/seconds rational triple to decimal degrees.
+fn degrees(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(parts) if !parts.is_empty() => {
+            let part = |i: usize| parts.get(i).map_or(0.0, |r| r.to_f64());
+            Some(part(0) + part(1) / 60.0 + part(2) / 3600.0)
+        },
+        _ => None,
+    }
+}
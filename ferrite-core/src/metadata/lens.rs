@@ -0,0 +1,33 @@
+use exif::{Exif, Tag};
+
+use super::{ascii_field, rational_field};
+
+/// Camera and lens a photo was taken with, as needed for lens correction
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensInfo {
+    pub camera_make:  Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_make:    Option<String>,
+    pub lens_model:   String,
+    /// Focal length in millimeters
+    pub focal_length: f32,
+    pub aperture:     Option<f32>,
+}
+
+impl LensInfo {
+    /// Returns `None` unless both the lens model and focal length are known.
+    pub fn from_exif(exif: &Exif) -> Option<Self> {
+        let lens_model = ascii_field(exif, Tag::LensModel)
+            .filter(|model| !model.is_empty())?;
+        let focal_length = rational_field(exif, Tag::FocalLength)? as f32;
+
+        Some(Self {
+            camera_make: ascii_field(exif, Tag::Make),
+            camera_model: ascii_field(exif, Tag::Model),
+            lens_make: ascii_field(exif, Tag::LensMake),
+            lens_model,
+            focal_length,
+            aperture: rational_field(exif, Tag::FNumber).map(|f| f as f32),
+        })
+    }
+}
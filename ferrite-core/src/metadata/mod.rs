@@ -0,0 +1,78 @@
+//! Reading of embedded image metadata (EXIF).
+
+use exif::{Exif, In, Reader, Tag};
+use std::{fs::File, io::BufReader, path::Path};
+use tracing::debug;
+
+mod cache;
+mod exposure;
+mod gps;
+mod lens;
+mod summary;
+pub mod xmp;
+
+pub use cache::{capture_date, xmp_fields, MetadataCache};
+pub use exposure::ExposureInfo;
+pub use gps::GpsCoordinate;
+pub use lens::LensInfo;
+pub use summary::ExifSummary;
+
+/// Reads the EXIF block of a file, returning `None` if the file has none or
+/// cannot be parsed.
+pub fn read_exif(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    match Reader::new().read_from_container(&mut reader) {
+        Ok(exif) => Some(exif),
+        Err(e) => {
+            debug!("No EXIF data in {}: {}", path.display(), e);
+            None
+        },
+    }
+}
+
+/// Reads the capture location of a file from its EXIF GPS tags.
+pub fn read_gps(path: &Path) -> Option<GpsCoordinate> {
+    let exif = read_exif(path)?;
+    GpsCoordinate::from_exif(&exif)
+}
+
+/// Reads the camera and lens of a file for lens correction.
+pub fn read_lens_info(path: &Path) -> Option<LensInfo> {
+    let exif = read_exif(path)?;
+    LensInfo::from_exif(&exif)
+}
+
+/// Reads the shutter time, aperture and ISO of a file.
+pub fn read_exposure(path: &Path) -> Option<ExposureInfo> {
+    let exif = read_exif(path)?;
+    ExposureInfo::from_exif(&exif)
+}
+
+/// Reads when a file was taken as `YYYY:MM:DD HH:MM:SS`, which sorts in
+/// time order.
+pub fn read_capture_date(path: &Path) -> Option<String> {
+    let exif = read_exif(path)?;
+    ascii_field(&exif, Tag::DateTimeOriginal)
+        .or_else(|| ascii_field(&exif, Tag::DateTime))
+        .filter(|date| !date.is_empty())
+}
+
+pub(crate) fn rational_field(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        exif::Value::Rational(values) => values
+            .first()
+            .filter(|r| r.denom != 0)
+            .map(|r| r.to_f64()),
+        _ => None,
+    }
+}
+
+pub(crate) fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim().to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,154 @@
+use exif::{Exif, Tag};
+
+use super::{ascii_field, rational_field, ExposureInfo, GpsCoordinate};
+
+/// The EXIF fields shown in the info panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExifSummary {
+    /// Make and model, without the make repeated
+    pub camera:       Option<String>,
+    pub lens:         Option<String>,
+    pub exposure:     Option<ExposureInfo>,
+    /// Focal length in millimeters
+    pub focal_length: Option<f64>,
+    pub gps:          Option<GpsCoordinate>,
+    /// Capture date as `YYYY-MM-DD HH:MM:SS`
+    pub taken:        Option<String>,
+}
+
+impl ExifSummary {
+    pub fn from_exif(exif: &Exif) -> Self {
+        let make = ascii_field(exif, Tag::Make).filter(|s| !s.is_empty());
+        let model = ascii_field(exif, Tag::Model).filter(|s| !s.is_empty());
+        let camera = match (make, model) {
+            (Some(make), Some(model))
+                if model
+                    .to_lowercase()
+                    .starts_with(&make.to_lowercase()) =>
+            {
+                Some(model)
+            },
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        };
+
+        Self {
+            camera,
+            lens: ascii_field(exif, Tag::LensModel).filter(|s| !s.is_empty()),
+            exposure: ExposureInfo::from_exif(exif),
+            focal_length: rational_field(exif, Tag::FocalLength)
+                .filter(|f| *f > 0.0),
+            gps: GpsCoordinate::from_exif(exif),
+            taken: ascii_field(exif, Tag::DateTimeOriginal)
+                .or_else(|| ascii_field(exif, Tag::DateTime))
+                .map(|date| format_date(&date)),
+        }
+    }
+
+    /// Labelled values of the known fields, in display order
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let mut rows = Vec::new();
+        if let Some(camera) = &self.camera {
+            rows.push(("Camera", camera.clone()));
+        }
+        if let Some(lens) = &self.lens {
+            rows.push(("Lens", lens.clone()));
+        }
+        if let Some(exposure) = &self.exposure {
+            rows.push(("Shutter", format_shutter(exposure.exposure_time)));
+            if let Some(f_number) = exposure.f_number {
+                rows.push(("Aperture", format!("f/{:.1}", f_number)));
+            }
+            if let Some(iso) = exposure.iso {
+                rows.push(("ISO", format!("{}", iso.round())));
+            }
+            if let Some(bias) = exposure.bias.filter(|b| *b != 0.0) {
+                rows.push(("Compensation", format!("{:+.1} EV", bias)));
+            }
+        }
+        if let Some(focal_length) = self.focal_length {
+            rows.push(("Focal length", format!("{} mm", focal_length)));
+        }
+        if let Some(gps) = &self.gps {
+            rows.push((
+                "Location",
+                format!("{:.6}, {:.6}", gps.latitude, gps.longitude),
+            ));
+        }
+        if let Some(taken) = &self.taken {
+            rows.push(("Taken", taken.clone()));
+        }
+        rows
+    }
+}
+
+/// Shutter times under a second as a fraction, as cameras show them
+fn format_shutter(seconds: f64) -> String {
+    if seconds < 1.0 {
+        format!("1/{} s", (1.0 / seconds).round())
+    } else {
+        format!("{} s", seconds)
+    }
+}
+
+/// EXIF writes dates as `YYYY:MM:DD HH:MM:SS`
+fn format_date(date: &str) -> String {
+    match date.split_once(' ') {
+        Some((day, time)) => format!("{} {}", day.replace(':', "-"), time),
+        None => date.replace(':', "-"),
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, In, Rational, Reader, Value};
+    use std::io::Cursor;
+
+    /// A TIFF holding only the EXIF fields of a typical camera photo
+    pub fn sample_tiff() -> Vec<u8> {
+        let field = |tag, value| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        };
+        let ascii = |s: &str| Value::Ascii(vec![s.as_bytes().to_vec()]);
+        let rational = |num, denom| {
+            Value::Rational(vec![Rational {
+                num,
+                denom,
+            }])
+        };
+        let fields = [
+            field(Tag::Make, ascii("FUJIFILM")),
+            field(Tag::Model, ascii("FUJIFILM X-T4")),
+            field(Tag::ExposureTime, rational(1, 250)),
+            field(Tag::FNumber, rational(28, 10)),
+            field(Tag::PhotographicSensitivity, Value::Short(vec![400])),
+            field(Tag::FocalLength, rational(35, 1)),
+            field(Tag::DateTimeOriginal, ascii("2024:05:01 18:30:12")),
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        tiff.into_inner()
+    }
+
+    #[test]
+    fn test_rows_are_formatted_for_display() {
+        let exif = Reader::new().read_raw(sample_tiff()).unwrap();
+        let summary = ExifSummary::from_exif(&exif);
+        assert_eq!(summary.rows(), [
+            ("Camera", "FUJIFILM X-T4".to_string()),
+            ("Shutter", "1/250 s".to_string()),
+            ("Aperture", "f/2.8".to_string()),
+            ("ISO", "400".to_string()),
+            ("Focal length", "35 mm".to_string()),
+            ("Taken", "2024-05-01 18:30:12".to_string()),
+        ]);
+        assert_eq!(format_shutter(2.5), "2.5 s");
+    }
+}
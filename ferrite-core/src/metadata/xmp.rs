@@ -0,0 +1,667 @@
+//! Reading and writing of descriptive XMP metadata.
+//!
+//! Only the fields used for captioning and culling are handled: title,
+//! description, copyright and keywords, stored as the Dublin Core properties
+//! that IPTC Core maps onto, and the star rating of the XMP basic schema.
+//! Packets are written either to a sidecar next to the image or embedded into
+//! JPEG files as an APP1 segment. An existing packet is edited in place:
+//! only the properties above are replaced, so develop settings, labels and
+//! history written by other programs survive. Every write goes to a
+//! temporary file first and is renamed over the target, so a failure never
+//! leaves a half-written image behind.
+
+use ferrite_config::XmpTarget;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+    Writer,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument, warn};
+
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Largest XMP packet that fits into a single JPEG APP1 segment
+const MAX_EMBEDDED_PACKET: usize = 65533 - XMP_HEADER.len();
+/// The properties [`XmpFields`] stands for; they are replaced on write and
+/// every other one is kept
+const MANAGED: [&[u8]; 5] = [
+    b"dc:title",
+    b"dc:description",
+    b"dc:rights",
+    b"dc:subject",
+    b"xmp:Rating",
+];
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+
+/// The editable descriptive fields
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmpFields {
+    pub title:       String,
+    pub description: String,
+    pub copyright:   String,
+    /// Also serve as the image's tags
+    pub keywords:    Vec<String>,
+    /// Stars from 1 to 5, 0 when unrated
+    pub rating:      u8,
+}
+
+impl XmpFields {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty()
+            && self.description.is_empty()
+            && self.copyright.is_empty()
+            && self.keywords.is_empty()
+            && self.rating == 0
+    }
+
+    /// Whether `tag` is among the keywords, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.keywords
+            .iter()
+            .any(|keyword| keyword.to_lowercase() == tag.to_lowercase())
+    }
+
+    /// Serializes the fields into a complete XMP packet.
+    pub fn to_packet(&self) -> String {
+        format!(
+            "<?xpacket begin=\"\u{feff}\" \
+             id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n<x:xmpmeta \
+             xmlns:x=\"adobe:ns:meta/\">\n <rdf:RDF \
+             xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\" xmlns:dc=\"{}\" \
+             xmlns:xmp=\"{}\">\n{}  </rdf:Description>\n </rdf:RDF>\n\
+             </x:xmpmeta>\n<?xpacket end=\"w\"?>",
+            DC_NS,
+            XMP_NS,
+            self.properties()
+        )
+    }
+
+    /// Writes the fields into `packet`, replacing the properties they stand
+    /// for and keeping all others. Falls back to a new packet when `packet`
+    /// cannot be parsed or has no description to write into.
+    pub fn merge_into(&self, packet: &str) -> String {
+        match merge(packet, &self.properties()) {
+            Ok(Some(merged)) => merged,
+            Ok(None) => self.to_packet(),
+            Err(e) => {
+                warn!("Replacing an XMP packet that cannot be parsed: {}", e);
+                self.to_packet()
+            },
+        }
+    }
+
+    /// The elements of the non-empty fields, for inside a description.
+    fn properties(&self) -> String {
+        let mut body = String::new();
+        let alt = |name: &str, value: &str| {
+            format!(
+                "   <dc:{name}>\n    <rdf:Alt>\n     <rdf:li \
+                 xml:lang=\"x-default\">{}</rdf:li>\n    </rdf:Alt>\n   \
+                 </dc:{name}>\n",
+                escape(value)
+            )
+        };
+
+        if !self.title.is_empty() {
+            body.push_str(&alt("title", &self.title));
+        }
+        if !self.description.is_empty() {
+            body.push_str(&alt("description", &self.description));
+        }
+        if !self.copyright.is_empty() {
+            body.push_str(&alt("rights", &self.copyright));
+        }
+        if !self.keywords.is_empty() {
+            body.push_str("   <dc:subject>\n    <rdf:Bag>\n");
+            for keyword in &self.keywords {
+                body.push_str(&format!(
+                    "     <rdf:li>{}</rdf:li>\n",
+                    escape(keyword)
+                ));
+            }
+            body.push_str("    </rdf:Bag>\n   </dc:subject>\n");
+        }
+        if self.rating > 0 {
+            body.push_str(&format!(
+                "   <xmp:Rating>{}</xmp:Rating>\n",
+                self.rating
+            ));
+        }
+        body
+    }
+
+    /// Extracts the descriptive fields from an XMP packet. Unknown
+    /// properties are ignored.
+    pub fn parse(packet: &str) -> Self {
+        let first = |name: &str| {
+            element(packet, &format!("dc:{}", name))
+                .and_then(|inner| list_items(inner).into_iter().next())
+                .unwrap_or_default()
+        };
+
+        Self {
+            title:       first("title"),
+            description: first("description"),
+            copyright:   first("rights"),
+            keywords:    element(packet, "dc:subject")
+                .map(list_items)
+                .unwrap_or_default(),
+            rating:      parse_rating(packet),
+        }
+    }
+}
+
+/// Sidecar location for an image, e.g. `photo.jpg` -> `photo.jpg.xmp`, so
+/// a raw file and its JPEG of the same name keep their own metadata.
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut name = image.as_os_str().to_owned();
+    name.push(".xmp");
+    PathBuf::from(name)
+}
+
+/// The sidecar read for an image: its own, or else one named after the
+/// image without its extension, as Lightroom writes them.
+pub(crate) fn existing_sidecar(image: &Path) -> Option<PathBuf> {
+    [sidecar_path(image), image.with_extension("xmp")]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Reads descriptive metadata, preferring a sidecar over embedded XMP.
+pub fn read(image: &Path) -> XmpFields {
+    if let Some(packet) = existing_sidecar(image)
+        .and_then(|sidecar| fs::read_to_string(sidecar).ok())
+    {
+        return XmpFields::parse(&packet);
+    }
+    read_segments(image)
+        .ok()
+        .and_then(|bytes| embedded_packet(&bytes))
+        .map(|packet| XmpFields::parse(&packet))
+        .unwrap_or_default()
+}
+
+/// The start of a JPEG file as far as its XMP segment, with the segments
+/// other than APP1 skipped rather than read, so the image data is never
+/// touched. Empty for other files.
+fn read_segments(image: &Path) -> io::Result<Vec<u8>> {
+    let mut file = BufReader::new(File::open(image)?);
+    let mut bytes = vec![0; 2];
+    file.read_exact(&mut bytes)?;
+    if !is_jpeg(&bytes) {
+        return Ok(Vec::new());
+    }
+    let mut header = [0; 4];
+    while file.read_exact(&mut header).is_ok() {
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        // Start of scan: entropy coded data follows
+        if header[0] != 0xFF || matches!(header[1], 0xDA | 0xD9) || length < 2 {
+            break;
+        }
+        if header[1] != 0xE1 {
+            file.seek_relative(length as i64 - 2)?;
+            continue;
+        }
+        let start = bytes.len();
+        bytes.extend_from_slice(&header);
+        bytes.resize(start + 2 + length, 0);
+        if file.read_exact(&mut bytes[start + 4..]).is_err() {
+            bytes.truncate(start);
+            break;
+        }
+        if bytes[start + 4..].starts_with(XMP_HEADER) {
+            break;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Writes descriptive metadata into the packet [`read`] takes it from, so
+/// an edit always shows: an existing sidecar, else the embedded packet of a
+/// JPEG file when `target` asks for it, else a new sidecar. Other
+/// properties of the packet are kept. Returns the file that was written.
+#[instrument(skip(fields))]
+pub fn write(
+    image: &Path,
+    fields: &XmpFields,
+    target: XmpTarget,
+) -> io::Result<PathBuf> {
+    let sidecar = existing_sidecar(image);
+
+    if sidecar.is_none() && target == XmpTarget::Embedded {
+        let bytes = fs::read(image)?;
+        if is_jpeg(&bytes) {
+            let packet = match embedded_packet(&bytes) {
+                Some(existing) => fields.merge_into(&existing),
+                None => fields.to_packet(),
+            };
+            let updated = embed_packet(&bytes, &packet)?;
+            write_atomically(image, &updated)?;
+            info!("Embedded XMP metadata into {}", image.display());
+            return Ok(image.to_path_buf());
+        }
+    }
+
+    // A new sidecar starts from the embedded packet, which it hides
+    let existing = match &sidecar {
+        Some(sidecar) => Some(fs::read_to_string(sidecar)?),
+        None => read_segments(image)
+            .ok()
+            .and_then(|bytes| embedded_packet(&bytes)),
+    };
+    let packet = match existing {
+        Some(existing) => fields.merge_into(&existing),
+        None => fields.to_packet(),
+    };
+    let sidecar = sidecar.unwrap_or_else(|| sidecar_path(image));
+    write_atomically(&sidecar, packet.as_bytes())?;
+    info!("Wrote XMP sidecar {}", sidecar.display());
+    Ok(sidecar)
+}
+
+/// Replaces `path` with `bytes` through a temporary file. A symlink is
+/// written through to its target, and the file keeps its permissions.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "no file name")
+    })?;
+    let temp = path.with_file_name(format!(
+        ".{}.ferrite-tmp",
+        file_name.to_string_lossy()
+    ));
+    let permissions = fs::metadata(&path).map(|m| m.permissions()).ok();
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&temp, permissions)?;
+        }
+        fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copies `packet` with the managed properties taken out of every
+/// description, as elements or attributes, and `properties` written into
+/// the first one. `None` when there is no description.
+fn merge(
+    packet: &str,
+    properties: &str,
+) -> Result<Option<String>, quick_xml::Error> {
+    let mut reader = Reader::from_str(packet);
+    let mut writer = Writer::new(Vec::with_capacity(packet.len()));
+    let mut written = false;
+
+    loop {
+        let event = reader.read_event()?;
+        match event {
+            Event::Start(e) if MANAGED.contains(&e.name().as_ref()) => {
+                let end = e.to_end().into_owned();
+                reader.read_to_end(end.name())?;
+            },
+            Event::Empty(e) if MANAGED.contains(&e.name().as_ref()) => {},
+            Event::Start(e) if e.name().as_ref() == b"rdf:Description" => {
+                let start = description(&e, packet, !written);
+                writer.write_event(Event::Start(start))?;
+                if !written {
+                    writer
+                        .get_mut()
+                        .extend_from_slice(properties.as_bytes());
+                    written = true;
+                }
+            },
+            Event::Empty(e) if e.name().as_ref() == b"rdf:Description" => {
+                let start = description(&e, packet, !written);
+                if written {
+                    writer.write_event(Event::Empty(start))?;
+                } else {
+                    let end = start.to_end().into_owned();
+                    writer.write_event(Event::Start(start))?;
+                    writer.get_mut().push(b'\n');
+                    writer
+                        .get_mut()
+                        .extend_from_slice(properties.as_bytes());
+                    writer.write_event(Event::End(end))?;
+                    written = true;
+                }
+            },
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+    }
+    Ok(written
+        .then(|| String::from_utf8_lossy(&writer.into_inner()).into_owned()))
+}
+
+/// A copy of a description's start tag without the managed properties,
+/// declaring their namespaces on the `first` one if the packet does not.
+fn description<'a>(
+    element: &BytesStart,
+    packet: &str,
+    first: bool,
+) -> BytesStart<'a> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+    let mut start = BytesStart::new(name);
+    for attribute in element.attributes().filter_map(Result::ok) {
+        if !MANAGED.contains(&attribute.key.as_ref()) {
+            start.push_attribute(attribute);
+        }
+    }
+    if first {
+        for (prefix, namespace) in [("dc", DC_NS), ("xmp", XMP_NS)] {
+            let key = format!("xmlns:{}", prefix);
+            if !packet.contains(&format!("{}=", key)) {
+                start.push_attribute((key.as_str(), namespace));
+            }
+        }
+    }
+    start
+}
+
+fn is_jpeg(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0xFF, 0xD8])
+}
+
+/// Iterates the marker segments before the image data, yielding
+/// `(marker, segment_start, segment_end)` with the end exclusive.
+fn segments(bytes: &[u8]) -> Vec<(u8, usize, usize)> {
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        // Start of scan: entropy coded data follows
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length =
+            u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > bytes.len() {
+            break;
+        }
+        segments.push((marker, pos, end));
+        pos = end;
+    }
+    segments
+}
+
+fn embedded_packet(bytes: &[u8]) -> Option<String> {
+    segments(bytes)
+        .into_iter()
+        .find_map(|(marker, start, end)| {
+            let payload = &bytes[start + 4..end];
+            (marker == 0xE1 && payload.starts_with(XMP_HEADER)).then(|| {
+                String::from_utf8_lossy(&payload[XMP_HEADER.len()..])
+                    .into_owned()
+            })
+        })
+}
+
+/// Returns a copy of the JPEG with any existing XMP segment replaced by
+/// `packet`. The new segment is placed after the JFIF/EXIF headers so other
+/// readers still find those first.
+fn embed_packet(bytes: &[u8], packet: &str) -> io::Result<Vec<u8>> {
+    if packet.len() > MAX_EMBEDDED_PACKET {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "XMP packet too large to embed",
+        ));
+    }
+
+    let segments = segments(bytes);
+    let is_xmp = |&(marker, start, end): &(u8, usize, usize)| {
+        marker == 0xE1 && bytes[start + 4..end].starts_with(XMP_HEADER)
+    };
+    let insert_at = segments
+        .iter()
+        .take_while(|s| matches!(s.0, 0xE0 | 0xE1) && !is_xmp(s))
+        .last()
+        .map_or(2, |s| s.2);
+
+    let mut segment = vec![0xFF, 0xE1];
+    let length = (2 + XMP_HEADER.len() + packet.len()) as u16;
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(XMP_HEADER);
+    segment.extend_from_slice(packet.as_bytes());
+
+    // Copy everything except old XMP segments, inserting the new one
+    let mut output = Vec::with_capacity(bytes.len() + segment.len());
+    output.extend_from_slice(&bytes[..insert_at]);
+    output.extend_from_slice(&segment);
+    let mut pos = insert_at;
+    for old in segments
+        .iter()
+        .filter(|s| s.1 >= insert_at && is_xmp(s))
+    {
+        output.extend_from_slice(&bytes[pos..old.1]);
+        pos = old.2;
+    }
+    output.extend_from_slice(&bytes[pos..]);
+    Ok(output)
+}
+
+/// The rating as an element or, as most editors write it, an attribute of
+/// the description. Rejected images (-1) count as unrated.
+fn parse_rating(packet: &str) -> u8 {
+    let attribute = || {
+        let start = packet.find("xmp:Rating=")? + "xmp:Rating=".len();
+        let quote = packet[start..].chars().next()?;
+        let value = &packet[start + quote.len_utf8()..];
+        Some(&value[..value.find(quote)?])
+    };
+    element(packet, "xmp:Rating")
+        .or_else(attribute)
+        .and_then(|value| value.trim().parse::<f32>().ok())
+        .map_or(0, |rating| rating.round().clamp(0.0, 5.0) as u8)
+}
+
+/// Returns the inner text of the first `<name ...>...</name>` element.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = xml.find(&format!("<{}", name))?;
+    let after_open = open + xml[open..].find('>')? + 1;
+    if xml[..after_open].ends_with("/>") {
+        return None;
+    }
+    let close = after_open + xml[after_open..].find(&format!("</{}>", name))?;
+    Some(&xml[after_open..close])
+}
+
+/// Collects the text of all `rdf:li` items in a container.
+fn list_items(container: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut rest = container;
+    while let Some(inner) = element(rest, "rdf:li") {
+        let text = unescape(inner.trim());
+        if !text.is_empty() {
+            items.push(text);
+        }
+        let consumed = rest
+            .find("</rdf:li>")
+            .map_or(rest.len(), |i| i + 9);
+        rest = &rest[consumed..];
+    }
+    items
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> XmpFields {
+        XmpFields {
+            title:       "Harbour at dusk".into(),
+            description: "Boats & lights <dock 3>".into(),
+            copyright:   "© 2024 Someone".into(),
+            keywords:    vec!["harbour".into(), "night".into()],
+            rating:      4,
+        }
+    }
+
+    /// A structurally valid JPEG header: SOI, APP0, SOS, EOI
+    fn minimal_jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend_from_slice(&[
+            0xFF, 0xE0, 0x00, 0x06, b'J', b'F', b'I', b'F',
+        ]);
+        bytes.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34]);
+        bytes.extend_from_slice(&[0xFF, 0xD9]);
+        bytes
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let fields = sample();
+        assert_eq!(XmpFields::parse(&fields.to_packet()), fields);
+    }
+
+    #[test]
+    fn test_rating_attribute_is_read() {
+        let packet = "<rdf:Description rdf:about=\"\" xmp:Rating='3' \
+                      xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"/>";
+        assert_eq!(XmpFields::parse(packet).rating, 3);
+        assert_eq!(XmpFields::parse("<x:xmpmeta/>").rating, 0);
+        // Not a quote, and more than one byte long
+        assert_eq!(XmpFields::parse("<x xmp:Rating=é4é/>").rating, 4);
+        assert_eq!(XmpFields::parse("<x xmp:Rating=é").rating, 0);
+    }
+
+    #[test]
+    fn test_writing_keeps_other_properties() {
+        let dir = std::env::temp_dir().join("ferrite-xmp-merge");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("IMG_1.CR2");
+        fs::write(&image, b"raw").unwrap();
+        // As darktable and Lightroom leave them
+        fs::write(
+            sidecar_path(&image),
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF \
+             xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+             <rdf:Description rdf:about=\"\" \
+             xmlns:crs=\"http://ns.adobe.com/camera-raw-settings/1.0/\" \
+             xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmp:Rating=\"2\" \
+             crs:Exposure2012=\"+0.50\"><crs:ToneCurve><rdf:Seq>\
+             <rdf:li>0, 0</rdf:li></rdf:Seq></crs:ToneCurve>\
+             <dc:title><rdf:Alt><rdf:li>Old</rdf:li></rdf:Alt></dc:title>\
+             </rdf:Description></rdf:RDF></x:xmpmeta>",
+        )
+        .unwrap();
+
+        let written = write(&image, &sample(), XmpTarget::Embedded).unwrap();
+        assert_eq!(written, sidecar_path(&image));
+        let packet = fs::read_to_string(&written).unwrap();
+        assert!(packet.contains("crs:Exposure2012=\"+0.50\""));
+        assert!(packet.contains("<rdf:li>0, 0</rdf:li>"));
+        assert!(!packet.contains("Old"));
+        assert_eq!(read(&image), sample());
+        // The JPEG of the same name has a sidecar of its own
+        assert_eq!(read(&dir.join("IMG_1.JPG")), XmpFields::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_written_through_symlinks_keeping_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join("ferrite-xmp-symlink");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("real.xmp");
+        fs::write(&real, sample().to_packet()).unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o640)).unwrap();
+        let image = dir.join("photo.png");
+        symlink(&real, sidecar_path(&image)).unwrap();
+
+        let mut changed = sample();
+        changed.rating = 1;
+        write(&image, &changed, XmpTarget::Sidecar).unwrap();
+        assert!(fs::symlink_metadata(sidecar_path(&image))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            XmpFields::parse(&fs::read_to_string(&real).unwrap()),
+            changed
+        );
+        assert_eq!(
+            fs::metadata(&real).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_embed_replaces_existing_packet() {
+        let jpeg = minimal_jpeg();
+        let first = embed_packet(&jpeg, &sample().to_packet()).unwrap();
+
+        let mut changed = sample();
+        changed.title = "Renamed".into();
+        let second = embed_packet(&first, &changed.to_packet()).unwrap();
+
+        let packet = embedded_packet(&second).unwrap();
+        assert_eq!(XmpFields::parse(&packet), changed);
+        assert_eq!(
+            segments(&second)
+                .iter()
+                .filter(|s| s.0 == 0xE1)
+                .count(),
+            1
+        );
+        // Image data after the headers is preserved untouched
+        assert!(
+            second.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9])
+        );
+    }
+
+    #[test]
+    fn test_embedded_packet_is_read_from_the_headers_alone() {
+        let dir = std::env::temp_dir().join("ferrite-xmp-headers");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.jpg");
+        let mut jpeg = minimal_jpeg();
+        // An EXIF segment comes first, as in camera files
+        jpeg.splice(2..2, [
+            0xFF, 0xE1, 0x00, 0x08, b'E', b'x', b'i', b'f', 0, 0,
+        ]);
+        let embedded = embed_packet(&jpeg, &sample().to_packet()).unwrap();
+        fs::write(&path, &embedded).unwrap();
+        assert_eq!(read(&path), sample());
+
+        // Cut off inside the packet
+        fs::write(&path, &embedded[..embedded.len() - 100]).unwrap();
+        assert_eq!(read(&path), XmpFields::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Pre-halved copies of the displayed image for drawing it zoomed out.
+//!
+//! The GPU samples a texture at a handful of texels per screen pixel, so an
+//! image drawn at a tenth of its size skips most of its pixels and fine
+//! detail turns into shimmering noise. Each level of the pyramid is half the
+//! size of the one before, averaged from it, and the view draws from the
+//! smallest level that still has at least one texel per screen pixel.
+
+use image::DynamicImage;
+
+use crate::linear;
+
+/// Levels stop once the image fits in this many pixels on its longer side
+const MIN_SIDE: u32 = 128;
+
+/// Halves `image` repeatedly until it is small, averaging in linear light
+/// when `linear`. The image itself is not included: the first level is half
+/// its size.
+pub fn pyramid(image: &DynamicImage, linear: bool) -> Vec<DynamicImage> {
+    let mut levels: Vec<DynamicImage> = Vec::new();
+    loop {
+        let previous = levels.last().unwrap_or(image);
+        if previous.width().max(previous.height()) <= MIN_SIDE {
+            return levels;
+        }
+        let half = linear::thumbnail(
+            previous,
+            previous.width().div_ceil(2),
+            previous.height().div_ceil(2),
+            linear,
+        );
+        levels.push(half);
+    }
+}
+
+/// Level to draw at `scale` screen pixels per image pixel, out of `count`
+/// levels below the full image, which is level 0.
+pub fn level_for(scale: f64, count: usize) -> usize {
+    if scale <= 0.0 {
+        return 0;
+    }
+    let level = (-scale.log2()).floor().max(0.0) as usize;
+    level.min(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_pyramid_halves_down_to_a_small_image() {
+        // Fine black and white stripes average to a flat gray
+        let stripes = RgbaImage::from_fn(1001, 300, |x, _| {
+            Rgba(if x % 2 == 0 { [0, 0, 0, 255] } else { [255; 4] })
+        });
+        let levels = pyramid(&DynamicImage::ImageRgba8(stripes), false);
+        let sizes: Vec<_> = levels
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        assert_eq!(sizes, [(501, 150), (251, 75), (126, 38)]);
+
+        let middle = levels[1].to_rgba8();
+        for x in 10..240 {
+            let value = middle.get_pixel(x, 30)[0];
+            assert!((100..=155).contains(&value), "{} at {}", value, x);
+        }
+
+        let small = DynamicImage::ImageRgba8(RgbaImage::new(128, 40));
+        assert!(pyramid(&small, true).is_empty());
+    }
+
+    #[test]
+    fn test_level_keeps_a_texel_per_screen_pixel() {
+        assert_eq!(level_for(4.0, 3), 0);
+        assert_eq!(level_for(1.0, 3), 0);
+        assert_eq!(level_for(0.6, 3), 0);
+        assert_eq!(level_for(0.5, 3), 1);
+        assert_eq!(level_for(0.3, 3), 1);
+        assert_eq!(level_for(0.25, 3), 2);
+        // Past the smallest level
+        assert_eq!(level_for(0.01, 3), 3);
+        assert_eq!(level_for(0.01, 0), 0);
+        assert_eq!(level_for(0.0, 3), 0);
+    }
+}
@@ -1,57 +1,75 @@
-use egui::Vec2;
+use ferrite_config::{Action, NavigationConfig, SortOrder};
+use rayon::prelude::*;
 use std::{
+    cmp::Ordering,
+    collections::HashSet,
     fs,
+    io,
+    iter::Peekable,
     path::{Path, PathBuf},
+    str::Chars,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::SystemTime,
 };
 use tracing::{info, warn};
 
-use crate::image::SupportedFormats;
+use crate::{
+    archive::{self, Archive},
+    image::SupportedFormats,
+    view_filter::ViewFilter,
+};
 
 pub struct NavigationManager {
+    /// Folder the images were listed from; `None` for a playlist
+    directory:        Option<PathBuf>,
     directory_images: Vec<PathBuf>,
     current_index:    usize,
+    config:           NavigationConfig,
+    /// What images listed from a folder have to pass
+    filter:           ViewFilter,
+    /// The listing in an order that reads the files, sorted on a worker
+    /// thread; see [`Self::poll`]
+    arranging:        Option<Receiver<Vec<PathBuf>>>,
 }
 
 impl NavigationManager {
-    pub fn new() -> Self {
+    pub fn new(config: NavigationConfig) -> Self {
         Self {
-            directory_images: Vec::new(), current_index: 0
+            directory: None,
+            directory_images: Vec::new(),
+            current_index: 0,
+            config,
+            filter: ViewFilter::default(),
+            arranging: None,
         }
     }
 
     pub fn load_current_directory(&mut self, image_path: &Path) -> Option<()> {
-        // Get absolute path
-        let absolute_path = fs::canonicalize(image_path).ok()?;
+        // The folder holding the path as given, so opening a symlink
+        // browses the folder of the link rather than that of its target
+        let absolute_path = std::path::absolute(image_path).ok()?;
         let parent_dir = absolute_path.parent()?;
+        // Images in archives have no file of their own to resolve
+        let target = fs::canonicalize(&absolute_path).ok();
 
         info!("Loading images from directory: {}", parent_dir.display());
 
-        // Read directory entries
-        let entries = fs::read_dir(parent_dir).ok()?;
-
-        // Collect valid image paths
-        self.directory_images = entries
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                if path.is_file() {
-                    // Use our new SupportedFormats check
-                    if SupportedFormats::is_supported(path.extension()) {
-                        return Some(path);
-                    }
-                }
-                None
-            })
-            .collect();
-
-        // Sort paths for consistent ordering
-        self.directory_images.sort();
+        self.directory_images = self
+            .list(parent_dir, Some(&absolute_path), Some(&absolute_path))
+            .ok()?;
 
         // Find current image index
         self.current_index = self
             .directory_images
             .iter()
             .position(|p| p == &absolute_path)
+            .or_else(|| {
+                let target = target.as_ref()?;
+                self.directory_images.iter().position(|p| {
+                    fs::canonicalize(p).is_ok_and(|p| p == *target)
+                })
+            })
             .unwrap_or(0);
 
         info!(
@@ -59,10 +77,261 @@ impl NavigationManager {
             self.directory_images.len(),
             self.current_index
         );
+        self.directory = Some(parent_dir.to_path_buf());
 
         Some(())
     }
 
+    /// Lists the directory again after files in it were renamed or moved
+    /// away. The current image keeps its place in the list; if it is gone,
+    /// the image that took its position becomes current.
+    pub fn refresh(&mut self) {
+        self.relist(true);
+    }
+
+    /// Lists the directory again; the current image is left out when it
+    /// does not pass the filter unless `keep_current` is set.
+    fn relist(&mut self, keep_current: bool) {
+        let Some(directory) = &self.directory else {
+            return;
+        };
+        let directory = directory.clone();
+        let current = self.current().map(Path::to_path_buf);
+        let pass = current.as_deref().filter(|_| keep_current);
+        let Ok(images) = self.list(&directory, current.as_deref(), pass) else {
+            return;
+        };
+        self.current_index = current
+            .and_then(|current| images.iter().position(|p| *p == current))
+            .unwrap_or_else(|| {
+                self.current_index
+                    .min(images.len().saturating_sub(1))
+            });
+        self.directory_images = images;
+    }
+
+    /// Lists the images of `dir` like [`list_images`] that pass the filter,
+    /// and `pass` in any case. Reading capture dates and XMP metadata takes
+    /// long in large folders, so where the order or the filter needs them
+    /// the images are listed by name and narrowed down by the stars alone at
+    /// first; the rest is done on a worker thread.
+    fn list(
+        &mut self,
+        dir: &Path,
+        keep: Option<&Path>,
+        pass: Option<&Path>,
+    ) -> io::Result<Vec<PathBuf>> {
+        self.arranging = None;
+        let order = self.config.sort_order;
+        let by_date = order == SortOrder::ByCaptureDate;
+        if !by_date && !self.filter.reads_files() {
+            let images = list_images(dir, &self.config, keep)?;
+            return Ok(filtered(images, &self.filter, pass));
+        }
+        let quick = NavigationConfig {
+            sort_order: if by_date { SortOrder::ByName } else { order },
+            ..self.config.clone()
+        };
+        let images = list_images(dir, &quick, keep)?;
+        let listed =
+            filtered(images.clone(), &self.filter.starred_only(), pass);
+
+        let filter = self.filter.clone();
+        let pass = pass.map(Path::to_path_buf);
+        let reverse = self.config.reverse_order;
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("navigation-arrange".to_string())
+            .spawn(move || {
+                // Filtered first, so fewer capture dates are read
+                let mut images = filtered(images, &filter, pass.as_deref());
+                if by_date {
+                    sort_images(&mut images, order, reverse);
+                }
+                let _ = sender.send(images);
+            });
+        match spawned {
+            Ok(_) => self.arranging = Some(receiver),
+            Err(e) => warn!("Failed to start arranging the images: {}", e),
+        }
+        Ok(listed)
+    }
+
+    /// Takes up the list arranged on the worker thread once it is done. The
+    /// current image stays current if it passed; images removed from the
+    /// list in the meantime stay out. Returns whether the list changed.
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.arranging else {
+            return false;
+        };
+        let sorted = match receiver.try_recv() {
+            Ok(sorted) => sorted,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {
+                self.arranging = None;
+                return false;
+            },
+        };
+        self.arranging = None;
+        let listed: HashSet<&PathBuf> = self.directory_images.iter().collect();
+        let sorted: Vec<PathBuf> = sorted
+            .into_iter()
+            .filter(|path| listed.contains(path))
+            .collect();
+        let current = self.current().map(Path::to_path_buf);
+        self.current_index = current
+            .and_then(|current| sorted.iter().position(|p| *p == current))
+            .unwrap_or_else(|| {
+                self.current_index
+                    .min(sorted.len().saturating_sub(1))
+            });
+        self.directory_images = sorted;
+        true
+    }
+
+    /// Whether the list is still being arranged, see [`Self::poll`]
+    pub fn is_arranging(&self) -> bool {
+        self.arranging.is_some()
+    }
+
+    /// Follows files renamed or moved elsewhere in the app. A playlist keeps
+    /// them in place under their new paths; a folder is listed again.
+    pub fn follow_renames(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        if self.directory.is_some() {
+            self.refresh();
+            return;
+        }
+        for image in &mut self.directory_images {
+            if let Some((_, new)) = renamed.iter().find(|(old, _)| old == image)
+            {
+                *image = new.clone();
+            }
+        }
+    }
+
+    /// Steps through `images` in the given order instead of a folder,
+    /// starting at the first.
+    pub fn load_playlist(&mut self, images: Vec<PathBuf>) {
+        info!("Loaded a playlist of {} images", images.len());
+        self.directory = None;
+        self.arranging = None;
+        self.directory_images = images;
+        self.current_index = 0;
+    }
+
+    /// Lists the folder again in another order. The current image stays
+    /// current; a playlist keeps the order it was given in.
+    pub fn set_order(&mut self, order: SortOrder, reverse: bool) {
+        self.config.sort_order = order;
+        self.config.reverse_order = reverse;
+        self.refresh();
+    }
+
+    /// Lists only the images of the folder passing `filter`. The current
+    /// image is left out if it does not pass; a playlist is left as it is.
+    pub fn set_filter(&mut self, filter: ViewFilter) {
+        self.filter = filter;
+        self.relist(false);
+    }
+
+    /// Folder the images were listed from, unless they are a playlist
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// The most recently modified image in the list
+    pub fn newest(&self) -> Option<&Path> {
+        self.directory_images
+            .iter()
+            .filter_map(|path| {
+                Some((fs::metadata(path).ok()?.modified().ok()?, path))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, path)| path.as_path())
+    }
+
+    /// Whether the images are a playlist rather than a folder listing
+    pub fn is_playlist(&self) -> bool {
+        self.directory.is_none() && !self.directory_images.is_empty()
+    }
+
+    /// Moves the image at `from` to `to`, shifting those in between. The
+    /// current image stays current.
+    pub fn move_image(&mut self, from: usize, to: usize) {
+        let count = self.directory_images.len();
+        if from >= count || to >= count {
+            return;
+        }
+        let current = self.current().map(Path::to_path_buf);
+        let image = self.directory_images.remove(from);
+        self.directory_images.insert(to, image);
+        if let Some(current) = current {
+            self.select(&current);
+        }
+    }
+
+    /// Takes the image at `index` out of the list. If it was the current
+    /// one, returns the image taking its place: the next in line, or the
+    /// one before at the end.
+    pub fn remove_image(&mut self, index: usize) -> Option<PathBuf> {
+        if index >= self.directory_images.len() {
+            return None;
+        }
+        self.directory_images.remove(index);
+        let was_current = index == self.current_index;
+        if index < self.current_index
+            || self.current_index == self.directory_images.len()
+        {
+            self.current_index = self.current_index.saturating_sub(1);
+        }
+        if was_current {
+            self.current().map(Path::to_path_buf)
+        } else {
+            None
+        }
+    }
+
+    pub fn current(&self) -> Option<&Path> {
+        self.directory_images
+            .get(self.current_index)
+            .map(PathBuf::as_path)
+    }
+
+    /// All images of the current directory in navigation order
+    pub fn images(&self) -> &[PathBuf] {
+        &self.directory_images
+    }
+
+    /// Makes `path` the current image if it is part of the directory list.
+    pub fn select(&mut self, path: &Path) -> bool {
+        match self
+            .directory_images
+            .iter()
+            .position(|p| p == path)
+        {
+            Some(index) => {
+                self.current_index = index;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The images stepping forward and back would open, next first
+    pub fn neighbours(&self) -> Vec<PathBuf> {
+        let count = self.directory_images.len();
+        if count < 2 {
+            return Vec::new();
+        }
+        let next = (self.current_index + 1) % count;
+        let previous = (self.current_index + count - 1) % count;
+        let mut paths = vec![self.directory_images[next].clone()];
+        if previous != next {
+            paths.push(self.directory_images[previous].clone());
+        }
+        paths
+    }
+
     pub fn next_image(&mut self) -> Option<PathBuf> {
         if self.directory_images.is_empty() {
             return None;
@@ -84,33 +353,459 @@ impl NavigationManager {
         Some(self.directory_images[self.current_index].clone())
     }
 
+    /// Steps to the next or previous image for the navigation actions
+    /// among `actions`.
     pub fn handle_keyboard_input(
         &mut self,
-        ctx: &eframe::egui::Context,
+        actions: &[Action],
         image_manager: &mut crate::image::ImageManager,
-        zoom_handler: &mut crate::ui::zoom::ZoomHandler,
     ) {
-        let next_pressed = ctx.input(|i| {
-            i.key_pressed(eframe::egui::Key::ArrowRight)
-                || i.key_pressed(eframe::egui::Key::D)
-        });
-        let prev_pressed = ctx.input(|i| {
-            i.key_pressed(eframe::egui::Key::ArrowLeft)
-                || i.key_pressed(eframe::egui::Key::A)
-        });
-
-        if next_pressed {
+        // The pan offset is reset once the image is shown
+        if actions.contains(&Action::NextImage) {
             if let Some(next_path) = self.next_image() {
-                let _ = image_manager.load_image(next_path);
-                // Reset pan offset while maintaining fit mode
-                zoom_handler.reset_view_position();
+                image_manager.request_image(next_path);
             }
-        } else if prev_pressed {
+        } else if actions.contains(&Action::PreviousImage) {
             if let Some(prev_path) = self.previous_image() {
-                let _ = image_manager.load_image(prev_path);
-                // Reset pan offset while maintaining fit mode
-                zoom_handler.reset_view_position();
+                image_manager.request_image(prev_path);
             }
         }
     }
 }
+
+/// The images passing `filter`, and `pass`, which the user is looking at
+fn filtered(
+    images: Vec<PathBuf>,
+    filter: &ViewFilter,
+    pass: Option<&Path>,
+) -> Vec<PathBuf> {
+    if filter.is_empty() {
+        return images;
+    }
+    images
+        .into_par_iter()
+        .filter(|image| Some(image.as_path()) == pass || filter.matches(image))
+        .collect()
+}
+
+/// Lists the supported images in `dir` in the configured order, applying
+/// the symlink and hidden file options. `keep` is never dropped as a duplicate,
+/// so the file the user opened stays in the list under the name they used.
+/// An archive, or a folder in one, lists all the images of the archive.
+pub fn list_images(
+    dir: &Path,
+    config: &NavigationConfig,
+    keep: Option<&Path>,
+) -> io::Result<Vec<PathBuf>> {
+    if let Some((archive, _)) = archive::locate(dir) {
+        let mut images = Archive::open(archive)?.images();
+        images.retain(|path| config.show_hidden || !is_hidden(path));
+        sort_images(&mut images, config.sort_order, config.reverse_order);
+        return Ok(images);
+    }
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if !config.show_hidden && is_hidden(&path) {
+                return None;
+            }
+            if !config.follow_symlinks && entry.file_type().ok()?.is_symlink() {
+                return None;
+            }
+            (path.is_file() && SupportedFormats::is_supported(path.extension()))
+                .then_some(path)
+        })
+        .collect();
+
+    sort_images(&mut images, config.sort_order, config.reverse_order);
+
+    if config.dedupe_symlinks {
+        let canonical =
+            |path: &Path| fs::canonicalize(path).unwrap_or(path.to_path_buf());
+        let mut seen: HashSet<PathBuf> =
+            keep.map(canonical).into_iter().collect();
+        images.retain(|path| {
+            Some(path.as_path()) == keep || seen.insert(canonical(path))
+        });
+    }
+    Ok(images)
+}
+
+/// What images are sorted by; name breaks ties, so equal keys keep a
+/// stable order.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    None,
+    Modified(Option<SystemTime>),
+    Size(Option<u64>),
+    /// Undated images after the dated ones
+    Captured {
+        undated: bool,
+        date:    Option<String>,
+    },
+}
+
+/// Sorts `images` by `order`, descending when `reverse` is set. Keys that
+/// need the file read are gathered on all cores.
+pub fn sort_images(images: &mut Vec<PathBuf>, order: SortOrder, reverse: bool) {
+    let key = |path: &PathBuf| match order {
+        SortOrder::ByName => SortKey::None,
+        SortOrder::ByModified => SortKey::Modified(
+            fs::metadata(path).and_then(|m| m.modified()).ok(),
+        ),
+        SortOrder::BySize => {
+            SortKey::Size(fs::metadata(path).map(|m| m.len()).ok())
+        },
+        SortOrder::ByCaptureDate => {
+            let date = crate::metadata::capture_date(path);
+            SortKey::Captured {
+                undated: date.is_none(),
+                date,
+            }
+        },
+    };
+    let mut keyed: Vec<(SortKey, PathBuf)> = images
+        .par_drain(..)
+        .map(|path| (key(&path), path))
+        .collect();
+    keyed.sort_by(|(a, a_path), (b, b_path)| {
+        a.cmp(b).then_with(|| natural_cmp(a_path, b_path))
+    });
+    if reverse {
+        keyed.reverse();
+    }
+    images.extend(keyed.into_iter().map(|(_, path)| path));
+}
+
+/// Compares paths the way people count: `img2` before `img10`. Letters are
+/// compared ignoring case first, so names that only differ in case still
+/// get a fixed order.
+fn natural_cmp(a: &Path, b: &Path) -> Ordering {
+    let a = a.to_string_lossy();
+    let b = b.to_string_lossy();
+    let mut left = a.chars().peekable();
+    let mut right = b.chars().peekable();
+    loop {
+        match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return a.cmp(&b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let l = take_number(&mut left);
+                let r = take_number(&mut right);
+                // Without leading zeros, the longer number is the larger
+                let ordering = l.len().cmp(&r.len()).then_with(|| l.cmp(&r));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            },
+            (Some(l), Some(r)) => {
+                let ordering = l.to_lowercase().cmp(r.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                left.next();
+                right.next();
+            },
+        }
+    }
+}
+
+/// The run of digits at the start of `chars`, without leading zeros
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if c != '0' || !digits.is_empty() {
+            digits.push(c);
+        }
+    }
+    digits
+}
+
+/// The supported images among files dropped or passed together, in the
+/// order given, with dropped folders searched as by `walk_images` and
+/// archives listed as by `list_images`. Each file is listed once.
+pub fn collect_images(
+    paths: &[PathBuf],
+    config: &NavigationConfig,
+) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut images = Vec::new();
+    for path in paths {
+        let found = if path.is_dir() {
+            walk_images(path, config)
+        } else if archive::is_archive(path) {
+            list_images(path, config, None).unwrap_or_default()
+        } else if SupportedFormats::is_supported(path.extension()) {
+            vec![path.clone()]
+        } else {
+            Vec::new()
+        };
+        images.extend(
+            found
+                .into_iter()
+                .filter(|image| seen.insert(image.clone())),
+        );
+    }
+    images
+}
+
+/// The supported images in `dir` and its subfolders, those of each folder
+/// before the ones below it, in name order. Searches at most
+/// `max_depth` levels down, stops after `max_images` images and skips
+/// folders reached a second time through links.
+pub fn walk_images(dir: &Path, config: &NavigationConfig) -> Vec<PathBuf> {
+    let mut images = Vec::new();
+    walk(dir, 0, config, &mut HashSet::new(), &mut images);
+    images.truncate(config.max_images);
+    images
+}
+
+fn walk(
+    dir: &Path,
+    depth: usize,
+    config: &NavigationConfig,
+    visited: &mut HashSet<PathBuf>,
+    images: &mut Vec<PathBuf>,
+) {
+    let canonical = fs::canonicalize(dir).unwrap_or(dir.to_path_buf());
+    if images.len() >= config.max_images || !visited.insert(canonical) {
+        return;
+    }
+    images.extend(list_images(dir, config, None).unwrap_or_default());
+    if depth == config.max_depth {
+        return;
+    }
+    for folder in list_folders(dir, config).unwrap_or_default() {
+        let linked = fs::symlink_metadata(&folder)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if !linked || config.follow_symlinks {
+            walk(&folder, depth + 1, config, visited, images);
+        }
+    }
+}
+
+/// Lists the folders in `dir` in name order, for browsing to an image.
+/// Hidden folders are left out unless the hidden file option is on.
+pub fn list_folders(
+    dir: &Path,
+    config: &NavigationConfig,
+) -> io::Result<Vec<PathBuf>> {
+    let mut folders: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if !config.show_hidden && is_hidden(&path) {
+                return None;
+            }
+            path.is_dir().then_some(path)
+        })
+        .collect();
+    folders.sort();
+    Ok(folders)
+}
+
+/// The archives in `dir` that can be browsed like folders, in name order
+pub fn list_archives(
+    dir: &Path,
+    config: &NavigationConfig,
+) -> io::Result<Vec<PathBuf>> {
+    let mut archives: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if !config.show_hidden && is_hidden(&path) {
+                return None;
+            }
+            archive::is_archive(&path).then_some(path)
+        })
+        .collect();
+    archives.sort();
+    Ok(archives)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_symlink_and_hidden_options() {
+        let dir = std::env::temp_dir().join("ferrite-navigation-links");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.png"), b"").unwrap();
+        fs::write(dir.join(".hidden.png"), b"").unwrap();
+        symlink(dir.join("a.png"), dir.join("b.png")).unwrap();
+
+        let names = |config: &NavigationConfig, keep: Option<&Path>| {
+            list_images(&dir, config, keep)
+                .unwrap()
+                .iter()
+                .map(|p| {
+                    p.file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let config = NavigationConfig::default();
+        assert_eq!(names(&config, None), ["a.png"]);
+        // The link the user opened wins over its target
+        assert_eq!(names(&config, Some(&dir.join("b.png"))), ["b.png"]);
+
+        let all = NavigationConfig {
+            dedupe_symlinks: false,
+            show_hidden: true,
+            ..NavigationConfig::default()
+        };
+        assert_eq!(names(&all, None), [".hidden.png", "a.png", "b.png"]);
+
+        let no_links = NavigationConfig {
+            follow_symlinks: false,
+            ..all
+        };
+        assert_eq!(names(&no_links, None), [".hidden.png", "a.png"]);
+    }
+
+    #[test]
+    fn test_folders_are_listed_apart_from_images() {
+        let dir = std::env::temp_dir().join("ferrite-navigation-folders");
+        let _ = fs::remove_dir_all(&dir);
+        for folder in ["b", "a", ".cache"] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        fs::write(dir.join("c.png"), b"").unwrap();
+
+        let config = NavigationConfig::default();
+        assert_eq!(list_folders(&dir, &config).unwrap(), [
+            dir.join("a"),
+            dir.join("b")
+        ]);
+        assert_eq!(list_images(&dir, &config, None).unwrap(), [
+            dir.join("c.png")
+        ]);
+        let all = NavigationConfig {
+            show_hidden: true,
+            ..config
+        };
+        assert_eq!(list_folders(&dir, &all).unwrap().len(), 3);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_playlist_of_dropped_files_and_folders() {
+        let dir = std::env::temp_dir().join("ferrite-navigation-playlist");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("more")).unwrap();
+        for name in ["z.png", "notes.txt", "more/b.png", "more/a.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let config = NavigationConfig::default();
+        let dropped = [
+            dir.join("z.png"),
+            dir.join("notes.txt"),
+            dir.join("more"),
+            dir.join("more/a.png"),
+        ];
+        let images = collect_images(&dropped, &config);
+        assert_eq!(images, [
+            dir.join("z.png"),
+            dir.join("more/a.png"),
+            dir.join("more/b.png")
+        ]);
+
+        let mut navigation = NavigationManager::new(config);
+        navigation.load_playlist(images.clone());
+        assert!(navigation.is_playlist());
+        navigation.next_image();
+        // Reordering keeps the current image, removing it moves on
+        navigation.move_image(1, 2);
+        assert_eq!(navigation.current(), Some(images[1].as_path()));
+        assert_eq!(navigation.remove_image(0), None);
+        assert_eq!(navigation.current(), Some(images[1].as_path()));
+        assert_eq!(navigation.remove_image(1), Some(images[2].clone()));
+        assert_eq!(navigation.images(), [images[2].clone()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_walk_respects_depth_count_and_links() {
+        let dir = std::env::temp_dir().join("ferrite-navigation-walk");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a/deep")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        for name in ["top.png", "a/one.png", "a/deep/two.png", "b/three.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        // Would list the tree forever if followed each time
+        symlink(&dir, dir.join("b/loop")).unwrap();
+
+        let config = NavigationConfig::default();
+        assert_eq!(walk_images(&dir, &config), [
+            dir.join("top.png"),
+            dir.join("a/one.png"),
+            dir.join("a/deep/two.png"),
+            dir.join("b/three.png")
+        ]);
+        let shallow = NavigationConfig {
+            max_depth: 1,
+            max_images: 3,
+            ..config
+        };
+        assert_eq!(walk_images(&dir, &shallow), [
+            dir.join("top.png"),
+            dir.join("a/one.png"),
+            dir.join("b/three.png")
+        ]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sort_orders_keep_the_current_image() {
+        let dir = std::env::temp_dir().join("ferrite-navigation-sort");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, size) in [("img10.png", 1), ("IMG2.png", 3), ("img1.png", 2)]
+        {
+            fs::write(dir.join(name), vec![0; size]).unwrap();
+        }
+        let names = |navigation: &NavigationManager| {
+            navigation
+                .images()
+                .iter()
+                .map(|p| {
+                    p.file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut navigation =
+            NavigationManager::new(NavigationConfig::default());
+        navigation.load_current_directory(&dir.join("IMG2.png"));
+        assert_eq!(names(&navigation), ["img1.png", "IMG2.png", "img10.png"]);
+        navigation.set_order(SortOrder::BySize, true);
+        assert_eq!(names(&navigation), ["IMG2.png", "img1.png", "img10.png"]);
+        assert_eq!(navigation.current(), Some(dir.join("IMG2.png").as_path()));
+        // Without capture dates the names decide, once read in the
+        // background
+        navigation.set_order(SortOrder::ByCaptureDate, true);
+        assert!(navigation.is_arranging());
+        while !navigation.poll() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(names(&navigation), ["img10.png", "IMG2.png", "img1.png"]);
+        assert_eq!(navigation.current(), Some(dir.join("IMG2.png").as_path()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
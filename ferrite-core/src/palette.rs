@@ -0,0 +1,146 @@
+//! Dominant color extraction by median cut.
+
+use image::DynamicImage;
+
+/// Pixels sampled at most; larger images are subsampled on a grid
+const MAX_SAMPLES: u32 = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteColor {
+    pub rgb:   [u8; 3],
+    /// Fraction of the sampled pixels this color stands for
+    pub share: f32,
+}
+
+impl PaletteColor {
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.rgb;
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+}
+
+/// Returns up to `count` dominant colors, most common first. Mostly
+/// transparent pixels are ignored.
+pub fn extract(image: &DynamicImage, count: usize) -> Vec<PaletteColor> {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let step = ((width as u64 * height as u64) as f64 / MAX_SAMPLES as f64)
+        .sqrt()
+        .ceil()
+        .max(1.0) as u32;
+
+    let mut pixels = Vec::new();
+    for y in (0..height).step_by(step as usize) {
+        for x in (0..width).step_by(step as usize) {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            if a >= 128 {
+                pixels.push([r, g, b]);
+            }
+        }
+    }
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let total = pixels.len() as f32;
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        // Split the box whose widest channel spans the most, weighted by size
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range as u64 * b.len() as u64)
+            })
+            .filter(|(_, _, score)| *score > 0)
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|p| p[channel]);
+        let upper = pixels.split_off(split_point(&pixels, channel));
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    let mut colors: Vec<PaletteColor> = boxes
+        .iter()
+        .map(|pixels| {
+            let mut sum = [0u64; 3];
+            for pixel in pixels {
+                for (s, v) in sum.iter_mut().zip(pixel) {
+                    *s += *v as u64;
+                }
+            }
+            let n = pixels.len() as u64;
+            PaletteColor {
+                rgb:   sum.map(|s| ((s + n / 2) / n) as u8),
+                share: pixels.len() as f32 / total,
+            }
+        })
+        .collect();
+    colors.sort_by(|a, b| b.share.total_cmp(&a.share));
+    colors
+}
+
+/// Index near the median of pixels sorted by `channel` that does not put
+/// equal values on both sides, so flat regions stay in one box.
+fn split_point(sorted: &[[u8; 3]], channel: usize) -> usize {
+    let median = sorted.len() / 2;
+    let value = sorted[median][channel];
+    let first = sorted.partition_point(|p| p[channel] < value);
+    let after = sorted.partition_point(|p| p[channel] <= value);
+    match (first, after) {
+        (0, after) => after,
+        (first, after) if after == sorted.len() => first,
+        (first, after) if median - first <= after - median => first,
+        (_, after) => after,
+    }
+}
+
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) =
+                pixels.iter().fold((u8::MAX, 0), |(min, max), p| {
+                    (min.min(p[channel]), max.max(p[channel]))
+                });
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_extracts_dominant_colors_in_order() {
+        let image = RgbaImage::from_fn(100, 100, |x, _| {
+            if x < 75 {
+                Rgba([200, 30, 30, 255])
+            } else {
+                Rgba([20, 40, 220, 255])
+            }
+        });
+        let colors = extract(&DynamicImage::ImageRgba8(image), 4);
+
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].hex(), "#C81E1E");
+        assert!((colors[0].share - 0.75).abs() < 0.01);
+        assert_eq!(colors[1].rgb, [20, 40, 220]);
+    }
+
+    #[test]
+    fn test_transparent_image_has_no_palette() {
+        let image = RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 0]));
+        assert!(extract(&DynamicImage::ImageRgba8(image), 5).is_empty());
+    }
+}
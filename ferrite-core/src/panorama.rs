@@ -0,0 +1,453 @@
+//! Stitching of overlapping photos taken while panning into a panorama.
+//!
+//! Every photo is projected onto a cylinder around the camera, where
+//! panning turns into a plain translation. Neighbouring photos are matched
+//! by corner features on downscaled copies, the offset between them is
+//! found with RANSAC, and the photos are blended with weights that fade
+//! out towards their edges. Photos are expected in the order they were
+//! taken, left to right or right to left.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage,
+    GrayImage,
+    Rgba,
+    RgbaImage,
+};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::info;
+
+use crate::{
+    image::{self as images, ImageLoadError},
+    metadata,
+};
+
+/// Width photos are matched at
+const MATCH_WIDTH: u32 = 800;
+/// Corners kept per photo for matching
+const MAX_FEATURES: usize = 600;
+/// Half the side of the patch a corner is described by
+const PATCH_RADIUS: i32 = 6;
+/// Best match must be this much closer than the second best
+const RATIO: f32 = 0.8;
+/// Matches agreeing with an offset within this many pixels support it
+const INLIER_DISTANCE: f32 = 2.0;
+const MIN_INLIERS: usize = 8;
+/// Focal length in image widths used when EXIF does not tell
+const DEFAULT_FOCAL: f32 = 1.2;
+
+#[derive(Error, Debug)]
+pub enum PanoramaError {
+    #[error("A panorama needs at least two photos")]
+    TooFewImages,
+
+    #[error("Photos {} and {} do not overlap enough to be matched", .0 + 1, .0 + 2)]
+    NoOverlap(usize),
+
+    #[error(transparent)]
+    Load(#[from] ImageLoadError),
+}
+
+/// Decodes and stitches the photos at `paths`, in order.
+pub fn stitch_files(
+    paths: &[PathBuf],
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<DynamicImage, PanoramaError> {
+    let photos = paths
+        .par_iter()
+        .map(|path| {
+            let image = images::decode_file(path, decoding, io)?;
+            let focal = focal_length_px(path, image.width());
+            Ok((image, focal))
+        })
+        .collect::<Result<Vec<_>, PanoramaError>>()?;
+    stitch(&photos).map(DynamicImage::ImageRgba8)
+}
+
+/// Focal length in pixels from the 35 mm equivalent in EXIF, taking the
+/// image width as the long side of the frame.
+fn focal_length_px(path: &Path, width: u32) -> Option<f32> {
+    let exif = metadata::read_exif(path)?;
+    let field =
+        exif.get_field(exif::Tag::FocalLengthIn35mmFilm, exif::In::PRIMARY)?;
+    let mm = field.value.get_uint(0).filter(|&mm| mm > 0)?;
+    Some(mm as f32 / 36.0 * width as f32)
+}
+
+/// Stitches photos given with their focal lengths in pixels, if known.
+pub fn stitch(
+    photos: &[(DynamicImage, Option<f32>)],
+) -> Result<RgbaImage, PanoramaError> {
+    if photos.len() < 2 {
+        return Err(PanoramaError::TooFewImages);
+    }
+    let warped: Vec<RgbaImage> = photos
+        .par_iter()
+        .map(|(image, focal)| {
+            let focal = focal.unwrap_or(image.width() as f32 * DEFAULT_FOCAL);
+            cylindrical(&image.to_rgba8(), focal)
+        })
+        .collect();
+
+    let features: Vec<(f32, Vec<Feature>)> = warped
+        .par_iter()
+        .map(|image| {
+            let scale = (MATCH_WIDTH as f32 / image.width() as f32).min(1.0);
+            let small = imageops::resize(
+                image,
+                ((image.width() as f32 * scale) as u32).max(1),
+                ((image.height() as f32 * scale) as u32).max(1),
+                FilterType::Triangle,
+            );
+            (scale, detect(&small))
+        })
+        .collect();
+
+    // Position of every photo relative to the first
+    let mut positions = vec![(0.0f32, 0.0f32)];
+    for (index, pair) in features.windows(2).enumerate() {
+        let (scale_a, a) = &pair[0];
+        let (scale_b, b) = &pair[1];
+        let offset = estimate_offset(a, b, scale_a / scale_b)
+            .ok_or(PanoramaError::NoOverlap(index))?;
+        let (x, y) = positions[index];
+        let offset = (offset.0 / scale_a, offset.1 / scale_a);
+        info!("Photo {} is offset by {:?}", index + 2, offset);
+        positions.push((x + offset.0, y + offset.1));
+    }
+    Ok(blend(&warped, &positions))
+}
+
+/// Projects a photo onto a cylinder of radius `focal` pixels.
+fn cylindrical(image: &RgbaImage, focal: f32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let half_angle = (cx / focal).atan();
+    let out_width = ((2.0 * focal * half_angle).round() as u32).max(1);
+    let out_cx = out_width as f32 / 2.0;
+
+    let mut out = RgbaImage::new(out_width, height);
+    out.par_chunks_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let theta = (x as f32 + 0.5 - out_cx) / focal;
+                let h = (y as f32 + 0.5 - cy) / focal;
+                let sx = focal * theta.tan() + cx - 0.5;
+                let sy = focal * h / theta.cos() + cy - 0.5;
+                if let Some(value) = sample(image, sx, sy) {
+                    pixel.copy_from_slice(&value.0);
+                }
+            }
+        });
+    out
+}
+
+/// Bilinear sample, `None` outside the image
+fn sample(image: &RgbaImage, x: f32, y: f32) -> Option<Rgba<u8>> {
+    let (width, height) = image.dimensions();
+    if x < 0.0 || y < 0.0 || x > (width - 1) as f32 || y > (height - 1) as f32 {
+        return None;
+    }
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let mut out = [0u8; 4];
+    for (c, out) in out.iter_mut().enumerate() {
+        let p = |x, y| image.get_pixel(x, y)[c] as f32;
+        let top = p(x0, y0) * (1.0 - fx) + p(x1, y0) * fx;
+        let bottom = p(x0, y1) * (1.0 - fx) + p(x1, y1) * fx;
+        *out = (top * (1.0 - fy) + bottom * fy).round() as u8;
+    }
+    Some(Rgba(out))
+}
+
+struct Feature {
+    x:          f32,
+    y:          f32,
+    descriptor: Vec<f32>,
+}
+
+/// Harris corners of an image with normalized patch descriptors. Pixels
+/// outside the projected photo (transparent) are never used.
+fn detect(image: &RgbaImage) -> Vec<Feature> {
+    let gray: GrayImage = DynamicImage::ImageRgba8(image.clone()).to_luma8();
+    let (width, height) = (gray.width() as i32, gray.height() as i32);
+    let value = |x: i32, y: i32| gray.get_pixel(x as u32, y as u32)[0] as f32;
+    let opaque = |x: i32, y: i32| image.get_pixel(x as u32, y as u32)[3] == 255;
+
+    let margin = PATCH_RADIUS + 2;
+    if width <= 2 * margin || height <= 2 * margin {
+        return Vec::new();
+    }
+    // Harris response from gradients summed over a 5×5 window
+    let mut gradients = vec![(0.0f32, 0.0f32); (width * height) as usize];
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            gradients[(y * width + x) as usize] = (
+                value(x + 1, y) - value(x - 1, y),
+                value(x, y + 1) - value(x, y - 1),
+            );
+        }
+    }
+    let mut corners: Vec<(f32, i32, i32)> = Vec::new();
+    for y in margin..height - margin {
+        for x in margin..width - margin {
+            if !opaque(x - margin, y - margin)
+                || !opaque(x + margin, y + margin)
+                || !opaque(x - margin, y + margin)
+                || !opaque(x + margin, y - margin)
+            {
+                continue;
+            }
+            let (mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0);
+            for dy in -2..=2 {
+                for dx in -2..=2 {
+                    let (gx, gy) =
+                        gradients[((y + dy) * width + x + dx) as usize];
+                    xx += gx * gx;
+                    yy += gy * gy;
+                    xy += gx * gy;
+                }
+            }
+            let response = xx * yy - xy * xy - 0.04 * (xx + yy) * (xx + yy);
+            if response > 0.0 {
+                corners.push((response, x, y));
+            }
+        }
+    }
+
+    // The strongest corner of every cell, so features spread over the photo
+    let cell = 12;
+    let columns = (width / cell + 1) as usize;
+    let mut best: Vec<Option<(f32, i32, i32)>> =
+        vec![None; columns * (height / cell + 1) as usize];
+    for corner in corners {
+        let slot = &mut best
+            [(corner.2 / cell) as usize * columns + (corner.1 / cell) as usize];
+        if slot.is_none_or(|s| s.0 < corner.0) {
+            *slot = Some(corner);
+        }
+    }
+    let mut corners: Vec<_> = best.into_iter().flatten().collect();
+    corners.sort_by(|a, b| b.0.total_cmp(&a.0));
+    corners.truncate(MAX_FEATURES);
+
+    corners
+        .into_iter()
+        .filter_map(|(_, x, y)| {
+            let mut descriptor =
+                Vec::with_capacity(((2 * PATCH_RADIUS + 1) as usize).pow(2));
+            for dy in -PATCH_RADIUS..=PATCH_RADIUS {
+                for dx in -PATCH_RADIUS..=PATCH_RADIUS {
+                    descriptor.push(value(x + dx, y + dy));
+                }
+            }
+            let mean = descriptor.iter().sum::<f32>() / descriptor.len() as f32;
+            let deviation = (descriptor
+                .iter()
+                .map(|v| (v - mean).powi(2))
+                .sum::<f32>()
+                / descriptor.len() as f32)
+                .sqrt();
+            // Flat patches match anything
+            (deviation > 4.0).then(|| {
+                descriptor
+                    .iter_mut()
+                    .for_each(|v| *v = (*v - mean) / deviation);
+                Feature {
+                    x: x as f32,
+                    y: y as f32,
+                    descriptor,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Offset of photo `b`'s origin from photo `a`'s, in `a`'s pixels.
+/// `b_to_a` scales `b`'s coordinates to `a`'s.
+fn estimate_offset(
+    a: &[Feature],
+    b: &[Feature],
+    b_to_a: f32,
+) -> Option<(f32, f32)> {
+    let distance = |p: &[f32], q: &[f32]| -> f32 {
+        p.iter()
+            .zip(q)
+            .map(|(p, q)| (p - q) * (p - q))
+            .sum()
+    };
+    let matches: Vec<(f32, f32)> = a
+        .par_iter()
+        .filter_map(|fa| {
+            let mut best = (f32::MAX, None);
+            let mut second = f32::MAX;
+            for fb in b {
+                let d = distance(&fa.descriptor, &fb.descriptor);
+                if d < best.0 {
+                    second = best.0;
+                    best = (d, Some(fb));
+                } else if d < second {
+                    second = d;
+                }
+            }
+            let fb = best.1?;
+            (best.0 < RATIO * RATIO * second)
+                .then_some((fa.x - fb.x * b_to_a, fa.y - fb.y * b_to_a))
+        })
+        .collect();
+
+    // Every match proposes an offset; keep the one most others agree with
+    let support = |offset: &(f32, f32)| {
+        matches
+            .iter()
+            .filter(|m| {
+                (m.0 - offset.0).abs() < INLIER_DISTANCE
+                    && (m.1 - offset.1).abs() < INLIER_DISTANCE
+            })
+            .count()
+    };
+    let best = matches
+        .iter()
+        .max_by_key(|offset| support(offset))?;
+    let inliers: Vec<&(f32, f32)> = matches
+        .iter()
+        .filter(|m| {
+            (m.0 - best.0).abs() < INLIER_DISTANCE
+                && (m.1 - best.1).abs() < INLIER_DISTANCE
+        })
+        .collect();
+    if inliers.len() < MIN_INLIERS {
+        return None;
+    }
+    let count = inliers.len() as f32;
+    Some((
+        inliers.iter().map(|m| m.0).sum::<f32>() / count,
+        inliers.iter().map(|m| m.1).sum::<f32>() / count,
+    ))
+}
+
+/// Places the photos on one canvas, fading each out towards its edges
+/// where they overlap.
+fn blend(photos: &[RgbaImage], positions: &[(f32, f32)]) -> RgbaImage {
+    let min_x = positions
+        .iter()
+        .map(|p| p.0)
+        .fold(f32::MAX, f32::min);
+    let min_y = positions
+        .iter()
+        .map(|p| p.1)
+        .fold(f32::MAX, f32::min);
+    let origins: Vec<(i64, i64)> = positions
+        .iter()
+        .map(|p| ((p.0 - min_x).round() as i64, (p.1 - min_y).round() as i64))
+        .collect();
+    let width = photos
+        .iter()
+        .zip(&origins)
+        .map(|(photo, o)| o.0 + photo.width() as i64)
+        .max()
+        .unwrap_or(1) as u32;
+    let height = photos
+        .iter()
+        .zip(&origins)
+        .map(|(photo, o)| o.1 + photo.height() as i64)
+        .max()
+        .unwrap_or(1) as u32;
+
+    let mut out = RgbaImage::new(width, height);
+    out.par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let mut sum = [0.0f32; 3];
+                let mut total = 0.0;
+                for (photo, &(ox, oy)) in photos.iter().zip(&origins) {
+                    let (px, py) = (x as i64 - ox, y as i64 - oy);
+                    if px < 0
+                        || py < 0
+                        || px >= photo.width() as i64
+                        || py >= photo.height() as i64
+                    {
+                        continue;
+                    }
+                    let value = photo.get_pixel(px as u32, py as u32);
+                    if value[3] == 0 {
+                        continue;
+                    }
+                    let edge = px
+                        .min(photo.width() as i64 - 1 - px)
+                        .min(py)
+                        .min(photo.height() as i64 - 1 - py);
+                    let weight = (edge + 1) as f32 * value[3] as f32 / 255.0;
+                    for (s, v) in sum.iter_mut().zip(&value.0[..3]) {
+                        *s += *v as f32 * weight;
+                    }
+                    total += weight;
+                }
+                if total > 0.0 {
+                    let [r, g, b] = sum.map(|s| (s / total).round() as u8);
+                    pixel.copy_from_slice(&[r, g, b, 255]);
+                }
+            }
+        });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blobs and edges at many scales, like a real scene
+    fn scene(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f32, y as f32);
+            let v = 128.0
+                + 50.0 * (x / 7.0).sin() * (y / 11.0).cos()
+                + 40.0 * ((x * 0.37 + y * 0.21).sin() * (x * y / 900.0).cos())
+                + if (x as i32 / 23 + y as i32 / 17) % 3 == 0 {
+                    30.0
+                } else {
+                    -30.0
+                };
+            let v = v.clamp(0.0, 255.0) as u8;
+            Rgba([v, v / 2 + 60, 255 - v, 255])
+        })
+    }
+
+    #[test]
+    fn test_estimate_offset_between_crops() {
+        let scene = scene(600, 300);
+        let left = imageops::crop_imm(&scene, 0, 10, 360, 260).to_image();
+        let right = imageops::crop_imm(&scene, 240, 0, 360, 260).to_image();
+
+        let offset =
+            estimate_offset(&detect(&left), &detect(&right), 1.0).unwrap();
+        assert!((offset.0 - 240.0).abs() < 1.0, "{:?}", offset);
+        assert!((offset.1 + 10.0).abs() < 1.0, "{:?}", offset);
+
+        let panorama = blend(&[left, right], &[(0.0, 0.0), offset]);
+        assert_eq!(panorama.dimensions(), (600, 270));
+    }
+
+    #[test]
+    fn test_cylindrical_keeps_center() {
+        let image = RgbaImage::from_fn(200, 100, |x, y| {
+            Rgba([x as u8, y as u8 * 2, 0, 255])
+        });
+        let warped = cylindrical(&image, 240.0);
+        assert!(warped.width() < image.width());
+        // The middle row and column stay straight, so the center keeps its
+        // value up to interpolation
+        let center = warped.get_pixel(warped.width() / 2, 50);
+        assert!(center[0].abs_diff(100) <= 1, "{:?}", center);
+        assert!(center[1].abs_diff(100) <= 1, "{:?}", center);
+        // Corners fall outside the projected photo
+        assert_eq!(warped.get_pixel(0, 0)[3], 0);
+    }
+}
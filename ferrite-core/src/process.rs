@@ -0,0 +1,61 @@
+//! Running commands from the configuration, such as external editors and
+//! wallpaper setters, with the path of an image filled in.
+
+use std::{path::Path, process::Command};
+
+/// Runs a user-configured command line through the shell.
+pub(crate) fn shell_command(template: &str, image: &Path) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        let line = template.replace("{path}", &cmd_quote(image));
+        let mut command = Command::new("cmd");
+        // cmd does not parse its command line like other programs, so the
+        // usual quoting of arguments would garble it. With /S everything
+        // between the outer quotes is run exactly as written.
+        command.raw_arg(format!("/D /S /C \"{}\"", line));
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        let line = template.replace("{path}", &sh_quote(image));
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(line);
+        command
+    }
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+fn sh_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// Quotes for cmd, which expands `%name%` even inside quotes: every `%`
+/// is put outside them and escaped. Windows paths cannot contain `"`.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn cmd_quote(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy().replace('%', "\"^%\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_command_quotes_path() {
+        let command =
+            shell_command("setbg --fill {path}", Path::new("/tmp/it's.png"));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args[1], r"setbg --fill '/tmp/it'\''s.png'");
+    }
+
+    #[test]
+    fn test_cmd_quote_keeps_percent_signs() {
+        assert_eq!(
+            cmd_quote(Path::new(r"C:\100% & more\%USERNAME%.png")),
+            r#""C:\100"^%" & more\"^%"USERNAME"^%".png""#
+        );
+    }
+}
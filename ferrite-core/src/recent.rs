@@ -0,0 +1,55 @@
+//! Images and folders opened by name, from the command line, the open
+//! dialog, by dropping them on the window or from the menu. They are listed
+//! under File > Recent, newest first, and `--recent` opens the last one.
+
+use std::path::PathBuf;
+
+use crate::warm_start::RecentIndex;
+
+const FILE_NAME: &str = "recent-opened";
+
+/// The list kept in `state_dir`, holding up to `capacity` entries
+pub(crate) fn load(state_dir: Option<PathBuf>, capacity: usize) -> RecentIndex {
+    RecentIndex::load_file(state_dir.map(|dir| dir.join(FILE_NAME)), capacity)
+}
+
+/// The image or folder opened last that still exists
+pub fn last_opened(state_dir: Option<PathBuf>) -> Option<PathBuf> {
+    load(state_dir, usize::MAX)
+        .entries()
+        .iter()
+        .find(|path| path.exists())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_last_opened_skips_deleted_files() {
+        let dir = std::env::temp_dir().join("ferrite-recent-opened");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let kept = dir.join("kept.png");
+        fs::write(&kept, b"png").unwrap();
+
+        let mut list = load(Some(dir.clone()), 10);
+        list.record(&kept).unwrap();
+        list.record(&dir.join("deleted.png")).unwrap();
+        assert_eq!(last_opened(Some(dir.clone())), Some(kept));
+
+        list.clear().unwrap();
+        assert_eq!(last_opened(Some(dir.clone())), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_nothing_is_opened_without_a_state_directory() {
+        let mut list = load(None, 10);
+        list.record(&PathBuf::from("a.png")).unwrap();
+        assert_eq!(list.entries(), [PathBuf::from("a.png")]);
+        assert_eq!(last_opened(None), None);
+    }
+}
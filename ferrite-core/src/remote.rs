@@ -0,0 +1,407 @@
+//! Opening images from HTTP(S) URLs.
+//!
+//! An image is downloaded on a background thread into a cache folder and
+//! then opened like any other file, so decoding, limits and the rest of the
+//! viewer stay the same. The file is named after a hash of the URL, so
+//! opening the URL again is served from the cache without a request. The
+//! cache is kept under its size limit by removing the downloads opened
+//! longest ago.
+
+use ferrite_config::RemoteConfig;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::SystemTime,
+};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::image::SupportedFormats;
+
+/// Bytes read between progress reports
+const CHUNK: usize = 64 * 1024;
+/// Hex digits of the URL hash naming a cached file
+const HASH_LEN: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("Download failed: {0}")]
+    Http(String),
+
+    #[error("The image is larger than the {0} MB download limit")]
+    TooLarge(u64),
+
+    #[error("{0} is not a supported image type")]
+    NotImage(String),
+
+    #[error("Failed to keep the download: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Whether `text` is an HTTP(S) URL to download an image from
+pub fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    ["http://", "https://"].iter().any(|scheme| {
+        text.get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+            && text.len() > scheme.len()
+    })
+}
+
+/// A supported image extension for the file at `url`, from the end of its
+/// path or else from the `content_type` the server sent.
+fn extension(url: &str, content_type: Option<&str>) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let from_url = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| Path::new(name).extension())
+        .filter(|ext| SupportedFormats::is_supported(Some(ext)))
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    from_url.or_else(|| {
+        let mime = content_type?.split(';').next()?.trim();
+        SupportedFormats::EXTENSIONS
+            .iter()
+            .find(|ext| {
+                SupportedFormats::mime_type(ext)
+                    .is_some_and(|m| m.eq_ignore_ascii_case(mime))
+            })
+            .map(|ext| ext.to_string())
+    })
+}
+
+fn url_hash(url: &str) -> String {
+    let digest = Sha256::digest(url.trim().as_bytes());
+    let hex: String = digest
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    hex[..HASH_LEN].to_string()
+}
+
+/// The file an earlier download of `url` was kept in
+fn cached(dir: &Path, hash: &str) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .find(|path| {
+            path.file_stem()
+                .is_some_and(|stem| stem.to_string_lossy() == hash)
+                && SupportedFormats::is_supported(path.extension())
+        })
+}
+
+/// Where a download is at
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    pub received: u64,
+    /// As announced by the server
+    pub total:    Option<u64>,
+}
+
+impl Progress {
+    /// Share of the download done, when its size is known
+    pub fn fraction(&self) -> Option<f32> {
+        self.total
+            .filter(|&total| total > 0)
+            .map(|total| (self.received as f32 / total as f32).min(1.0))
+    }
+}
+
+enum Update {
+    Progress(Progress),
+    Done(Result<PathBuf, RemoteError>),
+}
+
+/// An image being downloaded. Dropping it abandons the download.
+pub struct Download {
+    url:      String,
+    progress: Progress,
+    updates:  Receiver<Update>,
+}
+
+impl Download {
+    /// Starts fetching `url` into `cache_dir`, as limited by `config`.
+    pub fn start(url: &str, config: &RemoteConfig, cache_dir: PathBuf) -> Self {
+        let (sender, updates) = mpsc::channel();
+        let url = url.trim().to_string();
+        let owned = url.clone();
+        let config = config.clone();
+        thread::spawn(move || {
+            let result = fetch(&owned, &config, &cache_dir, &sender);
+            let _ = sender.send(Update::Done(result));
+        });
+        Self {
+            url,
+            progress: Progress::default(),
+            updates,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// The downloaded file, or why there is none, once the download ended.
+    pub fn poll(&mut self) -> Option<Result<PathBuf, RemoteError>> {
+        while let Ok(update) = self.updates.try_recv() {
+            match update {
+                Update::Progress(progress) => self.progress = progress,
+                Update::Done(result) => return Some(result),
+            }
+        }
+        None
+    }
+}
+
+fn fetch(
+    url: &str,
+    config: &RemoteConfig,
+    cache_dir: &Path,
+    updates: &Sender<Update>,
+) -> Result<PathBuf, RemoteError> {
+    let hash = url_hash(url);
+    if let Some(path) = cached(cache_dir, &hash) {
+        info!("Opening {} from the download cache", url);
+        // Counts as recently used when the cache is pruned
+        if let Err(e) = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            warn!("Failed to touch {}: {}", path.display(), e);
+        }
+        return Ok(path);
+    }
+
+    let limit = config.max_download_mb * 1024 * 1024;
+    let agent = ureq::AgentBuilder::new()
+        .user_agent(&format!("Ferrite/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(config.timeout())
+        .build();
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|e| RemoteError::Http(e.to_string()))?;
+    let ext =
+        extension(url, response.header("Content-Type")).ok_or_else(|| {
+            RemoteError::NotImage(
+                response
+                    .header("Content-Type")
+                    .unwrap_or("The download")
+                    .to_string(),
+            )
+        })?;
+    let total = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    if total.is_some_and(|total| total > limit) {
+        return Err(RemoteError::TooLarge(config.max_download_mb));
+    }
+
+    let mut reader = response.into_reader().take(limit + 1);
+    let mut bytes = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let read = reader
+            .read(&mut chunk)
+            .map_err(|e| RemoteError::Http(e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        if bytes.len() as u64 > limit {
+            return Err(RemoteError::TooLarge(config.max_download_mb));
+        }
+        let progress = Progress {
+            received: bytes.len() as u64,
+            total,
+        };
+        // Nobody is waiting any more
+        if updates.send(Update::Progress(progress)).is_err() {
+            return Err(RemoteError::Http("cancelled".into()));
+        }
+    }
+
+    // Written beside its final name first, so an interrupted write is never
+    // mistaken for a cached image
+    fs::create_dir_all(cache_dir)?;
+    let path = cache_dir.join(format!("{}.{}", hash, ext));
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes)?;
+    if let Err(e) = fs::rename(&partial, &path) {
+        warn!("Failed to keep {}: {}", path.display(), e);
+        return Err(e.into());
+    }
+    info!("Downloaded {} ({} bytes)", url, bytes.len());
+    if let Err(e) =
+        prune(cache_dir, config.max_cache_mb.saturating_mul(1024 * 1024), &path)
+    {
+        warn!("Failed to prune the download cache: {}", e);
+    }
+    Ok(path)
+}
+
+/// Removes the files in `dir` opened longest ago until the rest fit in
+/// `limit` bytes. The file just opened, `keep`, always stays.
+fn prune(dir: &Path, limit: u64, keep: &Path) -> io::Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            let modified = metadata
+                .modified()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((modified, metadata.len(), entry.path()));
+        }
+    }
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, path) in files {
+        if total <= limit {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        fs::remove_file(&path)?;
+        info!("Removed {} from the download cache", path.display());
+        total -= len;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::Write,
+        net::TcpListener,
+        time::{Duration, Instant},
+    };
+
+    /// Answers one request on a local port with `body`, returning the URL
+    fn serve(path: &str, content_type: &str, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+        let content_type = content_type.to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: \
+                 {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            let _ = stream.write_all(&body);
+        });
+        url
+    }
+
+    /// A fresh folder of its own, apart from the cache the viewer falls back
+    /// to
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ferrite-remote-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn wait(mut download: Download) -> Result<PathBuf, RemoteError> {
+        let start = Instant::now();
+        loop {
+            if let Some(result) = download.poll() {
+                return result;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_urls_and_their_image_types() {
+        assert!(is_url("https://example.com/a.png"));
+        assert!(is_url(" HTTP://example.com/a "));
+        assert!(!is_url("https://"));
+        assert!(!is_url("/home/a.png"));
+        assert!(!is_url("ftp://example.com/a.png"));
+
+        let ext = |url, mime| extension(url, mime);
+        assert_eq!(ext("https://x/a.JPG?w=200", None).as_deref(), Some("jpg"));
+        assert_eq!(
+            ext("https://x/photo", Some("image/png; q=1")).as_deref(),
+            Some("png")
+        );
+        assert_eq!(ext("https://x/page.html", Some("text/html")), None);
+    }
+
+    #[test]
+    fn test_downloads_are_cached_and_limited() {
+        let dir = temp_dir("cached");
+        let config = RemoteConfig {
+            max_download_mb: 1,
+            ..RemoteConfig::default()
+        };
+
+        let url = serve("/photo", "image/png", b"not checked".to_vec());
+        let path = wait(Download::start(&url, &config, dir.clone())).unwrap();
+        assert_eq!(path.extension().unwrap(), "png");
+        assert_eq!(fs::read(&path).unwrap(), b"not checked");
+        // Nothing answers the second time
+        assert_eq!(
+            wait(Download::start(&url, &config, dir.clone())).unwrap(),
+            path
+        );
+
+        let url = serve("/big.png", "image/png", vec![0; 1024 * 1024 + 1]);
+        assert!(matches!(
+            wait(Download::start(&url, &config, dir.clone())),
+            Err(RemoteError::TooLarge(1))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cache_keeps_the_recently_opened_downloads() {
+        let dir = temp_dir("pruned");
+        let files: Vec<PathBuf> = (0..3)
+            .map(|n| dir.join(format!("{}.png", n)))
+            .collect();
+        for (age, file) in files.iter().rev().enumerate() {
+            fs::write(file, [0; 100]).unwrap();
+            let modified =
+                SystemTime::now() - Duration::from_secs(60 * age as u64);
+            fs::File::options()
+                .write(true)
+                .open(file)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+
+        prune(&dir, 250, &files[2]).unwrap();
+        assert!(!files[0].exists());
+        assert!(files[1].exists() && files[2].exists());
+        // The download just opened stays even when it alone is too large
+        prune(&dir, 50, &files[2]).unwrap();
+        assert!(!files[1].exists());
+        assert!(files[2].exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
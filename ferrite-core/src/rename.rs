@@ -0,0 +1,507 @@
+//! Renaming several files from a template such as
+//! `{date}_{camera}_{seq:04}`.
+//!
+//! Tokens: `{name}` (original name without extension), `{seq}` or
+//! `{seq:WIDTH}` (counter, zero padded to WIDTH), `{date}` (`YYYY-MM-DD`),
+//! `{time}` (`HH-MM-SS`), `{year}`, `{month}`, `{day}`, `{camera}` and
+//! `{make}`. Dates come from the EXIF capture time, or the modification
+//! time for files without one. The original extension is always kept.
+
+use exif::Tag;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+use crate::metadata;
+
+const UNKNOWN: &str = "unknown";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("Unknown token {{{0}}}")]
+    UnknownToken(String),
+
+    #[error("Unclosed {{ in template")]
+    Unclosed,
+
+    #[error("Invalid counter width in {{{0}}}")]
+    InvalidWidth(String),
+
+    #[error("Template is empty")]
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Name,
+    Seq { width: usize },
+    Date,
+    Time,
+    Year,
+    Month,
+    Day,
+    Camera,
+    Make,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    tokens: Vec<Token>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        if template.trim().is_empty() {
+            return Err(TemplateError::Empty);
+        }
+        let mut tokens = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let Some(open) = rest.find('{') else {
+                tokens.push(Token::Literal(rest.to_string()));
+                break;
+            };
+            if open > 0 {
+                tokens.push(Token::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or(TemplateError::Unclosed)?
+                + open;
+            tokens.push(parse_token(&rest[open + 1..close])?);
+            rest = &rest[close + 1..];
+        }
+        Ok(Self {
+            tokens,
+        })
+    }
+
+    /// Builds the new file name, without extension, with counter value
+    /// `seq`.
+    pub fn render(&self, info: &FileInfo, seq: u32) -> String {
+        let mut name = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(text) => name.push_str(text),
+                Token::Name => name.push_str(&info.stem),
+                Token::Seq {
+                    width,
+                } => name.push_str(&format!("{:0width$}", seq, width = width)),
+                Token::Date => name.push_str(&format!(
+                    "{:04}-{:02}-{:02}",
+                    info.taken.year, info.taken.month, info.taken.day
+                )),
+                Token::Time => name.push_str(&format!(
+                    "{:02}-{:02}-{:02}",
+                    info.taken.hour, info.taken.minute, info.taken.second
+                )),
+                Token::Year => {
+                    name.push_str(&format!("{:04}", info.taken.year))
+                },
+                Token::Month => {
+                    name.push_str(&format!("{:02}", info.taken.month))
+                },
+                Token::Day => name.push_str(&format!("{:02}", info.taken.day)),
+                Token::Camera => {
+                    name.push_str(info.camera.as_deref().unwrap_or(UNKNOWN))
+                },
+                Token::Make => {
+                    name.push_str(info.make.as_deref().unwrap_or(UNKNOWN))
+                },
+            }
+        }
+        sanitize(&name)
+    }
+}
+
+fn parse_token(token: &str) -> Result<Token, TemplateError> {
+    let (name, argument) = match token.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (token, None),
+    };
+    Ok(match (name, argument) {
+        ("seq", None) => Token::Seq {
+            width: 1
+        },
+        ("seq", Some(width)) => Token::Seq {
+            width: width
+                .parse()
+                .ok()
+                .filter(|w| *w <= 12)
+                .ok_or_else(|| {
+                    TemplateError::InvalidWidth(token.to_string())
+                })?,
+        },
+        ("name", None) => Token::Name,
+        ("date", None) => Token::Date,
+        ("time", None) => Token::Time,
+        ("year", None) => Token::Year,
+        ("month", None) => Token::Month,
+        ("day", None) => Token::Day,
+        ("camera", None) => Token::Camera,
+        ("make", None) => Token::Make,
+        _ => return Err(TemplateError::UnknownToken(token.to_string())),
+    })
+}
+
+/// Replaces characters that are not allowed in file names.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timestamp {
+    pub year:   u32,
+    pub month:  u32,
+    pub day:    u32,
+    pub hour:   u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Timestamp {
+    /// Parses the EXIF `YYYY:MM:DD HH:MM:SS` format.
+    pub(crate) fn from_exif(text: &str) -> Option<Self> {
+        let (date, time) = text.trim().split_once(' ')?;
+        let mut date = date.split(':').map(|p| p.parse().ok());
+        let mut time = time.split(':').map(|p| p.parse().ok());
+        let stamp = Self {
+            year:   date.next()??,
+            month:  date.next()??,
+            day:    date.next()??,
+            hour:   time.next()??,
+            minute: time.next()??,
+            second: time.next()??,
+        };
+        (stamp.year > 0 && stamp.month > 0).then_some(stamp)
+    }
+
+    /// UTC date and time of a system time.
+    pub(crate) fn from_system(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let of_day = secs % 86_400;
+        Self {
+            year,
+            month,
+            day,
+            hour: (of_day / 3600) as u32,
+            minute: (of_day / 60 % 60) as u32,
+            second: (of_day % 60) as u32,
+        }
+    }
+}
+
+impl Timestamp {
+    /// Seconds since 1970-01-01, for comparing capture times.
+    pub fn seconds(&self) -> i64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        days * 86_400
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+    }
+}
+
+/// Inverse of [`civil_from_days`].
+fn days_from_civil(year: u32, month: u32, day: u32) -> i64 {
+    let year = year as i64 - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Days since 1970-01-01 to a (year, month, day) in the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (u32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year as u32, month as u32, day as u32)
+}
+
+/// What a template can refer to for one file
+#[derive(Debug, Clone, Default)]
+pub struct FileInfo {
+    pub stem:   String,
+    pub taken:  Timestamp,
+    pub camera: Option<String>,
+    pub make:   Option<String>,
+}
+
+impl FileInfo {
+    pub fn read(path: &Path) -> Self {
+        let exif = metadata::read_exif(path);
+        let field = |tag| {
+            exif.as_ref()
+                .and_then(|exif| metadata::ascii_field(exif, tag))
+                .filter(|value| !value.is_empty())
+        };
+        let taken = field(Tag::DateTimeOriginal)
+            .and_then(|text| Timestamp::from_exif(&text))
+            .or_else(|| {
+                fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(Timestamp::from_system)
+            })
+            .unwrap_or_default();
+
+        Self {
+            stem: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            taken,
+            camera: field(Tag::Model),
+            make: field(Tag::Make),
+        }
+    }
+}
+
+/// One row of the rename preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRename {
+    pub source:   PathBuf,
+    pub target:   PathBuf,
+    /// Why this rename cannot be applied
+    pub conflict: Option<String>,
+}
+
+/// Works out the new name of every file, numbering them from `start` in the
+/// given order, and flags names that collide.
+pub fn plan(
+    files: &[(PathBuf, FileInfo)],
+    template: &Template,
+    start: u32,
+) -> Vec<PlannedRename> {
+    let sources: HashSet<&Path> = files
+        .iter()
+        .map(|(path, _)| path.as_path())
+        .collect();
+    let mut planned: Vec<PlannedRename> = files
+        .iter()
+        .enumerate()
+        .map(|(index, (source, info))| {
+            let mut name = template.render(info, start + index as u32);
+            if let Some(ext) = source.extension() {
+                name.push('.');
+                name.push_str(&ext.to_string_lossy());
+            }
+            PlannedRename {
+                source:   source.clone(),
+                target:   source.with_file_name(name),
+                conflict: None,
+            }
+        })
+        .collect();
+
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    for rename in &planned {
+        *counts.entry(rename.target.clone()).or_default() += 1;
+    }
+    for rename in &mut planned {
+        if counts[&rename.target] > 1 {
+            rename.conflict = Some("same name as another file".to_string());
+        } else if rename.target != rename.source
+            && !sources.contains(rename.target.as_path())
+            && rename.target.exists()
+        {
+            rename.conflict = Some("a file with this name exists".to_string());
+        }
+    }
+    planned
+}
+
+/// Applies a conflict-free plan. Files are first moved to temporary names
+/// so renames that swap names do not overwrite each other.
+pub fn apply(plan: &[PlannedRename]) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    if let Some(rename) = plan.iter().find(|r| r.conflict.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} cannot be renamed", rename.source.display()),
+        ));
+    }
+    let moves: Vec<&PlannedRename> = plan
+        .iter()
+        .filter(|r| r.source != r.target)
+        .collect();
+
+    let mut staged: Vec<(&PlannedRename, PathBuf)> =
+        Vec::with_capacity(moves.len());
+    for (index, rename) in moves.iter().enumerate() {
+        let temporary = rename.source.with_file_name(format!(
+            ".ferrite-rename-{}-{}",
+            std::process::id(),
+            index
+        ));
+        if let Err(e) = fs::rename(&rename.source, &temporary) {
+            // Put back what was already moved
+            for (rename, temporary) in &staged {
+                let _ = fs::rename(temporary, &rename.source);
+            }
+            return Err(e);
+        }
+        staged.push((*rename, temporary));
+    }
+
+    let mut done = Vec::with_capacity(staged.len());
+    for (rename, temporary) in staged {
+        fs::rename(&temporary, &rename.target)?;
+        done.push((rename.source.clone(), rename.target.clone()));
+    }
+    Ok(done)
+}
+
+/// Gives `path` the file name `name` in the same folder. Names that would
+/// move it elsewhere or replace another file are refused.
+pub fn rename_in_place(path: &Path, name: &str) -> io::Result<PathBuf> {
+    let name = name.trim();
+    let invalid = |message: &str| {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, message.to_string()))
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return invalid("the name is empty");
+    }
+    if name.contains(['/', std::path::MAIN_SEPARATOR]) {
+        return invalid("the name cannot contain a path separator");
+    }
+    let target = path.with_file_name(name);
+    if target == path {
+        return Ok(target);
+    }
+    // Case-only renames on case-insensitive file systems find the file
+    // itself under the new name
+    let same_file = fs::canonicalize(&target)
+        .ok()
+        .zip(fs::canonicalize(path).ok())
+        .is_some_and(|(a, b)| a == b);
+    if target.symlink_metadata().is_ok() && !same_file {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", name),
+        ));
+    }
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(stem: &str) -> FileInfo {
+        FileInfo {
+            stem:   stem.to_string(),
+            taken:  Timestamp {
+                year:   2024,
+                month:  3,
+                day:    9,
+                hour:   14,
+                minute: 5,
+                second: 0,
+            },
+            camera: Some("X100/V".to_string()),
+            make:   None,
+        }
+    }
+
+    #[test]
+    fn test_template_tokens() {
+        let template = Template::parse("{date}_{camera}_{seq:04}").unwrap();
+        assert_eq!(template.render(&info("DSC1"), 7), "2024-03-09_X100_V_0007");
+        let template = Template::parse("{name}-{make}{seq}").unwrap();
+        assert_eq!(template.render(&info("DSC1"), 12), "DSC1-unknown12");
+
+        assert_eq!(
+            Template::parse("{lens}"),
+            Err(TemplateError::UnknownToken("lens".to_string()))
+        );
+        assert_eq!(Template::parse("{date"), Err(TemplateError::Unclosed));
+        assert_eq!(Template::parse("  "), Err(TemplateError::Empty));
+        assert_eq!(
+            Timestamp::from_system(
+                UNIX_EPOCH + std::time::Duration::from_secs(1_709_993_100)
+            ),
+            info("").taken
+        );
+        assert_eq!(info("").taken.seconds(), 1_709_993_100);
+    }
+
+    #[test]
+    fn test_plan_flags_duplicates_and_swaps_apply() {
+        let dir = std::env::temp_dir().join("ferrite-rename-plan");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let one = dir.join("1.jpg");
+        let two = dir.join("2.jpg");
+        fs::write(&one, "one").unwrap();
+        fs::write(&two, "two").unwrap();
+        fs::write(dir.join("other.jpg"), "").unwrap();
+
+        let files = vec![(one.clone(), info("1")), (two.clone(), info("2"))];
+        let same = plan(&files, &Template::parse("{date}").unwrap(), 1);
+        assert!(same.iter().all(|r| r.conflict.is_some()));
+        let taken = plan(&files[..1], &Template::parse("other").unwrap(), 1);
+        assert!(taken[0].conflict.is_some());
+
+        // Reversed order swaps the two names
+        let files = vec![(two, info("2")), (one, info("1"))];
+        let swap = plan(&files, &Template::parse("{seq}").unwrap(), 1);
+        assert!(swap.iter().all(|r| r.conflict.is_none()));
+        assert_eq!(apply(&swap).unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("1.jpg")).unwrap(), "two");
+        assert_eq!(fs::read_to_string(dir.join("2.jpg")).unwrap(), "one");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rename_in_place_refuses_moves_and_overwrites() {
+        let dir = std::env::temp_dir().join("ferrite-rename-in-place");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("DSC1.jpg");
+        fs::write(&photo, "photo").unwrap();
+        fs::write(dir.join("taken.jpg"), "").unwrap();
+
+        let renamed = rename_in_place(&photo, " beach.jpg ").unwrap();
+        assert_eq!(renamed, dir.join("beach.jpg"));
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "photo");
+        assert!(!photo.exists());
+
+        for name in ["", "..", "../beach.jpg", "sub/beach.jpg"] {
+            let e = rename_in_place(&renamed, name).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{:?}", name);
+        }
+        let e = rename_in_place(&renamed, "taken.jpg").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(rename_in_place(&renamed, "beach.jpg").unwrap(), renamed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,558 @@
+//! Decoding untrusted files in a separate process.
+//!
+//! The viewer re-executes itself with `--decode-worker` and hands it the
+//! file's bytes on standard input, so the worker never opens the file
+//! itself. The worker creates the file for its result, then lowers its
+//! resource limits and, on Linux, confines itself with Landlock: after
+//! that it can read the system library folders and nothing else, cannot
+//! write or create files anywhere, and cannot open TCP connections where
+//! the kernel supports that (6.7 and later). It decodes with the same
+//! decoders as the viewer and writes the pixels, in their own depth, into
+//! the memory-mapped result file (on tmpfs where available), which the
+//! viewer maps and copies out. A codec crash, runaway allocation or hang
+//! only takes down the worker.
+//!
+//! The worker still runs as the same user. Without Landlock (other
+//! systems, or Linux before 5.13) it keeps the viewer's access to files
+//! and the network; only its resource limits are lowered.
+
+use ferrite_config::DecodingConfig;
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba, Rgba32FImage, RgbaImage};
+use memmap2::{Mmap, MmapMut};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::{debug, info, instrument, warn};
+
+use crate::image::limits;
+
+pub const WORKER_FLAG: &str = "--decode-worker";
+
+const MAGIC: &[u8; 4] = b"FRDW";
+const HEADER_LEN: usize = 16;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+static HANDOFF_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Error, Debug)]
+pub enum SandboxError {
+    #[error("Failed to start decoder process: {0}")]
+    Launch(std::io::Error),
+
+    #[error("Decoder process timed out after {0} s")]
+    Timeout(u64),
+
+    #[error("Decoder process failed ({status}): {message}")]
+    Failed { status: String, message: String },
+
+    #[error("Decoder process returned malformed pixel data")]
+    Malformed,
+
+    #[error("Failed to read decoded pixels: {0}")]
+    Handoff(std::io::Error),
+}
+
+/// Depth of the pixels handed back, kept so 16-bit and HDR images are not
+/// clamped to 8 bits on the way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum PixelKind {
+    Rgba8 = 0,
+    Rgba16 = 1,
+    Rgba32F = 2,
+}
+
+impl PixelKind {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Rgba8),
+            1 => Some(Self::Rgba16),
+            2 => Some(Self::Rgba32F),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 => 4,
+            Self::Rgba16 => 8,
+            Self::Rgba32F => 16,
+        }
+    }
+}
+
+/// Decodes `bytes`, the contents of `path`, in a worker process started
+/// from `worker`, normally the running executable.
+#[instrument(skip(bytes, config, worker), fields(path = %path.display()))]
+pub fn decode(
+    path: &Path,
+    bytes: &[u8],
+    config: &DecodingConfig,
+    worker: &Path,
+) -> Result<DynamicImage, SandboxError> {
+    let handoff = handoff_path();
+    let mut child = Command::new(worker)
+        .arg(WORKER_FLAG)
+        .arg(&handoff)
+        .arg(config.worker_memory_mb.to_string())
+        .arg(config.worker_timeout_secs.to_string())
+        .arg(if config.develop_raw { "develop" } else { "preview" })
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(SandboxError::Launch)?;
+    let mut stdin = child.stdin.take();
+    let mut stderr = child.stderr.take();
+
+    // Fed and drained while the worker runs, so neither pipe can fill up
+    // and stall it
+    let (status, message) = thread::scope(|scope| {
+        scope.spawn(move || {
+            if let Some(stdin) = stdin.as_mut() {
+                // Fails when the worker gives up early, which it reports
+                let _ = stdin.write_all(bytes);
+            }
+        });
+        let messages = scope.spawn(move || {
+            let mut message = String::new();
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut message);
+            }
+            message
+        });
+
+        let deadline =
+            Instant::now() + Duration::from_secs(config.worker_timeout_secs);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) if Instant::now() >= deadline => {
+                    warn!("Decoder process timed out, killing it");
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break Err(SandboxError::Timeout(
+                        config.worker_timeout_secs,
+                    ));
+                },
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break Err(SandboxError::Launch(e));
+                },
+            }
+        };
+        (status, messages.join().unwrap_or_default())
+    });
+    let message = message.trim().to_string();
+
+    let result = match status {
+        Ok(status) if status.success() => {
+            if !message.is_empty() {
+                debug!("Decoder process: {}", message);
+            }
+            read_handoff(&handoff)
+        },
+        Ok(status) => Err(SandboxError::Failed {
+            status: status.to_string(),
+            message,
+        }),
+        Err(e) => Err(e),
+    };
+    let _ = fs::remove_file(&handoff);
+    result
+}
+
+/// Decodes with the running executable as the worker.
+pub fn decode_with_current_exe(
+    path: &Path,
+    bytes: &[u8],
+    config: &DecodingConfig,
+) -> Result<DynamicImage, SandboxError> {
+    let worker = std::env::current_exe().map_err(SandboxError::Launch)?;
+    decode(path, bytes, config, &worker)
+}
+
+/// Entry point for the worker. Returns the process exit code when the
+/// process was started as a decoder, `None` for a normal start.
+pub fn worker_main() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) != Some(WORKER_FLAG) {
+        return None;
+    }
+    let [_, _, output, memory_mb, timeout_secs, raw] = args.as_slice() else {
+        eprintln!(
+            "usage: {} <output> <memory-mb> <timeout-s> <develop|preview> < \
+             image",
+            WORKER_FLAG
+        );
+        return Some(2);
+    };
+    let config = DecodingConfig {
+        develop_raw: raw == "develop",
+        ..DecodingConfig::default()
+    };
+
+    match run_worker(
+        Path::new(output),
+        memory_mb.parse().unwrap_or(0),
+        timeout_secs.parse().unwrap_or(0),
+        &config,
+    ) {
+        Ok(()) => Some(0),
+        Err(message) => {
+            eprintln!("{}", message);
+            Some(1)
+        },
+    }
+}
+
+fn run_worker(
+    output: &Path,
+    memory_mb: u64,
+    cpu_secs: u64,
+    config: &DecodingConfig,
+) -> Result<(), String> {
+    // Everything the worker opens is opened before it is confined
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(output)
+        .map_err(|e| e.to_string())?;
+    restrict_process(memory_mb, cpu_secs);
+
+    let mut bytes = Vec::new();
+    io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    // Memory is capped by the process limits, so the decoder's own
+    // allocation limit would only get in the way of confirmed large images
+    let image =
+        limits::decode(&bytes, config, true).map_err(|e| e.to_string())?;
+    drop(bytes);
+
+    let (kind, image) = match image {
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => {
+            (PixelKind::Rgba16, DynamicImage::ImageRgba16(image.to_rgba16()))
+        },
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            (PixelKind::Rgba32F, DynamicImage::ImageRgba32F(image.to_rgba32f()))
+        },
+        image => (PixelKind::Rgba8, DynamicImage::ImageRgba8(image.to_rgba8())),
+    };
+    let pixels = image.as_bytes();
+
+    file.set_len((HEADER_LEN + pixels.len()) as u64)
+        .map_err(|e| e.to_string())?;
+    // SAFETY: the file was just created by this process and is not resized
+    // while mapped
+    let mut map =
+        unsafe { MmapMut::map_mut(&file) }.map_err(|e| e.to_string())?;
+    map[..4].copy_from_slice(MAGIC);
+    map[4..8].copy_from_slice(&image.width().to_le_bytes());
+    map[8..12].copy_from_slice(&image.height().to_le_bytes());
+    map[12..16].copy_from_slice(&(kind as u32).to_le_bytes());
+    map[HEADER_LEN..].copy_from_slice(pixels);
+    map.flush().map_err(|e| e.to_string())
+}
+
+fn read_handoff(path: &Path) -> Result<DynamicImage, SandboxError> {
+    let file = File::open(path).map_err(SandboxError::Handoff)?;
+    // SAFETY: the worker has exited, so nothing writes to the file anymore
+    let map = unsafe { Mmap::map(&file) }.map_err(SandboxError::Handoff)?;
+    parse_handoff(&map)
+}
+
+fn parse_handoff(bytes: &[u8]) -> Result<DynamicImage, SandboxError> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err(SandboxError::Malformed);
+    }
+    let word =
+        |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let (width, height) = (word(4), word(8));
+    let kind = PixelKind::from_u32(word(12)).ok_or(SandboxError::Malformed)?;
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(kind.bytes_per_pixel()))
+        .ok_or(SandboxError::Malformed)?;
+    let pixels = &bytes[HEADER_LEN..];
+    if pixels.len() != expected {
+        return Err(SandboxError::Malformed);
+    }
+
+    info!(
+        "Received {}x{} {:?} image from decoder process",
+        width, height, kind
+    );
+    let image = match kind {
+        PixelKind::Rgba8 => RgbaImage::from_raw(width, height, pixels.to_vec())
+            .map(DynamicImage::ImageRgba8),
+        PixelKind::Rgba16 => {
+            buffer::<Rgba<u16>, 2>(width, height, pixels, u16::from_ne_bytes)
+                .map(DynamicImage::ImageRgba16)
+        },
+        PixelKind::Rgba32F => {
+            buffer::<Rgba<f32>, 4>(width, height, pixels, f32::from_ne_bytes)
+                .map(|image: Rgba32FImage| DynamicImage::ImageRgba32F(image))
+        },
+    };
+    image.ok_or(SandboxError::Malformed)
+}
+
+/// Rebuilds an image of `N`-byte subpixels from their native-endian bytes.
+fn buffer<P: Pixel, const N: usize>(
+    width: u32,
+    height: u32,
+    bytes: &[u8],
+    from_bytes: fn([u8; N]) -> P::Subpixel,
+) -> Option<ImageBuffer<P, Vec<P::Subpixel>>> {
+    let subpixels = bytes
+        .chunks_exact(N)
+        .map(|chunk| from_bytes(chunk.try_into().unwrap()))
+        .collect();
+    ImageBuffer::from_raw(width, height, subpixels)
+}
+
+/// Picks a location for the pixel handoff, preferring memory-backed
+/// storage.
+fn handoff_path() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    let dir =
+        if shm.is_dir() { shm.to_path_buf() } else { std::env::temp_dir() };
+    dir.join(format!(
+        "ferrite-decode-{}-{}",
+        std::process::id(),
+        HANDOFF_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Caps memory and CPU time and, on Linux, forbids gaining privileges and
+/// confines the process with Landlock.
+#[cfg(unix)]
+fn restrict_process(memory_mb: u64, cpu_secs: u64) {
+    let set = |resource, value: u64| {
+        if value == 0 {
+            return;
+        }
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        };
+        // SAFETY: setrlimit only reads the passed struct
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            eprintln!("warning: failed to set resource limit {}", resource);
+        }
+    };
+    set(libc::RLIMIT_AS, memory_mb * 1024 * 1024);
+    set(libc::RLIMIT_CPU, cpu_secs);
+    set(libc::RLIMIT_CORE, 0);
+
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: PR_SET_NO_NEW_PRIVS takes no pointers
+        unsafe {
+            libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0);
+        }
+        if let Err(e) = landlock::confine() {
+            eprintln!("warning: running without Landlock: {}", e);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn restrict_process(_memory_mb: u64, _cpu_secs: u64) {}
+
+/// The Landlock calls, which libc has no wrappers for.
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::{ffi::CString, io, os::fd::RawFd};
+
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const FS_EXECUTE: u64 = 1 << 0;
+    const FS_READ_FILE: u64 = 1 << 2;
+    const FS_READ_DIR: u64 = 1 << 3;
+    /// Every right of the first ABI, writing and creating files among them
+    const FS_ABI_1: u64 = (1 << 13) - 1;
+    const FS_REFER: u64 = 1 << 13;
+    const FS_TRUNCATE: u64 = 1 << 14;
+    const NET_BIND_TCP: u64 = 1 << 0;
+    const NET_CONNECT_TCP: u64 = 1 << 1;
+
+    /// Left readable for the libraries decoders load on demand
+    const LIBRARY_DIRS: [&str; 3] = ["/usr", "/lib", "/lib64"];
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs:  u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd:      i32,
+    }
+
+    fn check(result: libc::c_long) -> io::Result<libc::c_long> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    /// Takes away all file system access but reading the library folders,
+    /// and TCP where the kernel handles it. Open files stay usable.
+    pub fn confine() -> io::Result<()> {
+        // SAFETY: a null attribute with the version flag only queries
+        let abi = check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        })?;
+        let mut handled_fs = FS_ABI_1;
+        if abi >= 2 {
+            handled_fs |= FS_REFER;
+        }
+        if abi >= 3 {
+            handled_fs |= FS_TRUNCATE;
+        }
+        let attr = RulesetAttr {
+            handled_access_fs:  handled_fs,
+            handled_access_net: if abi >= 4 {
+                NET_BIND_TCP | NET_CONNECT_TCP
+            } else {
+                0
+            },
+        };
+        let size = if abi >= 4 {
+            std::mem::size_of::<RulesetAttr>()
+        } else {
+            // Older kernels reject the network field
+            std::mem::size_of::<u64>()
+        };
+        // SAFETY: `attr` outlives the call and `size` does not exceed it
+        let ruleset = check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                size,
+                0u32,
+            )
+        })? as RawFd;
+
+        let result = (|| {
+            for dir in LIBRARY_DIRS {
+                allow_reading(ruleset, dir)?;
+            }
+            // SAFETY: both calls only take the ruleset descriptor
+            check(unsafe {
+                libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32)
+            })
+            .map(|_| ())
+        })();
+        // SAFETY: the descriptor was created above and is not used again
+        unsafe { libc::close(ruleset) };
+        result
+    }
+
+    fn allow_reading(ruleset: RawFd, dir: &str) -> io::Result<()> {
+        let path = CString::new(dir).expect("no interior nul");
+        // SAFETY: `path` is a valid C string for the duration of the call
+        let fd = unsafe {
+            libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC)
+        };
+        if fd < 0 {
+            // Not every system has every folder
+            return Ok(());
+        }
+        let rule = PathBeneathAttr {
+            allowed_access: FS_EXECUTE | FS_READ_FILE | FS_READ_DIR,
+            parent_fd:      fd,
+        };
+        // SAFETY: `rule` outlives the call
+        let result = check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset,
+                RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0u32,
+            )
+        });
+        // SAFETY: `fd` was opened above and is not used again
+        unsafe { libc::close(fd) };
+        result.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handoff(width: u32, height: u32, kind: u32, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(kind.to_le_bytes());
+        bytes.extend(pixels);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_handoff() {
+        let mut bytes = handoff(2, 1, 0, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let image = parse_handoff(&bytes).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(1, 0).0, [5, 6, 7, 8]);
+
+        // Size mismatch between header and payload is rejected
+        bytes.pop();
+        assert!(matches!(parse_handoff(&bytes), Err(SandboxError::Malformed)));
+
+        // Deeper pixels come back as deep as they were
+        let pixel: Vec<u8> = [1u16, 2, 3, 65535]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let DynamicImage::ImageRgba16(image) =
+            parse_handoff(&handoff(1, 1, 1, &pixel)).unwrap()
+        else {
+            panic!("not 16-bit");
+        };
+        assert_eq!(image.get_pixel(0, 0).0, [1, 2, 3, 65535]);
+        assert!(parse_handoff(&handoff(1, 1, 7, &pixel)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_worker_reports_error() {
+        let config = DecodingConfig::default();
+        let result = decode(
+            Path::new("in.png"),
+            b"not read",
+            &config,
+            Path::new("/bin/false"),
+        );
+        assert!(matches!(result, Err(SandboxError::Failed { .. })));
+    }
+}
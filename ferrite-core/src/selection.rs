@@ -0,0 +1,115 @@
+//! Rectangular regions of an image.
+
+use image::DynamicImage;
+
+/// A rectangle in image pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x:      u32,
+    pub y:      u32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// The pixels spanned by two corner points, clamped to an image of
+    /// `size`. Returns `None` if the region is empty.
+    pub fn from_corners(
+        a: (f32, f32),
+        b: (f32, f32),
+        size: (u32, u32),
+    ) -> Option<Self> {
+        let clamp = |v: f32, max: u32| v.round().clamp(0.0, max as f32) as u32;
+        let (x0, x1) =
+            (clamp(a.0.min(b.0), size.0), clamp(a.0.max(b.0), size.0));
+        let (y0, y1) =
+            (clamp(a.1.min(b.1), size.1), clamp(a.1.max(b.1), size.1));
+        (x1 > x0 && y1 > y0).then_some(Self {
+            x:      x0,
+            y:      y0,
+            width:  x1 - x0,
+            height: y1 - y0,
+        })
+    }
+
+    /// Moved by `(dx, dy)` pixels, stopping at the edges of an image of
+    /// `size`
+    pub fn moved(self, (dx, dy): (i64, i64), size: (u32, u32)) -> Self {
+        let shift = |start: u32, length: u32, delta: i64, max: u32| {
+            (start as i64 + delta).clamp(0, (max - length) as i64) as u32
+        };
+        Self {
+            x: shift(self.x, self.width, dx, size.0),
+            y: shift(self.y, self.height, dy, size.1),
+            ..self
+        }
+    }
+
+    /// Grown or shrunk by `(dx, dy)` pixels at the right and bottom edges,
+    /// keeping at least one pixel and staying inside an image of `size`
+    pub fn resized(self, (dx, dy): (i64, i64), size: (u32, u32)) -> Self {
+        let grow = |start: u32, length: u32, delta: i64, max: u32| {
+            (length as i64 + delta).clamp(1, (max - start) as i64) as u32
+        };
+        Self {
+            width: grow(self.x, self.width, dx, size.0),
+            height: grow(self.y, self.height, dy, size.1),
+            ..self
+        }
+    }
+
+    pub fn crop(&self, image: &DynamicImage) -> DynamicImage {
+        image.crop_imm(self.x, self.y, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_from_corners_is_ordered_and_clamped() {
+        let region = Region::from_corners((80.4, 30.0), (-5.0, 10.6), (50, 40));
+        assert_eq!(
+            region,
+            Some(Region {
+                x: 0, y: 11, width: 50, height: 19
+            })
+        );
+        assert_eq!(
+            Region::from_corners((3.0, 3.0), (3.2, 9.0), (50, 40)),
+            None
+        );
+
+        let image = DynamicImage::new_rgba8(50, 40);
+        let cropped = region.unwrap().crop(&image);
+        assert_eq!((cropped.width(), cropped.height()), (50, 19));
+    }
+
+    #[test]
+    fn test_moving_and_resizing_stay_inside_the_image() {
+        let region = Region {
+            x: 10, y: 10, width: 20, height: 10
+        };
+        assert_eq!(region.moved((-3, 4), (50, 40)), Region {
+            x: 7,
+            y: 14,
+            ..region
+        });
+        assert_eq!(region.moved((100, -100), (50, 40)), Region {
+            x: 30,
+            y: 0,
+            ..region
+        });
+        assert_eq!(region.resized((5, -20), (50, 40)), Region {
+            width: 25,
+            height: 1,
+            ..region
+        });
+        assert_eq!(region.resized((100, 100), (50, 40)), Region {
+            width: 40,
+            height: 30,
+            ..region
+        });
+    }
+}
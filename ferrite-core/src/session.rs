@@ -0,0 +1,143 @@
+//! What was on screen when Ferrite last closed, so the next start picks up
+//! where it left off.
+//!
+//! The state is written to `session.toml` in the state directory on exit.
+//! A missing or unreadable file just means starting fresh.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+const FILE_NAME: &str = "session.toml";
+
+/// Position and size of the window, in points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// Top left corner of the outer window
+    pub x:      f32,
+    pub y:      f32,
+    /// Size of the area inside the window decorations
+    pub width:  f32,
+    pub height: f32,
+}
+
+/// How the image was scaled
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoomState {
+    OneToOne,
+    #[default]
+    FitLonger,
+    FitShorter,
+    /// A zoom level picked by the user
+    Custom(f64),
+}
+
+/// Panels that were open
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelState {
+    pub exif:       bool,
+    pub filmstrip:  bool,
+    pub histogram:  bool,
+    pub map:        bool,
+    pub palette:    bool,
+    pub playlist:   bool,
+    pub tags:       bool,
+    /// Shown empty again, since the filter itself is not kept
+    pub filter_bar: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub image:      Option<PathBuf>,
+    pub zoom:       ZoomState,
+    pub panels:     PanelState,
+    /// Whether the window was borderless fullscreen
+    pub fullscreen: bool,
+    /// Unknown when the window was fullscreen or never reported it
+    pub window:     Option<WindowGeometry>,
+}
+
+impl SessionState {
+    /// Reads the session saved in `state_dir`, if there is a usable one.
+    pub fn load(state_dir: Option<&Path>) -> Option<Self> {
+        let file = state_dir?.join(FILE_NAME);
+        let text = fs::read_to_string(&file).ok()?;
+        match toml::from_str(&text) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                warn!("Ignoring session in {}: {}", file.display(), e);
+                None
+            },
+        }
+    }
+
+    pub fn save(&self, state_dir: Option<&Path>) -> io::Result<()> {
+        let Some(dir) = state_dir else {
+            return Ok(());
+        };
+        let text = toml::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(FILE_NAME), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_survives_a_restart() {
+        let dir = std::env::temp_dir().join("ferrite-session-round-trip");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(SessionState::load(Some(&dir)), None);
+        let session = SessionState {
+            image:      Some(PathBuf::from("/photos/a.png")),
+            zoom:       ZoomState::Custom(2.5),
+            panels:     PanelState {
+                exif: true,
+                histogram: true,
+                ..PanelState::default()
+            },
+            fullscreen: false,
+            window:     Some(WindowGeometry {
+                x:      40.0,
+                y:      30.0,
+                width:  1280.0,
+                height: 720.0,
+            }),
+        };
+        session.save(Some(&dir)).unwrap();
+        assert_eq!(SessionState::load(Some(&dir)), Some(session));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_partial_or_broken_sessions() {
+        let dir = std::env::temp_dir().join("ferrite-session-partial");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // Fields added later fall back to their defaults
+        fs::write(dir.join(FILE_NAME), "zoom = \"one_to_one\"\n").unwrap();
+        assert_eq!(
+            SessionState::load(Some(&dir)),
+            Some(SessionState {
+                zoom: ZoomState::OneToOne,
+                ..SessionState::default()
+            })
+        );
+
+        fs::write(dir.join(FILE_NAME), "zoom = [").unwrap();
+        assert_eq!(SessionState::load(Some(&dir)), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+//! Slideshow order through the folder, and Ken Burns motion: a slow zoom
+//! and pan across a photo while it is shown.
+
+use fastrand::Rng;
+use std::path::{Path, PathBuf};
+
+/// Which image follows the current one during a slideshow
+#[derive(Debug, Default)]
+pub struct SlideOrder {
+    /// Shuffled images not shown yet in this round
+    queue:    Vec<PathBuf>,
+    /// Whether a shuffled round was dealt since the slideshow started
+    shuffled: bool,
+}
+
+impl SlideOrder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts over, e.g. when the slideshow is started again.
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.shuffled = false;
+    }
+
+    /// The image to show after `current`, or `None` when the slideshow is
+    /// over. Shuffling shows every image once per round in random order;
+    /// with `repeat` the folder starts over after its last image.
+    pub fn next(
+        &mut self,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+        shuffle: bool,
+        repeat: bool,
+        rng: &mut Rng,
+    ) -> Option<PathBuf> {
+        if folder.is_empty() {
+            return None;
+        }
+        if !shuffle {
+            let index = current
+                .and_then(|current| folder.iter().position(|p| p == current));
+            return match index {
+                Some(index) if index + 1 < folder.len() => {
+                    Some(folder[index + 1].clone())
+                },
+                Some(_) if !repeat => None,
+                _ => Some(folder[0].clone()),
+            };
+        }
+
+        // Files may have gone since the round was dealt
+        self.queue.retain(|path| folder.contains(path));
+        if self.queue.is_empty() {
+            if self.shuffled && !repeat {
+                return None;
+            }
+            // The image on screen started this round
+            self.queue = folder
+                .iter()
+                .filter(|path| Some(path.as_path()) != current)
+                .cloned()
+                .collect();
+            rng.shuffle(&mut self.queue);
+            self.shuffled = true;
+        }
+        self.queue.pop()
+    }
+}
+
+/// How much of the image is shown and where, relative to fitting it into
+/// the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Framing {
+    /// Zoom relative to the fitted size, at least 1
+    pub scale:  f32,
+    /// Position within the range the zoomed image can be panned without
+    /// uncovering more background than the fitted image, from -1 to 1
+    pub center: (f32, f32),
+}
+
+impl Framing {
+    /// Zoom level and pan offset that show this framing of an image of
+    /// `image` pixels in a window of `viewport` points.
+    pub fn view(
+        &self,
+        image: (f32, f32),
+        viewport: (f32, f32),
+    ) -> (f64, (f32, f32)) {
+        let fit = (viewport.0 / image.0).min(viewport.1 / image.1);
+        let zoom = fit * self.scale;
+        let slack = |image: f32, viewport: f32| {
+            ((image * zoom - viewport) / 2.0).max(0.0)
+        };
+        (
+            zoom as f64,
+            (
+                self.center.0 * slack(image.0, viewport.0),
+                self.center.1 * slack(image.1, viewport.1),
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KenBurns {
+    from: Framing,
+    to:   Framing,
+}
+
+impl KenBurns {
+    /// A random move that either pushes in or pulls out by 10–25% while
+    /// drifting between two points away from the edges.
+    pub fn random(rng: &mut Rng) -> Self {
+        let mut point = || (rng.f32() * 1.6 - 0.8, rng.f32() * 1.6 - 0.8);
+        let (start, end) = (point(), point());
+        let wide = Framing {
+            scale: 1.0 + rng.f32() * 0.05, center: start
+        };
+        let close = Framing {
+            scale: 1.1 + rng.f32() * 0.15, center: end
+        };
+        if rng.bool() {
+            Self {
+                from: wide, to: close
+            }
+        } else {
+            Self {
+                from: close, to: wide
+            }
+        }
+    }
+
+    /// Framing at `progress` from 0 to 1 through the slide, eased at both
+    /// ends.
+    pub fn at(&self, progress: f32) -> Framing {
+        let t = progress.clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Framing {
+            scale:  mix(self.from.scale, self.to.scale),
+            center: (
+                mix(self.from.center.0, self.to.center.0),
+                mix(self.from.center.1, self.to.center.1),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder() -> Vec<PathBuf> {
+        ["a.png", "b.png", "c.png", "d.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_order_stops_or_repeats_at_the_end() {
+        let folder = folder();
+        let mut order = SlideOrder::new();
+        let mut rng = Rng::with_seed(1);
+        let mut next = |current: &str, repeat| {
+            order.next(
+                &folder,
+                Some(Path::new(current)),
+                false,
+                repeat,
+                &mut rng,
+            )
+        };
+        assert_eq!(next("b.png", false), Some(PathBuf::from("c.png")));
+        assert_eq!(next("d.png", false), None);
+        assert_eq!(next("d.png", true), Some(PathBuf::from("a.png")));
+    }
+
+    #[test]
+    fn test_shuffle_shows_each_image_once_per_round() {
+        let folder = folder();
+        let mut order = SlideOrder::new();
+        let mut rng = Rng::with_seed(3);
+        let mut current = folder[0].clone();
+        let mut shown = vec![current.clone()];
+        while let Some(next) =
+            order.next(&folder, Some(&current), true, false, &mut rng)
+        {
+            shown.push(next.clone());
+            current = next;
+        }
+        shown.sort();
+        assert_eq!(shown, folder);
+
+        // Repeating deals a new round instead of stopping
+        assert!(order
+            .next(&folder, Some(&current), true, true, &mut rng)
+            .is_some());
+    }
+
+    #[test]
+    fn test_motion_stays_within_the_image() {
+        let mut rng = Rng::with_seed(7);
+        for _ in 0..50 {
+            let motion = KenBurns::random(&mut rng);
+            assert_eq!(motion.at(0.0), motion.from);
+            assert!((motion.at(1.0).scale - motion.to.scale).abs() < 1e-6);
+
+            for step in 0..=10 {
+                let framing = motion.at(step as f32 / 10.0);
+                assert!(framing.scale >= 1.0);
+                let (zoom, offset) =
+                    framing.view((3000.0, 2000.0), (800.0, 600.0));
+                let shown = (3000.0 * zoom as f32, 2000.0 * zoom as f32);
+                // The image never moves further than its overhang
+                assert!(
+                    offset.0.abs() <= ((shown.0 - 800.0) / 2.0).max(0.0) + 1e-3
+                );
+                assert!(
+                    offset.1.abs() <= ((shown.1 - 600.0) / 2.0).max(0.0) + 1e-3
+                );
+            }
+        }
+    }
+}
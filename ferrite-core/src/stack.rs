@@ -0,0 +1,297 @@
+//! Stacking of repeated exposures of the same scene: frames are aligned to
+//! the first one and combined per pixel, which averages out sensor noise.
+//!
+//! Alignment only corrects translation, found by a coarse-to-fine search on
+//! grayscale pyramids. That covers tripod drift and handheld bursts; frames
+//! that were rotated against each other are not supported.
+
+use ferrite_config::{DecodingConfig, IoConfig};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage,
+    GenericImageView,
+    GrayImage,
+    ImageBuffer,
+    Rgba,
+};
+use rayon::prelude::*;
+use std::path::PathBuf;
+use thiserror::Error;
+use tracing::info;
+
+use crate::image::{self as images, ImageLoadError};
+
+/// Longer side of the coarsest pyramid level
+const COARSEST_SIZE: u32 = 128;
+/// Search radius at the coarsest level, in its pixels
+const COARSE_RADIUS: i32 = 8;
+/// Pixels compared per candidate shift at most
+const COST_SAMPLES: u32 = 65_536;
+
+#[derive(Error, Debug)]
+pub enum StackError {
+    #[error("Stacking needs at least two frames")]
+    TooFewFrames,
+
+    #[error("Frame is {found:?}, expected {expected:?} like the first frame")]
+    SizeMismatch { expected: (u32, u32), found: (u32, u32) },
+
+    #[error(transparent)]
+    Load(#[from] ImageLoadError),
+}
+
+/// How the aligned frames are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StackMethod {
+    #[default]
+    Mean,
+    /// Slower, but drops outliers such as satellites or passing people
+    Median,
+}
+
+impl StackMethod {
+    pub const ALL: [StackMethod; 2] = [StackMethod::Mean, StackMethod::Median];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StackMethod::Mean => "Mean",
+            StackMethod::Median => "Median",
+        }
+    }
+}
+
+/// Decodes the files and stacks them with [`stack`].
+pub fn stack_files(
+    paths: &[PathBuf],
+    method: StackMethod,
+    align: bool,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+) -> Result<DynamicImage, StackError> {
+    let frames = paths
+        .par_iter()
+        .map(|path| images::decode_file(path, decoding, io))
+        .collect::<Result<Vec<_>, _>>()?;
+    stack(&frames, method, align)
+}
+
+/// Aligns `frames` to the first one and combines them. The result has the
+/// size of the first frame; where shifted frames do not cover it, only the
+/// frames that do are used.
+pub fn stack(
+    frames: &[DynamicImage],
+    method: StackMethod,
+    align: bool,
+) -> Result<DynamicImage, StackError> {
+    let [reference, rest @ ..] = frames else {
+        return Err(StackError::TooFewFrames);
+    };
+    if rest.is_empty() {
+        return Err(StackError::TooFewFrames);
+    }
+    let size = reference.dimensions();
+    if let Some(frame) = rest
+        .iter()
+        .find(|frame| frame.dimensions() != size)
+    {
+        return Err(StackError::SizeMismatch {
+            expected: size,
+            found:    frame.dimensions(),
+        });
+    }
+
+    let shifts: Vec<(i32, i32)> = if align {
+        let reference = pyramid(reference);
+        std::iter::once((0, 0))
+            .chain(rest.iter().map(|frame| {
+                let shift = estimate_shift(&reference, &pyramid(frame));
+                info!("Frame shifted by {:?}", shift);
+                shift
+            }))
+            .collect()
+    } else {
+        vec![(0, 0); frames.len()]
+    };
+
+    // 16 bits keep the extra precision the averaging gains
+    let frames: Vec<_> = frames
+        .iter()
+        .map(DynamicImage::to_rgba16)
+        .collect();
+    let (width, height) = size;
+    let mut result = ImageBuffer::<Rgba<u16>, _>::new(width, height);
+    result
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let mut samples = Vec::with_capacity(frames.len());
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                samples.clear();
+                for (frame, &(dx, dy)) in frames.iter().zip(&shifts) {
+                    let (sx, sy) = (x as i64 + dx as i64, y as i64 + dy as i64);
+                    if (0..width as i64).contains(&sx)
+                        && (0..height as i64).contains(&sy)
+                    {
+                        let Rgba(p) = frame.get_pixel(sx as u32, sy as u32);
+                        samples.push(Rgba(p.map(f32::from)));
+                    }
+                }
+                let Rgba(value) = combine(&mut samples, method);
+                pixel.copy_from_slice(&value.map(|v| v.round() as u16));
+            }
+        });
+
+    Ok(DynamicImage::ImageRgba16(result))
+}
+
+fn combine(samples: &mut [Rgba<f32>], method: StackMethod) -> Rgba<f32> {
+    let mut out = [0.0; 4];
+    match method {
+        StackMethod::Mean => {
+            for sample in samples.iter() {
+                for (o, v) in out.iter_mut().zip(sample.0) {
+                    *o += v;
+                }
+            }
+            out.iter_mut()
+                .for_each(|o| *o /= samples.len() as f32);
+        },
+        StackMethod::Median => {
+            let mut channel = Vec::with_capacity(samples.len());
+            for (c, o) in out.iter_mut().enumerate() {
+                channel.clear();
+                channel.extend(samples.iter().map(|s| s.0[c]));
+                channel.sort_by(f32::total_cmp);
+                let middle = channel.len() / 2;
+                *o = if channel.len() % 2 == 0 {
+                    (channel[middle - 1] + channel[middle]) / 2.0
+                } else {
+                    channel[middle]
+                };
+            }
+        },
+    }
+    Rgba(out)
+}
+
+/// Grayscale levels from full size down to about `COARSEST_SIZE`
+fn pyramid(image: &DynamicImage) -> Vec<GrayImage> {
+    let mut levels = vec![image.to_luma8()];
+    while let Some(last) = levels.last() {
+        if last.width().max(last.height()) <= COARSEST_SIZE {
+            break;
+        }
+        let next = imageops::resize(
+            last,
+            (last.width() / 2).max(1),
+            (last.height() / 2).max(1),
+            FilterType::Triangle,
+        );
+        levels.push(next);
+    }
+    levels
+}
+
+/// Offset `(dx, dy)` such that `frame(x + dx, y + dy)` shows what
+/// `reference(x, y)` does.
+fn estimate_shift(reference: &[GrayImage], frame: &[GrayImage]) -> (i32, i32) {
+    let mut shift = (0, 0);
+    for (level, (a, b)) in reference.iter().zip(frame).enumerate().rev() {
+        let coarsest = level == reference.len() - 1;
+        let radius = if coarsest { COARSE_RADIUS } else { 2 };
+        if !coarsest {
+            shift = (shift.0 * 2, shift.1 * 2);
+        }
+        let center = shift;
+        let mut best = f32::MAX;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let candidate = (center.0 + dx, center.1 + dy);
+                let cost = difference(a, b, candidate);
+                if cost < best {
+                    best = cost;
+                    shift = candidate;
+                }
+            }
+        }
+    }
+    shift
+}
+
+/// Mean absolute difference of the overlapping part at `shift`
+fn difference(a: &GrayImage, b: &GrayImage, shift: (i32, i32)) -> f32 {
+    let (width, height) = (a.width() as i32, a.height() as i32);
+    let x_range = 0.max(-shift.0)..width.min(width - shift.0);
+    let y_range = 0.max(-shift.1)..height.min(height - shift.1);
+    if x_range.len() < 8 || y_range.len() < 8 {
+        return f32::MAX;
+    }
+    let area = (x_range.len() * y_range.len()) as f32;
+    let step = ((area / COST_SAMPLES as f32).sqrt().ceil() as usize).max(1);
+
+    let mut total = 0u64;
+    let mut count = 0u64;
+    for y in y_range.step_by(step) {
+        for x in x_range.clone().step_by(step) {
+            let p = a.get_pixel(x as u32, y as u32)[0];
+            let q = b.get_pixel((x + shift.0) as u32, (y + shift.1) as u32)[0];
+            total += p.abs_diff(q) as u64;
+            count += 1;
+        }
+    }
+    total as f32 / count as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, RgbaImage};
+
+    /// Smooth blobs, so every pyramid level has something to match
+    fn scene(width: u32, height: u32, offset: (i32, i32)) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let (x, y) =
+                (x as f32 + offset.0 as f32, y as f32 + offset.1 as f32);
+            let v =
+                (x / 23.0).sin() * (y / 17.0).cos() + (x * y / 5000.0).sin();
+            Luma([(128.0 + v * 60.0) as u8])
+        })
+    }
+
+    #[test]
+    fn test_estimate_shift() {
+        let reference = DynamicImage::ImageLuma8(scene(400, 300, (0, 0)));
+        // The frame's content sits 13 px left and 5 px down of the reference
+        let frame = DynamicImage::ImageLuma8(scene(400, 300, (13, -5)));
+        assert_eq!(
+            estimate_shift(&pyramid(&reference), &pyramid(&frame)),
+            (-13, 5)
+        );
+    }
+
+    #[test]
+    fn test_median_rejects_outlier() {
+        let gray = |v| {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                4,
+                4,
+                Rgba([v, v, v, 255]),
+            ))
+        };
+        let frames = [gray(100), gray(102), gray(255)];
+
+        let median = stack(&frames, StackMethod::Median, false)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(median.get_pixel(1, 1), &Rgba([102, 102, 102, 255]));
+        let mean = stack(&frames, StackMethod::Mean, false)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(mean.get_pixel(1, 1), &Rgba([152, 152, 152, 255]));
+
+        assert!(matches!(
+            stack(&frames[..1], StackMethod::Mean, false),
+            Err(StackError::TooFewFrames)
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+//! Levelling photos: finding the tilt of the horizon or of vertical lines,
+//! and rotating by an arbitrary angle with the empty corners cropped away.
+//!
+//! Angles are in degrees, positive clockwise as seen on screen.
+
+use image::{DynamicImage, GrayImage, Rgba32FImage};
+use rayon::prelude::*;
+
+/// Largest tilt looked for; steeper lines are taken to be intentional
+pub const MAX_ANGLE: f32 = 15.0;
+
+/// Longest side images are analysed at
+const ANALYSIS_SIZE: u32 = 512;
+/// Candidate angles per degree
+const STEPS_PER_DEGREE: f32 = 10.0;
+
+/// Edge pixel of the analysed image, turned so that near-vertical edges
+/// line up like near-horizontal ones
+struct EdgePoint {
+    x:      f32,
+    y:      f32,
+    weight: f32,
+}
+
+/// Suggests the rotation that levels the dominant near-horizontal and
+/// near-vertical lines of `image`, or `None` if it has no clear ones.
+///
+/// Edge pixels are projected across each candidate angle; at the tilt of
+/// the lines they fall into few bins, so the sum of squared bin weights
+/// peaks there.
+pub fn detect(image: &DynamicImage) -> Option<f32> {
+    let gray = if image.width().max(image.height()) > ANALYSIS_SIZE {
+        image
+            .thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE)
+            .to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    let points = edge_points(&gray);
+    if points.is_empty() {
+        return None;
+    }
+
+    let steps = (2.0 * MAX_ANGLE * STEPS_PER_DEGREE) as usize + 1;
+    let angle = |step: usize| step as f32 / STEPS_PER_DEGREE - MAX_ANGLE;
+    let extent = gray.width().max(gray.height()) as f32 * 2.0;
+    let scores: Vec<f32> = (0..steps)
+        .into_par_iter()
+        .map(|step| {
+            let (sin, cos) = angle(step).to_radians().sin_cos();
+            let mut bins = vec![0.0f32; extent as usize * 2 + 1];
+            for point in &points {
+                let rho = point.y * cos - point.x * sin + extent;
+                bins[rho.round() as usize] += point.weight;
+            }
+            bins.iter().map(|w| w * w).sum()
+        })
+        .collect();
+
+    let (best, &score) = scores
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    // Without straight lines no angle stands out
+    let mut sorted = scores.clone();
+    sorted.sort_by(f32::total_cmp);
+    if score < sorted[steps / 2] * 1.5 {
+        return None;
+    }
+
+    // Place the peak between candidates with a parabola through its
+    // neighbours
+    let offset = match (best.checked_sub(1), scores.get(best + 1)) {
+        (Some(before), Some(&after)) => {
+            let before = scores[before];
+            let curvature = before - 2.0 * score + after;
+            if curvature < 0.0 {
+                0.5 * (before - after) / curvature
+            } else {
+                0.0
+            }
+        },
+        _ => 0.0,
+    };
+    let tilt = angle(best) + offset / STEPS_PER_DEGREE;
+    Some(-tilt)
+}
+
+/// Strong edges within [`MAX_ANGLE`] of horizontal or vertical, weighted
+/// by their gradient
+fn edge_points(gray: &GrayImage) -> Vec<EdgePoint> {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return Vec::new();
+    }
+    let at = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f32;
+    (1..height - 1)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            (1..width - 1).filter_map(move |x| {
+                // Sobel gradient
+                let gx =
+                    at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                        - at(x - 1, y - 1)
+                        - 2.0 * at(x - 1, y)
+                        - at(x - 1, y + 1);
+                let gy =
+                    at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                        - at(x - 1, y - 1)
+                        - 2.0 * at(x, y - 1)
+                        - at(x + 1, y - 1);
+                let weight = (gx * gx + gy * gy).sqrt();
+                // Ignore noise and soft shading
+                if weight < 64.0 {
+                    return None;
+                }
+                // The edge runs across its gradient
+                let direction = gx.atan2(-gy).to_degrees().rem_euclid(180.0);
+                let (x, y) = (x as f32, y as f32);
+                match direction {
+                    d if !(MAX_ANGLE..=180.0 - MAX_ANGLE).contains(&d) => {
+                        Some(EdgePoint {
+                            x,
+                            y,
+                            weight,
+                        })
+                    },
+                    // A clockwise tilt turns vertical lines the same way
+                    d if (d - 90.0).abs() < MAX_ANGLE => Some(EdgePoint {
+                        x: y,
+                        y: -x,
+                        weight,
+                    }),
+                    _ => None,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Share of each side left after rotating `width`×`height` by `degrees`
+/// and cropping to the largest centered rectangle of the same shape
+pub fn crop_scale(width: u32, height: u32, degrees: f32) -> f32 {
+    let (sin, cos) = degrees.to_radians().abs().sin_cos();
+    let (w, h) = (width as f32, height as f32);
+    (w / (w * cos + h * sin)).min(h / (w * sin + h * cos))
+}
+
+/// Rotates `image` clockwise by `degrees`, keeping its aspect ratio and
+/// cropping the corners the rotation left empty.
+pub fn rotate(image: &DynamicImage, degrees: f32) -> DynamicImage {
+    if degrees == 0.0 {
+        return image.clone();
+    }
+    let (width, height) = (image.width(), image.height());
+    let scale = crop_scale(width, height, degrees);
+    let out_width = ((width as f32 * scale).floor() as u32).max(1);
+    let out_height = ((height as f32 * scale).floor() as u32).max(1);
+
+    let source = image.to_rgba32f();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (ox, oy) = (out_width as f32 / 2.0, out_height as f32 / 2.0);
+    let mut data = vec![0.0; out_width as usize * out_height as usize * 4];
+    data.par_chunks_mut(out_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, out) in row.chunks_exact_mut(4).enumerate() {
+                // Turn back by the rotation to find the source position
+                let dx = x as f32 + 0.5 - ox;
+                let dy = y as f32 + 0.5 - oy;
+                let sx = cx + dx * cos + dy * sin;
+                let sy = cy - dx * sin + dy * cos;
+                out.copy_from_slice(&bilinear(&source, sx - 0.5, sy - 0.5));
+            }
+        });
+    let rotated = DynamicImage::ImageRgba32F(
+        Rgba32FImage::from_raw(out_width, out_height, data)
+            .expect("buffer matches dimensions"),
+    );
+
+    let alpha = image.color().has_alpha();
+    match image {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => {
+            if alpha {
+                DynamicImage::ImageRgba8(rotated.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(rotated.to_rgb8())
+            }
+        },
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            if alpha {
+                rotated
+            } else {
+                DynamicImage::ImageRgb32F(rotated.to_rgb32f())
+            }
+        },
+        _ => {
+            if alpha {
+                DynamicImage::ImageRgba16(rotated.to_rgba16())
+            } else {
+                DynamicImage::ImageRgb16(rotated.to_rgb16())
+            }
+        },
+    }
+}
+
+/// Samples `image` at pixel coordinates `x`, `y`, clamping at the edges
+fn bilinear(image: &Rgba32FImage, x: f32, y: f32) -> [f32; 4] {
+    let max_x = image.width() as f32 - 1.0;
+    let max_y = image.height() as f32 - 1.0;
+    let (x, y) = (x.clamp(0.0, max_x), y.clamp(0.0, max_y));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (x1, y1) = ((x0 + 1.0).min(max_x), (y0 + 1.0).min(max_y));
+    let (fx, fy) = (x - x0, y - y0);
+    let pixel = |x: f32, y: f32| image.get_pixel(x as u32, y as u32).0;
+    let (a, b) = (pixel(x0, y0), pixel(x1, y0));
+    let (c, d) = (pixel(x0, y1), pixel(x1, y1));
+    std::array::from_fn(|i| {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        top + (bottom - top) * fy
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, RgbImage};
+
+    /// Sky above a smooth horizon tilted clockwise by `degrees`
+    fn horizon(degrees: f32) -> DynamicImage {
+        let slope = degrees.to_radians().tan();
+        DynamicImage::ImageLuma8(GrayImage::from_fn(400, 300, |x, y| {
+            let horizon = 150.0 + (x as f32 + 0.5 - 200.0) * slope;
+            let sky = (horizon - y as f32).clamp(0.0, 1.0);
+            Luma([(40.0 + 180.0 * sky) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_detects_tilted_horizon() {
+        for tilt in [-4.0, 2.5, 9.0] {
+            // Turned a quarter, the horizon is a tilted vertical edge
+            for image in [horizon(tilt), horizon(tilt).rotate90()] {
+                let suggestion = detect(&image).unwrap();
+                assert!(
+                    (suggestion + tilt).abs() < 0.3,
+                    "{}: {}",
+                    tilt,
+                    suggestion
+                );
+            }
+        }
+        let flat = DynamicImage::ImageLuma8(GrayImage::new(64, 64));
+        assert_eq!(detect(&flat), None);
+    }
+
+    #[test]
+    fn test_rotation_crops_to_the_same_shape() {
+        let image =
+            DynamicImage::ImageRgb8(RgbImage::from_fn(200, 100, |x, _| {
+                image::Rgb([(x % 256) as u8, 0, 0])
+            }));
+        assert_eq!(rotate(&image, 0.0).width(), 200);
+
+        let rotated = rotate(&image, 10.0);
+        assert!(matches!(rotated, DynamicImage::ImageRgb8(_)));
+        let scale = crop_scale(200, 100, 10.0);
+        assert_eq!(rotated.width(), (200.0 * scale) as u32);
+        assert_eq!(rotated.height(), (100.0 * scale) as u32);
+        // The center stays in place
+        let center = rotated
+            .to_rgb8()
+            .get_pixel(rotated.width() / 2, rotated.height() / 2)[0];
+        assert!((center as i32 - 100).abs() <= 2, "{}", center);
+
+        // Leveling what detection found undoes the tilt
+        let level = rotate(&horizon(5.0), detect(&horizon(5.0)).unwrap());
+        assert!(detect(&level).is_none_or(|angle| angle.abs() < 0.3));
+    }
+}
@@ -0,0 +1,390 @@
+//! Moving files to the desktop's trash instead of deleting them, so an
+//! image deleted by mistake can be restored from the file manager.
+//!
+//! Free desktops get the trash of the freedesktop.org trash specification
+//! directly: the home trash for files on the same file system, and the
+//! trash at the top of their own file system for the others, such as USB
+//! drives, so nothing is ever copied between the two. macOS goes through
+//! Finder and Windows through the recycle bin API of PowerShell, both
+//! reading the path from the environment so it needs no quoting.
+//!
+//! This stands in for the `trash` crate, which is not vendored for our
+//! builds; the specification is small enough to follow here, and the
+//! platform tools cover the rest without pulling in their bindings.
+
+use std::{io, path::Path};
+use thiserror::Error;
+use tracing::{info, instrument};
+
+#[derive(Error, Debug)]
+pub enum TrashError {
+    #[error("Failed to move to the trash: {0}")]
+    Io(#[from] io::Error),
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[error("No trash folder: neither XDG_DATA_HOME nor HOME is set")]
+    NoTrash,
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[error("No trash folder can be made on the file system of {0}")]
+    NoTrashOn(std::path::PathBuf),
+
+    #[cfg(any(target_os = "macos", windows))]
+    #[error("Moving to the trash failed: {0}")]
+    Command(String),
+
+    #[cfg(not(any(unix, windows)))]
+    #[error("Moving to the trash is not supported on this platform")]
+    Unsupported,
+}
+
+/// Moves the file at `path` to the trash.
+#[instrument]
+pub fn move_to_trash(path: &Path) -> Result<(), TrashError> {
+    let path = std::path::absolute(path)?;
+    // Only links themselves go to the trash, not what they point to
+    path.symlink_metadata()?;
+    platform::move_to_trash(&path)?;
+    info!("Moved {} to the trash", path.display());
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::{
+        env,
+        ffi::OsString,
+        fs::{self, DirBuilder, OpenOptions},
+        io::{self, Write},
+        os::unix::fs::{DirBuilderExt, MetadataExt},
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    use super::TrashError;
+    use crate::{rename::Timestamp, uri};
+
+    pub fn move_to_trash(path: &Path) -> Result<(), TrashError> {
+        let trash = trash_for(path)?;
+        trash_into(path, &trash, SystemTime::now())?;
+        Ok(())
+    }
+
+    /// The home trash if `path` is on its file system, or else the trash at
+    /// the top of the file system of `path`
+    fn trash_for(path: &Path) -> Result<PathBuf, TrashError> {
+        let home = home_trash().ok_or(TrashError::NoTrash)?;
+        let device = path.symlink_metadata()?.dev();
+        // The home trash may not exist yet; its nearest existing folder
+        // tells where it would go
+        let home_device = home
+            .ancestors()
+            .find_map(|dir| fs::metadata(dir).ok())
+            .map(|metadata| metadata.dev());
+        if home_device == Some(device) {
+            return Ok(home);
+        }
+        let top = top_dir(path, device);
+        top_trash(&top).ok_or(TrashError::NoTrashOn(top))
+    }
+
+    /// The outermost folder of `path` still on `device`, where the file
+    /// system is mounted
+    fn top_dir(path: &Path, device: u64) -> PathBuf {
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| {
+                fs::metadata(dir).is_ok_and(|metadata| metadata.dev() == device)
+            })
+            .last()
+            .unwrap_or(Path::new("/"))
+            .to_path_buf()
+    }
+
+    /// `$top/.Trash/$uid` where the administrator made a shared `.Trash`
+    /// with the sticky bit, or else `$top/.Trash-$uid`, made for the user
+    /// alone
+    fn top_trash(top: &Path) -> Option<PathBuf> {
+        // SAFETY: getuid has no preconditions and cannot fail
+        let uid = unsafe { libc::getuid() };
+        let shared = top.join(".Trash");
+        let usable = fs::symlink_metadata(&shared).is_ok_and(|metadata| {
+            metadata.is_dir() && metadata.mode() & 0o1000 != 0
+        });
+        if usable {
+            let dir = shared.join(uid.to_string());
+            if private_dir(&dir, uid).is_ok() {
+                return Some(dir);
+            }
+        }
+        let own = top.join(format!(".Trash-{}", uid));
+        private_dir(&own, uid).ok().map(|()| own)
+    }
+
+    /// Creates `dir` for `uid` alone, or checks that it is a real folder of
+    /// theirs
+    fn private_dir(dir: &Path, uid: u32) -> io::Result<()> {
+        match DirBuilder::new().mode(0o700).create(dir) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                return Err(e)
+            },
+            _ => {},
+        }
+        let metadata = fs::symlink_metadata(dir)?;
+        if !metadata.is_dir() || metadata.uid() != uid {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        Ok(())
+    }
+
+    /// `$XDG_DATA_HOME/Trash`, by default under `~/.local/share`
+    fn home_trash() -> Option<PathBuf> {
+        let data = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".local/share"))
+            })?;
+        Some(data.join("Trash"))
+    }
+
+    /// Name of the `n`th file called like `path` in the trash: `photo.jpg`,
+    /// then `photo.2.jpg` and so on
+    fn numbered(path: &Path, n: u32) -> OsString {
+        let name = path.file_name().unwrap_or_default();
+        if n == 1 {
+            return name.to_os_string();
+        }
+        let mut numbered = path.file_stem().unwrap_or(name).to_os_string();
+        numbered.push(format!(".{}", n));
+        if let Some(extension) = path.extension() {
+            numbered.push(".");
+            numbered.push(extension);
+        }
+        numbered
+    }
+
+    /// Moves `path`, which must be absolute, into the trash folder `trash`
+    /// and returns where it went. The info file is created first, which
+    /// reserves the name against other programs trashing at the same time.
+    fn trash_into(
+        path: &Path,
+        trash: &Path,
+        now: SystemTime,
+    ) -> io::Result<PathBuf> {
+        let files = trash.join("files");
+        let info = trash.join("info");
+        fs::create_dir_all(&files)?;
+        fs::create_dir_all(&info)?;
+
+        // Meant to be local time, but the standard library knows no time
+        // zones
+        let time = Timestamp::from_system(now);
+        let date = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            time.year,
+            time.month,
+            time.day,
+            time.hour,
+            time.minute,
+            time.second
+        );
+        let record = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            uri::encode(path),
+            date
+        );
+        for n in 1.. {
+            let name = numbered(path, n);
+            let mut info_name = name.clone();
+            info_name.push(".trashinfo");
+            let info_path = info.join(info_name);
+            let mut file = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            let target = files.join(&name);
+            // Left behind without its info file by some other program
+            if target.symlink_metadata().is_ok() {
+                let _ = fs::remove_file(&info_path);
+                continue;
+            }
+            // The trash is on the file system of `path`, so this never
+            // copies
+            let moved = file
+                .write_all(record.as_bytes())
+                .and_then(|()| fs::rename(path, &target));
+            return match moved {
+                Ok(()) => Ok(target),
+                Err(e) => {
+                    let _ = fs::remove_file(&info_path);
+                    Err(e)
+                },
+            };
+        }
+        unreachable!()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        fn temp_dir(name: &str) -> PathBuf {
+            let dir = env::temp_dir().join(format!(
+                "ferrite-trash-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        fn test_trashed_files_record_where_they_came_from() {
+            let dir = temp_dir("record");
+            let photo = dir.join("holiday photo #1.jpg");
+            fs::write(&photo, b"jpeg").unwrap();
+            let trash = dir.join("Trash");
+            // 2024-02-29 13:05:09 UTC
+            let now = UNIX_EPOCH + Duration::from_secs(1_709_211_909);
+
+            let target = trash_into(&photo, &trash, now).unwrap();
+            assert!(!photo.exists());
+            assert_eq!(target, trash.join("files/holiday photo #1.jpg"));
+            assert_eq!(fs::read(&target).unwrap(), b"jpeg");
+            let info = fs::read_to_string(
+                trash.join("info/holiday photo #1.jpg.trashinfo"),
+            )
+            .unwrap();
+            let path =
+                format!("{}/holiday%20photo%20%231.jpg", uri::encode(&dir));
+            assert_eq!(info.lines().collect::<Vec<_>>(), [
+                "[Trash Info]",
+                &format!("Path={}", path),
+                "DeletionDate=2024-02-29T13:05:09",
+            ]);
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn test_names_already_in_the_trash_are_numbered() {
+            let dir = temp_dir("numbered");
+            let trash = dir.join("Trash");
+            for n in 1..=3 {
+                let photo = dir.join("photo.jpg");
+                fs::write(&photo, [n]).unwrap();
+                trash_into(&photo, &trash, SystemTime::now()).unwrap();
+            }
+            for (name, content) in
+                [("photo.jpg", 1), ("photo.2.jpg", 2), ("photo.3.jpg", 3)]
+            {
+                assert_eq!(
+                    fs::read(trash.join("files").join(name)).unwrap(),
+                    [content]
+                );
+                assert!(trash
+                    .join("info")
+                    .join(format!("{}.trashinfo", name))
+                    .exists());
+            }
+            assert_eq!(numbered(Path::new("/a/notes"), 2), "notes.2");
+
+            // Nothing is reserved for a file that cannot be moved
+            let missing = dir.join("missing.png");
+            assert!(trash_into(&missing, &trash, SystemTime::now()).is_err());
+            assert!(!trash.join("info/missing.png.trashinfo").exists());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn test_top_dir_trash_is_kept_private() {
+            use std::os::unix::fs::PermissionsExt;
+
+            let dir = temp_dir("top");
+            let uid = unsafe { libc::getuid() };
+            let own = top_trash(&dir).unwrap();
+            assert_eq!(own, dir.join(format!(".Trash-{}", uid)));
+            let mode = fs::metadata(&own).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+
+            // A shared trash is only used with the sticky bit set
+            let shared = dir.join(".Trash");
+            fs::create_dir(&shared).unwrap();
+            fs::set_permissions(&shared, fs::Permissions::from_mode(0o777))
+                .unwrap();
+            assert_eq!(top_trash(&dir), Some(own));
+            fs::set_permissions(&shared, fs::Permissions::from_mode(0o1777))
+                .unwrap();
+            assert_eq!(top_trash(&dir), Some(shared.join(uid.to_string())));
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", windows))]
+mod platform {
+    use std::{path::Path, process::Command};
+
+    use super::TrashError;
+
+    /// Environment variable the scripts read the path from
+    const PATH_VARIABLE: &str = "FERRITE_TRASH_PATH";
+
+    #[cfg(target_os = "macos")]
+    pub fn move_to_trash(path: &Path) -> Result<(), TrashError> {
+        run(Command::new("osascript")
+            .args([
+                "-e",
+                "tell application \"Finder\" to delete POSIX file (system \
+                 attribute \"FERRITE_TRASH_PATH\")",
+            ])
+            .env(PATH_VARIABLE, path))
+    }
+
+    #[cfg(windows)]
+    pub fn move_to_trash(path: &Path) -> Result<(), TrashError> {
+        run(Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "Add-Type -AssemblyName Microsoft.VisualBasic; \
+                 [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile($env:\
+                 FERRITE_TRASH_PATH, 'OnlyErrorDialogs', 'SendToRecycleBin')",
+            ])
+            .env(PATH_VARIABLE, path))
+    }
+
+    fn run(command: &mut Command) -> Result<(), TrashError> {
+        let output = command.output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(TrashError::Command(
+                String::from_utf8_lossy(&output.stderr)
+                    .trim()
+                    .to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::path::Path;
+
+    use super::TrashError;
+
+    pub fn move_to_trash(_path: &Path) -> Result<(), TrashError> {
+        Err(TrashError::Unsupported)
+    }
+}
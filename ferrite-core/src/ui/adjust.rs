@@ -0,0 +1,168 @@
+use eframe::egui::{self, Context};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+use crate::{
+    adjust::Adjustments,
+    export::{self, ExportEvent, ExportFormat, ExportOptions, PngCompression},
+    image::{free_derived_path, ImageManager},
+};
+
+/// Brightness, contrast, gamma and saturation sliders. The changes are made
+/// to the texture only, so the file and the decoded image stay untouched
+/// until the adjusted result is exported.
+pub struct AdjustPanel {
+    open:         bool,
+    adjust:       Adjustments,
+    saving:       Option<Receiver<ExportEvent>>,
+    status:       Option<String>,
+    jpeg_quality: u8,
+}
+
+impl AdjustPanel {
+    pub fn new(jpeg_quality: u8) -> Self {
+        Self {
+            open: false,
+            adjust: Adjustments::default(),
+            saving: None,
+            status: None,
+            jpeg_quality,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// What the image on screen is shown with, kept when the panel closes
+    pub fn adjustments(&self) -> Adjustments {
+        self.adjust
+    }
+
+    /// Shows the sliders while open, converting the image again when they
+    /// move. Returns the exported file once it was written.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+    ) -> Option<PathBuf> {
+        let saved = self.poll(ctx);
+        if !self.open {
+            return saved;
+        }
+
+        let before = self.adjust;
+        let mut export = false;
+        let mut open = self.open;
+        egui::Window::new("Adjust")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let adjust = &mut self.adjust;
+                ui.add(
+                    egui::Slider::new(&mut adjust.brightness, -1.0..=1.0)
+                        .text("Brightness"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut adjust.contrast, -1.0..=1.0)
+                        .text("Contrast"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut adjust.gamma, 0.2..=5.0)
+                        .logarithmic(true)
+                        .text("Gamma"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut adjust.saturation, 0.0..=2.0)
+                        .text("Saturation"),
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !adjust.is_neutral(),
+                            egui::Button::new("Reset"),
+                        )
+                        .clicked()
+                    {
+                        *adjust = Adjustments::default();
+                    }
+                    export = ui
+                        .add_enabled(
+                            self.saving.is_none()
+                                && image_manager.current_path().is_some(),
+                            egui::Button::new("Export Adjusted"),
+                        )
+                        .clicked();
+                });
+                if self.saving.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Saving...");
+                    });
+                } else if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+
+        if self.adjust != before {
+            if let Some(image) = image_manager.current_image() {
+                image.texture = None;
+            }
+        }
+        if export {
+            self.export(image_manager);
+        }
+        saved
+    }
+
+    /// Writes the image on screen with the adjustments next to its file, in
+    /// the file's format when it can be written
+    fn export(&mut self, image_manager: &ImageManager) {
+        let (Some(source), Some(image)) =
+            (image_manager.current_path(), image_manager.displayed_image())
+        else {
+            return;
+        };
+        let format = ExportFormat::from_path(source).unwrap_or_default();
+        let path = free_derived_path(source, "adjusted", format.extension());
+        let image = self.adjust.applied_to(image);
+        let options = ExportOptions {
+            format,
+            jpeg_quality: self.jpeg_quality,
+            png_compression: PngCompression::default(),
+        };
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            export::run(image, path, options, sender, &AtomicBool::new(false))
+        });
+        self.status = None;
+        self.saving = Some(events);
+    }
+
+    fn poll(&mut self, ctx: &Context) -> Option<PathBuf> {
+        let events = self.saving.as_ref()?;
+        ctx.request_repaint();
+        let result = events.try_iter().find_map(|event| match event {
+            ExportEvent::Finished(result) => Some(result),
+            ExportEvent::Progress(_) => None,
+        })?;
+        self.saving = None;
+        match result {
+            Ok(path) => {
+                self.status = Some(format!("Saved {}", path.display()));
+                Some(path)
+            },
+            Err(e) => {
+                self.status = Some(format!("Export failed: {}", e));
+                None
+            },
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use eframe::egui::{
+    self,
+    Align2,
+    ColorImage,
+    Context,
+    Key,
+    Rect,
+    TextureHandle,
+};
+use ferrite_config::FerriteConfig;
+use image::RgbaImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Instant,
+};
+use tracing::{info, warn};
+
+use crate::{
+    animation::{self, Animation, AnimationError, LoopCount, Playback},
+    image::ImageManager,
+    ui::render::ImageRenderer,
+};
+
+type Decoded = Result<Option<Animation>, AnimationError>;
+
+struct Playing {
+    source:    PathBuf,
+    frames:    Vec<RgbaImage>,
+    /// Uploaded on first display
+    textures:  Vec<Option<TextureHandle>>,
+    playback:  Playback,
+    last_tick: Instant,
+    /// Frame whose texture is currently shown
+    shown:     usize,
+}
+
+/// Plays animated GIF, WebP and PNG files in place of their first frame,
+/// with pause, frame stepping and speed controls.
+pub struct AnimationPlayer {
+    /// File the frames are being decoded for
+    pending: Option<(PathBuf, Receiver<Decoded>)>,
+    /// Last file checked for frames, animated or not
+    checked: Option<PathBuf>,
+    playing: Option<Playing>,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self {
+            pending: None, checked: None, playing: None
+        }
+    }
+
+    /// Decodes the frames of each newly opened file in the background and
+    /// shows the frame that is due.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        let current = image_manager.current_path().cloned();
+        if current != self.checked {
+            self.checked = current.clone();
+            self.playing = None;
+            self.pending = None;
+            if let Some(path) = current.clone() {
+                if !config.decoding.sandboxed {
+                    self.start(path, config);
+                }
+            }
+        }
+
+        if let Some((source, receiver)) = &self.pending {
+            match receiver.try_recv() {
+                Ok(Ok(Some(animation))) => {
+                    info!(
+                        "{}: {} frames",
+                        source.display(),
+                        animation.frames.len()
+                    );
+                    let count = animation.frames.len();
+                    self.playing = Some(Playing {
+                        source:    source.clone(),
+                        frames:    animation.frames,
+                        textures:  vec![None; count],
+                        playback:  Playback::new(
+                            animation.delays,
+                            animation.loops,
+                        ),
+                        last_tick: Instant::now(),
+                        shown:     0,
+                    });
+                    self.pending = None;
+                },
+                Ok(Ok(None)) => self.pending = None,
+                Ok(Err(e)) => {
+                    warn!("Not animating {}: {}", source.display(), e);
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        let Some(playing) = &mut self.playing else {
+            return;
+        };
+        if current.as_ref() != Some(&playing.source) {
+            return;
+        }
+        self.handle_keys(ctx);
+        let Some(playing) = &mut self.playing else {
+            return;
+        };
+
+        let now = Instant::now();
+        playing.playback.advance(now - playing.last_tick);
+        playing.last_tick = now;
+
+        let frame = playing.playback.frame();
+        if frame != playing.shown {
+            let texture = playing.textures[frame].get_or_insert_with(|| {
+                let image = &playing.frames[frame];
+                ctx.load_texture(
+                    format!("animation-frame-{}", frame),
+                    ColorImage::from_rgba_unmultiplied(
+                        [image.width() as usize, image.height() as usize],
+                        image.as_raw(),
+                    ),
+                    ImageRenderer::texture_options(config),
+                )
+            });
+            image_manager.show_frame(texture.clone());
+            playing.shown = frame;
+        }
+        if let Some(delay) = playing.playback.until_next() {
+            ctx.request_repaint_after(delay);
+        }
+    }
+
+    fn start(&mut self, path: PathBuf, config: &FerriteConfig) {
+        let (sender, receiver) = mpsc::channel();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        let source = path.clone();
+        thread::spawn(move || {
+            let _ =
+                sender.send(animation::decode_file(&source, &decoding, &io));
+        });
+        self.pending = Some((path, receiver));
+    }
+
+    /// Space pauses and resumes, comma and period step a frame back and
+    /// forward, and with Ctrl they slow down and speed up playback.
+    fn handle_keys(&mut self, ctx: &Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let Some(playing) = &mut self.playing else {
+            return;
+        };
+        let playback = &mut playing.playback;
+        ctx.input(|i| {
+            if i.key_pressed(Key::Space) {
+                playback.toggle_pause();
+            }
+            if i.key_pressed(Key::Comma) {
+                if i.modifiers.command {
+                    playback.change_speed(false);
+                } else {
+                    playback.step(false);
+                }
+            }
+            if i.key_pressed(Key::Period) {
+                if i.modifiers.command {
+                    playback.change_speed(true);
+                } else {
+                    playback.step(true);
+                }
+            }
+        });
+    }
+
+    /// Draws the playback bar at the bottom of `viewport`.
+    /// Whether an animation is on screen, taking the playback keys
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some()
+    }
+
+    pub fn render_controls(&mut self, ctx: &Context, viewport: Rect) {
+        let Some(playing) = &mut self.playing else {
+            return;
+        };
+        let playback = &mut playing.playback;
+        egui::Area::new("animation-controls")
+            .fixed_pos(viewport.center_bottom())
+            .pivot(Align2::CENTER_BOTTOM)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Prev")
+                            .on_hover_text("Previous frame (,)")
+                            .clicked()
+                        {
+                            playback.step(false);
+                        }
+                        let label =
+                            if playback.is_paused() || playback.is_finished() {
+                                "Play"
+                            } else {
+                                "Pause"
+                            };
+                        if ui
+                            .button(label)
+                            .on_hover_text("Pause / resume (Space)")
+                            .clicked()
+                        {
+                            playback.toggle_pause();
+                        }
+                        if ui
+                            .button("Next")
+                            .on_hover_text("Next frame (.)")
+                            .clicked()
+                        {
+                            playback.step(true);
+                        }
+                        ui.label(format!(
+                            "{}/{}",
+                            playback.frame() + 1,
+                            playback.frame_count()
+                        ));
+
+                        ui.separator();
+                        if ui
+                            .small_button("−")
+                            .on_hover_text("Slower (Ctrl+,)")
+                            .clicked()
+                        {
+                            playback.change_speed(false);
+                        }
+                        ui.label(format!("{}×", playback.speed()));
+                        if ui
+                            .small_button("+")
+                            .on_hover_text("Faster (Ctrl+.)")
+                            .clicked()
+                        {
+                            playback.change_speed(true);
+                        }
+
+                        ui.separator();
+                        ui.label(match playback.loops() {
+                            LoopCount::Infinite => "Loops forever".to_string(),
+                            LoopCount::Plays(1) => "Plays once".to_string(),
+                            LoopCount::Plays(n) => format!("Plays {} times", n),
+                        });
+                    });
+                });
+            });
+    }
+}
@@ -0,0 +1,338 @@
+use eframe::egui::{self, Color32, Context, RichText, Ui};
+use ferrite_config::FerriteConfig;
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use super::rename::RenameTool;
+use crate::batch::{self, BatchEvent, BatchJob, OutputFormat, Rotation};
+
+const DEFAULT_RESIZE: u32 = 2048;
+
+#[derive(PartialEq)]
+enum Tab {
+    Process,
+    Rename,
+}
+
+/// Window for running resize, rotate, watermark and convert steps over a
+/// selection of files.
+pub struct BatchPanel {
+    open:        bool,
+    tab:         Tab,
+    selection:   BTreeSet<PathBuf>,
+    job:         BatchJob,
+    resize:      bool,
+    resize_to:   u32,
+    running:     Option<Receiver<BatchEvent>>,
+    /// Progress of the current or last batch
+    total:       usize,
+    done:        usize,
+    failures:    Vec<(PathBuf, String)>,
+    last_output: Option<PathBuf>,
+    rename:      RenameTool,
+}
+
+impl BatchPanel {
+    pub fn new(config: &FerriteConfig) -> Self {
+        Self {
+            open:        false,
+            tab:         Tab::Process,
+            selection:   BTreeSet::new(),
+            job:         BatchJob::default(),
+            resize:      false,
+            resize_to:   DEFAULT_RESIZE,
+            running:     None,
+            total:       0,
+            done:        0,
+            failures:    Vec::new(),
+            last_output: None,
+            rename:      RenameTool::new(&config.batch.rename_template),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window with `paths` as the selection.
+    pub fn open_with(&mut self, paths: Vec<PathBuf>) {
+        if !self.is_running() {
+            self.selection = paths.into_iter().collect();
+        }
+        self.open = true;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Fraction of the files done while a batch runs
+    pub fn progress(&self) -> Option<f32> {
+        self.is_running()
+            .then(|| self.done as f32 / self.total.max(1) as f32)
+    }
+
+    fn start(&mut self, config: &FerriteConfig) {
+        let paths: Vec<PathBuf> = self.selection.iter().cloned().collect();
+        let mut job = self.job.clone();
+        job.resize = self.resize.then_some(self.resize_to);
+        job.linear_light = config.color.linear_light;
+        let (sender, events) = mpsc::channel();
+        let batch_config = config.batch.clone();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+
+        self.total = paths.len();
+        self.done = 0;
+        self.failures.clear();
+        self.last_output = None;
+        self.running = Some(events);
+        thread::spawn(move || {
+            batch::run(paths, job, batch_config, decoding, io, sender);
+        });
+    }
+
+    /// Collects progress from a running batch.
+    pub fn update(&mut self, ctx: &Context) {
+        let Some(events) = &self.running else {
+            return;
+        };
+        let mut finished = false;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                BatchEvent::Done {
+                    output,
+                } => {
+                    self.done += 1;
+                    self.last_output = Some(output);
+                },
+                BatchEvent::Failed {
+                    source,
+                    error,
+                } => {
+                    self.done += 1;
+                    self.failures.push((source, error));
+                },
+                BatchEvent::Finished => finished = true,
+            }
+        }
+        if finished {
+            self.running = None;
+        } else {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Shows the window. Returns the old and new paths of renamed files.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+    ) -> Option<Vec<(PathBuf, PathBuf)>> {
+        if !self.open {
+            return None;
+        }
+
+        let mut open = self.open;
+        let mut renamed = None;
+        egui::Window::new("Batch Operations")
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(!self.is_running(), |ui| {
+                    self.render_selection(ui, folder);
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Process, "Process");
+                    ui.selectable_value(&mut self.tab, Tab::Rename, "Rename");
+                });
+                ui.separator();
+
+                match self.tab {
+                    Tab::Process => self.render_process(ui, config),
+                    Tab::Rename => {
+                        let selection: Vec<PathBuf> =
+                            self.selection.iter().cloned().collect();
+                        ui.add_enabled_ui(!self.is_running(), |ui| {
+                            renamed = self.rename.render(ui, &selection);
+                        });
+                    },
+                }
+            });
+        self.open = open;
+
+        if let Some(renamed) = &renamed {
+            for (old, new) in renamed {
+                if self.selection.remove(old) {
+                    self.selection.insert(new.clone());
+                }
+            }
+        }
+        renamed
+    }
+
+    fn render_process(&mut self, ui: &mut Ui, config: &FerriteConfig) {
+        ui.add_enabled_ui(!self.is_running(), |ui| {
+            self.render_steps(ui, config);
+        });
+
+        ui.separator();
+        let can_run = !self.selection.is_empty() && !self.is_running();
+        if ui
+            .add_enabled(can_run, egui::Button::new("Run"))
+            .clicked()
+        {
+            self.start(config);
+        }
+        self.render_progress(ui);
+    }
+
+    fn render_selection(&mut self, ui: &mut Ui, folder: &[PathBuf]) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} files selected", self.selection.len()));
+            if ui.small_button("All").clicked() {
+                self.selection = folder.iter().cloned().collect();
+            }
+            if ui.small_button("None").clicked() {
+                self.selection.clear();
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(160.0)
+            .show(ui, |ui| {
+                // Selected files from other folders stay listed
+                let listed: BTreeSet<PathBuf> = folder
+                    .iter()
+                    .chain(self.selection.iter())
+                    .cloned()
+                    .collect();
+                for path in listed {
+                    let mut selected = self.selection.contains(&path);
+                    let name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy();
+                    if ui.checkbox(&mut selected, name).changed() {
+                        if selected {
+                            self.selection.insert(path);
+                        } else {
+                            self.selection.remove(&path);
+                        }
+                    }
+                }
+            });
+    }
+
+    fn render_steps(&mut self, ui: &mut Ui, config: &FerriteConfig) {
+        egui::Grid::new("batch-steps")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.resize, "Resize");
+                ui.add_enabled(
+                    self.resize,
+                    egui::DragValue::new(&mut self.resize_to)
+                        .clamp_range(16..=65_535)
+                        .suffix(" px longer side"),
+                );
+                ui.end_row();
+
+                ui.label("Rotate");
+                egui::ComboBox::from_id_source("batch-rotation")
+                    .selected_text(self.job.rotation.label())
+                    .show_ui(ui, |ui| {
+                        for rotation in Rotation::ALL {
+                            ui.selectable_value(
+                                &mut self.job.rotation,
+                                rotation,
+                                rotation.label(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                let has_watermark = config.batch.watermark.is_some();
+                ui.add_enabled(
+                    has_watermark,
+                    egui::Checkbox::new(&mut self.job.watermark, "Watermark"),
+                )
+                .on_disabled_hover_text("Set batch.watermark to an image");
+                match &config.batch.watermark {
+                    Some(path) => ui.label(
+                        path.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy(),
+                    ),
+                    None => ui.label("not configured"),
+                };
+                ui.end_row();
+
+                ui.label("Format");
+                egui::ComboBox::from_id_source("batch-format")
+                    .selected_text(self.job.format.extension().to_uppercase())
+                    .show_ui(ui, |ui| {
+                        for &format in OutputFormat::AVAILABLE {
+                            ui.selectable_value(
+                                &mut self.job.format,
+                                format,
+                                format.extension().to_uppercase(),
+                            );
+                        }
+                    });
+                ui.end_row();
+            });
+        self.job.watermark &= config.batch.watermark.is_some();
+    }
+
+    fn render_progress(&self, ui: &mut Ui) {
+        if self.total == 0 {
+            return;
+        }
+        let fraction = self.done as f32 / self.total as f32;
+        ui.add(
+            egui::ProgressBar::new(fraction)
+                .text(format!("{} / {}", self.done, self.total)),
+        );
+
+        if !self.is_running() {
+            let succeeded = self.done - self.failures.len();
+            let mut summary = format!("{} written", succeeded);
+            if let Some(dir) =
+                self.last_output.as_ref().and_then(|p| p.parent())
+            {
+                summary.push_str(&format!(" to {}", dir.display()));
+            }
+            ui.label(summary);
+        }
+
+        if !self.failures.is_empty() {
+            egui::CollapsingHeader::new(
+                RichText::new(format!("{} failed", self.failures.len()))
+                    .color(Color32::from_rgb(230, 80, 80)),
+            )
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_source("batch-failures")
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for (path, error) in &self.failures {
+                            ui.label(format!(
+                                "{}: {}",
+                                path.file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy(),
+                                error
+                            ));
+                        }
+                    });
+            });
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use eframe::egui::{self, Color32, Context, RichText};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::warn;
+
+use crate::checksum::{self, FileChecksums, VerifyEntry, VerifyStatus};
+
+type HashResult = io::Result<FileChecksums>;
+
+/// Window showing the checksums of the current file and the results of
+/// verifying its folder against a `.sha256` manifest.
+pub struct ChecksumPanel {
+    open:           bool,
+    hashed_path:    Option<PathBuf>,
+    checksums:      Option<Result<FileChecksums, String>>,
+    pending_hash:   Option<Receiver<HashResult>>,
+    pending_verify: Option<Receiver<io::Result<Vec<VerifyEntry>>>>,
+    manifest:       Option<PathBuf>,
+    results:        Vec<VerifyEntry>,
+    status:         Option<String>,
+}
+
+impl ChecksumPanel {
+    pub fn new() -> Self {
+        Self {
+            open:           false,
+            hashed_path:    None,
+            checksums:      None,
+            pending_hash:   None,
+            pending_verify: None,
+            manifest:       None,
+            results:        Vec::new(),
+            status:         None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn start_hash(&mut self, path: &Path) {
+        let (sender, receiver) = mpsc::channel();
+        let input = path.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(checksum::hash_file(&input));
+        });
+        self.hashed_path = Some(path.to_path_buf());
+        self.checksums = None;
+        self.pending_hash = Some(receiver);
+    }
+
+    fn start_verify(&mut self, folder: &Path) {
+        let Some(manifest) = checksum::find_manifest(folder) else {
+            self.status = Some(format!(
+                "No .sha256 manifest found in {}",
+                folder.display()
+            ));
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        let input = manifest.clone();
+        thread::spawn(move || {
+            let _ = sender.send(checksum::verify_manifest(&input));
+        });
+        self.manifest = Some(manifest);
+        self.results.clear();
+        self.status = None;
+        self.pending_verify = Some(receiver);
+    }
+
+    fn poll(&mut self, ctx: &Context) {
+        if let Some(receiver) = &self.pending_hash {
+            match receiver.try_recv() {
+                Ok(result) => {
+                    self.checksums = Some(result.map_err(|e| e.to_string()));
+                    self.pending_hash = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending_hash = None,
+            }
+        }
+
+        if let Some(receiver) = &self.pending_verify {
+            match receiver.try_recv() {
+                Ok(Ok(results)) => {
+                    self.results = results;
+                    self.pending_verify = None;
+                },
+                Ok(Err(e)) => {
+                    warn!("Failed to read checksum manifest: {}", e);
+                    self.status = Some(format!("Verification failed: {}", e));
+                    self.pending_verify = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending_verify = None,
+            }
+        }
+    }
+
+    /// Shows the checksum window. Returns a verified file the user clicked
+    /// so it can be opened.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        current_path: Option<&PathBuf>,
+    ) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        // Hash lazily: only the file being looked at while the window is open
+        if let Some(path) = current_path {
+            if self.hashed_path.as_ref() != Some(path) {
+                self.start_hash(path);
+            }
+        }
+        self.poll(ctx);
+
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new("Checksums")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some(path) = current_path else {
+                    ui.label("No image open.");
+                    return;
+                };
+
+                match &self.checksums {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Hashing...");
+                        });
+                    },
+                    Some(Err(e)) => {
+                        ui.label(format!("Failed to read file: {}", e));
+                    },
+                    Some(Ok(sums)) => {
+                        egui::Grid::new("checksums").num_columns(3).show(
+                            ui,
+                            |ui| {
+                                for (name, digest) in [
+                                    ("MD5", &sums.md5),
+                                    ("SHA-256", &sums.sha256),
+                                ] {
+                                    ui.label(name);
+                                    ui.monospace(digest);
+                                    if ui.small_button("Copy").clicked() {
+                                        ctx.output_mut(|o| {
+                                            o.copied_text = digest.clone()
+                                        });
+                                    }
+                                    ui.end_row();
+                                }
+                            },
+                        );
+                    },
+                }
+
+                ui.separator();
+                if self.pending_verify.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Verifying folder...");
+                    });
+                } else if ui.button("Verify Folder").clicked() {
+                    if let Some(folder) = path.parent() {
+                        self.start_verify(folder);
+                    }
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                if !self.results.is_empty() {
+                    clicked = self.render_results(ui);
+                }
+            });
+        self.open = open;
+        clicked
+    }
+
+    fn render_results(&self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let failed = self
+            .results
+            .iter()
+            .filter(|e| e.status != VerifyStatus::Pass)
+            .count();
+        if let Some(manifest) = &self.manifest {
+            ui.label(format!(
+                "{}: {} files, {} problems",
+                manifest
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                self.results.len(),
+                failed
+            ));
+        }
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for entry in &self.results {
+                    let (marker, color) = match entry.status {
+                        VerifyStatus::Pass => ("✔", Color32::GREEN),
+                        VerifyStatus::Fail => ("✘", Color32::RED),
+                        VerifyStatus::Missing => ("?", Color32::YELLOW),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(marker).color(color));
+                        let name = entry
+                            .path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy();
+                        if entry.status == VerifyStatus::Missing {
+                            ui.label(format!("{} (missing)", name));
+                        } else if ui.link(name).clicked() {
+                            clicked = Some(entry.path.clone());
+                        }
+                    });
+                }
+            });
+        clicked
+    }
+}
@@ -0,0 +1,159 @@
+use eframe::egui::{self, Color32, Context, Painter, Pos2, Rect, Stroke};
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::codes::{self, DetectedCode};
+
+/// Runs code detection on a background thread and presents the results as an
+/// outline overlay plus a small window with copy/open actions.
+pub struct CodeScanner {
+    pending:      Option<Receiver<Vec<DetectedCode>>>,
+    results:      Vec<DetectedCode>,
+    scanned_path: Option<PathBuf>,
+    window_open:  bool,
+}
+
+impl CodeScanner {
+    pub fn new() -> Self {
+        Self {
+            pending:      None,
+            results:      Vec::new(),
+            scanned_path: None,
+            window_open:  false,
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Starts scanning a copy of the given image. Results from a previous
+    /// scan are discarded.
+    pub fn start_scan(&mut self, image: &DynamicImage, path: Option<PathBuf>) {
+        info!("Starting code scan");
+        let image = image.clone();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let _ = sender.send(codes::scan(&image));
+        });
+
+        self.pending = Some(receiver);
+        self.results.clear();
+        self.scanned_path = path;
+        self.window_open = true;
+    }
+
+    /// Collects finished scan results and drops results that belong to an
+    /// image that is no longer displayed.
+    pub fn update(&mut self, ctx: &Context, current_path: Option<&PathBuf>) {
+        if self.scanned_path.as_ref() != current_path {
+            self.pending = None;
+            self.results.clear();
+            self.scanned_path = None;
+            self.window_open = false;
+            return;
+        }
+
+        if let Some(receiver) = &self.pending {
+            match receiver.try_recv() {
+                Ok(results) => {
+                    self.results = results;
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => {
+                    warn!("Code scan thread terminated without a result");
+                    self.pending = None;
+                },
+            }
+        }
+    }
+
+    /// Draws the outline of every detected code on top of the image.
+    pub fn paint_outlines(
+        &self,
+        painter: &Painter,
+        image_rect: Rect,
+        image_size: (u32, u32),
+    ) {
+        if !self.window_open
+            || self.results.is_empty()
+            || image_size.0 == 0
+            || image_size.1 == 0
+        {
+            return;
+        }
+
+        let scale_x = image_rect.width() / image_size.0 as f32;
+        let scale_y = image_rect.height() / image_size.1 as f32;
+        let to_screen = |(x, y): (f32, f32)| {
+            Pos2::new(
+                image_rect.min.x + x * scale_x,
+                image_rect.min.y + y * scale_y,
+            )
+        };
+
+        let stroke = Stroke::new(2.0, Color32::from_rgb(0, 220, 120));
+        for code in &self.results {
+            let mut points: Vec<Pos2> = code
+                .outline
+                .iter()
+                .copied()
+                .map(to_screen)
+                .collect();
+            points.push(points[0]);
+            painter.add(egui::Shape::line(points, stroke));
+        }
+    }
+
+    /// Shows the list of decoded codes with actions to copy or open them.
+    pub fn render_window(&mut self, ctx: &Context) {
+        if !self.window_open {
+            return;
+        }
+
+        let mut open = self.window_open;
+        egui::Window::new("Detected Codes")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.is_scanning() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Scanning image...");
+                    });
+                    return;
+                }
+
+                if self.results.is_empty() {
+                    ui.label("No QR codes or barcodes found.");
+                    return;
+                }
+
+                for code in &self.results {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.strong(code.kind.label());
+                        ui.label(&code.content);
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Copy").clicked() {
+                            ctx.output_mut(|o| {
+                                o.copied_text = code.content.clone()
+                            });
+                        }
+                        if code.is_link() && ui.button("Open").clicked() {
+                            ctx.open_url(egui::OpenUrl::new_tab(&code.content));
+                        }
+                    });
+                }
+            });
+        self.window_open = open;
+    }
+}
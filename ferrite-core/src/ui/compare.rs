@@ -0,0 +1,471 @@
+use eframe::egui::{
+    self,
+    Align2,
+    Color32,
+    ColorImage,
+    Context,
+    CursorIcon,
+    FontId,
+    Id,
+    Painter,
+    Pos2,
+    Rect,
+    Sense,
+    Stroke,
+    TextureHandle,
+    TextureOptions,
+    Ui,
+    Vec2,
+};
+use ferrite_config::{DecodingConfig, DitherMode, IoConfig};
+use image::DynamicImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    clipboard,
+    diff::{self, Difference},
+    dither,
+    image as images,
+    ui::zoom::ZoomHandler,
+};
+
+/// Channel difference below which pixels count as equal
+const DEFAULT_THRESHOLD: u8 = 2;
+/// Half the width of the slider's grab area in points
+const HANDLE_REACH: f32 = 6.0;
+
+struct SecondImage {
+    label:   String,
+    image:   DynamicImage,
+    texture: Option<TextureHandle>,
+}
+
+/// How the two images share the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareLayout {
+    /// Each image gets half of the window
+    SideBySide,
+    /// The second image covers the first to the right of a slider
+    Split,
+}
+
+/// Which main image a difference was computed for, and the threshold
+type DiffSource = (Option<PathBuf>, bool, (u32, u32), u8);
+
+/// Highlight of the pixels that differ, ready to draw
+struct DiffOverlay {
+    difference: Difference,
+    texture:    Option<TextureHandle>,
+}
+
+/// Compare mode. The main image keeps the normal view controls; the second
+/// image follows its zoom and pan, scaled to the same displayed width,
+/// either in the right half of the window or under a draggable slider.
+/// Pixels that differ can be highlighted on the main image.
+pub struct CompareView {
+    second:      Option<SecondImage>,
+    status:      Option<String>,
+    layout:      CompareLayout,
+    /// Position of the slider as a fraction of the image width
+    split:       f32,
+    /// Screen area of the slider last frame, to keep its drags from panning
+    handle:      Option<Rect>,
+    dragging:    bool,
+    highlight:   bool,
+    threshold:   u8,
+    diff_source: Option<DiffSource>,
+    pending:     Option<Receiver<Difference>>,
+    overlay:     Option<DiffOverlay>,
+}
+
+impl CompareView {
+    pub fn new() -> Self {
+        Self {
+            second:      None,
+            status:      None,
+            layout:      CompareLayout::SideBySide,
+            split:       0.5,
+            handle:      None,
+            dragging:    false,
+            highlight:   false,
+            threshold:   DEFAULT_THRESHOLD,
+            diff_source: None,
+            pending:     None,
+            overlay:     None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.second.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.second = None;
+        self.handle = None;
+        self.dragging = false;
+        self.clear_difference();
+    }
+
+    fn clear_difference(&mut self) {
+        self.diff_source = None;
+        self.pending = None;
+        self.overlay = None;
+    }
+
+    pub fn open(&mut self, label: impl Into<String>, image: DynamicImage) {
+        self.second = Some(SecondImage {
+            label: label.into(),
+            image,
+            texture: None,
+        });
+        self.status = None;
+        self.clear_difference();
+    }
+
+    /// Whether the pointer is on the split slider, so dragging moves it
+    /// instead of panning.
+    pub fn wants_pointer(&self, ctx: &Context) -> bool {
+        if !self.is_active() || self.layout != CompareLayout::Split {
+            return false;
+        }
+        self.dragging
+            || self
+                .handle
+                .zip(ctx.input(|i| i.pointer.hover_pos()))
+                .is_some_and(|(handle, pos)| handle.contains(pos))
+    }
+
+    /// Opens the clipboard image as the second pane.
+    pub fn open_clipboard(&mut self) {
+        match clipboard::paste_image() {
+            Ok(image) => {
+                info!(
+                    "Comparing against clipboard image {}x{}",
+                    image.width(),
+                    image.height()
+                );
+                self.open("Clipboard", image);
+            },
+            Err(e) => {
+                warn!("No image on the clipboard: {}", e);
+                self.status = Some(format!("No image on the clipboard: {}", e));
+            },
+        }
+    }
+
+    /// Opens an image file as the second pane.
+    pub fn open_file(
+        &mut self,
+        path: &Path,
+        decoding: &DecodingConfig,
+        io: &IoConfig,
+    ) {
+        let label = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        match images::decode_file(path, decoding, io) {
+            Ok(image) => {
+                info!("Comparing against {}", path.display());
+                self.open(label, image);
+            },
+            Err(e) => {
+                warn!("Failed to open {} for comparing: {}", path.display(), e);
+                self.status = Some(format!("Failed to open {}: {}", label, e));
+            },
+        }
+    }
+
+    /// Splits `rect` into the main pane and the compare pane, which are the
+    /// same in the split layout.
+    pub fn panes(&self, rect: Rect) -> (Rect, Rect) {
+        if self.layout == CompareLayout::Split {
+            return (rect, rect);
+        }
+        let middle = rect.center().x;
+        (
+            Rect::from_min_max(rect.min, Pos2::new(middle - 1.0, rect.max.y)),
+            Rect::from_min_max(Pos2::new(middle + 1.0, rect.min.y), rect.max),
+        )
+    }
+
+    /// Computes the difference to the main image `image` in the background
+    /// whenever either image or the threshold changes. `derived` tells
+    /// whether `image` was made from the file at `current_path`.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        current_path: Option<&PathBuf>,
+        derived: bool,
+        image: Option<&DynamicImage>,
+    ) {
+        let (Some(second), Some(image), true) =
+            (&self.second, image, self.highlight)
+        else {
+            return;
+        };
+        let source = (
+            current_path.cloned(),
+            derived,
+            (image.width(), image.height()),
+            self.threshold,
+        );
+        if self.diff_source.as_ref() != Some(&source) {
+            let first = image.clone();
+            let second = second.image.clone();
+            let threshold = self.threshold;
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(diff::compare(&first, &second, threshold));
+            });
+            self.diff_source = Some(source);
+            self.pending = Some(receiver);
+        }
+        if let Some(receiver) = &self.pending {
+            match receiver.try_recv() {
+                Ok(difference) => {
+                    self.overlay = Some(DiffOverlay {
+                        difference,
+                        texture: None,
+                    });
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+    }
+
+    /// Draws the second image into `pane`, matching the main image drawn at
+    /// `main_rect` from an image `main_width` pixels wide, and the
+    /// highlight of the differences over the main image.
+    pub fn render_pane(
+        &mut self,
+        ui: &mut Ui,
+        pane: Rect,
+        main_rect: Option<Rect>,
+        zoom_handler: &ZoomHandler,
+        main_width: u32,
+        dither: DitherMode,
+    ) {
+        let Some(second) = self.second.as_mut() else {
+            return;
+        };
+        let texture = second.texture.get_or_insert_with(|| {
+            let rgba = dither::to_display(&second.image, dither);
+            ui.ctx().load_texture(
+                "compare-image",
+                ColorImage::from_rgba_unmultiplied(
+                    [rgba.width() as usize, rgba.height() as usize],
+                    rgba.as_flat_samples().as_slice(),
+                ),
+                TextureOptions::LINEAR,
+            )
+        });
+
+        let size = texture.size_vec2();
+        let (rect, visible) = match (self.layout, main_rect) {
+            (CompareLayout::Split, Some(main_rect)) => {
+                // Over the main image, right of the slider
+                let x = main_rect.left() + self.split * main_rect.width();
+                let visible = Rect::from_min_max(
+                    Pos2::new(x.max(pane.left()), pane.top()),
+                    pane.max,
+                );
+                (main_rect, visible)
+            },
+            _ => {
+                let scale = zoom_handler.zoom_level() as f32
+                    * main_width as f32
+                    / size.x;
+                let rect = Rect::from_center_size(
+                    pane.center() + zoom_handler.offset(),
+                    size * scale,
+                );
+                (rect, pane)
+            },
+        };
+
+        // Not clipped to `ui`, which holds only the main pane side by side
+        let painter_at =
+            |clip: Rect| Painter::new(ui.ctx().clone(), ui.layer_id(), clip);
+        let painter = painter_at(visible);
+        painter.image(
+            texture.id(),
+            rect,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        if let (Some(overlay), Some(main_rect), true) =
+            (&mut self.overlay, main_rect, self.highlight)
+        {
+            let texture = overlay.texture.get_or_insert_with(|| {
+                let mask = &overlay.difference.mask;
+                ui.ctx().load_texture(
+                    "compare-difference",
+                    ColorImage::from_rgba_unmultiplied(
+                        [mask.width() as usize, mask.height() as usize],
+                        mask.as_flat_samples().as_slice(),
+                    ),
+                    TextureOptions::NEAREST,
+                )
+            });
+            painter_at(pane).image(
+                texture.id(),
+                main_rect,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::WHITE,
+            );
+        }
+
+        let label = format!(
+            "{} ({}×{})",
+            second.label,
+            second.image.width(),
+            second.image.height()
+        );
+        let anchor = visible.min + Vec2::new(8.0, 8.0);
+        let text = painter.text(
+            anchor,
+            Align2::LEFT_TOP,
+            &label,
+            FontId::proportional(14.0),
+            Color32::WHITE,
+        );
+        painter.rect_filled(
+            text.expand(4.0),
+            3.0,
+            Color32::from_black_alpha(160),
+        );
+        painter.text(
+            anchor,
+            Align2::LEFT_TOP,
+            label,
+            FontId::proportional(14.0),
+            Color32::WHITE,
+        );
+
+        match (self.layout, main_rect) {
+            (CompareLayout::Split, Some(main_rect)) => {
+                self.interact_split(ui, pane, main_rect, visible.left())
+            },
+            _ => {
+                self.handle = None;
+                painter_at(ui.ctx().screen_rect()).vline(
+                    pane.min.x - 1.0,
+                    pane.y_range(),
+                    egui::Stroke::new(2.0, Color32::from_gray(90)),
+                );
+            },
+        }
+    }
+
+    /// Draws the slider at `x` and moves it on drag.
+    fn interact_split(&mut self, ui: &mut Ui, pane: Rect, image: Rect, x: f32) {
+        let handle = Rect::from_x_y_ranges(
+            x - HANDLE_REACH..=x + HANDLE_REACH,
+            pane.y_range(),
+        );
+        self.handle = Some(handle);
+        let response =
+            ui.interact(handle, Id::new("compare-split"), Sense::drag());
+        if response.hovered() || response.dragged() {
+            ui.ctx()
+                .set_cursor_icon(CursorIcon::ResizeHorizontal);
+        }
+        self.dragging = response.dragged();
+        if let Some(pos) = response.interact_pointer_pos() {
+            if image.width() > 0.0 {
+                self.split =
+                    ((pos.x - image.left()) / image.width()).clamp(0.0, 1.0);
+            }
+        }
+        let painter = ui.painter();
+        painter.vline(x, pane.y_range(), Stroke::new(3.0, Color32::BLACK));
+        painter.vline(x, pane.y_range(), Stroke::new(1.0, Color32::WHITE));
+        painter.circle_filled(
+            Pos2::new(x, pane.center().y),
+            6.0,
+            Color32::WHITE,
+        );
+    }
+
+    /// Shows the compare controls while comparing, and why opening the
+    /// second image failed.
+    pub fn render_window(&mut self, ctx: &Context) {
+        if !self.is_active() {
+            let Some(status) = &self.status else {
+                return;
+            };
+            let mut open = true;
+            egui::Window::new("Compare")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| ui.label(status));
+            if !open {
+                self.status = None;
+            }
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("Compare")
+            .open(&mut open)
+            .resizable(false)
+            .default_pos(
+                ctx.screen_rect().right_top() + Vec2::new(-240.0, 40.0),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.layout,
+                        CompareLayout::SideBySide,
+                        "Side by Side",
+                    );
+                    ui.radio_value(
+                        &mut self.layout,
+                        CompareLayout::Split,
+                        "Split",
+                    );
+                });
+                ui.checkbox(&mut self.highlight, "Highlight differences");
+                ui.add_enabled(
+                    self.highlight,
+                    egui::Slider::new(&mut self.threshold, 0..=64)
+                        .text("Threshold"),
+                );
+                if !self.highlight {
+                    return;
+                }
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Comparing pixels...");
+                    });
+                } else if let Some(overlay) = &self.overlay {
+                    let difference = &overlay.difference;
+                    ui.label(format!(
+                        "{:.2}% of pixels differ, by up to {}",
+                        difference.differing_share() * 100.0,
+                        difference.max
+                    ));
+                    ui.label(match difference.psnr {
+                        Some(psnr) => format!("PSNR {:.2} dB", psnr),
+                        None => "The images are identical".to_string(),
+                    });
+                    if difference.resized {
+                        ui.weak("The second image was scaled to match");
+                    }
+                }
+            });
+        if !open {
+            self.close();
+        }
+    }
+}
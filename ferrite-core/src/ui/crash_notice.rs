@@ -0,0 +1,58 @@
+use eframe::egui::{self, Context};
+use std::path::PathBuf;
+
+/// Tells the user about a crash in the previous run and where its report
+/// was saved.
+pub struct CrashNotice {
+    report: Option<PathBuf>,
+}
+
+impl CrashNotice {
+    pub fn new(report: Option<PathBuf>) -> Self {
+        Self {
+            report,
+        }
+    }
+
+    pub fn render_window(&mut self, ctx: &Context) {
+        let Some(report) = &self.report else {
+            return;
+        };
+
+        let mut dismissed = false;
+        egui::Window::new("Ferrite closed unexpectedly")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Ferrite crashed the last time it ran. A report was saved \
+                     to:",
+                );
+                ui.monospace(report.display().to_string());
+                ui.label("Please attach it when filing a bug report.");
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Path").clicked() {
+                        ctx.output_mut(|o| {
+                            o.copied_text = report.display().to_string()
+                        });
+                    }
+                    if let Some(folder) = report.parent() {
+                        if ui.button("Open Folder").clicked() {
+                            ctx.open_url(egui::OpenUrl::new_tab(format!(
+                                "file://{}",
+                                folder.display()
+                            )));
+                        }
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        dismissed = true;
+                    }
+                });
+            });
+
+        if dismissed {
+            self.report = None;
+        }
+    }
+}
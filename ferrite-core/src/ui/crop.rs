@@ -0,0 +1,345 @@
+use eframe::egui::{
+    self,
+    Align2,
+    Color32,
+    Context,
+    FontId,
+    Key,
+    Modifiers,
+    PointerButton,
+    Rect,
+    Stroke,
+    Ui,
+};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+use crate::{
+    export::{self, ExportEvent, ExportFormat, ExportOptions, PngCompression},
+    image::{free_derived_path, ImageManager},
+    selection::Region,
+    ui::overlay::ImageGeometry,
+};
+
+/// Pixels Shift moves the crop by with each arrow key press
+const NUDGE_STEP: i64 = 10;
+
+/// Dims the image around the crop
+const OUTSIDE: Color32 = Color32::from_black_alpha(140);
+
+/// The crop of one file, in its pixels
+struct Crop {
+    source: PathBuf,
+    region: Region,
+    size:   (u32, u32),
+}
+
+/// Crop mode: a rectangle dragged over the image, nudged with the arrow
+/// keys and saved as a new file next to the image or over it.
+pub struct CropTool {
+    active:       bool,
+    drag:         Option<((f32, f32), (f32, f32))>,
+    crop:         Option<Crop>,
+    /// Asking whether to overwrite the file
+    confirm:      bool,
+    saving:       Option<Receiver<ExportEvent>>,
+    status:       Option<String>,
+    jpeg_quality: u8,
+}
+
+impl CropTool {
+    pub fn new(jpeg_quality: u8) -> Self {
+        Self {
+            active: false,
+            drag: None,
+            crop: None,
+            confirm: false,
+            saving: None,
+            status: None,
+            jpeg_quality,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.drag = None;
+        self.crop = None;
+        self.confirm = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Takes the arrow keys while there is a crop, moving it by a pixel, or
+    /// ten with Shift, and resizing it with Ctrl. Runs before the shortcuts,
+    /// so the keys do not step to another image.
+    pub fn nudge(&mut self, ctx: &Context) {
+        let Some(crop) = self.crop.as_mut().filter(|_| self.active) else {
+            return;
+        };
+        let directions = [
+            (Key::ArrowLeft, (-1, 0)),
+            (Key::ArrowRight, (1, 0)),
+            (Key::ArrowUp, (0, -1)),
+            (Key::ArrowDown, (0, 1)),
+        ];
+        ctx.input_mut(|input| {
+            for (key, (dx, dy)) in directions {
+                if input.consume_key(Modifiers::NONE, key) {
+                    crop.region = crop.region.moved((dx, dy), crop.size);
+                }
+                if input.consume_key(Modifiers::SHIFT, key) {
+                    let delta = (dx * NUDGE_STEP, dy * NUDGE_STEP);
+                    crop.region = crop.region.moved(delta, crop.size);
+                }
+                if input.consume_key(Modifiers::COMMAND, key) {
+                    crop.region = crop.region.resized((dx, dy), crop.size);
+                }
+            }
+        });
+    }
+
+    /// Tracks the crop drag and draws the crop with its size.
+    pub fn interact(
+        &mut self,
+        ui: &mut Ui,
+        geometry: ImageGeometry,
+        current_path: Option<&PathBuf>,
+    ) {
+        if self.crop.as_ref().map(|crop| &crop.source) != current_path {
+            self.crop = None;
+            self.confirm = false;
+        }
+        if !self.active {
+            return;
+        }
+
+        let size = (geometry.size.x as u32, geometry.size.y as u32);
+        let (pointer, pressed, down) = ui.input(|i| {
+            (
+                i.pointer.interact_pos(),
+                i.pointer.button_pressed(PointerButton::Primary),
+                i.pointer.button_down(PointerButton::Primary),
+            )
+        });
+        if let Some(pos) = pointer {
+            let point = geometry.to_image_clamped(pos);
+            if pressed && ui.rect_contains_pointer(geometry.rect) {
+                self.drag = Some((point, point));
+            } else if let Some((start, end)) = self.drag.as_mut() {
+                *end = point;
+                if !down {
+                    self.crop = Region::from_corners(*start, *end, size)
+                        .zip(current_path.cloned())
+                        .map(|(region, source)| Crop {
+                            source,
+                            region,
+                            size,
+                        });
+                    self.confirm = false;
+                    self.status = None;
+                    self.drag = None;
+                }
+            }
+        }
+        if !ui.ctx().wants_keyboard_input()
+            && ui.input(|i| i.key_pressed(Key::Escape))
+        {
+            self.drag = None;
+            self.crop = None;
+        }
+
+        let region = match (self.drag, &self.crop) {
+            (Some((start, end)), _) => Region::from_corners(start, end, size),
+            (None, Some(crop)) => Some(crop.region),
+            _ => None,
+        };
+        let Some(region) = region else {
+            return;
+        };
+        let rect = Rect::from_min_max(
+            geometry.to_screen((region.x as f32, region.y as f32)),
+            geometry.to_screen((
+                (region.x + region.width) as f32,
+                (region.y + region.height) as f32,
+            )),
+        );
+        let image = geometry.rect;
+        let painter = ui.painter_at(image);
+        for outside in [
+            Rect::from_x_y_ranges(image.x_range(), image.top()..=rect.top()),
+            Rect::from_x_y_ranges(
+                image.x_range(),
+                rect.bottom()..=image.bottom(),
+            ),
+            Rect::from_x_y_ranges(image.left()..=rect.left(), rect.y_range()),
+            Rect::from_x_y_ranges(rect.right()..=image.right(), rect.y_range()),
+        ] {
+            painter.rect_filled(outside, 0.0, OUTSIDE);
+        }
+        painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::WHITE));
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 4.0),
+            Align2::LEFT_TOP,
+            format!("{} × {}", region.width, region.height),
+            FontId::proportional(14.0),
+            Color32::WHITE,
+        );
+    }
+
+    /// Options for the finished crop. Returns the file once it was written.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+    ) -> Option<PathBuf> {
+        let saved = self.poll(ctx);
+        if !self.active {
+            return saved;
+        }
+
+        let mut open = true;
+        egui::Window::new("Crop")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some((source, region)) = self
+                    .crop
+                    .as_ref()
+                    .map(|crop| (crop.source.clone(), crop.region))
+                else {
+                    ui.label("Drag over the image to choose the crop.");
+                    return;
+                };
+                ui.label(format!(
+                    "{} × {} at {}, {}",
+                    region.width, region.height, region.x, region.y
+                ));
+                ui.label(
+                    "Arrow keys move it, with Shift by ten. Ctrl and the \
+                     arrow keys resize it.",
+                );
+                let format = ExportFormat::from_path(&source);
+                let idle = self.saving.is_none();
+                let mut target = None;
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            idle,
+                            egui::Button::new("Save as New File"),
+                        )
+                        .clicked()
+                    {
+                        target = Some(free_derived_path(
+                            &source,
+                            "crop",
+                            format.unwrap_or_default().extension(),
+                        ));
+                    }
+                    if !self.confirm
+                        && ui
+                            .add_enabled(
+                                idle && format.is_some(),
+                                egui::Button::new("Overwrite..."),
+                            )
+                            .on_disabled_hover_text(
+                                "This format can only be saved as a new file",
+                            )
+                            .clicked()
+                    {
+                        self.confirm = true;
+                    }
+                });
+                if self.confirm {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Replace {}?",
+                            source
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                        ));
+                        if ui.button("Replace").clicked() {
+                            target = Some(source.clone());
+                            self.confirm = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm = false;
+                        }
+                    });
+                }
+                if self.saving.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Saving...");
+                    });
+                } else if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+
+                if let Some(path) = target {
+                    let format =
+                        ExportFormat::from_path(&path).unwrap_or_default();
+                    self.save(region, path, format, image_manager);
+                }
+            });
+        if !open {
+            self.toggle();
+        }
+        saved
+    }
+
+    fn save(
+        &mut self,
+        region: Region,
+        path: PathBuf,
+        format: ExportFormat,
+        image_manager: &mut ImageManager,
+    ) {
+        let Some(image) = image_manager
+            .current_image()
+            .map(|data| region.crop(&data.original))
+        else {
+            return;
+        };
+        let options = ExportOptions {
+            format,
+            jpeg_quality: self.jpeg_quality,
+            png_compression: PngCompression::default(),
+        };
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            export::run(image, path, options, sender, &AtomicBool::new(false))
+        });
+        self.status = None;
+        self.saving = Some(events);
+    }
+
+    fn poll(&mut self, ctx: &Context) -> Option<PathBuf> {
+        let events = self.saving.as_ref()?;
+        ctx.request_repaint();
+        let result = events.try_iter().find_map(|event| match event {
+            ExportEvent::Finished(result) => Some(result),
+            ExportEvent::Progress(_) => None,
+        })?;
+        self.saving = None;
+        match result {
+            Ok(path) => {
+                self.status = Some(format!("Saved {}", path.display()));
+                self.crop = None;
+                Some(path)
+            },
+            Err(e) => {
+                self.status = Some(format!("Saving failed: {}", e));
+                None
+            },
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use eframe::egui::{self, Align2, Context};
+use ferrite_config::FerriteConfig;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::remote::Download;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Box at the bottom of the window following the download of an image
+/// opened from a URL, and telling why it failed if it did.
+pub struct DownloadIndicator {
+    download: Option<Download>,
+    failed:   Option<String>,
+}
+
+impl DownloadIndicator {
+    pub fn new() -> Self {
+        Self {
+            download: None, failed: None
+        }
+    }
+
+    /// Starts downloading `url`, abandoning any download still running.
+    pub fn start(&mut self, url: &str, config: &FerriteConfig) {
+        let cache_dir = config
+            .remote
+            .cache_dir
+            .clone()
+            .or_else(|| {
+                config
+                    .paths
+                    .resolve_cache_dir()
+                    .map(|dir| dir.join("remote"))
+            })
+            .unwrap_or_else(|| std::env::temp_dir().join("ferrite-remote"));
+        self.download = Some(Download::start(url, &config.remote, cache_dir));
+        self.failed = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.download.is_some()
+    }
+
+    /// The downloaded file to open, once it is there.
+    pub fn poll(&mut self, ctx: &Context) -> Option<PathBuf> {
+        let download = self.download.as_mut()?;
+        let Some(result) = download.poll() else {
+            ctx.request_repaint();
+            return None;
+        };
+        let url = download.url().to_string();
+        self.download = None;
+        match result {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Failed to download {}: {}", url, e);
+                self.failed = Some(e.to_string());
+                None
+            },
+        }
+    }
+
+    pub fn render(&mut self, ctx: &Context) {
+        if self.download.is_none() && self.failed.is_none() {
+            return;
+        }
+        let mut dismissed = false;
+        egui::Area::new("download")
+            .anchor(Align2::CENTER_BOTTOM, egui::vec2(0.0, -48.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(360.0);
+                    if let Some(download) = &self.download {
+                        let progress = download.progress();
+                        ui.label(download.url());
+                        ui.horizontal(|ui| {
+                            let received = progress.received as f64 / MB;
+                            let text = match progress.total {
+                                Some(total) => format!(
+                                    "{:.1} of {:.1} MB",
+                                    received,
+                                    total as f64 / MB
+                                ),
+                                None => format!("{:.1} MB", received),
+                            };
+                            match progress.fraction() {
+                                Some(fraction) => ui.add(
+                                    egui::ProgressBar::new(fraction)
+                                        .desired_width(200.0)
+                                        .text(text),
+                                ),
+                                None => {
+                                    ui.spinner();
+                                    ui.label(text)
+                                },
+                            };
+                            dismissed = ui.button("Cancel").clicked();
+                        });
+                    } else if let Some(failed) = &self.failed {
+                        ui.horizontal(|ui| {
+                            ui.label(failed);
+                            dismissed = ui.button("Close").clicked();
+                        });
+                    }
+                });
+            });
+        if dismissed {
+            self.download = None;
+            self.failed = None;
+        }
+    }
+}
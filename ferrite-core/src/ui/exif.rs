@@ -0,0 +1,67 @@
+use eframe::egui::{self, Context, RichText};
+use std::path::Path;
+
+use crate::metadata::MetadataCache;
+
+const PANEL_WIDTH: f32 = 240.0;
+
+/// Side panel listing the camera, exposure, location and capture date of
+/// the current photo. Files are only parsed while the panel is open.
+pub struct ExifPanel {
+    open:  bool,
+    cache: MetadataCache,
+}
+
+impl ExifPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false, cache: MetadataCache::new()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Shows the panel at the right edge. Must run before the central
+    /// panel so the image area shrinks to make room.
+    pub fn render(&mut self, ctx: &Context, current_path: Option<&Path>) {
+        if !self.open {
+            return;
+        }
+
+        egui::SidePanel::right("exif_panel")
+            .default_width(PANEL_WIDTH)
+            .show(ctx, |ui| {
+                ui.heading("EXIF");
+                ui.separator();
+                let Some(path) = current_path else {
+                    ui.label("No image loaded.");
+                    return;
+                };
+                let rows = self
+                    .cache
+                    .get(path)
+                    .map(|summary| summary.rows())
+                    .unwrap_or_default();
+                if rows.is_empty() {
+                    ui.label("No EXIF data in this file.");
+                    return;
+                }
+                egui::Grid::new("exif_rows")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (label, value) in rows {
+                            ui.label(RichText::new(label).strong());
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,103 @@
+use eframe::egui::{self, ColorImage, Context, Event, Rect, ViewportCommand};
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::image::derived_path;
+
+/// Saves a screenshot of the image area, as rendered with the current zoom,
+/// background, overlays and filters, as a PNG next to the source image.
+pub struct ViewExporter {
+    /// Destination of a requested screenshot and the area to keep
+    pending: Option<(PathBuf, Rect)>,
+    /// Whether the screenshot command went out for the pending export
+    sent:    bool,
+    status:  Option<String>,
+}
+
+impl ViewExporter {
+    pub fn new() -> Self {
+        Self {
+            pending: None, sent: false, status: None
+        }
+    }
+
+    /// Schedules an export. The screenshot is taken of the next frame so
+    /// the menu that triggered it has closed.
+    pub fn request(&mut self, source: &Path, viewport: Rect) {
+        self.pending = Some((derived_path(source, "view"), viewport));
+        self.sent = false;
+        self.status = None;
+    }
+
+    pub fn update(&mut self, ctx: &Context) {
+        let Some((path, viewport)) = &self.pending else {
+            return;
+        };
+        if !self.sent {
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+            self.sent = true;
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.raw.events.iter().find_map(|event| match event {
+                Event::Screenshot {
+                    image, ..
+                } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(screenshot) = screenshot else {
+            ctx.request_repaint();
+            return;
+        };
+
+        let image = crop(&screenshot, *viewport, ctx.pixels_per_point());
+        self.status = Some(match image.save(path) {
+            Ok(()) => {
+                info!("Exported view to {}", path.display());
+                format!("Saved {}", path.display())
+            },
+            Err(e) => {
+                warn!("Failed to export view: {}", e);
+                format!("Export failed: {}", e)
+            },
+        });
+        self.pending = None;
+    }
+
+    pub fn render_window(&mut self, ctx: &Context) {
+        let Some(status) = &self.status else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Export View")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| ui.label(status));
+        if !open {
+            self.status = None;
+        }
+    }
+}
+
+/// Cuts the area `viewport` (in points) out of a screenshot.
+fn crop(
+    screenshot: &ColorImage,
+    viewport: Rect,
+    pixels_per_point: f32,
+) -> RgbaImage {
+    let [width, height] = screenshot.size;
+    let full = Rect::from_min_size(
+        egui::Pos2::ZERO,
+        egui::vec2(width as f32, height as f32) / pixels_per_point,
+    );
+    let region =
+        screenshot.region(&viewport.intersect(full), Some(pixels_per_point));
+
+    let [width, height] = region.size;
+    RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+        let pixel = region.pixels[y as usize * width + x as usize];
+        image::Rgba(pixel.to_srgba_unmultiplied())
+    })
+}
@@ -0,0 +1,281 @@
+use eframe::egui::{
+    self,
+    Align,
+    Align2,
+    Color32,
+    ColorImage,
+    Context,
+    FontId,
+    Pos2,
+    Rect,
+    Sense,
+    Stroke,
+    TextureOptions,
+    Vec2,
+};
+use ferrite_config::FerriteConfig;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use super::gallery::{self, Thumbnail};
+use crate::{favorites::Favorites, warm_start::ThumbnailStore};
+
+const THUMBNAIL_SIZE: u32 = 64;
+const CELL: f32 = 76.0;
+const STAR_COLOR: Color32 = Color32::from_rgb(240, 200, 60);
+
+/// Row of thumbnails of the current folder along the bottom of the window,
+/// for jumping straight to an image.
+pub struct Filmstrip {
+    open:       bool,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    /// Folder the thumbnails are being made for
+    folder:     Vec<PathBuf>,
+    pending:    Option<Receiver<(PathBuf, Option<ColorImage>)>>,
+    store:      Option<Arc<ThumbnailStore>>,
+    /// Image the strip was last scrolled to
+    scrolled:   Option<PathBuf>,
+}
+
+impl Filmstrip {
+    pub fn new(config: &FerriteConfig) -> Self {
+        // Stored next to the gallery's, which also prunes them
+        let store = config
+            .cache
+            .warm_start
+            .then(|| ThumbnailStore::new(config.paths.resolve_cache_dir()))
+            .flatten()
+            .map(Arc::new);
+        Self {
+            open: false,
+            thumbnails: HashMap::new(),
+            folder: Vec::new(),
+            pending: None,
+            store,
+            scrolled: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.scrolled = None;
+    }
+
+    /// Makes thumbnails of a newly listed folder in the background, those
+    /// closest to `current` first.
+    fn refresh(
+        &mut self,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+    ) {
+        if self.folder == folder {
+            return;
+        }
+        self.folder = folder.to_vec();
+        self.thumbnails
+            .retain(|path, _| folder.contains(path));
+
+        let start = current
+            .and_then(|current| folder.iter().position(|p| p == current))
+            .unwrap_or(0);
+        let mut missing: Vec<(usize, PathBuf)> = folder
+            .iter()
+            .enumerate()
+            .filter(|(_, path)| !self.thumbnails.contains_key(*path))
+            .map(|(index, path)| (index.abs_diff(start), path.clone()))
+            .collect();
+        missing.sort_by_key(|(distance, _)| *distance);
+
+        let (sender, receiver) = mpsc::channel();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        let color = config.color.clone();
+        let store = self.store.clone();
+        let variant = gallery::thumbnail_variant(config, THUMBNAIL_SIZE);
+        thread::spawn(move || {
+            // Stops early once the strip moved on to another folder
+            let _ = missing.into_par_iter().try_for_each_with(
+                sender,
+                |sender, (_, path)| {
+                    let thumbnail = gallery::make_thumbnail(
+                        &path,
+                        THUMBNAIL_SIZE,
+                        store.as_deref(),
+                        &variant,
+                        &decoding,
+                        &io,
+                        color.linear_light,
+                    )
+                    .map(|thumbnail| {
+                        gallery::to_color_image(&thumbnail, color.dither)
+                    });
+                    sender.send((path, thumbnail))
+                },
+            );
+        });
+        self.pending = Some(receiver);
+    }
+
+    fn collect_thumbnails(&mut self, ctx: &Context) {
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok((path, image)) => {
+                    let thumbnail = match image {
+                        Some(image) => Thumbnail::Loaded(ctx.load_texture(
+                            format!("filmstrip-{}", path.display()),
+                            image,
+                            TextureOptions::LINEAR,
+                        )),
+                        None => Thumbnail::Failed,
+                    };
+                    self.thumbnails.insert(path, thumbnail);
+                },
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint();
+                    return;
+                },
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.pending = None;
+                    return;
+                },
+            }
+        }
+    }
+
+    /// Shows the strip. Must run before the central panel so the image
+    /// area shrinks to make room. Starred images carry a star. Returns an
+    /// image that was clicked.
+    pub fn render(
+        &mut self,
+        ctx: &Context,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+        favorites: &Favorites,
+    ) -> Option<PathBuf> {
+        if !self.open || folder.is_empty() {
+            return None;
+        }
+        self.refresh(config, folder, current);
+        self.collect_thumbnails(ctx);
+
+        // Keeps the current image in view as it changes, without fighting
+        // the user scrolling elsewhere
+        let scroll = current.is_some() && self.scrolled.as_deref() != current;
+        self.scrolled = current.map(Path::to_path_buf);
+
+        let mut clicked = None;
+        egui::TopBottomPanel::bottom("filmstrip")
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::horizontal()
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            for path in folder {
+                                let is_current =
+                                    current == Some(path.as_path());
+                                let (cell, response) = ui.allocate_exact_size(
+                                    Vec2::splat(CELL),
+                                    Sense::click(),
+                                );
+                                if is_current && scroll {
+                                    response.scroll_to_me(Some(Align::Center));
+                                }
+                                if ui.is_rect_visible(cell) {
+                                    self.paint_cell(
+                                        ui.painter(),
+                                        cell,
+                                        path,
+                                        is_current,
+                                        favorites.contains(path),
+                                    );
+                                }
+                                let name = path
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy();
+                                if response.on_hover_text(name).clicked() {
+                                    clicked = Some(path.clone());
+                                }
+                            }
+                        });
+                    });
+            });
+        clicked
+    }
+
+    fn paint_cell(
+        &self,
+        painter: &egui::Painter,
+        cell: Rect,
+        path: &Path,
+        is_current: bool,
+        starred: bool,
+    ) {
+        let image_area = cell.shrink(4.0);
+        match self.thumbnails.get(path) {
+            Some(Thumbnail::Loaded(texture)) => {
+                let size = texture.size_vec2();
+                let scale = (image_area.width() / size.x)
+                    .min(image_area.height() / size.y)
+                    .min(1.0);
+                painter.image(
+                    texture.id(),
+                    Rect::from_center_size(image_area.center(), size * scale),
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+            },
+            Some(Thumbnail::Failed) => {
+                painter.text(
+                    image_area.center(),
+                    Align2::CENTER_CENTER,
+                    "✖",
+                    FontId::proportional(18.0),
+                    Color32::from_rgb(230, 80, 80),
+                );
+            },
+            None => {
+                painter.text(
+                    image_area.center(),
+                    Align2::CENTER_CENTER,
+                    "…",
+                    FontId::proportional(18.0),
+                    Color32::from_gray(120),
+                );
+            },
+        }
+        if starred {
+            painter.text(
+                cell.right_top() + Vec2::new(-5.0, 3.0),
+                Align2::RIGHT_TOP,
+                "★",
+                FontId::proportional(14.0),
+                STAR_COLOR,
+            );
+        }
+        let outline = if is_current {
+            Stroke::new(2.0, Color32::from_gray(200))
+        } else {
+            Stroke::new(1.0, Color32::from_gray(60))
+        };
+        painter.rect_stroke(cell.shrink(1.0), 4.0, outline);
+    }
+}
@@ -0,0 +1,111 @@
+use eframe::egui::{self, Context};
+
+use crate::view_filter::ViewFilter;
+
+/// Bar above the status bar restricting navigation to images with at least
+/// a rating and all of some tags, for culling a folder. The filter applies
+/// while the bar is shown.
+pub struct FilterBar {
+    open:       bool,
+    min_rating: u8,
+    /// Comma separated, as typed
+    tags:       String,
+}
+
+impl FilterBar {
+    pub fn new() -> Self {
+        Self {
+            open: false, min_rating: 0, tags: String::new()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Shows the bar asking for `min_rating` and all of `tags`.
+    pub fn show(&mut self, min_rating: u8, tags: &[String]) {
+        self.open = true;
+        self.min_rating = min_rating.min(5);
+        self.tags = tags.join(", ");
+    }
+
+    /// What the bar asks of the images, which is nothing while hidden
+    pub fn filter(&self) -> ViewFilter {
+        if !self.open {
+            return ViewFilter::default();
+        }
+        ViewFilter {
+            starred:    None,
+            min_rating: self.min_rating,
+            tags:       self
+                .tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    /// Shows the bar with the number of images that passed. Must run before
+    /// the central panel. Returns whether the filter changed.
+    pub fn render(&mut self, ctx: &Context, matching: usize) -> bool {
+        if !self.open {
+            return false;
+        }
+        let mut changed = false;
+        egui::TopBottomPanel::bottom("filter_bar")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Rating");
+                    for stars in 0..=5 {
+                        let label = match stars {
+                            0 => "Any".to_string(),
+                            _ => format!("{}+", "★".repeat(stars as usize)),
+                        };
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.min_rating,
+                                stars,
+                                label,
+                            )
+                            .clicked();
+                    }
+                    ui.separator();
+                    ui.label("Tags");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.tags)
+                            .hint_text("all of, comma separated")
+                            .desired_width(180.0),
+                    );
+                    // Reading the metadata of the folder waits for the
+                    // typing to end
+                    changed |= response.lost_focus();
+                    if ui
+                        .add_enabled(
+                            self.min_rating > 0 || !self.tags.is_empty(),
+                            egui::Button::new("Clear"),
+                        )
+                        .clicked()
+                    {
+                        self.min_rating = 0;
+                        self.tags.clear();
+                        changed = true;
+                    }
+                    ui.separator();
+                    ui.label(match matching {
+                        0 => "No images match".to_string(),
+                        1 => "1 image".to_string(),
+                        count => format!("{} images", count),
+                    });
+                });
+            });
+        changed
+    }
+}
@@ -0,0 +1,174 @@
+use eframe::egui::{self, Context, Key};
+use ferrite_config::{FerriteConfig, NavigationConfig};
+use std::path::{Path, PathBuf};
+
+use crate::navigation;
+
+/// Folders remembered as recent move targets
+const MAX_RECENT: usize = 8;
+
+/// Quick picker for the folder to move the current image into. It offers
+/// the sorting targets, recently used folders and the folders in and next
+/// to the image's own, narrowed down by what is typed; text matching none
+/// of them is taken as a path, relative to the image's folder.
+pub struct FolderPicker {
+    /// Folder of the image being moved while the picker is open
+    folder:   Option<PathBuf>,
+    query:    String,
+    choices:  Vec<PathBuf>,
+    selected: usize,
+    recent:   Vec<PathBuf>,
+    focus:    bool,
+}
+
+impl FolderPicker {
+    pub fn new() -> Self {
+        Self {
+            folder:   None,
+            query:    String::new(),
+            choices:  Vec::new(),
+            selected: 0,
+            recent:   Vec::new(),
+            focus:    false,
+        }
+    }
+
+    /// Opens the picker for moving `image`.
+    pub fn open(&mut self, image: &Path, config: &FerriteConfig) {
+        let Some(folder) = image.parent() else {
+            return;
+        };
+        let mut choices: Vec<PathBuf> = config
+            .sorting
+            .targets
+            .iter()
+            .map(|target| folder.join(&target.folder))
+            .chain(self.recent.iter().cloned())
+            .chain(Self::nearby(folder, &config.navigation))
+            .filter(|choice| choice != folder)
+            .collect();
+        let mut seen = std::collections::HashSet::new();
+        choices.retain(|choice| seen.insert(choice.clone()));
+
+        self.choices = choices;
+        self.folder = Some(folder.to_path_buf());
+        self.query.clear();
+        self.selected = 0;
+        self.focus = true;
+    }
+
+    /// Subfolders of `folder` and those beside it
+    fn nearby(folder: &Path, config: &NavigationConfig) -> Vec<PathBuf> {
+        let mut nearby =
+            navigation::list_folders(folder, config).unwrap_or_default();
+        if let Some(parent) = folder.parent() {
+            nearby.extend(
+                navigation::list_folders(parent, config).unwrap_or_default(),
+            );
+        }
+        nearby
+    }
+
+    /// Adds `folder` to the front of the recent move targets.
+    pub fn remember(&mut self, folder: &Path) {
+        self.recent.retain(|recent| recent != folder);
+        self.recent.insert(0, folder.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    fn matches(&self) -> Vec<&PathBuf> {
+        let query = self.query.trim().to_lowercase();
+        self.choices
+            .iter()
+            .filter(|choice| {
+                choice
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&query)
+            })
+            .collect()
+    }
+
+    /// Shows the picker while open and returns the folder chosen.
+    pub fn render_window(&mut self, ctx: &Context) -> Option<PathBuf> {
+        let folder = self.folder.clone()?;
+        let mut chosen = None;
+        let mut closed = false;
+        egui::Window::new("Move to Folder")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 60.0))
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Folder name or path")
+                        .desired_width(360.0),
+                );
+                if self.focus {
+                    self.focus = false;
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                let matches: Vec<PathBuf> =
+                    self.matches().into_iter().cloned().collect();
+                let (down, up, enter, escape) = ui.input(|i| {
+                    (
+                        i.key_pressed(Key::ArrowDown),
+                        i.key_pressed(Key::ArrowUp),
+                        i.key_pressed(Key::Enter),
+                        i.key_pressed(Key::Escape),
+                    )
+                });
+                if down {
+                    self.selected = (self.selected + 1)
+                        .min(matches.len().saturating_sub(1));
+                }
+                if up {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for (index, choice) in matches.iter().enumerate() {
+                            let label = choice
+                                .strip_prefix(&folder)
+                                .map(|relative| relative.display().to_string())
+                                .unwrap_or_else(|_| {
+                                    choice.display().to_string()
+                                });
+                            if ui
+                                .selectable_label(index == self.selected, label)
+                                .clicked()
+                            {
+                                chosen = Some(choice.clone());
+                            }
+                        }
+                    });
+
+                let typed = self.query.trim();
+                if enter && response.lost_focus() {
+                    chosen = match matches.get(self.selected) {
+                        Some(choice) => Some(choice.clone()),
+                        None if !typed.is_empty() => Some(folder.join(typed)),
+                        None => None,
+                    };
+                }
+                if matches.is_empty() && !typed.is_empty() {
+                    ui.weak(format!(
+                        "Enter moves to {}",
+                        folder.join(typed).display()
+                    ));
+                }
+                closed = escape || ui.button("Cancel").clicked();
+            });
+
+        if chosen.is_some() || closed {
+            self.folder = None;
+        }
+        chosen
+    }
+}
@@ -0,0 +1,752 @@
+use eframe::egui::{
+    self,
+    Align,
+    Align2,
+    Color32,
+    ColorImage,
+    Context,
+    FontId,
+    Key,
+    Modifiers,
+    Pos2,
+    Rect,
+    Sense,
+    Stroke,
+    TextureHandle,
+    TextureOptions,
+    Ui,
+    Vec2,
+};
+use ferrite_config::{DecodingConfig, DitherMode, FerriteConfig, IoConfig};
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    dither,
+    gallery::{self, ClickModifiers, GridLayout, Selection},
+    image as images,
+    linear,
+    warm_start::ThumbnailStore,
+};
+
+const THUMBNAIL_SIZE: u32 = 160;
+const CELL: f32 = 180.0;
+const LABEL_HEIGHT: f32 = 16.0;
+/// Pointer travel before a press on the grid becomes a rubber band
+const BAND_THRESHOLD: f32 = 6.0;
+/// Rows past the visible ones whose thumbnails are made ahead of scrolling
+const LOOKAHEAD_ROWS: f32 = 2.0;
+
+/// What the user asked for in the gallery
+pub enum GalleryAction {
+    /// Show an image in the single-image view
+    Open(PathBuf),
+    /// Show the first image with the second in compare mode
+    Compare(PathBuf, PathBuf),
+    /// Run batch operations on the selection
+    Batch(Vec<PathBuf>),
+    /// Align and average the selection
+    Stack(Vec<PathBuf>),
+    /// Merge the selection as an exposure bracket
+    Hdr(Vec<PathBuf>),
+    /// Stitch the selection into a panorama
+    Panorama(Vec<PathBuf>),
+    /// Files were moved; old and new paths
+    Moved(Vec<(PathBuf, PathBuf)>),
+}
+
+pub(super) enum Thumbnail {
+    Loaded(TextureHandle),
+    Failed,
+}
+
+type Made = (PathBuf, Option<ColorImage>);
+
+/// Thumbnail grid of the current folder with multi-selection. Thumbnails
+/// are made as their rows scroll into view, and only those rows are drawn.
+pub struct GalleryView {
+    active:      bool,
+    selection:   Selection,
+    /// Cell the arrow keys move from, which Enter opens
+    cursor:      Option<PathBuf>,
+    /// The cursor moved and should be scrolled into view
+    reveal:      bool,
+    /// Layout of the grid in the last frame, for stepping with the keys
+    layout:      Option<GridLayout>,
+    thumbnails:  HashMap<PathBuf, Thumbnail>,
+    /// Folder the thumbnails are being made for
+    folder:      Vec<PathBuf>,
+    /// Thumbnails asked for and not made yet
+    requested:   HashSet<PathBuf>,
+    sender:      Sender<Made>,
+    made:        Receiver<Made>,
+    /// Thumbnails read back from disk before the gallery was first shown
+    warming:     Option<Receiver<(PathBuf, ColorImage)>>,
+    store:       Option<Arc<ThumbnailStore>>,
+    move_target: String,
+    status:      Option<String>,
+}
+
+impl GalleryView {
+    pub fn new(config: &FerriteConfig) -> Self {
+        let store = config
+            .cache
+            .warm_start
+            .then(|| ThumbnailStore::new(config.paths.resolve_cache_dir()))
+            .flatten()
+            .map(Arc::new);
+        if let Some(store) = store.clone() {
+            let limit = config.cache.max_thumbnails;
+            thread::spawn(move || {
+                let removed = store.prune(limit);
+                if removed > 0 {
+                    info!("Removed {} unused thumbnails", removed);
+                }
+            });
+        }
+        let (sender, made) = mpsc::channel();
+        Self {
+            active: false,
+            selection: Selection::new(),
+            cursor: None,
+            reveal: false,
+            layout: None,
+            thumbnails: HashMap::new(),
+            folder: Vec::new(),
+            requested: HashSet::new(),
+            sender,
+            made,
+            warming: None,
+            store,
+            move_target: String::new(),
+            status: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        // The keys start out from the image on screen again
+        self.cursor = None;
+    }
+
+    pub fn close(&mut self) {
+        self.active = false;
+    }
+
+    /// Follows files renamed or moved elsewhere in the app.
+    pub fn rename(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        self.selection.rename(renamed);
+        for (old, new) in renamed {
+            if self.cursor.as_ref() == Some(old) {
+                self.cursor = Some(new.clone());
+            }
+            if let Some(thumbnail) = self.thumbnails.remove(old) {
+                self.thumbnails.insert(new.clone(), thumbnail);
+            }
+        }
+    }
+
+    /// Reads the stored thumbnails of `folder` in the background, so the
+    /// gallery opens with them in place, starting with the `recent` files.
+    /// Nothing is decoded.
+    pub fn warm(
+        &mut self,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+        recent: &[PathBuf],
+    ) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let rank: HashMap<&PathBuf, usize> = recent
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (path, i))
+            .collect();
+        let mut folder = folder.to_vec();
+        folder
+            .sort_by_key(|path| rank.get(path).copied().unwrap_or(usize::MAX));
+        let variant = thumbnail_variant(config, THUMBNAIL_SIZE);
+        let dither = config.color.dither;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = folder.into_par_iter().try_for_each_with(
+                sender,
+                |sender, path| match store.load(&path, &variant) {
+                    Some(thumbnail) => {
+                        let image = to_color_image(&thumbnail, dither);
+                        sender.send((path, image))
+                    },
+                    None => Ok(()),
+                },
+            );
+        });
+        self.warming = Some(receiver);
+    }
+
+    /// Forgets the thumbnails of images no longer in a newly listed folder.
+    fn refresh(&mut self, folder: &[PathBuf]) {
+        if self.folder == folder {
+            return;
+        }
+        self.folder = folder.to_vec();
+        self.thumbnails
+            .retain(|path, _| folder.contains(path));
+    }
+
+    /// Makes the missing thumbnails of `paths` on the rayon pool, reading
+    /// stored ones where they are still current.
+    fn request(&mut self, config: &FerriteConfig, paths: &[PathBuf]) {
+        let missing: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| {
+                !self.thumbnails.contains_key(*path)
+                    && !self.requested.contains(*path)
+            })
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.requested.extend(missing.iter().cloned());
+
+        let sender = self.sender.clone();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        let color = config.color.clone();
+        let store = self.store.clone();
+        let variant = thumbnail_variant(config, THUMBNAIL_SIZE);
+        thread::spawn(move || {
+            let _ = missing.into_par_iter().try_for_each_with(
+                sender,
+                |sender, path| {
+                    let thumbnail = make_thumbnail(
+                        &path,
+                        THUMBNAIL_SIZE,
+                        store.as_deref(),
+                        &variant,
+                        &decoding,
+                        &io,
+                        color.linear_light,
+                    )
+                    .map(|thumbnail| to_color_image(&thumbnail, color.dither));
+                    sender.send((path, thumbnail))
+                },
+            );
+        });
+    }
+
+    fn collect_thumbnails(&mut self, ui: &Ui) {
+        if let Some(receiver) = &self.warming {
+            loop {
+                match receiver.try_recv() {
+                    // The folder may have changed since
+                    Ok((path, _))
+                        if !self.folder.is_empty()
+                            && !self.folder.contains(&path) => {},
+                    Ok((path, image)) => {
+                        let texture = ui.ctx().load_texture(
+                            format!("thumbnail-{}", path.display()),
+                            image,
+                            TextureOptions::LINEAR,
+                        );
+                        self.thumbnails
+                            .entry(path)
+                            .or_insert(Thumbnail::Loaded(texture));
+                    },
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.warming = None;
+                        break;
+                    },
+                }
+            }
+        }
+
+        while let Ok((path, image)) = self.made.try_recv() {
+            self.requested.remove(&path);
+            // Made for a folder listed before
+            if !self.folder.contains(&path) {
+                continue;
+            }
+            let thumbnail = match image {
+                Some(image) => Thumbnail::Loaded(ui.ctx().load_texture(
+                    format!("thumbnail-{}", path.display()),
+                    image,
+                    TextureOptions::LINEAR,
+                )),
+                None => Thumbnail::Failed,
+            };
+            self.thumbnails.insert(path, thumbnail);
+        }
+        if !self.requested.is_empty() {
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// Moves the cursor with the arrow keys, extending the selection with
+    /// Shift, and opens the image under it with Enter. Runs before the
+    /// shortcuts, so the keys step through the grid instead of the images.
+    pub fn handle_keys(
+        &mut self,
+        ctx: &Context,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+    ) -> Option<GalleryAction> {
+        let layout = self
+            .layout
+            .filter(|layout| layout.count == folder.len())?;
+        let from = self
+            .cursor
+            .as_ref()
+            .and_then(|cursor| folder.iter().position(|p| p == cursor))
+            .or_else(|| self.selection.focus(folder))
+            .or_else(|| {
+                current.and_then(|current| {
+                    folder.iter().position(|p| p == current)
+                })
+            });
+
+        let open =
+            ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter));
+        if open {
+            let index = from.or((!folder.is_empty()).then_some(0))?;
+            return folder
+                .get(index)
+                .cloned()
+                .map(GalleryAction::Open);
+        }
+
+        for (key, columns, rows) in [
+            (Key::ArrowLeft, -1, 0),
+            (Key::ArrowRight, 1, 0),
+            (Key::ArrowUp, 0, -1),
+            (Key::ArrowDown, 0, 1),
+        ] {
+            let (plain, extend) = ctx.input_mut(|i| {
+                (
+                    i.consume_key(Modifiers::NONE, key),
+                    i.consume_key(Modifiers::SHIFT, key),
+                )
+            });
+            if !plain && !extend {
+                continue;
+            }
+            // The first press lands on the current image
+            let index = match from {
+                Some(from) if self.cursor.is_some() => {
+                    layout.step(from, columns, rows)
+                },
+                Some(from) => from,
+                None if folder.is_empty() => return None,
+                None => 0,
+            };
+            let modifiers = ClickModifiers {
+                toggle: false, range: extend
+            };
+            self.selection.click(folder, index, modifiers);
+            self.cursor = folder.get(index).cloned();
+            self.reveal = true;
+            break;
+        }
+        None
+    }
+
+    /// Draws the gallery into the main panel.
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+    ) -> Option<GalleryAction> {
+        self.refresh(folder);
+        self.collect_thumbnails(ui);
+
+        let mut action = self.render_toolbar(ui, folder);
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if let Some(open) =
+                    self.render_grid(ui, config, folder, current)
+                {
+                    action = Some(GalleryAction::Open(open));
+                }
+            });
+        action
+    }
+
+    fn render_toolbar(
+        &mut self,
+        ui: &mut Ui,
+        folder: &[PathBuf],
+    ) -> Option<GalleryAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} of {} selected",
+                self.selection.len(),
+                folder.len()
+            ));
+            if ui.small_button("All").clicked() {
+                self.selection.select_all(folder);
+            }
+            if ui.small_button("None").clicked() {
+                self.selection.clear();
+            }
+            ui.separator();
+
+            let selected = self.selection.paths(folder);
+            if ui
+                .add_enabled(selected.len() == 2, egui::Button::new("Compare"))
+                .on_disabled_hover_text("Select two images")
+                .clicked()
+            {
+                action = Some(GalleryAction::Compare(
+                    selected[0].clone(),
+                    selected[1].clone(),
+                ));
+            }
+            if ui
+                .add_enabled(
+                    !selected.is_empty(),
+                    egui::Button::new("Batch..."),
+                )
+                .clicked()
+            {
+                action = Some(GalleryAction::Batch(selected.clone()));
+            }
+            if ui
+                .add_enabled(selected.len() >= 2, egui::Button::new("Stack..."))
+                .on_disabled_hover_text("Select two or more frames")
+                .clicked()
+            {
+                action = Some(GalleryAction::Stack(selected.clone()));
+            }
+            if ui
+                .add_enabled(selected.len() >= 2, egui::Button::new("HDR..."))
+                .on_disabled_hover_text("Select the frames of a bracket")
+                .clicked()
+            {
+                action = Some(GalleryAction::Hdr(selected.clone()));
+            }
+            if ui
+                .add_enabled(
+                    selected.len() >= 2,
+                    egui::Button::new("Panorama..."),
+                )
+                .on_disabled_hover_text("Select overlapping photos")
+                .clicked()
+            {
+                action = Some(GalleryAction::Panorama(selected.clone()));
+            }
+            if ui
+                .add_enabled(
+                    !selected.is_empty(),
+                    egui::Button::new("Copy Paths"),
+                )
+                .clicked()
+            {
+                let text: Vec<String> = selected
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                ui.output_mut(|o| o.copied_text = text.join("\n"));
+            }
+            ui.separator();
+
+            ui.label("Move to");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.move_target)
+                    .hint_text("folder")
+                    .desired_width(160.0),
+            );
+            let target = self.move_target.trim();
+            if ui
+                .add_enabled(
+                    !selected.is_empty() && !target.is_empty(),
+                    egui::Button::new("Move"),
+                )
+                .clicked()
+            {
+                let gallery::Moved {
+                    moved,
+                    failed,
+                } = gallery::move_to_folder(&selected, Path::new(target));
+                self.status = Some(match failed.first() {
+                    None => format!("Moved {} files", moved.len()),
+                    Some((path, e)) => format!(
+                        "Moved {}, {} failed ({}: {})",
+                        moved.len(),
+                        failed.len(),
+                        path.display(),
+                        e
+                    ),
+                });
+                if !moved.is_empty() {
+                    action = Some(GalleryAction::Moved(moved));
+                }
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+        action
+    }
+
+    /// Draws the visible thumbnails and handles clicks and rubber bands.
+    /// Returns an image that was double-clicked.
+    fn render_grid(
+        &mut self,
+        ui: &mut Ui,
+        config: &FerriteConfig,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+    ) -> Option<PathBuf> {
+        let width = ui.available_width();
+        let layout = GridLayout::new(width, CELL, folder.len());
+        self.layout = Some(layout);
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(width, layout.rows() as f32 * CELL),
+            Sense::click_and_drag(),
+        );
+        let local = |pos: Pos2| (pos.x - rect.min.x, pos.y - rect.min.y);
+        let modifiers = ui.input(|i| ClickModifiers {
+            toggle: i.modifiers.command,
+            range:  i.modifiers.shift,
+        });
+
+        let mut open = None;
+        if let Some(pos) = response.interact_pointer_pos() {
+            let (x, y) = local(pos);
+            let index = layout.index_at(x, y);
+            if response.double_clicked() {
+                open = index.and_then(|index| folder.get(index).cloned());
+            } else if response.clicked() {
+                match index {
+                    Some(index) => {
+                        self.selection.click(folder, index, modifiers);
+                        self.cursor = folder.get(index).cloned();
+                    },
+                    None if modifiers == ClickModifiers::default() => {
+                        self.selection.clear()
+                    },
+                    None => {},
+                }
+            }
+        }
+
+        let mut band = None;
+        if response.dragged() {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            if let (Some(origin), Some(pos)) =
+                (origin, response.interact_pointer_pos())
+            {
+                if !self.selection.is_banding()
+                    && origin.distance(pos) > BAND_THRESHOLD
+                {
+                    self.selection
+                        .begin_band(modifiers.toggle || modifiers.range);
+                }
+                if self.selection.is_banding() {
+                    self.selection.update_band(
+                        folder,
+                        layout.indices_in(local(origin), local(pos)),
+                    );
+                    band = Some(Rect::from_two_pos(origin, pos));
+                    ui.scroll_to_rect(
+                        Rect::from_center_size(pos, Vec2::splat(1.0)),
+                        None,
+                    );
+                }
+            }
+        }
+        if response.drag_released() {
+            self.selection.end_band();
+        }
+
+        if std::mem::take(&mut self.reveal) {
+            let cursor = self
+                .cursor
+                .as_ref()
+                .and_then(|cursor| folder.iter().position(|p| p == cursor));
+            if let Some(index) = cursor {
+                let (x, y) = layout.origin(index);
+                let cell = Rect::from_min_size(
+                    rect.min + Vec2::new(x, y),
+                    Vec2::splat(CELL),
+                );
+                ui.scroll_to_rect(cell, Some(Align::Center));
+            }
+        }
+
+        let painter = ui.painter_at(rect);
+        let visible = ui.clip_rect();
+        let (top, bottom) =
+            (visible.min.y - rect.min.y, visible.max.y - rect.min.y);
+        let ahead = LOOKAHEAD_ROWS * CELL;
+        self.request(
+            config,
+            &folder[layout.rows_between(top - ahead, bottom + ahead)],
+        );
+        for index in layout.rows_between(top, bottom) {
+            let path = &folder[index];
+            let (x, y) = layout.origin(index);
+            let cell = Rect::from_min_size(
+                rect.min + Vec2::new(x, y),
+                Vec2::splat(CELL),
+            )
+            .shrink(4.0);
+            self.paint_cell(
+                &painter,
+                cell,
+                path,
+                current == Some(path.as_path()),
+            );
+        }
+        if let Some(band) = band {
+            painter.rect(
+                band,
+                0.0,
+                Color32::from_rgba_unmultiplied(100, 150, 255, 40),
+                Stroke::new(1.0, Color32::from_rgb(100, 150, 255)),
+            );
+        }
+        open
+    }
+
+    fn paint_cell(
+        &self,
+        painter: &egui::Painter,
+        cell: Rect,
+        path: &Path,
+        is_current: bool,
+    ) {
+        let selected = self.selection.contains(path);
+        if selected {
+            painter.rect_filled(cell, 4.0, Color32::from_rgb(40, 70, 120));
+        }
+        let outline = if selected {
+            Stroke::new(2.0, Color32::from_rgb(100, 150, 255))
+        } else if is_current {
+            Stroke::new(2.0, Color32::from_gray(200))
+        } else {
+            Stroke::new(1.0, Color32::from_gray(60))
+        };
+        painter.rect_stroke(cell, 4.0, outline);
+
+        let image_area = Rect::from_min_max(
+            cell.min,
+            Pos2::new(cell.max.x, cell.max.y - LABEL_HEIGHT),
+        )
+        .shrink(4.0);
+        match self.thumbnails.get(path) {
+            Some(Thumbnail::Loaded(texture)) => {
+                let size = texture.size_vec2();
+                let scale = (image_area.width() / size.x)
+                    .min(image_area.height() / size.y)
+                    .min(1.0);
+                painter.image(
+                    texture.id(),
+                    Rect::from_center_size(image_area.center(), size * scale),
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+            },
+            Some(Thumbnail::Failed) => {
+                painter.text(
+                    image_area.center(),
+                    Align2::CENTER_CENTER,
+                    "✖",
+                    FontId::proportional(24.0),
+                    Color32::from_rgb(230, 80, 80),
+                );
+            },
+            None => {
+                painter.text(
+                    image_area.center(),
+                    Align2::CENTER_CENTER,
+                    "…",
+                    FontId::proportional(24.0),
+                    Color32::from_gray(120),
+                );
+            },
+        }
+
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        painter.with_clip_rect(cell).text(
+            Pos2::new(cell.center().x, cell.max.y - LABEL_HEIGHT / 2.0 - 2.0),
+            Align2::CENTER_CENTER,
+            name,
+            FontId::proportional(12.0),
+            Color32::from_gray(220),
+        );
+    }
+}
+
+/// Tells apart stored thumbnails made with different settings
+pub(super) fn thumbnail_variant(config: &FerriteConfig, size: u32) -> String {
+    format!(
+        "{}{}",
+        size,
+        if config.color.linear_light { "-linear" } else { "" }
+    )
+}
+
+/// The stored thumbnail of `path` if still current, else one decoded and
+/// scaled to fit `size` and stored for next time.
+pub(super) fn make_thumbnail(
+    path: &Path,
+    size: u32,
+    store: Option<&ThumbnailStore>,
+    variant: &str,
+    decoding: &DecodingConfig,
+    io: &IoConfig,
+    linear_light: bool,
+) -> Option<image::DynamicImage> {
+    if let Some(thumbnail) = store.and_then(|store| store.load(path, variant)) {
+        return Some(thumbnail);
+    }
+    match images::decode_file(path, decoding, io) {
+        Ok(image) => {
+            let thumbnail = linear::thumbnail(&image, size, size, linear_light);
+            if let Some(store) = store {
+                store.save(path, variant, &thumbnail);
+            }
+            Some(thumbnail)
+        },
+        Err(e) => {
+            warn!("No thumbnail for {}: {}", path.display(), e);
+            None
+        },
+    }
+}
+
+pub(super) fn to_color_image(
+    thumbnail: &image::DynamicImage,
+    dither: DitherMode,
+) -> ColorImage {
+    let rgba = dither::to_display(thumbnail, dither);
+    ColorImage::from_rgba_unmultiplied(
+        [rgba.width() as usize, rgba.height() as usize],
+        rgba.as_flat_samples().as_slice(),
+    )
+}
@@ -0,0 +1,91 @@
+use eframe::egui::{Color32, Painter, Pos2, Rect, Stroke, Vec2};
+
+/// 1/φ, the golden section of a unit length
+const GOLDEN: f32 = 0.618_034;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridKind {
+    RuleOfThirds,
+    GoldenRatio,
+    CenterCross,
+}
+
+impl GridKind {
+    /// Line positions as fractions of the image width and height
+    fn fractions(&self) -> &'static [f32] {
+        match self {
+            GridKind::RuleOfThirds => &[1.0 / 3.0, 2.0 / 3.0],
+            GridKind::GoldenRatio => &[1.0 - GOLDEN, GOLDEN],
+            GridKind::CenterCross => &[],
+        }
+    }
+}
+
+/// Composition guides drawn over the image. They follow the image rectangle,
+/// so they stay aligned at every zoom level and pan offset.
+pub struct GridOverlay {
+    kind: Option<GridKind>,
+}
+
+impl GridOverlay {
+    pub fn new() -> Self {
+        Self {
+            kind: None
+        }
+    }
+
+    /// Steps through the grids and back to none.
+    pub fn cycle(&mut self) {
+        self.kind = match self.kind {
+            None => Some(GridKind::RuleOfThirds),
+            Some(GridKind::RuleOfThirds) => Some(GridKind::GoldenRatio),
+            Some(GridKind::GoldenRatio) => Some(GridKind::CenterCross),
+            Some(GridKind::CenterCross) => None,
+        };
+    }
+
+    pub fn paint(&self, painter: &Painter, image_rect: Rect) {
+        let Some(kind) = self.kind else {
+            return;
+        };
+        let painter = painter.with_clip_rect(image_rect);
+        let shadow = Stroke::new(3.0, Color32::from_black_alpha(100));
+        let line = Stroke::new(1.0, Color32::from_white_alpha(200));
+
+        let segments: Vec<[Pos2; 2]> = match kind {
+            GridKind::CenterCross => {
+                // Keep the cross short so it does not read as quadrants
+                let center = image_rect.center();
+                let arm = image_rect.size().min_elem() * 0.05;
+                vec![[center - Vec2::X * arm, center + Vec2::X * arm], [
+                    center - Vec2::Y * arm,
+                    center + Vec2::Y * arm,
+                ]]
+            },
+            _ => kind
+                .fractions()
+                .iter()
+                .flat_map(|&fraction| {
+                    let x = image_rect.min.x + image_rect.width() * fraction;
+                    let y = image_rect.min.y + image_rect.height() * fraction;
+                    [
+                        [
+                            Pos2::new(x, image_rect.min.y),
+                            Pos2::new(x, image_rect.max.y),
+                        ],
+                        [
+                            Pos2::new(image_rect.min.x, y),
+                            Pos2::new(image_rect.max.x, y),
+                        ],
+                    ]
+                })
+                .collect(),
+        };
+
+        for stroke in [shadow, line] {
+            for segment in &segments {
+                painter.line_segment(*segment, stroke);
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use eframe::egui::{
+    Align2,
+    Color32,
+    Context,
+    CursorIcon,
+    FontId,
+    Pos2,
+    Rect,
+    Stroke,
+    Ui,
+    Vec2,
+};
+
+use crate::ui::overlay::ImageGeometry;
+
+/// Width of the strips along the top and left image edges that guides are
+/// dragged out of
+const RULER_WIDTH: f32 = 14.0;
+/// Distance in screen points at which a guide can be grabbed
+const GRAB_DISTANCE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    /// A horizontal line at an image row
+    Horizontal,
+    /// A vertical line at an image column
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Guide {
+    axis:     Axis,
+    /// Image pixel row or column
+    position: u32,
+}
+
+/// Horizontal and vertical guide lines dragged onto the image from rulers
+/// along its edges. Positions snap to whole pixels and are kept for the
+/// rest of the session, across images.
+pub struct GuideOverlay {
+    visible:  bool,
+    guides:   Vec<Guide>,
+    dragging: Option<usize>,
+    /// Visible part of the image in the last frame, for hit testing before
+    /// the image is drawn
+    last:     Option<(ImageGeometry, Rect)>,
+}
+
+impl GuideOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible:  false,
+            guides:   Vec::new(),
+            dragging: None,
+            last:     None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.dragging = None;
+    }
+
+    /// Whether a press at the pointer would grab a guide or ruler, so the
+    /// image should not start panning.
+    pub fn wants_pointer(&self, ctx: &Context) -> bool {
+        if !self.visible {
+            return false;
+        }
+        if self.dragging.is_some() {
+            return true;
+        }
+        let (Some(pos), Some((geometry, visible))) =
+            (ctx.input(|i| i.pointer.hover_pos()), self.last)
+        else {
+            return false;
+        };
+        visible.contains(pos)
+            && (in_rulers(visible, pos)
+                || self.guide_at(geometry, pos).is_some())
+    }
+
+    fn guide_at(&self, geometry: ImageGeometry, pos: Pos2) -> Option<usize> {
+        self.guides.iter().position(|guide| {
+            let screen = screen_position(geometry, guide);
+            match guide.axis {
+                Axis::Horizontal => (pos.y - screen).abs() <= GRAB_DISTANCE,
+                Axis::Vertical => (pos.x - screen).abs() <= GRAB_DISTANCE,
+            }
+        })
+    }
+
+    /// Handles dragging guides and draws them with their pixel positions.
+    pub fn interact(&mut self, ui: &mut Ui, geometry: ImageGeometry) {
+        if !self.visible {
+            self.last = None;
+            return;
+        }
+        let visible = geometry.rect.intersect(ui.clip_rect());
+        self.last = Some((geometry, visible));
+
+        let (pointer, pressed, down) = ui.input(|i| {
+            (
+                i.pointer.interact_pos(),
+                i.pointer.primary_pressed(),
+                i.pointer.primary_down(),
+            )
+        });
+        if let Some(pos) =
+            pointer.filter(|_| pressed && ui.rect_contains_pointer(visible))
+        {
+            self.dragging = self.guide_at(geometry, pos).or_else(|| {
+                let axis = if pos.y < visible.min.y + RULER_WIDTH {
+                    Axis::Horizontal
+                } else if pos.x < visible.min.x + RULER_WIDTH {
+                    Axis::Vertical
+                } else {
+                    return None;
+                };
+                self.guides.push(Guide {
+                    axis,
+                    position: 0,
+                });
+                Some(self.guides.len() - 1)
+            });
+        }
+
+        if let (Some(index), Some(pos)) = (self.dragging, pointer) {
+            let (x, y) = geometry.to_image_clamped(pos);
+            let guide = &mut self.guides[index];
+            guide.position = match guide.axis {
+                Axis::Horizontal => y.round() as u32,
+                Axis::Vertical => x.round() as u32,
+            };
+            if !down {
+                // Dropping a guide outside the image removes it
+                if !geometry.rect.contains(pos) {
+                    self.guides.remove(index);
+                }
+                self.dragging = None;
+            }
+        }
+
+        let hovering = pointer.is_some_and(|pos| {
+            visible.contains(pos)
+                && (in_rulers(visible, pos)
+                    || self.guide_at(geometry, pos).is_some())
+        });
+        if hovering || self.dragging.is_some() {
+            ui.ctx().set_cursor_icon(CursorIcon::Grab);
+        }
+
+        self.paint(ui, geometry, visible);
+    }
+
+    fn paint(&self, ui: &Ui, geometry: ImageGeometry, visible: Rect) {
+        let painter = ui.painter_at(visible);
+        let ruler = Color32::from_black_alpha(110);
+        painter.rect_filled(
+            Rect::from_min_size(
+                visible.min,
+                Vec2::new(visible.width(), RULER_WIDTH),
+            ),
+            0.0,
+            ruler,
+        );
+        painter.rect_filled(
+            Rect::from_min_size(
+                visible.min + Vec2::Y * RULER_WIDTH,
+                Vec2::new(RULER_WIDTH, visible.height() - RULER_WIDTH),
+            ),
+            0.0,
+            ruler,
+        );
+
+        let color = Color32::from_rgb(0, 200, 255);
+        let font = FontId::monospace(11.0);
+        for (index, guide) in self.guides.iter().enumerate() {
+            let screen = screen_position(geometry, guide);
+            let width = if self.dragging == Some(index) { 2.0 } else { 1.0 };
+            let (line, label_pos, align) = match guide.axis {
+                Axis::Horizontal => (
+                    [
+                        Pos2::new(visible.min.x, screen),
+                        Pos2::new(visible.max.x, screen),
+                    ],
+                    Pos2::new(visible.min.x + RULER_WIDTH + 2.0, screen - 2.0),
+                    Align2::LEFT_BOTTOM,
+                ),
+                Axis::Vertical => (
+                    [
+                        Pos2::new(screen, visible.min.y),
+                        Pos2::new(screen, visible.max.y),
+                    ],
+                    Pos2::new(screen + 3.0, visible.min.y + RULER_WIDTH + 2.0),
+                    Align2::LEFT_TOP,
+                ),
+            };
+            painter.line_segment(line, Stroke::new(width, color));
+            painter.text(
+                label_pos,
+                align,
+                format!("{} px", guide.position),
+                font.clone(),
+                color,
+            );
+        }
+    }
+}
+
+fn in_rulers(visible: Rect, pos: Pos2) -> bool {
+    pos.y < visible.min.y + RULER_WIDTH || pos.x < visible.min.x + RULER_WIDTH
+}
+
+fn screen_position(geometry: ImageGeometry, guide: &Guide) -> f32 {
+    let position = guide.position as f32;
+    match guide.axis {
+        Axis::Horizontal => geometry.to_screen((0.0, position)).y,
+        Axis::Vertical => geometry.to_screen((position, 0.0)).x,
+    }
+}
@@ -0,0 +1,343 @@
+use eframe::egui::{self, Context, Ui};
+use ferrite_config::FerriteConfig;
+use image::{DynamicImage, Rgb32FImage};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    batch::{self, OutputFormat},
+    hdr::{self, Frame, HdrError, ToneMap},
+    image::{self as images, ImageManager},
+};
+
+struct HdrResult {
+    /// First frame, which the result is shown in place of
+    source:  PathBuf,
+    hdr:     Rgb32FImage,
+    preview: DynamicImage,
+}
+
+/// Window listing the exposure brackets of the folder and merging one into
+/// a tone-mapped HDR image.
+pub struct HdrPanel {
+    open:          bool,
+    /// Folder the brackets were found in
+    scanned:       Option<Vec<PathBuf>>,
+    scan:          Option<Receiver<Vec<Vec<PathBuf>>>>,
+    brackets:      Vec<Vec<PathBuf>>,
+    /// Frames chosen in the gallery
+    chosen:        Option<Vec<PathBuf>>,
+    tone_map:      ToneMap,
+    ev:            f32,
+    pending:       Option<(PathBuf, Receiver<Result<Rgb32FImage, HdrError>>)>,
+    result:        Option<HdrResult>,
+    export_format: OutputFormat,
+    status:        Option<String>,
+}
+
+impl HdrPanel {
+    pub fn new() -> Self {
+        Self {
+            open:          false,
+            scanned:       None,
+            scan:          None,
+            brackets:      Vec::new(),
+            chosen:        None,
+            tone_map:      ToneMap::default(),
+            ev:            0.0,
+            pending:       None,
+            result:        None,
+            export_format: OutputFormat::Png,
+            status:        None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window with `frames` as a bracket of their own.
+    pub fn open_with(&mut self, frames: Vec<PathBuf>) {
+        self.chosen = Some(frames);
+        self.open = true;
+    }
+
+    /// Reads the EXIF data of a newly listed folder in the background.
+    fn refresh(&mut self, folder: &[PathBuf]) {
+        if self.scanned.as_deref() == Some(folder) {
+            return;
+        }
+        let paths = folder.to_vec();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let frames = paths
+                .iter()
+                .filter_map(|path| Frame::read(path))
+                .collect();
+            let brackets = hdr::find_brackets(frames)
+                .into_iter()
+                .map(|bracket| bracket.into_iter().map(|f| f.path).collect())
+                .collect();
+            let _ = sender.send(brackets);
+        });
+        self.scanned = Some(folder.to_vec());
+        self.scan = Some(receiver);
+    }
+
+    fn start(&mut self, frames: Vec<PathBuf>, config: &FerriteConfig) {
+        let (sender, receiver) = mpsc::channel();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        let source = frames[0].clone();
+        thread::spawn(move || {
+            let _ = sender.send(hdr::merge_files(&frames, &decoding, &io));
+        });
+        self.pending = Some((source, receiver));
+        self.result = None;
+        self.status = None;
+    }
+
+    /// Collects brackets and merge results. The result is shown in place
+    /// of the first frame of its bracket.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        folder: &[PathBuf],
+    ) {
+        if !self.open {
+            return;
+        }
+        self.refresh(folder);
+        if let Some(scan) = &self.scan {
+            match scan.try_recv() {
+                Ok(brackets) => {
+                    info!("Found {} exposure brackets", brackets.len());
+                    self.brackets = brackets;
+                    self.scan = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.scan = None,
+            }
+        }
+
+        let current = image_manager.current_path().cloned();
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|r| Some(&r.source) != current.as_ref())
+        {
+            self.result = None;
+        }
+
+        let Some((source, receiver)) = &self.pending else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(hdr)) => {
+                let preview = DynamicImage::ImageRgb8(hdr::tone_map(
+                    &hdr,
+                    self.tone_map,
+                    self.ev,
+                ));
+                if current.as_ref() == Some(source) {
+                    image_manager.show_derived(preview.clone());
+                }
+                self.result = Some(HdrResult {
+                    source: source.clone(),
+                    hdr,
+                    preview,
+                });
+                self.pending = None;
+            },
+            Ok(Err(e)) => {
+                warn!("HDR merge failed: {}", e);
+                self.status = Some(e.to_string());
+                self.pending = None;
+            },
+            Err(TryRecvError::Empty) => ctx.request_repaint(),
+            Err(TryRecvError::Disconnected) => {
+                self.status = Some("Merge thread terminated".to_string());
+                self.pending = None;
+            },
+        }
+    }
+
+    /// Shows the window. Returns the first frame of a bracket being merged,
+    /// for the app to open.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        let mut open = self.open;
+        let mut opened = None;
+        egui::Window::new("HDR Merge")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                opened = self.render_brackets(ui, config);
+                ui.separator();
+                self.render_result(ui, image_manager, config);
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+        opened
+    }
+
+    fn render_brackets(
+        &mut self,
+        ui: &mut Ui,
+        config: &FerriteConfig,
+    ) -> Option<PathBuf> {
+        if self.scan.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Looking for exposure brackets...");
+            });
+        } else if self.brackets.is_empty() && self.chosen.is_none() {
+            ui.label(
+                "No brackets found. Select frames in the gallery (T) to merge \
+                 them anyway.",
+            );
+        }
+
+        let mut merge = None;
+        egui::ScrollArea::vertical()
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for bracket in self.chosen.iter().chain(&self.brackets) {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                self.pending.is_none(),
+                                egui::Button::new("Merge"),
+                            )
+                            .clicked()
+                        {
+                            merge = Some(bracket.clone());
+                        }
+                        let names: Vec<_> = bracket
+                            .iter()
+                            .map(|p| {
+                                p.file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                            })
+                            .collect();
+                        ui.label(names.join(", "));
+                    });
+                }
+            });
+
+        if self.pending.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Merging...");
+            });
+        }
+        let frames = merge?;
+        let first = frames[0].clone();
+        self.start(frames, config);
+        Some(first)
+    }
+
+    fn render_result(
+        &mut self,
+        ui: &mut Ui,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Tone mapping:");
+            for method in ToneMap::ALL {
+                changed |= ui
+                    .selectable_value(
+                        &mut self.tone_map,
+                        method,
+                        method.label(),
+                    )
+                    .changed();
+            }
+        });
+        changed |= ui
+            .add(egui::Slider::new(&mut self.ev, -4.0..=4.0).text("EV"))
+            .changed();
+
+        let Some(result) = &mut self.result else {
+            return;
+        };
+        let is_current = image_manager.current_path() == Some(&result.source);
+        if changed {
+            result.preview = DynamicImage::ImageRgb8(hdr::tone_map(
+                &result.hdr,
+                self.tone_map,
+                self.ev,
+            ));
+            if is_current {
+                image_manager.show_derived(result.preview.clone());
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if is_current && image_manager.is_showing_derived() {
+                if ui.button("Show First Frame").clicked() {
+                    image_manager.restore_original();
+                }
+            } else if is_current && ui.button("Show Merged").clicked() {
+                image_manager.show_derived(result.preview.clone());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("hdr-export-format")
+                .selected_text(self.export_format.extension().to_uppercase())
+                .show_ui(ui, |ui| {
+                    for &format in OutputFormat::AVAILABLE {
+                        ui.selectable_value(
+                            &mut self.export_format,
+                            format,
+                            format.extension().to_uppercase(),
+                        );
+                    }
+                });
+            if ui.button("Export Tone-Mapped").clicked() {
+                let path = images::derived_path(&result.source, "hdr")
+                    .with_extension(self.export_format.extension());
+                self.status = Some(
+                    match batch::save(
+                        &result.preview,
+                        &path,
+                        self.export_format,
+                        config.batch.jpeg_quality,
+                    ) {
+                        Ok(()) => format!("Saved {}", path.display()),
+                        Err(e) => format!("Export failed: {}", e),
+                    },
+                );
+            }
+            #[cfg(feature = "exr")]
+            if ui.button("Export EXR").clicked() {
+                let path = images::derived_path(&result.source, "hdr")
+                    .with_extension("exr");
+                self.status = Some(match hdr::save_exr(&result.hdr, &path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use eframe::egui::{
+    self,
+    epaint::Mesh,
+    Color32,
+    Context,
+    Pos2,
+    Rect,
+    Sense,
+    Shape,
+    Stroke,
+    Vec2,
+};
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use crate::histogram::{self, Channel, Histogram};
+
+const PLOT_SIZE: Vec2 = Vec2::new(256.0, 128.0);
+
+/// Which image a histogram was computed for: the file, whether an image
+/// derived from it was on screen, and the size
+type Source = (Option<PathBuf>, bool, (u32, u32));
+
+/// Window plotting the luminance and RGB histograms of the image on screen,
+/// computed in the background whenever it changes.
+pub struct HistogramPanel {
+    open:        bool,
+    source:      Option<Source>,
+    pending:     Option<Receiver<Histogram>>,
+    histogram:   Option<Histogram>,
+    shown:       [bool; 4],
+    logarithmic: bool,
+}
+
+impl HistogramPanel {
+    pub fn new() -> Self {
+        Self {
+            open:        false,
+            source:      None,
+            pending:     None,
+            histogram:   None,
+            shown:       [true; 4],
+            logarithmic: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn refresh(&mut self, source: Source, image: &DynamicImage) {
+        if self.source.as_ref() == Some(&source) {
+            return;
+        }
+        let image = image.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(histogram::compute(&image));
+        });
+        self.source = Some(source);
+        self.pending = Some(receiver);
+    }
+
+    /// `derived` tells whether `image` was made from the file at
+    /// `current_path`, as by a LUT or lens correction.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        current_path: Option<&PathBuf>,
+        derived: bool,
+        image: Option<&DynamicImage>,
+    ) {
+        if !self.open {
+            return;
+        }
+        match image {
+            Some(image) => {
+                let source = (
+                    current_path.cloned(),
+                    derived,
+                    (image.width(), image.height()),
+                );
+                self.refresh(source, image);
+            },
+            None => {
+                self.source = None;
+                self.pending = None;
+                self.histogram = None;
+            },
+        }
+        if let Some(receiver) = &self.pending {
+            match receiver.try_recv() {
+                Ok(histogram) => {
+                    self.histogram = Some(histogram);
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Histogram")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for channel in Channel::ALL {
+                        ui.checkbox(
+                            &mut self.shown[channel as usize],
+                            channel.label(),
+                        );
+                    }
+                });
+                ui.checkbox(&mut self.logarithmic, "Logarithmic");
+
+                let (rect, _) =
+                    ui.allocate_exact_size(PLOT_SIZE, Sense::hover());
+                let painter = ui.painter();
+                painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+                if let Some(histogram) = &self.histogram {
+                    painter.add(self.plot(histogram, rect));
+                }
+                painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::GRAY));
+
+                if image.is_none() {
+                    ui.label("Open an image to see its histogram.");
+                } else if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Counting pixels...");
+                    });
+                }
+            });
+        self.open = open;
+    }
+
+    /// One translucent bar per value and shown channel, scaled to the
+    /// highest bar
+    fn plot(&self, histogram: &Histogram, rect: Rect) -> Shape {
+        let channels: Vec<_> = Channel::ALL
+            .into_iter()
+            .filter(|&channel| self.shown[channel as usize])
+            .collect();
+        let scale = |count: u32| match self.logarithmic {
+            true => (count as f32).ln_1p(),
+            false => count as f32,
+        };
+        let peak = scale(histogram.peak(channels.iter().copied()));
+        let mut mesh = Mesh::default();
+        if peak <= 0.0 {
+            return Shape::mesh(mesh);
+        }
+        let width = rect.width() / 256.0;
+        for channel in channels {
+            let color = match channel {
+                Channel::Luminance => Color32::from_white_alpha(110),
+                Channel::Red => {
+                    Color32::from_rgba_unmultiplied(255, 60, 60, 110)
+                },
+                Channel::Green => {
+                    Color32::from_rgba_unmultiplied(60, 255, 60, 110)
+                },
+                Channel::Blue => {
+                    Color32::from_rgba_unmultiplied(60, 110, 255, 110)
+                },
+            };
+            for (value, &count) in histogram.bins(channel).iter().enumerate() {
+                let height = scale(count) / peak * rect.height();
+                if height <= 0.0 {
+                    continue;
+                }
+                let x = rect.left() + value as f32 * width;
+                mesh.add_colored_rect(
+                    Rect::from_min_max(
+                        Pos2::new(x, rect.bottom() - height),
+                        Pos2::new(x + width, rect.bottom()),
+                    ),
+                    color,
+                );
+            }
+        }
+        Shape::mesh(mesh)
+    }
+}
@@ -1,70 +1,336 @@
-use crate::ui::zoom::ZoomHandler;
-use eframe::egui::{self, Context, Rect, Ui};
-use egui::{Pos2, Vec2};
+use crate::ui::{
+    render::ViewInput,
+    zoom::{FitMode, ZoomHandler},
+};
+use eframe::egui::{
+    self,
+    Context,
+    Event,
+    Key,
+    Modifiers,
+    MouseWheelUnit,
+    PointerButton,
+    Rect,
+    Ui,
+    Vec2,
+};
+use ferrite_config::{
+    Action,
+    ControlsConfig,
+    KeyChord,
+    Keymap,
+    WheelAction,
+    ZoomConfig,
+};
 
-pub fn handle_input(
+/// Points of travel per line of a notched mouse wheel, as egui counts them
+pub const POINTS_PER_LINE: f32 = 50.0;
+
+/// Points the image moves by with each panning key press
+const KEY_PAN_STEP: f32 = 60.0;
+
+/// Takes the chords of the panning actions, the arrow keys by default,
+/// while the image is larger than the view, panning it instead of doing
+/// what else they are bound to. Runs before the shortcuts.
+pub fn pan_with_keys(
     ctx: &Context,
-    ui: &Ui,
+    keymap: &Keymap,
     zoom_handler: &mut ZoomHandler,
-    panel_rect: Rect,
 ) {
-    // Keyboard zoom controls
-    if ctx.input(|i| {
-        i.key_pressed(egui::Key::Equals)
-            || i.key_pressed(egui::Key::Plus)
-            || i.key_pressed(egui::Key::W)
-    }) {
-        handle_zoom(ui, zoom_handler, 1.0);
+    if !zoom_handler.is_zoomed_in() {
+        return;
     }
-    if ctx.input(|i| {
-        i.key_pressed(egui::Key::Minus) || i.key_pressed(egui::Key::S)
-    }) {
-        handle_zoom(ui, zoom_handler, -1.0);
+    // Moving the view left brings the image right
+    let directions = [
+        (Action::PanLeft, Vec2::new(1.0, 0.0)),
+        (Action::PanRight, Vec2::new(-1.0, 0.0)),
+        (Action::PanUp, Vec2::new(0.0, 1.0)),
+        (Action::PanDown, Vec2::new(0.0, -1.0)),
+    ];
+    let (chords, moves): (Vec<KeyChord>, Vec<Vec2>) = directions
+        .iter()
+        .flat_map(|&(action, direction)| {
+            keymap
+                .chords(action)
+                .iter()
+                .map(move |&chord| (chord, direction))
+        })
+        .unzip();
+    let delta = take_chords(ctx, &chords)
+        .into_iter()
+        .fold(Vec2::ZERO, |delta, index| delta + moves[index] * KEY_PAN_STEP);
+    if delta != Vec2::ZERO {
+        zoom_handler.add_offset(delta);
+        zoom_handler.keep_in_view();
     }
+}
 
-    // Scroll wheel zoom
-    let scroll_delta = ctx.input(|i| i.raw_scroll_delta.y);
-    if scroll_delta != 0.0 {
-        handle_zoom(ui, zoom_handler, scroll_delta.into());
-    }
+/// Whether a wheel event is two-finger scrolling on a touchpad
+fn is_swipe(unit: MouseWheelUnit, modifiers: &Modifiers) -> bool {
+    unit == MouseWheelUnit::Point && modifiers.is_none()
+}
 
-    // Reset zoom and position
-    if ctx.input(|i| i.key_pressed(egui::Key::Num0)) {
-        zoom_handler.reset();
-    }
+/// Upward wheel travel this frame, in points, of the turns bound to
+/// `action` by the modifiers held during each. Two-finger scrolling is
+/// left out while it is taken for gestures.
+pub fn wheel_travel(
+    ctx: &Context,
+    controls: &ControlsConfig,
+    action: WheelAction,
+) -> f32 {
+    let bindings = &controls.wheel;
+    let gestures = controls.touchpad.gestures;
+    ctx.input(|i| {
+        i.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::MouseWheel {
+                    unit,
+                    modifiers,
+                    ..
+                } if gestures && is_swipe(*unit, modifiers) => None,
+                Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers,
+                } => {
+                    let bound = bindings.action(
+                        modifiers.ctrl || modifiers.command,
+                        modifiers.shift,
+                        modifiers.alt,
+                    );
+                    let points = match unit {
+                        MouseWheelUnit::Point => delta.y,
+                        MouseWheelUnit::Line => delta.y * POINTS_PER_LINE,
+                        MouseWheelUnit::Page => {
+                            delta.y * i.screen_rect.height()
+                        },
+                    };
+                    (bound == action).then_some(points)
+                },
+                _ => None,
+            })
+            .sum()
+    })
 }
 
-fn handle_zoom(ui: &Ui, zoom_handler: &mut ZoomHandler, scroll_delta: f64) {
-    // Only handle zoom if we have a cursor position
-    if let Some(mouse_pos) = ui.input(|i| i.pointer.hover_pos()) {
-        // Calculate the current center of the image
-        let panel_rect = ui.available_rect_before_wrap();
-        let old_center = panel_rect.center() + zoom_handler.offset();
+/// The actions whose chords or mouse buttons were pressed this frame, in
+/// the order pressed
+pub fn pressed_actions(
+    ctx: &Context,
+    controls: &ControlsConfig,
+) -> Vec<Action> {
+    let keymap = &controls.keymap;
+    let buttons = &controls.buttons;
+    ctx.input(|i| {
+        // The window reports clipboard chords as commands instead of keys
+        let command =
+            |key| keymap.action(key, true, i.modifiers.shift, i.modifiers.alt);
+        i.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => keymap.action(
+                    *key,
+                    modifiers.ctrl || modifiers.command,
+                    modifiers.shift,
+                    modifiers.alt,
+                ),
+                Event::Copy => command(Key::C),
+                Event::Cut => command(Key::X),
+                // Only sent while the clipboard holds text
+                Event::Paste(_) => command(Key::V),
+                Event::PointerButton {
+                    button,
+                    pressed: true,
+                    ..
+                } => match button {
+                    PointerButton::Middle => buttons.middle,
+                    PointerButton::Extra1 => buttons.back,
+                    PointerButton::Extra2 => buttons.forward,
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    })
+}
 
-        // Calculate new zoom level with smooth stepping
-        let zoom_step = if scroll_delta > 0.0 { 1.1 } else { 0.9 };
-        let new_zoom = (zoom_handler.zoom_level() * zoom_step).clamp(0.1, 10.0);
+/// Indices of the `chords` pressed this frame. Their key presses are taken
+/// out of the input, so the keymap does not act on them as well.
+pub fn take_chords(ctx: &Context, chords: &[KeyChord]) -> Vec<usize> {
+    let mut pressed = Vec::new();
+    ctx.input_mut(|i| {
+        i.events.retain(|event| {
+            let Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } = event
+            else {
+                return true;
+            };
+            let chord = KeyChord {
+                key:   *key,
+                ctrl:  modifiers.ctrl || modifiers.command,
+                shift: modifiers.shift,
+                alt:   modifiers.alt,
+            };
+            match chords.iter().position(|c| *c == chord) {
+                Some(index) => {
+                    pressed.push(index);
+                    false
+                },
+                None => true,
+            }
+        })
+    });
+    pressed
+}
 
-        // Find the vector from cursor to image center that we want to preserve
-        let mouse_to_center = mouse_pos - old_center;
+pub fn handle_input(
+    ctx: &Context,
+    ui: &Ui,
+    zoom_handler: &mut ZoomHandler,
+    panel_rect: Rect,
+    controls: &ControlsConfig,
+    zoom: &ZoomConfig,
+    view_input: ViewInput,
+) {
+    // Keyboard and mouse button view controls, unless a text field is
+    // being edited
+    let actions = if !view_input.keys || ctx.wants_keyboard_input() {
+        Vec::new()
+    } else {
+        pressed_actions(ctx, controls)
+    };
+    // Keys step through the configured levels if asked to, else by 10%
+    let key_step = |zoom_handler: &ZoomHandler, zoom_in| match zoom
+        .use_predefined_steps
+    {
+        true => zoom_handler.preset_step(zoom.zoom_steps.as_slice(), zoom_in),
+        false => zoom_handler.step_zoom(zoom_in),
+    };
+    for action in actions {
+        match action {
+            Action::ZoomIn => {
+                let new_zoom = key_step(zoom_handler, true);
+                handle_zoom(ui, zoom_handler, panel_rect, new_zoom)
+            },
+            Action::ZoomOut => {
+                let new_zoom = key_step(zoom_handler, false);
+                handle_zoom(ui, zoom_handler, panel_rect, new_zoom)
+            },
+            // Reset zoom and position
+            Action::ResetZoom => zoom_handler.reset(),
+            Action::FitToWindow => {
+                zoom_handler.set_fit_mode(FitMode::FitLonger)
+            },
+            Action::FillWindow => {
+                zoom_handler.set_fit_mode(FitMode::FitShorter)
+            },
+            Action::ActualSize => zoom_handler.set_fit_mode(FitMode::OneToOne),
+            Action::RotateClockwise => {
+                zoom_handler.change_transform(|t| t.rotate(true))
+            },
+            Action::RotateCounterClockwise => {
+                zoom_handler.change_transform(|t| t.rotate(false))
+            },
+            Action::FlipHorizontal => {
+                zoom_handler.change_transform(|t| t.flip_horizontal())
+            },
+            Action::FlipVertical => {
+                zoom_handler.change_transform(|t| t.flip_vertical())
+            },
+            _ => {},
+        }
+    }
 
-        // Calculate how this vector should scale with the new zoom level
-        let scale_factor = new_zoom / zoom_handler.zoom_level();
-        let new_mouse_to_center = mouse_to_center * scale_factor as f32;
+    if !view_input.wheel {
+        return;
+    }
+    // Mouse wheel, as bound for the modifiers held
+    let zoom_travel = wheel_travel(ctx, controls, WheelAction::Zoom);
+    if zoom_travel != 0.0 {
+        let new_zoom = zoom_handler.step_zoom(zoom_travel > 0.0);
+        handle_zoom(ui, zoom_handler, panel_rect, new_zoom);
+    }
+    let pan_travel = wheel_travel(ctx, controls, WheelAction::Pan);
+    if pan_travel != 0.0 {
+        zoom_handler.add_offset(egui::vec2(0.0, pan_travel));
+    }
 
-        // Calculate and apply the offset needed to maintain cursor position
-        let offset_correction = mouse_to_center - new_mouse_to_center;
-        zoom_handler.add_offset(offset_correction);
+    if controls.touchpad.gestures {
+        handle_gestures(ui, zoom_handler, panel_rect, controls);
+    }
+}
 
-        // Finally, apply the new zoom level
-        zoom_handler.set_zoom(new_zoom);
+/// Pinching zooms about the pointer and two-finger scrolling pans, coasting
+/// on after the fingers lift when enabled.
+fn handle_gestures(
+    ui: &Ui,
+    zoom_handler: &mut ZoomHandler,
+    panel_rect: Rect,
+    controls: &ControlsConfig,
+) {
+    let (pinch, swipe, dt) = ui.input(|i| {
+        // Ctrl and the wheel also arrive as zoom, which the wheel bindings
+        // already took care of
+        let wheel_zoom = i.events.iter().any(|event| {
+            matches!(event, Event::MouseWheel { modifiers, .. }
+                if modifiers.ctrl || modifiers.command)
+        });
+        let mut pinch = 1.0;
+        let mut swipe = Vec2::ZERO;
+        for event in &i.events {
+            match event {
+                Event::Zoom(factor) if !wheel_zoom => pinch *= factor,
+                Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers,
+                } if is_swipe(*unit, modifiers) => swipe += *delta,
+                _ => {},
+            }
+        }
+        (pinch, swipe, i.stable_dt.min(0.1))
+    });
 
-        // Request repaint for smooth updates
+    if pinch != 1.0 {
+        zoom_handler.pinch(pointer_anchor(ui, panel_rect), pinch as f64);
         ui.ctx().request_repaint();
-    } else {
-        // If no cursor position (e.g. keyboard zoom), zoom from center
-        let zoom_step = if scroll_delta > 0.0 { 1.1 } else { 0.9 };
-        let new_zoom = (zoom_handler.zoom_level() * zoom_step).clamp(0.1, 10.0);
-        zoom_handler.set_zoom(new_zoom);
     }
+    if swipe != Vec2::ZERO {
+        zoom_handler.pan_by_fingers(swipe, dt);
+        ui.ctx().request_repaint();
+    } else if controls.touchpad.coasting && zoom_handler.coast(dt) {
+        ui.ctx().request_repaint();
+    }
+}
+
+/// Where zooming is anchored: the pointer, measured from the view center,
+/// or the center itself when the pointer is elsewhere.
+fn pointer_anchor(ui: &Ui, panel_rect: Rect) -> Vec2 {
+    ui.input(|i| i.pointer.hover_pos())
+        .filter(|pos| panel_rect.contains(*pos))
+        .map(|pos| pos - panel_rect.center())
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// Zooms to `new_zoom` about the pointer, or about the view center when the
+/// pointer is elsewhere.
+fn handle_zoom(
+    ui: &Ui,
+    zoom_handler: &mut ZoomHandler,
+    panel_rect: Rect,
+    new_zoom: f64,
+) {
+    let anchor = pointer_anchor(ui, panel_rect);
+    zoom_handler.zoom_about(anchor, new_zoom);
+    ui.ctx().request_repaint();
 }
@@ -0,0 +1,164 @@
+use eframe::egui::{
+    Align2,
+    Color32,
+    FontId,
+    Id,
+    Pos2,
+    Rect,
+    Sense,
+    Stroke,
+    Ui,
+    Vec2,
+};
+use image::DynamicImage;
+
+use crate::{
+    inspect::{self, PixelSample},
+    ui::overlay::ImageGeometry,
+};
+
+/// Pixels shown on each side of the one under the cursor
+const RADIUS: u32 = 7;
+/// Side of one magnified pixel in screen points
+const CELL: f32 = 10.0;
+/// Distance between the cursor and the panel
+const OFFSET: f32 = 24.0;
+const TEXT_HEIGHT: f32 = 52.0;
+
+/// Eyedropper: while active, hovering the image shows the pixels around the
+/// cursor enlarged with the exact value of the one under it, and clicking
+/// copies its hex code. Clicks go to the tool instead of panning.
+pub struct PixelInspector {
+    active: bool,
+    /// Pixel whose code was copied, until the cursor leaves it
+    copied: Option<(u32, u32)>,
+}
+
+impl PixelInspector {
+    pub fn new() -> Self {
+        Self {
+            active: false, copied: None
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn interact(
+        &mut self,
+        ui: &mut Ui,
+        geometry: ImageGeometry,
+        image: &DynamicImage,
+    ) {
+        if !self.active {
+            return;
+        }
+        let response = ui.interact(
+            geometry.rect,
+            Id::new("pixel-inspector"),
+            Sense::click(),
+        );
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+        let Some(pixel) = inspect::sample(image, geometry.to_image(pointer))
+        else {
+            return;
+        };
+        if self.copied != Some((pixel.x, pixel.y)) {
+            self.copied = None;
+        }
+        if response.clicked() {
+            ui.ctx()
+                .output_mut(|o| o.copied_text = pixel.hex());
+            self.copied = Some((pixel.x, pixel.y));
+        }
+        self.paint(ui, pointer, image, pixel);
+    }
+
+    fn paint(
+        &self,
+        ui: &Ui,
+        pointer: Pos2,
+        image: &DynamicImage,
+        pixel: PixelSample,
+    ) {
+        let side = (2 * RADIUS + 1) as f32 * CELL;
+        let size = Vec2::new(side, side + TEXT_HEIGHT);
+        // Below and right of the cursor, flipped where it would leave the
+        // view
+        let bounds = ui.clip_rect();
+        let mut min = pointer + Vec2::splat(OFFSET);
+        if min.x + size.x > bounds.max.x {
+            min.x = pointer.x - OFFSET - size.x;
+        }
+        if min.y + size.y > bounds.max.y {
+            min.y = pointer.y - OFFSET - size.y;
+        }
+        let panel = Rect::from_min_size(min, size);
+
+        let painter = ui.painter();
+        painter.rect_filled(
+            panel.expand(4.0),
+            4.0,
+            Color32::from_black_alpha(200),
+        );
+        let cells = inspect::neighbourhood(image, pixel, RADIUS);
+        let columns = 2 * RADIUS as usize + 1;
+        for (index, color) in cells.into_iter().enumerate() {
+            let Some([r, g, b, a]) = color else {
+                continue;
+            };
+            let cell = Rect::from_min_size(
+                min + Vec2::new(
+                    (index % columns) as f32,
+                    (index / columns) as f32,
+                ) * CELL,
+                Vec2::splat(CELL),
+            );
+            painter.rect_filled(
+                cell,
+                0.0,
+                Color32::from_rgba_unmultiplied(r, g, b, a),
+            );
+        }
+        let center = Rect::from_min_size(
+            min + Vec2::splat(RADIUS as f32 * CELL),
+            Vec2::splat(CELL),
+        );
+        painter.rect_stroke(center, 0.0, Stroke::new(2.0, Color32::BLACK));
+        painter.rect_stroke(
+            center.shrink(1.0),
+            0.0,
+            Stroke::new(1.0, Color32::WHITE),
+        );
+
+        let [r, g, b, a] = pixel.rgba;
+        let hint = match self.copied {
+            Some(_) => "Copied",
+            None => "Click to copy",
+        };
+        painter.text(
+            Pos2::new(min.x, min.y + side + 4.0),
+            Align2::LEFT_TOP,
+            format!(
+                "{}, {}\nRGBA {} {} {} {}\n{}  {}",
+                pixel.x,
+                pixel.y,
+                r,
+                g,
+                b,
+                a,
+                pixel.hex(),
+                hint
+            ),
+            FontId::monospace(12.0),
+            Color32::WHITE,
+        );
+    }
+}
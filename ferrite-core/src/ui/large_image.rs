@@ -0,0 +1,55 @@
+use eframe::egui::{self, Context};
+
+use crate::image::ImageManager;
+
+/// Asks whether an image that exceeds the decoding limits should be opened
+/// anyway.
+pub struct LargeImagePrompt;
+
+impl LargeImagePrompt {
+    /// Shows the prompt while a file is held back, requesting the image
+    /// after confirmation.
+    pub fn render(ctx: &Context, image_manager: &mut ImageManager) {
+        let Some((path, size)) = image_manager.oversized() else {
+            return;
+        };
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        let mut open_anyway = false;
+        let mut cancel = false;
+        egui::Window::new("Very large image")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} is {}×{} pixels and needs about {} MB of memory once \
+                     decoded, which is above the configured limits.",
+                    name,
+                    size.width,
+                    size.height,
+                    size.megabytes()
+                ));
+                ui.label(
+                    "Files like this can be crafted to exhaust memory. Only \
+                     open it if you trust it.",
+                );
+                ui.horizontal(|ui| {
+                    open_anyway = ui.button("Open Anyway").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if open_anyway {
+            if let Some(path) = image_manager.take_oversized() {
+                image_manager.request_image_unlimited(path);
+            }
+        } else if cancel {
+            image_manager.take_oversized();
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use eframe::egui::{self, Context};
+use ferrite_config::LensConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+use tracing::info;
+
+use crate::{
+    filters::ViewFilter,
+    image::{derived_path, ImageManager},
+    lens::{CorrectionOptions, LensDatabase},
+    metadata::{self, LensInfo},
+};
+
+struct PendingCorrection {
+    source:   PathBuf,
+    receiver: Receiver<DynamicImage>,
+}
+
+/// Window for correcting distortion, chromatic aberration and vignetting of
+/// the current photo with its lensfun profile.
+pub struct LensPanel {
+    open:             bool,
+    options:          CorrectionOptions,
+    database:         Option<Arc<LensDatabase>>,
+    database_pending: Option<Receiver<LensDatabase>>,
+    lens:             Option<(PathBuf, Option<LensInfo>)>,
+    pending:          Option<PendingCorrection>,
+    result:           Option<(PathBuf, DynamicImage)>,
+    status:           Option<String>,
+}
+
+impl LensPanel {
+    pub fn new() -> Self {
+        Self {
+            open:             false,
+            options:          CorrectionOptions::all(),
+            database:         None,
+            database_pending: None,
+            lens:             None,
+            pending:          None,
+            result:           None,
+            status:           None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Reads the lensfun database in the background the first time it is
+    /// needed.
+    fn load_database(&mut self, config: &LensConfig) {
+        if self.database.is_some() || self.database_pending.is_some() {
+            return;
+        }
+        let mut dirs: Vec<PathBuf> =
+            config.database_dir.iter().cloned().collect();
+        dirs.extend(LensDatabase::default_dirs());
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(LensDatabase::load(&dirs));
+        });
+        self.database_pending = Some(receiver);
+    }
+
+    fn start(&mut self, source: PathBuf, image: DynamicImage) {
+        let (Some(database), Some((_, Some(info)))) =
+            (&self.database, &self.lens)
+        else {
+            return;
+        };
+        let Some(profile) = database.find(info) else {
+            return;
+        };
+        let correction =
+            profile.correction(info.focal_length, info.aperture, self.options);
+        if correction.is_identity() {
+            self.status = Some("Nothing to correct.".to_string());
+            return;
+        }
+        info!(
+            "{}: {} with {}",
+            correction.name(),
+            source.display(),
+            profile.model
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(correction.apply(&image));
+        });
+        self.pending = Some(PendingCorrection {
+            source,
+            receiver,
+        });
+        self.status = None;
+    }
+
+    /// Tracks the current photo, collects finished corrections and applies
+    /// them automatically when configured.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &LensConfig,
+    ) {
+        let current = image_manager.current_path().cloned();
+        if self.lens.as_ref().map(|(p, _)| p) != current.as_ref() {
+            self.lens = current
+                .as_ref()
+                .map(|path| (path.clone(), metadata::read_lens_info(path)));
+            self.result = None;
+        }
+
+        if self.open || config.auto_apply {
+            self.load_database(config);
+        }
+        if let Some(receiver) = &self.database_pending {
+            match receiver.try_recv() {
+                Ok(database) => {
+                    self.database = Some(Arc::new(database));
+                    self.database_pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.database_pending = None,
+            }
+        }
+
+        if let Some(pending) = &self.pending {
+            match pending.receiver.try_recv() {
+                Ok(image) => {
+                    if current.as_ref() == Some(&pending.source) {
+                        image_manager.show_derived(image.clone());
+                        self.result = Some((pending.source.clone(), image));
+                    }
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => {
+                    self.status =
+                        Some("Correction thread terminated".to_string());
+                    self.pending = None;
+                },
+            }
+        } else if config.auto_apply
+            && self.result.is_none()
+            && self.has_profile()
+        {
+            if let (Some(source), Some(image)) =
+                (current, image_manager.original_image())
+            {
+                self.start(source, image.clone());
+            }
+        }
+    }
+
+    fn has_profile(&self) -> bool {
+        match (&self.database, &self.lens) {
+            (Some(database), Some((_, Some(info)))) => {
+                database.find(info).is_some()
+            },
+            _ => false,
+        }
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Lens Correction")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some((source, info)) = self.lens.clone() else {
+                    ui.label("Open a photo to correct it.");
+                    return;
+                };
+                let Some(info) = info else {
+                    ui.label("This photo does not record its lens.");
+                    return;
+                };
+
+                if let Some(camera) = &info.camera_model {
+                    ui.label(format!("Camera: {}", camera));
+                }
+                ui.label(format!("Lens: {}", info.lens_model));
+                ui.label(match info.aperture {
+                    Some(aperture) => {
+                        format!("{} mm at f/{:.1}", info.focal_length, aperture)
+                    },
+                    None => format!("{} mm", info.focal_length),
+                });
+                ui.separator();
+
+                let Some(database) = self.database.clone() else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Loading lens database...");
+                    });
+                    return;
+                };
+                if database.is_empty() {
+                    ui.label("No lensfun database found.");
+                    return;
+                }
+                let Some(profile) = database.find(&info) else {
+                    ui.label("This lens is not in the database.");
+                    return;
+                };
+                ui.label(format!(
+                    "Profile: {} {}",
+                    profile.maker, profile.model
+                ));
+
+                ui.add_enabled(
+                    profile.has_distortion(),
+                    egui::Checkbox::new(
+                        &mut self.options.distortion,
+                        "Distortion",
+                    ),
+                );
+                ui.add_enabled(
+                    profile.has_tca(),
+                    egui::Checkbox::new(
+                        &mut self.options.tca,
+                        "Chromatic aberration",
+                    ),
+                );
+                ui.add_enabled(
+                    profile.has_vignetting(),
+                    egui::Checkbox::new(
+                        &mut self.options.vignetting,
+                        "Vignetting",
+                    ),
+                );
+
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Correcting...");
+                    });
+                } else if ui.button("Apply").clicked() {
+                    if let Some(image) = image_manager.original_image() {
+                        self.start(source, image.clone());
+                    }
+                }
+
+                if let Some((result_source, image)) = &self.result {
+                    ui.horizontal(|ui| {
+                        if image_manager.is_showing_derived() {
+                            if ui.button("Show Original").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if ui.button("Show Corrected").clicked() {
+                            image_manager.show_derived(image.clone());
+                        }
+
+                        if ui.button("Export Corrected").clicked() {
+                            let path = derived_path(result_source, "corrected");
+                            self.status = Some(match image.save(&path) {
+                                Ok(()) => format!("Saved {}", path.display()),
+                                Err(e) => format!("Export failed: {}", e),
+                            });
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,121 @@
+use eframe::egui::{
+    epaint::{Mesh, Vertex},
+    Color32,
+    Pos2,
+    Rect,
+    Shape,
+    Stroke,
+    TextureId,
+    Ui,
+    Vec2,
+};
+use ferrite_config::{LoupeConfig, LoupeShape};
+use std::f32::consts::TAU;
+
+use crate::ui::overlay::ImageGeometry;
+
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// Magnifier that follows the cursor over the image while the main view
+/// keeps its zoom.
+pub struct Loupe {
+    active:        bool,
+    magnification: f32,
+}
+
+impl Loupe {
+    pub fn new(config: &LoupeConfig) -> Self {
+        Self {
+            active: false, magnification: config.magnification
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Changes the magnification by one step up or down (`[` and `]`).
+    pub fn adjust(&mut self, steps: f32) {
+        let range = LoupeConfig::magnification_range();
+        self.magnification = (self.magnification * 1.25f32.powf(steps))
+            .clamp(*range.start(), *range.end());
+    }
+
+    pub fn paint(
+        &self,
+        ui: &Ui,
+        geometry: ImageGeometry,
+        texture: TextureId,
+        config: &LoupeConfig,
+    ) {
+        if !self.active {
+            return;
+        }
+        let Some(pointer) = ui.input(|i| i.pointer.hover_pos()) else {
+            return;
+        };
+        if !geometry.rect.contains(pointer) {
+            return;
+        }
+
+        // Screen area under the loupe, shrunk by the magnification and
+        // converted to texture coordinates
+        let (x, y) = geometry.to_image(pointer);
+        let center_uv = Pos2::new(x / geometry.size.x, y / geometry.size.y);
+        let half_uv = Vec2::splat(config.size / 2.0 / self.magnification)
+            / geometry.scale()
+            / geometry.size;
+
+        let painter = ui.painter();
+        let radius = config.size / 2.0;
+        let border = Stroke::new(2.0, Color32::WHITE);
+        match config.shape {
+            LoupeShape::Square => {
+                let rect =
+                    Rect::from_center_size(pointer, Vec2::splat(config.size));
+                painter.rect_filled(rect, 0.0, Color32::BLACK);
+                painter.image(
+                    texture,
+                    rect,
+                    Rect::from_center_size(center_uv, half_uv * 2.0),
+                    Color32::WHITE,
+                );
+                painter.rect_stroke(rect, 0.0, border);
+            },
+            LoupeShape::Circle => {
+                painter.circle_filled(pointer, radius, Color32::BLACK);
+                let mut mesh = Mesh::with_texture(texture);
+                mesh.vertices.push(Vertex {
+                    pos:   pointer,
+                    uv:    center_uv,
+                    color: Color32::WHITE,
+                });
+                for i in 0..=CIRCLE_SEGMENTS {
+                    let angle = TAU * i as f32 / CIRCLE_SEGMENTS as f32;
+                    let direction = Vec2::angled(angle);
+                    mesh.vertices.push(Vertex {
+                        pos:   pointer + direction * radius,
+                        uv:    center_uv + direction * half_uv,
+                        color: Color32::WHITE,
+                    });
+                    if i > 0 {
+                        mesh.add_triangle(0, i as u32, i as u32 + 1);
+                    }
+                }
+                painter.add(Shape::mesh(mesh));
+                painter.circle_stroke(pointer, radius, border);
+            },
+        }
+        painter.text(
+            pointer + Vec2::new(radius, radius) * 0.7,
+            eframe::egui::Align2::LEFT_TOP,
+            format!("{:.1}×", self.magnification),
+            eframe::egui::FontId::proportional(12.0),
+            Color32::WHITE,
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use eframe::egui::{self, Context};
+use ferrite_config::FerriteConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    batch::{self, OutputFormat},
+    filters::ViewFilter,
+    image::{self as images, ImageManager},
+    lut::{self, CubeLut, LutFilter},
+};
+
+/// Image, LUT and strength a graded image was made from
+#[derive(Debug, Clone, PartialEq)]
+struct GradeKey {
+    source:   PathBuf,
+    lut:      PathBuf,
+    strength: f32,
+}
+
+/// Window browsing the user's LUT folder. The chosen LUT stays applied to
+/// every image opened until it is switched off.
+pub struct LutPanel {
+    open:          bool,
+    /// Folder the list was read from
+    listed:        Option<PathBuf>,
+    luts:          Vec<PathBuf>,
+    active:        Option<(PathBuf, Arc<CubeLut>)>,
+    strength:      f32,
+    pending:       Option<(GradeKey, Receiver<DynamicImage>)>,
+    result:        Option<(GradeKey, DynamicImage)>,
+    export_format: OutputFormat,
+    status:        Option<String>,
+}
+
+impl LutPanel {
+    pub fn new() -> Self {
+        Self {
+            open:          false,
+            listed:        None,
+            luts:          Vec::new(),
+            active:        None,
+            strength:      1.0,
+            pending:       None,
+            result:        None,
+            export_format: OutputFormat::Png,
+            status:        None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn refresh(&mut self, config: &FerriteConfig) {
+        let folder = config.lut.resolve_folder();
+        if self.listed.is_some() && self.listed == folder {
+            return;
+        }
+        self.luts = folder
+            .as_deref()
+            .map(lut::list)
+            .unwrap_or_default();
+        self.listed = folder;
+    }
+
+    fn activate(&mut self, path: PathBuf, image_manager: &mut ImageManager) {
+        match CubeLut::load(&path) {
+            Ok(cube) => {
+                info!("Loaded LUT {}", path.display());
+                self.active = Some((path, Arc::new(cube)));
+                self.status = None;
+            },
+            Err(e) => {
+                warn!("Failed to load LUT {}: {}", path.display(), e);
+                self.status = Some(format!(
+                    "{}: {}",
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    e
+                ));
+                self.deactivate(image_manager);
+            },
+        }
+    }
+
+    fn deactivate(&mut self, image_manager: &mut ImageManager) {
+        self.active = None;
+        self.pending = None;
+        if self.result.take().is_some() && image_manager.is_showing_derived() {
+            image_manager.restore_original();
+        }
+    }
+
+    /// Grades each image opened while a LUT is active and shows the result
+    /// in its place.
+    pub fn update(&mut self, ctx: &Context, image_manager: &mut ImageManager) {
+        let current = image_manager.current_path().cloned();
+        if let Some(pending) = &self.pending {
+            match pending.1.try_recv() {
+                Ok(image) => {
+                    if current.as_ref() == Some(&pending.0.source) {
+                        image_manager.show_derived(image.clone());
+                        self.result = Some((pending.0.clone(), image));
+                    }
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => {
+                    ctx.request_repaint();
+                    return;
+                },
+                Err(TryRecvError::Disconnected) => {
+                    self.status = Some("Grading thread terminated".to_string());
+                    self.pending = None;
+                    return;
+                },
+            }
+        }
+
+        let (Some((lut_path, cube)), Some(source)) = (&self.active, current)
+        else {
+            return;
+        };
+        let key = GradeKey {
+            source,
+            lut: lut_path.clone(),
+            strength: self.strength,
+        };
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|(k, _)| *k == key)
+        {
+            return;
+        }
+        let Some(image) = image_manager.original_image().cloned() else {
+            return;
+        };
+
+        let filter = LutFilter {
+            lut:      cube.clone(),
+            strength: self.strength,
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(filter.apply(&image));
+        });
+        self.pending = Some((key, receiver));
+        ctx.request_repaint();
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        if !self.open {
+            return;
+        }
+        self.refresh(config);
+
+        let mut open = self.open;
+        egui::Window::new("Creative LUT")
+            .open(&mut open)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                let Some(folder) = self.listed.clone() else {
+                    ui.label("No configuration directory for LUTs.");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("Folder: {}", folder.display()));
+                    if ui.small_button("Refresh").clicked() {
+                        self.luts = lut::list(&folder);
+                    }
+                });
+                if self.luts.is_empty() {
+                    ui.label("Put .cube files in this folder to use them.");
+                }
+
+                let active = self.active.as_ref().map(|(path, _)| path.clone());
+                let mut chosen = None;
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        if ui
+                            .selectable_label(active.is_none(), "None")
+                            .clicked()
+                        {
+                            chosen = Some(None);
+                        }
+                        for path in &self.luts {
+                            let name = path
+                                .file_stem()
+                                .unwrap_or_default()
+                                .to_string_lossy();
+                            if ui
+                                .selectable_label(
+                                    active.as_ref() == Some(path),
+                                    name,
+                                )
+                                .clicked()
+                            {
+                                chosen = Some(Some(path.clone()));
+                            }
+                        }
+                    });
+                match chosen {
+                    Some(Some(path)) if active.as_ref() != Some(&path) => {
+                        self.activate(path, image_manager)
+                    },
+                    Some(None) => self.deactivate(image_manager),
+                    _ => {},
+                }
+
+                if let Some(title) = self
+                    .active
+                    .as_ref()
+                    .and_then(|(_, cube)| cube.title.as_ref())
+                {
+                    ui.label(format!("Look: {}", title));
+                }
+                ui.add(
+                    egui::Slider::new(&mut self.strength, 0.0..=1.0)
+                        .text("Strength"),
+                );
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Grading...");
+                    });
+                }
+
+                if let Some((key, image)) = &self.result {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if image_manager.is_showing_derived() {
+                            if ui.button("Show Original").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if ui.button("Show Graded").clicked() {
+                            image_manager.show_derived(image.clone());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("lut-export-format")
+                            .selected_text(
+                                self.export_format.extension().to_uppercase(),
+                            )
+                            .show_ui(ui, |ui| {
+                                for &format in OutputFormat::AVAILABLE {
+                                    ui.selectable_value(
+                                        &mut self.export_format,
+                                        format,
+                                        format.extension().to_uppercase(),
+                                    );
+                                }
+                            });
+                        if ui.button("Export Graded").clicked() {
+                            let path =
+                                images::derived_path(&key.source, "graded")
+                                    .with_extension(
+                                        self.export_format.extension(),
+                                    );
+                            self.status = Some(
+                                match batch::save(
+                                    image,
+                                    &path,
+                                    self.export_format,
+                                    config.batch.jpeg_quality,
+                                ) {
+                                    Ok(()) => {
+                                        format!("Saved {}", path.display())
+                                    },
+                                    Err(e) => format!("Export failed: {}", e),
+                                },
+                            );
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
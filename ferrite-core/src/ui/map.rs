@@ -0,0 +1,326 @@
+use eframe::egui::{
+    self,
+    Color32,
+    Context,
+    Pos2,
+    Rect,
+    Sense,
+    Stroke,
+    Ui,
+    Vec2,
+};
+use ferrite_config::{MapConfig, PathsConfig};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    map::{self, TileCache, TileId, TILE_SIZE},
+    metadata::{self, GpsCoordinate},
+};
+
+const MAP_SIZE: Vec2 = Vec2::new(320.0, 280.0);
+
+/// Panel showing the capture location of the current photo on a map, or all
+/// geotagged photos of the current folder in gallery mode.
+pub struct MapPanel {
+    open:            bool,
+    gallery:         bool,
+    zoom:            u8,
+    pan:             Vec2,
+    tiles:           Option<TileCache>,
+    location:        Option<(PathBuf, Option<GpsCoordinate>)>,
+    gallery_points:  Vec<(PathBuf, GpsCoordinate)>,
+    gallery_pending: Option<Receiver<Vec<(PathBuf, GpsCoordinate)>>>,
+    gallery_folder:  Option<Vec<PathBuf>>,
+}
+
+impl MapPanel {
+    pub fn new(config: &MapConfig) -> Self {
+        Self {
+            open:            false,
+            gallery:         false,
+            zoom:            config.default_zoom,
+            pan:             Vec2::ZERO,
+            tiles:           None,
+            location:        None,
+            gallery_points:  Vec::new(),
+            gallery_pending: None,
+            gallery_folder:  None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn location_for(&mut self, path: &Path) -> Option<GpsCoordinate> {
+        if self.location.as_ref().map(|(p, _)| p.as_path()) != Some(path) {
+            self.location =
+                Some((path.to_path_buf(), metadata::read_gps(path)));
+            self.pan = Vec2::ZERO;
+        }
+        self.location.as_ref().and_then(|(_, gps)| *gps)
+    }
+
+    /// Reads the GPS position of every image in the folder in the
+    /// background when the folder changes.
+    fn refresh_gallery(&mut self, folder: &[PathBuf]) {
+        if self.gallery_folder.as_deref() == Some(folder) {
+            return;
+        }
+        let paths = folder.to_vec();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let points = paths
+                .into_iter()
+                .filter_map(|path| metadata::read_gps(&path).map(|g| (path, g)))
+                .collect();
+            let _ = sender.send(points);
+        });
+
+        self.gallery_folder = Some(folder.to_vec());
+        self.gallery_points.clear();
+        self.gallery_pending = Some(receiver);
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Shows the map window. Returns a photo the user clicked in gallery
+    /// mode so it can be opened.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        config: &MapConfig,
+        paths: &PathsConfig,
+        current_path: Option<&PathBuf>,
+        folder: &[PathBuf],
+    ) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        let tiles = self.tiles.get_or_insert_with(|| {
+            TileCache::new(config, paths.resolve_cache_dir())
+        });
+        tiles.poll(ctx);
+
+        if let Some(receiver) = &self.gallery_pending {
+            if let Ok(points) = receiver.try_recv() {
+                self.gallery_points = points;
+                self.gallery_pending = None;
+            } else {
+                ctx.request_repaint();
+            }
+        }
+
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new("Map")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.gallery, false, "Photo");
+                    ui.selectable_value(&mut self.gallery, true, "Folder");
+                    ui.separator();
+                    if ui.button("−").clicked() && self.zoom > 0 {
+                        self.zoom -= 1;
+                        self.pan = Vec2::ZERO;
+                    }
+                    ui.label(format!("z{}", self.zoom));
+                    if ui.button("+").clicked()
+                        && self.zoom < MapConfig::max_zoom()
+                    {
+                        self.zoom += 1;
+                        self.pan = Vec2::ZERO;
+                    }
+                });
+
+                if self.gallery {
+                    clicked = self.render_gallery(ui, folder, current_path);
+                } else {
+                    self.render_location(ui, current_path);
+                }
+
+                ui.small("© OpenStreetMap contributors");
+            });
+        self.open = open;
+        clicked
+    }
+
+    fn render_location(&mut self, ui: &mut Ui, current_path: Option<&PathBuf>) {
+        let Some(path) = current_path else {
+            ui.label("No image open.");
+            return;
+        };
+        let Some(location) = self.location_for(path) else {
+            ui.label("This image has no GPS position.");
+            return;
+        };
+
+        ui.label(format!(
+            "{:.5}, {:.5}",
+            location.latitude, location.longitude
+        ));
+        let center = map::project(location, self.zoom);
+        let (rect, to_screen) = self.draw_map(ui, center);
+        draw_pin(ui, to_screen(map::project(location, self.zoom)), rect);
+    }
+
+    fn render_gallery(
+        &mut self,
+        ui: &mut Ui,
+        folder: &[PathBuf],
+        current_path: Option<&PathBuf>,
+    ) -> Option<PathBuf> {
+        self.refresh_gallery(folder);
+
+        if self.gallery_pending.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Reading GPS positions...");
+            });
+            return None;
+        }
+        if self.gallery_points.is_empty() {
+            ui.label("No geotagged photos in this folder.");
+            return None;
+        }
+
+        ui.label(format!("{} geotagged photos", self.gallery_points.len()));
+        let coordinates: Vec<GpsCoordinate> = self
+            .gallery_points
+            .iter()
+            .map(|(_, g)| *g)
+            .collect();
+        if self.pan == Vec2::ZERO {
+            self.zoom = map::zoom_to_fit(
+                &coordinates,
+                MAP_SIZE.x as f64 * 0.8,
+                MAP_SIZE.y as f64 * 0.8,
+                MapConfig::max_zoom().min(17),
+            );
+        }
+        let center = map::center_of(&coordinates, self.zoom);
+        let (rect, to_screen) = self.draw_map(ui, center);
+
+        let pointer = ui.input(|i| i.pointer.interact_pos());
+        let pressed = ui.input(|i| i.pointer.primary_clicked());
+        let mut clicked = None;
+        for (path, coordinate) in &self.gallery_points {
+            let pos = to_screen(map::project(*coordinate, self.zoom));
+            if !rect.contains(pos) {
+                continue;
+            }
+            let is_current = Some(path) == current_path;
+            let color = if is_current {
+                Color32::from_rgb(230, 60, 60)
+            } else {
+                Color32::from_rgb(40, 120, 230)
+            };
+            ui.painter().circle(
+                pos,
+                5.0,
+                color,
+                Stroke::new(1.0, Color32::WHITE),
+            );
+
+            if pressed && pointer.is_some_and(|p| p.distance(pos) < 7.0) {
+                clicked = Some(path.clone());
+            }
+        }
+        clicked
+    }
+
+    /// Draws the tiles around `center` (world pixels) and returns the map
+    /// rectangle plus a world-to-screen mapping.
+    fn draw_map(
+        &mut self,
+        ui: &mut Ui,
+        center: (f64, f64),
+    ) -> (Rect, impl Fn((f64, f64)) -> Pos2) {
+        let (rect, response) = ui.allocate_exact_size(MAP_SIZE, Sense::drag());
+        if response.dragged() {
+            self.pan += response.drag_delta();
+        }
+
+        let center =
+            (center.0 - self.pan.x as f64, center.1 - self.pan.y as f64);
+        let origin = (
+            center.0 - rect.width() as f64 / 2.0,
+            center.1 - rect.height() as f64 / 2.0,
+        );
+        let rect_min = rect.min;
+        let to_screen = move |(x, y): (f64, f64)| {
+            Pos2::new(
+                rect_min.x + (x - origin.0) as f32,
+                rect_min.y + (y - origin.1) as f32,
+            )
+        };
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(40));
+
+        let tiles_per_axis = 1u32 << self.zoom;
+        let first_x = (origin.0 / TILE_SIZE).floor() as i64;
+        let first_y = (origin.1 / TILE_SIZE).floor() as i64;
+        let last_x =
+            ((origin.0 + rect.width() as f64) / TILE_SIZE).floor() as i64;
+        let last_y =
+            ((origin.1 + rect.height() as f64) / TILE_SIZE).floor() as i64;
+
+        if let Some(tiles) = self.tiles.as_mut() {
+            for ty in first_y..=last_y {
+                if ty < 0 || ty >= tiles_per_axis as i64 {
+                    continue;
+                }
+                for tx in first_x..=last_x {
+                    // Wrap horizontally around the antimeridian
+                    let wrapped = tx.rem_euclid(tiles_per_axis as i64) as u32;
+                    let id = TileId {
+                        z: self.zoom, x: wrapped, y: ty as u32
+                    };
+                    let Some(texture) = tiles.tile(id) else {
+                        continue;
+                    };
+                    let min = to_screen((
+                        tx as f64 * TILE_SIZE,
+                        ty as f64 * TILE_SIZE,
+                    ));
+                    painter.image(
+                        texture.id(),
+                        Rect::from_min_size(min, Vec2::splat(TILE_SIZE as f32)),
+                        Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                        Color32::WHITE,
+                    );
+                }
+            }
+            if tiles.is_loading() {
+                ui.ctx().request_repaint();
+            }
+        }
+
+        (rect, to_screen)
+    }
+}
+
+fn draw_pin(ui: &Ui, pos: Pos2, clip: Rect) {
+    let painter = ui.painter_at(clip);
+    let color = Color32::from_rgb(230, 60, 60);
+    painter.line_segment(
+        [pos, pos - Vec2::new(0.0, 14.0)],
+        Stroke::new(2.0, color),
+    );
+    painter.circle(
+        pos - Vec2::new(0.0, 16.0),
+        5.0,
+        color,
+        Stroke::new(1.0, Color32::WHITE),
+    );
+}
@@ -0,0 +1,158 @@
+use eframe::egui::{
+    self,
+    Align2,
+    Color32,
+    Context,
+    FontId,
+    Id,
+    Sense,
+    Stroke,
+    Ui,
+};
+
+use crate::{measure::Measurement, ui::overlay::ImageGeometry};
+
+/// Ruler tool: while active, dragging over the image measures a line
+/// instead of panning. Lengths are shown in pixels and, once calibrated,
+/// in millimeters.
+pub struct MeasureTool {
+    active:         bool,
+    line:           Option<Measurement>,
+    pixels_per_mm:  Option<f32>,
+    calibration_mm: f32,
+}
+
+impl MeasureTool {
+    pub fn new() -> Self {
+        Self {
+            active:         false,
+            line:           None,
+            pixels_per_mm:  None,
+            calibration_mm: 10.0,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn describe(&self, line: &Measurement) -> String {
+        match self
+            .pixels_per_mm
+            .and_then(|p| line.millimeters(p))
+        {
+            Some(mm) => format!(
+                "{:.1} mm ({:.1} px) · {:.1}°",
+                mm,
+                line.length(),
+                line.angle()
+            ),
+            None => format!("{:.1} px · {:.1}°", line.length(), line.angle()),
+        }
+    }
+
+    /// Measures on drag and draws the current line over the image.
+    pub fn interact(&mut self, ui: &mut Ui, geometry: ImageGeometry) {
+        if !self.active {
+            return;
+        }
+
+        let response =
+            ui.interact(geometry.rect, Id::new("measure-tool"), Sense::drag());
+        if let Some(pos) = response.interact_pointer_pos() {
+            let point = geometry.to_image_clamped(pos);
+            if response.drag_started() {
+                self.line = Some(Measurement {
+                    start: point, end: point
+                });
+            } else if let Some(line) = self.line.as_mut() {
+                if response.dragged() {
+                    line.end = point;
+                }
+            }
+        }
+
+        let Some(line) = self.line else {
+            return;
+        };
+        let start = geometry.to_screen(line.start);
+        let end = geometry.to_screen(line.end);
+        let painter = ui.painter();
+        let color = Color32::from_rgb(255, 200, 0);
+        painter.line_segment([start, end], Stroke::new(4.0, Color32::BLACK));
+        painter.line_segment([start, end], Stroke::new(2.0, color));
+        for point in [start, end] {
+            painter.circle_filled(point, 3.0, color);
+        }
+        if line.length() > 0.0 {
+            let label = self.describe(&line);
+            let anchor = start + (end - start) / 2.0;
+            let galley_rect = painter.text(
+                anchor,
+                Align2::CENTER_BOTTOM,
+                &label,
+                FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+            painter.rect_filled(
+                galley_rect.expand(3.0),
+                3.0,
+                Color32::from_black_alpha(160),
+            );
+            painter.text(
+                anchor,
+                Align2::CENTER_BOTTOM,
+                label,
+                FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+        }
+    }
+
+    /// Readout and calibration controls, shown while the tool is active.
+    pub fn render_window(&mut self, ctx: &Context) {
+        if !self.active {
+            return;
+        }
+
+        let mut open = self.active;
+        egui::Window::new("Measure")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(line) = self.line else {
+                    ui.label("Drag over the image to measure.");
+                    return;
+                };
+                ui.label(self.describe(&line));
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("This line is");
+                    ui.add(
+                        egui::DragValue::new(&mut self.calibration_mm)
+                            .clamp_range(0.01..=100_000.0)
+                            .speed(0.1)
+                            .suffix(" mm"),
+                    );
+                    if ui.button("Calibrate").clicked() {
+                        self.pixels_per_mm =
+                            line.calibrate(self.calibration_mm);
+                    }
+                });
+                if let Some(pixels_per_mm) = self.pixels_per_mm {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{:.3} px/mm", pixels_per_mm));
+                        if ui.small_button("Clear").clicked() {
+                            self.pixels_per_mm = None;
+                        }
+                    });
+                }
+            });
+        self.active = open;
+    }
+}
@@ -1,5 +1,90 @@
 use eframe::egui::{self, Context, Ui};
-use ferrite_config::FerriteConfig;
+use ferrite_config::{
+    Action,
+    BackgroundMode,
+    ColorRGBA,
+    FerriteConfig,
+    SortOrder,
+};
+
+use super::{
+    recent::RecentMenu,
+    zoom::{FitMode, ZOOM_PRESETS},
+};
+use crate::favorites::Favorites;
+
+/// Actions triggered from the menu that need to be handled by the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Open,
+    /// Index of the entry under File > Recent
+    OpenRecent(usize),
+    ClearRecent,
+    ScanCodes,
+    Upscale,
+    ToggleMap,
+    ExifInfo,
+    EditMetadata,
+    Checksums,
+    LensCorrection,
+    Palette,
+    Histogram,
+    Measure,
+    CycleGrid,
+    ToggleGuides,
+    ToggleLoupe,
+    PixelInspector,
+    Crop,
+    Adjust,
+    ExportView,
+    CompareClipboard,
+    CompareFile,
+    CloseCompare,
+    SplitView,
+    Slideshow,
+    Batch,
+    Gallery,
+    Playlist,
+    Fullscreen,
+    Filmstrip,
+    Stack,
+    Hdr,
+    Panorama,
+    CreativeLut,
+    Straighten,
+    AutoStraighten,
+    ViewMode(FitMode),
+    /// Index into `ZOOM_PRESETS`
+    ZoomPreset(usize),
+    Rotate {
+        clockwise: bool,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    SaveTransformed,
+    Shortcuts,
+    ColorManagement,
+    #[cfg(feature = "raw")]
+    DevelopRaw,
+    /// The background was changed and should be saved
+    Background,
+    /// The sort order was changed; the folder is listed again
+    Sort,
+    MoveToTrash,
+    Rename,
+    MoveToFolder,
+    ToggleStar,
+    /// Browse only the starred images of the folder, or all again
+    StarredOnly,
+    Tags,
+    FilterBar,
+    CopyImage,
+    CopyPath,
+    PasteImage,
+    SaveAs,
+    /// Index into the configured editors
+    OpenInEditor(usize),
+}
 
 pub struct MenuBar {
     hidden: bool,
@@ -25,29 +110,546 @@ impl MenuBar {
         ui: &mut Ui,
         ctx: &Context,
         config: &mut FerriteConfig,
-    ) {
+        recent: &mut RecentMenu,
+        favorites: &Favorites,
+        starred_only: bool,
+    ) -> Option<MenuAction> {
+        let mut action = None;
+        // Labels name the first chord bound to the same action
+        let keymap = config.controls.keymap.clone();
+        let hint = |label: &str, shortcut: Action| match keymap
+            .chords(shortcut)
+            .first()
+        {
+            Some(chord) => format!("{} ({})", label, chord),
+            None => label.to_string(),
+        };
+
         egui::menu::bar(ui, |ui| {
             ui.menu_button("File", |ui| {
-                if ui.button("Toggle Menu (M)").clicked() {
+                if ui
+                    .button(hint("Open...", Action::OpenFile))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Open);
+                    ui.close_menu();
+                }
+                ui.menu_button("Recent", |ui| {
+                    if let Some(index) = recent.render(ui, config) {
+                        action = Some(MenuAction::OpenRecent(index));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            !recent.entries().is_empty(),
+                            egui::Button::new("Clear Recent"),
+                        )
+                        .clicked()
+                    {
+                        action = Some(MenuAction::ClearRecent);
+                        ui.close_menu();
+                    }
+                });
+                if ui
+                    .button(hint("Save As...", Action::SaveAs))
+                    .clicked()
+                {
+                    action = Some(MenuAction::SaveAs);
+                    ui.close_menu();
+                }
+                ui.menu_button("Open With", |ui| {
+                    let editors = &config.editors.programs;
+                    if editors.is_empty() {
+                        ui.label("Add editors under [editors] in the config");
+                    }
+                    for (index, editor) in editors.iter().enumerate() {
+                        let label = match index {
+                            0 => hint(&editor.name, Action::OpenInEditor),
+                            _ => editor.name.clone(),
+                        };
+                        if ui.button(label).clicked() {
+                            action = Some(MenuAction::OpenInEditor(index));
+                            ui.close_menu();
+                        }
+                    }
+                });
+                if ui
+                    .button(hint("Move to Trash", Action::MoveToTrash))
+                    .clicked()
+                {
+                    action = Some(MenuAction::MoveToTrash);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Rename...", Action::Rename))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Rename);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Move to Folder...", Action::MoveToFolder))
+                    .clicked()
+                {
+                    action = Some(MenuAction::MoveToFolder);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Toggle Menu", Action::ToggleMenu))
+                    .clicked()
+                {
                     config.window.hide_menu = !config.window.hide_menu;
                     ui.close_menu();
                 }
+                if ui.button("Save Rotated Copy").clicked() {
+                    action = Some(MenuAction::SaveTransformed);
+                    ui.close_menu();
+                }
+                if ui.button("Export View as PNG").clicked() {
+                    action = Some(MenuAction::ExportView);
+                    ui.close_menu();
+                }
+                if ui.button("Batch Operations...").clicked() {
+                    action = Some(MenuAction::Batch);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Keyboard Shortcuts...", Action::Shortcuts))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Shortcuts);
+                    ui.close_menu();
+                }
+            });
+
+            ui.menu_button("Edit", |ui| {
+                if ui
+                    .button(hint("Copy Image", Action::CopyImage))
+                    .clicked()
+                {
+                    action = Some(MenuAction::CopyImage);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Copy Path", Action::CopyPath))
+                    .clicked()
+                {
+                    action = Some(MenuAction::CopyPath);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Paste Image", Action::PasteImage))
+                    .clicked()
+                {
+                    action = Some(MenuAction::PasteImage);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui
+                    .button(hint("Star / Unstar", Action::ToggleStar))
+                    .clicked()
+                {
+                    action = Some(MenuAction::ToggleStar);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Rating and Tags...", Action::Tags))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Tags);
+                    ui.close_menu();
+                }
             });
 
             ui.menu_button("View", |ui| {
-                if ui.button("Zoom In (+)").clicked() {
+                if ui
+                    .button(hint("Zoom In", Action::ZoomIn))
+                    .clicked()
+                {
                     ctx.request_repaint();
                     ui.close_menu();
                 }
-                if ui.button("Zoom Out (-)").clicked() {
+                if ui
+                    .button(hint("Zoom Out", Action::ZoomOut))
+                    .clicked()
+                {
                     ctx.request_repaint();
                     ui.close_menu();
                 }
-                if ui.button("Reset Zoom (0)").clicked() {
+                if ui
+                    .button(hint("Reset Zoom", Action::ResetZoom))
+                    .clicked()
+                {
                     ctx.request_repaint();
                     ui.close_menu();
                 }
+                for (mode, label, shortcut) in [
+                    (FitMode::FitLonger, "Fit to Window", Action::FitToWindow),
+                    (FitMode::FitShorter, "Fill Window", Action::FillWindow),
+                    (FitMode::OneToOne, "Actual Pixels", Action::ActualSize),
+                ] {
+                    if ui.button(hint(label, shortcut)).clicked() {
+                        action = Some(MenuAction::ViewMode(mode));
+                        ui.close_menu();
+                    }
+                }
+                ui.menu_button("Zoom", |ui| {
+                    for (index, zoom) in ZOOM_PRESETS.iter().enumerate() {
+                        if ui
+                            .button(format!("{:.0}%", zoom * 100.0))
+                            .clicked()
+                        {
+                            action = Some(MenuAction::ZoomPreset(index));
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.checkbox(&mut config.status_bar.visible, "Status Bar");
+                let mut only = starred_only;
+                if ui
+                    .add_enabled(
+                        starred_only || !favorites.is_empty(),
+                        egui::Checkbox::new(&mut only, "Starred Only"),
+                    )
+                    .on_disabled_hover_text("No images are starred")
+                    .clicked()
+                {
+                    action = Some(MenuAction::StarredOnly);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Filter Bar", Action::FilterBar))
+                    .clicked()
+                {
+                    action = Some(MenuAction::FilterBar);
+                    ui.close_menu();
+                }
+                ui.menu_button("Sort By", |ui| {
+                    let navigation = &mut config.navigation;
+                    for order in SortOrder::ALL {
+                        if ui
+                            .radio_value(
+                                &mut navigation.sort_order,
+                                order,
+                                order.label(),
+                            )
+                            .clicked()
+                        {
+                            action = Some(MenuAction::Sort);
+                        }
+                    }
+                    ui.separator();
+                    if ui
+                        .checkbox(&mut navigation.reverse_order, "Descending")
+                        .clicked()
+                    {
+                        action = Some(MenuAction::Sort);
+                    }
+                });
+                ui.menu_button("Background", |ui| {
+                    let background = &mut config.background;
+                    for mode in BackgroundMode::ALL {
+                        if ui
+                            .radio_value(
+                                &mut background.mode,
+                                mode,
+                                mode.label(),
+                            )
+                            .clicked()
+                        {
+                            action = Some(MenuAction::Background);
+                        }
+                    }
+                    if background.mode == BackgroundMode::Solid {
+                        let [r, g, b, _] =
+                            background.color.to_color32().to_array();
+                        let mut rgb = [r, g, b];
+                        if ui.color_edit_button_srgb(&mut rgb).changed() {
+                            background.color =
+                                ColorRGBA::new(rgb[0], rgb[1], rgb[2], 255);
+                            action = Some(MenuAction::Background);
+                        }
+                    }
+                });
+                ui.checkbox(
+                    &mut config.zoom.pixel_perfect,
+                    hint("Pixel Perfect", Action::PixelPerfect),
+                );
+                if ui
+                    .checkbox(
+                        &mut config.decoding.color_management,
+                        "Color Management",
+                    )
+                    .on_hover_text(
+                        "Convert embedded color profiles to sRGB, and sRGB to \
+                         the display profile",
+                    )
+                    .changed()
+                {
+                    action = Some(MenuAction::ColorManagement);
+                }
+                ui.checkbox(
+                    &mut config.io.reload_on_change,
+                    "Reload on Change",
+                )
+                .on_hover_text(
+                    "Show the image again when another program rewrites its \
+                     file",
+                );
+                #[cfg(feature = "raw")]
+                if ui
+                    .checkbox(
+                        &mut config.decoding.develop_raw,
+                        "Develop RAW Files",
+                    )
+                    .on_hover_text(
+                        "Demosaic the sensor data instead of showing the \
+                         camera's preview",
+                    )
+                    .changed()
+                {
+                    action = Some(MenuAction::DevelopRaw);
+                }
+                ui.separator();
+                if ui
+                    .button(hint("Rotate Clockwise", Action::RotateClockwise))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Rotate {
+                        clockwise: true
+                    });
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint(
+                        "Rotate Counter-clockwise",
+                        Action::RotateCounterClockwise,
+                    ))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Rotate {
+                        clockwise: false
+                    });
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Flip Horizontally", Action::FlipHorizontal))
+                    .clicked()
+                {
+                    action = Some(MenuAction::FlipHorizontal);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Flip Vertically", Action::FlipVertical))
+                    .clicked()
+                {
+                    action = Some(MenuAction::FlipVertical);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui
+                    .button(hint("Fullscreen", Action::ToggleFullscreen))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Fullscreen);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Gallery", Action::Gallery))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Gallery);
+                    ui.close_menu();
+                }
+                if ui.button("Playlist").clicked() {
+                    action = Some(MenuAction::Playlist);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Filmstrip", Action::Filmstrip))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Filmstrip);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Slideshow", Action::ToggleSlideshow))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Slideshow);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Composition Grid", Action::CycleGrid))
+                    .clicked()
+                {
+                    action = Some(MenuAction::CycleGrid);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Guides", Action::Guides))
+                    .clicked()
+                {
+                    action = Some(MenuAction::ToggleGuides);
+                    ui.close_menu();
+                }
+                if ui.button(hint("Loupe", Action::Loupe)).clicked() {
+                    action = Some(MenuAction::ToggleLoupe);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Pixel Inspector", Action::PixelInspector))
+                    .clicked()
+                {
+                    action = Some(MenuAction::PixelInspector);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Compare with File...").clicked() {
+                    action = Some(MenuAction::CompareFile);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint(
+                        "Compare with Clipboard",
+                        Action::CompareClipboard,
+                    ))
+                    .clicked()
+                {
+                    action = Some(MenuAction::CompareClipboard);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Close Compare", Action::CloseView))
+                    .clicked()
+                {
+                    action = Some(MenuAction::CloseCompare);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Split View", Action::SplitView))
+                    .clicked()
+                {
+                    action = Some(MenuAction::SplitView);
+                    ui.close_menu();
+                }
+            });
+
+            ui.menu_button("Tools", |ui| {
+                if ui
+                    .button(hint("Scan QR/Barcodes", Action::ScanCodes))
+                    .clicked()
+                {
+                    action = Some(MenuAction::ScanCodes);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Upscale...", Action::Upscale))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Upscale);
+                    ui.close_menu();
+                }
+                if ui.button(hint("Map", Action::Map)).clicked() {
+                    action = Some(MenuAction::ToggleMap);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("EXIF Info", Action::ExifInfo))
+                    .clicked()
+                {
+                    action = Some(MenuAction::ExifInfo);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Edit Metadata...", Action::EditMetadata))
+                    .clicked()
+                {
+                    action = Some(MenuAction::EditMetadata);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Checksums...", Action::Checksums))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Checksums);
+                    ui.close_menu();
+                }
+                if ui.button("Creative LUT...").clicked() {
+                    action = Some(MenuAction::CreativeLut);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Lens Correction...", Action::LensCorrection))
+                    .clicked()
+                {
+                    action = Some(MenuAction::LensCorrection);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Color Palette", Action::Palette))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Palette);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Histogram", Action::Histogram))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Histogram);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Adjust...", Action::Adjust))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Adjust);
+                    ui.close_menu();
+                }
+                if ui
+                    .button(hint("Measure", Action::Measure))
+                    .clicked()
+                {
+                    action = Some(MenuAction::Measure);
+                    ui.close_menu();
+                }
+                if ui.button(hint("Crop", Action::Crop)).clicked() {
+                    action = Some(MenuAction::Crop);
+                    ui.close_menu();
+                }
+                if ui.button("Straighten...").clicked() {
+                    action = Some(MenuAction::Straighten);
+                    ui.close_menu();
+                }
+                if ui.button("Auto Straighten").clicked() {
+                    action = Some(MenuAction::AutoStraighten);
+                    ui.close_menu();
+                }
+                if ui.button("Stack Frames...").clicked() {
+                    action = Some(MenuAction::Stack);
+                    ui.close_menu();
+                }
+                if ui.button("HDR Merge...").clicked() {
+                    action = Some(MenuAction::Hdr);
+                    ui.close_menu();
+                }
+                if ui.button("Panorama...").clicked() {
+                    action = Some(MenuAction::Panorama);
+                    ui.close_menu();
+                }
+                ui.separator();
+                let mut profiling = ferrite_logging::is_tracy_enabled();
+                if ui
+                    .checkbox(&mut profiling, "Tracy Profiling")
+                    .changed()
+                {
+                    ferrite_logging::set_tracy_enabled(profiling);
+                }
             });
         });
+
+        action
     }
 }
@@ -1,4 +1,51 @@
+pub mod adjust;
+pub mod animation;
+pub mod batch;
+pub mod checksum;
+pub mod codes;
+pub mod compare;
+pub mod crash_notice;
+pub mod crop;
+pub mod download;
+pub mod exif;
+pub mod export_view;
+pub mod filmstrip;
+pub mod filter_bar;
+pub mod folder_picker;
+pub mod gallery;
+pub mod grid;
+pub mod guides;
+pub mod hdr;
+pub mod histogram;
 pub mod input;
+pub mod inspector;
+pub mod large_image;
+pub mod lens;
+pub mod loupe;
+pub mod lut;
+pub mod map;
+pub mod measure;
 pub mod menu;
+pub mod open_dialog;
+pub mod overlay;
+pub mod palette;
+pub mod panorama;
+pub mod playlist;
+pub mod recent;
+pub mod rename;
+pub mod rename_file;
 pub mod render;
+pub mod save_as;
+pub mod selection;
+pub mod shortcuts;
+pub mod slideshow;
+pub mod split;
+pub mod stack;
+pub mod status_bar;
+pub mod straighten;
+pub mod tags;
+pub mod tone_map;
+pub mod trash;
+pub mod upscale;
+pub mod xmp_editor;
 pub mod zoom;
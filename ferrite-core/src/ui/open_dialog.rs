@@ -0,0 +1,284 @@
+use eframe::egui::{self, Context, Key};
+use ferrite_config::FerriteConfig;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+use tracing::debug;
+
+use crate::{
+    archive,
+    file_dialog::{self, FileDialogError},
+    image::SupportedFormats,
+    navigation,
+};
+
+/// The desktop's dialog, run on its own thread
+struct Native {
+    chosen:  Receiver<Result<Option<PathBuf>, FileDialogError>>,
+    /// Selected if the window has to take its place
+    current: Option<PathBuf>,
+}
+
+/// Asks for an image to open through the desktop's own dialog, and where
+/// there is none, in a window listing folders and archives and the
+/// supported images in them.
+pub struct OpenDialog {
+    /// The desktop's dialog while it is up
+    native:   Option<Native>,
+    open:     bool,
+    dir:      PathBuf,
+    /// Typed or chosen path
+    location: String,
+    folders:  Vec<PathBuf>,
+    images:   Vec<PathBuf>,
+    selected: Option<PathBuf>,
+    status:   Option<String>,
+}
+
+impl OpenDialog {
+    pub fn new() -> Self {
+        Self {
+            native:   None,
+            open:     false,
+            dir:      PathBuf::new(),
+            location: String::new(),
+            folders:  Vec::new(),
+            images:   Vec::new(),
+            selected: None,
+            status:   None,
+        }
+    }
+
+    /// Asks in the folder of `current`, or in the working directory.
+    pub fn show(&mut self, current: Option<&Path>) {
+        if self.native.is_some() || self.open {
+            return;
+        }
+        let dir = start_dir(current);
+        let extensions: Vec<&'static str> = SupportedFormats::EXTENSIONS
+            .iter()
+            .chain(archive::EXTENSIONS)
+            .copied()
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(file_dialog::open_file(&dir, &extensions));
+        });
+        self.native = Some(Native {
+            chosen:  receiver,
+            current: current.map(Path::to_path_buf),
+        });
+    }
+
+    /// Opens the window in the folder of `current`, in place of the
+    /// desktop's dialog.
+    fn show_window(
+        &mut self,
+        current: Option<PathBuf>,
+        config: &FerriteConfig,
+    ) {
+        self.open = true;
+        self.browse(start_dir(current.as_deref()), config);
+        self.selected = current;
+    }
+
+    /// The outcome of the desktop's dialog once it is closed
+    fn poll_native(
+        &mut self,
+        ctx: &Context,
+        config: &FerriteConfig,
+    ) -> Option<PathBuf> {
+        let result = match self.native.as_ref()?.chosen.try_recv() {
+            Ok(result) => result,
+            // Not closed yet; checked again without waiting for input
+            Err(TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return None;
+            },
+            Err(TryRecvError::Disconnected) => Err(FileDialogError::Failed(
+                "the dialog thread stopped".to_string(),
+            )),
+        };
+        let current = self.native.take()?.current;
+        match result {
+            Ok(chosen) => chosen,
+            Err(e) => {
+                debug!("Browsing in a window instead: {}", e);
+                self.show_window(current, config);
+                if matches!(e, FileDialogError::Failed(_)) {
+                    self.status = Some(e.to_string());
+                }
+                None
+            },
+        }
+    }
+
+    fn browse(&mut self, dir: PathBuf, config: &FerriteConfig) {
+        let options = &config.navigation;
+        // Archives are browsed like folders, with the images they hold
+        let folders = if archive::locate(&dir).is_some() {
+            Ok(Vec::new())
+        } else {
+            navigation::list_folders(&dir, options).and_then(|mut folders| {
+                folders.extend(navigation::list_archives(&dir, options)?);
+                Ok(folders)
+            })
+        };
+        let listed = folders.and_then(|folders| {
+            let images = navigation::list_images(&dir, options, None)?;
+            Ok((folders, images))
+        });
+        match listed {
+            Ok((folders, images)) => {
+                self.folders = folders;
+                self.images = images;
+                self.location = dir.display().to_string();
+                self.dir = dir;
+                self.selected = None;
+                self.status = None;
+            },
+            Err(e) => {
+                self.status =
+                    Some(format!("Cannot open {}: {}", dir.display(), e));
+            },
+        }
+    }
+
+    /// Goes to the typed location: a folder is browsed, a supported image
+    /// is returned for opening.
+    fn go_to_location(&mut self, config: &FerriteConfig) -> Option<PathBuf> {
+        let path = PathBuf::from(self.location.trim());
+        if path.is_dir() {
+            self.browse(path, config);
+            None
+        } else if path.is_file()
+            && (SupportedFormats::is_supported(path.extension())
+                || archive::is_archive(&path))
+        {
+            Some(path)
+        } else {
+            self.status =
+                Some(format!("{} is not a supported image", path.display()));
+            None
+        }
+    }
+
+    /// Shows the window. Returns the image to open once one is chosen.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        config: &FerriteConfig,
+    ) -> Option<PathBuf> {
+        if let Some(chosen) = self.poll_native(ctx, config) {
+            return Some(chosen);
+        }
+        if !self.open {
+            return None;
+        }
+
+        let mut open = self.open;
+        let mut chosen = None;
+        let mut browse_to = None;
+        egui::Window::new("Open Image")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            self.dir.parent().is_some(),
+                            egui::Button::new("Up"),
+                        )
+                        .clicked()
+                    {
+                        browse_to = self.dir.parent().map(Path::to_path_buf);
+                    }
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.location)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if response.lost_focus()
+                        && ui.input(|i| i.key_pressed(Key::Enter))
+                    {
+                        chosen = self.go_to_location(config);
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for folder in &self.folders {
+                            let name = file_name(folder);
+                            if ui
+                                .selectable_label(false, format!("{}/", name))
+                                .clicked()
+                            {
+                                browse_to = Some(folder.clone());
+                            }
+                        }
+                        for image in &self.images {
+                            let selected =
+                                self.selected.as_ref() == Some(image);
+                            let response =
+                                ui.selectable_label(selected, file_name(image));
+                            if response.clicked() {
+                                self.selected = Some(image.clone());
+                            }
+                            if response.double_clicked() {
+                                chosen = Some(image.clone());
+                            }
+                        }
+                        if self.folders.is_empty() && self.images.is_empty() {
+                            ui.label("No folders or supported images here.");
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            self.selected.is_some(),
+                            egui::Button::new("Open"),
+                        )
+                        .clicked()
+                    {
+                        chosen = self.selected.clone();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.open = false;
+                    }
+                    ui.label(format!("{} images", self.images.len()));
+                });
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        if let Some(dir) = browse_to {
+            self.browse(dir, config);
+        }
+        self.open &= open && chosen.is_none();
+        chosen
+    }
+}
+
+fn start_dir(current: Option<&Path>) -> PathBuf {
+    current
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
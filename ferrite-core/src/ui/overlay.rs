@@ -0,0 +1,39 @@
+use eframe::egui::{Pos2, Rect, Vec2};
+
+/// Where the current image is drawn on screen, for tools that paint on top
+/// of it or map pointer positions to image pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageGeometry {
+    pub rect: Rect,
+    /// Image size in pixels
+    pub size: Vec2,
+}
+
+impl ImageGeometry {
+    pub fn new(rect: Rect, (width, height): (u32, u32)) -> Option<Self> {
+        (width > 0 && height > 0).then(|| Self {
+            rect,
+            size: Vec2::new(width as f32, height as f32),
+        })
+    }
+
+    /// Screen points per image pixel
+    pub fn scale(self) -> Vec2 {
+        self.rect.size() / self.size
+    }
+
+    pub fn to_screen(self, (x, y): (f32, f32)) -> Pos2 {
+        self.rect.min + Vec2::new(x, y) * self.scale()
+    }
+
+    pub fn to_image(self, pos: Pos2) -> (f32, f32) {
+        let image = (pos - self.rect.min) / self.scale();
+        (image.x, image.y)
+    }
+
+    /// Like `to_image`, clamped to the image bounds.
+    pub fn to_image_clamped(self, pos: Pos2) -> (f32, f32) {
+        let (x, y) = self.to_image(pos);
+        (x.clamp(0.0, self.size.x), y.clamp(0.0, self.size.y))
+    }
+}
@@ -0,0 +1,140 @@
+use eframe::egui::{self, Color32, Context, Sense, Vec2};
+use image::DynamicImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use crate::palette::{self, PaletteColor};
+
+const SWATCH_SIZE: Vec2 = Vec2::new(48.0, 32.0);
+
+/// Window listing the dominant colors of the current image as swatches that
+/// copy their hex value when clicked.
+pub struct PalettePanel {
+    open:    bool,
+    count:   usize,
+    source:  Option<(PathBuf, usize)>,
+    pending: Option<Receiver<Vec<PaletteColor>>>,
+    colors:  Vec<PaletteColor>,
+    copied:  Option<String>,
+}
+
+impl PalettePanel {
+    pub fn new() -> Self {
+        Self {
+            open:    false,
+            count:   8,
+            source:  None,
+            pending: None,
+            colors:  Vec::new(),
+            copied:  None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Extracts the palette in the background whenever the image or the
+    /// number of colors changes while the window is open.
+    fn refresh(&mut self, path: &Path, image: &DynamicImage) {
+        if self.source.as_ref() == Some(&(path.to_path_buf(), self.count)) {
+            return;
+        }
+        let image = image.clone();
+        let count = self.count;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(palette::extract(&image, count));
+        });
+        self.source = Some((path.to_path_buf(), count));
+        self.pending = Some(receiver);
+        self.copied = None;
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        current_path: Option<&PathBuf>,
+        image: Option<&DynamicImage>,
+    ) {
+        if !self.open {
+            return;
+        }
+        if let (Some(path), Some(image)) = (current_path, image) {
+            self.refresh(path, image);
+        }
+        if let Some(receiver) = &self.pending {
+            match receiver.try_recv() {
+                Ok(colors) => {
+                    self.colors = colors;
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Palette")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if current_path.is_none() {
+                    ui.label("Open an image to extract its palette.");
+                    return;
+                }
+                ui.add(
+                    egui::Slider::new(&mut self.count, 2..=16).text("Colors"),
+                );
+
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Extracting colors...");
+                    });
+                    return;
+                }
+                if self.colors.is_empty() {
+                    ui.label("No opaque pixels.");
+                    return;
+                }
+
+                for color in &self.colors {
+                    ui.horizontal(|ui| {
+                        let [r, g, b] = color.rgb;
+                        let (rect, response) =
+                            ui.allocate_exact_size(SWATCH_SIZE, Sense::click());
+                        ui.painter().rect_filled(
+                            rect,
+                            3.0,
+                            Color32::from_rgb(r, g, b),
+                        );
+                        let hex = color.hex();
+                        let label = ui
+                            .add(egui::Label::new(&hex).sense(Sense::click()));
+                        ui.weak(format!("{:.0}%", color.share * 100.0));
+
+                        if response.on_hover_text("Click to copy").clicked()
+                            || label.clicked()
+                        {
+                            ui.output_mut(|o| o.copied_text = hex.clone());
+                            self.copied = Some(hex);
+                        }
+                    });
+                }
+
+                if let Some(hex) = &self.copied {
+                    ui.separator();
+                    ui.label(format!("Copied {}", hex));
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,190 @@
+use eframe::egui::{self, Context};
+use ferrite_config::FerriteConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    image::{self as images, ImageManager},
+    panorama::{self, PanoramaError},
+};
+
+struct PanoramaResult {
+    /// First photo, which the result is shown in place of
+    source: PathBuf,
+    image:  DynamicImage,
+}
+
+/// Window for stitching photos selected in the gallery into a panorama.
+pub struct PanoramaPanel {
+    open:    bool,
+    photos:  Vec<PathBuf>,
+    pending: Option<Receiver<Result<DynamicImage, PanoramaError>>>,
+    result:  Option<PanoramaResult>,
+    status:  Option<String>,
+}
+
+impl PanoramaPanel {
+    pub fn new() -> Self {
+        Self {
+            open:    false,
+            photos:  Vec::new(),
+            pending: None,
+            result:  None,
+            status:  None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window for stitching `photos`.
+    pub fn open_with(&mut self, photos: Vec<PathBuf>) {
+        if self.pending.is_none() {
+            self.photos = photos;
+            self.status = None;
+        }
+        self.open = true;
+    }
+
+    fn start(&mut self, config: &FerriteConfig) {
+        let (sender, receiver) = mpsc::channel();
+        let photos = self.photos.clone();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        thread::spawn(move || {
+            let _ =
+                sender.send(panorama::stitch_files(&photos, &decoding, &io));
+        });
+        self.pending = Some(receiver);
+        self.result = None;
+        self.status = None;
+    }
+
+    /// Collects the panorama and shows it in place of the first photo.
+    pub fn update(&mut self, ctx: &Context, image_manager: &mut ImageManager) {
+        let current = image_manager.current_path().cloned();
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|r| Some(&r.source) != current.as_ref())
+        {
+            self.result = None;
+        }
+
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(image)) => {
+                info!(
+                    "Stitched {} photos into {}x{}",
+                    self.photos.len(),
+                    image.width(),
+                    image.height()
+                );
+                let source = self.photos[0].clone();
+                if current.as_ref() == Some(&source) {
+                    image_manager.show_derived(image.clone());
+                }
+                self.result = Some(PanoramaResult {
+                    source,
+                    image,
+                });
+                self.pending = None;
+            },
+            Ok(Err(e)) => {
+                warn!("Stitching failed: {}", e);
+                self.status = Some(e.to_string());
+                self.pending = None;
+            },
+            Err(TryRecvError::Empty) => ctx.request_repaint(),
+            Err(TryRecvError::Disconnected) => {
+                self.status = Some("Stitching thread terminated".to_string());
+                self.pending = None;
+            },
+        }
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Panorama")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.photos.len() < 2 {
+                    ui.label(
+                        "Select overlapping photos in the gallery (T), in the \
+                         order they were taken, and choose Panorama.",
+                    );
+                    return;
+                }
+
+                ui.label(format!("{} photos", self.photos.len()));
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Stitching...");
+                    });
+                } else if ui.button("Stitch").clicked() {
+                    self.start(config);
+                }
+
+                if let Some(result) = &self.result {
+                    ui.separator();
+                    ui.label(format!(
+                        "Result: {}×{}",
+                        result.image.width(),
+                        result.image.height()
+                    ));
+                    ui.horizontal(|ui| {
+                        let is_current = image_manager.current_path()
+                            == Some(&result.source);
+                        if is_current && image_manager.is_showing_derived() {
+                            if ui.button("Show First Photo").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if is_current
+                            && ui.button("Show Panorama").clicked()
+                        {
+                            image_manager.show_derived(result.image.clone());
+                        }
+
+                        if ui.button("Export PNG").clicked() {
+                            let path = images::derived_path(
+                                &result.source,
+                                "panorama",
+                            );
+                            self.status =
+                                Some(match result.image.save(&path) {
+                                    Ok(()) => {
+                                        format!("Saved {}", path.display())
+                                    },
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,117 @@
+use eframe::egui::{self, Context};
+use std::path::{Path, PathBuf};
+
+/// A change the user made to the playlist
+pub enum PlaylistAction {
+    Open(PathBuf),
+    Move { from: usize, to: usize },
+    Remove(usize),
+}
+
+/// Sidebar listing the images of a playlist, with buttons to reorder and
+/// remove them.
+pub struct PlaylistPanel {
+    open: bool,
+}
+
+impl PlaylistPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.open = true;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Shows the sidebar at the left edge. Must run before the central
+    /// panel so the image area shrinks to make room.
+    pub fn render(
+        &mut self,
+        ctx: &Context,
+        images: &[PathBuf],
+        current: Option<&Path>,
+    ) -> Option<PlaylistAction> {
+        if !self.open || images.is_empty() {
+            return None;
+        }
+
+        let mut action = None;
+        egui::SidePanel::left("playlist")
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Playlist");
+                    ui.label(format!("{} images", images.len()));
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (index, image) in images.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let last = index + 1 == images.len();
+                                if ui
+                                    .add_enabled(
+                                        index > 0,
+                                        egui::Button::new("⏶").small(),
+                                    )
+                                    .on_hover_text("Move up")
+                                    .clicked()
+                                {
+                                    action = Some(PlaylistAction::Move {
+                                        from: index,
+                                        to:   index - 1,
+                                    });
+                                }
+                                if ui
+                                    .add_enabled(
+                                        !last,
+                                        egui::Button::new("⏷").small(),
+                                    )
+                                    .on_hover_text("Move down")
+                                    .clicked()
+                                {
+                                    action = Some(PlaylistAction::Move {
+                                        from: index,
+                                        to:   index + 1,
+                                    });
+                                }
+                                if ui
+                                    .small_button("✖")
+                                    .on_hover_text("Remove from the playlist")
+                                    .clicked()
+                                {
+                                    action =
+                                        Some(PlaylistAction::Remove(index));
+                                }
+                                let name = image
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy();
+                                let selected = current == Some(image.as_path());
+                                if ui
+                                    .selectable_label(selected, name)
+                                    .on_hover_text(image.display().to_string())
+                                    .clicked()
+                                {
+                                    action = Some(PlaylistAction::Open(
+                                        image.clone(),
+                                    ));
+                                }
+                            });
+                        }
+                    });
+            });
+        action
+    }
+}
@@ -0,0 +1,215 @@
+use eframe::egui::{
+    self,
+    load::SizedTexture,
+    ColorImage,
+    Context,
+    TextureOptions,
+    Ui,
+};
+use ferrite_config::FerriteConfig;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use super::gallery::{self, Thumbnail};
+use crate::{
+    recent,
+    warm_start::{RecentIndex, ThumbnailStore},
+};
+
+const THUMBNAIL_SIZE: u32 = 64;
+/// Longer side of a thumbnail in the menu, in points
+const SHOWN_SIZE: f32 = 32.0;
+
+/// The File > Recent menu: images and folders opened lately, each with a
+/// thumbnail once made.
+pub struct RecentMenu {
+    list:       Option<RecentIndex>,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    /// Entries thumbnails were asked for, made or not
+    asked:      Vec<PathBuf>,
+    pending:    Option<Receiver<(PathBuf, Option<ColorImage>)>>,
+    store:      Option<Arc<ThumbnailStore>>,
+}
+
+impl RecentMenu {
+    pub fn new(config: &FerriteConfig) -> Self {
+        let list = (config.cache.recent_menu > 0).then(|| {
+            recent::load(
+                config.paths.resolve_state_dir(),
+                config.cache.recent_menu,
+            )
+        });
+        // Stored next to the gallery's, which also prunes them
+        let store = config
+            .cache
+            .warm_start
+            .then(|| ThumbnailStore::new(config.paths.resolve_cache_dir()))
+            .flatten()
+            .map(Arc::new);
+        Self {
+            list,
+            thumbnails: HashMap::new(),
+            asked: Vec::new(),
+            pending: None,
+            store,
+        }
+    }
+
+    pub fn entries(&self) -> &[PathBuf] {
+        self.list
+            .as_ref()
+            .map(RecentIndex::entries)
+            .unwrap_or_default()
+    }
+
+    /// Puts `path` at the top of the menu, and among the recent documents of
+    /// the system.
+    pub fn record(&mut self, path: &Path) {
+        let Some(list) = &mut self.list else {
+            return;
+        };
+        if let Err(e) = list.record(path) {
+            tracing::warn!("Failed to save the recent files menu: {}", e);
+        }
+        crate::desktop::note_recent(path);
+    }
+
+    pub fn clear(&mut self) {
+        if let Some(list) = &mut self.list {
+            if let Err(e) = list.clear() {
+                tracing::warn!("Failed to clear the recent files menu: {}", e);
+            }
+        }
+        self.thumbnails.clear();
+        self.asked.clear();
+    }
+
+    /// Lists the entries as buttons while the menu is open. Returns the one
+    /// clicked, by its index in `entries`.
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        config: &FerriteConfig,
+    ) -> Option<usize> {
+        if self.list.is_none() {
+            ui.label("Turned off by cache.recent_menu in the config");
+            return None;
+        }
+        self.request_thumbnails(config);
+        self.collect_thumbnails(ui.ctx());
+        if self.entries().is_empty() {
+            ui.label("Nothing opened yet");
+        }
+
+        let mut clicked = None;
+        for (index, path) in self.entries().iter().enumerate() {
+            let name = path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy();
+            let button = match self.thumbnails.get(path) {
+                Some(Thumbnail::Loaded(texture)) => {
+                    let size = texture.size_vec2();
+                    let shown = size * (SHOWN_SIZE / size.max_elem());
+                    egui::Button::image_and_text(
+                        SizedTexture::new(texture.id(), shown),
+                        name,
+                    )
+                },
+                _ => egui::Button::new(name),
+            };
+            if ui
+                .add_enabled(path.exists(), button)
+                .on_hover_text(path.display().to_string())
+                .on_disabled_hover_text("No longer there")
+                .clicked()
+            {
+                clicked = Some(index);
+            }
+        }
+        clicked
+    }
+
+    /// Makes the thumbnails of entries added since the last time in the
+    /// background.
+    fn request_thumbnails(&mut self, config: &FerriteConfig) {
+        if self.pending.is_some() {
+            return;
+        }
+        let missing: Vec<PathBuf> = self
+            .entries()
+            .iter()
+            .filter(|path| path.is_file() && !self.asked.contains(path))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.asked.extend(missing.iter().cloned());
+
+        let (sender, receiver) = mpsc::channel();
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        let color = config.color.clone();
+        let store = self.store.clone();
+        let variant = gallery::thumbnail_variant(config, THUMBNAIL_SIZE);
+        thread::spawn(move || {
+            let _ = missing.into_par_iter().try_for_each_with(
+                sender,
+                |sender, path| {
+                    let thumbnail = gallery::make_thumbnail(
+                        &path,
+                        THUMBNAIL_SIZE,
+                        store.as_deref(),
+                        &variant,
+                        &decoding,
+                        &io,
+                        color.linear_light,
+                    )
+                    .map(|thumbnail| {
+                        gallery::to_color_image(&thumbnail, color.dither)
+                    });
+                    sender.send((path, thumbnail))
+                },
+            );
+        });
+        self.pending = Some(receiver);
+    }
+
+    fn collect_thumbnails(&mut self, ctx: &Context) {
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok((path, image)) => {
+                    let thumbnail = match image {
+                        Some(image) => Thumbnail::Loaded(ctx.load_texture(
+                            format!("recent-{}", path.display()),
+                            image,
+                            TextureOptions::LINEAR,
+                        )),
+                        None => Thumbnail::Failed,
+                    };
+                    self.thumbnails.insert(path, thumbnail);
+                },
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint();
+                    return;
+                },
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.pending = None;
+                    return;
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use eframe::egui::{self, Color32, RichText, Ui};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::rename::{self, FileInfo, PlannedRename, Template, TemplateError};
+
+type Files = Vec<(PathBuf, FileInfo)>;
+
+/// Rename part of the batch window: a template, the resulting names of the
+/// selected files and a button to apply them.
+pub struct RenameTool {
+    template: String,
+    parsed:   Result<Template, TemplateError>,
+    start:    u32,
+    /// EXIF details of the files the preview is for
+    files:    Files,
+    pending:  Option<(Vec<PathBuf>, Receiver<Files>)>,
+    status:   Option<String>,
+}
+
+impl RenameTool {
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            parsed:   Template::parse(template),
+            start:    1,
+            files:    Vec::new(),
+            pending:  None,
+            status:   None,
+        }
+    }
+
+    /// Reads the metadata of a changed selection in the background.
+    fn refresh(&mut self, ui: &Ui, selection: &[PathBuf]) {
+        if let Some((_, receiver)) = &self.pending {
+            if let Ok(files) = receiver.try_recv() {
+                self.files = files;
+                self.pending = None;
+            } else {
+                ui.ctx().request_repaint();
+            }
+        }
+
+        let requested = self
+            .pending
+            .as_ref()
+            .map(|(paths, _)| paths.as_slice());
+        let current = self
+            .files
+            .iter()
+            .map(|(path, _)| path)
+            .eq(selection.iter());
+        if requested == Some(selection) || (requested.is_none() && current) {
+            return;
+        }
+
+        let paths = selection.to_vec();
+        let (sender, receiver) = mpsc::channel();
+        let input = paths.clone();
+        thread::spawn(move || {
+            let files = input
+                .into_iter()
+                .map(|path| {
+                    let info = FileInfo::read(&path);
+                    (path, info)
+                })
+                .collect();
+            let _ = sender.send(files);
+        });
+        self.pending = Some((paths, receiver));
+    }
+
+    /// Shows the template and preview. Returns the renames that were applied.
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        selection: &[PathBuf],
+    ) -> Option<Vec<(PathBuf, PathBuf)>> {
+        self.refresh(ui, selection);
+
+        ui.horizontal(|ui| {
+            ui.label("Template");
+            if ui
+                .text_edit_singleline(&mut self.template)
+                .changed()
+            {
+                self.parsed = Template::parse(&self.template);
+                self.status = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Counter starts at");
+            ui.add(egui::DragValue::new(&mut self.start));
+        });
+        ui.small(
+            "{name} {seq} {seq:04} {date} {time} {year} {month} {day} \
+             {camera} {make}",
+        );
+
+        let template = match &self.parsed {
+            Ok(template) => template,
+            Err(e) => {
+                ui.colored_label(Color32::from_rgb(230, 80, 80), e.to_string());
+                return None;
+            },
+        };
+        if self.pending.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Reading metadata...");
+            });
+            return None;
+        }
+
+        let plan = rename::plan(&self.files, template, self.start);
+        render_preview(ui, &plan);
+
+        let conflicts = plan
+            .iter()
+            .filter(|r| r.conflict.is_some())
+            .count();
+        if conflicts > 0 {
+            ui.colored_label(
+                Color32::from_rgb(230, 80, 80),
+                format!("{} names conflict", conflicts),
+            );
+        }
+
+        let mut renamed = None;
+        if ui
+            .add_enabled(
+                !plan.is_empty() && conflicts == 0,
+                egui::Button::new("Rename"),
+            )
+            .clicked()
+        {
+            match rename::apply(&plan) {
+                Ok(done) => {
+                    self.status = Some(format!("Renamed {} files", done.len()));
+                    renamed = Some(done);
+                },
+                Err(e) => self.status = Some(format!("Rename failed: {}", e)),
+            }
+        }
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        renamed
+    }
+}
+
+fn render_preview(ui: &mut Ui, plan: &[PlannedRename]) {
+    let name = |path: &PathBuf| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    egui::ScrollArea::vertical()
+        .id_source("rename-preview")
+        .max_height(200.0)
+        .show(ui, |ui| {
+            egui::Grid::new("rename-preview-grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for rename in plan {
+                        ui.label(name(&rename.source));
+                        let target = RichText::new(format!(
+                            "→ {}",
+                            name(&rename.target)
+                        ));
+                        match &rename.conflict {
+                            Some(reason) => {
+                                ui.label(
+                                    target
+                                        .color(Color32::from_rgb(230, 80, 80)),
+                                )
+                                .on_hover_text(reason);
+                            },
+                            None => {
+                                ui.label(target);
+                            },
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
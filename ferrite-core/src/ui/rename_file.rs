@@ -0,0 +1,97 @@
+use eframe::egui::{
+    self,
+    text::{CCursor, CCursorRange},
+    Context,
+    Key,
+};
+use std::path::{Path, PathBuf};
+
+use crate::rename;
+
+/// Renames the current image in place, starting from its name with the
+/// part before the extension selected.
+pub struct RenameDialog {
+    path:  Option<PathBuf>,
+    name:  String,
+    error: Option<String>,
+    /// Set until the name field took focus
+    focus: bool,
+}
+
+impl RenameDialog {
+    pub fn new() -> Self {
+        Self {
+            path: None, name: String::new(), error: None, focus: false
+        }
+    }
+
+    pub fn open(&mut self, path: &Path) {
+        self.name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        self.path = Some(path.to_path_buf());
+        self.error = None;
+        self.focus = true;
+    }
+
+    /// Shows the dialog while open. Returns the old and new path once the
+    /// file was renamed.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+    ) -> Option<(PathBuf, PathBuf)> {
+        let path = self.path.clone()?;
+        let mut submitted = false;
+        let mut closed = false;
+        egui::Window::new("Rename")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let mut output = egui::TextEdit::singleline(&mut self.name)
+                    .desired_width(320.0)
+                    .show(ui);
+                if self.focus {
+                    self.focus = false;
+                    output.response.request_focus();
+                    let stem = Path::new(&self.name)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().chars().count())
+                        .unwrap_or_default();
+                    output.state.cursor.set_char_range(Some(
+                        CCursorRange::two(CCursor::new(0), CCursor::new(stem)),
+                    ));
+                    output.state.store(ctx, output.response.id);
+                }
+                let response = &output.response;
+                submitted = response.lost_focus()
+                    && ui.input(|i| i.key_pressed(Key::Enter));
+                closed = ui.input(|i| i.key_pressed(Key::Escape));
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.horizontal(|ui| {
+                    submitted |= ui.button("Rename").clicked();
+                    closed |= ui.button("Cancel").clicked();
+                });
+            });
+
+        if closed {
+            self.path = None;
+        } else if submitted {
+            match rename::rename_in_place(&path, &self.name) {
+                Ok(renamed) => {
+                    self.path = None;
+                    return Some((path, renamed));
+                },
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    self.focus = true;
+                },
+            }
+        }
+        None
+    }
+}
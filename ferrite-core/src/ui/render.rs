@@ -1,208 +1,403 @@
 use crate::ui::input;
-use eframe::egui::{self, ColorImage, Pos2, Rect, TextureOptions, Ui};
+use eframe::egui::{
+    self,
+    epaint::{Mesh, Vertex},
+    ColorImage,
+    Pos2,
+    Rect,
+    TextureFilter,
+    TextureHandle,
+    TextureOptions,
+    Ui,
+};
 use egui::{Color32, Context, Sense, Vec2};
-use ferrite_config::{Corner, FerriteConfig};
-
-use crate::{image::ImageManager, ui::zoom::ZoomHandler};
-
-use super::zoom::FitMode;
+use ferrite_config::{BackgroundConfig, BackgroundMode, Corner, FerriteConfig};
+use image::{DynamicImage, RgbaImage};
+use tracing::info_span;
+
+use crate::{
+    image::{
+        display::{Appearance, DisplayConversion},
+        ImageData,
+        ImageManager,
+        TiledTexture,
+    },
+    linear,
+    mipmap,
+    ui::zoom::ZoomHandler,
+};
+
+use super::zoom::{FitMode, ViewTransform};
+
+const CHECKER_DARK: Color32 = Color32::from_gray(102);
+const CHECKER_LIGHT: Color32 = Color32::from_gray(153);
 
 pub struct ImageRenderer;
 
+/// Which input a view responds to. With the window split, each pane only
+/// takes its own.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewInput {
+    /// The keys zoom, fit and rotate the view
+    pub keys:  bool,
+    /// The wheel and touchpad gestures zoom and pan it
+    pub wheel: bool,
+    /// Dragging pans it, unless a tool uses the drag
+    pub drag:  bool,
+}
+
 impl ImageRenderer {
+    /// Renders the current image and returns the screen rectangle it was
+    /// drawn into, so overlays can be placed on top of it. The pixels are
+    /// shown with `appearance`.
     pub fn render(
         ui: &mut Ui,
         ctx: &Context,
         image_manager: &mut ImageManager,
         zoom_handler: &mut ZoomHandler,
         config: &FerriteConfig,
-    ) {
+        appearance: Appearance,
+        view_input: ViewInput,
+    ) -> Option<Rect> {
         let panel_rect = ui.available_rect_before_wrap();
 
-        // Handle keyboard input and general interactions
-        input::handle_input(ctx, ui, zoom_handler, panel_rect);
-
-        // Handle texture creation/retrieval
-        let texture_handle =
+        let pixel_grid = config
+            .zoom
+            .pixel_perfect
+            .then(|| ctx.pixels_per_point());
+        if zoom_handler.pixel_grid() != pixel_grid {
+            zoom_handler.set_pixel_grid(pixel_grid);
+            // Upload again with the filtering of the new mode
             if let Some(image_data) = image_manager.current_image() {
-                if image_data.texture.is_none() {
-                    let size = [
-                        image_data.original.width() as usize,
-                        image_data.original.height() as usize,
-                    ];
-                    let image = image_data.original.to_rgba8();
-
-                    let texture = ctx.load_texture(
-                        "current-image",
-                        ColorImage::from_rgba_unmultiplied(
-                            size,
-                            image.as_flat_samples().as_slice(),
-                        ),
-                        TextureOptions::LINEAR,
-                    );
-
-                    // Update zoom for new image
-                    let image_size = Vec2::new(size[0] as f32, size[1] as f32);
-                    zoom_handler
-                        .update_for_new_image(image_size, panel_rect.size());
-
-                    image_data.texture = Some(texture);
-                }
-                image_data.texture.as_ref()
-            } else {
-                None
-            };
+                image_data.texture = None;
+            }
+        }
 
-        if let Some(texture) = texture_handle {
-            let original_size = texture.size_vec2();
-            let scaled_size = original_size * zoom_handler.zoom_level() as f32;
+        // Handle keyboard input and general interactions
+        input::handle_input(
+            ctx,
+            ui,
+            zoom_handler,
+            panel_rect,
+            &config.controls,
+            &config.zoom,
+            view_input,
+        );
+
+        let image_data = image_manager.current_image()?;
+        if image_data.texture.is_none() {
+            Self::upload(ctx, image_data, config, appearance);
+        }
+        let texture = image_data.texture.as_ref()?;
 
-            // Handle image positioning and dragging
-            let (image_rect, response) = Self::handle_image_positioning(
-                ui,
-                panel_rect,
-                scaled_size,
-                zoom_handler,
-            );
+        if zoom_handler.animate(ctx.input(|i| i.time)) {
+            ctx.request_repaint();
+        }
+        let transform = zoom_handler.transform();
+        let (width, height) = image_data.dimensions();
+        let image_size = Vec2::new(width as f32, height as f32);
+        let original_size = transform.displayed_size(image_size);
+        // Follows window resizes and newly opened images
+        zoom_handler.fit_view(original_size, panel_rect.size());
+        zoom_handler.set_bounds(original_size, panel_rect.size());
+
+        // Handle image positioning and dragging
+        let (image_rect, _) = Self::handle_image_positioning(
+            ui,
+            panel_rect,
+            original_size,
+            zoom_handler,
+            view_input.drag,
+        );
+
+        // Start on a physical pixel so no texel straddles two
+        let image_rect = if pixel_grid.is_some() {
+            let aligned = ui.painter().round_pos_to_pixels(image_rect.min);
+            image_rect.translate(aligned - image_rect.min)
+        } else {
+            image_rect
+        };
 
-            // Update offset if dragged
-            if response.dragged() {
-                zoom_handler.add_offset(response.drag_delta());
-            }
+        // Physical pixels per texel of the whole image texture, which is
+        // smaller than the image when it is tiled. Mipmaps keep it at one or
+        // more; tiles take over where it gets magnified.
+        let scale = zoom_handler.shown().scale
+            * ctx.pixels_per_point() as f64
+            * (image_size.x / texture.size_vec2().x) as f64;
+        let level = mipmap::level_for(scale, image_data.mipmaps.len());
+        let texture_id = match level {
+            0 => texture.id(),
+            level => image_data.mipmaps[level - 1].id(),
+        };
 
-            // Handle zoom interactions
-            let scroll_delta = ctx.input(|i| i.raw_scroll_delta.y);
-            if scroll_delta != 0.0 {
-                Self::handle_zoom(
-                    ui,
-                    zoom_handler,
-                    scroll_delta.into(),
-                    panel_rect,
-                );
-            }
+        if image_data.original.color().has_alpha() {
+            Self::paint_background(ui, image_rect, &config.background);
+        }
 
-            // Render the image
+        // Render the image
+        if transform.is_identity() {
             ui.painter().image(
-                texture.id(),
+                texture_id,
                 image_rect,
                 Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
                 Color32::WHITE,
             );
+        } else {
+            // Rotated and flipped through the texture coordinates of
+            // the corners
+            let mut mesh = Mesh::with_texture(texture_id);
+            for corner in [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(1.0, 0.0),
+                Pos2::new(1.0, 1.0),
+                Pos2::new(0.0, 1.0),
+            ] {
+                mesh.vertices.push(Vertex {
+                    pos:   image_rect.lerp_inside(corner.to_vec2()),
+                    uv:    transform.source_uv(corner),
+                    color: Color32::WHITE,
+                });
+            }
+            mesh.add_triangle(0, 1, 2);
+            mesh.add_triangle(0, 2, 3);
+            ui.painter().add(mesh);
+        }
 
-            Self::render_zoom_indicator(
-                ui,
-                zoom_handler,
-                panel_rect,
-                &config.indicator.corner,
+        if let Some(tiles) = &mut image_data.tiles {
+            let visible = if scale > 1.0 {
+                // Texture coordinates of the image in view
+                let view = ui.clip_rect().intersect(image_rect);
+                let corners = [
+                    view.left_top(),
+                    view.right_top(),
+                    view.right_bottom(),
+                    view.left_bottom(),
+                ]
+                .map(|corner| {
+                    transform.source_uv(
+                        ((corner - image_rect.min) / image_rect.size())
+                            .to_pos2(),
+                    )
+                });
+                tiles.grid().covering(Rect::from_points(&corners))
+            } else {
+                Vec::new()
+            };
+            let pending = tiles.update(
+                ctx,
+                &image_data.original,
+                &visible,
+                Self::texture_options(config),
             );
+            if pending {
+                ctx.request_repaint();
+            }
+            for tile in visible {
+                if let Some(texture) = tiles.texture(tile) {
+                    Self::paint_tile(
+                        ui,
+                        texture,
+                        tiles.grid().image_uv(tile),
+                        tiles.grid().inner_uv(tile),
+                        image_rect,
+                        transform,
+                    );
+                }
+            }
         }
-    }
 
-    /// Handles zoom operations with cursor-relative or center-based zooming
-    fn handle_zoom(
-        ui: &Ui,
-        zoom_handler: &mut ZoomHandler,
-        scroll_delta: f64,
-        panel_rect: Rect,
-    ) {
-        // Calculate the zoom factor based on scroll direction
-        let zoom_step = if scroll_delta > 0.0 { 1.1 } else { 0.9 };
-        let new_zoom = (zoom_handler.zoom_level() * zoom_step).clamp(0.1, 10.0);
+        Self::render_zoom_indicator(
+            ui,
+            zoom_handler,
+            panel_rect,
+            &config.indicator.corner,
+        );
 
-        // Get the current image center accounting for panning
-        let current_center = panel_rect.center() + zoom_handler.offset();
+        Some(image_rect)
+    }
 
-        if let Some(cursor_pos) = ui.input(|i| i.pointer.hover_pos()) {
-            // Calculate the vector from cursor to current center
-            let cursor_to_center = cursor_pos - current_center;
+    /// Fills the image area, as far as it is in view, with what should show
+    /// through transparent pixels.
+    fn paint_background(ui: &Ui, image_rect: Rect, config: &BackgroundConfig) {
+        let view = ui.clip_rect().intersect(image_rect);
+        if !view.is_positive() {
+            return;
+        }
+        let painter = ui.painter();
+        match config.mode {
+            BackgroundMode::None => {},
+            BackgroundMode::Black => {
+                painter.rect_filled(view, 0.0, Color32::BLACK);
+            },
+            BackgroundMode::White => {
+                painter.rect_filled(view, 0.0, Color32::WHITE);
+            },
+            BackgroundMode::Solid => {
+                painter.rect_filled(view, 0.0, config.color.to_color32());
+            },
+            BackgroundMode::Checkerboard => {
+                painter.rect_filled(view, 0.0, CHECKER_DARK);
+                // Squares start at the image corner so they move with it
+                let size = config.checker_size;
+                let first = ((view.min - image_rect.min) / size).floor();
+                let last = ((view.max - image_rect.min) / size).ceil();
+                let mut mesh = Mesh::default();
+                for row in first.y as i64..last.y as i64 {
+                    for column in first.x as i64..last.x as i64 {
+                        if (row + column) % 2 == 0 {
+                            continue;
+                        }
+                        let min = image_rect.min
+                            + Vec2::new(column as f32, row as f32) * size;
+                        let square =
+                            Rect::from_min_size(min, Vec2::splat(size))
+                                .intersect(view);
+                        mesh.add_colored_rect(square, CHECKER_LIGHT);
+                    }
+                }
+                painter.add(mesh);
+            },
+        }
+    }
 
-            // Calculate how this vector should scale with the new zoom
-            let scale_factor = new_zoom / zoom_handler.zoom_level();
-            let new_cursor_to_center = cursor_to_center * scale_factor as f32;
+    /// Sampling for textures of the main image: magnified pixels stay hard
+    /// squares in pixel perfect mode.
+    pub fn texture_options(config: &FerriteConfig) -> TextureOptions {
+        if config.zoom.pixel_perfect {
+            TextureOptions {
+                magnification: TextureFilter::Nearest,
+                ..TextureOptions::LINEAR
+            }
+        } else {
+            TextureOptions::LINEAR
+        }
+    }
 
-            // Calculate needed offset correction to maintain cursor position
-            let offset_correction = cursor_to_center - new_cursor_to_center;
+    /// Uploads the image as one texture, with its mipmaps, converted for
+    /// display. Images larger than the GPU allows are uploaded
+    /// shrunk to fit, with tiles at full resolution set up to be drawn over
+    /// it.
+    fn upload(
+        ctx: &Context,
+        image_data: &mut ImageData,
+        config: &FerriteConfig,
+        appearance: Appearance,
+    ) {
+        let _span = info_span!("texture_upload").entered();
+        let max_side = ctx.input(|i| i.max_texture_side) as u32;
+        let conversion = DisplayConversion::new(config, appearance);
+        let (width, height) = image_data.dimensions();
+        let image = if width.max(height) > max_side {
+            image_data.tiles =
+                Some(TiledTexture::new(width, height, conversion.clone()));
+            let overview = linear::thumbnail(
+                &image_data.original,
+                max_side,
+                max_side,
+                config.color.linear_light,
+            );
+            conversion.apply(&overview)
+        } else {
+            image_data.tiles = None;
+            conversion.apply(&image_data.original)
+        };
 
-            // Apply the zoom and offset changes
-            zoom_handler.set_zoom(new_zoom);
-            zoom_handler.add_offset(offset_correction);
+        let texture = ctx.load_texture(
+            "current-image",
+            ColorImage::from_rgba_unmultiplied(
+                [image.width() as usize, image.height() as usize],
+                image.as_flat_samples().as_slice(),
+            ),
+            Self::texture_options(config),
+        );
+        image_data.texture = Some(texture);
+        image_data.mipmaps = if config.zoom.mipmaps {
+            Self::upload_mipmaps(ctx, image, config)
         } else {
-            // If no cursor present, just zoom from center
-            zoom_handler.set_zoom(new_zoom);
+            Vec::new()
+        };
+    }
+
+    /// Draws the tile covering `image_uv` of the image, the part
+    /// `texture_uv` of `texture`, where it falls in the displayed image.
+    fn paint_tile(
+        ui: &Ui,
+        texture: &TextureHandle,
+        image_uv: Rect,
+        texture_uv: Rect,
+        image_rect: Rect,
+        transform: ViewTransform,
+    ) {
+        let mut mesh = Mesh::with_texture(texture.id());
+        for corner in [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ] {
+            let displayed =
+                transform.displayed_uv(image_uv.lerp_inside(corner));
+            mesh.vertices.push(Vertex {
+                pos:   image_rect.lerp_inside(displayed.to_vec2()),
+                uv:    texture_uv.lerp_inside(corner),
+                color: Color32::WHITE,
+            });
         }
+        mesh.add_triangle(0, 1, 2);
+        mesh.add_triangle(0, 2, 3);
+        ui.painter().add(mesh);
+    }
 
-        // Request a repaint for smooth updates
-        ui.ctx().request_repaint();
+    /// Uploads the levels of the pyramid for the texture made of `image`.
+    fn upload_mipmaps(
+        ctx: &Context,
+        image: RgbaImage,
+        config: &FerriteConfig,
+    ) -> Vec<TextureHandle> {
+        let _span = info_span!("mipmap_upload").entered();
+        let levels = mipmap::pyramid(
+            &DynamicImage::ImageRgba8(image),
+            config.color.linear_light,
+        );
+        levels
+            .iter()
+            .enumerate()
+            .map(|(index, level)| {
+                let level = level.to_rgba8();
+                ctx.load_texture(
+                    format!("current-image-mip-{}", index + 1),
+                    ColorImage::from_rgba_unmultiplied(
+                        [level.width() as usize, level.height() as usize],
+                        level.as_raw(),
+                    ),
+                    TextureOptions::LINEAR,
+                )
+            })
+            .collect()
     }
 
+    /// Lays out the image and pans it while dragged, always leaving some
+    /// of it in view.
     fn handle_image_positioning(
         ui: &mut Ui,
         panel_rect: Rect,
-        scaled_size: Vec2,
-        zoom_handler: &ZoomHandler,
+        image_size: Vec2,
+        zoom_handler: &mut ZoomHandler,
+        pan_enabled: bool,
     ) -> (Rect, egui::Response) {
-        // Calculate the center of the panel as our reference point
-        let panel_center = panel_rect.center();
-
-        // Apply the current pan offset from the zoom handler to our center
-        // position
-        let image_center = panel_center + zoom_handler.offset();
-
-        // Create the initial image rectangle centered at our calculated
-        // position
-        let image_rect = Rect::from_center_size(image_center, scaled_size);
-
-        // If we're in a fit mode, we might want to constrain the dragging
-        let constrain_dragging = zoom_handler.get_fit_mode() != FitMode::Custom;
-
-        // Create the interactive area for the image
-        let response = ui.allocate_rect(image_rect, Sense::drag());
-
-        // If we're constraining the drag and the image is being dragged
-        let final_rect = if constrain_dragging && response.dragged() {
-            // Get the drag delta from the response
-            let drag_delta = response.drag_delta();
-
-            // Calculate the proposed new position
-            let mut new_rect = image_rect.translate(drag_delta);
-
-            // Minimum pixels of image that must remain visible
-            let min_visible = 50.0;
-
-            // Constrain horizontally
-            if new_rect.max.x < panel_rect.min.x + min_visible {
-                new_rect = new_rect.translate(Vec2::new(
-                    panel_rect.min.x + min_visible - new_rect.max.x,
-                    0.0,
-                ));
-            }
-            if new_rect.min.x > panel_rect.max.x - min_visible {
-                new_rect = new_rect.translate(Vec2::new(
-                    panel_rect.max.x - min_visible - new_rect.min.x,
-                    0.0,
-                ));
-            }
-
-            // Constrain vertically
-            if new_rect.max.y < panel_rect.min.y + min_visible {
-                new_rect = new_rect.translate(Vec2::new(
-                    0.0,
-                    panel_rect.min.y + min_visible - new_rect.max.y,
-                ));
-            }
-            if new_rect.min.y > panel_rect.max.y - min_visible {
-                new_rect = new_rect.translate(Vec2::new(
-                    0.0,
-                    panel_rect.max.y - min_visible - new_rect.min.y,
-                ));
-            }
-
-            new_rect
-        } else {
-            image_rect
-        };
-
-        (final_rect, response)
+        let camera = zoom_handler.shown();
+        let image_rect = camera.image_rect(panel_rect.center(), image_size);
+        let sense = if pan_enabled { Sense::drag() } else { Sense::hover() };
+        let response = ui.allocate_rect(image_rect, sense);
+
+        if response.dragged() {
+            zoom_handler.add_offset(response.drag_delta());
+            zoom_handler.keep_in_view();
+        }
+        let image_rect = zoom_handler
+            .shown()
+            .image_rect(panel_rect.center(), image_size);
+        (image_rect, response)
     }
 
     fn render_zoom_indicator(
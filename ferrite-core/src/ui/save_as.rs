@@ -0,0 +1,321 @@
+use eframe::egui::{self, Context, Key};
+use ferrite_config::FerriteConfig;
+use image::DynamicImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use crate::{
+    export::{self, ExportEvent, ExportFormat, ExportOptions, PngCompression},
+    file_dialog::{self, FileDialogError},
+    image::ImageManager,
+    rename::Timestamp,
+};
+
+/// Work the dialog waits for
+enum Pending {
+    None,
+    /// The desktop's save dialog is open
+    Browsing(Receiver<Result<Option<PathBuf>, FileDialogError>>),
+    Exporting {
+        events:   Receiver<ExportEvent>,
+        cancel:   Arc<AtomicBool>,
+        progress: f32,
+    },
+}
+
+/// Saves the image on screen under a chosen path, converted to the chosen
+/// format, so an image pasted from the clipboard can be kept. Encoding runs
+/// on its own thread with a progress bar.
+pub struct SaveAsDialog {
+    open:     bool,
+    location: String,
+    options:  ExportOptions,
+    pending:  Pending,
+    status:   Option<String>,
+    focus:    bool,
+}
+
+impl SaveAsDialog {
+    pub fn new(config: &FerriteConfig) -> Self {
+        Self {
+            open:     false,
+            location: String::new(),
+            options:  ExportOptions {
+                format:          ExportFormat::default(),
+                jpeg_quality:    config.batch.jpeg_quality,
+                png_compression: PngCompression::default(),
+            },
+            pending:  Pending::None,
+            status:   None,
+            focus:    false,
+        }
+    }
+
+    /// Opens the dialog suggesting a file next to `source`, or a name with
+    /// the time in `dir` for an image without a file.
+    pub fn show(&mut self, source: Option<&Path>, dir: &Path) {
+        if matches!(self.pending, Pending::Exporting { .. }) {
+            self.open = true;
+            return;
+        }
+        let extension = self.options.format.extension();
+        let suggested = match source {
+            Some(source) => crate::image::derived_path(source, "copy")
+                .with_extension(extension),
+            None => {
+                let time = Timestamp::from_system(SystemTime::now());
+                dir.join(format!(
+                    "Clipboard {:04}-{:02}-{:02} {:02}{:02}{:02}.{}",
+                    time.year,
+                    time.month,
+                    time.day,
+                    time.hour,
+                    time.minute,
+                    time.second,
+                    extension
+                ))
+            },
+        };
+        self.location = suggested.display().to_string();
+        self.status = None;
+        self.open = true;
+        self.focus = true;
+    }
+
+    /// Follows the extension of a typed or chosen path, or gives the path
+    /// the extension of the chosen format.
+    fn target(&mut self) -> PathBuf {
+        let mut path = PathBuf::from(self.location.trim());
+        match ExportFormat::from_path(&path) {
+            Some(format) => self.options.format = format,
+            None => {
+                let mut name = path.file_name().unwrap_or_default().to_owned();
+                name.push(".");
+                name.push(self.options.format.extension());
+                path.set_file_name(name);
+            },
+        }
+        self.location = path.display().to_string();
+        path
+    }
+
+    fn browse(&mut self) {
+        let suggested = self.target();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(file_dialog::save_file(&suggested));
+        });
+        self.pending = Pending::Browsing(receiver);
+    }
+
+    fn start(&mut self, image_manager: &mut ImageManager) {
+        let path = self.target();
+        if path.exists() {
+            self.status = Some(format!("{} already exists", path.display()));
+            return;
+        }
+        let Some(image) = image_manager
+            .current_image()
+            .map(|data| DynamicImage::clone(&data.original))
+        else {
+            self.status = Some("No image to save".to_string());
+            return;
+        };
+        let (sender, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let options = self.options;
+        let stop = Arc::clone(&cancel);
+        thread::spawn(move || export::run(image, path, options, sender, &stop));
+        self.status = None;
+        self.pending = Pending::Exporting {
+            events,
+            cancel,
+            progress: 0.0,
+        };
+    }
+
+    /// Picks up the results of the save dialog and the export. Returns the
+    /// file once it was written.
+    fn poll(&mut self) -> Option<PathBuf> {
+        match &mut self.pending {
+            Pending::None => None,
+            Pending::Browsing(receiver) => {
+                let result = receiver.try_recv().ok()?;
+                self.pending = Pending::None;
+                match result {
+                    Ok(Some(path)) => {
+                        self.location = path.display().to_string();
+                        self.target();
+                    },
+                    Ok(None) => {},
+                    Err(e) => self.status = Some(e.to_string()),
+                }
+                None
+            },
+            Pending::Exporting {
+                events,
+                progress,
+                ..
+            } => {
+                let mut finished = None;
+                for event in events.try_iter() {
+                    match event {
+                        ExportEvent::Progress(fraction) => *progress = fraction,
+                        ExportEvent::Finished(result) => {
+                            finished = Some(result)
+                        },
+                    }
+                }
+                match finished? {
+                    Ok(path) => {
+                        self.pending = Pending::None;
+                        self.open = false;
+                        Some(path)
+                    },
+                    Err(e) => {
+                        self.pending = Pending::None;
+                        self.status = Some(e.to_string());
+                        None
+                    },
+                }
+            },
+        }
+    }
+
+    /// Shows the dialog while open. Returns the file once it was written.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+    ) -> Option<PathBuf> {
+        let saved = self.poll();
+        if !matches!(self.pending, Pending::None) {
+            ctx.request_repaint();
+        }
+        if !self.open {
+            return saved;
+        }
+
+        let idle = matches!(self.pending, Pending::None);
+        let mut open = self.open;
+        let mut submitted = false;
+        let mut browse = false;
+        let mut cancel = false;
+        egui::Window::new("Save As")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(idle, |ui| {
+                    ui.horizontal(|ui| {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.location)
+                                .desired_width(320.0),
+                        );
+                        if self.focus {
+                            self.focus = false;
+                            response.request_focus();
+                        }
+                        submitted = response.lost_focus()
+                            && ui.input(|i| i.key_pressed(Key::Enter));
+                        browse = ui.button("Browse...").clicked();
+                    });
+                    self.render_options(ui);
+                });
+
+                match &self.pending {
+                    Pending::Exporting {
+                        progress, ..
+                    } => {
+                        ui.add(
+                            egui::ProgressBar::new(*progress).show_percentage(),
+                        );
+                        cancel = ui.button("Cancel").clicked();
+                    },
+                    _ => {
+                        ui.horizontal(|ui| {
+                            submitted |= ui
+                                .add_enabled(idle, egui::Button::new("Save"))
+                                .clicked();
+                            if ui.button("Close").clicked() {
+                                self.open = false;
+                            }
+                        });
+                    },
+                }
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        // Closing the window lets a running export finish in the background
+        self.open &= open;
+
+        if let Pending::Exporting {
+            cancel: flag, ..
+        } = &self.pending
+        {
+            if cancel {
+                flag.store(true, Ordering::Relaxed);
+            }
+        } else if browse {
+            self.browse();
+        } else if submitted && self.open {
+            self.start(image_manager);
+        }
+        saved
+    }
+
+    fn render_options(&mut self, ui: &mut egui::Ui) {
+        let before = self.options.format;
+        egui::ComboBox::from_label("Format")
+            .selected_text(self.options.format.label())
+            .show_ui(ui, |ui| {
+                for &format in ExportFormat::AVAILABLE {
+                    ui.selectable_value(
+                        &mut self.options.format,
+                        format,
+                        format.label(),
+                    );
+                }
+            });
+        if self.options.format != before {
+            let path = PathBuf::from(self.location.trim())
+                .with_extension(self.options.format.extension());
+            self.location = path.display().to_string();
+        }
+
+        match self.options.format {
+            ExportFormat::Png => {
+                egui::ComboBox::from_label("Compression")
+                    .selected_text(self.options.png_compression.label())
+                    .show_ui(ui, |ui| {
+                        for compression in PngCompression::ALL {
+                            ui.selectable_value(
+                                &mut self.options.png_compression,
+                                compression,
+                                compression.label(),
+                            );
+                        }
+                    });
+            },
+            #[cfg(feature = "jpeg")]
+            ExportFormat::Jpeg => {
+                ui.add(
+                    egui::Slider::new(&mut self.options.jpeg_quality, 1..=100)
+                        .text("Quality"),
+                );
+            },
+            ExportFormat::WebP => {
+                ui.label("Every pixel is kept exactly.");
+            },
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use eframe::egui::{self, Context, Key, PointerButton, Rect, Stroke, Ui};
+use ferrite_config::{MouseButton, SelectionConfig};
+use std::path::PathBuf;
+
+use crate::{
+    clipboard,
+    image::{derived_path, ImageManager},
+    selection::Region,
+    ui::overlay::ImageGeometry,
+};
+
+/// What the app should do with the selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionAction {
+    ZoomTo(Region),
+}
+
+/// Rectangular selection dragged with the configured mouse button. The
+/// selection can be zoomed into, copied or exported as a crop.
+pub struct SelectionTool {
+    drag:   Option<((f32, f32), (f32, f32))>,
+    region: Option<(PathBuf, Region)>,
+    status: Option<String>,
+}
+
+impl SelectionTool {
+    pub fn new() -> Self {
+        Self {
+            drag: None, region: None, status: None
+        }
+    }
+
+    fn trigger(config: &SelectionConfig) -> PointerButton {
+        match config.trigger_button {
+            MouseButton::Left => PointerButton::Primary,
+            MouseButton::Right => PointerButton::Secondary,
+            MouseButton::Middle => PointerButton::Middle,
+        }
+    }
+
+    /// Whether the pointer is currently used for selecting, so dragging
+    /// must not pan the image. With the left button as trigger this is
+    /// whenever it is pressed.
+    pub fn wants_pointer(
+        &self,
+        ctx: &Context,
+        config: &SelectionConfig,
+    ) -> bool {
+        config.enabled
+            && (self.drag.is_some()
+                || ctx
+                    .input(|i| i.pointer.button_pressed(Self::trigger(config))))
+    }
+
+    /// Tracks the selection drag and draws the selection box.
+    pub fn interact(
+        &mut self,
+        ui: &mut Ui,
+        geometry: ImageGeometry,
+        config: &SelectionConfig,
+        current_path: Option<&PathBuf>,
+    ) {
+        if self.region.as_ref().map(|(p, _)| p) != current_path {
+            self.region = None;
+            self.status = None;
+        }
+        if !config.enabled {
+            return;
+        }
+
+        let trigger = Self::trigger(config);
+        let (pointer, pressed, down) = ui.input(|i| {
+            (
+                i.pointer.interact_pos(),
+                i.pointer.button_pressed(trigger),
+                i.pointer.button_down(trigger),
+            )
+        });
+        if let Some(pos) = pointer {
+            let point = geometry.to_image(pos);
+            if pressed && ui.rect_contains_pointer(geometry.rect) {
+                self.drag = Some((point, point));
+            } else if let Some((start, end)) = self.drag.as_mut() {
+                *end = point;
+                if !down {
+                    let size = (geometry.size.x as u32, geometry.size.y as u32);
+                    self.region = Region::from_corners(*start, *end, size)
+                        .zip(current_path.cloned())
+                        .map(|(region, path)| (path, region));
+                    self.status = None;
+                    self.drag = None;
+                }
+            }
+        }
+        if !ui.ctx().wants_keyboard_input()
+            && ui.input(|i| i.key_pressed(Key::Escape))
+        {
+            self.drag = None;
+            self.region = None;
+        }
+
+        let stroke = Stroke::new(
+            config.box_thickness as f32,
+            config.box_color.to_color32(),
+        );
+        let rect = match (self.drag, &self.region) {
+            (Some((start, end)), _) => Some(Rect::from_two_pos(
+                geometry.to_screen(start),
+                geometry.to_screen(end),
+            )),
+            (None, Some((_, region))) if config.show_box => {
+                Some(Rect::from_min_max(
+                    geometry.to_screen((region.x as f32, region.y as f32)),
+                    geometry.to_screen((
+                        (region.x + region.width) as f32,
+                        (region.y + region.height) as f32,
+                    )),
+                ))
+            },
+            _ => None,
+        };
+        if let Some(rect) = rect {
+            ui.painter_at(geometry.rect)
+                .rect_stroke(rect, 0.0, stroke);
+        }
+    }
+
+    /// Actions for the finished selection.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+    ) -> Option<SelectionAction> {
+        let (source, region) = self.region.clone()?;
+
+        let mut action = None;
+        let mut open = true;
+        egui::Window::new("Selection")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{}×{} at {}, {}",
+                    region.width, region.height, region.x, region.y
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Zoom to Selection").clicked() {
+                        action = Some(SelectionAction::ZoomTo(region));
+                    }
+                    let mut crop = || {
+                        image_manager
+                            .current_image()
+                            .map(|data| region.crop(&data.original))
+                    };
+                    if ui.button("Copy").clicked() {
+                        if let Some(cropped) = crop() {
+                            self.status =
+                                Some(match clipboard::copy_image(&cropped) {
+                                    Ok(()) => "Copied to clipboard".to_string(),
+                                    Err(e) => format!("Copy failed: {}", e),
+                                });
+                        }
+                    }
+                    if ui.button("Export PNG").clicked() {
+                        if let Some(cropped) = crop() {
+                            let path = derived_path(&source, "selection");
+                            self.status = Some(match cropped.save(&path) {
+                                Ok(()) => format!("Saved {}", path.display()),
+                                Err(e) => format!("Export failed: {}", e),
+                            });
+                        }
+                    }
+                });
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        if !open {
+            self.region = None;
+            self.status = None;
+        }
+        action
+    }
+}
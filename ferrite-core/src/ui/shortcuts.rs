@@ -0,0 +1,182 @@
+use eframe::egui::{self, Color32, Context, Event, EventFilter, Key};
+use ferrite_config::{Action, KeyChord, Keymap};
+
+const CONFLICT_COLOR: Color32 = Color32::from_rgb(230, 80, 80);
+
+/// Window listing every action with its key chords, for rebinding them.
+/// Changes apply at once; saving writes them to the config file.
+pub struct ShortcutsPanel {
+    open:      bool,
+    /// Action waiting for the next chord pressed
+    capturing: Option<Action>,
+    status:    Option<String>,
+}
+
+impl ShortcutsPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false, capturing: None, status: None
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.capturing = None;
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = Some(status.into());
+    }
+
+    /// Binds the first chord pressed while waiting for one. Escape gives up
+    /// instead.
+    fn capture(&mut self, ctx: &Context, keymap: &mut Keymap) {
+        let Some(action) = self.capturing else {
+            return;
+        };
+        let pressed = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some(KeyChord {
+                    key:   *key,
+                    ctrl:  modifiers.ctrl || modifiers.command,
+                    shift: modifiers.shift,
+                    alt:   modifiers.alt,
+                }),
+                _ => None,
+            })
+        });
+        let Some(chord) = pressed else {
+            return;
+        };
+        self.capturing = None;
+        if chord == KeyChord::new(Key::Escape) {
+            return;
+        }
+        keymap.bind(action, chord);
+        self.status = keymap
+            .conflicts(action, chord)
+            .first()
+            .map(|other| {
+                format!("{} is also bound to {}", chord, other.label())
+            });
+    }
+
+    /// Shows the window. Returns whether the user asked to save the keymap.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        keymap: &mut Keymap,
+    ) -> bool {
+        if !self.open {
+            return false;
+        }
+        self.capture(ctx, keymap);
+
+        let mut save = false;
+        let mut open = self.open;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .default_height(480.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("shortcuts")
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for &action in Action::ALL {
+                                    ui.label(action.label());
+                                    ui.horizontal(|ui| {
+                                        self.render_chords(ui, keymap, action)
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.separator();
+                let valid = keymap.validate();
+                if let Err(e) = &valid {
+                    ui.colored_label(CONFLICT_COLOR, e.to_string());
+                } else if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(valid.is_ok(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        save = true;
+                    }
+                    if ui.button("Reset to Defaults").clicked() {
+                        *keymap = Keymap::default();
+                        self.capturing = None;
+                        self.status = None;
+                    }
+                });
+            });
+        self.open = open;
+        if !open {
+            self.capturing = None;
+        }
+        save
+    }
+
+    fn render_chords(
+        &mut self,
+        ui: &mut egui::Ui,
+        keymap: &mut Keymap,
+        action: Action,
+    ) {
+        for &chord in keymap.chords(action).to_vec().iter() {
+            let conflicts = keymap.conflicts(action, chord);
+            let mut text = egui::RichText::new(chord.to_string());
+            if !conflicts.is_empty() {
+                text = text.color(CONFLICT_COLOR);
+            }
+            let hover = match conflicts.first() {
+                Some(other) => {
+                    format!("Also bound to {}. Click to remove.", other.label())
+                },
+                None => "Click to remove".to_string(),
+            };
+            if ui
+                .small_button(text)
+                .on_hover_text(hover)
+                .clicked()
+            {
+                keymap.unbind(action, chord);
+            }
+        }
+
+        if self.capturing == Some(action) {
+            // Holding focus keeps the chord from also triggering its action
+            let response = ui.small_button("Press a key...");
+            response.request_focus();
+            ui.memory_mut(|m| {
+                m.set_focus_lock_filter(response.id, EventFilter {
+                    tab:               true,
+                    horizontal_arrows: true,
+                    vertical_arrows:   true,
+                    escape:            true,
+                })
+            });
+            if response.clicked() {
+                self.capturing = None;
+            }
+        } else if ui
+            .small_button("+")
+            .on_hover_text("Add a shortcut")
+            .clicked()
+        {
+            self.capturing = Some(action);
+            self.status = None;
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use eframe::egui::{
+    self,
+    Align2,
+    Color32,
+    Context,
+    Pos2,
+    Rect,
+    TextureHandle,
+    Ui,
+    Vec2,
+};
+use fastrand::Rng;
+use ferrite_config::{SlideshowConfig, Transition};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    slideshow::{KenBurns, SlideOrder},
+    ui::zoom::ZoomHandler,
+};
+
+/// Seconds the controls stay up after the pointer last moved
+const CONTROLS_SECS: f32 = 2.0;
+
+/// The image being replaced, as it was last drawn
+struct Outgoing {
+    texture: TextureHandle,
+    rect:    Rect,
+}
+
+/// Advances through the folder at the configured interval, optionally with
+/// a Ken Burns motion over each image and a transition between them.
+pub struct Slideshow {
+    running:     bool,
+    /// When the slide was paused, which freezes its timer and motion
+    paused_at:   Option<f64>,
+    slide_start: f64,
+    order:       SlideOrder,
+    motion:      Option<KenBurns>,
+    outgoing:    Option<Outgoing>,
+    rng:         Rng,
+}
+
+impl Slideshow {
+    pub fn new() -> Self {
+        Self {
+            running:     false,
+            paused_at:   None,
+            slide_start: 0.0,
+            order:       SlideOrder::new(),
+            motion:      None,
+            outgoing:    None,
+            rng:         Rng::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn toggle(&mut self, ctx: &Context, config: &SlideshowConfig) {
+        self.running = !self.running;
+        self.paused_at = None;
+        if self.running {
+            self.order.reset();
+            self.start_slide(ctx, config);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.paused_at = None;
+        self.outgoing = None;
+    }
+
+    /// Holds the current image on screen, or continues where it left off.
+    pub fn toggle_pause(&mut self, ctx: &Context) {
+        let now = ctx.input(|i| i.time);
+        match self.paused_at.take() {
+            Some(paused_at) => self.slide_start += now - paused_at,
+            None => self.paused_at = Some(now),
+        }
+    }
+
+    /// The image to show after `current` in `folder`, or `None` once the
+    /// slideshow is over.
+    pub fn next(
+        &mut self,
+        folder: &[PathBuf],
+        current: Option<&Path>,
+        config: &SlideshowConfig,
+    ) -> Option<PathBuf> {
+        self.order.next(
+            folder,
+            current,
+            config.shuffle,
+            config.repeat,
+            &mut self.rng,
+        )
+    }
+
+    /// Keeps the current image so the next one can transition over it.
+    pub fn set_outgoing(&mut self, texture: TextureHandle, rect: Rect) {
+        self.outgoing = Some(Outgoing {
+            texture,
+            rect,
+        });
+    }
+
+    /// Restarts the timer for a newly shown image.
+    pub fn start_slide(&mut self, ctx: &Context, config: &SlideshowConfig) {
+        self.slide_start = ctx.input(|i| i.time);
+        self.motion = config
+            .ken_burns
+            .then(|| KenBurns::random(&mut self.rng));
+    }
+
+    fn elapsed(&self, ctx: &Context) -> f64 {
+        let now = self
+            .paused_at
+            .unwrap_or_else(|| ctx.input(|i| i.time));
+        now - self.slide_start
+    }
+
+    fn progress(&self, ctx: &Context, config: &SlideshowConfig) -> f64 {
+        self.elapsed(ctx) / config.interval_secs
+    }
+
+    /// Returns true when the current image has been shown long enough.
+    pub fn update(&mut self, ctx: &Context, config: &SlideshowConfig) -> bool {
+        if !self.running || self.is_paused() {
+            return false;
+        }
+        let progress = self.progress(ctx, config);
+        if progress >= 1.0 {
+            return true;
+        }
+        if self.motion.is_some() || self.outgoing.is_some() {
+            ctx.request_repaint();
+        } else {
+            ctx.request_repaint_after(Duration::from_secs_f64(
+                (1.0 - progress) * config.interval_secs,
+            ));
+        }
+        false
+    }
+
+    /// Moves the view along the Ken Burns path for the current frame.
+    pub fn apply_motion(
+        &self,
+        ctx: &Context,
+        config: &SlideshowConfig,
+        zoom_handler: &mut ZoomHandler,
+        image_size: (u32, u32),
+        viewport: Vec2,
+    ) {
+        let Some(motion) = self.motion.filter(|_| self.running) else {
+            return;
+        };
+        if image_size.0 == 0 || image_size.1 == 0 || viewport.x <= 0.0 {
+            return;
+        }
+        let framing = motion.at(self.progress(ctx, config) as f32);
+        let (zoom, (x, y)) = framing.view(
+            (image_size.0 as f32, image_size.1 as f32),
+            (viewport.x, viewport.y),
+        );
+        zoom_handler.set_view(zoom, Vec2::new(x, y));
+    }
+
+    /// Shows pause, stop and the slideshow settings at the top of
+    /// `viewport` while the pointer moves or the slideshow is paused.
+    pub fn render_controls(
+        &mut self,
+        ctx: &Context,
+        config: &mut SlideshowConfig,
+        viewport: Rect,
+    ) {
+        if !self.running {
+            return;
+        }
+        let idle = ctx.input(|i| i.pointer.time_since_last_movement());
+        if idle > CONTROLS_SECS && !self.is_paused() {
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_secs_f32(
+            (CONTROLS_SECS - idle).max(0.0),
+        ));
+
+        egui::Area::new("slideshow-controls")
+            .fixed_pos(viewport.center_top() + Vec2::new(0.0, 8.0))
+            .pivot(Align2::CENTER_TOP)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let label =
+                            if self.is_paused() { "Resume" } else { "Pause" };
+                        if ui
+                            .button(label)
+                            .on_hover_text("Pause / resume (Space)")
+                            .clicked()
+                        {
+                            self.toggle_pause(ctx);
+                        }
+                        if ui.button("Stop").on_hover_text("Esc").clicked() {
+                            self.stop();
+                        }
+                        ui.separator();
+                        ui.add(
+                            egui::DragValue::new(&mut config.interval_secs)
+                                .clamp_range(
+                                    (config.transition_secs + 0.5).max(0.5)
+                                        ..=3600.0,
+                                )
+                                .speed(0.1)
+                                .suffix(" s"),
+                        )
+                        .on_hover_text("Time per image");
+                        ui.checkbox(&mut config.shuffle, "Shuffle");
+                        ui.checkbox(&mut config.repeat, "Loop");
+                    });
+                });
+            });
+    }
+
+    /// Draws the transition from the previous image to the one the renderer
+    /// just drew at `incoming_rect`, covering the normal rendering while it
+    /// runs.
+    pub fn paint_transition(
+        &mut self,
+        ui: &Ui,
+        ctx: &Context,
+        config: &SlideshowConfig,
+        viewport: Rect,
+        incoming: Option<(&TextureHandle, Rect)>,
+    ) {
+        let Some(outgoing) = &self.outgoing else {
+            return;
+        };
+        let duration = config.transition_secs;
+        let t = if duration > 0.0 {
+            (self.elapsed(ctx) / duration) as f32
+        } else {
+            1.0
+        };
+        if config.transition == Transition::None || t >= 1.0 {
+            self.outgoing = None;
+            return;
+        }
+        let t = t * t * (3.0 - 2.0 * t);
+
+        let painter = ui.painter_at(viewport);
+        painter.rect_filled(viewport, 0.0, ui.visuals().panel_fill);
+        let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+        match config.transition {
+            Transition::Crossfade => {
+                painter.image(
+                    outgoing.texture.id(),
+                    outgoing.rect,
+                    uv,
+                    Color32::WHITE,
+                );
+                if let Some((texture, rect)) = incoming {
+                    painter.image(
+                        texture.id(),
+                        rect,
+                        uv,
+                        Color32::WHITE.gamma_multiply(t),
+                    );
+                }
+            },
+            Transition::Slide => {
+                let shift = Vec2::new(viewport.width() * t, 0.0);
+                painter.image(
+                    outgoing.texture.id(),
+                    outgoing.rect.translate(-shift),
+                    uv,
+                    Color32::WHITE,
+                );
+                if let Some((texture, rect)) = incoming {
+                    let entering = Vec2::new(viewport.width(), 0.0) - shift;
+                    painter.image(
+                        texture.id(),
+                        rect.translate(entering),
+                        uv,
+                        Color32::WHITE,
+                    );
+                }
+            },
+            Transition::None => {},
+        }
+    }
+}
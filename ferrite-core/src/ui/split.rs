@@ -0,0 +1,222 @@
+use eframe::egui::{self, Color32, Context, PointerButton, Rect, Stroke, Ui};
+use ferrite_config::{Action, FerriteConfig, NavigationConfig};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    image::{display::Appearance, ImageManager},
+    navigation::{self, NavigationManager},
+    ui::{
+        render::{ImageRenderer, ViewInput},
+        zoom::ZoomHandler,
+    },
+};
+
+/// Gap between the two panes
+const GAP: f32 = 4.0;
+const SPINNER_SIZE: f32 = 32.0;
+
+/// A second image to the right of the main one, with its own folder, loader
+/// and view, so two folders can be compared side by side. Stepping, zooming
+/// and opening files go to the pane clicked last; the tools and panels keep
+/// working on the main image.
+pub struct SplitPane {
+    open:          bool,
+    /// Clicked last, so it takes the keys
+    focused:       bool,
+    image_manager: ImageManager,
+    navigation:    NavigationManager,
+    zoom_handler:  ZoomHandler,
+}
+
+impl SplitPane {
+    pub fn new(config: &FerriteConfig) -> Self {
+        let mut zoom_handler = ZoomHandler::new(config.zoom.default_zoom);
+        if config.zoom.transition_enabled {
+            zoom_handler.set_transition(config.zoom.transition_duration);
+        }
+        Self {
+            open: false,
+            focused: false,
+            image_manager: ImageManager::new(
+                config.decoding.clone(),
+                config.io.clone(),
+                config.cache.memory_mb,
+            ),
+            navigation: NavigationManager::new(config.navigation.clone()),
+            zoom_handler,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether the keys and opened files go to this pane
+    pub fn is_focused(&self) -> bool {
+        self.open && self.focused
+    }
+
+    /// Splits the window or joins it again. A newly split pane starts out
+    /// on `start`, the main image, when it has nothing to show yet.
+    pub fn toggle(&mut self, start: Option<&Path>, config: &NavigationConfig) {
+        self.open = !self.open;
+        self.focused = false;
+        if self.open && self.image_manager.current_path().is_none() {
+            if let Some(start) = start {
+                self.open_paths(vec![start.to_path_buf()], config);
+            }
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.open && self.image_manager.loading().is_some()
+    }
+
+    pub fn current_path(&self) -> Option<&Path> {
+        self.image_manager
+            .current_path()
+            .map(PathBuf::as_path)
+    }
+
+    /// Shows a single image with its folder, or an archive at its first
+    /// image; several files, or a folder, become a playlist.
+    pub fn open_paths(
+        &mut self,
+        files: Vec<PathBuf>,
+        config: &NavigationConfig,
+    ) {
+        let images = navigation::collect_images(&files, config);
+        match images.first() {
+            None => {},
+            Some(image) if files.len() == 1 && !files[0].is_dir() => {
+                self.navigation.load_current_directory(image);
+                self.image_manager.request_image(image.clone());
+            },
+            Some(first) => {
+                self.image_manager.request_image(first.clone());
+                self.navigation.load_playlist(images);
+            },
+        }
+    }
+
+    /// Steps through the pane's folder for the navigation actions.
+    pub fn navigate(&mut self, actions: &[Action]) {
+        self.navigation
+            .handle_keyboard_input(actions, &mut self.image_manager);
+    }
+
+    /// Steps once, for the wheel; `back` goes to the previous image.
+    pub fn step(&mut self, back: bool) {
+        let path = if back {
+            self.navigation.previous_image()
+        } else {
+            self.navigation.next_image()
+        };
+        if let Some(path) = path {
+            self.image_manager.request_image(path);
+        }
+    }
+
+    /// The view the panning keys move while this pane has the keys
+    pub fn focused_view(&mut self) -> Option<&mut ZoomHandler> {
+        self.is_focused()
+            .then_some(&mut self.zoom_handler)
+    }
+
+    /// Shows the image the loader finished, if any.
+    pub fn poll(&mut self, ctx: &Context) {
+        if !self.open {
+            return;
+        }
+        match self.image_manager.poll() {
+            Some(Ok(())) => {
+                self.zoom_handler.reset_view_position();
+                self.zoom_handler.reset_transform();
+                self.image_manager
+                    .prefetch(self.navigation.neighbours());
+            },
+            Some(Err(e)) => tracing::warn!("Failed to open image: {}", e),
+            None => {},
+        }
+        if self.is_loading() {
+            ctx.request_repaint();
+        }
+    }
+
+    /// The halves of `rect` for the main image and this pane.
+    pub fn panes(&self, rect: Rect) -> (Rect, Rect) {
+        let middle = rect.center().x;
+        let (mut left, mut right) = (rect, rect);
+        left.max.x = middle - GAP / 2.0;
+        right.min.x = middle + GAP / 2.0;
+        (left, right)
+    }
+
+    /// Gives the keys to the pane pressed in, of `main` and `pane` as laid
+    /// out by [`Self::panes`].
+    pub fn follow_pointer(&mut self, ctx: &Context, main: Rect, pane: Rect) {
+        let pressed = ctx.input(|i| {
+            i.pointer
+                .button_pressed(PointerButton::Primary)
+                .then(|| i.pointer.interact_pos())
+                .flatten()
+        });
+        match pressed {
+            Some(pos) if pane.contains(pos) => self.focused = true,
+            Some(pos) if main.contains(pos) => self.focused = false,
+            _ => {},
+        }
+    }
+
+    /// Draws the pane into `rect` in the `appearance` of the main image,
+    /// outlined while it has the keys.
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        ctx: &Context,
+        rect: Rect,
+        config: &FerriteConfig,
+        appearance: Appearance,
+    ) {
+        let mut pane_ui = ui.child_ui(rect, *ui.layout());
+        pane_ui.set_clip_rect(rect);
+        let view_input = ViewInput {
+            keys:  self.focused,
+            wheel: pane_ui.rect_contains_pointer(rect),
+            drag:  true,
+        };
+        ImageRenderer::render(
+            &mut pane_ui,
+            ctx,
+            &mut self.image_manager,
+            &mut self.zoom_handler,
+            config,
+            appearance,
+            view_input,
+        );
+        if self.image_manager.loading().is_some() {
+            egui::Spinner::new().size(SPINNER_SIZE).paint_at(
+                &pane_ui,
+                Rect::from_center_size(
+                    rect.center(),
+                    egui::Vec2::splat(SPINNER_SIZE),
+                ),
+            );
+        } else if self.image_manager.current_path().is_none() {
+            pane_ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Click here and open or drop an image",
+                egui::FontId::proportional(16.0),
+                Color32::from_gray(160),
+            );
+        }
+        if self.focused {
+            pane_ui.painter().rect_stroke(
+                rect.shrink(1.0),
+                0.0,
+                Stroke::new(2.0, Color32::from_rgb(100, 150, 255)),
+            );
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use eframe::egui::{self, Context};
+use ferrite_config::FerriteConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    image::{self as images, ImageManager},
+    stack::{self, StackError, StackMethod},
+};
+
+struct StackResult {
+    /// First frame, which the result is shown in place of
+    source: PathBuf,
+    image:  DynamicImage,
+}
+
+/// Window for aligning and averaging frames selected in the gallery.
+pub struct StackPanel {
+    open:    bool,
+    frames:  Vec<PathBuf>,
+    method:  StackMethod,
+    align:   bool,
+    pending: Option<Receiver<Result<DynamicImage, StackError>>>,
+    result:  Option<StackResult>,
+    status:  Option<String>,
+}
+
+impl StackPanel {
+    pub fn new() -> Self {
+        Self {
+            open:    false,
+            frames:  Vec::new(),
+            method:  StackMethod::default(),
+            align:   true,
+            pending: None,
+            result:  None,
+            status:  None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window for stacking `frames`.
+    pub fn open_with(&mut self, frames: Vec<PathBuf>) {
+        if self.pending.is_none() {
+            self.frames = frames;
+            self.status = None;
+        }
+        self.open = true;
+    }
+
+    fn start(&mut self, config: &FerriteConfig) {
+        let (sender, receiver) = mpsc::channel();
+        let frames = self.frames.clone();
+        let (method, align) = (self.method, self.align);
+        let decoding = config.decoding.clone();
+        let io = config.io.clone();
+        thread::spawn(move || {
+            let _ = sender.send(stack::stack_files(
+                &frames, method, align, &decoding, &io,
+            ));
+        });
+        self.pending = Some(receiver);
+        self.result = None;
+        self.status = None;
+    }
+
+    /// Collects the stacked result and shows it in place of the first frame.
+    pub fn update(&mut self, ctx: &Context, image_manager: &mut ImageManager) {
+        let current = image_manager.current_path().cloned();
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|r| Some(&r.source) != current.as_ref())
+        {
+            self.result = None;
+        }
+
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(image)) => {
+                info!("Stacked {} frames", self.frames.len());
+                let source = self.frames[0].clone();
+                if current.as_ref() == Some(&source) {
+                    image_manager.show_derived(image.clone());
+                }
+                self.result = Some(StackResult {
+                    source,
+                    image,
+                });
+                self.pending = None;
+            },
+            Ok(Err(e)) => {
+                warn!("Stacking failed: {}", e);
+                self.status = Some(e.to_string());
+                self.pending = None;
+            },
+            Err(TryRecvError::Empty) => ctx.request_repaint(),
+            Err(TryRecvError::Disconnected) => {
+                self.status = Some("Stacking thread terminated".to_string());
+                self.pending = None;
+            },
+        }
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Stack Frames")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if self.frames.len() < 2 {
+                    ui.label(
+                        "Select the frames in the gallery (T) and choose \
+                         Stack.",
+                    );
+                    return;
+                }
+
+                ui.label(format!(
+                    "{} frames, aligned to {}",
+                    self.frames.len(),
+                    self.frames[0]
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                ));
+                ui.add_enabled_ui(self.pending.is_none(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Method:");
+                        for method in StackMethod::ALL {
+                            ui.selectable_value(
+                                &mut self.method,
+                                method,
+                                method.label(),
+                            );
+                        }
+                    });
+                    ui.checkbox(&mut self.align, "Align frames");
+                });
+
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Stacking...");
+                    });
+                } else if ui.button("Stack").clicked() {
+                    self.start(config);
+                }
+
+                if let Some(result) = &self.result {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let is_current = image_manager.current_path()
+                            == Some(&result.source);
+                        if is_current && image_manager.is_showing_derived() {
+                            if ui.button("Show First Frame").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if is_current
+                            && ui.button("Show Stacked").clicked()
+                        {
+                            image_manager.show_derived(result.image.clone());
+                        }
+
+                        if ui.button("Export PNG").clicked() {
+                            let path =
+                                images::derived_path(&result.source, "stacked");
+                            self.status =
+                                Some(match result.image.save(&path) {
+                                    Ok(()) => {
+                                        format!("Saved {}", path.display())
+                                    },
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,220 @@
+use eframe::egui::{self, Context, Rect};
+use ferrite_config::StatusBarConfig;
+use image::{GenericImageView, ImageFormat};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{image::ImageManager, inspect, ui::overlay::ImageGeometry};
+
+/// One part of the status bar
+enum Field {
+    Text(String),
+    /// The zoom, which can be typed over
+    Zoom,
+}
+
+/// How the image on screen was marked for culling
+pub struct Marks {
+    pub starred: bool,
+    /// Stars of its XMP rating, 0 when unrated
+    pub rating:  u8,
+}
+
+/// Line along the bottom of the window with the name, size and format of
+/// the image on screen, the zoom, and the pixel under the cursor.
+pub struct StatusBar {
+    /// Size of the file last shown, looked up once per file
+    file_size: Option<(PathBuf, Option<u64>)>,
+    /// What the zoom box holds, the current zoom unless being edited
+    zoom_text: String,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self {
+            file_size: None, zoom_text: String::new()
+        }
+    }
+
+    fn file_size(&mut self, path: &Path) -> Option<u64> {
+        if self
+            .file_size
+            .as_ref()
+            .map(|(cached, _)| cached.as_path())
+            != Some(path)
+        {
+            let size = fs::metadata(path).ok().map(|meta| meta.len());
+            self.file_size = Some((path.to_path_buf(), size));
+        }
+        self.file_size
+            .as_ref()
+            .and_then(|(_, size)| *size)
+    }
+
+    /// `image_rect` is where the image was drawn on screen, to find the
+    /// pixel under the cursor. Returns the zoom typed into the zoom box, as a
+    /// scale, when Enter was pressed.
+    pub fn render(
+        &mut self,
+        ctx: &Context,
+        config: &StatusBarConfig,
+        image_manager: &mut ImageManager,
+        image_rect: Option<Rect>,
+        zoom_percentage: f64,
+        marks: Marks,
+    ) -> Option<f64> {
+        if !config.visible {
+            return None;
+        }
+        let path = image_manager.current_path().cloned();
+        let mut fields = Vec::new();
+
+        if config.show_name {
+            fields.push(Field::Text(match &path {
+                Some(path) => path
+                    .file_name()
+                    .unwrap_or(path.as_os_str())
+                    .to_string_lossy()
+                    .into_owned(),
+                None if image_manager.is_unsaved() => {
+                    "Unsaved image".to_string()
+                },
+                None => "No image".to_string(),
+            }));
+        }
+        if config.show_star && marks.starred {
+            fields.push(Field::Text("★ Starred".to_string()));
+        }
+        if config.show_rating && marks.rating > 0 {
+            let rating = marks.rating.min(5) as usize;
+            fields.push(Field::Text(format!(
+                "{}{}",
+                "★".repeat(rating),
+                "☆".repeat(5 - rating)
+            )));
+        }
+        if config.show_file_size {
+            if let Some(size) = path.as_deref().and_then(|p| self.file_size(p))
+            {
+                fields.push(Field::Text(format_size(size)));
+            }
+        }
+        if config.show_format {
+            if let Some(format) = path.as_deref().and_then(format_name) {
+                fields.push(Field::Text(format));
+            }
+        }
+
+        if let Some(data) = image_manager.current_image() {
+            let image = &data.original;
+            if config.show_resolution {
+                fields.push(Field::Text(format!(
+                    "{} × {}",
+                    image.width(),
+                    image.height()
+                )));
+            }
+            if config.show_zoom {
+                fields.push(Field::Zoom);
+            }
+            if config.show_cursor {
+                let pointer = ctx.input(|i| i.pointer.hover_pos());
+                let pixel =
+                    image_rect
+                        .zip(pointer)
+                        .and_then(|(rect, pointer)| {
+                            let geometry =
+                                ImageGeometry::new(rect, image.dimensions())?;
+                            inspect::sample(image, geometry.to_image(pointer))
+                        });
+                if let Some(pixel) = pixel {
+                    fields.push(Field::Text(format!(
+                        "{}, {}  {}",
+                        pixel.x,
+                        pixel.y,
+                        pixel.hex()
+                    )));
+                }
+            }
+        } else if config.show_zoom {
+            fields.push(Field::Zoom);
+        }
+
+        let mut typed = None;
+        egui::TopBottomPanel::bottom("status_bar")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, field) in fields.iter().enumerate() {
+                        if index > 0 {
+                            ui.separator();
+                        }
+                        match field {
+                            Field::Text(text) => {
+                                ui.label(text);
+                            },
+                            Field::Zoom => {
+                                typed = self.zoom_box(ui, zoom_percentage);
+                            },
+                        }
+                    }
+                });
+            });
+        typed
+    }
+
+    /// The zoom as an editable percentage
+    fn zoom_box(&mut self, ui: &mut egui::Ui, percentage: f64) -> Option<f64> {
+        let id = egui::Id::new("status_bar_zoom");
+        if !ui.memory(|memory| memory.has_focus(id)) {
+            self.zoom_text = format!("{:.0}%", percentage);
+        }
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.zoom_text)
+                .id(id)
+                .desired_width(48.0),
+        );
+        let entered = response.lost_focus()
+            && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        entered
+            .then(|| parse_percentage(&self.zoom_text))
+            .flatten()
+            .map(|percentage| percentage / 100.0)
+    }
+}
+
+/// A typed zoom such as "150" or "150%"
+fn parse_percentage(text: &str) -> Option<f64> {
+    let number = text.trim().trim_end_matches('%').trim_end();
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+}
+
+/// Size in bytes with a binary unit, as file managers show it
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Name of the file's format, from its extension
+fn format_name(path: &Path) -> Option<String> {
+    match ImageFormat::from_path(path) {
+        Ok(format) => Some(format!("{:?}", format).to_uppercase()),
+        Err(_) => path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_uppercase()),
+    }
+}
@@ -0,0 +1,284 @@
+use eframe::egui::{self, Context};
+use ferrite_config::FerriteConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::info;
+
+use crate::{
+    batch::{self, OutputFormat},
+    image::{self as images, ImageManager},
+    straighten::{self, MAX_ANGLE},
+};
+
+struct Straightened {
+    source: PathBuf,
+    angle:  f32,
+    image:  DynamicImage,
+}
+
+/// Window for rotating the current image by an arbitrary angle, with a
+/// suggestion from the lines found in it.
+pub struct StraightenPanel {
+    open:          bool,
+    angle:         f32,
+    detecting:     Option<(PathBuf, Receiver<Option<f32>>)>,
+    /// Suggested angle for an image, `None` inside if no lines stood out
+    suggestion:    Option<(PathBuf, Option<f32>)>,
+    pending:       Option<(PathBuf, f32, Receiver<DynamicImage>)>,
+    result:        Option<Straightened>,
+    export_format: OutputFormat,
+    status:        Option<String>,
+}
+
+impl StraightenPanel {
+    pub fn new() -> Self {
+        Self {
+            open:          false,
+            angle:         0.0,
+            detecting:     None,
+            suggestion:    None,
+            pending:       None,
+            result:        None,
+            export_format: OutputFormat::Png,
+            status:        None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window and looks for the horizon of the current image.
+    pub fn auto_straighten(&mut self, image_manager: &ImageManager) {
+        self.open = true;
+        self.detect(image_manager);
+    }
+
+    fn detect(&mut self, image_manager: &ImageManager) {
+        let (Some(source), Some(image)) = (
+            image_manager.current_path().cloned(),
+            image_manager.original_image().cloned(),
+        ) else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(straighten::detect(&image));
+        });
+        self.detecting = Some((source, receiver));
+        self.suggestion = None;
+    }
+
+    fn start(&mut self, image_manager: &ImageManager) {
+        let (Some(source), Some(image)) = (
+            image_manager.current_path().cloned(),
+            image_manager.original_image().cloned(),
+        ) else {
+            return;
+        };
+        let angle = self.angle;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(straighten::rotate(&image, angle));
+        });
+        self.pending = Some((source, angle, receiver));
+        self.status = None;
+    }
+
+    /// Collects suggestions and rotated images for the current file.
+    pub fn update(&mut self, ctx: &Context, image_manager: &mut ImageManager) {
+        let current = image_manager.current_path().cloned();
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|r| Some(&r.source) != current.as_ref())
+        {
+            self.result = None;
+        }
+        if self
+            .suggestion
+            .as_ref()
+            .is_some_and(|(source, _)| Some(source) != current.as_ref())
+        {
+            self.suggestion = None;
+        }
+
+        if let Some((source, receiver)) = &self.detecting {
+            match receiver.try_recv() {
+                Ok(angle) => {
+                    if current.as_ref() == Some(source) {
+                        if let Some(angle) = angle {
+                            info!("Suggested rotation of {:.2}°", angle);
+                        }
+                        self.suggestion = Some((source.clone(), angle));
+                    }
+                    self.detecting = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.detecting = None,
+            }
+        }
+
+        if let Some((source, angle, receiver)) = &self.pending {
+            match receiver.try_recv() {
+                Ok(image) => {
+                    if current.as_ref() == Some(source) {
+                        image_manager.show_derived(image.clone());
+                        self.result = Some(Straightened {
+                            source: source.clone(),
+                            angle: *angle,
+                            image,
+                        });
+                    }
+                    self.pending = None;
+                },
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => {
+                    self.status =
+                        Some("Rotation thread terminated".to_string());
+                    self.pending = None;
+                },
+            }
+        }
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &FerriteConfig,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Straighten")
+            .open(&mut open)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                if image_manager.current_path().is_none() {
+                    ui.label("Open an image to straighten it.");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    let busy = self.detecting.is_some();
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("Detect Horizon"))
+                        .clicked()
+                    {
+                        self.detect(image_manager);
+                    }
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+                match &self.suggestion {
+                    Some((_, Some(angle))) => {
+                        let angle = *angle;
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Suggested: {:+.2}°", angle));
+                            if ui.button("Use").clicked() {
+                                self.angle = angle;
+                            }
+                        });
+                    },
+                    Some((_, None)) => {
+                        ui.label("No clear horizon or vertical lines found.");
+                    },
+                    None => {},
+                }
+
+                ui.separator();
+                ui.add(
+                    egui::Slider::new(&mut self.angle, -MAX_ANGLE..=MAX_ANGLE)
+                        .text("Angle")
+                        .suffix("°")
+                        .fixed_decimals(2),
+                );
+                ui.label("Positive angles turn clockwise.");
+                ui.horizontal(|ui| {
+                    let busy = self.pending.is_some();
+                    if ui
+                        .add_enabled(!busy, egui::Button::new("Rotate"))
+                        .clicked()
+                    {
+                        self.start(image_manager);
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.angle = 0.0;
+                    }
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+
+                if let Some(result) = &self.result {
+                    ui.separator();
+                    ui.label(format!(
+                        "Rotated {:+.2}°, {}×{}",
+                        result.angle,
+                        result.image.width(),
+                        result.image.height()
+                    ));
+                    ui.horizontal(|ui| {
+                        if image_manager.is_showing_derived() {
+                            if ui.button("Show Original").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if ui.button("Show Straightened").clicked() {
+                            image_manager.show_derived(result.image.clone());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source(
+                            "straighten-export-format",
+                        )
+                        .selected_text(
+                            self.export_format.extension().to_uppercase(),
+                        )
+                        .show_ui(ui, |ui| {
+                            for &format in OutputFormat::AVAILABLE {
+                                ui.selectable_value(
+                                    &mut self.export_format,
+                                    format,
+                                    format.extension().to_uppercase(),
+                                );
+                            }
+                        });
+                        if ui.button("Export").clicked() {
+                            let path = images::derived_path(
+                                &result.source,
+                                "straightened",
+                            )
+                            .with_extension(self.export_format.extension());
+                            self.status = Some(
+                                match batch::save(
+                                    &result.image,
+                                    &path,
+                                    self.export_format,
+                                    config.batch.jpeg_quality,
+                                ) {
+                                    Ok(()) => {
+                                        format!("Saved {}", path.display())
+                                    },
+                                    Err(e) => format!("Export failed: {}", e),
+                                },
+                            );
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,169 @@
+use eframe::egui::{self, Context};
+use ferrite_config::XmpConfig;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::metadata::xmp::{self, XmpFields};
+
+/// Window for rating the image on screen and editing its tags, saved to its
+/// XMP metadata as soon as they change. The tags are the XMP keywords.
+pub struct TagPanel {
+    open:    bool,
+    /// Image the fields were read from
+    path:    Option<PathBuf>,
+    fields:  XmpFields,
+    new_tag: String,
+    status:  Option<String>,
+}
+
+impl TagPanel {
+    pub fn new() -> Self {
+        Self {
+            open:    false,
+            path:    None,
+            fields:  XmpFields::default(),
+            new_tag: String::new(),
+            status:  None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Reads the metadata again whenever the image on screen changes, so
+    /// its rating is at hand for the status bar.
+    pub fn sync(&mut self, path: Option<&Path>) {
+        if self.path.as_deref() == path {
+            return;
+        }
+        self.fields = path.map(xmp::read).unwrap_or_default();
+        self.path = path.map(Path::to_path_buf);
+        self.status = None;
+    }
+
+    pub fn rating(&self) -> u8 {
+        self.fields.rating
+    }
+
+    /// Gives the image `stars`, or takes them away when it has that many
+    /// already. Returns whether the change was saved.
+    pub fn rate(&mut self, stars: u8, config: &XmpConfig) -> bool {
+        if self.path.is_none() {
+            return false;
+        }
+        self.fields.rating =
+            if self.fields.rating == stars { 0 } else { stars };
+        self.save(config)
+    }
+
+    fn save(&mut self, config: &XmpConfig) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        // Keeps what was edited elsewhere in the meantime, such as the title
+        let fields = XmpFields {
+            keywords: self.fields.keywords.clone(),
+            rating: self.fields.rating,
+            ..xmp::read(path)
+        };
+        match xmp::write(path, &fields, config.target) {
+            Ok(_) => {
+                self.fields = fields;
+                self.status = None;
+                true
+            },
+            Err(e) => {
+                warn!("Failed to write the rating and tags: {}", e);
+                self.status = Some(format!("Saving failed: {}", e));
+                false
+            },
+        }
+    }
+
+    /// Shows the rating and tags while open. Returns whether they were
+    /// changed and saved.
+    pub fn render_window(&mut self, ctx: &Context, config: &XmpConfig) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        let mut changed = false;
+        let mut open = self.open;
+        egui::Window::new("Rating and Tags")
+            .open(&mut open)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                if self.path.is_none() {
+                    ui.label("No image open.");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Rating");
+                    for stars in 1..=5 {
+                        let star = if stars <= self.fields.rating {
+                            "★"
+                        } else {
+                            "☆"
+                        };
+                        if ui.selectable_label(false, star).clicked() {
+                            changed |= self.rate(stars, config);
+                        }
+                    }
+                });
+
+                ui.separator();
+                let mut removed = None;
+                ui.horizontal_wrapped(|ui| {
+                    if self.fields.keywords.is_empty() {
+                        ui.label("No tags yet.");
+                    }
+                    for (index, tag) in self.fields.keywords.iter().enumerate()
+                    {
+                        if ui
+                            .button(format!("{} ✖", tag))
+                            .on_hover_text("Remove")
+                            .clicked()
+                        {
+                            removed = Some(index);
+                        }
+                    }
+                });
+                if let Some(index) = removed {
+                    self.fields.keywords.remove(index);
+                    changed |= self.save(config);
+                }
+
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.new_tag)
+                            .hint_text("New tag")
+                            .desired_width(160.0),
+                    );
+                    let entered = response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let tag = self.new_tag.trim().to_string();
+                    if (ui.button("Add").clicked() || entered)
+                        && !tag.is_empty()
+                    {
+                        if !self.fields.has_tag(&tag) {
+                            self.fields.keywords.push(tag);
+                            changed |= self.save(config);
+                        }
+                        self.new_tag.clear();
+                    }
+                });
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+        changed
+    }
+}
@@ -0,0 +1,59 @@
+use eframe::egui::{self, Align2, Context, Vec2};
+
+use crate::hdr::{ToneMap, ToneMapping};
+
+/// Range of the exposure slider, in stops
+const EV_RANGE: std::ops::RangeInclusive<f32> = -10.0..=10.0;
+
+/// Small panel over HDR images for picking the tone mapping operator and
+/// exposure they are shown with.
+pub struct ToneMapPanel {
+    tone: ToneMapping,
+}
+
+impl ToneMapPanel {
+    pub fn new() -> Self {
+        Self {
+            tone: ToneMapping::default()
+        }
+    }
+
+    pub fn tone(&self) -> ToneMapping {
+        self.tone
+    }
+
+    /// Shows the panel in the bottom right corner. Returns whether the tone
+    /// mapping changed, so the image has to be converted again.
+    pub fn render(&mut self, ctx: &Context) -> bool {
+        let before = self.tone;
+        egui::Window::new("Tone Mapping")
+            .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-10.0, -10.0))
+            .resizable(false)
+            .default_open(true)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Operator")
+                    .selected_text(self.tone.method.label())
+                    .show_ui(ui, |ui| {
+                        for method in ToneMap::ALL {
+                            ui.selectable_value(
+                                &mut self.tone.method,
+                                method,
+                                method.label(),
+                            );
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::Slider::new(&mut self.tone.ev, EV_RANGE)
+                            .step_by(0.1)
+                            .suffix(" EV")
+                            .text("Exposure"),
+                    );
+                    if ui.small_button("Reset").clicked() {
+                        self.tone.ev = 0.0;
+                    }
+                });
+            });
+        self.tone != before
+    }
+}
@@ -0,0 +1,91 @@
+use eframe::egui::{self, Context, Key};
+use std::path::PathBuf;
+
+enum State {
+    Hidden,
+    Confirm(PathBuf),
+    Failed(String),
+}
+
+/// Asks before an image is moved to the trash, and tells when that failed.
+pub struct TrashPrompt {
+    state: State,
+}
+
+impl TrashPrompt {
+    pub fn new() -> Self {
+        Self {
+            state: State::Hidden
+        }
+    }
+
+    pub fn ask(&mut self, path: PathBuf) {
+        self.state = State::Confirm(path);
+    }
+
+    pub fn fail(&mut self, message: String) {
+        self.state = State::Failed(message);
+    }
+
+    /// Shows the prompt while one is pending and returns the file once the
+    /// user confirmed. Ticking "Don't ask again" clears `confirm`.
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        confirm: &mut bool,
+    ) -> Option<PathBuf> {
+        let mut accepted = false;
+        let mut closed = false;
+        match &self.state {
+            State::Hidden => return None,
+            State::Confirm(path) => {
+                let name = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                egui::Window::new("Move to Trash")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.label(format!("Move {} to the trash?", name));
+                        let mut skip = !*confirm;
+                        if ui
+                            .checkbox(&mut skip, "Don't ask again")
+                            .changed()
+                        {
+                            *confirm = !skip;
+                        }
+                        ui.horizontal(|ui| {
+                            accepted = ui.button("Move to Trash").clicked()
+                                || ui.input(|i| i.key_pressed(Key::Enter));
+                            closed = ui.button("Cancel").clicked()
+                                || ui.input(|i| i.key_pressed(Key::Escape));
+                        });
+                    });
+            },
+            State::Failed(message) => {
+                egui::Window::new("Could not move to the trash")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                    .show(ctx, |ui| {
+                        ui.label(message);
+                        closed = ui.button("OK").clicked();
+                    });
+            },
+        }
+
+        if accepted {
+            if let State::Confirm(path) =
+                std::mem::replace(&mut self.state, State::Hidden)
+            {
+                return Some(path);
+            }
+        } else if closed {
+            self.state = State::Hidden;
+        }
+        None
+    }
+}
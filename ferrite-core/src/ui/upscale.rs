@@ -0,0 +1,194 @@
+use eframe::egui::{self, Context};
+use ferrite_config::UpscaleConfig;
+use image::DynamicImage;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    image::ImageManager,
+    upscale::{self, UpscaleError},
+};
+
+struct PendingUpscale {
+    source:   PathBuf,
+    scale:    u32,
+    receiver: Receiver<Result<DynamicImage, UpscaleError>>,
+}
+
+struct UpscaleResult {
+    source: PathBuf,
+    scale:  u32,
+    image:  DynamicImage,
+}
+
+/// Window for running the external upscaler on the current file and
+/// switching between the original and the upscaled result.
+pub struct UpscalePanel {
+    open:    bool,
+    scale:   u32,
+    pending: Option<PendingUpscale>,
+    result:  Option<UpscaleResult>,
+    status:  Option<String>,
+}
+
+impl UpscalePanel {
+    pub fn new(config: &UpscaleConfig) -> Self {
+        Self {
+            open:    false,
+            scale:   config.default_scale,
+            pending: None,
+            result:  None,
+            status:  None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn start(&mut self, source: PathBuf, config: &UpscaleConfig) {
+        let (sender, receiver) = mpsc::channel();
+        let config = config.clone();
+        let scale = self.scale;
+        let input = source.clone();
+
+        thread::spawn(move || {
+            let _ = sender.send(upscale::run_external(&config, &input, scale));
+        });
+
+        self.pending = Some(PendingUpscale {
+            source,
+            scale,
+            receiver,
+        });
+        self.status = None;
+    }
+
+    /// Collects results from the upscaler and drops state that belongs to an
+    /// image that is no longer displayed.
+    pub fn update(&mut self, ctx: &Context, image_manager: &mut ImageManager) {
+        let current = image_manager.current_path().cloned();
+        if self
+            .result
+            .as_ref()
+            .is_some_and(|r| Some(&r.source) != current.as_ref())
+        {
+            self.result = None;
+        }
+
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        match pending.receiver.try_recv() {
+            Ok(Ok(image)) => {
+                info!("Upscaled image to {}x{}", image.width(), image.height());
+                if current.as_ref() == Some(&pending.source) {
+                    image_manager.show_derived(image.clone());
+                    self.result = Some(UpscaleResult {
+                        source: pending.source.clone(),
+                        scale: pending.scale,
+                        image,
+                    });
+                }
+                self.pending = None;
+            },
+            Ok(Err(e)) => {
+                warn!("Upscaling failed: {}", e);
+                self.status = Some(e.to_string());
+                self.pending = None;
+            },
+            Err(TryRecvError::Empty) => ctx.request_repaint(),
+            Err(TryRecvError::Disconnected) => {
+                self.status = Some("Upscaler thread terminated".to_string());
+                self.pending = None;
+            },
+        }
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        image_manager: &mut ImageManager,
+        config: &UpscaleConfig,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Upscale")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(source) = image_manager.current_path().cloned() else {
+                    ui.label("Open an image to upscale it.");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label("Factor:");
+                    for &scale in UpscaleConfig::supported_scales() {
+                        ui.selectable_value(
+                            &mut self.scale,
+                            scale,
+                            format!("{}×", scale),
+                        );
+                    }
+                });
+                ui.label(format!("Model: {}", config.model));
+
+                if self.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Upscaling...");
+                    });
+                } else if ui.button("Upscale").clicked() {
+                    self.start(source, config);
+                }
+
+                if let Some(result) = &self.result {
+                    ui.separator();
+                    ui.label(format!(
+                        "Result: {}×{} ({}×)",
+                        result.image.width(),
+                        result.image.height(),
+                        result.scale
+                    ));
+                    ui.horizontal(|ui| {
+                        if image_manager.is_showing_derived() {
+                            if ui.button("Show Original").clicked() {
+                                image_manager.restore_original();
+                            }
+                        } else if ui.button("Show Upscaled").clicked() {
+                            image_manager.show_derived(result.image.clone());
+                        }
+
+                        if ui.button("Export PNG").clicked() {
+                            let path = upscale::export_path(
+                                config,
+                                &result.source,
+                                result.scale,
+                            );
+                            self.status =
+                                Some(match result.image.save(&path) {
+                                    Ok(()) => {
+                                        format!("Saved {}", path.display())
+                                    },
+                                    Err(e) => format!("Export failed: {}", e),
+                                });
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,132 @@
+use eframe::egui::{self, Context};
+use ferrite_config::XmpConfig;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::metadata::xmp::{self, XmpFields};
+
+/// Window for editing the title, description, copyright and keywords of the
+/// current image.
+pub struct MetadataEditor {
+    open:     bool,
+    path:     Option<PathBuf>,
+    fields:   XmpFields,
+    keywords: String,
+    status:   Option<String>,
+}
+
+impl MetadataEditor {
+    pub fn new() -> Self {
+        Self {
+            open:     false,
+            path:     None,
+            fields:   XmpFields::default(),
+            keywords: String::new(),
+            status:   None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn load(&mut self, path: &Path) {
+        self.fields = xmp::read(path);
+        self.keywords = self.fields.keywords.join(", ");
+        self.path = Some(path.to_path_buf());
+        self.status = self
+            .fields
+            .is_empty()
+            .then(|| "No XMP metadata yet.".to_string());
+    }
+
+    fn save(&mut self, config: &XmpConfig) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.fields.keywords = self
+            .keywords
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(String::from)
+            .collect();
+        // Rated from the keyboard or the tags panel since the fields loaded
+        self.fields.rating = xmp::read(path).rating;
+
+        self.status =
+            Some(match xmp::write(path, &self.fields, config.target) {
+                Ok(written) => format!("Saved to {}", written.display()),
+                Err(e) => {
+                    warn!("Failed to write metadata: {}", e);
+                    format!("Saving failed: {}", e)
+                },
+            });
+    }
+
+    pub fn render_window(
+        &mut self,
+        ctx: &Context,
+        config: &XmpConfig,
+        current_path: Option<&PathBuf>,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        // Reload whenever the displayed image changes
+        match current_path {
+            Some(path) if self.path.as_ref() != Some(path) => self.load(path),
+            None => self.path = None,
+            _ => {},
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Edit Metadata")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                if self.path.is_none() {
+                    ui.label("No image open.");
+                    return;
+                }
+
+                egui::Grid::new("xmp-fields")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Title");
+                        ui.text_edit_singleline(&mut self.fields.title);
+                        ui.end_row();
+
+                        ui.label("Description");
+                        ui.text_edit_multiline(&mut self.fields.description);
+                        ui.end_row();
+
+                        ui.label("Copyright");
+                        ui.text_edit_singleline(&mut self.fields.copyright);
+                        ui.end_row();
+
+                        ui.label("Keywords");
+                        ui.text_edit_singleline(&mut self.keywords)
+                            .on_hover_text("Comma separated");
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        self.save(config);
+                    }
+                    if ui.button("Revert").clicked() {
+                        if let Some(path) = self.path.clone() {
+                            self.load(&path);
+                        }
+                    }
+                });
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        self.open = open;
+    }
+}
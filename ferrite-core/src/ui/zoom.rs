@@ -1,47 +1,234 @@
-use eframe::egui::Vec2;
+use eframe::egui::{Pos2, Vec2};
+use image::DynamicImage;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::{camera::Transform, kinetic::Coasting};
+
+/// Points of the image that dragging always leaves in view
+const MIN_VISIBLE: f32 = 50.0;
+
+/// Zoom levels offered in the View menu
+pub const ZOOM_PRESETS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FitMode {
+    /// Actual pixels, one image pixel per point
     OneToOne,
+    /// The whole image fits in the window
     FitLonger,
+    /// The image fills the window, cropping its longer side
     FitShorter,
     Custom,
 }
 
+/// Rotation and mirroring of the displayed image, applied when drawing so
+/// no pixels are touched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ViewTransform {
+    /// Clockwise quarter turns, 0 to 3
+    quarter_turns: u8,
+    /// Mirrored left to right after rotating
+    mirrored:      bool,
+}
+
+impl ViewTransform {
+    pub fn rotate(&mut self, clockwise: bool) {
+        // Mirroring swaps which way the image appears to turn
+        let step = if clockwise != self.mirrored { 1 } else { 3 };
+        self.quarter_turns = (self.quarter_turns + step) % 4;
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        self.mirrored = !self.mirrored;
+    }
+
+    pub fn flip_vertical(&mut self) {
+        self.quarter_turns = (self.quarter_turns + 2) % 4;
+        self.mirrored = !self.mirrored;
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Size on screen of an image of `size` before zooming
+    pub fn displayed_size(&self, size: Vec2) -> Vec2 {
+        if self.quarter_turns % 2 == 1 {
+            Vec2::new(size.y, size.x)
+        } else {
+            size
+        }
+    }
+
+    /// Texture coordinate shown at `corner` of the displayed image, both
+    /// from (0, 0) at the top left to (1, 1) at the bottom right.
+    pub fn source_uv(&self, corner: Pos2) -> Pos2 {
+        let mut uv = corner;
+        if self.mirrored {
+            uv.x = 1.0 - uv.x;
+        }
+        for _ in 0..self.quarter_turns {
+            uv = Pos2::new(uv.y, 1.0 - uv.x);
+        }
+        uv
+    }
+
+    /// Inverse of [`Self::source_uv`]: where texture coordinate `uv` is
+    /// shown in the displayed image.
+    pub fn displayed_uv(&self, uv: Pos2) -> Pos2 {
+        let mut corner = uv;
+        for _ in 0..self.quarter_turns {
+            corner = Pos2::new(1.0 - corner.y, corner.x);
+        }
+        if self.mirrored {
+            corner.x = 1.0 - corner.x;
+        }
+        corner
+    }
+
+    /// `image` as displayed, for saving a transformed copy
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let rotated = match self.quarter_turns {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image.clone(),
+        };
+        if self.mirrored {
+            rotated.fliph()
+        } else {
+            rotated
+        }
+    }
+}
+
 pub struct ZoomHandler {
-    zoom_level: f64,
-    pan_offset: Vec2,
+    camera:     Transform,
     fit_mode:   FitMode,
     min_zoom:   f64,
     max_zoom:   f64,
+    /// Physical pixels per point while zoom is snapped to the pixel grid
+    pixel_grid: Option<f32>,
+    /// Image and window size the zoom of the fit mode was computed for
+    fitted_to:  Option<(Vec2, Vec2)>,
+    transform:  ViewTransform,
+    /// Unscaled size of the image and the view it was last drawn in
+    bounds:     Option<(Vec2, Vec2)>,
+    /// Seconds a zoom takes to reach `camera`; 0 jumps there
+    duration:   f64,
+    transition: Option<Transition>,
+    /// Where a two-finger pan carries on to
+    coasting:   Coasting,
+}
+
+/// The shown view easing from `from` to the camera
+#[derive(Debug, Clone, Copy)]
+struct Transition {
+    from:     Transform,
+    /// Frame time of the first frame, unknown until it is drawn
+    started:  Option<f64>,
+    /// Eased fraction of the way, from 0 to 1
+    progress: f64,
+}
+
+/// Fast start and gentle stop
+fn ease_out(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Nearest zoom at or below `zoom`, or nearest either way unless `down`,
+/// at which one image pixel covers a whole number of physical pixels, or
+/// a whole number of image pixels fall into one.
+pub fn snap_to_pixel_grid(zoom: f64, pixels_per_point: f32, down: bool) -> f64 {
+    let ppp = pixels_per_point as f64;
+    let physical = zoom * ppp;
+    let snapped = if physical >= 1.0 {
+        if down {
+            physical.floor()
+        } else {
+            physical.round()
+        }
+    } else {
+        let divisor = 1.0 / physical;
+        1.0 / if down { divisor.ceil() } else { divisor.round() }
+    };
+    snapped / ppp
 }
 
 impl ZoomHandler {
     pub fn new(default_zoom: f64) -> Self {
         Self {
-            zoom_level: default_zoom,
-            pan_offset: Vec2::ZERO,
+            camera:     Transform::new(default_zoom),
             // Start with FitLonger as the default mode
             fit_mode:   FitMode::FitLonger,
             min_zoom:   0.1,
             max_zoom:   10.0,
+            pixel_grid: None,
+            fitted_to:  None,
+            transform:  ViewTransform::default(),
+            bounds:     None,
+            duration:   0.0,
+            transition: None,
+            coasting:   Coasting::default(),
         }
     }
 
-    pub fn update_for_new_image(
-        &mut self,
-        image_size: Vec2,
-        window_size: Vec2,
-    ) {
-        // When a new image is loaded, calculate and apply the appropriate zoom
-        // level
-        let new_zoom = self.calculate_fit_zoom(image_size, window_size);
+    /// Eases zooms over `duration` seconds from now on, or jumps at 0.
+    pub fn set_transition(&mut self, duration: f64) {
+        self.duration = duration;
+        self.transition = None;
+    }
 
-        // Always apply the calculated zoom unless we're in Custom mode
-        if self.fit_mode != FitMode::Custom {
-            self.zoom_level = new_zoom;
-            self.pan_offset = Vec2::ZERO;
+    /// Moves the shown view on to frame time `now`. Returns whether it is
+    /// still on the way, so another frame is needed.
+    pub fn animate(&mut self, now: f64) -> bool {
+        let Some(transition) = self.transition.as_mut() else {
+            return false;
+        };
+        let started = *transition.started.get_or_insert(now);
+        let t = (now - started) / self.duration;
+        if t >= 1.0 {
+            self.transition = None;
+            return false;
         }
+        transition.progress = ease_out(t.max(0.0));
+        true
+    }
+
+    /// Where the image is drawn this frame, on the way to `camera` while
+    /// a zoom animates.
+    pub fn shown(&self) -> Transform {
+        match self.transition {
+            Some(transition) => transition
+                .from
+                .lerp(self.camera, transition.progress),
+            None => self.camera,
+        }
+    }
+
+    /// Animates from the view on screen to the camera as it is now.
+    fn start_transition(&mut self, from: Transform) {
+        self.transition = (self.duration > 0.0 && from != self.camera)
+            .then_some(Transition {
+                from,
+                started: None,
+                progress: 0.0,
+            });
+    }
+
+    /// Applies the zoom of the fit mode whenever the image or the window
+    /// changed size since it was last computed. Called every frame; does
+    /// nothing in Custom mode.
+    pub fn fit_view(&mut self, image_size: Vec2, window_size: Vec2) {
+        if self.fit_mode == FitMode::Custom
+            || self.fitted_to == Some((image_size, window_size))
+        {
+            return;
+        }
+        self.camera =
+            Transform::new(self.calculate_fit_zoom(image_size, window_size));
+        self.fitted_to = Some((image_size, window_size));
+        self.transition = None;
+        self.coasting.stop();
     }
 
     pub fn calculate_fit_zoom(
@@ -57,53 +244,262 @@ impl ZoomHandler {
             FitMode::OneToOne => 1.0,
             FitMode::FitLonger => scale_x.min(scale_y),
             FitMode::FitShorter => scale_x.max(scale_y),
-            FitMode::Custom => self.zoom_level,
+            FitMode::Custom => self.camera.scale,
         };
 
         // Ensure zoom stays within bounds
+        let zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+        match (self.pixel_grid, self.fit_mode) {
+            (Some(ppp), FitMode::OneToOne) => {
+                snap_to_pixel_grid(zoom, ppp, false)
+            },
+            // Rounding down keeps the whole image in the window
+            (Some(ppp), FitMode::FitLonger | FitMode::FitShorter) => {
+                snap_to_pixel_grid(zoom, ppp, true)
+            },
+            _ => zoom,
+        }
+    }
+
+    pub fn pixel_grid(&self) -> Option<f32> {
+        self.pixel_grid
+    }
+
+    /// Snaps zoom to the physical pixel grid with `pixels_per_point` until
+    /// called with `None`.
+    pub fn set_pixel_grid(&mut self, pixels_per_point: Option<f32>) {
+        self.pixel_grid = pixels_per_point;
+        if let Some(ppp) = pixels_per_point {
+            self.camera.scale =
+                snap_to_pixel_grid(self.camera.scale, ppp, false);
+        }
+    }
+
+    /// Zoom level one step in or out from the current one: the next pixel
+    /// multiple when snapping to the pixel grid, else 10% either way.
+    pub fn step_zoom(&self, zoom_in: bool) -> f64 {
+        let zoom = match self.pixel_grid {
+            Some(ppp) => {
+                let physical =
+                    snap_to_pixel_grid(self.camera.scale, ppp, false)
+                        * ppp as f64;
+                let stepped = match (physical >= 1.0, zoom_in) {
+                    (true, true) => physical + 1.0,
+                    (true, false) if physical > 1.0 => physical - 1.0,
+                    (_, true) => 1.0 / (1.0 / physical - 1.0),
+                    (_, false) => 1.0 / (1.0 / physical + 1.0),
+                };
+                stepped / ppp as f64
+            },
+            None => self.camera.scale * if zoom_in { 1.1 } else { 0.9 },
+        };
         zoom.clamp(self.min_zoom, self.max_zoom)
     }
 
+    /// The first of the ascending `steps` past the current zoom, in or out.
+    /// Past the last step the zoom stays.
+    pub fn preset_step(&self, steps: &[f64], zoom_in: bool) -> f64 {
+        let zoom = self.camera.scale;
+        // A level within rounding of the current one counts as reached
+        let next = match zoom_in {
+            true => steps
+                .iter()
+                .find(|&&step| step > zoom * (1.0 + 1e-6)),
+            false => steps
+                .iter()
+                .rev()
+                .find(|&&step| step < zoom * (1.0 - 1e-6)),
+        };
+        next.copied()
+            .unwrap_or(zoom)
+            .clamp(self.min_zoom, self.max_zoom)
+    }
+
     pub fn reset_view_position(&mut self) {
-        self.pan_offset = Vec2::ZERO;
+        self.camera.translation = Vec2::ZERO;
+        self.transition = None;
+        self.coasting.stop();
+    }
+
+    pub fn transform(&self) -> ViewTransform {
+        self.transform
     }
 
+    /// Rotates or flips the view with `change`, refitting it in the fit
+    /// modes.
+    pub fn change_transform(
+        &mut self,
+        change: impl FnOnce(&mut ViewTransform),
+    ) {
+        change(&mut self.transform);
+        self.fitted_to = None;
+    }
+
+    /// Shows the next image upright and unmirrored.
+    pub fn reset_transform(&mut self) {
+        self.change_transform(|t| *t = ViewTransform::default());
+    }
+
+    /// Zoom `new_zoom` within the limits, on the pixel grid if snapping
+    fn allowed_zoom(&self, new_zoom: f64) -> f64 {
+        let zoom = new_zoom.clamp(self.min_zoom, self.max_zoom);
+        match self.pixel_grid {
+            Some(ppp) => snap_to_pixel_grid(zoom, ppp, false),
+            None => zoom,
+        }
+    }
+
+    /// Zooms about the view center.
     pub fn set_zoom(&mut self, new_zoom: f64) {
-        self.zoom_level = new_zoom.clamp(self.min_zoom, self.max_zoom);
+        self.zoom_about(Vec2::ZERO, new_zoom);
+    }
+
+    /// Zooms so that the image point under `anchor`, measured from the view
+    /// center, stays under it.
+    pub fn zoom_about(&mut self, anchor: Vec2, new_zoom: f64) {
+        let zoom = self.allowed_zoom(new_zoom);
+        let shown = self.shown();
+        self.camera.zoom_about(anchor, zoom);
+        self.start_transition(shown);
         // Setting zoom explicitly switches to custom mode
         self.fit_mode = FitMode::Custom;
     }
 
+    /// Zooms by `factor` about `anchor` at once, following a pinch that
+    /// already moves smoothly.
+    pub fn pinch(&mut self, anchor: Vec2, factor: f64) {
+        let zoom = self.allowed_zoom(self.camera.scale * factor);
+        self.transition = None;
+        self.camera.zoom_about(anchor, zoom);
+        self.fit_mode = FitMode::Custom;
+    }
+
+    /// Sets zoom and pan offset directly, e.g. for animated views.
+    pub fn set_view(&mut self, zoom: f64, offset: Vec2) {
+        self.camera = Transform {
+            scale:       self.allowed_zoom(zoom),
+            translation: offset,
+        };
+        self.fit_mode = FitMode::Custom;
+        self.transition = None;
+        self.coasting.stop();
+    }
+
     pub fn add_offset(&mut self, delta: Vec2) {
-        self.pan_offset += delta;
+        self.coasting.stop();
+        self.pan(delta);
+    }
+
+    /// Pans with fingers that moved `delta` over `dt` seconds, which the
+    /// view coasts on from once they lift.
+    pub fn pan_by_fingers(&mut self, delta: Vec2, dt: f32) {
+        self.pan(delta);
+        self.coasting.track(delta, dt);
+        self.keep_in_view();
+    }
+
+    /// Carries a two-finger pan on by `dt` seconds. Returns whether it is
+    /// still moving.
+    pub fn coast(&mut self, dt: f32) -> bool {
+        match self.coasting.coast(dt) {
+            Some(step) => {
+                self.pan(step);
+                self.keep_in_view();
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn pan(&mut self, delta: Vec2) {
+        self.camera.pan(delta);
+        // A drag moves the image with the pointer even mid-zoom
+        if let Some(transition) = self.transition.as_mut() {
+            transition.from.pan(delta);
+        }
         // When panning, switch to custom mode
         self.fit_mode = FitMode::Custom;
     }
 
+    /// Records where the image of `image_size` displayed pixels is drawn,
+    /// for panning to stay within.
+    pub fn set_bounds(&mut self, image_size: Vec2, view: Vec2) {
+        self.bounds = Some((image_size, view));
+    }
+
+    /// Whether the image is larger than the view along either side
+    pub fn is_zoomed_in(&self) -> bool {
+        self.bounds.is_some_and(|(image_size, view)| {
+            let scaled = image_size * self.camera.scale as f32;
+            scaled.x > view.x || scaled.y > view.y
+        })
+    }
+
+    /// Pans back so part of the image stays in the view, and centers it
+    /// along the sides it fits.
+    pub fn keep_in_view(&mut self) {
+        if let Some((image_size, view)) = self.bounds {
+            self.camera
+                .keep_in_view(image_size, view, MIN_VISIBLE);
+        }
+    }
+
+    /// Zooms and pans so that the image region starting at `min` with size
+    /// `region` fills the window. With `fit_longer` the whole region stays
+    /// visible; otherwise its shorter side fills the window.
+    pub fn zoom_to_region(
+        &mut self,
+        image_size: Vec2,
+        min: Vec2,
+        region: Vec2,
+        window_size: Vec2,
+        fit_longer: bool,
+    ) {
+        let scale = window_size / region;
+        let zoom = if fit_longer { scale.min_elem() } else { scale.max_elem() };
+        let mut zoom = (zoom as f64).clamp(self.min_zoom, self.max_zoom);
+        if let Some(ppp) = self.pixel_grid {
+            zoom = snap_to_pixel_grid(zoom, ppp, true);
+        }
+
+        // The region center goes to the view center
+        let center = min + region / 2.0 - image_size / 2.0;
+        let shown = self.shown();
+        self.camera = Transform::new(zoom);
+        self.camera.translation = -self.camera.to_screen(center);
+        self.fit_mode = FitMode::Custom;
+        self.start_transition(shown);
+        self.coasting.stop();
+    }
+
     pub fn reset(&mut self) {
-        self.fit_mode = FitMode::OneToOne;
-        self.zoom_level = 1.0;
-        self.pan_offset = Vec2::ZERO;
+        self.set_fit_mode(FitMode::OneToOne);
+        self.camera = Transform::new(match self.pixel_grid {
+            Some(ppp) => snap_to_pixel_grid(1.0, ppp, false),
+            None => 1.0,
+        });
     }
 
     // Getters and setters
     pub fn zoom_level(&self) -> f64 {
-        self.zoom_level
+        self.camera.scale
     }
 
     pub fn zoom_percentage(&self) -> f64 {
-        self.zoom_level * 100.0
+        self.camera.scale * 100.0
     }
 
     pub fn offset(&self) -> Vec2 {
-        self.pan_offset
+        self.camera.translation
     }
 
     pub fn get_fit_mode(&self) -> FitMode {
         self.fit_mode
     }
 
+    /// Switches to `mode`, which is applied on the next frame.
     pub fn set_fit_mode(&mut self, mode: FitMode) {
         self.fit_mode = mode;
+        self.fitted_to = None;
     }
 }
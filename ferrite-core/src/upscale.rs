@@ -0,0 +1,129 @@
+//! Super-resolution upscaling through an external ESRGAN-class tool.
+//!
+//! The tool is invoked with the `realesrgan-ncnn-vulkan` command line
+//! convention and writes a PNG into a temporary location, which is decoded
+//! back into memory for inspection and export.
+
+use ferrite_config::UpscaleConfig;
+use image::{DynamicImage, ImageError};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use thiserror::Error;
+use tracing::{info, instrument, warn};
+
+static RUN_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Error, Debug)]
+pub enum UpscaleError {
+    #[error("Upscaler binary {0} could not be started: {1}")]
+    Launch(PathBuf, io::Error),
+
+    #[error("Upscaler exited with {status}: {stderr}")]
+    Failed { status: String, stderr: String },
+
+    #[error("Failed to read upscaled result: {0}")]
+    Result(#[from] ImageError),
+}
+
+/// Upscales the image at `input` by `scale` using the configured tool.
+#[instrument(skip(config), fields(binary = %config.binary.display()))]
+pub fn run_external(
+    config: &UpscaleConfig,
+    input: &Path,
+    scale: u32,
+) -> Result<DynamicImage, UpscaleError> {
+    let output = temp_output_path();
+
+    info!("Running upscaler on {} at {}x", input.display(), scale);
+    let result = Command::new(&config.binary)
+        .arg("-i")
+        .arg(input)
+        .arg("-o")
+        .arg(&output)
+        .arg("-n")
+        .arg(&config.model)
+        .arg("-s")
+        .arg(scale.to_string())
+        .output()
+        .map_err(|e| UpscaleError::Launch(config.binary.clone(), e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output);
+        return Err(UpscaleError::Failed {
+            status: result.status.to_string(),
+            stderr: String::from_utf8_lossy(&result.stderr)
+                .trim()
+                .to_string(),
+        });
+    }
+
+    let image = image::open(&output);
+    if let Err(e) = std::fs::remove_file(&output) {
+        warn!("Failed to remove temporary upscale output: {}", e);
+    }
+    Ok(image?)
+}
+
+/// Builds the export path for an upscaled copy of `source`, e.g.
+/// `photo.jpg` -> `photo_x4.png`.
+pub fn export_path(
+    config: &UpscaleConfig,
+    source: &Path,
+    scale: u32,
+) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "upscaled".to_string());
+    let directory = config
+        .output_dir
+        .clone()
+        .or_else(|| source.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    directory.join(format!("{}_x{}.png", stem, scale))
+}
+
+fn temp_output_path() -> PathBuf {
+    let run = RUN_COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "ferrite-upscale-{}-{}.png",
+        std::process::id(),
+        run
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_path_next_to_source() {
+        let config = UpscaleConfig::default();
+        let path = export_path(&config, Path::new("/photos/cat.jpg"), 4);
+        assert_eq!(path, PathBuf::from("/photos/cat_x4.png"));
+    }
+
+    #[test]
+    fn test_export_path_uses_output_dir() {
+        let config = UpscaleConfig {
+            output_dir: Some(PathBuf::from("/exports")),
+            ..UpscaleConfig::default()
+        };
+        let path = export_path(&config, Path::new("/photos/cat.jpg"), 2);
+        assert_eq!(path, PathBuf::from("/exports/cat_x2.png"));
+    }
+
+    #[test]
+    fn test_missing_binary_reports_launch_error() {
+        let config = UpscaleConfig {
+            binary: PathBuf::from("/nonexistent/ferrite-upscaler"),
+            ..UpscaleConfig::default()
+        };
+        let result = run_external(&config, Path::new("in.png"), 4);
+        assert!(matches!(result, Err(UpscaleError::Launch(..))));
+    }
+}
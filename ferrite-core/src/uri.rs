@@ -0,0 +1,43 @@
+//! Paths written the way free desktops expect them in `file://` URIs and
+//! `.trashinfo` files: bytes outside the unreserved set percent-encoded.
+
+use std::{os::unix::ffi::OsStrExt, path::Path};
+
+/// `path` percent-encoded, keeping its slashes
+pub(crate) fn encode(path: &Path) -> String {
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `file://` URI of the absolute `path`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn file_uri(path: &Path) -> String {
+    format!("file://{}", encode(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri_escapes_reserved_bytes() {
+        assert_eq!(
+            file_uri(Path::new("/tmp/sun set #2 100%.png")),
+            "file:///tmp/sun%20set%20%232%20100%25.png"
+        );
+        assert_eq!(file_uri(Path::new("/tmp/é.png")), "file:///tmp/%C3%A9.png");
+    }
+}
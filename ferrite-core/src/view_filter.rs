@@ -0,0 +1,99 @@
+//! Narrowing a folder down to the images worth a second look, by star,
+//! rating and tags, for culling.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use crate::metadata;
+
+/// What an image needs to be listed. The default lets every image through.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ViewFilter {
+    /// Only these images, when set
+    pub starred:    Option<BTreeSet<PathBuf>>,
+    /// Fewest stars of the XMP rating
+    pub min_rating: u8,
+    /// Keywords an image needs all of
+    pub tags:       Vec<String>,
+}
+
+impl ViewFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether passing needs the XMP metadata of the files
+    pub fn reads_files(&self) -> bool {
+        self.min_rating > 0 || !self.tags.is_empty()
+    }
+
+    /// What passes on the stars alone, which reads no files
+    pub fn starred_only(&self) -> Self {
+        Self {
+            starred: self.starred.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether `path` passes. The XMP metadata is only read if the rating or
+    /// tags are asked for, and once per file until it changes.
+    pub fn matches(&self, path: &Path) -> bool {
+        if let Some(starred) = &self.starred {
+            if !starred.contains(path) {
+                return false;
+            }
+        }
+        if !self.reads_files() {
+            return true;
+        }
+        let fields = metadata::xmp_fields(path);
+        fields.rating >= self.min_rating
+            && self.tags.iter().all(|tag| fields.has_tag(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::xmp::{self, XmpFields};
+    use std::fs;
+
+    #[test]
+    fn test_rating_and_tags_narrow_the_images() {
+        let dir = std::env::temp_dir().join("ferrite-view-filter");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("a.png");
+        let fields = XmpFields {
+            keywords: vec!["Harbour".into(), "night".into()],
+            rating: 3,
+            ..XmpFields::default()
+        };
+        fs::write(xmp::sidecar_path(&image), fields.to_packet()).unwrap();
+
+        let filter = |min_rating, tags: &[&str]| ViewFilter {
+            starred: None,
+            min_rating,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        assert!(filter(3, &["harbour"]).matches(&image));
+        assert!(!filter(4, &[]).matches(&image));
+        assert!(!filter(0, &["harbour", "day"]).matches(&image));
+        assert!(!filter(1, &[]).matches(&dir.join("unrated.png")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_starred_images_only() {
+        let filter = ViewFilter {
+            starred: Some(BTreeSet::from([PathBuf::from("/a.png")])),
+            ..ViewFilter::default()
+        };
+        assert!(filter.matches(Path::new("/a.png")));
+        assert!(!filter.matches(Path::new("/b.png")));
+        assert!(ViewFilter::default().is_empty());
+        assert!(!filter.is_empty());
+    }
+}
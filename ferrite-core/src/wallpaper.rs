@@ -0,0 +1,237 @@
+//! Headless wallpaper rotation for `--wallpaper-daemon`.
+//!
+//! Every interval the next image of a folder is decoded exactly like the
+//! viewer would (decoding limits, sandbox, lens correction), written out as
+//! a PNG in the cache directory and handed to the desktop's wallpaper
+//! command. Going through a PNG lets desktops show formats they cannot
+//! decode themselves.
+
+use ferrite_config::FerriteConfig;
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
+use thiserror::Error;
+use tracing::{info, instrument, warn};
+
+use crate::{
+    filters::ViewFilter,
+    image::{ImageLoadError, ImageManager},
+    lens::{CorrectionOptions, LensDatabase},
+    metadata,
+    navigation,
+    process::shell_command,
+};
+
+#[derive(Error, Debug)]
+pub enum WallpaperError {
+    #[error("No supported images in {0}")]
+    NoImages(PathBuf),
+
+    #[error("Failed to read wallpaper folder: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(
+        "Don't know how to set the wallpaper on this desktop; set \
+         wallpaper.command in the configuration"
+    )]
+    NoBackend,
+
+    #[error("Wallpaper command {command} failed: {stderr}")]
+    Command { command: String, stderr: String },
+}
+
+/// Rotates the wallpaper through the images in `folder` until the process is
+/// stopped. Images that fail to decode are skipped.
+#[instrument(skip(config))]
+pub fn run_daemon(
+    folder: &Path,
+    interval: Duration,
+    config: &FerriteConfig,
+) -> Result<(), WallpaperError> {
+    let output_dir = config
+        .paths
+        .resolve_cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("wallpaper");
+    fs::create_dir_all(&output_dir)?;
+
+    // Each file is rendered once per cycle, so nothing is cached
+    let mut images =
+        ImageManager::new(config.decoding.clone(), config.io.clone(), 0);
+    let lenses = config.lens.auto_apply.then(|| {
+        let mut dirs: Vec<PathBuf> =
+            config.lens.database_dir.iter().cloned().collect();
+        dirs.extend(LensDatabase::default_dirs());
+        LensDatabase::load(&dirs)
+    });
+    let mut rng = fastrand::Rng::new();
+    let mut index = 0;
+    let mut rendered = 0usize;
+
+    loop {
+        // Rescanned every round so files added to the folder show up
+        let paths = navigation::list_images(folder, &config.navigation, None)?;
+        if paths.is_empty() {
+            return Err(WallpaperError::NoImages(folder.to_path_buf()));
+        }
+        let path = if config.wallpaper.shuffle {
+            &paths[rng.usize(..paths.len())]
+        } else {
+            index %= paths.len();
+            index += 1;
+            &paths[index - 1]
+        };
+
+        // Desktops tend to ignore a wallpaper change to the same file name,
+        // so alternate between two
+        let target = output_dir.join(format!("wallpaper-{}.png", rendered % 2));
+        match render(&mut images, lenses.as_ref(), path, &target) {
+            Ok(()) => {
+                set_wallpaper(&target, config.wallpaper.command.as_deref())?;
+                info!("Wallpaper set to {}", path.display());
+                rendered += 1;
+            },
+            Err(e) => warn!("Skipping {}: {}", path.display(), e),
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+fn render(
+    images: &mut ImageManager,
+    lenses: Option<&LensDatabase>,
+    path: &Path,
+    target: &Path,
+) -> Result<(), ImageLoadError> {
+    images.load_image(path.to_path_buf())?;
+    let Some(image) = images.original_image() else {
+        return Err(ImageLoadError::InvalidPath(path.display().to_string()));
+    };
+
+    let correction = lenses.and_then(|database| {
+        let info = metadata::read_lens_info(path)?;
+        let profile = database.find(&info)?;
+        let correction = profile.correction(
+            info.focal_length,
+            info.aperture,
+            CorrectionOptions::all(),
+        );
+        (!correction.is_identity()).then_some(correction)
+    });
+    match correction {
+        Some(correction) => correction.apply(image).save(target)?,
+        None => image.save(target)?,
+    }
+    Ok(())
+}
+
+fn set_wallpaper(
+    image: &Path,
+    command: Option<&str>,
+) -> Result<(), WallpaperError> {
+    let commands = match command {
+        Some(template) => vec![shell_command(template, image)],
+        None => desktop_commands(image).ok_or(WallpaperError::NoBackend)?,
+    };
+
+    for mut command in commands {
+        let description = format!("{:?}", command.get_program());
+        let output = command
+            .output()
+            .map_err(|e| WallpaperError::Command {
+                command: description.clone(),
+                stderr:  e.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(WallpaperError::Command {
+                command: description,
+                stderr:  String::from_utf8_lossy(&output.stderr)
+                    .trim()
+                    .to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_commands(image: &Path) -> Option<Vec<Command>> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_lowercase();
+    let uri = crate::uri::file_uri(image);
+
+    let gsettings = |schema: &str, keys: &[&str]| {
+        keys.iter()
+            .map(|key| {
+                let mut command = Command::new("gsettings");
+                command.args(["set", schema, key, &uri]);
+                command
+            })
+            .collect()
+    };
+
+    if desktop.contains("gnome") || desktop.contains("unity") {
+        Some(gsettings("org.gnome.desktop.background", &[
+            "picture-uri",
+            "picture-uri-dark",
+        ]))
+    } else if desktop.contains("cinnamon") {
+        Some(gsettings("org.cinnamon.desktop.background", &["picture-uri"]))
+    } else if desktop.contains("kde") {
+        let mut command = Command::new("plasma-apply-wallpaperimage");
+        command.arg(image);
+        Some(vec![command])
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        let mut command = Command::new("swaymsg");
+        command
+            .args(["output", "*", "bg"])
+            .arg(image)
+            .arg("fill");
+        Some(vec![command])
+    } else if std::env::var_os("DISPLAY").is_some() {
+        let mut command = Command::new("feh");
+        command.arg("--bg-fill").arg(image);
+        Some(vec![command])
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn desktop_commands(image: &Path) -> Option<Vec<Command>> {
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!(
+        "tell application \"System Events\" to tell every desktop to set \
+         picture to \"{}\"",
+        image.display()
+    ));
+    Some(vec![command])
+}
+
+#[cfg(windows)]
+fn desktop_commands(image: &Path) -> Option<Vec<Command>> {
+    // SPI_SETDESKWALLPAPER with SPIF_UPDATEINIFILE | SPIF_SENDCHANGE
+    let script = format!(
+        "Add-Type -TypeDefinition 'using System.Runtime.InteropServices; \
+         public class Wallpaper {{ [DllImport(\"user32.dll\", CharSet = \
+         CharSet.Unicode)] public static extern int SystemParametersInfo(int \
+         action, int param, string file, int flags); }}'; \
+         [Wallpaper]::SystemParametersInfo(20, 0, '{}', 3)",
+        image.display().to_string().replace('\'', "''")
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    Some(vec![command])
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn desktop_commands(_image: &Path) -> Option<Vec<Command>> {
+    None
+}
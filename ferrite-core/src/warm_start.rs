@@ -0,0 +1,242 @@
+//! Data kept between runs so that reopening a folder is quick: thumbnails
+//! in the cache directory and an index of recently viewed files in the
+//! state directory.
+//!
+//! Thumbnails are keyed by the path, size and modification time of their
+//! source, so an edited file gets a new one. Stale entries are never read
+//! again and go once the store is over its limit, least recently used
+//! first.
+
+use image::{DynamicImage, ImageFormat};
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Reverse,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+/// Thumbnails on disk, one PNG per source file and variant
+#[derive(Debug, Clone)]
+pub struct ThumbnailStore {
+    dir: PathBuf,
+}
+
+impl ThumbnailStore {
+    /// Thumbnails are kept in `thumbnails` inside `cache_root`.
+    pub fn new(cache_root: Option<PathBuf>) -> Option<Self> {
+        cache_root.map(|root| Self {
+            dir: root.join("thumbnails")
+        })
+    }
+
+    /// File the thumbnail of `source` is stored in. `variant` tells apart
+    /// thumbnails made with different settings.
+    fn file_for(&self, source: &Path, variant: &str) -> Option<PathBuf> {
+        let metadata = fs::metadata(source).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update(source.to_string_lossy().as_bytes());
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.as_nanos().to_le_bytes());
+        hasher.update(variant.as_bytes());
+        let name: String = hasher.finalize()[..16]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(self.dir.join(format!("{}.png", name)))
+    }
+
+    /// Reads the stored thumbnail of `source`, marking it as used.
+    pub fn load(&self, source: &Path, variant: &str) -> Option<DynamicImage> {
+        let file = self.file_for(source, variant)?;
+        let image = image::open(&file).ok()?;
+        let touched = fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            debug!("Failed to mark {} as used: {}", file.display(), e);
+        }
+        Some(image)
+    }
+
+    pub fn save(&self, source: &Path, variant: &str, thumbnail: &DynamicImage) {
+        let Some(file) = self.file_for(source, variant) else {
+            return;
+        };
+        // PNG holds 8 and 16 bits per channel
+        let thumbnail = match thumbnail {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                DynamicImage::ImageRgba16(thumbnail.to_rgba16())
+            },
+            _ => thumbnail.clone(),
+        };
+        let saved = fs::create_dir_all(&self.dir)
+            .map_err(Into::into)
+            .and_then(|_| thumbnail.save_with_format(&file, ImageFormat::Png));
+        if let Err(e) = saved {
+            warn!("Failed to store thumbnail of {}: {}", source.display(), e);
+        }
+    }
+
+    /// Removes the least recently used thumbnails beyond `limit`. Returns
+    /// how many were removed.
+    pub fn prune(&self, limit: usize) -> usize {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let mut files: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect();
+        if files.len() <= limit {
+            return 0;
+        }
+        files.sort_unstable_by_key(|(modified, _)| Reverse(*modified));
+        files[limit..]
+            .iter()
+            .filter(|(_, path)| fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+/// Recently viewed files, newest first, kept in a text file with one path
+/// per line
+#[derive(Debug, Clone)]
+pub struct RecentIndex {
+    file:     Option<PathBuf>,
+    entries:  Vec<PathBuf>,
+    capacity: usize,
+}
+
+impl RecentIndex {
+    /// Reads `recent-files` in `state_dir`. A missing or unreadable index
+    /// starts out empty.
+    pub fn load(state_dir: Option<PathBuf>, capacity: usize) -> Self {
+        Self::load_file(state_dir.map(|dir| dir.join("recent-files")), capacity)
+    }
+
+    /// Reads the index kept in `file`, or keeps one in memory only without
+    /// a file.
+    pub fn load_file(file: Option<PathBuf>, capacity: usize) -> Self {
+        let mut entries: Vec<PathBuf> = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        entries.truncate(capacity);
+        Self {
+            file,
+            entries,
+            capacity,
+        }
+    }
+
+    pub fn entries(&self) -> &[PathBuf] {
+        &self.entries
+    }
+
+    pub fn most_recent(&self) -> Option<&Path> {
+        self.entries.first().map(PathBuf::as_path)
+    }
+
+    /// Moves `path` to the front and writes the index if it changed.
+    pub fn record(&mut self, path: &Path) -> io::Result<()> {
+        if self.most_recent() == Some(path) {
+            return Ok(());
+        }
+        self.entries.retain(|entry| entry != path);
+        self.entries.insert(0, path.to_path_buf());
+        self.entries.truncate(self.capacity);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for entry in &self.entries {
+            // A path with a line break cannot be stored in this format
+            let line = entry.to_string_lossy();
+            if !line.contains('\n') {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        fs::write(file, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn test_thumbnails_follow_their_source() {
+        let dir = std::env::temp_dir().join("ferrite-warm-thumbnails");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("photo.jpg");
+        fs::write(&source, b"original").unwrap();
+
+        let store = ThumbnailStore::new(Some(dir.join("cache"))).unwrap();
+        assert!(store.load(&source, "160").is_none());
+        let thumbnail = DynamicImage::ImageRgb8(RgbImage::new(4, 3));
+        store.save(&source, "160", &thumbnail);
+        assert_eq!(store.load(&source, "160").unwrap().width(), 4);
+        assert!(store.load(&source, "320").is_none());
+
+        // Editing the source makes its thumbnail stale
+        fs::write(&source, b"edited, and longer").unwrap();
+        assert!(store.load(&source, "160").is_none());
+        store.save(&source, "160", &thumbnail);
+        assert_eq!(store.prune(1), 1);
+        assert!(store.load(&source, "160").is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recent_index_keeps_newest_first() {
+        let dir = std::env::temp_dir().join("ferrite-warm-recent");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut index = RecentIndex::load(Some(dir.clone()), 2);
+        assert!(index.entries().is_empty());
+        for name in ["a.png", "b.png", "a.png", "c.png"] {
+            index.record(Path::new(name)).unwrap();
+        }
+        assert_eq!(index.entries(), [
+            PathBuf::from("c.png"),
+            PathBuf::from("a.png")
+        ]);
+
+        let reloaded = RecentIndex::load(Some(dir.clone()), 5);
+        assert_eq!(reloaded.entries(), index.entries());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,282 @@
+//! Noticing when the file on screen is rewritten, such as by an encoder or
+//! an editor exporting over it.
+//!
+//! A background thread compares the file's modification time and size at a
+//! fixed interval. A change is reported once the file has stayed the same
+//! for one more interval, so a file still being written is not read half
+//! done, and a file replaced by renaming over it counts like any other
+//! rewrite. Polling needs no platform support and works on network mounts,
+//! where change notifications are often missing. That is why this polls
+//! instead of using the `notify` crate: two `stat` calls per interval cost
+//! nothing next to decoding, and one code path behaves the same on every
+//! system and file system.
+//!
+//! The folder of the file can be watched the same way: its modification time
+//! changes whenever a file is added to it, removed or renamed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::warn;
+
+/// What is compared between polls
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// What a poll found changed
+enum Change {
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+/// Changes to the watched file and folder since the last call
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub file:      Option<PathBuf>,
+    pub directory: Option<PathBuf>,
+}
+
+struct Shared {
+    target:    Mutex<Option<PathBuf>>,
+    directory: Mutex<Option<PathBuf>>,
+    stop:      AtomicBool,
+}
+
+pub struct FileWatcher {
+    shared:  Arc<Shared>,
+    changes: Receiver<Change>,
+}
+
+impl FileWatcher {
+    /// Starts the polling thread. `wake` is called from it after a change,
+    /// so the UI can pick it up without polling itself. When the thread
+    /// cannot be started, nothing is reported changed.
+    pub fn new(interval: Duration, wake: impl Fn() + Send + 'static) -> Self {
+        let shared = Arc::new(Shared {
+            target:    Mutex::new(None),
+            directory: Mutex::new(None),
+            stop:      AtomicBool::new(false),
+        });
+        let (sender, changes) = mpsc::channel();
+        let thread_shared = Arc::clone(&shared);
+        if let Err(e) = thread::Builder::new()
+            .name("file-watcher".into())
+            .spawn(move || run(&thread_shared, interval, &sender, &wake))
+        {
+            warn!("Not watching files for changes: {}", e);
+        }
+        Self {
+            shared,
+            changes,
+        }
+    }
+
+    /// Watches `path` instead of the previous file, or nothing.
+    pub fn watch(&self, path: Option<&Path>) {
+        *self.shared.target.lock().unwrap() = path.map(Path::to_path_buf);
+    }
+
+    /// Watches the entries of `directory` instead of the previous folder,
+    /// or none.
+    pub fn watch_directory(&self, directory: Option<&Path>) {
+        *self.shared.directory.lock().unwrap() =
+            directory.map(Path::to_path_buf);
+    }
+
+    /// What changed since the last call, leaving out files and folders no
+    /// longer watched
+    pub fn changed(&self) -> Changes {
+        let target = self.shared.target.lock().unwrap().clone();
+        let directory = self.shared.directory.lock().unwrap().clone();
+        let mut changes = Changes::default();
+        for change in self.changes.try_iter() {
+            match change {
+                Change::File(path) if Some(&path) == target.as_ref() => {
+                    changes.file = Some(path)
+                },
+                Change::Directory(path)
+                    if Some(&path) == directory.as_ref() =>
+                {
+                    changes.directory = Some(path)
+                },
+                _ => {},
+            }
+        }
+        changes
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Polls one path, reporting a change once it settled
+#[derive(Default)]
+struct Poll {
+    watched:  Option<PathBuf>,
+    /// Stamp last seen, and whether it changed since the last report
+    last:     Stamp,
+    settling: bool,
+}
+
+impl Poll {
+    /// Whether `target` changed and then stayed the same since the last poll
+    fn settled(&mut self, target: Option<PathBuf>) -> bool {
+        let Some(path) = target else {
+            self.watched = None;
+            return false;
+        };
+        let current = stamp(&path);
+        if self.watched.as_ref() != Some(&path) {
+            self.watched = Some(path);
+            self.last = current;
+            self.settling = false;
+            return false;
+        }
+        if current != self.last {
+            self.last = current;
+            self.settling = true;
+        } else if self.settling && current.is_some() {
+            self.settling = false;
+            return true;
+        }
+        false
+    }
+}
+
+fn run(
+    shared: &Shared,
+    interval: Duration,
+    changes: &Sender<Change>,
+    wake: &dyn Fn(),
+) {
+    let mut file = Poll::default();
+    let mut directory = Poll::default();
+    while !shared.stop.load(Ordering::Relaxed) {
+        thread::sleep(interval);
+        let mut found = Vec::new();
+        let target = shared.target.lock().unwrap().clone();
+        if file.settled(target) {
+            found.extend(file.watched.clone().map(Change::File));
+        }
+        let target = shared.directory.lock().unwrap().clone();
+        if directory.settled(target) {
+            found.extend(directory.watched.clone().map(Change::Directory));
+        }
+        if found.is_empty() {
+            continue;
+        }
+        for change in found {
+            if changes.send(change).is_err() {
+                return;
+            }
+        }
+        wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    fn wait_for_change(watcher: &FileWatcher) -> Changes {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            let changes = watcher.changed();
+            if changes != Changes::default() {
+                return changes;
+            }
+            thread::sleep(INTERVAL);
+        }
+        Changes::default()
+    }
+
+    #[test]
+    fn test_reports_a_rewritten_file_once() {
+        let dir = std::env::temp_dir()
+            .join(format!("ferrite-watch-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.png");
+        fs::write(&path, b"first").unwrap();
+
+        let watcher = FileWatcher::new(INTERVAL, || {});
+        watcher.watch(Some(&path));
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(watcher.changed(), Changes::default());
+
+        fs::write(&path, b"second version").unwrap();
+        assert_eq!(wait_for_change(&watcher).file, Some(path.clone()));
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(watcher.changed(), Changes::default());
+
+        // Nothing is reported for a file no longer watched
+        watcher.watch(None);
+        fs::write(&path, b"third").unwrap();
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(watcher.changed(), Changes::default());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wakes_after_a_change() {
+        let dir = std::env::temp_dir()
+            .join(format!("ferrite-watch-wake-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.png");
+        fs::write(&path, b"first").unwrap();
+
+        let woken = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&woken);
+        let watcher = FileWatcher::new(INTERVAL, move || {
+            flag.store(true, Ordering::Relaxed)
+        });
+        watcher.watch(Some(&path));
+        thread::sleep(INTERVAL * 5);
+        fs::write(&path, b"rewritten").unwrap();
+        assert!(wait_for_change(&watcher).file.is_some());
+        // Woken right after the change is sent
+        thread::sleep(INTERVAL * 2);
+        assert!(woken.load(Ordering::Relaxed));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reports_a_file_added_to_the_folder() {
+        let dir = std::env::temp_dir()
+            .join(format!("ferrite-watch-dir-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("first.png"), b"first").unwrap();
+
+        let watcher = FileWatcher::new(INTERVAL, || {});
+        watcher.watch_directory(Some(&dir));
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(watcher.changed(), Changes::default());
+
+        fs::write(dir.join("second.png"), b"second").unwrap();
+        let changes = wait_for_change(&watcher);
+        assert_eq!(changes.directory, Some(dir.clone()));
+        assert_eq!(changes.file, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
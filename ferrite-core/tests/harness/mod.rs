@@ -0,0 +1,424 @@
+//! Headless driver for `FeriteApp`.
+//!
+//! Frames are run against a plain `egui::Context` with synthesized input, so
+//! tests can open fixture images, press keys, move and scroll the pointer and
+//! then assert on application state and on what the frame produced.
+
+#![allow(dead_code)]
+
+use eframe::egui::{
+    self,
+    Context,
+    Event,
+    FullOutput,
+    Key,
+    Modifiers,
+    Pos2,
+    RawInput,
+    Rect,
+    Vec2,
+};
+use ferrite_config::FerriteConfig;
+use ferrite_core::FeriteApp;
+use image::{Rgb, RgbImage};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SCREEN_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+const FRAME_TIME: f64 = 1.0 / 60.0;
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+static FIXTURE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// A temporary folder of generated images, removed on drop.
+pub struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    /// Creates one PNG per `(name, width, height)`, each filled with a
+    /// different color.
+    pub fn images(images: &[(&str, u32, u32)]) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "ferrite-ui-test-{}-{}",
+            std::process::id(),
+            FIXTURE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).expect("create fixture folder");
+
+        for (index, (name, width, height)) in images.iter().enumerate() {
+            let shade = (index as u8).wrapping_mul(60);
+            RgbImage::from_pixel(
+                *width,
+                *height,
+                Rgb([shade, 128, 255 - shade]),
+            )
+            .save(dir.join(name))
+            .expect("write fixture image");
+        }
+        Self {
+            dir,
+        }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        fs::canonicalize(self.dir.join(name)).expect("fixture exists")
+    }
+
+    /// Stores fixture images, uncompressed, in a ZIP archive called `name`,
+    /// each `(entry, fixture)` under the entry name.
+    pub fn archive(&self, name: &str, entries: &[(&str, &str)]) -> PathBuf {
+        let (mut zip, mut directory) = (Vec::new(), Vec::new());
+        for (entry, fixture) in entries {
+            let data = fs::read(self.dir.join(fixture)).expect("fixture");
+            let offset = zip.len() as u32;
+            let size = (data.len() as u32).to_le_bytes();
+            let name_len = (entry.len() as u16).to_le_bytes();
+            zip.extend(0x0403_4b50u32.to_le_bytes());
+            zip.extend([0; 14]);
+            zip.extend(size);
+            zip.extend(size);
+            zip.extend(name_len);
+            zip.extend([0; 2]);
+            zip.extend(entry.as_bytes());
+            zip.extend(&data);
+
+            directory.extend(0x0201_4b50u32.to_le_bytes());
+            directory.extend([0; 16]);
+            directory.extend(size);
+            directory.extend(size);
+            directory.extend(name_len);
+            directory.extend([0; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(entry.as_bytes());
+        }
+        let offset = zip.len() as u32;
+        let count = (entries.len() as u16).to_le_bytes();
+        zip.extend(&directory);
+        zip.extend(0x0605_4b50u32.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend(count);
+        zip.extend(count);
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(offset.to_le_bytes());
+        zip.extend([0; 2]);
+        fs::write(self.dir.join(name), zip).expect("write fixture archive");
+        self.path(name)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.state_dir);
+    }
+}
+
+pub struct Harness {
+    pub app:     FeriteApp,
+    ctx:         Context,
+    events:      Vec<Event>,
+    time:        f64,
+    pointer:     Option<Pos2>,
+    last_output: Option<FullOutput>,
+    screen_size: Vec2,
+    /// Held during the next frame
+    modifiers:   Modifiers,
+    /// Dropped onto the window during the next frame
+    dropped:     Vec<PathBuf>,
+    /// Stands in for the user's state directory, removed on drop
+    state_dir:   PathBuf,
+}
+
+impl Harness {
+    pub fn new(initial_image: Option<&Path>) -> Self {
+        Self::with_config(initial_image, FerriteConfig::default())
+    }
+
+    pub fn with_config(
+        initial_image: Option<&Path>,
+        mut config: FerriteConfig,
+    ) -> Self {
+        // Leave the user's cache and recent files alone
+        config.cache.warm_start = false;
+        let state_dir = std::env::temp_dir().join(format!(
+            "ferrite-ui-state-{}-{}",
+            std::process::id(),
+            FIXTURE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        config
+            .paths
+            .state_dir
+            .get_or_insert(state_dir.clone());
+        // Zooms land at once, so each frame shows where they end up
+        config.zoom.transition_enabled = false;
+        let app = FeriteApp::from_config(
+            initial_image.map(Path::to_path_buf),
+            config,
+        );
+        let mut harness = Self {
+            app,
+            ctx: Context::default(),
+            events: Vec::new(),
+            time: 0.0,
+            pointer: None,
+            last_output: None,
+            screen_size: SCREEN_SIZE,
+            modifiers: Modifiers::NONE,
+            dropped: Vec::new(),
+            state_dir,
+        };
+        // The first frame uploads the image and fits it to the window
+        harness.step();
+        harness
+    }
+
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+
+    /// Runs a frame with all input queued since the last one. If it started
+    /// opening an image, frames are run until the loader thread is done.
+    pub fn step(&mut self) -> &FullOutput {
+        self.run_frame();
+        let start = Instant::now();
+        while self.app.is_loading() {
+            assert!(start.elapsed() < LOAD_TIMEOUT, "image took too long");
+            thread::sleep(Duration::from_millis(1));
+            self.run_frame();
+        }
+        self.last_output.as_ref().expect("a frame ran")
+    }
+
+    fn run_frame(&mut self) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                self.screen_size,
+            )),
+            modifiers: std::mem::take(&mut self.modifiers),
+            time: Some(self.time),
+            predicted_dt: FRAME_TIME as f32,
+            events: std::mem::take(&mut self.events),
+            dropped_files: std::mem::take(&mut self.dropped)
+                .into_iter()
+                .map(|path| egui::DroppedFile {
+                    path: Some(path),
+                    ..Default::default()
+                })
+                .collect(),
+            ..RawInput::default()
+        };
+        self.time += FRAME_TIME;
+
+        let app = &mut self.app;
+        let output = self.ctx.run(input, |ctx| app.ui(ctx));
+        self.last_output = Some(output);
+    }
+
+    /// Runs frames until the app stops asking for repaints, for background
+    /// work such as scans to finish.
+    pub fn run_until_idle(&mut self, max_frames: usize) {
+        for _ in 0..max_frames {
+            self.step();
+            if !self.ctx.has_requested_repaint() {
+                break;
+            }
+        }
+    }
+
+    pub fn press(&mut self, key: Key) {
+        self.press_with(key, Modifiers::NONE);
+    }
+
+    /// Presses `key` while holding `modifiers`. Like the window does, the
+    /// copy and cut chords arrive as commands instead of keys.
+    pub fn press_with(&mut self, key: Key, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+        if modifiers.command {
+            match key {
+                Key::C => return self.events.push(Event::Copy),
+                Key::X => return self.events.push(Event::Cut),
+                _ => {},
+            }
+        }
+        for pressed in [true, false] {
+            self.events.push(Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers,
+            });
+        }
+    }
+
+    /// Resizes the window from the next frame on.
+    /// Pastes `text` as if from the clipboard.
+    pub fn paste(&mut self, text: &str) {
+        self.events.push(Event::Paste(text.to_string()));
+    }
+
+    pub fn resize(&mut self, size: Vec2) {
+        self.screen_size = size;
+    }
+
+    /// Presses `key` and runs the frame that handles it.
+    pub fn press_and_step(&mut self, key: Key) -> &FullOutput {
+        self.press(key);
+        self.step()
+    }
+
+    pub fn move_pointer(&mut self, pos: Pos2) {
+        self.pointer = Some(pos);
+        self.events.push(Event::PointerMoved(pos));
+    }
+
+    pub fn scroll(&mut self, delta: Vec2) {
+        self.scroll_with(delta, Modifiers::NONE);
+    }
+
+    /// Turns the wheel by `delta` lines, as winit reports a notched wheel.
+    pub fn scroll_with(&mut self, delta: Vec2, modifiers: Modifiers) {
+        self.events.push(Event::MouseWheel {
+            unit: egui::MouseWheelUnit::Line,
+            delta,
+            modifiers,
+        });
+        // Followed by what egui-winit derives from it
+        let points = delta * 50.0;
+        self.events
+            .push(if modifiers.ctrl || modifiers.command {
+                Event::Zoom((points.y / 200.0).exp())
+            } else if modifiers.shift {
+                Event::Scroll(Vec2::new(points.x + points.y, 0.0))
+            } else {
+                Event::Scroll(points)
+            });
+    }
+
+    /// Moves two fingers by `delta` points on a touchpad.
+    pub fn swipe(&mut self, delta: Vec2) {
+        self.events.push(Event::MouseWheel {
+            unit: egui::MouseWheelUnit::Point,
+            delta,
+            modifiers: Modifiers::NONE,
+        });
+        self.events.push(Event::Scroll(delta));
+    }
+
+    /// Pinches a touchpad, spreading the fingers above 1.
+    pub fn pinch(&mut self, factor: f32) {
+        self.events.push(Event::Zoom(factor));
+    }
+
+    /// Clicks `button` in the middle of the window.
+    pub fn click(&mut self, button: egui::PointerButton) {
+        self.click_at((self.screen_size / 2.0).to_pos2(), button);
+    }
+
+    /// Clicks `button` at `pos`.
+    pub fn click_at(&mut self, pos: Pos2, button: egui::PointerButton) {
+        self.move_pointer(pos);
+        for pressed in [true, false] {
+            self.events.push(Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: Modifiers::NONE,
+            });
+        }
+    }
+
+    pub fn drag(&mut self, from: Pos2, to: Pos2) {
+        let button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        self.move_pointer(from);
+        self.events.push(button(from, true));
+        self.step();
+        self.move_pointer(to);
+        self.step();
+        self.events.push(button(to, false));
+        self.step();
+    }
+
+    /// Drops `paths` onto the window and runs the frame that handles it.
+    pub fn drop_files(&mut self, paths: &[PathBuf]) -> &FullOutput {
+        self.dropped = paths.to_vec();
+        self.step()
+    }
+
+    /// Clicks twice at `pos`, a frame apart.
+    pub fn double_click(&mut self, pos: Pos2) {
+        self.move_pointer(pos);
+        for _ in 0..2 {
+            for pressed in [true, false] {
+                self.events.push(Event::PointerButton {
+                    pos,
+                    button: egui::PointerButton::Primary,
+                    pressed,
+                    modifiers: Modifiers::NONE,
+                });
+            }
+            self.step();
+        }
+    }
+
+    pub fn ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    pub fn last_output(&self) -> &FullOutput {
+        self.last_output
+            .as_ref()
+            .expect("at least one frame has run")
+    }
+
+    /// Number of textures created by the last frame
+    pub fn textures_uploaded(&self) -> usize {
+        self.last_output()
+            .textures_delta
+            .set
+            .iter()
+            .filter(|(_, delta)| delta.pos.is_none())
+            .count()
+    }
+
+    /// Whether the last frame asked the window to close
+    pub fn requested_close(&self) -> bool {
+        self.last_output()
+            .viewport_output
+            .values()
+            .any(|viewport| {
+                viewport
+                    .commands
+                    .contains(&egui::ViewportCommand::Close)
+            })
+    }
+
+    /// The fullscreen state the last frame asked the window for, if any
+    pub fn requested_fullscreen(&self) -> Option<bool> {
+        self.last_output()
+            .viewport_output
+            .values()
+            .flat_map(|viewport| &viewport.commands)
+            .find_map(|command| match command {
+                egui::ViewportCommand::Fullscreen(on) => Some(*on),
+                _ => None,
+            })
+    }
+}
@@ -0,0 +1,1035 @@
+mod harness;
+
+use eframe::egui::{Key, Modifiers, PointerButton, Pos2, Vec2};
+use ferrite_config::{Action, FerriteConfig, KeyChord, MoveTarget};
+use ferrite_core::{recent, session::ZoomState};
+use harness::{Fixture, Harness, SCREEN_SIZE};
+use std::path::PathBuf;
+
+fn folder() -> Fixture {
+    Fixture::images(&[
+        ("a.png", 200, 100),
+        ("b.png", 100, 200),
+        ("c.png", 50, 50),
+    ])
+}
+
+#[test]
+fn test_opens_initial_image_and_fits_it() {
+    let fixture = folder();
+    let harness = Harness::new(Some(&fixture.path("a.png")));
+
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    // A 200x100 image in an 800x600 window is scaled up to fit its width
+    let zoom = harness.app.zoom_level();
+    assert!(zoom > 3.5 && zoom <= 4.0, "unexpected fit zoom {}", zoom);
+    assert!(harness.textures_uploaded() >= 1);
+}
+
+#[test]
+fn test_navigation_keys_wrap_around_the_folder() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::A);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+    harness.press_and_step(Key::PageDown);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::PageUp);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+}
+
+#[test]
+fn test_keyboard_zoom_steps_and_reset() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("c.png")));
+    let fitted = harness.app.zoom_level();
+
+    harness.press_and_step(Key::S);
+    assert!((harness.app.zoom_level() - fitted * 0.9).abs() < 1e-9);
+    harness.press_and_step(Key::S);
+    assert!((harness.app.zoom_level() - fitted * 0.81).abs() < 1e-9);
+
+    harness.press_and_step(Key::Num0);
+    assert_eq!(harness.app.zoom_level(), 1.0);
+}
+
+#[test]
+fn test_keyboard_zoom_follows_predefined_steps() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.zoom.use_predefined_steps = true;
+    config.zoom.zoom_steps =
+        ferrite_config::ZoomSteps::new(vec![0.5, 1.0, 3.0, 8.0]).unwrap();
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("c.png")), config);
+    harness.press_and_step(Key::Num0);
+
+    harness.press_and_step(Key::W);
+    assert_eq!(harness.app.zoom_level(), 3.0);
+    harness.press_and_step(Key::W);
+    assert_eq!(harness.app.zoom_level(), 8.0);
+    // Stays at the last step
+    harness.press_and_step(Key::W);
+    assert_eq!(harness.app.zoom_level(), 8.0);
+    harness.press_and_step(Key::S);
+    assert_eq!(harness.app.zoom_level(), 3.0);
+}
+
+#[test]
+fn test_keys_pan_while_zoomed_in() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    // Fitted at 800 × 400, one step in overflows the window sideways
+    harness.press_and_step(Key::Plus);
+    let before = harness.app.image_rect().unwrap();
+
+    harness.press_and_step(Key::ArrowLeft);
+    let after = harness.app.image_rect().unwrap();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    assert!(after.center().x > before.center().x);
+    // It fits the window vertically, so stays centered that way
+    harness.press_and_step(Key::ArrowUp);
+    assert_eq!(harness.app.image_rect().unwrap().center().y, after.center().y);
+
+    // W and S zoom while zoomed in as well
+    let zoom = harness.app.zoom_level();
+    harness.press_and_step(Key::W);
+    let zoomed = harness.app.zoom_level();
+    assert!(zoomed > zoom);
+    harness.press_and_step(Key::S);
+    assert!(harness.app.zoom_level() < zoomed);
+
+    // Back at actual size the arrow keys step through images again
+    harness.press_and_step(Key::Num0);
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_touchpad_pinches_and_pans_with_coasting() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    let fitted = harness.app.zoom_level();
+
+    harness.pinch(1.5);
+    harness.step();
+    assert!((harness.app.zoom_level() - fitted * 1.5).abs() < 1e-6);
+
+    // Now 1200 wide, so there is room to pan sideways
+    let start = harness.app.image_rect().unwrap().center().x;
+    for _ in 0..3 {
+        harness.swipe(Vec2::new(-10.0, 0.0));
+        harness.step();
+    }
+    let lifted = harness.app.image_rect().unwrap().center().x;
+    assert_eq!(lifted, start - 30.0);
+    // Keeps going the same way for a while after the fingers lift
+    harness.run_until_idle(200);
+    let coasted = harness.app.image_rect().unwrap().center().x;
+    assert!(coasted < lifted - 10.0);
+    harness.step();
+    assert_eq!(harness.app.image_rect().unwrap().center().x, coasted);
+}
+
+#[test]
+fn test_mouse_buttons_trigger_their_bound_actions() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.controls.buttons.middle = Some(Action::ActualSize);
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+
+    harness.click(PointerButton::Extra2);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+    harness.click(PointerButton::Extra1);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+
+    harness.click(PointerButton::Middle);
+    harness.step();
+    harness.step();
+    assert_eq!(harness.app.zoom_level(), 1.0);
+}
+
+#[test]
+fn test_scroll_zooms_one_step_per_event() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("c.png")));
+    harness.press_and_step(Key::Num0);
+
+    harness.move_pointer(Pos2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0));
+    harness.scroll(Vec2::new(0.0, 1.0));
+    harness.step();
+    assert!((harness.app.zoom_level() - 1.1).abs() < 1e-9);
+}
+
+#[test]
+fn test_scroll_zoom_holds_the_point_under_the_pointer() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    // Off to the corner, away from the image center
+    let pointer = Pos2::new(SCREEN_SIZE.x - 20.0, 40.0);
+    harness.move_pointer(pointer);
+    harness.step();
+
+    for delta in [1.0, 1.0, 1.0, -1.0, 1.0] {
+        let before = harness.app.image_rect().unwrap();
+        harness.scroll(Vec2::new(0.0, delta));
+        harness.step();
+        let after = harness.app.image_rect().unwrap();
+        // Same fraction of the image under the pointer
+        let fraction =
+            |rect: eframe::egui::Rect| (pointer - rect.min) / rect.size();
+        assert!((fraction(before) - fraction(after)).length() < 1e-4);
+    }
+}
+
+#[test]
+fn test_texture_is_uploaded_once_per_image() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+
+    harness.step();
+    assert_eq!(harness.textures_uploaded(), 0);
+    harness.press_and_step(Key::D);
+    // The 100x200 image and its half size copy for zooming out
+    assert_eq!(harness.textures_uploaded(), 2);
+    harness.step();
+    assert_eq!(harness.textures_uploaded(), 0);
+}
+
+#[test]
+fn test_oversized_image_streams_the_tiles_in_view() {
+    let fixture = Fixture::images(&[("wide.png", 5000, 300)]);
+    let mut harness = Harness::new(Some(&fixture.path("wide.png")));
+    let allocated =
+        |harness: &Harness| harness.ctx().tex_manager().read().num_allocated();
+    harness.run_until_idle(10);
+    // Fitted, the image is drawn from a copy shrunk to the 2048 pixel limit
+    let overview = allocated(&harness);
+    assert!(harness
+        .ctx()
+        .tex_manager()
+        .read()
+        .allocated()
+        .all(|(_, meta)| meta.size[0].max(meta.size[1]) <= 2048));
+
+    // The middle of the image at 1:1 lies in the third column of tiles
+    harness.press_and_step(Key::Num1);
+    harness.run_until_idle(10);
+    assert_eq!(allocated(&harness), overview + 1);
+
+    // Panning left brings the second column into view
+    harness.drag(Pos2::new(100.0, 300.0), Pos2::new(700.0, 300.0));
+    harness.run_until_idle(10);
+    assert_eq!(allocated(&harness), overview + 2);
+
+    harness.press_and_step(Key::F);
+    harness.run_until_idle(10);
+    assert_eq!(allocated(&harness), overview);
+}
+
+#[test]
+fn test_quit_key_closes_window() {
+    let mut harness = Harness::new(None);
+    assert!(!harness.requested_close());
+    harness.press_and_step(Key::Q);
+    assert!(harness.requested_close());
+}
+
+#[test]
+fn test_oversized_image_is_held_back() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.decoding.max_dimension = 150;
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+
+    assert_eq!(harness.app.current_path(), None);
+    // Images within the limits still open normally
+    harness.press_and_step(Key::A);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+}
+
+#[test]
+fn test_slideshow_advances_after_interval() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.slideshow.interval_secs = 0.5;
+    config.slideshow.ken_burns = true;
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+
+    harness.press_and_step(Key::F5);
+    for _ in 0..20 {
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    for _ in 0..20 {
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+
+    harness.press_and_step(Key::Escape);
+    for _ in 0..40 {
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_pixel_perfect_zoom_steps_through_whole_multiples() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("c.png")));
+    harness.press_and_step(Key::Num0);
+    harness.press_and_step(Key::I);
+
+    harness.move_pointer(Pos2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0));
+    harness.scroll(Vec2::new(0.0, 1.0));
+    harness.step();
+    assert_eq!(harness.app.zoom_level(), 2.0);
+    harness.press_and_step(Key::S);
+    harness.press_and_step(Key::S);
+    assert_eq!(harness.app.zoom_level(), 0.5);
+
+    // Leaving the mode zooms freely again
+    harness.press_and_step(Key::I);
+    harness.press_and_step(Key::W);
+    assert!((harness.app.zoom_level() - 0.55).abs() < 1e-9);
+}
+
+#[test]
+fn test_view_modes_follow_keys_and_window_size() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    let fitted = harness.app.zoom_level();
+
+    harness.press_and_step(Key::Num1);
+    assert_eq!(harness.app.zoom_level(), 1.0);
+    // Filling the window makes the 100 pixel high image as tall as it
+    harness.press_with(Key::F, Modifiers::SHIFT);
+    harness.step();
+    let filled = harness.app.zoom_level();
+    assert!(filled > fitted, "{} <= {}", filled, fitted);
+    harness.press_and_step(Key::F);
+    assert_eq!(harness.app.zoom_level(), fitted);
+
+    // Fitting follows the window, a custom zoom stays put
+    harness.resize(SCREEN_SIZE / 2.0);
+    harness.step();
+    assert!((harness.app.zoom_level() - fitted / 2.0).abs() < 0.1);
+    harness.press_and_step(Key::W);
+    let custom = harness.app.zoom_level();
+    harness.resize(SCREEN_SIZE);
+    harness.step();
+    assert_eq!(harness.app.zoom_level(), custom);
+}
+
+#[test]
+fn test_wheel_follows_modifier_bindings() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("c.png")));
+    harness.press_and_step(Key::Num0);
+    harness.move_pointer(Pos2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0));
+
+    // Ctrl zooms like the plain wheel
+    harness.scroll_with(Vec2::new(0.0, 1.0), Modifiers::CTRL);
+    harness.step();
+    assert!((harness.app.zoom_level() - 1.1).abs() < 1e-9);
+
+    // Alt steps through the folder, turning down for the next image
+    harness.scroll_with(Vec2::new(0.0, -1.0), Modifiers::ALT);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.scroll_with(Vec2::new(0.0, 2.0), Modifiers::ALT);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_rotation_refits_and_resets_on_next_image() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    let fitted = harness.app.zoom_level();
+
+    // Turned upright the 200x100 image is limited by the window height
+    harness.press_and_step(Key::C);
+    let rotated = harness.app.zoom_level();
+    assert!(rotated < fitted, "{} >= {}", rotated, fitted);
+    harness.press_and_step(Key::X);
+    assert_eq!(harness.app.zoom_level(), rotated);
+    harness.press_with(Key::C, Modifiers::SHIFT);
+    harness.step();
+    assert_eq!(harness.app.zoom_level(), fitted);
+
+    harness.press_and_step(Key::C);
+    harness.press_and_step(Key::D);
+    harness.press_and_step(Key::A);
+    assert_eq!(harness.app.zoom_level(), fitted);
+}
+
+#[test]
+fn test_filmstrip_click_opens_image() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_and_step(Key::N);
+
+    // The third cell of the strip along the bottom edge
+    let cell = Pos2::new(8.0 + 2.0 * 84.0 + 38.0, SCREEN_SIZE.y - 40.0);
+    harness.drag(cell, cell);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+}
+
+#[test]
+fn test_gallery_keys_move_the_cursor_and_enter_opens() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_and_step(Key::T);
+    harness.run_until_idle(10);
+
+    // The first press lands on the image on screen, then the arrows step
+    // through the grid rather than the images
+    harness.press_and_step(Key::ArrowRight);
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    // There is no row below, so the cursor stops at the last image
+    harness.press_and_step(Key::ArrowDown);
+    harness.press_and_step(Key::Enter);
+    harness.run_until_idle(10);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+
+    harness.press_and_step(Key::ArrowLeft);
+    harness.run_until_idle(10);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_fullscreen_follows_f11_and_double_click() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    assert_eq!(harness.requested_fullscreen(), None);
+
+    harness.press_and_step(Key::F11);
+    assert!(harness.app.is_fullscreen());
+    assert_eq!(harness.requested_fullscreen(), Some(true));
+
+    harness.double_click(Pos2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0));
+    assert!(!harness.app.is_fullscreen());
+    assert_eq!(harness.requested_fullscreen(), Some(false));
+}
+
+#[test]
+fn test_slideshow_pauses_and_stops_after_last_image() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.slideshow.interval_secs = 0.5;
+    config.slideshow.repeat = false;
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("b.png")), config);
+
+    harness.press_and_step(Key::F5);
+    harness.press_and_step(Key::Space);
+    for _ in 0..40 {
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+
+    // Resumed, it moves on to the last image and ends there
+    harness.press_and_step(Key::Space);
+    for _ in 0..80 {
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+}
+
+#[test]
+fn test_dropped_files_play_as_a_playlist() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("b.png")));
+    harness.drop_files(&[fixture.path("c.png"), fixture.path("a.png")]);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+
+    // Navigation follows the dropped order and skips b.png
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+}
+
+#[test]
+fn test_opened_files_are_remembered_for_the_recent_menu() {
+    let fixture = folder();
+    let last_opened = |harness: &Harness| {
+        recent::last_opened(Some(harness.state_dir().to_path_buf()))
+    };
+    let mut harness = Harness::new(Some(&fixture.path("b.png")));
+    assert_eq!(last_opened(&harness), Some(fixture.path("b.png")));
+
+    harness.drop_files(&[fixture.path("c.png")]);
+    assert_eq!(last_opened(&harness), Some(fixture.path("c.png")));
+    // Stepping to another image does not count as opening it
+    harness.press_and_step(Key::D);
+    assert_eq!(last_opened(&harness), Some(fixture.path("c.png")));
+}
+
+#[test]
+fn test_starred_only_browses_the_starred_images() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_with(Key::S, Modifiers::SHIFT);
+    harness.step();
+    harness.press_and_step(Key::D);
+    harness.press_and_step(Key::D);
+    harness.press_with(Key::S, Modifiers::SHIFT);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+
+    // b.png is skipped both ways
+    harness.app.set_starred_only(true);
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::A);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+
+    // Taking the star away moves on to the next starred image
+    harness.press_with(Key::S, Modifiers::SHIFT);
+    harness.step();
+    harness.run_until_idle(10);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+}
+
+#[test]
+fn test_ratings_and_tags_narrow_navigation() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_with(Key::Num3, Modifiers::CTRL);
+    harness.step();
+    let sidecar = fixture.path("a.png").with_file_name("a.png.xmp");
+    let packet = std::fs::read_to_string(sidecar).unwrap();
+    assert!(packet.contains("<xmp:Rating>3</xmp:Rating>"), "{}", packet);
+
+    harness.press_and_step(Key::D);
+    harness.press_and_step(Key::D);
+    harness.press_with(Key::Num4, Modifiers::CTRL);
+    harness.step();
+    harness.app.filter_by(3, &[]);
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+
+    // Pressing the same rating again takes it away
+    harness.press_with(Key::Num4, Modifiers::CTRL);
+    harness.step();
+    harness.run_until_idle(10);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+
+    std::fs::write(
+        fixture.path("b.png").with_extension("xmp"),
+        "<rdf:Description \
+         xmp:Rating=\"5\"><dc:subject><rdf:Bag><rdf:li>keeper</rdf:li></rdf:\
+         Bag></dc:subject></rdf:Description>",
+    )
+    .unwrap();
+    harness.app.filter_by(0, &["Keeper".to_string()]);
+    harness.run_until_idle(10);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_session_restores_zoom_and_panels() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_and_step(Key::E);
+    harness.press_with(Key::H, Modifiers::SHIFT);
+    harness.step();
+    harness.press_and_step(Key::Plus);
+    let session = harness.app.session();
+    assert_eq!(session.image, Some(fixture.path("a.png")));
+    assert!(matches!(session.zoom, ZoomState::Custom(_)));
+    assert!(session.panels.exif);
+    assert!(session.panels.histogram);
+    assert!(!session.panels.filmstrip);
+
+    let mut restored = Harness::new(session.image.as_deref());
+    restored.app.restore_session(&session);
+    restored.step();
+    assert_eq!(restored.app.session(), session);
+}
+
+#[test]
+fn test_session_restores_fullscreen() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    assert!(!harness.app.session().fullscreen);
+    harness.press_and_step(Key::F11);
+    let session = harness.app.session();
+    assert!(session.fullscreen);
+
+    let mut restored = Harness::new(session.image.as_deref());
+    restored.app.restore_session(&session);
+    restored.step();
+    assert!(restored.app.is_fullscreen());
+    assert_eq!(restored.app.session(), session);
+}
+
+#[test]
+fn test_remapped_keys_follow_the_keymap() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    let keymap = &mut config.controls.keymap;
+    keymap.unbind(Action::NextImage, KeyChord::new(Key::D));
+    keymap.bind(Action::NextImage, KeyChord::new(Key::O));
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+    harness.press_and_step(Key::O);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+    // Chords with modifiers only match with those held
+    harness.press_with(Key::O, Modifiers::COMMAND);
+    harness.step();
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_sorting_keys_move_the_image_into_their_folder() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    // 1 also shows the image at actual size, which the target takes over
+    config.sorting.targets = vec![MoveTarget {
+        key:    KeyChord::new(Key::Num1),
+        folder: PathBuf::from("keep"),
+    }];
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+
+    harness.press_and_step(Key::Num1);
+    harness.run_until_idle(10);
+    let moved = fixture.path("b.png").with_file_name("a.png");
+    assert!(!moved.exists());
+    assert!(moved
+        .with_file_name("keep")
+        .join("a.png")
+        .is_file());
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+    assert_ne!(harness.app.zoom_level(), 1.0);
+
+    // The rest of the folder is still navigated without the moved image
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("c.png").as_path())
+    );
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_copy_path_puts_the_file_path_on_the_clipboard() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+
+    harness.press_with(Key::C, Modifiers::COMMAND | Modifiers::SHIFT);
+    let output = harness.step();
+    assert_eq!(
+        output.platform_output.copied_text,
+        fixture.path("a.png").display().to_string()
+    );
+}
+
+#[test]
+fn test_pixel_inspector_copies_the_color_under_the_cursor() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_with(Key::I, Modifiers::SHIFT);
+    harness.step();
+
+    let center = Pos2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0);
+    harness.drag(center, center);
+    assert_eq!(harness.last_output().platform_output.copied_text, "#0080FF");
+}
+
+#[test]
+fn test_rewritten_file_reloads_in_the_same_view() {
+    let fixture = folder();
+    let path = fixture.path("a.png");
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.io.watch_interval_ms = 50;
+    let mut harness = Harness::with_config(Some(&path), config);
+    harness.press_and_step(Key::W);
+    harness.drag(Pos2::new(400.0, 300.0), Pos2::new(350.0, 280.0));
+    let zoom = harness.app.zoom_level();
+    let rect = harness.app.image_rect();
+    // Rewritten after the watcher noted the file as it was
+    std::thread::sleep(std::time::Duration::from_millis(150));
+
+    image::RgbImage::from_pixel(200, 100, image::Rgb([10, 20, 30]))
+        .save(&path)
+        .unwrap();
+    let start = std::time::Instant::now();
+    while harness.textures_uploaded() == 0 {
+        assert!(start.elapsed().as_secs() < 5, "the file was not reloaded");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        harness.step();
+    }
+    assert_eq!(harness.app.zoom_level(), zoom);
+    assert_eq!(harness.app.image_rect(), rect);
+}
+
+#[test]
+fn test_monitor_mode_shows_images_added_to_the_folder() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    config.io.watch_interval_ms = 50;
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+    harness.app.set_monitor(true);
+    harness.step();
+    // Added after the watcher noted the folder as it was
+    std::thread::sleep(std::time::Duration::from_millis(150));
+
+    let added = fixture.path("a.png").with_file_name("render.png");
+    image::RgbImage::from_pixel(20, 10, image::Rgb([0, 0, 0]))
+        .save(&added)
+        .unwrap();
+    let start = std::time::Instant::now();
+    while harness.app.current_path() != Some(added.as_path()) {
+        assert!(start.elapsed().as_secs() < 5, "the new image was not shown");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        harness.step();
+    }
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_image_saved_by_an_external_editor_reloads() {
+    let fixture = folder();
+    let mut config = ferrite_config::FerriteConfig::default();
+    // Files opened in an editor are watched regardless
+    config.io.reload_on_change = false;
+    config.io.watch_interval_ms = 50;
+    // Saves once the watcher noted the file as it was
+    config
+        .editors
+        .programs
+        .push(ferrite_config::ExternalEditor {
+            name:    "Copy".into(),
+            command: format!(
+                "sleep 0.3 && cp '{}' {{path}}",
+                fixture.path("b.png").display()
+            ),
+        });
+    let mut harness =
+        Harness::with_config(Some(&fixture.path("a.png")), config);
+    harness.press_with(Key::E, Modifiers::SHIFT);
+    harness.step();
+
+    let start = std::time::Instant::now();
+    while harness.textures_uploaded() == 0 {
+        assert!(start.elapsed().as_secs() < 5, "the file was not reloaded");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        harness.step();
+    }
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+}
+
+#[test]
+fn test_arrow_keys_nudge_the_crop_instead_of_navigating() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_with(Key::X, Modifiers::SHIFT);
+    harness.step();
+    let rect = harness.app.image_rect().unwrap();
+    harness.drag(
+        rect.lerp_inside(Vec2::splat(0.25)),
+        rect.lerp_inside(Vec2::splat(0.75)),
+    );
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+
+    // Leaving crop mode gives the keys back
+    harness.press_with(Key::X, Modifiers::SHIFT);
+    harness.step();
+    harness.press_and_step(Key::ArrowRight);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
+
+#[test]
+fn test_split_pane_browses_on_its_own() {
+    let fixture = folder();
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness.press_and_step(Key::Backslash);
+    assert_eq!(harness.app.pane_path(), Some(fixture.path("a.png").as_path()));
+
+    // Clicking the right half gives it the keys and the dropped files
+    harness.click_at(Pos2::new(600.0, 300.0), PointerButton::Primary);
+    harness.step();
+    harness.drop_files(&[fixture.path("b.png")]);
+    harness.step();
+    harness.press_and_step(Key::D);
+    assert_eq!(harness.app.pane_path(), Some(fixture.path("c.png").as_path()));
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("a.png").as_path())
+    );
+
+    harness.press_and_step(Key::Backslash);
+    assert_eq!(harness.app.pane_path(), None);
+}
+
+#[test]
+fn test_archive_pages_are_browsed_like_a_folder() {
+    let fixture = folder();
+    let archive = fixture
+        .archive("book.cbz", &[("02.png", "b.png"), ("01.png", "c.png")]);
+    let mut harness = Harness::new(Some(&archive));
+    assert_eq!(
+        harness.app.current_path(),
+        Some(archive.join("01.png").as_path())
+    );
+
+    harness.press_and_step(Key::D);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(archive.join("02.png").as_path())
+    );
+}
+
+#[test]
+fn test_pasted_links_are_downloaded_and_shown() {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    let fixture = folder();
+    let body = std::fs::read(fixture.path("a.png")).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/photo", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0; 1024]);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: \
+             {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        let _ = stream.write_all(&body);
+    });
+
+    let mut config = FerriteConfig::default();
+    let cache = fixture.path("a.png").with_file_name("downloads");
+    config.remote.cache_dir = Some(cache.clone());
+    let mut harness = Harness::with_config(None, config);
+    harness.paste(&url);
+    harness.step();
+
+    let shown = harness
+        .app
+        .current_path()
+        .expect("the download is shown");
+    assert_eq!(shown.parent(), Some(cache.as_path()));
+    assert_eq!(shown.extension().unwrap(), "png");
+    let rect = harness
+        .app
+        .image_rect()
+        .expect("the image is drawn");
+    assert_eq!(rect.width() / rect.height(), 2.0);
+}
+
+#[test]
+fn test_piped_bytes_are_shown_without_a_file() {
+    let fixture = folder();
+    let mut harness = Harness::new(None);
+    harness.app.show_bytes(b"not an image");
+    harness.step();
+    assert!(harness.app.image_rect().is_none());
+
+    harness
+        .app
+        .show_bytes(&std::fs::read(fixture.path("b.png")).unwrap());
+    harness.step();
+    assert_eq!(harness.app.current_path(), None);
+    let rect = harness
+        .app
+        .image_rect()
+        .expect("the image is drawn");
+    assert_eq!(rect.height() / rect.width(), 2.0);
+}
+
+#[test]
+fn test_later_starts_open_in_the_running_window() {
+    use ferrite_core::instance::{self, Listener};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    let fixture = folder();
+    let socket = fixture
+        .path("a.png")
+        .with_file_name("ferrite.sock");
+    let mut harness = Harness::new(Some(&fixture.path("a.png")));
+    harness
+        .app
+        .set_inbox(Listener::bind(&socket).unwrap().serve(|| {}));
+
+    assert!(instance::forward(&socket, &[fixture.path("c.png")]));
+    let start = Instant::now();
+    let c = fixture.path("c.png");
+    while harness.app.current_path() != Some(c.as_path()) {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(5));
+        harness.step();
+    }
+    // Opened with its folder
+    harness.press_and_step(Key::ArrowLeft);
+    assert_eq!(
+        harness.app.current_path(),
+        Some(fixture.path("b.png").as_path())
+    );
+}
@@ -1,17 +1,34 @@
-use std::str::FromStr;
-use tracing::{info, instrument, Level};
+use directories::ProjectDirs;
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+        OnceLock,
+    },
+};
+use tracing::{info, instrument, warn, Level, Subscriber};
 use tracing_subscriber::{
-    fmt::{self, format::FmtSpan},
+    filter::{filter_fn, LevelFilter},
+    fmt::{self, format::FmtSpan, writer::BoxMakeWriter},
     layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
     util::SubscriberInitExt,
     Layer,
     Registry,
 };
+use tracing_tracy::TracyLayer;
 
 // Export our new metrics module
 pub mod metrics;
 pub use metrics::PerformanceMetrics;
 
+mod rotation;
+use rotation::RotatingFile;
+pub use rotation::RotationPolicy;
+
 #[derive(Debug, Clone, Copy)]
 pub enum LogLevel {
     Trace,
@@ -47,12 +64,43 @@ impl From<LogLevel> for Level {
     }
 }
 
+/// How log events are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, for mechanical analysis. Notable events
+    /// carry an `event` field such as `file_opened` or `decode_failed`.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LogConfig {
     pub level:        LogLevel,
     pub enable_tracy: bool,
     // Adding new configuration options for performance logging
     pub log_spans:    bool,
+    pub format:       LogFormat,
+    /// Write log output to this file instead of stdout
+    pub file:         Option<PathBuf>,
+    /// Keep a log in the state directory next to stdout output
+    pub state_file:   bool,
+    /// Overrides the platform state directory
+    pub state_dir:    Option<PathBuf>,
+    pub rotation:     RotationPolicy,
 }
 
 impl Default for LogConfig {
@@ -61,45 +109,167 @@ impl Default for LogConfig {
             level:        LogLevel::Info,
             enable_tracy: false,
             log_spans:    true,
+            format:       LogFormat::Text,
+            file:         None,
+            state_file:   false,
+            state_dir:    None,
+            rotation:     RotationPolicy::default(),
         }
     }
 }
 
+type TracyHandle = reload::Handle<Option<TracyLayer>, Registry>;
+
+/// Whether spans are currently forwarded to Tracy
+static TRACY_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Lets the Tracy layer be installed after the subscriber is initialized
+static TRACY_HANDLE: OnceLock<TracyHandle> = OnceLock::new();
+
 #[instrument]
 pub fn init(config: LogConfig) {
     let level = Level::from(config.level);
     let filter = tracing_subscriber::filter::LevelFilter::from_level(level);
 
-    let fmt_layer = fmt::layer()
+    // An explicit log file replaces stdout; otherwise stdout is kept and the
+    // state directory log is written alongside it
+    let mut outputs = Vec::new();
+    match &config.file {
+        Some(path) => outputs.push(path.clone()),
+        None => {
+            if config.state_file {
+                outputs.extend(
+                    config
+                        .state_dir
+                        .as_ref()
+                        .map(|dir| dir.join("logs").join("ferrite.log"))
+                        .or_else(default_log_path),
+                );
+            }
+        },
+    }
+
+    let mut layers = Vec::new();
+    if config.file.is_none() {
+        layers.push(fmt_layer(
+            &config,
+            BoxMakeWriter::new(std::io::stdout),
+            true,
+            filter,
+        ));
+    }
+    for path in outputs {
+        match RotatingFile::open(&path, config.rotation) {
+            Ok(file) => layers.push(fmt_layer(
+                &config,
+                BoxMakeWriter::new(Mutex::new(file)),
+                false,
+                filter,
+            )),
+            Err(e) => {
+                eprintln!("Cannot open log file {}: {}", path.display(), e);
+            },
+        }
+    }
+
+    // The Tracy layer starts out empty and is only created once profiling
+    // is enabled, since starting the client opens a listening socket
+    let (tracy_layer, handle) = reload::Layer::new(None);
+    let tracy_filter = filter_fn(move |metadata| {
+        TRACY_ENABLED.load(Ordering::Relaxed) && *metadata.level() <= level
+    });
+
+    Registry::default()
+        .with(tracy_layer.with_filter(tracy_filter))
+        .with(layers)
+        .try_init()
+        .expect("Failed to initialize logging");
+
+    let _ = TRACY_HANDLE.set(handle);
+    if config.enable_tracy {
+        set_tracy_enabled(true);
+    }
+}
+
+/// Location of the persistent log: the platform state directory where one
+/// exists, the local data directory otherwise.
+pub fn default_log_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("com", "ferrite", "ferrite")?;
+    let base = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+    Some(base.join("logs").join("ferrite.log"))
+}
+
+fn fmt_layer<S>(
+    config: &LogConfig,
+    writer: BoxMakeWriter,
+    ansi: bool,
+    filter: LevelFilter,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let layer = fmt::layer()
         .with_line_number(true)
         .with_thread_ids(true)
         .with_file(true)
         .with_timer(fmt::time::UtcTime::rfc_3339())
-        .with_span_events(if config.log_spans {
-            FmtSpan::NEW | FmtSpan::ENTER | FmtSpan::EXIT
-        } else {
-            FmtSpan::NONE
-        })
-        .with_filter(filter.clone());
+        .with_ansi(ansi)
+        .with_writer(writer);
+    match config.format {
+        LogFormat::Text => layer
+            .with_span_events(if config.log_spans {
+                FmtSpan::NEW | FmtSpan::ENTER | FmtSpan::EXIT
+            } else {
+                FmtSpan::NONE
+            })
+            .with_filter(filter)
+            .boxed(),
+        // Close events carry the busy/idle time of each span, which is what
+        // makes durations like decoding time available in JSON output
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_span_events(if config.log_spans {
+                FmtSpan::CLOSE
+            } else {
+                FmtSpan::NONE
+            })
+            .with_filter(filter)
+            .boxed(),
+    }
+}
 
-    let registry = Registry::default().with(fmt_layer);
+/// Starts or pauses forwarding spans to Tracy. The Tracy client is started
+/// the first time profiling is enabled and keeps running afterwards, so a
+/// connected profiler simply stops receiving data while paused.
+pub fn set_tracy_enabled(enabled: bool) {
+    if enabled && tracy_client::Client::running().is_none() {
+        let Some(handle) = TRACY_HANDLE.get() else {
+            warn!("Logging is not initialized, cannot enable Tracy");
+            return;
+        };
+        if let Err(e) = handle.modify(|layer| *layer = Some(TracyLayer::new()))
+        {
+            warn!("Failed to install Tracy layer: {}", e);
+            return;
+        }
+    }
 
-    if config.enable_tracy {
-        // Create the tracy layer with the correct configuration
-        let tracy_layer = tracing_tracy::TracyLayer::new().with_filter(filter);
-
-        registry
-            .with(tracy_layer)
-            .try_init()
-            .expect("Failed to initialize logging with tracy");
-
-        // After initialization, we can use tracy-client directly for frame
-        // marking
-        tracy_client::frame_mark();
-    } else {
-        registry
-            .try_init()
-            .expect("Failed to initialize logging");
+    TRACY_ENABLED.store(enabled, Ordering::Relaxed);
+    info!("Tracy profiling {}", if enabled { "enabled" } else { "paused" });
+}
+
+pub fn is_tracy_enabled() -> bool {
+    TRACY_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Marks the end of a frame in Tracy; does nothing while profiling is off.
+pub fn frame_mark() {
+    if !is_tracy_enabled() {
+        return;
+    }
+    if let Some(client) = tracy_client::Client::running() {
+        client.frame_mark();
     }
 }
 
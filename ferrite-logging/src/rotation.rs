@@ -0,0 +1,144 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// When a log file is rotated and how many old files are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Rotate once the file would grow beyond this many bytes
+    pub max_bytes: u64,
+    /// Number of rotated files (`ferrite.log.1`, `.2`, ...) to keep
+    pub max_files: u32,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024, max_files: 5
+        }
+    }
+}
+
+/// A log file that is rotated when it grows too large or a new (UTC) day
+/// starts.
+pub struct RotatingFile {
+    path:   PathBuf,
+    policy: RotationPolicy,
+    file:   File,
+    size:   u64,
+    day:    u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, policy: RotationPolicy) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(path)?;
+        let metadata = file.metadata()?;
+        let day = metadata
+            .modified()
+            .map(day_of)
+            .unwrap_or_else(|_| today());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            policy,
+            file,
+            size: metadata.len(),
+            day,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.policy.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for index in (1..self.policy.max_files).rev() {
+                let from = numbered(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, numbered(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+            self.file = open_append(&self.path)?;
+        }
+        self.size = 0;
+        self.day = today();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let too_large = self.size > 0
+            && self.size + buf.len() as u64 > self.policy.max_bytes;
+        if too_large || today() != self.day {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+}
+
+fn numbered(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+fn today() -> u64 {
+    day_of(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let dir = std::env::temp_dir().join("ferrite-log-rotation");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("ferrite.log");
+        let policy = RotationPolicy {
+            max_bytes: 10, max_files: 2
+        };
+
+        let mut file = RotatingFile::open(&path, policy).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(numbered(&path, 1)), "third\n");
+        assert_eq!(read(numbered(&path, 2)), "second\n");
+        assert!(!numbered(&path, 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,22 +1,101 @@
 use eframe::Error;
 use egui::ViewportBuilder;
-use ferrite_cli::Args;
-use ferrite_core::FeriteApp;
-use ferrite_logging::{init, LogConfig};
+use ferrite_cli::{Args, Command};
+use ferrite_core::{
+    instance::{self, Listener},
+    recent,
+    session::SessionState,
+    FeriteApp,
+};
+use ferrite_logging::{init, LogConfig, RotationPolicy};
+use std::{
+    io::{self, IsTerminal, Read},
+    time::Duration,
+};
 
 fn main() -> Result<(), Error> {
+    // Act as a sandboxed decoder when started by another Ferrite process
+    if let Some(code) = ferrite_core::sandbox::worker_main() {
+        std::process::exit(code);
+    }
+
     // Now Args::parse() will work correctly
     let args = Args::parse();
 
-    // Initialize logging
-    init(LogConfig {
-        level:        args.get_log_level(),
-        enable_tracy: true,
-        log_spans:    true,
-    });
+    if let Some(command) = args.command.clone() {
+        let result = match command {
+            Command::InstallAssociations => {
+                ferrite_core::associations::install().map_err(|e| e.to_string())
+            },
+            Command::UninstallAssociations => {
+                ferrite_core::associations::uninstall()
+                    .map_err(|e| e.to_string())
+            },
+            Command::Thumbnail {
+                input,
+                output,
+                size,
+            } => args
+                .handle_config()
+                .map_err(|e| e.to_string())
+                .and_then(|config| {
+                    ferrite_core::convert::thumbnail(
+                        &input, &output, size, &config,
+                    )
+                    .map_err(|e| e.to_string())
+                }),
+            Command::Convert {
+                input,
+                output,
+            } => args
+                .handle_config()
+                .map_err(|e| e.to_string())
+                .and_then(|config| {
+                    ferrite_core::convert::convert(&input, &output, &config)
+                        .map_err(|e| e.to_string())
+                }),
+            Command::Bench {
+                dir,
+                json,
+            } => args
+                .handle_config()
+                .map_err(|e| e.to_string())
+                .and_then(|config| {
+                    let interactive = io::stderr().is_terminal();
+                    let report = ferrite_core::bench::run(
+                        &dir,
+                        &config,
+                        |done, found| {
+                            if interactive {
+                                eprint!("\rDecoding {}/{}", done, found);
+                            }
+                        },
+                    );
+                    if interactive {
+                        eprintln!();
+                    }
+                    match report {
+                        Ok(report) if json => report.to_json(),
+                        Ok(report) => Ok(report.table()),
+                        Err(e) => Err(e),
+                    }
+                    .map_err(|e| e.to_string())
+                }),
+        };
+        match result {
+            Ok(summary) => {
+                println!("{}", summary);
+                std::process::exit(0);
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            },
+        }
+    }
 
-    // Handle configuration
-    let mut config = args.handle_config().unwrap_or_else(|e| {
+    // Handle configuration first, since it decides where logs are written
+    let config = args.handle_config().unwrap_or_else(|e| {
         eprintln!(
             "Configuration error: {}. Run with --generate-config to create \
              one.",
@@ -25,26 +104,140 @@ fn main() -> Result<(), Error> {
         std::process::exit(1);
     });
 
-    // Configure native window options based on config
-    let mut native_options = eframe::NativeOptions::default();
+    // Initialize logging
+    let logging = &config.logging;
+    init(LogConfig {
+        level:        args.get_log_level(),
+        enable_tracy: args.profile_tracy || logging.enable_tracy,
+        log_spans:    true,
+        format:       args.get_log_format(),
+        file:         args.log_file.clone(),
+        state_file:   logging.log_to_file,
+        state_dir:    config.paths.resolve_state_dir(),
+        rotation:     RotationPolicy {
+            max_bytes: logging.max_file_size_mb * 1024 * 1024,
+            max_files: logging.max_files,
+        },
+    });
 
-    // Set window options
-    native_options.default_theme = eframe::Theme::Dark;
+    ferrite_core::crash::install(config.paths.resolve_state_dir());
 
-    // Set initial window size if configured
+    if let Some(folder) = &args.wallpaper_daemon {
+        let interval = args
+            .interval
+            .unwrap_or(Duration::from_secs(config.wallpaper.interval_secs));
+        if let Err(e) =
+            ferrite_core::wallpaper::run_daemon(folder, interval, &config)
+        {
+            eprintln!("Wallpaper daemon stopped: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Hand the image to the window already running, if there is one, and
+    // otherwise become the window later starts hand theirs to
+    let single = config.instance.single_instance && !args.new_window;
+    let socket = instance::socket_path(&config.instance);
+    if single && !args.stdin {
+        if let Some(image) = &args.image_path {
+            if instance::forward(&socket, std::slice::from_ref(image)) {
+                return Ok(());
+            }
+        }
+    }
+    let listener = single
+        .then(|| Listener::bind(&socket))
+        .and_then(|bound| {
+            bound
+                .map_err(|e| {
+                    tracing::debug!("Not taking {}: {}", socket.display(), e)
+                })
+                .ok()
+        });
+
+    // Read before the window opens, so the encoder piping in is not kept
+    // waiting
+    let piped = args.stdin.then(|| {
+        let mut stdin = io::stdin();
+        if stdin.is_terminal() {
+            eprintln!("--stdin needs an image piped to standard input");
+            std::process::exit(1);
+        }
+        let mut bytes = Vec::new();
+        if let Err(e) = stdin.read_to_end(&mut bytes) {
+            eprintln!("Failed to read standard input: {}", e);
+            std::process::exit(1);
+        }
+        if bytes.is_empty() {
+            eprintln!("Nothing was piped to standard input");
+            std::process::exit(1);
+        }
+        bytes
+    });
+
+    let session = if args.no_restore {
+        None
+    } else {
+        SessionState::load(config.paths.resolve_state_dir().as_deref())
+    };
 
+    // Set initial window size if configured
     let width: f32 = 1920.;
     let height: f32 = 1080.;
 
-    native_options.viewport = ViewportBuilder::default()
+    let mut viewport = ViewportBuilder::default()
         .with_inner_size([width, height])
         .with_decorations(!config.window.borderless);
+    if let Some(window) = session.as_ref().and_then(|s| s.window) {
+        viewport = viewport
+            .with_inner_size([window.width, window.height])
+            .with_position([window.x, window.y]);
+    }
+    if session.as_ref().is_some_and(|s| s.fullscreen) {
+        viewport = viewport.with_fullscreen(true);
+    }
+
+    // Configure native window options based on config
+    let native_options = eframe::NativeOptions {
+        default_theme: eframe::Theme::Dark,
+        viewport,
+        ..Default::default()
+    };
+
+    let monitor = args.watch;
+    // An image named on the command line wins over the last one viewed
+    let recent = args
+        .recent
+        .then(|| recent::last_opened(config.paths.resolve_state_dir()))
+        .flatten();
+    if args.recent && recent.is_none() {
+        eprintln!("No recently opened image or folder is left to open");
+    }
+    let image = args.image_path.or(recent).or_else(|| {
+        session
+            .as_ref()
+            .and_then(|s| s.image.clone())
+            .filter(|image| image.is_file() && piped.is_none())
+    });
 
+    ferrite_core::desktop::prepare();
     eframe::run_native(
         "Ferrite",
         native_options,
         Box::new(move |cc| {
-            let app = FeriteApp::new(cc, args.image_path, config);
+            let mut app = FeriteApp::new(cc, image, config);
+            if let Some(session) = &session {
+                app.restore_session(session);
+            }
+            app.set_monitor(monitor);
+            if let Some(listener) = listener {
+                let ctx = cc.egui_ctx.clone();
+                app.set_inbox(listener.serve(move || ctx.request_repaint()));
+            }
+            if let Some(bytes) = &piped {
+                app.show_bytes(bytes);
+            }
             Box::new(app)
         }),
     )
@@ -0,0 +1,55 @@
+use image::{Rgba, RgbaImage};
+use std::{fs, process::Command};
+
+const FERRITE: &str = env!("CARGO_BIN_EXE_ferrite");
+
+#[test]
+fn test_thumbnail_and_convert_run_without_a_window() {
+    let dir = std::env::temp_dir().join("ferrite-cli-convert");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("wide.png");
+    RgbaImage::from_pixel(300, 150, Rgba([200, 100, 50, 255]))
+        .save(&input)
+        .unwrap();
+    let ferrite = |args: &[&str]| {
+        Command::new(FERRITE)
+            .args(args)
+            // Defaults, whatever the machine running the tests has set up
+            .env("FERRITE_CONF", dir.join("missing.toml"))
+            .output()
+            .unwrap()
+    };
+
+    let thumb = dir.join("thumb.png");
+    let run = ferrite(&[
+        "thumbnail",
+        input.to_str().unwrap(),
+        thumb.to_str().unwrap(),
+        "--size",
+        "60",
+    ]);
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(
+        image::open(&thumb)
+            .unwrap()
+            .to_rgba8()
+            .dimensions(),
+        (60, 30)
+    );
+
+    let converted = dir.join("converted.png");
+    let run = ferrite(&[
+        "convert",
+        input.to_str().unwrap(),
+        converted.to_str().unwrap(),
+    ]);
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(
+        image::open(&converted).unwrap().to_rgba8(),
+        image::open(&input).unwrap().to_rgba8()
+    );
+
+    let run = ferrite(&["convert", input.to_str().unwrap(), "out.txt"]);
+    assert!(!run.status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,57 @@
+use ferrite_config::DecodingConfig;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::{fs, path::Path};
+
+const WORKER: &str = env!("CARGO_BIN_EXE_ferrite");
+
+fn decode(
+    path: &Path,
+    config: &DecodingConfig,
+) -> Result<DynamicImage, ferrite_core::sandbox::SandboxError> {
+    let bytes = fs::read(path).unwrap();
+    ferrite_core::sandbox::decode(path, &bytes, config, Path::new(WORKER))
+}
+
+#[test]
+fn test_sandboxed_decode_matches_direct_decode() {
+    let dir = std::env::temp_dir().join("ferrite-sandbox-decode");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("gradient.png");
+    let original = RgbaImage::from_fn(64, 32, |x, y| {
+        Rgba([(x * 4) as u8, (y * 8) as u8, 128, 255])
+    });
+    original.save(&path).unwrap();
+
+    let decoded = decode(&path, &DecodingConfig::default()).unwrap();
+    assert_eq!(decoded.to_rgba8(), original);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sandboxed_decode_keeps_16_bit_pixels() {
+    let dir = std::env::temp_dir().join("ferrite-sandbox-16-bit");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("deep.png");
+    let original = image::ImageBuffer::<Rgba<u16>, _>::from_fn(8, 4, |x, y| {
+        Rgba([x as u16 * 4097, y as u16 * 257 + 1, 65535, 65535])
+    });
+    original.save(&path).unwrap();
+
+    let decoded = decode(&path, &DecodingConfig::default()).unwrap();
+    let DynamicImage::ImageRgba16(decoded) = decoded else {
+        panic!("decoded to {:?}", decoded.color());
+    };
+    assert_eq!(decoded, original);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sandboxed_decode_reports_corrupt_file() {
+    let dir = std::env::temp_dir().join("ferrite-sandbox-corrupt");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("broken.png");
+    fs::write(&path, b"\x89PNG\r\n\x1a\nnot really a png").unwrap();
+
+    assert!(decode(&path, &DecodingConfig::default()).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}